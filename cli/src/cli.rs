@@ -1,7 +1,16 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 
 use crate::commands::MainCommand;
 
+/// Output format of commands that print information (e.g. list and status)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+  /// Human readable (or an interactive ui if stdout is a terminal)
+  Text,
+  /// Json for scripting
+  Json,
+}
+
 #[derive(Debug, Parser)]
 #[clap(name = "t-rust-less", about = "Manages passwords", version = clap::crate_version!())]
 pub struct Args {
@@ -11,6 +20,9 @@ pub struct Args {
   #[clap(short, long, help = "Select store to use")]
   pub store: Option<String>,
 
+  #[clap(long, value_enum, global = true, default_value = "text", help = "Output format")]
+  pub output: OutputFormat,
+
   #[clap(subcommand)]
   pub sub_command: MainCommand,
 }
@@ -29,7 +29,7 @@ impl AddIdentitiesCommand {
 
     siv.add_global_callback(Key::Esc, Cursive::quit);
 
-    add_identity_dialog(&mut siv, secrets_store, "Add identity");
+    add_identity_dialog(&mut siv, secrets_store, "Add identity", false);
 
     siv.run();
 
@@ -37,7 +37,12 @@ impl AddIdentitiesCommand {
   }
 }
 
-pub fn add_identity_dialog(siv: &mut Cursive, secrets_store: Arc<dyn SecretsStore>, title: &str) {
+pub fn add_identity_dialog(
+  siv: &mut Cursive,
+  secrets_store: Arc<dyn SecretsStore>,
+  title: &str,
+  hardware_factor: bool,
+) {
   siv.set_user_data(secrets_store);
   siv.add_layer(
     Dialog::around(
@@ -55,7 +60,7 @@ pub fn add_identity_dialog(siv: &mut Cursive, secrets_store: Arc<dyn SecretsStor
         .child(PasswordView::new(100).with_name("passphrase")),
     )
     .title(title)
-    .button("Create", create_identity)
+    .button("Create", move |s| create_identity(s, hardware_factor))
     .button("Abort", Cursive::quit)
     .padding_left(5)
     .padding_right(5)
@@ -64,12 +69,13 @@ pub fn add_identity_dialog(siv: &mut Cursive, secrets_store: Arc<dyn SecretsStor
   )
 }
 
-fn create_identity(s: &mut Cursive) {
+fn create_identity(s: &mut Cursive, hardware_factor: bool) {
   let identity = Identity {
     id: s.find_name::<EditView>("id").unwrap().get_content().to_string(),
     name: s.find_name::<EditView>("name").unwrap().get_content().to_string(),
     email: s.find_name::<EditView>("email").unwrap().get_content().to_string(),
     hidden: false,
+    hardware_factor,
   };
   let passphrase = s.find_name::<PasswordView>("passphrase").unwrap().get_content();
 
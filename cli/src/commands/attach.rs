@@ -0,0 +1,82 @@
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use clap::Args;
+use std::path::PathBuf;
+use std::sync::Arc;
+use t_rust_less_lib::api::{SecretAttachment, DEFAULT_MAX_ATTACHMENT_SIZE};
+use t_rust_less_lib::service::TrustlessService;
+
+use super::{tui::create_tui, unlock_store};
+use crate::model::attachment::read_attachment;
+
+#[derive(Debug, Args)]
+pub struct AttachCommand {
+  #[clap(help = "Id of the secret to attach the file to")]
+  pub secret_id: String,
+  #[clap(help = "File to attach")]
+  pub file: PathBuf,
+  #[clap(long, help = "Name of the attachment (default: file name)")]
+  pub name: Option<String>,
+  #[clap(long, help = "Mime type of the attachment (default: detected from file)")]
+  pub mime_type: Option<String>,
+}
+
+impl AttachCommand {
+  pub fn run(self, service: Arc<dyn TrustlessService>, store_name: String) -> Result<()> {
+    let name = match self.name {
+      Some(name) => name,
+      None => match self.file.file_name() {
+        Some(file_name) => file_name.to_string_lossy().to_string(),
+        None => bail!("Unable to derive attachment name from {}", self.file.to_string_lossy()),
+      },
+    };
+    let max_size = service
+      .list_stores()?
+      .into_iter()
+      .find(|store_config| store_config.name == store_name)
+      .map(|store_config| store_config.max_attachment_size)
+      .unwrap_or(DEFAULT_MAX_ATTACHMENT_SIZE);
+    let mut content = read_attachment(&self.file, max_size)?;
+    let mime_type = match self.mime_type {
+      Some(mime_type) => mime_type,
+      None => SecretAttachment::guess_mime_type(&name, &content).to_string(),
+    };
+
+    let secrets_store = service
+      .open_store(&store_name)
+      .with_context(|| format!("Failed opening store {}: ", store_name))?;
+    let status = secrets_store.status().with_context(|| "Get status")?;
+
+    if status.locked {
+      let mut siv = create_tui();
+      unlock_store(&mut siv, &secrets_store, &store_name)?;
+      siv.quit();
+    }
+
+    let secret = secrets_store
+      .get(&self.secret_id)
+      .with_context(|| format!("Get secret {}", self.secret_id))?;
+    let mut secret_version = secret.current.clone();
+
+    secret_version.timestamp = Utc::now().into();
+    secret_version.parent_block_id = Some(secret.current_block_id.clone());
+    secret_version
+      .attachments
+      .retain(|attachment| attachment.name() != name);
+    let size = content.len();
+    // The content is moved into the attachment (which takes care of zeroizing it), not copied
+    secret_version.attachments.push(SecretAttachment::new(
+      name.as_str(),
+      mime_type.as_str(),
+      std::mem::take(&mut *content),
+    ));
+
+    secrets_store
+      .add(secret_version)
+      .with_context(|| format!("Add attachment to {}", self.secret_id))?;
+
+    println!("Attached {} ({}, {} bytes)", name, mime_type, size);
+
+    Ok(())
+  }
+}
@@ -0,0 +1,141 @@
+use anyhow::{bail, Context, Result};
+use atty::Stream;
+use clap::{Args, Subcommand};
+use std::fs::OpenOptions;
+use std::io;
+use std::iter;
+use std::path::PathBuf;
+use std::sync::Arc;
+use t_rust_less_lib::api::Secret;
+use t_rust_less_lib::secrets_store::{SecretStoreError, SecretsStore};
+use t_rust_less_lib::service::TrustlessService;
+
+use super::{tui::create_tui, unlock_store};
+use crate::model::attachment::{attachment_file_name, write_chunks, AttachmentChunks};
+
+#[derive(Debug, Args)]
+pub struct AttachmentsListCommand {
+  #[clap(help = "Id of the secret")]
+  pub secret_id: String,
+}
+
+impl AttachmentsListCommand {
+  pub fn run(self, service: Arc<dyn TrustlessService>, store_name: String) -> Result<()> {
+    let secret = get_secret(service, &store_name, &self.secret_id)?;
+
+    for attachment in &secret.current.attachments {
+      println!(
+        "{} ({}, {} bytes)",
+        attachment.name(),
+        attachment.mime_type(),
+        attachment.content().len()
+      );
+    }
+
+    Ok(())
+  }
+}
+
+#[derive(Debug, Args)]
+pub struct AttachmentsGetCommand {
+  #[clap(help = "Id of the secret")]
+  pub secret_id: String,
+  #[clap(help = "Name of the attachment")]
+  pub name: String,
+  #[clap(
+    long,
+    short,
+    alias = "out",
+    help = "File or directory to write the attachment to (default: stdout)"
+  )]
+  pub output: Option<PathBuf>,
+}
+
+impl AttachmentsGetCommand {
+  pub fn run(self, service: Arc<dyn TrustlessService>, store_name: String) -> Result<()> {
+    let secrets_store = open_unlocked_store(service, &store_name)?;
+    // The attachment is streamed chunk by chunk, the first one is read upfront to check that
+    // it actually exists (before creating any file)
+    let mut chunks = AttachmentChunks::new(secrets_store.as_ref(), &self.secret_id, &self.name);
+    let first = match chunks.next() {
+      Some(Ok(first)) => first,
+      Some(Err(SecretStoreError::NotFound)) | None => {
+        bail!("Secret {} has no attachment {}", self.secret_id, self.name)
+      }
+      Some(Err(err)) => return Err(err).with_context(|| format!("Get attachment {}", self.name)),
+    };
+
+    match &self.output {
+      Some(output) => {
+        let path = if output.is_dir() {
+          output.join(attachment_file_name(&first))
+        } else {
+          output.clone()
+        };
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+          use std::os::unix::fs::OpenOptionsExt;
+          options.mode(0o600);
+        }
+        let file = options
+          .open(&path)
+          .with_context(|| format!("Failed creating {}", path.to_string_lossy()))?;
+        write_chunks(file, iter::once(Ok(first)).chain(chunks))
+          .with_context(|| format!("Failed writing {}", path.to_string_lossy()))?;
+      }
+      None if atty::is(Stream::Stdout) => bail!("Refusing to write attachment to a terminal (use --output)"),
+      None => {
+        write_chunks(io::stdout().lock(), iter::once(Ok(first)).chain(chunks))
+          .with_context(|| "Failed writing to stdout")?;
+      }
+    }
+
+    Ok(())
+  }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AttachmentsSubCommand {
+  #[clap(about = "List attachments of a secret", alias = "ls")]
+  List(AttachmentsListCommand),
+  #[clap(about = "Extract an attachment of a secret")]
+  Get(AttachmentsGetCommand),
+}
+
+#[derive(Debug, Args)]
+pub struct AttachmentsCommand {
+  #[clap(subcommand)]
+  subcommand: AttachmentsSubCommand,
+}
+
+impl AttachmentsCommand {
+  pub fn run(self, service: Arc<dyn TrustlessService>, store_name: String) -> Result<()> {
+    match self.subcommand {
+      AttachmentsSubCommand::List(cmd) => cmd.run(service, store_name),
+      AttachmentsSubCommand::Get(cmd) => cmd.run(service, store_name),
+    }
+  }
+}
+
+fn get_secret(service: Arc<dyn TrustlessService>, store_name: &str, secret_id: &str) -> Result<Secret> {
+  open_unlocked_store(service, store_name)?
+    .get(secret_id)
+    .with_context(|| format!("Get secret {}", secret_id))
+}
+
+fn open_unlocked_store(service: Arc<dyn TrustlessService>, store_name: &str) -> Result<Arc<dyn SecretsStore>> {
+  let secrets_store = service
+    .open_store(store_name)
+    .with_context(|| format!("Failed opening store {}: ", store_name))?;
+  let status = secrets_store.status().with_context(|| "Get status")?;
+
+  if status.locked {
+    let mut siv = create_tui();
+    unlock_store(&mut siv, &secrets_store, store_name)?;
+    siv.quit();
+  }
+
+  Ok(secrets_store)
+}
@@ -0,0 +1,171 @@
+use anyhow::{Context, Result};
+use atty::Stream;
+use chrono::{Duration, Utc};
+use clap::{Args, Subcommand};
+use crossterm_style::{style, Color};
+use std::sync::Arc;
+use t_rust_less_lib::api::{
+  AuditPolicy, AuditReason, SecretListFilter, DEFAULT_AUDIT_MAX_AGE_DAYS, DEFAULT_AUDIT_MIN_SCORE,
+};
+use t_rust_less_lib::secrets_store::SecretsStore;
+use t_rust_less_lib::service::TrustlessService;
+
+use super::{tui::create_tui, unlock_store};
+use crate::model::expiring::{expiring_entries, expiring_filter};
+
+#[derive(Debug, Subcommand)]
+pub enum AuditSubCommand {
+  #[clap(about = "List groups of secrets sharing the same password")]
+  Reuse,
+}
+
+#[derive(Debug, Args)]
+pub struct AuditCommand {
+  #[clap(subcommand)]
+  subcommand: Option<AuditSubCommand>,
+  #[clap(
+    long,
+    value_name = "DAYS",
+    num_args = 0..=1,
+    default_missing_value = "30",
+    help = "List secrets expiring within the next DAYS (default: 30)"
+  )]
+  pub expiring: Option<i64>,
+  #[clap(long, default_value_t = DEFAULT_AUDIT_MIN_SCORE, help = "Flag passwords with a lower strength score (0-4, 0 = disabled)")]
+  pub min_score: u8,
+  #[clap(long, value_name = "DAYS", default_value_t = DEFAULT_AUDIT_MAX_AGE_DAYS, help = "Flag passwords not changed for more than DAYS (0 = disabled)")]
+  pub max_age: u32,
+  #[clap(long, help = "Flag passwords shared with other secrets")]
+  pub include_reuse: bool,
+  #[clap(long, help = "Flag passwords found in the breach directory of the store")]
+  pub include_breached: bool,
+}
+
+impl AuditCommand {
+  pub fn run(self, service: Arc<dyn TrustlessService>, store_name: String) -> Result<()> {
+    let secrets_store = service
+      .open_store(&store_name)
+      .with_context(|| format!("Failed opening store {}: ", store_name))?;
+    let status = secrets_store.status().with_context(|| "Get status")?;
+
+    if status.locked {
+      let mut siv = create_tui();
+      unlock_store(&mut siv, &secrets_store, &store_name)?;
+      siv.quit();
+    }
+
+    match (self.subcommand, self.expiring) {
+      (Some(AuditSubCommand::Reuse), _) => audit_reuse(secrets_store.as_ref()),
+      (None, Some(days)) => audit_expiring(secrets_store.as_ref(), days),
+      (None, None) => audit_passwords(
+        secrets_store.as_ref(),
+        &AuditPolicy {
+          min_score: self.min_score,
+          max_age_days: self.max_age,
+          include_reuse: self.include_reuse,
+          include_breached: self.include_breached,
+        },
+      ),
+    }
+  }
+}
+
+fn audit_expiring(secrets_store: &dyn SecretsStore, days: i64) -> Result<()> {
+  let now = Utc::now();
+  let list = secrets_store
+    .list(&expiring_filter(now, days))
+    .with_context(|| "List entries")?;
+
+  for (expires_at, entry) in expiring_entries(&list) {
+    let expires = expires_at.format("%Y-%m-%d");
+
+    if atty::is(Stream::Stdout) {
+      let color = if expires_at - now < Duration::zero() {
+        Color::Red
+      } else {
+        Color::Yellow
+      };
+      println!("{} {} ({})", style(expires).with(color), entry.name, entry.id);
+    } else {
+      println!("{} {} ({})", expires, entry.name, entry.id);
+    }
+  }
+
+  Ok(())
+}
+
+/// Print all groups of secrets sharing a password by name (the passwords themselves are never revealed)
+fn audit_reuse(secrets_store: &dyn SecretsStore) -> Result<()> {
+  let groups = secrets_store
+    .find_reused_passwords()
+    .with_context(|| "Find reused passwords")?;
+  let list = secrets_store
+    .list(&SecretListFilter::default())
+    .with_context(|| "List entries")?;
+
+  if groups.is_empty() {
+    println!("No reused passwords");
+    return Ok(());
+  }
+  for (idx, group) in groups.iter().enumerate() {
+    if idx > 0 {
+      println!();
+    }
+    println!("Password shared by {} secrets:", group.secret_ids.len());
+    for secret_id in &group.secret_ids {
+      match list
+        .entries
+        .iter()
+        .find(|entry_match| &entry_match.entry.id == secret_id)
+      {
+        Some(entry_match) => println!("  {} ({})", entry_match.entry.name, secret_id),
+        None => println!("  {}", secret_id),
+      }
+    }
+  }
+
+  Ok(())
+}
+
+/// Print all secrets failing the password policy with their reasons (the passwords themselves are never revealed)
+fn audit_passwords(secrets_store: &dyn SecretsStore, policy: &AuditPolicy) -> Result<()> {
+  let report = secrets_store.audit(policy).with_context(|| "Audit passwords")?;
+  // Other secrets sharing a password are only known by id
+  let list = secrets_store
+    .list(&SecretListFilter::default())
+    .with_context(|| "List entries")?;
+  let name_of = |secret_id: &String| match list
+    .entries
+    .iter()
+    .find(|entry_match| &entry_match.entry.id == secret_id)
+  {
+    Some(entry_match) => entry_match.entry.name.clone(),
+    None => secret_id.clone(),
+  };
+
+  for entry in &report.entries {
+    if atty::is(Stream::Stdout) {
+      println!("{} ({})", style(&entry.name).with(Color::Red), entry.secret_id);
+    } else {
+      println!("{} ({})", entry.name, entry.secret_id);
+    }
+    for reason in &entry.reasons {
+      match reason {
+        AuditReason::Weak { property, score } => println!("  weak {} (score {})", property, score),
+        AuditReason::Old { days } => println!("  not changed for {} days", days),
+        AuditReason::Reused { secret_ids } => println!(
+          "  password shared with {}",
+          secret_ids.iter().map(name_of).collect::<Vec<_>>().join(", ")
+        ),
+        AuditReason::Breached { count } => println!("  password seen {} times in known data breaches", count),
+      }
+    }
+  }
+  println!(
+    "{} of {} secrets with passwords failed the audit",
+    report.entries.len(),
+    report.checked
+  );
+
+  Ok(())
+}
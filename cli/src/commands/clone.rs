@@ -0,0 +1,105 @@
+use anyhow::{anyhow, bail, Context, Result};
+use clap::Args;
+use std::env;
+use std::path::PathBuf;
+use std::sync::Arc;
+use t_rust_less_lib::api::{
+  StoreConfig, DEFAULT_CLIPBOARD_TIMEOUT_SECS, DEFAULT_MAX_ATTACHMENT_SIZE, DEFAULT_SYNC_CONCURRENCY,
+};
+use t_rust_less_lib::block_store::sync::clone_store;
+use t_rust_less_lib::service::TrustlessService;
+use url::Url;
+
+use crate::config::{default_autolock_timeout, default_sync_interval};
+
+use super::generate_id;
+
+#[derive(Debug, Args)]
+pub struct CloneCommand {
+  #[clap(help = "Url of the remote store to clone")]
+  pub remote_url: String,
+  #[clap(help = "Directory of the new local store (has to be empty)")]
+  pub local_path: PathBuf,
+  #[clap(long, help = "Name of the new store (default: name of the directory)")]
+  pub name: Option<String>,
+  #[clap(
+    long,
+    default_value_t = DEFAULT_SYNC_CONCURRENCY,
+    help = "Maximum number of blocks transferred at the same time (also used for later synchronizations)"
+  )]
+  pub concurrency: usize,
+}
+
+impl CloneCommand {
+  pub fn run(self, service: Arc<dyn TrustlessService>) -> Result<()> {
+    // Secrets store urls (like multilane+dropbox://...) are accepted as well
+    let remote_url = match self.remote_url.find('+') {
+      Some(idx) => &self.remote_url[idx + 1..],
+      None => &self.remote_url,
+    };
+    let local_path = if self.local_path.is_absolute() {
+      self.local_path.clone()
+    } else {
+      env::current_dir()?.join(&self.local_path)
+    };
+    let store_name = match self.name {
+      Some(name) => name,
+      None => local_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or_else(|| anyhow!("Unable to derive store name from {}", local_path.to_string_lossy()))?,
+    };
+    if service
+      .list_stores()?
+      .iter()
+      .any(|store_config| store_config.name == store_name)
+    {
+      bail!("Store {} is already configured", store_name);
+    }
+
+    let store_url = Url::from_directory_path(&local_path)
+      .map_err(|_| anyhow!("Invalid directory {}", local_path.to_string_lossy()))?;
+    let client_id = generate_id(64);
+    let report = clone_store(store_url.as_str(), remote_url, &client_id, self.concurrency)
+      .with_context(|| format!("Failed cloning {}", remote_url))?;
+
+    service
+      .upsert_store_config(StoreConfig {
+        name: store_name.clone(),
+        store_url: format!("multilane+{}", store_url),
+        remote_url: Some(remote_url.to_string()),
+        sync_interval_sec: default_sync_interval().as_secs() as u32,
+        sync_max_rate: 0,
+        sync_concurrency: self.concurrency.max(1),
+        client_id,
+        autolock_timeout_secs: default_autolock_timeout().as_secs(),
+        default_identity_id: None,
+        unlock_throttle_attempts: 5,
+        unlock_throttle_file: None,
+        url_tag_rules: vec![],
+        default_recipients: Default::default(),
+        strength_estimator: Default::default(),
+        attachment_storage: Default::default(),
+        max_attachment_size: DEFAULT_MAX_ATTACHMENT_SIZE,
+        compress_blocks: false,
+        cipher_suite: Default::default(),
+        key_derivation_preset: None,
+        restore_clipboard: None,
+        clipboard_timeout_secs: DEFAULT_CLIPBOARD_TIMEOUT_SECS,
+        pepper_file: None,
+        breach_dir: None,
+      })
+      .with_context(|| format!("Failed to store config of {}", store_name))?;
+
+    println!(
+      "Cloned {} to {} ({} rings, {} blocks)",
+      remote_url,
+      local_path.to_string_lossy(),
+      report.rings_pulled,
+      report.blocks_pulled + report.blocks_resumed
+    );
+    println!("Store {} can be unlocked with any existing identity", store_name);
+
+    Ok(())
+  }
+}
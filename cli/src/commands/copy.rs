@@ -0,0 +1,73 @@
+use anyhow::{bail, Context, Result};
+use clap::Args;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use t_rust_less_lib::api::{PROPERTY_PASSWORD, PROPERTY_USERNAME};
+use t_rust_less_lib::service::TrustlessService;
+
+use super::{tui::create_tui, unlock_store};
+
+/// Interval in which the state of the clipboard is checked.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Args)]
+pub struct CopyCommand {
+  #[clap(help = "Id of the secret to copy")]
+  pub secret_id: String,
+  #[clap(help = "Properties to provide one after the other (default: username password)")]
+  pub properties: Vec<String>,
+}
+
+impl CopyCommand {
+  pub fn run(self, service: Arc<dyn TrustlessService>, store_name: String) -> Result<()> {
+    let secrets_store = service
+      .open_store(&store_name)
+      .with_context(|| format!("Failed opening store {}: ", store_name))?;
+    let status = secrets_store.status().with_context(|| "Get status")?;
+
+    if status.locked {
+      let mut siv = create_tui();
+      unlock_store(&mut siv, &secrets_store, &store_name)?;
+      siv.quit();
+    }
+
+    let secret = secrets_store
+      .get(&self.secret_id)
+      .with_context(|| format!("Get secret {}", self.secret_id))?;
+    let properties: Vec<&str> = if self.properties.is_empty() {
+      vec![PROPERTY_USERNAME, PROPERTY_PASSWORD]
+    } else {
+      self.properties.iter().map(String::as_str).collect()
+    };
+    let properties: Vec<&str> = properties
+      .into_iter()
+      .filter(|property| secret.current.properties.has_non_empty(property))
+      .collect();
+    if properties.is_empty() {
+      bail!("Secret {} has none of the requested properties", self.secret_id);
+    }
+
+    // Only the block id is passed on, the service takes the values directly from the store
+    let clipboard = service
+      .secret_to_clipboard(&store_name, &secret.current_block_id, &properties)
+      .with_context(|| "Copy to clipboard")?;
+
+    let mut last_property = None;
+    while !clipboard.is_done()? {
+      let property = clipboard
+        .currently_providing()?
+        .map(|providing| providing.property.clone());
+      if property != last_property {
+        if let Some(property) = &property {
+          println!("Clipboard: {} of {} (next paste)", property, secret.current.name);
+        }
+        last_property = property;
+      }
+      thread::sleep(POLL_INTERVAL);
+    }
+    println!("Clipboard done");
+
+    Ok(())
+  }
+}
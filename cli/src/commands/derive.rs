@@ -0,0 +1,37 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use std::sync::Arc;
+use t_rust_less_lib::service::TrustlessService;
+
+use super::{generate::GeneratorArgs, tui::create_tui, unlock_store};
+
+#[derive(Debug, Args)]
+pub struct DeriveCommand {
+  #[clap(help = "Site to derive the password for (e.g. example.com or an url)")]
+  site: String,
+  #[clap(flatten)]
+  generator: GeneratorArgs,
+}
+
+impl DeriveCommand {
+  pub fn run(self, service: Arc<dyn TrustlessService>, store_name: String) -> Result<()> {
+    let secrets_store = service
+      .open_store(&store_name)
+      .with_context(|| format!("Failed opening store {}: ", store_name))?;
+    let status = secrets_store.status().with_context(|| "Get status")?;
+
+    if status.locked {
+      let mut siv = create_tui();
+      unlock_store(&mut siv, &secrets_store, &store_name)?;
+      siv.quit();
+    }
+
+    let password = service
+      .derive_site_password(&store_name, &self.site, self.generator.param())
+      .with_context(|| format!("Derive password for {}", self.site))?;
+
+    println!("{}", password);
+
+    Ok(())
+  }
+}
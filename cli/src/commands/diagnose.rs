@@ -0,0 +1,26 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use std::sync::Arc;
+use t_rust_less_lib::service::TrustlessService;
+
+#[derive(Debug, Args)]
+pub struct DiagnoseCommand {
+  #[clap(long, help = "Print as json")]
+  pub json: bool,
+}
+
+impl DiagnoseCommand {
+  pub fn run(self, service: Arc<dyn TrustlessService>, store_name: String) -> Result<()> {
+    let diagnostics = service
+      .diagnose(&store_name)
+      .with_context(|| format!("Diagnose store {}", store_name))?;
+
+    if self.json {
+      println!("{}", serde_json::to_string_pretty(&diagnostics)?);
+    } else {
+      print!("{}", diagnostics);
+    }
+
+    Ok(())
+  }
+}
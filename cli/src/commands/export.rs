@@ -1,31 +1,68 @@
 use std::{
-  fs::File,
+  fs::OpenOptions,
   io::{stdout, Write},
   sync::Arc,
 };
 
-use anyhow::{Context, Result};
-use clap::Args;
-use t_rust_less_lib::{api::SecretListFilter, service::TrustlessService};
+use anyhow::{bail, Context, Result};
+use clap::{Args, ValueEnum};
+use t_rust_less_lib::{
+  api::{SecretListFilter, TagMatch},
+  service::TrustlessService,
+};
 
-use crate::{error::ExtResult, model::import_v2::SecretV2};
+use crate::{
+  error::ExtResult,
+  model::{
+    export_csv::{csv_header, csv_row, CsvColumn},
+    import_v2::SecretV2,
+  },
+};
 
 use super::{tui::create_tui, unlock_store};
 
+/// Formats that can be exported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+  /// Json lines (that can be imported again)
+  V2,
+  /// Comma separated values of the current versions (with a header line)
+  Csv,
+}
+
 #[derive(Debug, Args)]
 pub struct ExportCommand {
-  #[clap(help = "File to export to. If not set export will write to stdout")]
+  #[clap(help = "File to export to (must not exist yet). If not set export will write to stdout")]
   pub file: Option<String>,
 
   #[clap(long)]
   pub include_deleted: bool,
 
-  #[clap(long)]
+  #[clap(long, conflicts_with = "columns")]
   pub include_version: bool,
+
+  #[clap(long, value_enum, default_value = "v2")]
+  pub format: ExportFormat,
+
+  #[clap(
+    long,
+    value_enum,
+    value_delimiter = ',',
+    default_value = "name,type,username,password,urls,tags,notes",
+    help = "Columns of the csv export"
+  )]
+  pub columns: Vec<CsvColumn>,
+
+  #[clap(long, help = "Confirm that the csv export contains unencrypted passwords")]
+  pub i_know_this_is_plaintext: bool,
 }
 
 impl ExportCommand {
   pub fn run(self, service: Arc<dyn TrustlessService>, store_name: String) -> Result<()> {
+    if self.format == ExportFormat::Csv && !self.i_know_this_is_plaintext {
+      bail!("The csv export contains all passwords as plain text, add --i-know-this-is-plaintext to proceed");
+    }
+
     let secrets_store = service
       .open_store(&store_name)
       .with_context(|| format!("Failed opening store {}: ", store_name))?;
@@ -40,7 +77,11 @@ impl ExportCommand {
     let mut filters = vec![SecretListFilter {
       name: None,
       url: None,
-      tag: None,
+      tags: vec![],
+      tag_match: TagMatch::All,
+      content: None,
+      offset: 0,
+      limit: None,
       ..Default::default()
     }];
 
@@ -48,20 +89,38 @@ impl ExportCommand {
       filters.push(SecretListFilter {
         name: None,
         url: None,
-        tag: None,
+        tags: vec![],
+        tag_match: TagMatch::All,
         deleted: true,
+        content: None,
+        offset: 0,
+        limit: None,
         ..Default::default()
       })
     }
 
     let mut export_stream: Box<dyn Write> = match &self.file {
       Some(file_name) => {
-        let file = File::open(file_name).with_context(|| format!("Failed opening {}", file_name))?;
+        // The export is in plain text: Never overwrite anything and keep it private
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+          use std::os::unix::fs::OpenOptionsExt;
+          options.mode(0o600);
+        }
+        let file = options
+          .open(file_name)
+          .with_context(|| format!("Failed creating {}", file_name))?;
         Box::new(file)
       }
       None => Box::new(stdout()),
     };
 
+    if self.format == ExportFormat::Csv {
+      writeln!(&mut export_stream, "{}", csv_header(&self.columns))?;
+    }
+
     for filter in &filters {
       let list = secrets_store.list(filter)?;
 
@@ -70,6 +129,11 @@ impl ExportCommand {
           .get(&entry_match.entry.id)
           .with_context(|| format!("Get entry {} {}", entry_match.entry.id, entry_match.entry.name))?;
 
+        if self.format == ExportFormat::Csv {
+          writeln!(&mut export_stream, "{}", csv_row(&secret.current, &self.columns))?;
+          continue;
+        }
+
         let mut service_v2 = SecretV2 {
           id: secret.id.clone(),
           current: (&secret.current).into(),
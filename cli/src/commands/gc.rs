@@ -0,0 +1,38 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use std::sync::Arc;
+use t_rust_less_lib::service::TrustlessService;
+
+use super::{tui::create_tui, unlock_store};
+
+#[derive(Debug, Args)]
+pub struct GcCommand {
+  #[clap(
+    long,
+    help = "Keep previous versions of all secrets (otherwise they are deleted, which is synchronized to all other nodes and cannot be undone)"
+  )]
+  pub keep_history: bool,
+}
+
+impl GcCommand {
+  pub fn run(self, service: Arc<dyn TrustlessService>, store_name: String) -> Result<()> {
+    let secrets_store = service
+      .open_store(&store_name)
+      .with_context(|| format!("Failed opening store {}: ", store_name))?;
+    let status = secrets_store.status().with_context(|| "Get status")?;
+
+    if status.locked {
+      let mut siv = create_tui();
+      unlock_store(&mut siv, &secrets_store, &store_name)?;
+      siv.quit();
+    }
+
+    let removed = secrets_store
+      .collect_garbage(!self.keep_history)
+      .with_context(|| "Collect garbage")?;
+
+    println!("Removed {} unreferenced blocks from {}", removed, store_name);
+
+    Ok(())
+  }
+}
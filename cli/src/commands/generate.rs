@@ -1,14 +1,28 @@
 use crate::error::ExtResult;
 use anyhow::Result;
-use clap::Args;
+use clap::{Args, Subcommand};
+use std::path::PathBuf;
 use std::sync::Arc;
 use t_rust_less_lib::{
-  api::{PasswordGeneratorCharsParam, PasswordGeneratorParam, PasswordGeneratorWordsParam},
+  api::{
+    PasswordGeneratorCharsParam, PasswordGeneratorParam, PasswordGeneratorPronounceableParam,
+    PasswordGeneratorWordsParam,
+  },
   service::TrustlessService,
 };
 
+#[derive(Debug, Subcommand)]
+pub enum GenerateSubCommand {
+  #[clap(about = "Generate numeric PINs")]
+  Pin {
+    #[clap(help = "Number of digits")]
+    length: u8,
+  },
+}
+
+/// Constraints of generated passwords (shared by all commands generating passwords)
 #[derive(Debug, Args)]
-pub struct GenerateCommand {
+pub struct GeneratorArgs {
   #[clap(long)]
   exclude_uppers: bool,
   #[clap(long)]
@@ -25,22 +39,38 @@ pub struct GenerateCommand {
   include_ambiguous: bool,
   #[clap(long)]
   include_similar: bool,
-  #[clap(long)]
+  #[clap(
+    long,
+    help = "Symbols to use instead of the default ones (e.g. if a site only accepts some)"
+  )]
+  symbols: Option<String>,
+  #[clap(long, value_name = "CHARS", help = "Characters that must not be used")]
+  exclude: Option<String>,
+  #[clap(long, conflicts_with = "pronounceable")]
   words: bool,
+  #[clap(long, help = "Generate pronounceable passwords (alternating consonants and vowels)")]
+  pronounceable: bool,
   #[clap(long, default_value = ".")]
   delim: String,
+  #[clap(long, help = "Generate words from a custom wordlist (one word per line)")]
+  wordlist: Option<PathBuf>,
   #[clap(long)]
   length: Option<u8>,
-  #[clap(long, default_value = "5")]
-  count: usize,
 }
 
-impl GenerateCommand {
-  pub fn run(self, service: Arc<dyn TrustlessService>) -> Result<()> {
-    let param: PasswordGeneratorParam = if self.words {
+impl GeneratorArgs {
+  pub fn param(self) -> PasswordGeneratorParam {
+    if self.words || self.wordlist.is_some() {
       PasswordGeneratorParam::Words(PasswordGeneratorWordsParam {
         num_words: self.length.unwrap_or(4),
         delim: self.delim.chars().next().unwrap_or('.'),
+        wordlist_path: self.wordlist.map(|wordlist| wordlist.to_string_lossy().to_string()),
+      })
+    } else if self.pronounceable {
+      PasswordGeneratorParam::Pronounceable(PasswordGeneratorPronounceableParam {
+        num_chars: self.length.unwrap_or(12),
+        include_number: !self.exclude_numbers,
+        include_symbol: !self.exclude_symbols,
       })
     } else {
       PasswordGeneratorParam::Chars(PasswordGeneratorCharsParam {
@@ -53,7 +83,28 @@ impl GenerateCommand {
         require_symbol: self.require_symbol,
         exclude_ambiguous: !self.include_ambiguous,
         exclude_similar: !self.include_similar,
+        symbol_set: self.symbols,
+        exclude_chars: self.exclude,
       })
+    }
+  }
+}
+
+#[derive(Debug, Args)]
+pub struct GenerateCommand {
+  #[clap(subcommand)]
+  subcommand: Option<GenerateSubCommand>,
+  #[clap(flatten)]
+  generator: GeneratorArgs,
+  #[clap(long, default_value = "5")]
+  count: usize,
+}
+
+impl GenerateCommand {
+  pub fn run(self, service: Arc<dyn TrustlessService>) -> Result<()> {
+    let param = match self.subcommand {
+      Some(GenerateSubCommand::Pin { length }) => PasswordGeneratorParam::Pin { length },
+      None => self.generator.param(),
     };
 
     for _ in 0..self.count {
@@ -1,68 +1,93 @@
 use crate::commands::tui::create_tui;
 use crate::commands::unlock_store;
+use crate::model::import_format::{detect_format, strip_bom, ImportFormat};
 use crate::model::import_v1::SecretV1;
+use crate::model::import_v2::SecretV2;
 use anyhow::{bail, Context, Result};
 use clap::Args;
+use serde::de::DeserializeOwned;
 use std::fs::File;
-use std::io::{stdin, BufRead, BufReader};
+use std::io::{stdin, Read};
 use std::sync::Arc;
 use t_rust_less_lib::api::SecretVersion;
+use t_rust_less_lib::secrets_store::SecretsStore;
 use t_rust_less_lib::service::TrustlessService;
+use zeroize::Zeroizing;
 
 #[derive(Debug, Args)]
 pub struct ImportCommand {
-  #[clap(long, help = "Import V1 format (from original trustless)")]
+  #[clap(long, help = "Import V1 format (from original trustless), same as --format v1")]
   pub v1: bool,
 
+  #[clap(long, value_enum, help = "Format of the import. If not set the format is detected")]
+  pub format: Option<ImportFormat>,
+
+  #[clap(long, conflicts_with = "file", help = "Read the import from stdin")]
+  pub from_stdin: bool,
+
   #[clap(help = "File to import. If not set import will read from stdin")]
   pub file: Option<String>,
 }
 
 impl ImportCommand {
   pub fn run(self, service: Arc<dyn TrustlessService>, store_name: String) -> Result<()> {
-    if self.v1 {
-      import_v1(service, store_name, self.file)?;
-    } else {
-      bail!("Only v1 import supported yet");
-    }
+    let secrets_store = service
+      .open_store(&store_name)
+      .with_context(|| format!("Failed opening store {}: ", store_name))?;
 
-    Ok(())
-  }
-}
+    let status = secrets_store.status().with_context(|| "Get status")?;
 
-pub fn import_v1(
-  service: Arc<dyn TrustlessService>,
-  store_name: String,
-  maybe_file_name: Option<String>,
-) -> Result<()> {
-  let secrets_store = service
-    .open_store(&store_name)
-    .with_context(|| format!("Failed opening store {}: ", store_name))?;
-
-  let status = secrets_store.status().with_context(|| "Get status")?;
-
-  let import_stream: Box<dyn BufRead> = match &maybe_file_name {
-    Some(file_name) => {
-      let file = File::open(file_name).with_context(|| format!("Failed opening {}", file_name))?;
-      Box::new(BufReader::new(file))
-    }
-    None => {
-      if status.locked {
-        bail!("Store is locked! Cannot unlock store when importing from stdin (duh).");
+    let mut content = Zeroizing::new(vec![]);
+    match &self.file {
+      Some(file_name) => {
+        let mut file = File::open(file_name).with_context(|| format!("Failed opening {}", file_name))?;
+        file.read_to_end(&mut content).with_context(|| "IO Error")?;
+      }
+      None => {
+        if status.locked {
+          bail!("Store is locked! Cannot unlock store when importing from stdin (duh).");
+        }
+        stdin().read_to_end(&mut content).with_context(|| "IO Error")?;
       }
-      Box::new(BufReader::new(stdin()))
+    };
+    let content = strip_bom(&content);
+
+    let format = match self.format {
+      Some(format) => format,
+      None if self.v1 => ImportFormat::V1,
+      None => detect_format(content)?,
+    };
+
+    if status.locked {
+      let mut siv = create_tui();
+      unlock_store(&mut siv, &secrets_store, &store_name)?;
+    }
+
+    match format {
+      ImportFormat::V1 => import_v1(secrets_store.as_ref(), content)?,
+      ImportFormat::V2 => import_v2(secrets_store.as_ref(), content)?,
+      ImportFormat::Csv | ImportFormat::KeepassXml => bail!("Import of {} is not supported yet", format),
     }
-  };
 
-  if status.locked {
-    let mut siv = create_tui();
-    unlock_store(&mut siv, &secrets_store, &store_name)?;
+    secrets_store.update_index().with_context(|| "Index update")?;
+
+    Ok(())
   }
+}
 
-  for maybe_line in import_stream.lines() {
-    let line = maybe_line.with_context(|| "IO Error")?;
-    let mut secret = serde_json::from_str::<SecretV1>(&line).with_context(|| "Invalid format")?;
+/// Parse json lines or a json array.
+fn json_records<T: DeserializeOwned>(content: &[u8]) -> Result<Vec<T>> {
+  if content.trim_ascii_start().starts_with(b"[") {
+    return serde_json::from_slice(content).with_context(|| "Invalid format");
+  }
+  serde_json::Deserializer::from_slice(content)
+    .into_iter::<T>()
+    .collect::<serde_json::Result<Vec<T>>>()
+    .with_context(|| "Invalid format")
+}
 
+fn import_v1(secrets_store: &dyn SecretsStore, content: &[u8]) -> Result<()> {
+  for mut secret in json_records::<SecretV1>(content)? {
     eprintln!("Importing secret {}", secret.id);
 
     for v1_version in secret.versions.iter_mut() {
@@ -77,13 +102,32 @@ pub fn import_v1(
         properties: v1_version.properties.clone(),
         deleted: v1_version.deleted,
         recipients: vec![],
+        expires_at: None,
+        parent_block_id: None,
+        modified_by: None,
       };
 
       secrets_store.add(version).with_context(|| "Add secret version")?;
     }
   }
 
-  secrets_store.update_index().with_context(|| "Index update")?;
+  Ok(())
+}
+
+fn import_v2(secrets_store: &dyn SecretsStore, content: &[u8]) -> Result<()> {
+  for secret in json_records::<SecretV2>(content)? {
+    eprintln!("Importing secret {}", secret.id);
+
+    // With --include-version the current version is also part of the versions
+    for version in secret.versions.iter().filter(|version| **version != secret.current) {
+      secrets_store
+        .add(version.to_version(&secret.id))
+        .with_context(|| "Add secret version")?;
+    }
+    secrets_store
+      .add(secret.current.to_version(&secret.id))
+      .with_context(|| "Add secret version")?;
+  }
 
   Ok(())
 }
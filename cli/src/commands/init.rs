@@ -1,10 +1,12 @@
 use anyhow::{bail, Context, Result};
 use atty::Stream;
-use clap::Args;
+use clap::{Args, ValueEnum};
 use cursive::traits::{Nameable, Resizable};
 use cursive::views::{Dialog, DummyView, EditView, LinearLayout, TextView};
 use cursive::Cursive;
-use t_rust_less_lib::api::StoreConfig;
+use t_rust_less_lib::api::{
+  CipherSuite, StoreConfig, DEFAULT_CLIPBOARD_TIMEOUT_SECS, DEFAULT_MAX_ATTACHMENT_SIZE, DEFAULT_SYNC_CONCURRENCY,
+};
 
 use crate::commands::add_identity::add_identity_dialog;
 use crate::commands::generate_id;
@@ -12,12 +14,77 @@ use crate::commands::tui::create_tui;
 use crate::config::{default_autolock_timeout, default_store_dir};
 use cursive::event::Key;
 use std::fs;
+use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
+use t_rust_less_lib::secrets_store::cipher::{
+  has_aes_hardware_support, preferred_cipher_suite, KeyDerivation, ARGON2_PRESET_DESKTOP, ARGON2_PRESET_MOBILE,
+  ARGON2_PRESET_PARANOID, RUST_ARGON2_ID,
+};
+use t_rust_less_lib::secrets_store::pepper::{create_pepper_file, read_pepper};
 use t_rust_less_lib::service::TrustlessService;
 use url::Url;
 
 #[derive(Debug, Args)]
-pub struct InitCommand {}
+pub struct InitCommand {
+  #[cfg(feature = "with_fido2")]
+  #[clap(
+    long,
+    help = "Require a FIDO2 authenticator (hmac-secret) to unlock the initial identity"
+  )]
+  pub require_fido2: bool,
+
+  #[clap(
+    long,
+    value_name = "FILE",
+    help = "Require a pepper file to unlock the store (a random one is created if the file does not exist)"
+  )]
+  pub pepper: Option<String>,
+
+  #[clap(
+    long,
+    value_enum,
+    help = "Strength of the key derivation of the passphrase (auto picks the strongest taking less than a second)"
+  )]
+  pub kdf_preset: Option<KdfPreset>,
+
+  #[clap(
+    long,
+    value_enum,
+    help = "Cipher suite secrets are encrypted with (all by default, auto shows the fastest single suite on this machine)"
+  )]
+  pub cipher_suite: Option<InitCipherSuite>,
+}
+
+/// Key derivation presets selectable on init.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum KdfPreset {
+  /// 32 MiB memory, 3 iterations
+  Mobile,
+  /// 64 MiB memory, 4 iterations
+  Desktop,
+  /// 256 MiB memory, 6 iterations
+  Paranoid,
+  /// Benchmark all presets on this machine
+  Auto,
+}
+
+/// Cipher suites selectable on init.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum InitCipherSuite {
+  /// One layer of encryption for each supported suite
+  All,
+  /// RSA with AES-GCM
+  RsaAesGcm,
+  /// X25519 key agreement with ChaCha20-Poly1305
+  X25519Chacha20Poly1305,
+  /// Like all, but recommends a single suite (AES-GCM if there is AES hardware acceleration,
+  /// ChaCha20-Poly1305 otherwise), which has to be selected explicitly
+  Auto,
+}
+
+/// Time the derivation of a key may take with `KdfPreset::Auto`
+const CALIBRATION_TARGET: Duration = Duration::from_secs(1);
 
 impl InitCommand {
   pub fn run(self, service: Arc<dyn TrustlessService>, maybe_store_name: Option<String>) -> Result<()> {
@@ -44,7 +111,45 @@ impl InitCommand {
       Some(config) => config.autolock_timeout_secs,
       _ => default_autolock_timeout().as_secs(),
     };
+    let pepper_file = match self.pepper {
+      Some(pepper_file) => {
+        let pepper_file = expand_path(&pepper_file);
+        if !Path::new(&pepper_file).exists() {
+          create_pepper_file(&pepper_file).with_context(|| format!("Creating pepper file {}", pepper_file))?;
+        }
+        read_pepper(&pepper_file).with_context(|| format!("Checking pepper file {}", pepper_file))?;
+        Some(pepper_file)
+      }
+      // Dropping the pepper of an existing store would make it inaccessible
+      None => maybe_config.and_then(|config| config.pepper_file.clone()),
+    };
+
+    let key_derivation_preset = match self.kdf_preset {
+      Some(KdfPreset::Mobile) => Some(ARGON2_PRESET_MOBILE),
+      Some(KdfPreset::Desktop) => Some(ARGON2_PRESET_DESKTOP),
+      Some(KdfPreset::Paranoid) => Some(ARGON2_PRESET_PARANOID),
+      Some(KdfPreset::Auto) => Some(RUST_ARGON2_ID.calibrate(CALIBRATION_TARGET)),
+      None => maybe_config.and_then(|config| config.key_derivation_preset),
+    };
 
+    let cipher_suite = match self.cipher_suite {
+      Some(InitCipherSuite::All) => CipherSuite::All,
+      Some(InitCipherSuite::RsaAesGcm) => CipherSuite::RsaAesGcm,
+      Some(InitCipherSuite::X25519Chacha20Poly1305) => CipherSuite::X25519Chacha20Poly1305,
+      // Restricting a store to a single suite is always an explicit choice
+      Some(InitCipherSuite::Auto) => CipherSuite::All,
+      // Existing stores keep their cipher suite
+      None => match maybe_config {
+        Some(config) => config.cipher_suite,
+        None => CipherSuite::All,
+      },
+    };
+    let recommend_cipher_suite = self.cipher_suite == Some(InitCipherSuite::Auto);
+
+    #[cfg(feature = "with_fido2")]
+    let hardware_factor = self.require_fido2;
+    #[cfg(not(feature = "with_fido2"))]
+    let hardware_factor = false;
     let mut siv = create_tui();
 
     siv.set_user_data(service);
@@ -69,10 +174,22 @@ impl InitCommand {
             EditView::new()
               .content(autolock_timeout_secs.to_string())
               .with_name("autolock_timeout"),
-          ),
+          )
+          .child(DummyView {})
+          .child(TextView::new(cipher_suite_notice(cipher_suite, recommend_cipher_suite)))
+          .child(DummyView {})
+          .child(TextView::new(pepper_notice(pepper_file.as_deref()))),
       )
       .button("Abort", Cursive::quit)
-      .button("Store", store_config)
+      .button("Store", move |s| {
+        store_config(
+          s,
+          hardware_factor,
+          key_derivation_preset,
+          cipher_suite,
+          pepper_file.clone(),
+        )
+      })
       .title("t-rust-less configuration")
       .padding_left(5)
       .padding_right(5)
@@ -98,7 +215,13 @@ macro_rules! try_with_dialog {
   };
 }
 
-fn store_config(s: &mut Cursive) {
+fn store_config(
+  s: &mut Cursive,
+  hardware_factor: bool,
+  key_derivation_preset: Option<u8>,
+  cipher_suite: CipherSuite,
+  pepper_file: Option<String>,
+) {
   let service = s.user_data::<Arc<dyn TrustlessService>>().unwrap().clone();
   let store_name = s.find_name::<EditView>("store_name").unwrap().get_content();
   let store_path = expand_path(&s.find_name::<EditView>("store_dir").unwrap().get_content());
@@ -131,8 +254,24 @@ fn store_config(s: &mut Cursive) {
     store_url: secrets_store_url,
     remote_url: None,
     sync_interval_sec: 0,
+    sync_max_rate: 0,
+    sync_concurrency: DEFAULT_SYNC_CONCURRENCY,
     autolock_timeout_secs,
     default_identity_id: None,
+    unlock_throttle_attempts: 5,
+    unlock_throttle_file: None,
+    url_tag_rules: vec![],
+    default_recipients: Default::default(),
+    strength_estimator: Default::default(),
+    attachment_storage: Default::default(),
+    max_attachment_size: DEFAULT_MAX_ATTACHMENT_SIZE,
+    compress_blocks: false,
+    cipher_suite,
+    key_derivation_preset,
+    restore_clipboard: None,
+    clipboard_timeout_secs: DEFAULT_CLIPBOARD_TIMEOUT_SECS,
+    pepper_file,
+    breach_dir: None,
   };
 
   try_with_dialog!(service.upsert_store_config(config), s, "Failed to store config:\n{}");
@@ -148,13 +287,62 @@ fn store_config(s: &mut Cursive) {
   if identities.is_empty() {
     s.pop_layer();
 
-    add_identity_dialog(s, secrets_store, "Create initial identity");
+    add_identity_dialog(s, secrets_store, "Create initial identity", hardware_factor);
     return;
   }
 
   s.quit();
 }
 
+fn cipher_suite_name(cipher_suite: CipherSuite) -> &'static str {
+  match cipher_suite {
+    CipherSuite::All => "All (one layer per suite)",
+    CipherSuite::RsaAesGcm => "RSA with AES-GCM",
+    CipherSuite::X25519Chacha20Poly1305 => "X25519 with ChaCha20-Poly1305",
+  }
+}
+
+fn cipher_suite_notice(cipher_suite: CipherSuite, recommend: bool) -> String {
+  let hardware = if has_aes_hardware_support() {
+    "AES hardware acceleration available"
+  } else {
+    "No AES hardware acceleration"
+  };
+
+  if recommend {
+    let (recommended, option) = match preferred_cipher_suite() {
+      CipherSuite::RsaAesGcm => (CipherSuite::RsaAesGcm, InitCipherSuite::RsaAesGcm),
+      _ => (
+        CipherSuite::X25519Chacha20Poly1305,
+        InitCipherSuite::X25519Chacha20Poly1305,
+      ),
+    };
+    return format!(
+      "Cipher suite: {}\n({}, recommended single suite: {}, use --cipher-suite {} to select it)",
+      cipher_suite_name(cipher_suite),
+      hardware,
+      cipher_suite_name(recommended),
+      option.to_possible_value().unwrap().get_name()
+    );
+  }
+
+  format!(
+    "Cipher suite: {}\n({}, use --cipher-suite to change)",
+    cipher_suite_name(cipher_suite),
+    hardware
+  )
+}
+
+fn pepper_notice(pepper_file: Option<&str>) -> String {
+  match pepper_file {
+    Some(pepper_file) => format!(
+      "Pepper file: {}\nWARNING: The store can not be unlocked without this file, keep a backup of it!",
+      collapse_path(pepper_file.to_string())
+    ),
+    None => "No pepper file (use --pepper to require one)".to_string(),
+  }
+}
+
 fn collapse_path(path: String) -> String {
   match dirs::home_dir() {
     Some(home_dir) => {
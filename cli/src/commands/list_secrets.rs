@@ -1,7 +1,10 @@
+use crate::cli::OutputFormat;
 use crate::commands::tui::create_tui;
 use crate::commands::unlock_store;
+use crate::config::{read_tui_config, TuiConfig};
 use crate::error::ExtResult;
-use crate::view::{SecretView, StatusView};
+use crate::model::date_bound::parse_date_bound;
+use crate::view::{content_preview, SecretView, StatusView};
 use anyhow::{Context, Result};
 use atty::Stream;
 use chrono::{DateTime, Utc};
@@ -10,14 +13,28 @@ use cursive::event::{Event, Key};
 use cursive::theme::Effect;
 use cursive::traits::{Nameable, Resizable, Scrollable};
 use cursive::utils::markup::StyledString;
-use cursive::views::{EditView, LinearLayout, ResizedView, SelectView, TextContent};
+use cursive::views::{Dialog, EditView, LinearLayout, OnEventView, ResizedView, SelectView, TextContent, TextView};
 use cursive::{Cursive, CursiveRunnable};
+use std::collections::HashSet;
 use std::sync::Arc;
 use t_rust_less_lib::api::{
-  SecretEntry, SecretEntryMatch, SecretListFilter, Status, PROPERTY_PASSWORD, PROPERTY_TOTP_URL, PROPERTY_USERNAME,
+  SecretEntryMatch, SecretList, SecretListFilter, SecretType, Status, TagMatch, TagTreeNode, PROPERTY_PASSWORD,
+  PROPERTY_TOTP_URL, PROPERTY_USERNAME,
 };
 use t_rust_less_lib::secrets_store::SecretsStore;
-use t_rust_less_lib::service::TrustlessService;
+use t_rust_less_lib::service::{ClipboardControl, TrustlessService};
+
+/// Content is only searched for queries of at least this many chars (every search has to decrypt all secrets)
+const MIN_CONTENT_SEARCH_LENGTH: usize = 3;
+
+const SECRET_TYPES: &[SecretType] = &[
+  SecretType::Login,
+  SecretType::Password,
+  SecretType::Note,
+  SecretType::Licence,
+  SecretType::Wlan,
+  SecretType::Other,
+];
 
 #[derive(Debug, Args)]
 pub struct ListSecretsCommand {
@@ -25,23 +42,53 @@ pub struct ListSecretsCommand {
   pub name: Option<String>,
   #[clap(long, short)]
   pub url: Option<String>,
-  #[clap(long, short)]
-  pub tag: Option<String>,
+  #[clap(
+    long = "tag",
+    short,
+    help = "Tag filter (may be repeated, all tags have to match by default)"
+  )]
+  pub tags: Vec<String>,
+  #[clap(long, help = "Match secrets with any of the tags instead of all of them")]
+  pub match_any: bool,
   #[clap(long)]
   pub deleted: bool,
+  #[clap(long, help = "Group secrets by their hierarchical tags (e.g. work/aws/prod)")]
+  pub tag_tree: bool,
+  #[clap(long, short, help = "Search in the property values (except passwords)")]
+  pub content: Option<String>,
+  #[clap(
+    long,
+    value_parser = parse_date_bound,
+    help = "Only secrets modified since (ISO-8601 date or relative like 30d, 2w, 12h)"
+  )]
+  pub since: Option<DateTime<Utc>>,
+  #[clap(
+    long,
+    value_parser = parse_date_bound,
+    help = "Only secrets modified until (ISO-8601 date or relative like 30d, 2w, 12h)"
+  )]
+  pub until: Option<DateTime<Utc>>,
 }
 
 impl ListSecretsCommand {
-  pub fn run(self, service: Arc<dyn TrustlessService>, store_name: String) -> Result<()> {
+  pub fn run(self, service: Arc<dyn TrustlessService>, store_name: String, output: OutputFormat) -> Result<()> {
     let filter = SecretListFilter {
       name: self.name,
-      tag: self.tag,
+      tags: self.tags,
+      tag_match: if self.match_any { TagMatch::Any } else { TagMatch::All },
       url: self.url,
       deleted: self.deleted,
+      group_by_tag: self.tag_tree,
+      content: self.content,
+      modified_after: self.since.map(Into::into),
+      modified_before: self.until.map(Into::into),
       ..Default::default()
     };
 
-    list_secrets(service, store_name, filter)
+    match output {
+      OutputFormat::Text => list_secrets(service, store_name, filter),
+      OutputFormat::Json => list_secrets_json(service, store_name, filter),
+    }
   }
 }
 
@@ -60,41 +107,154 @@ pub fn list_secrets(service: Arc<dyn TrustlessService>, store_name: String, filt
     }
     let mut siv = create_tui();
 
+    let search_mode = if filter.content.is_some() {
+      SearchMode::Content
+    } else {
+      SearchMode::Name
+    };
     let initial_state = ListUIState {
       service,
       store_name,
       secrets_store,
       filter,
+      search_mode,
+      search_label: TextContent::new(search_mode.label()),
+      preview_text: TextContent::new(""),
       status_text: TextContent::new(status_text(&status)),
       last_update: None,
+      tui_config: read_tui_config(),
+      clipboard_text: TextContent::new(""),
+      clipboard: None,
+      clipboard_clear_at: None,
+      collapsed_tags: HashSet::new(),
     };
     list_secrets_ui(&mut siv, initial_state, status)?;
   } else {
     let list = secrets_store.list(&filter).with_context(|| "List entries")?;
 
-    for entry in list.entries.iter() {
-      println!("{:?}", entry);
+    match &list.tag_tree {
+      Some(tag_tree) => {
+        for node in &tag_tree.roots {
+          print_tag_node(secrets_store.as_ref(), &list, node, 0);
+        }
+        for entry_id in &tag_tree.untagged {
+          print_entry(secrets_store.as_ref(), &list, entry_id, 0);
+        }
+      }
+      None => {
+        for entry in list.entries.iter() {
+          println!("{:?}{}", entry, breach_flag(secrets_store.as_ref(), &entry.entry.id));
+        }
+      }
+    }
+    if !list.unavailable_blocks.is_empty() {
+      eprintln!(
+        "{} secrets unavailable (sync in progress?)",
+        list.unavailable_blocks.len()
+      );
     }
   }
 
   Ok(())
 }
 
+/// Print the list (including all highlights) as json. Like the list itself this never contains any
+/// property values, only the positions of content matches.
+fn list_secrets_json(service: Arc<dyn TrustlessService>, store_name: String, filter: SecretListFilter) -> Result<()> {
+  let secrets_store = service
+    .open_store(&store_name)
+    .with_context(|| format!("Failed opening store {}: ", store_name))?;
+  let list = secrets_store.list(&filter).with_context(|| "List entries")?;
+
+  println!("{}", serde_json::to_string(&list)?);
+
+  Ok(())
+}
+
+fn print_tag_node(secrets_store: &dyn SecretsStore, list: &SecretList, node: &TagTreeNode, depth: usize) {
+  println!("{:indent$}{}/ ({})", "", node.name, node.count, indent = depth * 2);
+  for child in &node.children {
+    print_tag_node(secrets_store, list, child, depth + 1);
+  }
+  for entry_id in &node.entries {
+    print_entry(secrets_store, list, entry_id, depth + 1);
+  }
+}
+
+fn print_entry(secrets_store: &dyn SecretsStore, list: &SecretList, entry_id: &str, depth: usize) {
+  if let Some(entry_match) = list.entries.iter().find(|entry_match| entry_match.entry.id == entry_id) {
+    println!(
+      "{:indent$}{:?}{}",
+      "",
+      entry_match.entry,
+      breach_flag(secrets_store, entry_id),
+      indent = depth * 2
+    );
+  }
+}
+
+/// Warning flag for secrets with a breached password (failed checks are not flagged, the list is still useful)
+fn breach_flag(secrets_store: &dyn SecretsStore, secret_id: &str) -> &'static str {
+  match secrets_store.check_breached(secret_id) {
+    Ok(Some(_)) => " [BREACHED]",
+    _ => "",
+  }
+}
+
+/// Item of the list view, either a secret or a (collapsible) tag folder
+#[derive(Clone)]
+enum ListItem {
+  Folder(String),
+  Entry(Box<SecretEntryMatch>),
+}
+
+/// What the search box is searching for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SearchMode {
+  Name,
+  Content,
+}
+
+impl SearchMode {
+  fn label(&self) -> &'static str {
+    match self {
+      SearchMode::Name => "Name ",
+      SearchMode::Content => "Content ",
+    }
+  }
+}
+
 struct ListUIState {
   service: Arc<dyn TrustlessService>,
   store_name: String,
   secrets_store: Arc<dyn SecretsStore>,
   filter: SecretListFilter,
+  search_mode: SearchMode,
+  search_label: TextContent,
+  /// Snippets of the content matches of the selected entry
+  preview_text: TextContent,
   status_text: TextContent,
   last_update: Option<DateTime<Utc>>,
+  tui_config: TuiConfig,
+  clipboard_text: TextContent,
+  /// Clipboard provided by the ui (if any)
+  clipboard: Option<Arc<dyn ClipboardControl>>,
+  clipboard_clear_at: Option<DateTime<Utc>>,
+  /// Paths of the tag folders that are collapsed
+  collapsed_tags: HashSet<String>,
 }
 
-fn list_secrets_ui(siv: &mut CursiveRunnable, initial_state: ListUIState, status: Status) -> Result<()> {
+fn list_secrets_ui(siv: &mut CursiveRunnable, mut initial_state: ListUIState, status: Status) -> Result<()> {
   let mut name_search = EditView::new();
-  if let Some(name_filter) = &initial_state.filter.name {
-    name_search.set_content(name_filter.to_string());
+  if let Some(query) = initial_state
+    .filter
+    .name
+    .as_ref()
+    .or(initial_state.filter.content.as_ref())
+  {
+    name_search.set_content(query.to_string());
   }
-  name_search.set_on_edit(update_name_filter);
+  name_search.set_on_edit(update_search_filter);
 
   let secrets_store = initial_state.secrets_store.clone();
 
@@ -107,11 +267,14 @@ fn list_secrets_ui(siv: &mut CursiveRunnable, initial_state: ListUIState, status
   siv.add_global_callback(Event::CtrlChar('u'), secret_to_clipboard(&[PROPERTY_USERNAME]));
   siv.add_global_callback(Event::CtrlChar('p'), secret_to_clipboard(&[PROPERTY_PASSWORD]));
   siv.add_global_callback(Event::CtrlChar('o'), secret_to_clipboard(&[PROPERTY_TOTP_URL]));
+  siv.add_global_callback(Event::CtrlChar('t'), change_secret_type);
+  siv.add_global_callback(Event::CtrlChar('f'), toggle_search_mode);
   siv.add_global_callback(Event::Refresh, update_status);
   siv.add_fullscreen_layer(
     LinearLayout::vertical()
       .child(
         LinearLayout::horizontal()
+          .child(TextView::new_with_content(initial_state.search_label.clone()))
           .child(name_search.with_name("name_search").full_width())
           .child(
             StatusView::new(secrets_store.clone(), status)
@@ -119,7 +282,9 @@ fn list_secrets_ui(siv: &mut CursiveRunnable, initial_state: ListUIState, status
               .fixed_width(14),
           ),
       )
-      .child(create_list_view(&initial_state))
+      .child(create_list_view(&mut initial_state))
+      .child(TextView::new_with_content(initial_state.preview_text.clone()))
+      .child(TextView::new_with_content(initial_state.clipboard_text.clone()))
       .with_name("list_view"),
   );
   siv.set_user_data(initial_state);
@@ -129,38 +294,209 @@ fn list_secrets_ui(siv: &mut CursiveRunnable, initial_state: ListUIState, status
   Ok(())
 }
 
-fn update_name_filter(s: &mut Cursive, name_filter: &str, _: usize) {
-  let next_entries: Vec<SecretEntryMatch> = {
+fn update_search_filter(s: &mut Cursive, query: &str, _: usize) {
+  {
     let state = s.user_data::<ListUIState>().unwrap();
-    state.filter.name = if name_filter.is_empty() {
-      None
-    } else {
-      Some(name_filter.to_string())
+    set_search_query(state, query);
+  }
+  refresh_list(s);
+}
+
+/// Switch the search box between searching names and contents
+fn toggle_search_mode(s: &mut Cursive) {
+  let query = s
+    .find_name::<EditView>("name_search")
+    .map(|name_search| name_search.get_content().to_string())
+    .unwrap_or_default();
+  {
+    let state = s.user_data::<ListUIState>().unwrap();
+    state.search_mode = match state.search_mode {
+      SearchMode::Name => SearchMode::Content,
+      SearchMode::Content => SearchMode::Name,
     };
+    state.search_label.set_content(state.search_mode.label());
+    set_search_query(state, &query);
+  }
+  refresh_list(s);
+}
 
-    let mut list = state.secrets_store.list(&state.filter).ok_or_exit("List entries");
+fn set_search_query(state: &mut ListUIState, query: &str) {
+  state.filter.name = None;
+  state.filter.content = None;
+  match state.search_mode {
+    SearchMode::Name if !query.is_empty() => state.filter.name = Some(query.to_string()),
+    SearchMode::Content if query.chars().count() >= MIN_CONTENT_SEARCH_LENGTH => {
+      state.filter.content = Some(query.to_string())
+    }
+    _ => (),
+  }
+}
+
+/// List the entries matching the current filter.
+///
+/// If the store is unable to search contents (e.g. an older daemon simply ignoring the filter) this falls back
+/// to a search by name.
+fn list_entries(state: &mut ListUIState) -> SecretList {
+  let maybe_list = state.secrets_store.list(&state.filter);
+  let content_supported = match &maybe_list {
+    Ok(list) => {
+      list.entries.is_empty()
+        || list
+          .entries
+          .iter()
+          .any(|entry_match| !entry_match.content_highlights.is_empty())
+    }
+    Err(_) => false,
+  };
+
+  if state.filter.content.is_some() && !content_supported {
+    state.filter.name = state.filter.content.take();
+    state.search_mode = SearchMode::Name;
+    state.search_label.set_content(state.search_mode.label());
+    state
+      .clipboard_text
+      .set_content(" content search unavailable, searching names only");
+    return state.secrets_store.list(&state.filter).ok_or_exit("List entries");
+  }
+
+  maybe_list.ok_or_exit("List entries")
+}
+
+fn refresh_list(s: &mut Cursive) {
+  let next_items = {
+    let state = s.user_data::<ListUIState>().unwrap();
+    let mut list = list_entries(state);
     list.entries.sort();
-    list.entries.drain(..).collect()
+    list_items(&list, &state.collapsed_tags)
   };
 
-  let mut entry_select = s.find_name::<SelectView<SecretEntry>>("entry_list").unwrap();
+  let first_entry = first_entry(&next_items);
+  show_entry(s, first_entry.as_ref());
+  let mut entry_select = s.find_name::<SelectView<ListItem>>("entry_list").unwrap();
+  entry_select.clear();
+  entry_select.add_all(next_items);
+}
+
+fn update_selection(s: &mut Cursive, item: &ListItem) {
+  if let ListItem::Entry(entry_match) = item {
+    show_entry(s, Some(entry_match.as_ref()));
+  }
+}
+
+fn show_entry(s: &mut Cursive, maybe_entry_match: Option<&SecretEntryMatch>) {
+  {
+    let state = s.user_data::<ListUIState>().unwrap();
+    update_preview(state, maybe_entry_match);
+  }
   let mut secret_view = s.find_name::<SecretView>("secret_view").unwrap();
-  match next_entries.first() {
-    Some(new_selection) => secret_view.show_secret(&new_selection.entry.id),
+  match maybe_entry_match {
+    Some(entry_match) => secret_view.show_secret(&entry_match.entry.id),
     None => secret_view.clear(),
   }
+}
+
+/// Show the content matches of an entry, the previous preview is always dropped.
+///
+/// The list itself only contains the positions of the matches, so the content is fetched freshly.
+fn update_preview(state: &ListUIState, maybe_entry_match: Option<&SecretEntryMatch>) {
+  state.preview_text.set_content("");
+
+  if let Some(entry_match) = maybe_entry_match.filter(|entry_match| !entry_match.content_highlights.is_empty()) {
+    if let Ok(secret) = state.secrets_store.get(&entry_match.entry.id) {
+      state
+        .preview_text
+        .set_content(content_preview(&secret.current, &entry_match.content_highlights));
+    }
+  }
+}
+
+/// Collapse or expand a tag folder
+fn toggle_folder(s: &mut Cursive, item: &ListItem) {
+  let ListItem::Folder(path) = item else {
+    return;
+  };
+  let next_items = {
+    let state = s.user_data::<ListUIState>().unwrap();
+    if !state.collapsed_tags.remove(path) {
+      state.collapsed_tags.insert(path.clone());
+    }
+    let list = list_entries(state);
+    list_items(&list, &state.collapsed_tags)
+  };
+
+  let mut entry_select = s.find_name::<SelectView<ListItem>>("entry_list").unwrap();
+  let selected = next_items
+    .iter()
+    .position(|(_, item)| matches!(item, ListItem::Folder(folder) if folder == path))
+    .unwrap_or_default();
   entry_select.clear();
-  entry_select.add_all(next_entries.into_iter().map(entry_list_item));
+  entry_select.add_all(next_items);
+  entry_select.set_selection(selected);
 }
 
-fn update_selection(s: &mut Cursive, entry: &SecretEntry) {
-  let mut secret_view = s.find_name::<SecretView>("secret_view").unwrap();
-  secret_view.show_secret(&entry.id);
+fn first_entry(items: &[(StyledString, ListItem)]) -> Option<SecretEntryMatch> {
+  items.iter().find_map(|(_, item)| match item {
+    ListItem::Entry(entry_match) => Some(entry_match.as_ref().clone()),
+    ListItem::Folder(_) => None,
+  })
+}
+
+/// Items of the list view, i.e. either the plain entries or the folders of the tag tree
+fn list_items(list: &SecretList, collapsed_tags: &HashSet<String>) -> Vec<(StyledString, ListItem)> {
+  let mut items = vec![];
+
+  match &list.tag_tree {
+    Some(tag_tree) => {
+      for node in &tag_tree.roots {
+        tag_folder_items(list, node, 0, collapsed_tags, &mut items);
+      }
+      for entry_id in &tag_tree.untagged {
+        items.extend(tagged_entry_item(list, entry_id, 0));
+      }
+    }
+    None => items.extend(list.entries.iter().map(|entry_match| entry_list_item(entry_match, 0))),
+  }
+
+  items
 }
 
-fn entry_list_item(entry_match: SecretEntryMatch) -> (StyledString, SecretEntry) {
+fn tag_folder_items(
+  list: &SecretList,
+  node: &TagTreeNode,
+  depth: usize,
+  collapsed_tags: &HashSet<String>,
+  items: &mut Vec<(StyledString, ListItem)>,
+) {
+  let collapsed = collapsed_tags.contains(&node.path);
+  let mut label = StyledString::plain(" ".repeat(depth * 2));
+  label.append_styled(
+    format!("{} {} ({})", if collapsed { "▸" } else { "▾" }, node.name, node.count),
+    Effect::Bold,
+  );
+  items.push((label, ListItem::Folder(node.path.clone())));
+
+  if collapsed {
+    return;
+  }
+  for child in &node.children {
+    tag_folder_items(list, child, depth + 1, collapsed_tags, items);
+  }
+  for entry_id in &node.entries {
+    items.extend(tagged_entry_item(list, entry_id, depth + 1));
+  }
+}
+
+fn tagged_entry_item(list: &SecretList, entry_id: &str, depth: usize) -> Option<(StyledString, ListItem)> {
+  list
+    .entries
+    .iter()
+    .find(|entry_match| entry_match.entry.id == entry_id)
+    .map(|entry_match| entry_list_item(entry_match, depth))
+}
+
+fn entry_list_item(entry_match: &SecretEntryMatch, depth: usize) -> (StyledString, ListItem) {
   let name = &entry_match.entry.name;
-  let mut styled_name = StyledString::new();
+  let mut styled_name = StyledString::plain(" ".repeat(depth * 2));
   let mut last = 0usize;
 
   for highlight in entry_match.name_highlights.iter() {
@@ -175,30 +511,133 @@ fn entry_list_item(entry_match: SecretEntryMatch) -> (StyledString, SecretEntry)
   }
   styled_name.append_plain(name.chars().skip(last).collect::<String>());
 
-  (styled_name, entry_match.entry.clone())
+  (styled_name, ListItem::Entry(Box::new(entry_match.clone())))
 }
 
 fn secret_to_clipboard(properties: &'static [&'static str]) -> impl Fn(&mut Cursive) {
   move |s: &mut Cursive| {
-    let maybe_secret = {
+    // Only the block id and the names of the properties are passed on, the service takes the actual
+    // values directly from the store (TOTP codes are generated on paste)
+    let maybe_block_id = {
       let secret_view = s.find_name::<SecretView>("secret_view").unwrap();
-      secret_view.current_secret()
+      secret_view
+        .current_secret()
+        .filter(|secret| {
+          properties
+            .iter()
+            .any(|property| secret.current.properties.get(property).is_some())
+        })
+        .map(|secret| secret.current_block_id.clone())
     };
     let state = s.user_data::<ListUIState>().unwrap();
+    let labels = properties
+      .iter()
+      .map(|property| property_label(property))
+      .collect::<Vec<_>>();
 
-    if let Some(secret) = maybe_secret {
-      state
-        .service
-        .secret_to_clipboard(&state.store_name, &secret.current_block_id, properties)
-        .ok_or_exit("Copy to clipboard");
+    match maybe_block_id {
+      Some(block_id) => {
+        let clipboard = state
+          .service
+          .secret_to_clipboard(&state.store_name, &block_id, properties)
+          .ok_or_exit("Copy to clipboard");
+        let timeout = state.tui_config.clipboard_timeout_secs;
+
+        if timeout > 0 {
+          state
+            .clipboard_text
+            .set_content(format!(" copied {} (clears in {}s)", labels.join(", "), timeout));
+          state.clipboard_clear_at = Some(Utc::now() + chrono::Duration::seconds(timeout as i64));
+        } else {
+          state
+            .clipboard_text
+            .set_content(format!(" copied {}", labels.join(", ")));
+          state.clipboard_clear_at = None;
+        }
+        state.clipboard = Some(clipboard);
+      }
+      None => state
+        .clipboard_text
+        .set_content(format!(" no {} to copy", labels.join(", "))),
+    }
+  }
+}
+
+fn property_label(property: &str) -> &str {
+  match property {
+    PROPERTY_TOTP_URL => "totp",
+    property => property,
+  }
+}
+
+/// Clear the status line once the clipboard is done, or destroy the clipboard once its timeout is reached
+fn update_clipboard(state: &mut ListUIState) {
+  let now = Utc::now();
+
+  match &state.clipboard {
+    Some(clipboard) if clipboard.is_done().unwrap_or(true) => {
+      state.clipboard_text.set_content("");
+      state.clipboard = None;
+    }
+    Some(clipboard) if state.clipboard_clear_at.iter().any(|clear_at| *clear_at <= now) => {
+      clipboard.destroy().ok_or_exit("Clear clipboard");
+      state.clipboard_text.set_content(" clipboard cleared");
+      state.clipboard = None;
     }
+    _ => (),
   }
 }
 
+fn change_secret_type(s: &mut Cursive) {
+  let maybe_secret = {
+    let secret_view = s.find_name::<SecretView>("secret_view").unwrap();
+    secret_view.current_secret()
+  };
+
+  if let Some(secret) = maybe_secret {
+    let secret_id = secret.id.clone();
+    let selected = SECRET_TYPES
+      .iter()
+      .position(|secret_type| *secret_type == secret.secret_type)
+      .unwrap_or_default();
+
+    s.add_layer(
+      Dialog::around(
+        SelectView::new()
+          .with_all(
+            SECRET_TYPES
+              .iter()
+              .map(|secret_type| (secret_type.to_string(), *secret_type)),
+          )
+          .selected(selected)
+          .on_submit(move |s: &mut Cursive, secret_type: &SecretType| {
+            do_change_secret_type(s, &secret_id, *secret_type)
+          }),
+      )
+      .title(format!("Change type of {}", secret.current.name))
+      .dismiss_button("Abort"),
+    );
+  }
+}
+
+fn do_change_secret_type(s: &mut Cursive, secret_id: &str, secret_type: SecretType) {
+  s.pop_layer();
+  {
+    let state = s.user_data::<ListUIState>().unwrap();
+    state
+      .secrets_store
+      .change_type(secret_id, secret_type)
+      .ok_or_exit("Change type");
+  }
+  let mut secret_view = s.find_name::<SecretView>("secret_view").unwrap();
+  secret_view.show_secret(secret_id);
+}
+
 fn update_status(s: &mut Cursive) {
   let next_status = {
     let state = s.user_data::<ListUIState>().unwrap();
     let now = Utc::now();
+    update_clipboard(state);
     if state.last_update.is_none() || (now - state.last_update.unwrap()).num_milliseconds() > 400 {
       state.service.check_autolock();
       state.last_update.replace(now);
@@ -233,22 +672,40 @@ fn status_text(status: &Status) -> String {
   }
 }
 
-fn create_list_view(state: &ListUIState) -> ResizedView<LinearLayout> {
+fn create_list_view(state: &mut ListUIState) -> ResizedView<LinearLayout> {
   let mut entry_select = SelectView::new();
-  let mut list = state.secrets_store.list(&state.filter).ok_or_exit("List entries");
-  let initial_selected = list.entries.first().map(|e| e.entry.id.clone());
-  entry_select.add_all(list.entries.drain(..).map(entry_list_item));
+  let list = list_entries(state);
+  let items = list_items(&list, &state.collapsed_tags);
+  let initial_entry = first_entry(&items);
+  update_preview(state, initial_entry.as_ref());
+  let initial_selected = initial_entry.map(|entry_match| entry_match.entry.id.clone());
+  entry_select.add_all(items);
   entry_select.set_on_select(update_selection);
+  entry_select.set_on_submit(toggle_folder);
   LinearLayout::horizontal()
     .child(entry_select.with_name("entry_list").scrollable())
     .child(
-      SecretView::new(
-        state.service.clone(),
-        state.store_name.clone(),
-        state.secrets_store.clone(),
-        initial_selected,
+      OnEventView::new(
+        SecretView::new(
+          state.service.clone(),
+          state.store_name.clone(),
+          state.secrets_store.clone(),
+          initial_selected,
+        )
+        .with_name("secret_view"),
+      )
+      .on_event(
+        state.tui_config.copy_username_key,
+        secret_to_clipboard(&[PROPERTY_USERNAME]),
+      )
+      .on_event(
+        state.tui_config.copy_password_key,
+        secret_to_clipboard(&[PROPERTY_PASSWORD]),
       )
-      .with_name("secret_view"),
+      .on_event(
+        state.tui_config.copy_totp_key,
+        secret_to_clipboard(&[PROPERTY_TOTP_URL]),
+      ),
     )
     .full_screen()
 }
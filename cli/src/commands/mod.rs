@@ -1,13 +1,30 @@
 mod add_identity;
+mod attach;
+mod attachments;
+mod audit;
+mod clone;
 mod completions;
+mod copy;
+mod derive;
+mod diagnose;
 mod export;
+mod gc;
 mod generate;
 mod import;
 mod init;
 mod list_identities;
 mod list_secrets;
 mod lock;
+mod panic;
+mod reindex;
+mod retag;
+mod retype;
+mod revoke;
+mod rotate_cipher;
+mod share;
+mod show;
 mod status;
+mod store;
 pub mod tui;
 mod unlock;
 
@@ -27,6 +44,8 @@ use rand::{distributions, thread_rng, Rng};
 use t_rust_less_lib::service::config_file;
 use t_rust_less_lib::service::TrustlessService;
 
+use crate::cli::OutputFormat;
+
 fn generate_id(length: usize) -> String {
   let rng = thread_rng();
 
@@ -64,8 +83,12 @@ impl IdentitiesCommand {
 pub enum MainCommand {
   #[clap(about = "Initialize configuration and store (if necessary)")]
   Init(init::InitCommand),
+  #[clap(about = "Create a new local store from an existing remote store")]
+  Clone(clone::CloneCommand),
   #[clap(about = "Lock the store")]
   Lock(lock::LockCommand),
+  #[clap(about = "Lock all stores and clear the clipboard immediately")]
+  Panic(panic::PanicCommand),
   #[clap(about = "Unlock the store")]
   Unlock(unlock::UnlockCommand),
   #[clap(about = "Import secrets entries")]
@@ -76,18 +99,57 @@ pub enum MainCommand {
   Status(status::StatusCommand),
   #[clap(about = "List secrets", alias = "ls")]
   List(list_secrets::ListSecretsCommand),
+  #[clap(about = "Show the fields of a secret (or reveal a single property)")]
+  Show(show::ShowCommand),
+  #[clap(about = "Copy properties of a secret to the clipboard (one after the other on each paste)")]
+  Copy(copy::CopyCommand),
   #[clap(about = "Generate password")]
   Generate(generate::GenerateCommand),
+  #[clap(about = "Derive the password of a site from the unlocked identity (always the same, nothing is stored)")]
+  Derive(derive::DeriveCommand),
+  #[clap(about = "Attach a file to a secret")]
+  Attach(attach::AttachCommand),
+  #[clap(about = "List or extract attachments of a secret", alias = "attachment")]
+  Attachments(attachments::AttachmentsCommand),
+  #[clap(about = "Audit passwords (weak, old, reused or breached) or secrets for upcoming expiry")]
+  Audit(audit::AuditCommand),
+  #[clap(about = "Tag secrets according to the url rules of the store")]
+  Retag(retag::RetagCommand),
+  #[clap(about = "Change the type of a secret")]
+  Retype(retype::RetypeCommand),
+  #[clap(about = "Share a secret with additional identities")]
+  Share(share::ShareCommand),
+  #[clap(about = "Remove an identity from the current versions of all secrets shared with it")]
+  Revoke(revoke::RevokeCommand),
+  #[clap(about = "Update the index of the store (or rebuild it with --force)")]
+  Reindex(reindex::ReindexCommand),
+  #[clap(about = "Re-encrypt all secrets with a single cipher (previous blocks are kept)")]
+  RotateCipher(rotate_cipher::RotateCipherCommand),
+  #[clap(about = "Remove blocks that are no longer referenced and previous versions (unless --keep-history)")]
+  Gc(gc::GcCommand),
   #[clap(about = "Control identities of a store", alias = "ids")]
   Identities(IdentitiesCommand),
   #[clap(about = "Generate shell completions")]
   Completions(completions::CompletionCommand),
+  #[clap(about = "Print a diagnostic dump of the store (without any secret content)")]
+  Diagnose(diagnose::DiagnoseCommand),
+  #[clap(about = "Block store utilities", alias = "storage")]
+  Store(store::StoreCommand),
 }
 
 impl MainCommand {
-  pub fn run(self, service: Arc<dyn TrustlessService>, maybe_store_name: Option<String>) -> Result<()> {
-    if let MainCommand::Init(cmd) = self {
-      return cmd.run(service, maybe_store_name);
+  pub fn run(
+    self,
+    service: Arc<dyn TrustlessService>,
+    maybe_store_name: Option<String>,
+    output: OutputFormat,
+  ) -> Result<()> {
+    match self {
+      MainCommand::Init(cmd) => return cmd.run(service, maybe_store_name),
+      MainCommand::Clone(cmd) => return cmd.run(service),
+      MainCommand::Panic(cmd) => return cmd.run(service),
+      MainCommand::Store(cmd) => return cmd.run(service, maybe_store_name),
+      _ => (),
     }
 
     let store_name = match maybe_store_name {
@@ -103,10 +165,24 @@ impl MainCommand {
       MainCommand::Unlock(cmd) => cmd.run(service, store_name),
       MainCommand::Import(cmd) => cmd.run(service, store_name),
       MainCommand::Export(cmd) => cmd.run(service, store_name),
-      MainCommand::Status(cmd) => cmd.run(service, store_name),
-      MainCommand::List(cmd) => cmd.run(service, store_name),
+      MainCommand::Status(cmd) => cmd.run(service, store_name, output),
+      MainCommand::List(cmd) => cmd.run(service, store_name, output),
+      MainCommand::Show(cmd) => cmd.run(service, store_name),
+      MainCommand::Copy(cmd) => cmd.run(service, store_name),
       MainCommand::Generate(cmd) => cmd.run(service),
+      MainCommand::Derive(cmd) => cmd.run(service, store_name),
+      MainCommand::Attach(cmd) => cmd.run(service, store_name),
+      MainCommand::Attachments(cmd) => cmd.run(service, store_name),
+      MainCommand::Audit(cmd) => cmd.run(service, store_name),
+      MainCommand::Retag(cmd) => cmd.run(service, store_name),
+      MainCommand::Retype(cmd) => cmd.run(service, store_name),
+      MainCommand::Share(cmd) => cmd.run(service, store_name),
+      MainCommand::Revoke(cmd) => cmd.run(service, store_name),
+      MainCommand::Reindex(cmd) => cmd.run(service, store_name),
+      MainCommand::RotateCipher(cmd) => cmd.run(service, store_name),
+      MainCommand::Gc(cmd) => cmd.run(service, store_name),
       MainCommand::Identities(cmd) => cmd.run(service, store_name),
+      MainCommand::Diagnose(cmd) => cmd.run(service, store_name),
       MainCommand::Completions(cmd) => cmd.run(),
       _ => Ok(()),
     }
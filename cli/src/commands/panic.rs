@@ -0,0 +1,28 @@
+use anyhow::{bail, Context, Result};
+use clap::Args;
+use std::sync::Arc;
+use t_rust_less_lib::service::TrustlessService;
+
+#[derive(Debug, Args)]
+pub struct PanicCommand {
+  #[clap(long, help = "Also wipe the local index blocks (rebuilt on next unlock)")]
+  pub wipe_local: bool,
+}
+
+impl PanicCommand {
+  pub fn run(self, service: Arc<dyn TrustlessService>) -> Result<()> {
+    let report = service.panic_lock(self.wipe_local).with_context(|| "Panic lock")?;
+
+    println!("Locked: {}", report.locked_stores.join(", "));
+    if self.wipe_local {
+      println!("Wiped indexes: {}", report.wiped_indexes.join(", "));
+    }
+    println!("Clipboard cleared: {}", report.clipboard_cleared);
+
+    if !report.sessions_cleared {
+      bail!("Not all stores could be locked");
+    }
+
+    Ok(())
+  }
+}
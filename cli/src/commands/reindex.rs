@@ -0,0 +1,37 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use std::sync::Arc;
+use t_rust_less_lib::service::TrustlessService;
+
+use super::{tui::create_tui, unlock_store};
+
+#[derive(Debug, Args)]
+pub struct ReindexCommand {
+  #[clap(long, help = "Discard the current index and rebuild it from all secrets")]
+  pub force: bool,
+}
+
+impl ReindexCommand {
+  pub fn run(self, service: Arc<dyn TrustlessService>, store_name: String) -> Result<()> {
+    let secrets_store = service
+      .open_store(&store_name)
+      .with_context(|| format!("Failed opening store {}: ", store_name))?;
+    let status = secrets_store.status().with_context(|| "Get status")?;
+
+    if status.locked {
+      let mut siv = create_tui();
+      unlock_store(&mut siv, &secrets_store, &store_name)?;
+      siv.quit();
+    }
+
+    if self.force {
+      secrets_store.rebuild_index().with_context(|| "Index rebuild")?;
+      println!("Rebuilt index of {}", store_name);
+    } else {
+      secrets_store.update_index().with_context(|| "Index update")?;
+      println!("Updated index of {}", store_name);
+    }
+
+    Ok(())
+  }
+}
@@ -0,0 +1,97 @@
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use clap::Args;
+use std::sync::Arc;
+use t_rust_less_lib::api::{missing_tags, SecretListFilter, TagMatch};
+use t_rust_less_lib::service::TrustlessService;
+
+use super::{tui::create_tui, unlock_store};
+
+#[derive(Debug, Args)]
+pub struct RetagCommand {
+  #[clap(long, help = "Add missing tags (as new versions of the secrets)")]
+  pub apply: bool,
+  #[clap(long, help = "Only report which tags would be added", conflicts_with = "apply")]
+  pub dry_run: bool,
+}
+
+impl RetagCommand {
+  pub fn run(self, service: Arc<dyn TrustlessService>, store_name: String) -> Result<()> {
+    if !self.apply && !self.dry_run {
+      bail!("Either --apply or --dry-run is required");
+    }
+    let rules = match service
+      .list_stores()?
+      .into_iter()
+      .find(|store_config| store_config.name == store_name)
+    {
+      Some(store_config) => store_config.url_tag_rules.clone(),
+      None => bail!("Store {} not configured", store_name),
+    };
+    if rules.is_empty() {
+      bail!("No url_tag_rules configured for store {}", store_name);
+    }
+
+    let secrets_store = service
+      .open_store(&store_name)
+      .with_context(|| format!("Failed opening store {}: ", store_name))?;
+    let status = secrets_store.status().with_context(|| "Get status")?;
+
+    if status.locked {
+      let mut siv = create_tui();
+      unlock_store(&mut siv, &secrets_store, &store_name)?;
+      siv.quit();
+    }
+
+    let filter = SecretListFilter {
+      url: None,
+      tags: vec![],
+      tag_match: TagMatch::All,
+      secret_type: None,
+      name: None,
+      deleted: false,
+      expiring_before: None,
+      modified_after: None,
+      modified_before: None,
+      group_by_tag: false,
+      content: None,
+      offset: 0,
+      limit: None,
+    };
+    let list = secrets_store.list(&filter).with_context(|| "List entries")?;
+    let mut changed = 0;
+
+    for entry_match in &list.entries {
+      let entry = &entry_match.entry;
+      let missing = missing_tags(&rules, &entry.urls, &entry.tags);
+      if missing.is_empty() {
+        continue;
+      }
+      changed += 1;
+      println!("{} ({}): +{}", entry.name, entry.id, missing.join(", +"));
+
+      if self.apply {
+        let secret = secrets_store
+          .get(&entry.id)
+          .with_context(|| format!("Get secret {}", entry.id))?;
+        let mut secret_version = secret.current.clone();
+
+        secret_version.timestamp = Utc::now().into();
+        secret_version.parent_block_id = Some(secret.current_block_id.clone());
+        secret_version.tags.extend(missing);
+
+        secrets_store
+          .add(secret_version)
+          .with_context(|| format!("Update secret {}", entry.id))?;
+      }
+    }
+
+    match (changed, self.apply) {
+      (0, _) => println!("All secrets are tagged according to the rules"),
+      (changed, true) => println!("Updated {} secrets", changed),
+      (changed, false) => println!("{} secrets would be updated", changed),
+    }
+
+    Ok(())
+  }
+}
@@ -0,0 +1,38 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use std::sync::Arc;
+use t_rust_less_lib::api::SecretType;
+use t_rust_less_lib::service::TrustlessService;
+
+use super::{tui::create_tui, unlock_store};
+
+#[derive(Debug, Args)]
+pub struct RetypeCommand {
+  #[clap(help = "Id of the secret to change")]
+  pub secret_id: String,
+  #[clap(help = "New type of the secret (login, note, licence, wlan, password, other)")]
+  pub secret_type: SecretType,
+}
+
+impl RetypeCommand {
+  pub fn run(self, service: Arc<dyn TrustlessService>, store_name: String) -> Result<()> {
+    let secrets_store = service
+      .open_store(&store_name)
+      .with_context(|| format!("Failed opening store {}: ", store_name))?;
+    let status = secrets_store.status().with_context(|| "Get status")?;
+
+    if status.locked {
+      let mut siv = create_tui();
+      unlock_store(&mut siv, &secrets_store, &store_name)?;
+      siv.quit();
+    }
+
+    secrets_store
+      .change_type(&self.secret_id, self.secret_type)
+      .with_context(|| format!("Change type of {}", self.secret_id))?;
+
+    println!("Changed type of {} to {}", self.secret_id, self.secret_type);
+
+    Ok(())
+  }
+}
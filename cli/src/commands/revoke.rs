@@ -0,0 +1,38 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use std::sync::Arc;
+use t_rust_less_lib::service::TrustlessService;
+
+use super::{tui::create_tui, unlock_store};
+
+#[derive(Debug, Args)]
+pub struct RevokeCommand {
+  #[clap(help = "Id of the identity that should no longer have access to the current versions")]
+  pub identity: String,
+}
+
+impl RevokeCommand {
+  pub fn run(self, service: Arc<dyn TrustlessService>, store_name: String) -> Result<()> {
+    let secrets_store = service
+      .open_store(&store_name)
+      .with_context(|| format!("Failed opening store {}: ", store_name))?;
+    let status = secrets_store.status().with_context(|| "Get status")?;
+
+    if status.locked {
+      let mut siv = create_tui();
+      unlock_store(&mut siv, &secrets_store, &store_name)?;
+      siv.quit();
+    }
+
+    let revoked = secrets_store
+      .revoke_recipient(&self.identity)
+      .with_context(|| format!("Revoke {}", self.identity))?;
+
+    for secret_id in &revoked {
+      println!("Revoked {} from {}", self.identity, secret_id);
+    }
+    println!("{} secrets affected", revoked.len());
+
+    Ok(())
+  }
+}
@@ -0,0 +1,59 @@
+use anyhow::{Context, Result};
+use clap::{Args, ValueEnum};
+use std::sync::Arc;
+use t_rust_less_lib::secrets_store_capnp::KeyType;
+use t_rust_less_lib::service::TrustlessService;
+
+use super::{tui::create_tui, unlock_store};
+
+/// Ciphers that may be used as target of a rotation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum TargetCipher {
+  /// X25519 key agreement with ChaCha20-Poly1305
+  X25519Chacha20Poly1305,
+  /// RSA with AES-GCM
+  RsaAesGcm,
+}
+
+impl From<TargetCipher> for KeyType {
+  fn from(target: TargetCipher) -> Self {
+    match target {
+      TargetCipher::X25519Chacha20Poly1305 => KeyType::Ed25519Chacha20Poly1305,
+      TargetCipher::RsaAesGcm => KeyType::RsaAesGcm,
+    }
+  }
+}
+
+#[derive(Debug, Args)]
+pub struct RotateCipherCommand {
+  #[clap(
+    long,
+    value_enum,
+    default_value = "x25519-chacha20-poly1305",
+    help = "Cipher all secrets should be encrypted with"
+  )]
+  pub target: TargetCipher,
+}
+
+impl RotateCipherCommand {
+  pub fn run(self, service: Arc<dyn TrustlessService>, store_name: String) -> Result<()> {
+    let secrets_store = service
+      .open_store(&store_name)
+      .with_context(|| format!("Failed opening store {}: ", store_name))?;
+    let status = secrets_store.status().with_context(|| "Get status")?;
+
+    if status.locked {
+      let mut siv = create_tui();
+      unlock_store(&mut siv, &secrets_store, &store_name)?;
+      siv.quit();
+    }
+
+    let migrated = secrets_store
+      .rotate_cipher(self.target.into())
+      .with_context(|| "Rotate cipher")?;
+
+    println!("Re-encrypted {} secret versions of {}", migrated, store_name);
+
+    Ok(())
+  }
+}
@@ -0,0 +1,37 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use std::sync::Arc;
+use t_rust_less_lib::service::TrustlessService;
+
+use super::{tui::create_tui, unlock_store};
+
+#[derive(Debug, Args)]
+pub struct ShareCommand {
+  #[clap(help = "Id of the secret to share")]
+  pub secret_id: String,
+  #[clap(required = true, help = "Ids of the identities to share the secret with")]
+  pub identities: Vec<String>,
+}
+
+impl ShareCommand {
+  pub fn run(self, service: Arc<dyn TrustlessService>, store_name: String) -> Result<()> {
+    let secrets_store = service
+      .open_store(&store_name)
+      .with_context(|| format!("Failed opening store {}: ", store_name))?;
+    let status = secrets_store.status().with_context(|| "Get status")?;
+
+    if status.locked {
+      let mut siv = create_tui();
+      unlock_store(&mut siv, &secrets_store, &store_name)?;
+      siv.quit();
+    }
+
+    secrets_store
+      .share_with(&self.secret_id, &self.identities)
+      .with_context(|| format!("Share {}", self.secret_id))?;
+
+    println!("Shared {} with {}", self.secret_id, self.identities.join(", "));
+
+    Ok(())
+  }
+}
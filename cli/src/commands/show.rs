@@ -0,0 +1,89 @@
+use crate::view::breach_warning;
+use anyhow::{bail, Context, Result};
+use clap::Args;
+use std::sync::Arc;
+use t_rust_less_lib::api::{SecretVersion, PROPERTY_PASSWORD, PROPERTY_TOTP_URL};
+use t_rust_less_lib::service::TrustlessService;
+use zeroize::Zeroizing;
+
+const MASK: &str = "********";
+
+#[derive(Debug, Args)]
+pub struct ShowCommand {
+  #[clap(help = "Id of the secret to show")]
+  pub secret_id: String,
+  #[clap(long, default_value = PROPERTY_PASSWORD, help = "Property to reveal")]
+  pub property: String,
+  #[clap(long, help = "Only print the value of the property (e.g. to pipe it somewhere)")]
+  pub reveal: bool,
+}
+
+impl ShowCommand {
+  pub fn run(self, service: Arc<dyn TrustlessService>, store_name: String) -> Result<()> {
+    let secrets_store = service
+      .open_store(&store_name)
+      .with_context(|| format!("Failed opening store {}: ", store_name))?;
+    let status = secrets_store.status().with_context(|| "Get status")?;
+
+    if status.locked {
+      bail!("Store {} is locked, unlock it first", store_name);
+    }
+
+    let secret = secrets_store
+      .get(&self.secret_id)
+      .with_context(|| format!("Get secret {}", self.secret_id))?;
+    let breach_count = secrets_store
+      .check_breached(&self.secret_id)
+      .with_context(|| format!("Check secret {} for breaches", self.secret_id))?;
+
+    if self.reveal {
+      let value = match secret.current.properties.get(&self.property) {
+        Some(value) => Zeroizing::new(value.clone()),
+        None => bail!("Secret {} has no {}", self.secret_id, self.property),
+      };
+      // Valid otpauth urls are replaced by the current code
+      let otp = if self.property == PROPERTY_TOTP_URL {
+        secrets_store.get_otp(&self.secret_id).ok().flatten()
+      } else {
+        None
+      };
+      match otp {
+        Some(otp) => println!("{}", otp.code),
+        None => println!("{}", value.as_str()),
+      }
+      // Only a warning on stderr, the output itself is most likely piped somewhere
+      if let Some(count) = breach_count {
+        eprintln!("Warning: {}", breach_warning(count));
+      }
+    } else {
+      print_version(&secret.current);
+      if let Some(count) = breach_count {
+        println!("Breached  : {}", breach_warning(count));
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// Print all fields of a secret version, the values of passwords (and otp urls) are masked.
+fn print_version(version: &SecretVersion) {
+  println!("Id        : {}", version.secret_id);
+  println!("Name      : {}", version.name);
+  println!("Type      : {}", version.secret_type);
+  println!("Tags      : {}", version.tags.join(", "));
+  println!("Urls      : {}", version.urls.join(", "));
+  if let Some(expires_at) = &version.expires_at {
+    println!("Expires at: {}", expires_at.format("%Y-%m-%d %H:%M"));
+  }
+  for (name, value) in version.properties.iter() {
+    if name == PROPERTY_TOTP_URL || version.secret_type.password_properties().contains(&name) {
+      println!("{:<10}: {}", name, MASK);
+    } else {
+      println!("{:<10}: {}", name, value);
+    }
+  }
+  for attachment in &version.attachments {
+    println!("Attachment: {} ({})", attachment.name(), attachment.mime_type());
+  }
+}
@@ -1,20 +1,30 @@
 use anyhow::{Context, Result};
 use atty::Stream;
+use chrono::Utc;
 use clap::Args;
 use crossterm_style::{style, Color};
 use std::sync::Arc;
 use t_rust_less_lib::service::TrustlessService;
 
+use crate::cli::OutputFormat;
+
 #[derive(Debug, Args)]
 pub struct StatusCommand {}
 
 impl StatusCommand {
-  pub fn run(self, service: Arc<dyn TrustlessService>, store_name: String) -> Result<()> {
+  pub fn run(self, service: Arc<dyn TrustlessService>, store_name: String, output: OutputFormat) -> Result<()> {
     let secrets_store = service
       .open_store(&store_name)
       .with_context(|| format!("Failed opening store {}: ", store_name))?;
     let status = secrets_store.status().with_context(|| "Get status")?;
 
+    if output == OutputFormat::Json {
+      println!("{}", serde_json::to_string(&status)?);
+      return Ok(());
+    }
+
+    let capabilities = service.capabilities().with_context(|| "Get capabilities")?;
+
     if atty::is(Stream::Stdout) {
       println!();
       println!("Client version: {}", style(env!("CARGO_PKG_VERSION")).with(Color::Cyan));
@@ -26,10 +36,43 @@ impl StatusCommand {
         } else {
           style("Unlocked").with(Color::Red)
         }
-      )
+      );
+      if let Some(unlock_blocked_until) = status.unlock_blocked_until {
+        println!(
+          "Unlock        : {}",
+          style(format!(
+            "Blocked, try again in {}s",
+            (unlock_blocked_until - Utc::now()).num_seconds().max(1)
+          ))
+          .with(Color::Red)
+        );
+      }
+      println!(
+        "Memory locking: {}",
+        if status.memory_locking {
+          style("Yes").with(Color::Green)
+        } else {
+          style("No (secrets might be swapped to disk)").with(Color::Yellow)
+        }
+      );
+      println!(
+        "AES hardware  : {}",
+        if capabilities.aes_hardware_acceleration {
+          style("Yes").with(Color::Green)
+        } else {
+          style("No").with(Color::Yellow)
+        }
+      );
+      println!(
+        "Preferred     : {}",
+        style(capabilities.preferred_cipher.clone()).with(Color::Cyan)
+      );
     } else {
       println!("Client version: {}", env!("CARGO_PKG_VERSION"));
       println!("Store version : {}", status.version);
+      println!("Memory locking: {}", status.memory_locking);
+      println!("AES hardware  : {}", capabilities.aes_hardware_acceleration);
+      println!("Preferred     : {}", capabilities.preferred_cipher);
     }
 
     Ok(())
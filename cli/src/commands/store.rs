@@ -0,0 +1,172 @@
+use anyhow::{anyhow, Context, Result};
+use clap::{Args, Subcommand};
+use std::sync::Arc;
+use t_rust_less_lib::block_store::open_block_store;
+use t_rust_less_lib::service::TrustlessService;
+
+use super::generate_id;
+
+#[derive(Debug, Args)]
+pub struct StoreTestCommand {
+  #[clap(help = "Url of the block store to test (e.g. a remote url)")]
+  pub url: String,
+}
+
+impl StoreTestCommand {
+  pub fn run(self) -> Result<()> {
+    // Secrets store urls (like multilane+file://...) are accepted as well
+    let block_store_url = match self.url.find('+') {
+      Some(idx) => &self.url[idx + 1..],
+      None => &self.url,
+    };
+    // Use a throwaway node id so that nothing in the store might be mistaken as ours
+    let node_id = generate_id(64);
+
+    let block_store =
+      open_block_store(block_store_url, &node_id).with_context(|| format!("Failed opening {}", block_store_url))?;
+    // Read-only operations only: Listing rings and change logs has no effect on the store's data
+    let ring_ids = block_store
+      .list_ring_ids()
+      .with_context(|| format!("Failed listing rings of {}", block_store_url))?;
+    let change_logs = block_store
+      .change_logs()
+      .with_context(|| format!("Failed reading change logs of {}", block_store_url))?;
+
+    println!(
+      "{} is accessible: {} rings, {} change logs",
+      block_store_url,
+      ring_ids.len(),
+      change_logs.len()
+    );
+
+    Ok(())
+  }
+}
+
+#[derive(Debug, Args)]
+pub struct StoreStatsCommand {
+  #[clap(help = "Url of the block store to analyze (default: url of the current store)")]
+  pub url: Option<String>,
+}
+
+/// Url of the block store to analyze, either explicit or the url of the current store.
+fn resolve_block_store_url(
+  maybe_url: Option<String>,
+  service: Arc<dyn TrustlessService>,
+  maybe_store_name: Option<String>,
+) -> Result<String> {
+  let url = match maybe_url {
+    Some(url) => url,
+    None => {
+      let store_name = maybe_store_name.ok_or_else(|| anyhow!("No store configured (use an explicit url)"))?;
+      service
+        .list_stores()?
+        .into_iter()
+        .find(|store_config| store_config.name == store_name)
+        .map(|store_config| store_config.store_url.clone())
+        .ok_or_else(|| anyhow!("Store {} not found", store_name))?
+    }
+  };
+
+  Ok(match url.find('+') {
+    Some(idx) => url[idx + 1..].to_string(),
+    None => url,
+  })
+}
+
+impl StoreStatsCommand {
+  pub fn run(self, service: Arc<dyn TrustlessService>, maybe_store_name: Option<String>) -> Result<()> {
+    let block_store_url = resolve_block_store_url(self.url, service, maybe_store_name)?;
+    let block_store = open_block_store(&block_store_url, &generate_id(64))
+      .with_context(|| format!("Failed opening {}", block_store_url))?;
+    let stats = block_store
+      .storage_stats()
+      .with_context(|| format!("Failed analyzing {}", block_store_url))?;
+
+    println!("Blocks      : {} ({} bytes)", stats.block_count, stats.total_bytes);
+    if stats.missing_blocks > 0 {
+      println!("Missing     : {} blocks", stats.missing_blocks);
+    }
+    println!(
+      "Reclaimable : {} blocks ({} bytes)",
+      stats.reclaimable_blocks, stats.reclaimable_bytes
+    );
+    println!("Largest blocks:");
+    for (block_id, size) in &stats.largest_blocks {
+      println!("  {} {}", block_id, size);
+    }
+    println!("Rings (retained versions):");
+    for (ring_id, versions) in &stats.ring_versions {
+      println!("  {} {}", ring_id, versions);
+    }
+    println!("Change logs (changes per node):");
+    for (node, changes) in &stats.change_logs {
+      println!("  {} {}", node, changes);
+    }
+
+    Ok(())
+  }
+}
+
+#[derive(Debug, Args)]
+pub struct StoreFsckCommand {
+  #[clap(help = "Url of the block store to verify (default: url of the current store)")]
+  pub url: Option<String>,
+}
+
+impl StoreFsckCommand {
+  pub fn run(self, service: Arc<dyn TrustlessService>, maybe_store_name: Option<String>) -> Result<()> {
+    let block_store_url = resolve_block_store_url(self.url, service, maybe_store_name)?;
+    let block_store = open_block_store(&block_store_url, &generate_id(64))
+      .with_context(|| format!("Failed opening {}", block_store_url))?;
+    let report = block_store
+      .verify()
+      .with_context(|| format!("Failed verifying {}", block_store_url))?;
+
+    println!("Checked     : {} blocks", report.checked_blocks);
+    println!("Mismatched  : {} blocks", report.mismatched_blocks.len());
+    for block_id in &report.mismatched_blocks {
+      println!("  {}", block_id);
+    }
+    println!("Missing     : {} blocks", report.missing_blocks.len());
+    for block_id in &report.missing_blocks {
+      println!("  {}", block_id);
+    }
+    println!("Orphans     : {} blocks", report.orphan_blocks.len());
+    for block_id in &report.orphan_blocks {
+      println!("  {}", block_id);
+    }
+
+    if !report.is_ok() {
+      return Err(anyhow!("{} is corrupted", block_store_url));
+    }
+
+    Ok(())
+  }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum StoreSubCommand {
+  #[clap(about = "Test if a block store url is accessible (without modifying it)")]
+  Test(StoreTestCommand),
+  #[clap(about = "Show storage statistics of a block store (without decrypting anything)")]
+  Stats(StoreStatsCommand),
+  #[clap(about = "Verify the integrity of a block store (without modifying it)")]
+  Fsck(StoreFsckCommand),
+}
+
+#[derive(Debug, Args)]
+pub struct StoreCommand {
+  #[clap(subcommand)]
+  subcommand: StoreSubCommand,
+}
+
+impl StoreCommand {
+  pub fn run(self, service: Arc<dyn TrustlessService>, maybe_store_name: Option<String>) -> Result<()> {
+    match self.subcommand {
+      StoreSubCommand::Test(cmd) => cmd.run(),
+      StoreSubCommand::Stats(cmd) => cmd.run(service, maybe_store_name),
+      StoreSubCommand::Fsck(cmd) => cmd.run(service, maybe_store_name),
+    }
+  }
+}
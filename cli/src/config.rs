@@ -1,5 +1,10 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
 use std::path::PathBuf;
 use std::time::Duration;
+use t_rust_less_lib::service::config_file;
 
 pub fn default_store_dir(store_name: &str) -> PathBuf {
   let home_dir = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
@@ -10,3 +15,49 @@ pub fn default_store_dir(store_name: &str) -> PathBuf {
 pub fn default_autolock_timeout() -> Duration {
   Duration::from_secs(300)
 }
+
+pub fn default_sync_interval() -> Duration {
+  Duration::from_secs(300)
+}
+
+/// Settings of the terminal ui, these are purely client side (i.e. not shared with the daemon)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TuiConfig {
+  pub copy_username_key: char,
+  pub copy_password_key: char,
+  pub copy_totp_key: char,
+  /// Clear the clipboard if nothing has been pasted after this many seconds (0: never)
+  pub clipboard_timeout_secs: u64,
+}
+
+impl Default for TuiConfig {
+  fn default() -> Self {
+    TuiConfig {
+      copy_username_key: 'u',
+      copy_password_key: 'p',
+      copy_totp_key: 't',
+      clipboard_timeout_secs: 30,
+    }
+  }
+}
+
+pub fn tui_config_file() -> PathBuf {
+  config_file().with_file_name("tui.toml")
+}
+
+pub fn read_tui_config() -> TuiConfig {
+  let tui_config_file = tui_config_file();
+
+  match fs::read_to_string(&tui_config_file) {
+    Ok(content) => toml::from_str(&content).unwrap_or_else(|error| {
+      warn!("Invalid {}: {}", tui_config_file.to_string_lossy(), error);
+      Default::default()
+    }),
+    Err(ref error) if error.kind() == io::ErrorKind::NotFound => Default::default(),
+    Err(error) => {
+      warn!("Unable to read {}: {}", tui_config_file.to_string_lossy(), error);
+      Default::default()
+    }
+  }
+}
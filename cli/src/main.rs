@@ -29,5 +29,5 @@ fn main() -> Result<()> {
     .store
     .or_else(|| service.get_default_store().ok_or_exit("Get default store"));
 
-  args.sub_command.run(service, maybe_store_name)
+  args.sub_command.run(service, maybe_store_name, args.output)
 }
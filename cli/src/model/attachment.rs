@@ -0,0 +1,102 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use t_rust_less_lib::api::SecretAttachment;
+use t_rust_less_lib::secrets_store::{SecretStoreResult, SecretsStore};
+use zeroize::Zeroizing;
+
+/// Read a file to be attached, files larger than `max_size` are rejected.
+pub fn read_attachment(path: &Path, max_size: usize) -> Result<Zeroizing<Vec<u8>>> {
+  let file = File::open(path).with_context(|| format!("Failed opening {}", path.to_string_lossy()))?;
+  let size = file.metadata()?.len();
+
+  if size > max_size as u64 {
+    bail!(
+      "{} is too large for an attachment ({} bytes, max {} bytes)",
+      path.to_string_lossy(),
+      size,
+      max_size
+    );
+  }
+
+  let mut content = Zeroizing::new(Vec::with_capacity(size as usize));
+  // Guard against files growing while being read
+  file
+    .take(max_size as u64 + 1)
+    .read_to_end(&mut content)
+    .with_context(|| format!("Failed reading {}", path.to_string_lossy()))?;
+
+  if content.len() > max_size {
+    bail!("{} is too large for an attachment", path.to_string_lossy());
+  }
+
+  Ok(content)
+}
+
+/// File name for an attachment written to a directory, the extension is derived from the mime
+/// type if the name of the attachment does not have one.
+pub fn attachment_file_name(attachment: &SecretAttachment) -> String {
+  // Attachment names are not trusted to be a plain file name
+  let name = Path::new(attachment.name())
+    .file_name()
+    .map(|name| name.to_string_lossy().to_string())
+    .unwrap_or_else(|| "attachment".to_string());
+
+  match attachment.default_extension() {
+    Some(extension) if Path::new(&name).extension().is_none() => format!("{}.{}", name, extension),
+    _ => name,
+  }
+}
+
+/// Chunks of an attachment of the current version of a secret as they are read from the store,
+/// i.e. the attachment is never entirely in memory.
+pub struct AttachmentChunks<'a> {
+  secrets_store: &'a dyn SecretsStore,
+  secret_id: &'a str,
+  name: &'a str,
+  index: usize,
+}
+
+impl<'a> AttachmentChunks<'a> {
+  pub fn new(secrets_store: &'a dyn SecretsStore, secret_id: &'a str, name: &'a str) -> Self {
+    AttachmentChunks {
+      secrets_store,
+      secret_id,
+      name,
+      index: 0,
+    }
+  }
+}
+
+impl<'a> Iterator for AttachmentChunks<'a> {
+  type Item = SecretStoreResult<SecretAttachment>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let chunk = self
+      .secrets_store
+      .get_attachment_chunk(self.secret_id, self.name, self.index)
+      .transpose()?;
+    self.index += 1;
+    Some(chunk)
+  }
+}
+
+/// Write all `chunks` of an attachment to `writer`, returns the number of bytes written.
+pub fn write_chunks<W, I>(mut writer: W, chunks: I) -> Result<usize>
+where
+  W: Write,
+  I: IntoIterator<Item = SecretStoreResult<SecretAttachment>>,
+{
+  let mut written = 0;
+
+  for chunk in chunks {
+    let chunk = chunk?;
+    writer.write_all(chunk.content())?;
+    written += chunk.content().len();
+  }
+  writer.flush()?;
+
+  Ok(written)
+}
@@ -0,0 +1,114 @@
+use std::fs;
+use std::sync::Arc;
+
+use chrono::Utc;
+use spectral::prelude::*;
+use t_rust_less_lib::api::{
+  AttachmentStorage, EventData, EventHub, Identity, SecretAttachment, SecretProperties, SecretType, SecretVersion,
+  StoreConfig,
+};
+use t_rust_less_lib::memguard::SecretBytes;
+use t_rust_less_lib::secrets_store::open_secrets_store;
+
+use super::attachment::{attachment_file_name, read_attachment, write_chunks, AttachmentChunks};
+
+struct TestEventHub;
+
+impl EventHub for TestEventHub {
+  fn send(&self, _event: EventData) {}
+}
+
+fn small_png() -> Vec<u8> {
+  let mut content = b"\x89PNG\r\n\x1a\n".to_vec();
+  content.extend((0..=255u8).cycle().take(4000));
+  content
+}
+
+#[test]
+fn test_read_attachment() {
+  let dir = tempfile::tempdir().unwrap();
+  let path = dir.path().join("logo");
+  let content = small_png();
+  fs::write(&path, &content).unwrap();
+
+  let read = read_attachment(&path, 8192).unwrap();
+
+  assert_that(&read.as_slice()).is_equal_to(content.as_slice());
+  assert_that(&SecretAttachment::guess_mime_type("logo", &read)).is_equal_to("image/png");
+  assert_that(&read_attachment(&path, 1024)).is_err();
+  assert_that(&read_attachment(&dir.path().join("missing"), 8192)).is_err();
+}
+
+#[test]
+fn test_attachment_file_name() {
+  let attachment = SecretAttachment::new("../../logo", "image/png", vec![]);
+
+  assert_that(&attachment_file_name(&attachment)).is_equal_to("logo.png".to_string());
+
+  let attachment = SecretAttachment::new("notes.md", "text/plain", vec![]);
+
+  assert_that(&attachment_file_name(&attachment)).is_equal_to("notes.md".to_string());
+}
+
+#[test]
+#[cfg_attr(debug_assertions, ignore)]
+fn test_attach_and_extract() {
+  let dir = tempfile::tempdir().unwrap();
+  let path = dir.path().join("logo.png");
+  fs::write(&path, small_png()).unwrap();
+
+  let mut store_config = StoreConfig::new("test", "multilane+memory://", "node1");
+  store_config.attachment_storage = AttachmentStorage::ChunkedCompressed;
+  let (secrets_store, _) = open_secrets_store(&store_config, Arc::new(TestEventHub)).unwrap();
+  let identity = Identity {
+    id: "identity1".to_string(),
+    name: "Name1".to_string(),
+    email: "Email1".to_string(),
+    hidden: false,
+    hardware_factor: false,
+  };
+  secrets_store
+    .add_identity(identity, SecretBytes::from(b"Passphrase1".to_vec()))
+    .unwrap();
+  secrets_store
+    .unlock("identity1", SecretBytes::from(b"Passphrase1".to_vec()))
+    .unwrap();
+
+  let mut content = read_attachment(&path, 8192).unwrap();
+  let mime_type = SecretAttachment::guess_mime_type("logo.png", &content);
+  secrets_store
+    .add(SecretVersion {
+      secret_id: "secret1".to_string(),
+      secret_type: SecretType::Login,
+      timestamp: Utc::now().into(),
+      name: "Secret1".to_string(),
+      tags: vec![],
+      urls: vec![],
+      properties: SecretProperties::new(Default::default()),
+      attachments: vec![SecretAttachment::new(
+        "logo.png",
+        mime_type,
+        std::mem::take(&mut *content),
+      )],
+      deleted: false,
+      recipients: vec!["identity1".to_string()],
+      expires_at: None,
+      parent_block_id: None,
+      modified_by: None,
+    })
+    .unwrap();
+
+  let mut chunks = AttachmentChunks::new(secrets_store.as_ref(), "secret1", "logo.png");
+  let first = chunks.next().unwrap().unwrap();
+
+  assert_that(&attachment_file_name(&first)).is_equal_to("logo.png".to_string());
+
+  let mut extracted = vec![];
+  let written = write_chunks(&mut extracted, std::iter::once(Ok(first)).chain(chunks)).unwrap();
+
+  assert_that(&written).is_equal_to(small_png().len());
+  assert_that(&extracted).is_equal_to(small_png());
+  assert_that(&AttachmentChunks::new(secrets_store.as_ref(), "secret1", "unknown.png").next())
+    .is_some()
+    .is_err();
+}
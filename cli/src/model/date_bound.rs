@@ -0,0 +1,39 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+
+/// Parse a bound of a date range given on the command line.
+///
+/// Either an ISO-8601 timestamp (e.g. `2024-01-31T12:00:00Z`), a plain date (e.g. `2024-01-31`,
+/// which is the start of that day in UTC) or a relative expression like `12h`, `30d` or `2w`
+/// (i.e. that long before `now`).
+pub fn parse_date_bound_at(value: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+  let value = value.trim();
+
+  if let Ok(timestamp) = DateTime::parse_from_rfc3339(value) {
+    return Ok(timestamp.with_timezone(&Utc));
+  }
+  if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+    return Ok(date.and_hms_opt(0, 0, 0).unwrap().and_utc());
+  }
+
+  let unit_start = value.len() - value.chars().last().map(char::len_utf8).unwrap_or(0);
+  let amount = value[..unit_start].parse::<i64>().ok().filter(|amount| *amount >= 0);
+  let duration = match (amount, &value[unit_start..]) {
+    (Some(amount), "h") => Duration::try_hours(amount),
+    (Some(amount), "d") => Duration::try_days(amount),
+    (Some(amount), "w") => Duration::try_weeks(amount),
+    _ => None,
+  };
+
+  match duration.and_then(|duration| now.checked_sub_signed(duration)) {
+    Some(bound) => Ok(bound),
+    None => Err(format!(
+      "Invalid date '{}' (expected ISO-8601 like 2024-01-31 or relative like 30d)",
+      value
+    )),
+  }
+}
+
+/// Same as `parse_date_bound_at` relative to the current time (for use as clap value parser).
+pub fn parse_date_bound(value: &str) -> Result<DateTime<Utc>, String> {
+  parse_date_bound_at(value, Utc::now())
+}
@@ -0,0 +1,33 @@
+use chrono::{Duration, TimeZone, Utc};
+use spectral::prelude::*;
+
+use super::date_bound::parse_date_bound_at;
+
+#[test]
+fn test_parse_absolute() {
+  let now = Utc.with_ymd_and_hms(2024, 3, 15, 10, 30, 0).unwrap();
+
+  assert_that(&parse_date_bound_at("2024-01-31T12:00:00Z", now))
+    .is_ok_containing(Utc.with_ymd_and_hms(2024, 1, 31, 12, 0, 0).unwrap());
+  assert_that(&parse_date_bound_at("2024-01-31T12:00:00+02:00", now))
+    .is_ok_containing(Utc.with_ymd_and_hms(2024, 1, 31, 10, 0, 0).unwrap());
+  assert_that(&parse_date_bound_at("2024-01-31", now))
+    .is_ok_containing(Utc.with_ymd_and_hms(2024, 1, 31, 0, 0, 0).unwrap());
+}
+
+#[test]
+fn test_parse_relative() {
+  let now = Utc.with_ymd_and_hms(2024, 3, 15, 10, 30, 0).unwrap();
+
+  assert_that(&parse_date_bound_at("12h", now)).is_ok_containing(now - Duration::hours(12));
+  assert_that(&parse_date_bound_at("30d", now)).is_ok_containing(now - Duration::days(30));
+  assert_that(&parse_date_bound_at("2w", now)).is_ok_containing(now - Duration::weeks(2));
+  assert_that(&parse_date_bound_at("0d", now)).is_ok_containing(now);
+
+  assert_that(&parse_date_bound_at("", now)).is_err();
+  assert_that(&parse_date_bound_at("d", now)).is_err();
+  assert_that(&parse_date_bound_at("-3d", now)).is_err();
+  assert_that(&parse_date_bound_at("30x", now)).is_err();
+  assert_that(&parse_date_bound_at("30ä", now)).is_err();
+  assert_that(&parse_date_bound_at("yesterday", now)).is_err();
+}
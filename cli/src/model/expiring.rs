@@ -0,0 +1,22 @@
+use chrono::{DateTime, Duration, Utc};
+use t_rust_less_lib::api::{SecretEntry, SecretList, SecretListFilter, ZeroizeDateTime};
+
+/// Filter for all secrets expiring within the next `days` after `now` (including already expired ones).
+pub fn expiring_filter(now: DateTime<Utc>, days: i64) -> SecretListFilter {
+  let mut filter = SecretListFilter::default();
+  filter.expiring_before = Some((now + Duration::days(days)).into());
+  filter
+}
+
+/// Entries of `list` with an expiry date, the ones expiring first at the front.
+pub fn expiring_entries(list: &SecretList) -> Vec<(ZeroizeDateTime, &SecretEntry)> {
+  let mut entries: Vec<(ZeroizeDateTime, &SecretEntry)> = list
+    .entries
+    .iter()
+    .filter_map(|entry_match| Some((entry_match.entry.expires_at?, &entry_match.entry)))
+    .collect();
+
+  entries.sort_by_key(|(expires_at, _)| *expires_at);
+
+  entries
+}
@@ -0,0 +1,55 @@
+use chrono::{Duration, TimeZone, Utc};
+use spectral::prelude::*;
+use t_rust_less_lib::api::{SecretEntry, SecretEntryMatch, SecretList, SecretType, ZeroizeDateTime};
+
+use super::expiring::{expiring_entries, expiring_filter};
+
+fn entry_match(id: &str, expires_at: Option<ZeroizeDateTime>) -> SecretEntryMatch {
+  SecretEntryMatch {
+    entry: SecretEntry {
+      id: id.to_string(),
+      name: format!("Name of {}", id),
+      secret_type: SecretType::Login,
+      tags: vec![],
+      urls: vec![],
+      timestamp: Utc::now().into(),
+      deleted: false,
+      expires_at,
+    },
+    name_score: 0,
+    name_highlights: vec![],
+    url_highlights: vec![],
+    tags_highlights: vec![],
+    content_highlights: vec![],
+  }
+}
+
+#[test]
+fn test_expiring_filter() {
+  let now = Utc.with_ymd_and_hms(2024, 3, 15, 10, 30, 0).unwrap();
+  let filter = expiring_filter(now, 30);
+
+  assert_that(&filter.expiring_before).is_equal_to(Some((now + Duration::days(30)).into()));
+  assert_that(&filter.deleted).is_false();
+  assert_that(&filter.name).is_none();
+  assert_that(&filter.limit).is_none();
+}
+
+#[test]
+fn test_expiring_entries() {
+  let now = Utc.with_ymd_and_hms(2024, 3, 15, 10, 30, 0).unwrap();
+  let mut list = SecretList::default();
+  list.entries = vec![
+    entry_match("secret1", Some((now + Duration::days(20)).into())),
+    entry_match("secret2", None),
+    entry_match("secret3", Some((now - Duration::days(2)).into())),
+    entry_match("secret4", Some((now + Duration::days(5)).into())),
+  ];
+
+  let entries = expiring_entries(&list);
+
+  assert_that(&entries.iter().map(|(_, entry)| entry.id.as_str()).collect::<Vec<_>>())
+    .is_equal_to(vec!["secret3", "secret4", "secret1"]);
+  assert_that(&entries[0].0).is_equal_to(ZeroizeDateTime::from(now - Duration::days(2)));
+  assert_that(&expiring_entries(&SecretList::default())).is_empty();
+}
@@ -0,0 +1,70 @@
+use std::fmt;
+
+use clap::ValueEnum;
+use t_rust_less_lib::api::{SecretVersion, PROPERTY_NOTES, PROPERTY_PASSWORD, PROPERTY_USERNAME};
+
+/// Delimiter of multiple urls or tags within a single field.
+const LIST_DELIMITER: &str = ",";
+
+/// Columns of a csv export.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum CsvColumn {
+  Name,
+  Type,
+  Username,
+  Password,
+  Urls,
+  Tags,
+  Notes,
+}
+
+impl fmt::Display for CsvColumn {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      CsvColumn::Name => write!(f, "name"),
+      CsvColumn::Type => write!(f, "type"),
+      CsvColumn::Username => write!(f, "username"),
+      CsvColumn::Password => write!(f, "password"),
+      CsvColumn::Urls => write!(f, "urls"),
+      CsvColumn::Tags => write!(f, "tags"),
+      CsvColumn::Notes => write!(f, "notes"),
+    }
+  }
+}
+
+impl CsvColumn {
+  fn value(&self, version: &SecretVersion) -> String {
+    match self {
+      CsvColumn::Name => version.name.clone(),
+      CsvColumn::Type => version.secret_type.to_string(),
+      CsvColumn::Username => version.properties.get(PROPERTY_USERNAME).cloned().unwrap_or_default(),
+      CsvColumn::Password => version.properties.get(PROPERTY_PASSWORD).cloned().unwrap_or_default(),
+      CsvColumn::Urls => version.urls.join(LIST_DELIMITER),
+      CsvColumn::Tags => version.tags.join(LIST_DELIMITER),
+      CsvColumn::Notes => version.properties.get(PROPERTY_NOTES).cloned().unwrap_or_default(),
+    }
+  }
+}
+
+/// Header line (without line break).
+pub fn csv_header(columns: &[CsvColumn]) -> String {
+  csv_line(columns.iter().map(ToString::to_string))
+}
+
+/// Line of the `columns` of a secret version (without line break).
+pub fn csv_row(version: &SecretVersion, columns: &[CsvColumn]) -> String {
+  csv_line(columns.iter().map(|column| column.value(version)))
+}
+
+fn csv_line<I: Iterator<Item = String>>(fields: I) -> String {
+  fields.map(|field| escape_field(&field)).collect::<Vec<_>>().join(",")
+}
+
+/// Quote a field if necessary (see RFC 4180).
+fn escape_field(field: &str) -> String {
+  if field.contains([',', '"', '\r', '\n']) {
+    format!("\"{}\"", field.replace('"', "\"\""))
+  } else {
+    field.to_string()
+  }
+}
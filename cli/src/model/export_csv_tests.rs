@@ -0,0 +1,47 @@
+use chrono::Utc;
+use spectral::prelude::*;
+use t_rust_less_lib::api::{SecretProperties, SecretType, SecretVersion};
+
+use super::export_csv::{csv_header, csv_row, CsvColumn};
+
+fn version() -> SecretVersion {
+  SecretVersion {
+    secret_id: "secret1".to_string(),
+    secret_type: SecretType::Login,
+    timestamp: Utc::now().into(),
+    name: "Secret, \"quoted\"".to_string(),
+    tags: vec!["work".to_string(), "mail".to_string()],
+    urls: vec!["https://example.com".to_string()],
+    properties: SecretProperties::new(
+      [
+        ("username".to_string(), "user1".to_string()),
+        ("password".to_string(), "pw1".to_string()),
+        ("notes".to_string(), "line1\nline2".to_string()),
+      ]
+      .into_iter()
+      .collect(),
+    ),
+    attachments: vec![],
+    deleted: false,
+    recipients: vec![],
+    expires_at: None,
+    parent_block_id: None,
+    modified_by: None,
+  }
+}
+
+#[test]
+fn test_csv_columns() {
+  let columns = [CsvColumn::Name, CsvColumn::Username, CsvColumn::Password];
+
+  assert_that(&csv_header(&columns)).is_equal_to("name,username,password".to_string());
+  assert_that(&csv_row(&version(), &columns)).is_equal_to("\"Secret, \"\"quoted\"\"\",user1,pw1".to_string());
+}
+
+#[test]
+fn test_csv_escaping() {
+  let columns = [CsvColumn::Type, CsvColumn::Urls, CsvColumn::Tags, CsvColumn::Notes];
+
+  assert_that(&csv_row(&version(), &columns))
+    .is_equal_to("Login,https://example.com,\"work,mail\",\"line1\nline2\"".to_string());
+}
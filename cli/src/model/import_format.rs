@@ -0,0 +1,116 @@
+use std::fmt;
+
+use clap::ValueEnum;
+use serde_json::Value;
+
+/// Only this many bytes are inspected to detect the format of an import.
+const SNIFF_LENGTH: usize = 4096;
+const CSV_DELIMITERS: &[char] = &[',', ';', '\t'];
+const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+
+/// Formats that can be imported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ImportFormat {
+  /// Json lines of the original trustless
+  V1,
+  /// Json lines (or a json array) as written by `export`
+  V2,
+  /// Comma, semicolon or tab separated values with a header line
+  Csv,
+  /// Unencrypted xml export of KeePass
+  KeepassXml,
+}
+
+impl fmt::Display for ImportFormat {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ImportFormat::V1 => write!(f, "v1"),
+      ImportFormat::V2 => write!(f, "v2"),
+      ImportFormat::Csv => write!(f, "csv"),
+      ImportFormat::KeepassXml => write!(f, "keepass-xml"),
+    }
+  }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum DetectError {
+  Empty,
+  Unknown,
+  Ambiguous(&'static str),
+}
+
+impl fmt::Display for DetectError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      DetectError::Empty => write!(f, "Nothing to import"),
+      DetectError::Unknown => write!(f, "Unable to detect import format, please specify --format"),
+      DetectError::Ambiguous(reason) => write!(f, "Ambiguous import format ({}), please specify --format", reason),
+    }
+  }
+}
+
+impl std::error::Error for DetectError {}
+
+/// Detect the format of an import from its leading bytes.
+///
+/// Only the first record is inspected, i.e. this does not validate the whole import.
+pub fn detect_format(content: &[u8]) -> Result<ImportFormat, DetectError> {
+  let content = strip_bom(content);
+  let head = String::from_utf8_lossy(&content[..content.len().min(SNIFF_LENGTH)]);
+  let head = head.trim_start();
+
+  match head.chars().next() {
+    None => Err(DetectError::Empty),
+    Some('<') => detect_xml(head),
+    Some('{') | Some('[') => detect_json(content),
+    Some(_) => detect_csv(head),
+  }
+}
+
+/// Strip the byte order mark some tools (mostly on windows) put in front of their exports.
+pub fn strip_bom(content: &[u8]) -> &[u8] {
+  content.strip_prefix(UTF8_BOM).unwrap_or(content)
+}
+
+fn detect_xml(head: &str) -> Result<ImportFormat, DetectError> {
+  if head.contains("<KeePassFile") {
+    Ok(ImportFormat::KeepassXml)
+  } else {
+    Err(DetectError::Unknown)
+  }
+}
+
+fn detect_json(content: &[u8]) -> Result<ImportFormat, DetectError> {
+  let first = match serde_json::Deserializer::from_slice(content)
+    .into_iter::<Value>()
+    .next()
+  {
+    Some(Ok(Value::Array(elements))) => elements.into_iter().next().ok_or(DetectError::Empty)?,
+    Some(Ok(value)) => value,
+    _ => return Err(DetectError::Unknown),
+  };
+  let record = first.as_object().ok_or(DetectError::Unknown)?;
+  let is_v1 = record.contains_key("type") && record.contains_key("versions");
+  let is_v2 = record.contains_key("current");
+
+  match (is_v1, is_v2) {
+    (true, false) => Ok(ImportFormat::V1),
+    (false, true) => Ok(ImportFormat::V2),
+    (true, true) => Err(DetectError::Ambiguous("json matches v1 and v2")),
+    (false, false) => Err(DetectError::Unknown),
+  }
+}
+
+fn detect_csv(head: &str) -> Result<ImportFormat, DetectError> {
+  let header = head.lines().next().ok_or(DetectError::Empty)?;
+  let delimiters = CSV_DELIMITERS
+    .iter()
+    .filter(|delimiter| header.contains(**delimiter))
+    .count();
+
+  match delimiters {
+    0 => Err(DetectError::Unknown),
+    1 => Ok(ImportFormat::Csv),
+    _ => Err(DetectError::Ambiguous("csv header with mixed delimiters")),
+  }
+}
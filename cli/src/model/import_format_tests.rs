@@ -0,0 +1,66 @@
+use spectral::prelude::*;
+
+use super::import_format::{detect_format, DetectError, ImportFormat};
+
+const V1_LINES: &str = r#"{"id":"secret1","type":"login","versions":[{"timestamp":"2019-01-01T00:00:00Z","name":"Secret1","tags":null,"urls":null,"properties":{"password":"pw1"},"attachments":null,"deleted":false}]}
+{"id":"secret2","type":"note","versions":[]}
+"#;
+
+const V2_LINES: &str = r#"{"id":"secret1","current":{"type":"login","timestamp":"2019-01-01T00:00:00Z","name":"Secret1","properties":{"password":"pw1"}},"versions":[]}
+{"id":"secret2","current":{"type":"note","timestamp":"2019-01-01T00:00:00Z","name":"Secret2","properties":{}},"versions":[]}
+"#;
+
+const V2_ARRAY: &str = r#"[
+  {"id":"secret1","current":{"type":"login","timestamp":"2019-01-01T00:00:00Z","name":"Secret1","properties":{}},"versions":[]}
+]"#;
+
+const CSV_COMMA: &str = "name,url,username,password\nSecret1,https://example.com,user1,pw1\n";
+
+const CSV_SEMICOLON: &str = "\"Title\";\"Username\";\"Password\"\n\"Secret1\";\"user1\";\"pw1\"\n";
+
+const CSV_TAB: &str = "Title\tUsername\tPassword\nSecret1\tuser1\tpw1\n";
+
+const KEEPASS_XML: &str = r#"<?xml version="1.0" encoding="utf-8" standalone="yes"?>
+<KeePassFile>
+  <Meta><Generator>KeePass</Generator></Meta>
+  <Root><Group><Name>Root</Name></Group></Root>
+</KeePassFile>
+"#;
+
+#[test]
+fn test_detect_json_formats() {
+  assert_that(&detect_format(V1_LINES.as_bytes())).is_ok_containing(ImportFormat::V1);
+  assert_that(&detect_format(V2_LINES.as_bytes())).is_ok_containing(ImportFormat::V2);
+  assert_that(&detect_format(V2_ARRAY.as_bytes())).is_ok_containing(ImportFormat::V2);
+
+  // Leading whitespace and byte order mark are ignored
+  let with_bom = [b"\xef\xbb\xbf  \n".as_ref(), V2_LINES.as_bytes()].concat();
+  assert_that(&detect_format(&with_bom)).is_ok_containing(ImportFormat::V2);
+}
+
+#[test]
+fn test_detect_csv_formats() {
+  assert_that(&detect_format(CSV_COMMA.as_bytes())).is_ok_containing(ImportFormat::Csv);
+  assert_that(&detect_format(CSV_SEMICOLON.as_bytes())).is_ok_containing(ImportFormat::Csv);
+  assert_that(&detect_format(CSV_TAB.as_bytes())).is_ok_containing(ImportFormat::Csv);
+}
+
+#[test]
+fn test_detect_keepass_xml() {
+  assert_that(&detect_format(KEEPASS_XML.as_bytes())).is_ok_containing(ImportFormat::KeepassXml);
+}
+
+#[test]
+fn test_detect_unknown_or_ambiguous() {
+  assert_that(&detect_format(b"")).is_err_containing(DetectError::Empty);
+  assert_that(&detect_format(b" \n\t\n")).is_err_containing(DetectError::Empty);
+  assert_that(&detect_format(b"[]")).is_err_containing(DetectError::Empty);
+  assert_that(&detect_format(b"just some text\n")).is_err_containing(DetectError::Unknown);
+  assert_that(&detect_format(b"<html><body></body></html>")).is_err_containing(DetectError::Unknown);
+  assert_that(&detect_format(b"{\"name\":\"something else\"}")).is_err_containing(DetectError::Unknown);
+  assert_that(&detect_format(b"{\"id\":\"broken")).is_err_containing(DetectError::Unknown);
+  assert_that(&detect_format(b"{\"type\":\"login\",\"versions\":[],\"current\":{}}"))
+    .is_err_containing(DetectError::Ambiguous("json matches v1 and v2"));
+  assert_that(&detect_format(b"name;url,notes\nSecret1;https://example.com,notes\n"))
+    .is_err_containing(DetectError::Ambiguous("csv header with mixed delimiters"));
+}
@@ -28,6 +28,8 @@ pub struct SecretVersionV2 {
   pub deleted: bool,
   #[serde(default)]
   pub recipients: Vec<String>,
+  #[serde(default)]
+  pub expires_at: Option<ZeroizeDateTime>,
 }
 
 impl From<&SecretVersion> for SecretVersionV2 {
@@ -42,6 +44,27 @@ impl From<&SecretVersion> for SecretVersionV2 {
       attachments: value.attachments.clone(),
       deleted: value.deleted,
       recipients: value.recipients.clone(),
+      expires_at: value.expires_at,
+    }
+  }
+}
+
+impl SecretVersionV2 {
+  pub fn to_version(&self, secret_id: &str) -> SecretVersion {
+    SecretVersion {
+      secret_id: secret_id.to_string(),
+      secret_type: self.secret_type,
+      timestamp: self.timestamp,
+      name: self.name.clone(),
+      tags: self.tags.clone(),
+      urls: self.urls.clone(),
+      properties: self.properties.clone(),
+      attachments: self.attachments.clone(),
+      deleted: self.deleted,
+      recipients: self.recipients.clone(),
+      expires_at: self.expires_at,
+      parent_block_id: None,
+      modified_by: None,
     }
   }
 }
@@ -1,2 +1,17 @@
+pub mod attachment;
+#[cfg(test)]
+mod attachment_tests;
+pub mod date_bound;
+#[cfg(test)]
+mod date_bound_tests;
+pub mod expiring;
+#[cfg(test)]
+mod expiring_tests;
+pub mod export_csv;
+#[cfg(test)]
+mod export_csv_tests;
+pub mod import_format;
+#[cfg(test)]
+mod import_format_tests;
 pub mod import_v1;
 pub mod import_v2;
@@ -0,0 +1,73 @@
+use cursive::theme::Effect;
+use cursive::utils::markup::StyledString;
+use t_rust_less_lib::api::{ContentHighlight, SecretVersion};
+
+/// Number of chars shown in front of and after the first match of a property.
+pub const SNIPPET_CONTEXT: usize = 20;
+
+/// Preview of the matches of a content search, one snippet per matching property.
+///
+/// This should always be rendered from a freshly fetched version, the highlights only refer to positions.
+pub fn content_preview(version: &SecretVersion, highlights: &[ContentHighlight]) -> StyledString {
+  let mut preview = StyledString::new();
+  let mut properties: Vec<&str> = highlights.iter().map(|highlight| highlight.property.as_str()).collect();
+  properties.dedup();
+
+  for property in properties {
+    let Some(value) = version.properties.get(property) else {
+      continue;
+    };
+    let ranges: Vec<(usize, usize)> = highlights
+      .iter()
+      .filter(|highlight| highlight.property == property)
+      .map(|highlight| (highlight.start, highlight.end))
+      .collect();
+
+    if !preview.is_empty() {
+      preview.append_plain("\n");
+    }
+    preview.append(content_snippet(property, value, &ranges, SNIPPET_CONTEXT));
+  }
+
+  preview
+}
+
+/// Single line snippet of a property value around its first match, all matches within are highlighted.
+pub fn content_snippet(property: &str, value: &str, ranges: &[(usize, usize)], context: usize) -> StyledString {
+  let chars: Vec<char> = value.chars().map(|c| if c.is_whitespace() { ' ' } else { c }).collect();
+  let mut snippet = StyledString::styled(format!("{}: ", property), Effect::Bold);
+  let Some((first_start, first_end)) = ranges.first() else {
+    return snippet;
+  };
+  let start = first_start.saturating_sub(context).min(chars.len());
+  let end = (first_end + context).min(chars.len());
+
+  if start > 0 {
+    snippet.append_plain("…");
+  }
+  let is_highlighted = |position: usize| {
+    ranges
+      .iter()
+      .any(|(range_start, range_end)| *range_start <= position && position < *range_end)
+  };
+  let mut position = start;
+  while position < end {
+    let highlighted = is_highlighted(position);
+    let run_end = (position + 1..end)
+      .find(|next| is_highlighted(*next) != highlighted)
+      .unwrap_or(end);
+    let part: String = chars[position..run_end].iter().collect();
+
+    if highlighted {
+      snippet.append_styled(part, Effect::Reverse);
+    } else {
+      snippet.append_plain(part);
+    }
+    position = run_end;
+  }
+  if end < chars.len() {
+    snippet.append_plain("…");
+  }
+
+  snippet
+}
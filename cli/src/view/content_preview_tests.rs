@@ -0,0 +1,99 @@
+use std::collections::BTreeMap;
+
+use chrono::Utc;
+use cursive::theme::{Effect, Style};
+use cursive::utils::markup::StyledString;
+use spectral::prelude::*;
+use t_rust_less_lib::api::{ContentHighlight, SecretProperties, SecretType, SecretVersion};
+
+use super::content_preview::{content_preview, content_snippet};
+
+/// Plain text of a styled string, highlighted parts are enclosed in brackets
+fn marked(styled: &StyledString) -> String {
+  styled
+    .spans()
+    .map(|span| {
+      if *span.attr == Style::from(Effect::Reverse) {
+        format!("[{}]", span.content)
+      } else {
+        span.content.to_string()
+      }
+    })
+    .collect()
+}
+
+fn highlight(property: &str, start: usize, end: usize) -> ContentHighlight {
+  ContentHighlight {
+    property: property.to_string(),
+    start,
+    end,
+  }
+}
+
+#[test]
+fn test_content_snippet() {
+  assert_that(&marked(&content_snippet("notes", "short note", &[(6, 10)], 20)))
+    .is_equal_to("notes: short [note]".to_string());
+  assert_that(&marked(&content_snippet("notes", "no matches", &[], 20))).is_equal_to("notes: ".to_string());
+
+  // Context is limited on both sides
+  assert_that(&marked(&content_snippet(
+    "notes",
+    "The recovery codes are in the safe behind the painting",
+    &[(30, 34)],
+    5,
+  )))
+  .is_equal_to("notes: … the [safe] behi…".to_string());
+
+  // All matches within the context are highlighted, line breaks are flattened
+  assert_that(&marked(&content_snippet(
+    "notes",
+    "Safe:\nsafe\tSAFE and more",
+    &[(0, 4), (6, 10), (11, 15)],
+    12,
+  )))
+  .is_equal_to("notes: [Safe]: [safe] [SAFE] …".to_string());
+
+  // Positions refer to chars, not bytes
+  assert_that(&marked(&content_snippet("city", "Grüße aus Köln", &[(10, 14)], 4)))
+    .is_equal_to("city: …aus [Köln]".to_string());
+}
+
+#[test]
+fn test_content_preview() {
+  let mut properties = BTreeMap::new();
+  properties.insert("username".to_string(), "backup-user".to_string());
+  properties.insert(
+    "notes".to_string(),
+    "Restore the backup first, then the other backup".to_string(),
+  );
+  let version = SecretVersion {
+    secret_id: "secret1".to_string(),
+    secret_type: SecretType::Login,
+    timestamp: Utc::now().into(),
+    name: "Secret1".to_string(),
+    tags: vec![],
+    urls: vec![],
+    properties: SecretProperties::new(properties),
+    attachments: vec![],
+    deleted: false,
+    recipients: vec![],
+    expires_at: None,
+    parent_block_id: None,
+    modified_by: None,
+  };
+
+  let preview = content_preview(
+    &version,
+    &[
+      highlight("notes", 12, 18),
+      highlight("notes", 41, 47),
+      highlight("username", 0, 6),
+      highlight("removed", 0, 6),
+    ],
+  );
+
+  assert_that(&marked(&preview))
+    .is_equal_to("notes: Restore the [backup] first, then the oth…\nusername: [backup]-user".to_string());
+  assert_that(&content_preview(&version, &[]).is_empty()).is_true();
+}
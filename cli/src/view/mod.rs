@@ -1,3 +1,6 @@
+mod content_preview;
+#[cfg(test)]
+mod content_preview_tests;
 mod password_view;
 mod secret_copy_view;
 mod secret_note_view;
@@ -7,6 +10,7 @@ mod secret_type_view;
 mod secret_view;
 mod status_view;
 
+pub use self::content_preview::*;
 pub use self::password_view::*;
 pub use self::secret_copy_view::*;
 pub use self::secret_note_view::*;
@@ -4,7 +4,7 @@ use cursive::view::ViewWrapper;
 use cursive::views::{DummyView, LinearLayout};
 use cursive::Cursive;
 use std::sync::Arc;
-use t_rust_less_lib::api::{Secret, PROPERTY_NOTES, PROPERTY_PASSWORD, PROPERTY_TOTP_URL};
+use t_rust_less_lib::api::{Secret, SecretVersionRef, PROPERTY_NOTES, PROPERTY_PASSWORD, PROPERTY_TOTP_URL};
 use t_rust_less_lib::secrets_store::SecretsStore;
 use t_rust_less_lib::service::TrustlessService;
 
@@ -50,8 +50,19 @@ impl SecretView {
       Ok(secret) => {
         let mut layout = LinearLayout::vertical()
           .child(SecretSimpleView::new("Name", &secret.current.name))
-          .child(SecretTypeView::new(secret.current.secret_type))
-          .child(DummyView {});
+          .child(SecretTypeView::new(secret.current.secret_type));
+
+        if let Some(version) = secret.versions.first() {
+          layout = layout.child(SecretSimpleView::new("Modified", &self.version_display(version)));
+        }
+        layout = layout.child(SecretSimpleView::new(
+          "Recipients",
+          &self.recipients_display(&secret.current.recipients),
+        ));
+        if let Ok(Some(count)) = self.secrets_store.check_breached(secret_id) {
+          layout = layout.child(SecretSimpleView::new("Breached", &breach_warning(count)));
+        }
+        layout = layout.child(DummyView {});
 
         for (property, value) in secret.current.properties.iter() {
           match property {
@@ -90,6 +101,36 @@ impl SecretView {
     }
   }
 
+  /// Timestamp and author of a version, authors are displayed by name if known
+  fn version_display(&self, version: &SecretVersionRef) -> String {
+    let timestamp = version.timestamp.format("%Y-%m-%d %H:%M:%S");
+    let identities = self.secrets_store.identities().unwrap_or_default();
+
+    match &version.modified_by {
+      Some(modified_by) => match identities.iter().find(|identity| &identity.id == modified_by) {
+        Some(identity) => format!("{} by {}", timestamp, identity),
+        None => format!("{} by {}", timestamp, modified_by),
+      },
+      None => timestamp.to_string(),
+    }
+  }
+
+  /// Effective recipients of a version, displayed by name if known
+  fn recipients_display(&self, recipients: &[String]) -> String {
+    let identities = self.secrets_store.identities().unwrap_or_default();
+
+    recipients
+      .iter()
+      .map(
+        |recipient| match identities.iter().find(|identity| &identity.id == recipient) {
+          Some(identity) => identity.name.clone(),
+          None => recipient.clone(),
+        },
+      )
+      .collect::<Vec<_>>()
+      .join(", ")
+  }
+
   fn copy_to_clipboard(&self, secret_id: &str, property: &str) -> impl Fn(&mut Cursive) {
     let service = self.service.clone();
     let store_name = self.store_name.clone();
@@ -103,6 +144,11 @@ impl SecretView {
   }
 }
 
+/// Warning shown for secrets with a password found in the local breach directory of the store
+pub fn breach_warning(count: u64) -> String {
+  format!("password has been seen {} times in known data breaches", count)
+}
+
 impl ViewWrapper for SecretView {
   type V = LinearLayout;
 
@@ -4,6 +4,8 @@ use t_rust_less_lib::service::TrustlessService;
 use tokio::time::interval;
 
 pub fn start_autolock_loop(service: Arc<dyn TrustlessService>) {
+  start_sleep_watcher(service.clone());
+
   let mut interval = interval(Duration::from_secs(1));
   tokio::spawn(async move {
     loop {
@@ -12,3 +14,151 @@ pub fn start_autolock_loop(service: Arc<dyn TrustlessService>) {
     }
   });
 }
+
+/// Lock all stores when logind signals that the system is about to sleep.
+#[cfg(target_os = "linux")]
+fn start_sleep_watcher(service: Arc<dyn TrustlessService>) {
+  tokio::spawn(async move {
+    if let Err(err) = logind::watch_prepare_for_sleep(service).await {
+      log::warn!(
+        "Unable to watch for system sleep (stores are not locked on suspend): {}",
+        err
+      );
+    }
+  });
+}
+
+/// Lock all stores when windows broadcasts that the system is about to suspend.
+#[cfg(windows)]
+fn start_sleep_watcher(service: Arc<dyn TrustlessService>) {
+  std::thread::spawn(move || power_broadcast::run(service));
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn start_sleep_watcher(_service: Arc<dyn TrustlessService>) {}
+
+#[cfg(target_os = "linux")]
+mod logind {
+  use futures::StreamExt;
+  use log::{info, warn};
+  use std::sync::Arc;
+  use t_rust_less_lib::service::TrustlessService;
+  use zbus::zvariant::OwnedFd;
+  use zbus::{Connection, Proxy};
+
+  pub async fn watch_prepare_for_sleep(service: Arc<dyn TrustlessService>) -> zbus::Result<()> {
+    let connection = Connection::system().await?;
+    let manager = Proxy::new(
+      &connection,
+      "org.freedesktop.login1",
+      "/org/freedesktop/login1",
+      "org.freedesktop.login1.Manager",
+    )
+    .await?;
+    let mut prepare_for_sleep = manager.receive_signal("PrepareForSleep").await?;
+    // Holding a delay lock ensures that the stores are locked before the system actually sleeps
+    let mut inhibitor = take_sleep_inhibitor(&manager).await;
+
+    while let Some(message) = prepare_for_sleep.next().await {
+      let going_to_sleep: bool = message.body().deserialize()?;
+
+      if going_to_sleep {
+        info!("System is going to sleep");
+        service.lock_on_sleep();
+        inhibitor.take();
+      } else if inhibitor.is_none() {
+        inhibitor = take_sleep_inhibitor(&manager).await;
+      }
+    }
+
+    Ok(())
+  }
+
+  async fn take_sleep_inhibitor(manager: &Proxy<'_>) -> Option<OwnedFd> {
+    match manager
+      .call(
+        "Inhibit",
+        &("sleep", "t-rust-less", "Lock stores before sleep", "delay"),
+      )
+      .await
+    {
+      Ok(fd) => Some(fd),
+      Err(err) => {
+        warn!("Unable to delay sleep: {}", err);
+        None
+      }
+    }
+  }
+}
+
+#[cfg(windows)]
+mod power_broadcast {
+  use log::{error, info};
+  use std::ptr;
+  use std::sync::{Arc, OnceLock};
+  use t_rust_less_lib::service::TrustlessService;
+  use winapi::shared::minwindef::{LPARAM, LRESULT, TRUE, UINT, WPARAM};
+  use winapi::shared::windef::HWND;
+  use winapi::um::libloaderapi::GetModuleHandleW;
+  use winapi::um::winuser::{
+    CreateWindowExW, DefWindowProcW, DispatchMessageW, GetMessageW, RegisterClassW, TranslateMessage, MSG,
+    PBT_APMSUSPEND, WM_POWERBROADCAST, WNDCLASSW,
+  };
+
+  static SERVICE: OnceLock<Arc<dyn TrustlessService>> = OnceLock::new();
+
+  unsafe extern "system" fn window_proc(hwnd: HWND, msg: UINT, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if msg == WM_POWERBROADCAST && wparam == PBT_APMSUSPEND as WPARAM {
+      info!("System is going to sleep");
+      if let Some(service) = SERVICE.get() {
+        service.lock_on_sleep();
+      }
+      return TRUE as LRESULT;
+    }
+    DefWindowProcW(hwnd, msg, wparam, lparam)
+  }
+
+  /// WM_POWERBROADCAST is only sent to top-level windows, i.e. an invisible one is created to receive it.
+  pub fn run(service: Arc<dyn TrustlessService>) {
+    if SERVICE.set(service).is_err() {
+      return;
+    }
+    let class_name: Vec<u16> = "t-rust-less-power\0".encode_utf16().collect();
+
+    unsafe {
+      let instance = GetModuleHandleW(ptr::null());
+      let mut class: WNDCLASSW = std::mem::zeroed();
+      class.lpfnWndProc = Some(window_proc);
+      class.hInstance = instance;
+      class.lpszClassName = class_name.as_ptr();
+      if RegisterClassW(&class) == 0 {
+        error!("Unable to register power broadcast window class");
+        return;
+      }
+      let hwnd = CreateWindowExW(
+        0,
+        class_name.as_ptr(),
+        class_name.as_ptr(),
+        0,
+        0,
+        0,
+        0,
+        0,
+        ptr::null_mut(),
+        ptr::null_mut(),
+        instance,
+        ptr::null_mut(),
+      );
+      if hwnd.is_null() {
+        error!("Unable to create power broadcast window");
+        return;
+      }
+
+      let mut msg: MSG = std::mem::zeroed();
+      while GetMessageW(&mut msg, ptr::null_mut(), 0, 0) > 0 {
+        TranslateMessage(&msg);
+        DispatchMessageW(&msg);
+      }
+    }
+  }
+}
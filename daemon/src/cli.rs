@@ -14,5 +14,12 @@ pub fn app() -> App<'static, 'static> {
   #[cfg(unix)]
   let app = app.arg(Arg::with_name("journal").long("journal").help("Log to systemd journal"));
 
+  #[cfg(target_os = "linux")]
+  let app = app.arg(
+    Arg::with_name("no-screensaver-lock")
+      .long("no-screensaver-lock")
+      .help("Do not lock stores when the screen is locked (e.g. for headless setups)"),
+  );
+
   app
 }
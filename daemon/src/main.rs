@@ -2,6 +2,8 @@ mod cli;
 
 mod autolock;
 mod processor;
+#[cfg(target_os = "linux")]
+mod screensaver;
 mod sync_trigger;
 
 #[cfg(unix)]
@@ -34,6 +36,10 @@ async fn main() -> Result<(), Box<dyn Error>> {
     sync_trigger::start_sync_loop(service.clone());
   }
   autolock::start_autolock_loop(service.clone());
+  #[cfg(target_os = "linux")]
+  if !matches.is_present("no-screensaver-lock") {
+    screensaver::start_screensaver_watcher(service.clone());
+  }
 
   run_server(service).await
 }
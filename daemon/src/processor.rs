@@ -1,11 +1,14 @@
+use log::error;
 use std::error::Error;
 use std::io;
 use std::sync::Arc;
 use t_rust_less_lib::api::{Command, CommandResult};
 use t_rust_less_lib::memguard::ZeroizeBytesBuffer;
+use t_rust_less_lib::secrets_store_capnp::KeyType;
 use t_rust_less_lib::service::local::LocalTrustlessService;
 use t_rust_less_lib::service::{ClipboardControl, ServiceError, ServiceResult, TrustlessService};
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::task::spawn_blocking;
 use zeroize::Zeroizing;
 
 #[derive(Clone)]
@@ -50,7 +53,25 @@ impl Processor {
       Command::SetDefaultStore(name) => write_result(wr, self.service.set_default_store(name)).await?,
       Command::GenerateId => write_result(wr, self.service.generate_id()).await?,
       Command::GeneratePassword(param) => write_result(wr, self.service.generate_password(param.clone())).await?,
+      Command::Capabilities => write_result(wr, self.service.capabilities()).await?,
       Command::PollEvents(last_id) => write_result(wr, self.service.poll_events(*last_id)).await?,
+      Command::SynchronizeNow(store_name) => {
+        // Synchronization might take a while (and block on the remote)
+        let service = self.service.clone();
+        let store_name = store_name.clone();
+        let result = match spawn_blocking(move || service.synchronize_now(&store_name)).await {
+          Ok(result) => result,
+          Err(err) => Err(ServiceError::IO(format!("{}", err))),
+        };
+        write_result(wr, result).await?
+      }
+      Command::PanicLock { wipe_local } => write_result(wr, self.service.panic_lock(*wipe_local)).await?,
+      Command::Diagnose(store_name) => write_result(wr, self.service.diagnose(store_name)).await?,
+      Command::DeriveSitePassword {
+        store_name,
+        site,
+        param,
+      } => write_result(wr, self.service.derive_site_password(store_name, site, param.clone())).await?,
       Command::Status(store_name) => {
         write_result(wr, self.service.open_store(store_name).and_then(|store| store.status())).await?
       }
@@ -71,6 +92,20 @@ impl Processor {
         )
         .await?
       }
+      Command::VerifyPassphrase {
+        store_name,
+        identity_id,
+        passphrase,
+      } => {
+        write_result(
+          wr,
+          self
+            .service
+            .open_store(store_name)
+            .and_then(|store| store.verify_passphrase(identity_id, passphrase.clone())),
+        )
+        .await?
+      }
       Command::Identities(store_name) => {
         write_result(
           wr,
@@ -112,6 +147,59 @@ impl Processor {
         )
         .await?
       }
+      Command::WipeIndex(store_name) => {
+        write_result(
+          wr,
+          self.service.open_store(store_name).and_then(|store| store.wipe_index()),
+        )
+        .await?
+      }
+      Command::RebuildIndex(store_name) => {
+        write_result(
+          wr,
+          self
+            .service
+            .open_store(store_name)
+            .and_then(|store| store.rebuild_index()),
+        )
+        .await?
+      }
+      Command::RotateCipher { store_name, target } => {
+        write_result(
+          wr,
+          self
+            .service
+            .open_store(store_name)
+            .and_then(|store| store.rotate_cipher(KeyType::try_from(*target)?)),
+        )
+        .await?
+      }
+      Command::CollectGarbage {
+        store_name,
+        prune_history,
+      } => {
+        write_result(
+          wr,
+          self
+            .service
+            .open_store(store_name)
+            .and_then(|store| store.collect_garbage(*prune_history)),
+        )
+        .await?
+      }
+      Command::RevokeRecipient {
+        store_name,
+        recipient_id,
+      } => {
+        write_result(
+          wr,
+          self
+            .service
+            .open_store(store_name)
+            .and_then(|store| store.revoke_recipient(recipient_id)),
+        )
+        .await?
+      }
       Command::List { store_name, filter } => {
         write_result(
           wr,
@@ -152,6 +240,61 @@ impl Processor {
         )
         .await?
       }
+      Command::GetAttachmentChunk {
+        store_name,
+        secret_id,
+        name,
+        index,
+      } => {
+        write_result(
+          wr,
+          self
+            .service
+            .open_store(store_name)
+            .and_then(|store| store.get_attachment_chunk(secret_id, name, *index)),
+        )
+        .await?
+      }
+      Command::FindConcurrentVersions { store_name, block_ids } => {
+        write_result(
+          wr,
+          self
+            .service
+            .open_store(store_name)
+            .and_then(|store| store.find_concurrent_versions(block_ids)),
+        )
+        .await?
+      }
+      Command::FindReusedPasswords(store_name) => {
+        write_result(
+          wr,
+          self
+            .service
+            .open_store(store_name)
+            .and_then(|store| store.find_reused_passwords()),
+        )
+        .await?
+      }
+      Command::Audit { store_name, policy } => {
+        write_result(
+          wr,
+          self
+            .service
+            .open_store(store_name)
+            .and_then(|store| store.audit(policy)),
+        )
+        .await?
+      }
+      Command::CheckBreached { store_name, secret_id } => {
+        write_result(
+          wr,
+          self
+            .service
+            .open_store(store_name)
+            .and_then(|store| store.check_breached(secret_id)),
+        )
+        .await?
+      }
       Command::SecretToClipboard {
         store_name,
         block_id,
@@ -191,10 +334,30 @@ impl Processor {
       },
     }
 
+    // Block stores might buffer writes, i.e. everything committed should be persisted right away
+    if let Some(store_name) = committing_store(&command) {
+      if let Err(err) = self.service.open_store(store_name).and_then(|store| store.flush()) {
+        error!("Flush of {} failed: {}", store_name, err);
+      }
+    }
+
     Ok(())
   }
 }
 
+/// Name of the store a command commits changes (or rings) to.
+fn committing_store(command: &Command) -> Option<&str> {
+  match command {
+    Command::AddIdentity { store_name, .. }
+    | Command::ChangePassphrase { store_name, .. }
+    | Command::Add { store_name, .. }
+    | Command::RotateCipher { store_name, .. }
+    | Command::RevokeRecipient { store_name, .. }
+    | Command::CollectGarbage { store_name, .. } => Some(store_name),
+    _ => None,
+  }
+}
+
 async fn write_result<R, W>(wr: &mut W, result: R) -> Result<(), Box<dyn Error>>
 where
   R: Into<CommandResult>,
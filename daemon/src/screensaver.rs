@@ -0,0 +1,57 @@
+use futures::stream::{self, StreamExt};
+use log::{debug, info};
+use std::sync::Arc;
+use t_rust_less_lib::service::TrustlessService;
+use zbus::fdo::DBusProxy;
+use zbus::names::BusName;
+use zbus::{Connection, Proxy};
+
+/// Screensavers (name and object path) emitting `ActiveChanged` on the session bus.
+/// The interface has the same name as the service.
+const SCREENSAVERS: &[(&str, &str)] = &[
+  ("org.gnome.ScreenSaver", "/org/gnome/ScreenSaver"),
+  ("org.freedesktop.ScreenSaver", "/org/freedesktop/ScreenSaver"),
+];
+
+/// Lock all stores when the screensaver of the desktop (GNOME or KDE) becomes active.
+///
+/// This is optional, i.e. if there is no session bus or no screensaver at all (e.g. headless)
+/// the watcher just quits.
+pub fn start_screensaver_watcher(service: Arc<dyn TrustlessService>) {
+  tokio::spawn(async move {
+    if let Err(err) = watch_active_changed(service).await {
+      debug!("Not watching for screen locks: {}", err);
+    }
+  });
+}
+
+async fn watch_active_changed(service: Arc<dyn TrustlessService>) -> zbus::Result<()> {
+  let connection = Connection::session().await?;
+  let dbus = DBusProxy::new(&connection).await?;
+  let mut active_changed = vec![];
+
+  for (name, path) in SCREENSAVERS {
+    if !dbus.name_has_owner(BusName::try_from(*name)?).await? {
+      continue;
+    }
+    let screensaver = Proxy::new(&connection, *name, *path, *name).await?;
+    active_changed.push(screensaver.receive_signal("ActiveChanged").await?);
+    debug!("Watching for screen locks of {}", name);
+  }
+  if active_changed.is_empty() {
+    debug!("No screensaver found, not watching for screen locks");
+    return Ok(());
+  }
+
+  let mut active_changed = stream::select_all(active_changed);
+  while let Some(message) = active_changed.next().await {
+    let active: bool = message.body().deserialize()?;
+
+    if active {
+      info!("Screensaver has been activated");
+      service.lock_on_screen_lock();
+    }
+  }
+
+  Ok(())
+}
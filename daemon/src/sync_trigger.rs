@@ -2,9 +2,9 @@ use std::{pin::Pin, sync::Arc};
 
 use chrono::Utc;
 use futures::Future;
-use log::debug;
+use log::{debug, error};
 use t_rust_less_lib::service::TrustlessService;
-use tokio::{spawn, time::sleep, time::Duration};
+use tokio::{spawn, task::spawn_blocking, time::sleep, time::Duration};
 
 pub fn start_sync_loop(service: Arc<dyn TrustlessService>) {
   spawn(trigger_sync(service));
@@ -12,7 +12,16 @@ pub fn start_sync_loop(service: Arc<dyn TrustlessService>) {
 
 fn trigger_sync(service: Arc<dyn TrustlessService>) -> Pin<Box<dyn Future<Output = ()> + Send>> {
   Box::pin(async move {
-    let millis = match service.synchronize() {
+    // Synchronization involves (potentially slow) remote I/O, which must not block a worker thread
+    let sync_service = service.clone();
+    let next_run = match spawn_blocking(move || sync_service.synchronize()).await {
+      Ok(next_run) => next_run,
+      Err(err) => {
+        error!("Synchronization task failed: {}", err);
+        None
+      }
+    };
+    let millis = match next_run {
       Some(next_run) => (next_run - Utc::now()).num_milliseconds(),
       _ => 0,
     };
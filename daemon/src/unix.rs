@@ -1,11 +1,12 @@
 use crate::processor::Processor;
 use futures::future;
-use log::{error, info};
+use log::{error, info, warn};
 use std::error::Error;
 use std::fs;
 use std::sync::Arc;
 use t_rust_less_lib::service::local::LocalTrustlessService;
 use t_rust_less_lib::service::unix::daemon_socket_path;
+use t_rust_less_lib::service::TrustlessService;
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::UnixListener;
 use tokio::signal;
@@ -22,6 +23,24 @@ pub async fn run_server(service: Arc<LocalTrustlessService>) -> Result<(), Box<d
   let listener = UnixListener::bind(&socket_path)?;
   unsafe { libc::umask(prev_mask) };
 
+  // SIGUSR1 triggers a panic lock, so that it can be bound to a global hotkey
+  // (e.g. `pkill -USR1 t-rust-less-daemon`)
+  let panic_service = service.clone();
+  tokio::spawn(async move {
+    match signal::unix::signal(signal::unix::SignalKind::user_defined1()) {
+      Ok(mut signal) => {
+        while signal.recv().await.is_some() {
+          warn!("Received SIGUSR1");
+          if let Err(error) = panic_service.panic_lock(false) {
+            error!("Panic lock failed: {}", error);
+          }
+        }
+      }
+      Err(error) => error!("Unable to register SIGUSR1 handler: {}", error),
+    }
+  });
+
+  let shutdown_service = service.clone();
   tokio::spawn(async move {
     while let Ok((mut socket, _)) = listener.accept().await {
       let mut processor = Processor::new(service.clone());
@@ -53,6 +72,7 @@ pub async fn run_server(service: Arc<LocalTrustlessService>) -> Result<(), Box<d
   .await;
 
   info!("Cleaning up");
+  shutdown_service.flush_stores();
   if let Err(error) = fs::remove_file(&socket_path) {
     error!("Cleanup of {} failed: {}", socket_path.to_string_lossy(), error)
   }
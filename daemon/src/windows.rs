@@ -16,6 +16,7 @@ pub async fn run_server(service: Arc<LocalTrustlessService>) -> Result<(), Box<d
 
   info!("Listening on socket {}", DAEMON_PIPE_NAME);
 
+  let shutdown_service = service.clone();
   tokio::spawn(async move {
     while server.connect().await.is_ok() {
       let mut processor = Processor::new(service.clone());
@@ -65,5 +66,8 @@ pub async fn run_server(service: Arc<LocalTrustlessService>) -> Result<(), Box<d
 
   signal::ctrl_c().await.ok();
 
+  info!("Cleaning up");
+  shutdown_service.flush_stores();
+
   Ok(())
 }
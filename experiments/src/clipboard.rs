@@ -46,7 +46,7 @@ impl EventHub for TestEventHub {
 }
 
 pub fn experimental_clipboard() {
-  let clipboard = Arc::new(Clipboard::new(DummyProvider { counter: 0 }, Arc::new(TestEventHub)).unwrap());
+  let clipboard = Arc::new(Clipboard::new(DummyProvider { counter: 0 }, false, None, Arc::new(TestEventHub)).unwrap());
 
   thread::spawn({
     let cloned = clipboard.clone();
@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+/// Minimum score (of the password strength estimation) a password has to reach by default.
+pub const DEFAULT_AUDIT_MIN_SCORE: u8 = 3;
+/// Maximum age (in days) of a password by default.
+pub const DEFAULT_AUDIT_MAX_AGE_DAYS: u32 = 365;
+
+/// What is considered a problem by `SecretsStore::audit`.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Zeroize)]
+#[cfg_attr(feature = "with_specta", derive(specta::Type))]
+#[zeroize(drop)]
+pub struct AuditPolicy {
+  /// Passwords with a lower estimated score are considered weak (0 = disabled)
+  pub min_score: u8,
+  /// Passwords not changed for longer than this many days are considered old (0 = disabled)
+  pub max_age_days: u32,
+  /// Check for passwords that are shared with other secrets
+  pub include_reuse: bool,
+  /// Check passwords against the local breach directory of the store (if configured)
+  pub include_breached: bool,
+}
+
+impl Default for AuditPolicy {
+  fn default() -> Self {
+    AuditPolicy {
+      min_score: DEFAULT_AUDIT_MIN_SCORE,
+      max_age_days: DEFAULT_AUDIT_MAX_AGE_DAYS,
+      include_reuse: false,
+      include_breached: false,
+    }
+  }
+}
+
+/// Why a secret failed the audit.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Zeroize)]
+#[cfg_attr(feature = "with_specta", derive(specta::Type))]
+pub enum AuditReason {
+  /// Estimated strength of a password property is below the minimum score
+  Weak { property: String, score: u8 },
+  /// The current version is older than the maximum age
+  Old { days: i64 },
+  /// The password is shared with the other secrets
+  Reused { secret_ids: Vec<String> },
+  /// The password has been seen this often in known data breaches
+  Breached { count: u64 },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Zeroize)]
+#[cfg_attr(feature = "with_specta", derive(specta::Type))]
+#[zeroize(drop)]
+pub struct AuditEntry {
+  pub secret_id: String,
+  pub name: String,
+  pub reasons: Vec<AuditReason>,
+}
+
+/// Outcome of `SecretsStore::audit`.
+///
+/// Like the list of secrets this never contains any passwords, only the reasons why a secret failed.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Zeroize)]
+#[cfg_attr(feature = "with_specta", derive(specta::Type))]
+#[zeroize(drop)]
+pub struct AuditReport {
+  /// Number of secrets with a password that have been checked
+  pub checked: usize,
+  /// All secrets failing the audit
+  pub entries: Vec<AuditEntry>,
+}
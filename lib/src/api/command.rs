@@ -5,8 +5,9 @@ use serde::{Deserialize, Serialize};
 use zeroize::Zeroize;
 
 use super::{
-  ClipboardProviding, Event, Identity, PasswordGeneratorParam, Secret, SecretList, SecretListFilter, SecretVersion,
-  Status, StoreConfig,
+  AuditPolicy, AuditReport, Capabilities, ClipboardProviding, Diagnostics, Event, Identity, PanicLockReport,
+  PasswordGeneratorParam, ReuseGroup, Secret, SecretAttachment, SecretList, SecretListFilter, SecretVersion, Status,
+  StoreConfig, StoreDiagnostics, SyncReport,
 };
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Zeroize)]
@@ -21,6 +22,17 @@ pub enum Command {
   GenerateId,
   GeneratePassword(PasswordGeneratorParam),
   PollEvents(u64),
+  Capabilities,
+  SynchronizeNow(String),
+  PanicLock {
+    wipe_local: bool,
+  },
+  Diagnose(String),
+  DeriveSitePassword {
+    store_name: String,
+    site: String,
+    param: PasswordGeneratorParam,
+  },
 
   Status(String),
   Lock(String),
@@ -29,6 +41,11 @@ pub enum Command {
     identity_id: String,
     passphrase: SecretBytes,
   },
+  VerifyPassphrase {
+    store_name: String,
+    identity_id: String,
+    passphrase: SecretBytes,
+  },
   Identities(String),
   AddIdentity {
     store_name: String,
@@ -44,6 +61,22 @@ pub enum Command {
     filter: SecretListFilter,
   },
   UpdateIndex(String),
+  RebuildIndex(String),
+  WipeIndex(String),
+  /// Re-encrypt all readable versions with a single cipher (`target` being the ordinal of its `KeyType`)
+  RotateCipher {
+    store_name: String,
+    target: u16,
+  },
+  RevokeRecipient {
+    store_name: String,
+    recipient_id: String,
+  },
+  /// Remove unreferenced blocks (and previous versions if `prune_history` is set)
+  CollectGarbage {
+    store_name: String,
+    prune_history: bool,
+  },
   Add {
     store_name: String,
     secret_version: SecretVersion,
@@ -56,6 +89,27 @@ pub enum Command {
     store_name: String,
     block_id: String,
   },
+  /// Read a single chunk of an attachment of the current version of a secret
+  GetAttachmentChunk {
+    store_name: String,
+    secret_id: String,
+    name: String,
+    index: usize,
+  },
+  FindConcurrentVersions {
+    store_name: String,
+    block_ids: Vec<String>,
+  },
+  FindReusedPasswords(String),
+  Audit {
+    store_name: String,
+    policy: AuditPolicy,
+  },
+  /// Check the passwords of a secret against the local breach directory of the store
+  CheckBreached {
+    store_name: String,
+    secret_id: String,
+  },
 
   SecretToClipboard {
     store_name: String,
@@ -74,14 +128,24 @@ pub enum Command {
 pub enum CommandResult {
   Void,
   Bool(bool),
+  Count(usize),
+  BreachCount(u64),
   String(String),
+  Strings(Vec<String>),
   Configs(Vec<StoreConfig>),
   Events(Vec<Event>),
   Status(Status),
+  Capabilities(Capabilities),
+  SyncReport(SyncReport),
+  PanicLockReport(PanicLockReport),
+  Diagnostics(Diagnostics),
   SecretList(SecretList),
   Identities(Vec<Identity>),
+  ReuseGroups(Vec<ReuseGroup>),
+  AuditReport(AuditReport),
   Secret(Secret),
   SecretVersion(SecretVersion),
+  Attachment(SecretAttachment),
   ClipboardProviding(ClipboardProviding),
   SecretStoreError(SecretStoreError),
   ServiceError(ServiceError),
@@ -249,6 +313,25 @@ impl From<SecretStoreResult<()>> for CommandResult {
   }
 }
 
+impl From<CommandResult> for SecretStoreResult<bool> {
+  fn from(result: CommandResult) -> Self {
+    match result {
+      CommandResult::Bool(value) => Ok(value),
+      CommandResult::SecretStoreError(ref error) => Err(error.clone()),
+      _ => Err(SecretStoreError::IO("Invalid command result".to_string())),
+    }
+  }
+}
+
+impl From<SecretStoreResult<bool>> for CommandResult {
+  fn from(result: SecretStoreResult<bool>) -> Self {
+    match result {
+      Ok(value) => CommandResult::Bool(value),
+      Err(error) => CommandResult::SecretStoreError(error),
+    }
+  }
+}
+
 impl From<CommandResult> for SecretStoreResult<String> {
   fn from(result: CommandResult) -> Self {
     match &result {
@@ -268,6 +351,156 @@ impl From<SecretStoreResult<String>> for CommandResult {
   }
 }
 
+impl From<CommandResult> for SecretStoreResult<usize> {
+  fn from(result: CommandResult) -> Self {
+    match &result {
+      CommandResult::Count(value) => Ok(*value),
+      CommandResult::SecretStoreError(error) => Err(error.clone()),
+      _ => Err(SecretStoreError::IO("Invalid command result".to_string())),
+    }
+  }
+}
+
+impl From<SecretStoreResult<usize>> for CommandResult {
+  fn from(result: SecretStoreResult<usize>) -> Self {
+    match result {
+      Ok(value) => CommandResult::Count(value),
+      Err(error) => CommandResult::SecretStoreError(error),
+    }
+  }
+}
+
+impl From<CommandResult> for SecretStoreResult<Option<u64>> {
+  fn from(result: CommandResult) -> Self {
+    match &result {
+      CommandResult::Void => Ok(None),
+      CommandResult::BreachCount(value) => Ok(Some(*value)),
+      CommandResult::SecretStoreError(error) => Err(error.clone()),
+      _ => Err(SecretStoreError::IO("Invalid command result".to_string())),
+    }
+  }
+}
+
+impl From<SecretStoreResult<Option<u64>>> for CommandResult {
+  fn from(result: SecretStoreResult<Option<u64>>) -> Self {
+    match result {
+      Ok(Some(value)) => CommandResult::BreachCount(value),
+      Ok(None) => CommandResult::Void,
+      Err(error) => CommandResult::SecretStoreError(error),
+    }
+  }
+}
+
+impl From<CommandResult> for SecretStoreResult<Vec<String>> {
+  fn from(result: CommandResult) -> Self {
+    match &result {
+      CommandResult::Strings(value) => Ok(value.clone()),
+      CommandResult::SecretStoreError(error) => Err(error.clone()),
+      _ => Err(SecretStoreError::IO("Invalid command result".to_string())),
+    }
+  }
+}
+
+impl From<SecretStoreResult<Vec<String>>> for CommandResult {
+  fn from(result: SecretStoreResult<Vec<String>>) -> Self {
+    match result {
+      Ok(value) => CommandResult::Strings(value),
+      Err(error) => CommandResult::SecretStoreError(error),
+    }
+  }
+}
+
+impl From<CommandResult> for ServiceResult<SyncReport> {
+  fn from(result: CommandResult) -> Self {
+    match &result {
+      CommandResult::SyncReport(value) => Ok(value.clone()),
+      CommandResult::ServiceError(error) => Err(error.clone()),
+      CommandResult::SecretStoreError(error) => Err(ServiceError::SecretsStore(error.clone())),
+      _ => Err(ServiceError::IO("Invalid command result".to_string())),
+    }
+  }
+}
+
+impl From<ServiceResult<SyncReport>> for CommandResult {
+  fn from(result: ServiceResult<SyncReport>) -> Self {
+    match result {
+      Ok(value) => CommandResult::SyncReport(value),
+      Err(error) => CommandResult::ServiceError(error),
+    }
+  }
+}
+
+impl From<CommandResult> for ServiceResult<PanicLockReport> {
+  fn from(result: CommandResult) -> Self {
+    match &result {
+      CommandResult::PanicLockReport(value) => Ok(value.clone()),
+      CommandResult::ServiceError(error) => Err(error.clone()),
+      CommandResult::SecretStoreError(error) => Err(ServiceError::SecretsStore(error.clone())),
+      _ => Err(ServiceError::IO("Invalid command result".to_string())),
+    }
+  }
+}
+
+impl From<ServiceResult<PanicLockReport>> for CommandResult {
+  fn from(result: ServiceResult<PanicLockReport>) -> Self {
+    match result {
+      Ok(value) => CommandResult::PanicLockReport(value),
+      Err(error) => CommandResult::ServiceError(error),
+    }
+  }
+}
+
+impl From<CommandResult> for ServiceResult<Diagnostics> {
+  fn from(result: CommandResult) -> Self {
+    match &result {
+      CommandResult::Diagnostics(value) => Ok(value.clone()),
+      CommandResult::ServiceError(error) => Err(error.clone()),
+      CommandResult::SecretStoreError(error) => Err(ServiceError::SecretsStore(error.clone())),
+      _ => Err(ServiceError::IO("Invalid command result".to_string())),
+    }
+  }
+}
+
+impl From<ServiceResult<Diagnostics>> for CommandResult {
+  fn from(result: ServiceResult<Diagnostics>) -> Self {
+    match result {
+      Ok(value) => CommandResult::Diagnostics(value),
+      Err(error) => CommandResult::ServiceError(error),
+    }
+  }
+}
+
+impl From<CommandResult> for SecretStoreResult<StoreDiagnostics> {
+  fn from(result: CommandResult) -> Self {
+    match &result {
+      CommandResult::Diagnostics(value) => Ok(value.store.clone()),
+      CommandResult::SecretStoreError(error) => Err(error.clone()),
+      CommandResult::ServiceError(ServiceError::SecretsStore(error)) => Err(error.clone()),
+      _ => Err(SecretStoreError::IO("Invalid command result".to_string())),
+    }
+  }
+}
+
+impl From<CommandResult> for ServiceResult<Capabilities> {
+  fn from(result: CommandResult) -> Self {
+    match &result {
+      CommandResult::Capabilities(value) => Ok(value.clone()),
+      CommandResult::ServiceError(error) => Err(error.clone()),
+      CommandResult::SecretStoreError(error) => Err(ServiceError::SecretsStore(error.clone())),
+      _ => Err(ServiceError::IO("Invalid command result".to_string())),
+    }
+  }
+}
+
+impl From<ServiceResult<Capabilities>> for CommandResult {
+  fn from(result: ServiceResult<Capabilities>) -> Self {
+    match result {
+      Ok(value) => CommandResult::Capabilities(value),
+      Err(error) => CommandResult::ServiceError(error),
+    }
+  }
+}
+
 impl From<CommandResult> for SecretStoreResult<Status> {
   fn from(result: CommandResult) -> Self {
     match &result {
@@ -306,6 +539,44 @@ impl From<SecretStoreResult<Vec<Identity>>> for CommandResult {
   }
 }
 
+impl From<CommandResult> for SecretStoreResult<Vec<ReuseGroup>> {
+  fn from(result: CommandResult) -> Self {
+    match &result {
+      CommandResult::ReuseGroups(value) => Ok(value.clone()),
+      CommandResult::SecretStoreError(error) => Err(error.clone()),
+      _ => Err(SecretStoreError::IO("Invalid command result".to_string())),
+    }
+  }
+}
+
+impl From<SecretStoreResult<Vec<ReuseGroup>>> for CommandResult {
+  fn from(result: SecretStoreResult<Vec<ReuseGroup>>) -> Self {
+    match result {
+      Ok(value) => CommandResult::ReuseGroups(value),
+      Err(error) => CommandResult::SecretStoreError(error),
+    }
+  }
+}
+
+impl From<CommandResult> for SecretStoreResult<AuditReport> {
+  fn from(result: CommandResult) -> Self {
+    match &result {
+      CommandResult::AuditReport(value) => Ok(value.clone()),
+      CommandResult::SecretStoreError(error) => Err(error.clone()),
+      _ => Err(SecretStoreError::IO("Invalid command result".to_string())),
+    }
+  }
+}
+
+impl From<SecretStoreResult<AuditReport>> for CommandResult {
+  fn from(result: SecretStoreResult<AuditReport>) -> Self {
+    match result {
+      Ok(value) => CommandResult::AuditReport(value),
+      Err(error) => CommandResult::SecretStoreError(error),
+    }
+  }
+}
+
 impl From<CommandResult> for SecretStoreResult<SecretList> {
   fn from(result: CommandResult) -> Self {
     match &result {
@@ -362,3 +633,24 @@ impl From<SecretStoreResult<SecretVersion>> for CommandResult {
     }
   }
 }
+
+impl From<CommandResult> for SecretStoreResult<Option<SecretAttachment>> {
+  fn from(result: CommandResult) -> Self {
+    match &result {
+      CommandResult::Void => Ok(None),
+      CommandResult::Attachment(value) => Ok(Some(value.clone())),
+      CommandResult::SecretStoreError(error) => Err(error.clone()),
+      _ => Err(SecretStoreError::IO("Invalid command result".to_string())),
+    }
+  }
+}
+
+impl From<SecretStoreResult<Option<SecretAttachment>>> for CommandResult {
+  fn from(result: SecretStoreResult<Option<SecretAttachment>>) -> Self {
+    match result {
+      Ok(Some(value)) => CommandResult::Attachment(value),
+      Ok(None) => CommandResult::Void,
+      Err(error) => CommandResult::SecretStoreError(error),
+    }
+  }
+}
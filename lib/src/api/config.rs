@@ -1,16 +1,207 @@
 use serde::{Deserialize, Serialize};
 use zeroize::Zeroize;
 
+use super::{UrlTagRule, DEFAULT_MAX_ATTACHMENT_SIZE};
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Zeroize)]
 #[cfg_attr(feature = "with_specta", derive(specta::Type))]
 #[zeroize(drop)]
 pub struct StoreConfig {
   pub name: String,
+  /// Url of the store, e.g. `multilane+file:///path/to/store`.
+  ///
+  /// Sled stores (`multilane+sled:///path/to/db`) can be encrypted at rest via `?encrypted=true`, the
+  /// device key is kept in the keyring of the OS. On systems without a keyring a `key_file=<path>` can be
+  /// given instead, which only protects the database if the key file is not on the same disk.
   pub store_url: String,
   pub remote_url: Option<String>,
   #[serde(default)]
   pub sync_interval_sec: u32,
+  /// Bandwidth limit of the synchronization with the remote (in bytes per second, 0 = unlimited)
+  #[serde(default)]
+  pub sync_max_rate: u64,
+  /// Number of blocks transferred concurrently during the synchronization with the remote
+  #[serde(default = "default_sync_concurrency")]
+  pub sync_concurrency: usize,
   pub client_id: String,
   pub autolock_timeout_secs: u64,
   pub default_identity_id: Option<String>,
+  /// Number of failed unlock attempts after which further attempts are delayed (0 = disabled).
+  /// The failed attempts are kept in a local file (see `unlock_throttle_file`), i.e. they are not reset by a restart.
+  #[serde(default)]
+  pub unlock_throttle_attempts: u32,
+  /// File the state of the unlock throttle is kept in
+  /// (None = `<local data dir>/t-rust-less/<name>.unlock-throttle`)
+  #[serde(default)]
+  pub unlock_throttle_file: Option<String>,
+  /// Rules to automatically tag secrets based on their urls (see `retag` command)
+  #[serde(default)]
+  pub url_tag_rules: Vec<UrlTagRule>,
+  /// Recipients of new secrets that do not have any explicit recipients
+  #[serde(default)]
+  pub default_recipients: DefaultRecipients,
+  /// Estimator used for the strength of passwords
+  #[serde(default)]
+  pub strength_estimator: StrengthEstimatorConfig,
+  /// How the content of attachments is stored
+  #[serde(default)]
+  pub attachment_storage: AttachmentStorage,
+  /// Maximum size (in bytes) of a single attachment
+  #[serde(default = "default_max_attachment_size")]
+  pub max_attachment_size: usize,
+  /// Compress the content of secrets with zstd before encryption (blocks written without compression remain readable)
+  #[serde(default)]
+  pub compress_blocks: bool,
+  /// Cipher suite new secret versions are encrypted with (blocks encrypted otherwise remain readable)
+  #[serde(default)]
+  pub cipher_suite: CipherSuite,
+  /// Key derivation preset for sealing the private keys of identities (None = default preset)
+  #[serde(default)]
+  pub key_derivation_preset: Option<u8>,
+  /// Restore the previous content of the clipboard once a secret of this store has been provided
+  /// (None = use the setting of the service)
+  #[serde(default)]
+  pub restore_clipboard: Option<bool>,
+  /// Clear the clipboard if nothing else has been provided for this long (0 = never)
+  #[serde(default = "default_clipboard_timeout_secs")]
+  pub clipboard_timeout_secs: u64,
+  /// File with a device-local secret that is required in addition to the passphrase to unlock the store.
+  /// The pepper is not part of the store, i.e. if this file is lost the store can not be unlocked anymore.
+  #[serde(default)]
+  pub pepper_file: Option<String>,
+  /// Directory with a local copy of the "Have I Been Pwned" password range files (one file per 5 character
+  /// prefix of the SHA-1 hash, as provided by the range API). Passwords are only ever checked against this copy.
+  #[serde(default)]
+  pub breach_dir: Option<String>,
+}
+
+impl StoreConfig {
+  /// Configuration of the store `name` at `store_url` with the default settings for everything else
+  /// (i.e. without remote).
+  pub fn new<N, U, C>(name: N, store_url: U, client_id: C) -> StoreConfig
+  where
+    N: Into<String>,
+    U: Into<String>,
+    C: Into<String>,
+  {
+    StoreConfig {
+      name: name.into(),
+      store_url: store_url.into(),
+      remote_url: None,
+      sync_interval_sec: 0,
+      sync_max_rate: 0,
+      sync_concurrency: DEFAULT_SYNC_CONCURRENCY,
+      client_id: client_id.into(),
+      autolock_timeout_secs: DEFAULT_AUTOLOCK_TIMEOUT_SECS,
+      default_identity_id: None,
+      unlock_throttle_attempts: 0,
+      unlock_throttle_file: None,
+      url_tag_rules: vec![],
+      default_recipients: Default::default(),
+      strength_estimator: Default::default(),
+      attachment_storage: Default::default(),
+      max_attachment_size: DEFAULT_MAX_ATTACHMENT_SIZE,
+      compress_blocks: false,
+      cipher_suite: Default::default(),
+      key_derivation_preset: None,
+      restore_clipboard: None,
+      clipboard_timeout_secs: DEFAULT_CLIPBOARD_TIMEOUT_SECS,
+      pepper_file: None,
+      breach_dir: None,
+    }
+  }
+}
+
+pub const DEFAULT_AUTOLOCK_TIMEOUT_SECS: u64 = 300;
+
+pub const DEFAULT_CLIPBOARD_TIMEOUT_SECS: u64 = 45;
+
+pub const DEFAULT_SYNC_CONCURRENCY: usize = 4;
+
+fn default_sync_concurrency() -> usize {
+  DEFAULT_SYNC_CONCURRENCY
+}
+
+fn default_clipboard_timeout_secs() -> u64 {
+  DEFAULT_CLIPBOARD_TIMEOUT_SECS
+}
+
+fn default_max_attachment_size() -> usize {
+  DEFAULT_MAX_ATTACHMENT_SIZE
+}
+
+/// Default recipient set of new secrets
+///
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Zeroize)]
+#[cfg_attr(feature = "with_specta", derive(specta::Type))]
+#[serde(rename_all = "snake_case")]
+pub enum DefaultRecipients {
+  /// Only the identity adding the secret
+  #[default]
+  Own,
+  /// All identities of the store at the time the secret is added
+  All,
+  /// A fixed set of identities (in addition to the identity adding the secret)
+  Identities(Vec<String>),
+}
+
+/// Storage of the content of attachments
+///
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Zeroize)]
+#[cfg_attr(feature = "with_specta", derive(specta::Type))]
+#[serde(rename_all = "snake_case")]
+pub enum AttachmentStorage {
+  /// As part of the secret version they belong to
+  #[default]
+  Inline,
+  /// Split into chunks that are stored separately, identical chunks are only stored once
+  Chunked,
+  /// Like `Chunked`, but chunks are compressed (with zstd) as well
+  ChunkedCompressed,
+}
+
+/// Cipher suites secret versions are encrypted with
+///
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Zeroize)]
+#[cfg_attr(feature = "with_specta", derive(specta::Type))]
+#[serde(rename_all = "snake_case")]
+pub enum CipherSuite {
+  /// One layer of encryption for each supported suite
+  #[default]
+  All,
+  /// Only RSA with AES-GCM (fastest with AES hardware acceleration)
+  RsaAesGcm,
+  /// Only X25519 key agreement with ChaCha20-Poly1305 (fastest without AES hardware acceleration)
+  X25519Chacha20Poly1305,
+}
+
+/// Estimator of password strengths
+///
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Zeroize)]
+#[cfg_attr(feature = "with_specta", derive(specta::Type))]
+#[serde(rename_all = "snake_case")]
+pub enum StrengthEstimatorConfig {
+  /// Estimate via zxcvbn (i.e. guessability)
+  #[default]
+  Zxcvbn,
+  /// Estimate via the rules of a password policy only
+  Policy(PasswordPolicy),
+  /// Estimate via zxcvbn, but passwords violating the policy have a score of 0
+  Combined(PasswordPolicy),
+}
+
+/// Rules a password has to satisfy
+///
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Zeroize)]
+#[cfg_attr(feature = "with_specta", derive(specta::Type))]
+pub struct PasswordPolicy {
+  /// Minimum number of characters
+  #[serde(default)]
+  pub min_length: u32,
+  /// Minimum number of character classes (lower case, upper case, digits, others)
+  #[serde(default)]
+  pub min_char_classes: u8,
+  /// File containing banned passwords (one per line, matched case-insensitive)
+  #[serde(default)]
+  pub banned_list_file: Option<String>,
 }
@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+use super::{SecretVersion, PROPERTY_TOTP_URL};
+
+/// Match of a content search (see `SecretListFilter::content`) in a property of a secret.
+///
+/// This only refers to the matching range, the content itself has to be fetched separately.
+///
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Zeroize)]
+#[cfg_attr(feature = "with_specta", derive(specta::Type))]
+#[zeroize(drop)]
+pub struct ContentHighlight {
+  pub property: String,
+  /// Position (in chars) of the first matching char
+  pub start: usize,
+  /// Position (in chars) after the last matching char
+  pub end: usize,
+}
+
+/// Find all (case-insensitive) occurrences of `query` in the properties of a secret version.
+///
+/// Passwords and TOTP urls are never searched.
+pub fn find_content_highlights(version: &SecretVersion, query: &str) -> Vec<ContentHighlight> {
+  let password_properties = version.secret_type.password_properties();
+  let mut highlights = vec![];
+
+  for (property, value) in version.properties.iter() {
+    if property == PROPERTY_TOTP_URL || password_properties.contains(&property) {
+      continue;
+    }
+    highlights.extend(
+      find_occurrences(value, query)
+        .into_iter()
+        .map(|(start, end)| ContentHighlight {
+          property: property.to_string(),
+          start,
+          end,
+        }),
+    );
+  }
+
+  highlights
+}
+
+/// Find all non-overlapping (case-insensitive) occurrences of `query` in `value`, as ranges of chars.
+pub fn find_occurrences(value: &str, query: &str) -> Vec<(usize, usize)> {
+  let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+  if query.is_empty() {
+    return vec![];
+  }
+  // Lowercasing might expand a char, so every lowered char remembers its position in the original value
+  let (lowered, positions): (Vec<char>, Vec<usize>) = value
+    .chars()
+    .enumerate()
+    .flat_map(|(position, c)| c.to_lowercase().map(move |lower| (lower, position)))
+    .unzip();
+  let mut occurrences = vec![];
+  let mut idx = 0;
+
+  while idx + query.len() <= lowered.len() {
+    if lowered[idx..idx + query.len()] == query[..] {
+      occurrences.push((positions[idx], positions[idx + query.len() - 1] + 1));
+      idx += query.len();
+    } else {
+      idx += 1;
+    }
+  }
+
+  occurrences
+}
@@ -0,0 +1,205 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+use zeroize::Zeroize;
+
+use super::{AttachmentStorage, StoreConfig};
+
+/// Placeholder for redacted parts of an url.
+pub const REDACTED: &str = "<redacted>";
+
+/// Query parameters of store urls that are known to be non-sensitive.
+const PUBLIC_QUERY_PARAMETERS: &[&str] = &["encrypted"];
+
+/// Diagnostic dump of a store (see `TrustlessService::diagnose`).
+///
+/// This is meant to be copy-pasted into bug reports, so every field has to be non-sensitive: There
+/// are no property values, passphrases, keys or tokens, only ids, counts and sizes.
+///
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Zeroize)]
+#[cfg_attr(feature = "with_specta", derive(specta::Type))]
+#[zeroize(drop)]
+pub struct Diagnostics {
+  pub version: String,
+  /// Secure memory is locked (i.e. mlock/VirtualLock is permitted), so that secrets are not swapped to disk
+  pub memory_locking: bool,
+  pub store_name: String,
+  /// Url of the store with all credentials redacted
+  pub store_url: String,
+  /// Url of the remote store with all credentials redacted
+  pub remote_url: Option<String>,
+  pub node_id: String,
+  pub sync_interval_sec: u32,
+  pub autolock_timeout_secs: u64,
+  pub unlock_throttle_attempts: u32,
+  pub attachment_storage: AttachmentStorage,
+  pub store: StoreDiagnostics,
+}
+
+/// Diagnostic information gathered from the secrets store and its block store.
+///
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Zeroize)]
+#[cfg_attr(feature = "with_specta", derive(specta::Type))]
+#[zeroize(drop)]
+pub struct StoreDiagnostics {
+  pub locked: bool,
+  /// Cipher suites of the store
+  pub ciphers: Vec<String>,
+  /// Number of distinct data blocks referenced by any change log
+  pub block_count: usize,
+  /// Total size of all (available) data blocks
+  pub total_bytes: u64,
+  /// Number of blocks referenced by change logs, but not available in the store
+  pub missing_blocks: usize,
+  pub rings: Vec<RingDiagnostics>,
+  pub change_logs: Vec<ChangeLogDiagnostics>,
+  pub indexes: Vec<IndexDiagnostics>,
+}
+
+/// Diagnostic information of the ring of an identity.
+///
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Zeroize)]
+#[cfg_attr(feature = "with_specta", derive(specta::Type))]
+#[zeroize(drop)]
+pub struct RingDiagnostics {
+  pub id: String,
+  pub version: u64,
+  /// Number of retained versions
+  pub versions: usize,
+  pub hidden: bool,
+  pub hardware_factor: bool,
+  /// Types of the public keys
+  pub key_types: Vec<String>,
+  /// Key derivation (type and preset) of each private key
+  pub key_derivations: Vec<String>,
+}
+
+/// Summary of the change log of a node.
+///
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Zeroize)]
+#[cfg_attr(feature = "with_specta", derive(specta::Type))]
+#[zeroize(drop)]
+pub struct ChangeLogDiagnostics {
+  pub node: String,
+  pub adds: usize,
+  pub deletes: usize,
+  pub commits: usize,
+}
+
+/// Presence of the index of an identity.
+///
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Zeroize)]
+#[cfg_attr(feature = "with_specta", derive(specta::Type))]
+#[zeroize(drop)]
+pub struct IndexDiagnostics {
+  pub identity_id: String,
+  /// Size of the (encrypted) index block, `None` if there is none
+  pub size: Option<u64>,
+}
+
+impl Diagnostics {
+  pub fn new(store_config: &StoreConfig, store: StoreDiagnostics, memory_locking: bool) -> Diagnostics {
+    Diagnostics {
+      version: env!("CARGO_PKG_VERSION").to_string(),
+      memory_locking,
+      store_name: store_config.name.clone(),
+      store_url: redact_url(&store_config.store_url),
+      remote_url: store_config.remote_url.as_deref().map(redact_url),
+      node_id: store_config.client_id.clone(),
+      sync_interval_sec: store_config.sync_interval_sec,
+      autolock_timeout_secs: store_config.autolock_timeout_secs,
+      unlock_throttle_attempts: store_config.unlock_throttle_attempts,
+      attachment_storage: store_config.attachment_storage,
+      store,
+    }
+  }
+}
+
+/// Redact all credentials of a store url.
+///
+/// User info (e.g. the dropbox token) is replaced entirely, query parameters keep their names only.
+/// Urls that can not be parsed are redacted as a whole.
+///
+pub fn redact_url(url: &str) -> String {
+  let mut parsed = match Url::parse(url) {
+    Ok(parsed) => parsed,
+    Err(_) => return REDACTED.to_string(),
+  };
+
+  if !parsed.username().is_empty() && parsed.set_username(REDACTED).is_err() {
+    return REDACTED.to_string();
+  }
+  if parsed.password().is_some() && parsed.set_password(Some(REDACTED)).is_err() {
+    return REDACTED.to_string();
+  }
+  if parsed.query().is_some() {
+    let query: Vec<(String, String)> = parsed
+      .query_pairs()
+      .map(|(name, value)| {
+        if PUBLIC_QUERY_PARAMETERS.contains(&name.as_ref()) {
+          (name.to_string(), value.to_string())
+        } else {
+          (name.to_string(), REDACTED.to_string())
+        }
+      })
+      .collect();
+    parsed.query_pairs_mut().clear().extend_pairs(query);
+  }
+  parsed.set_fragment(None);
+
+  parsed.to_string()
+}
+
+impl fmt::Display for Diagnostics {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    writeln!(f, "Version            : {}", self.version)?;
+    writeln!(f, "Memory locking     : {}", self.memory_locking)?;
+    writeln!(f, "Store              : {}", self.store_name)?;
+    writeln!(f, "Store url          : {}", self.store_url)?;
+    writeln!(f, "Remote url         : {}", self.remote_url.as_deref().unwrap_or("-"))?;
+    writeln!(f, "Node id            : {}", self.node_id)?;
+    writeln!(f, "Sync interval      : {}s", self.sync_interval_sec)?;
+    writeln!(f, "Autolock timeout   : {}s", self.autolock_timeout_secs)?;
+    writeln!(f, "Unlock throttle    : {}", self.unlock_throttle_attempts)?;
+    writeln!(f, "Attachment storage : {:?}", self.attachment_storage)?;
+    writeln!(f, "Locked             : {}", self.store.locked)?;
+    writeln!(f, "Ciphers            : {}", self.store.ciphers.join(", "))?;
+    writeln!(
+      f,
+      "Blocks             : {} ({} bytes, {} missing)",
+      self.store.block_count, self.store.total_bytes, self.store.missing_blocks
+    )?;
+    writeln!(f, "Rings:")?;
+    for ring in &self.store.rings {
+      writeln!(
+        f,
+        "  {} version {} ({} retained), hidden: {}, hardware factor: {}, keys: {}, derivations: {}",
+        ring.id,
+        ring.version,
+        ring.versions,
+        ring.hidden,
+        ring.hardware_factor,
+        ring.key_types.join(", "),
+        ring.key_derivations.join(", ")
+      )?;
+    }
+    writeln!(f, "Change logs:")?;
+    for change_log in &self.store.change_logs {
+      writeln!(
+        f,
+        "  {} adds: {}, deletes: {}, commits: {}",
+        change_log.node, change_log.adds, change_log.deletes, change_log.commits
+      )?;
+    }
+    writeln!(f, "Indexes:")?;
+    for index in &self.store.indexes {
+      match index.size {
+        Some(size) => writeln!(f, "  {} {} bytes", index.identity_id, size)?,
+        None => writeln!(f, "  {} missing", index.identity_id)?,
+      }
+    }
+
+    Ok(())
+  }
+}
@@ -1,4 +1,4 @@
-use crate::api::Identity;
+use crate::api::{Identity, SyncProgress, SyncReport, ZeroizeDateTime};
 use serde::{Deserialize, Serialize};
 use zeroize::Zeroize;
 
@@ -13,6 +13,9 @@ pub enum EventData {
   },
   StoreLocked {
     store_name: String,
+    /// Store has been locked automatically after being idle for its autolock timeout
+    #[serde(default)]
+    autolock: bool,
   },
   SecretOpened {
     store_name: String,
@@ -28,8 +31,55 @@ pub enum EventData {
     store_name: String,
     identity: Identity,
   },
+  SecretExpiring {
+    store_name: String,
+    secret_id: String,
+    expires_at: ZeroizeDateTime,
+  },
   ClipboardProviding(ClipboardProviding),
+  /// Another application pasted a selection (never contains the value itself)
+  ClipboardPasted {
+    store_name: String,
+    property: String,
+    paste_count: u32,
+  },
   ClipboardDone,
+  /// The clipboard has been cleared because nothing has been provided for the configured timeout
+  ClipboardTimedOut {
+    store_name: String,
+  },
+  /// Content synchronized from a remote has been added to the index of an unlocked store
+  /// (i.e. lists should be refreshed)
+  StoreContentChanged {
+    store_name: String,
+  },
+  /// Synchronization of a store with its remote is making progress
+  SyncProgress {
+    store_name: String,
+    progress: SyncProgress,
+  },
+  /// Synchronization of a store with its remote has finished (`report` contains the errors of an
+  /// incomplete synchronization)
+  SyncCompleted {
+    store_name: String,
+    report: SyncReport,
+  },
+  /// Secrets have been modified concurrently on different nodes (both versions are kept and merged,
+  /// though the user might want to review them)
+  ConcurrentVersions {
+    store_name: String,
+    secret_ids: Vec<String>,
+  },
+  /// Synchronization of a store with its remote has been aborted
+  SyncFailed {
+    store_name: String,
+    error: String,
+  },
+  /// All stores have been locked by a panic lock
+  PanicLocked {
+    store_names: Vec<String>,
+    wiped_local: bool,
+  },
 }
 
 pub trait EventHub: Send + Sync {
@@ -40,5 +90,8 @@ pub trait EventHub: Send + Sync {
 #[zeroize(drop)]
 pub struct Event {
   pub id: u64,
+  /// Time the event has been queued (events of older services do not have one)
+  #[serde(default = "ZeroizeDateTime::now")]
+  pub timestamp: ZeroizeDateTime,
   pub data: EventData,
 }
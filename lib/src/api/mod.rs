@@ -1,23 +1,37 @@
 use crate::secrets_store_capnp::{self, secret_entry, secret_version_ref};
 use capnp::text_list;
 use chrono::{TimeZone, Utc};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::cmp::Ordering;
 use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::fmt;
 use zeroize::Zeroize;
+mod audit;
 mod command;
 mod config;
+mod content_search;
+mod diagnostics;
 mod event;
+mod panic_lock;
+mod sync_report;
+mod tag_tree;
+mod url_rules;
 mod zeroize_datetime;
 
 #[cfg(test)]
 mod tests;
 
+pub use audit::*;
 pub use command::*;
 pub use config::*;
+pub use content_search::*;
+pub use diagnostics::*;
 pub use event::*;
+pub use panic_lock::*;
+pub use sync_report::*;
+pub use tag_tree::*;
+pub use url_rules::*;
 pub use zeroize_datetime::*;
 
 pub const PROPERTY_USERNAME: &str = "username";
@@ -36,6 +50,23 @@ pub struct Status {
   pub autolock_at: Option<ZeroizeDateTime>,
   pub version: String,
   pub autolock_timeout: u64,
+  /// Unlock attempts are rejected until then (after too many failed attempts)
+  #[serde(default)]
+  pub unlock_blocked_until: Option<ZeroizeDateTime>,
+  /// Secrets are locked into RAM by the service (i.e. are never swapped to disk)
+  #[serde(default)]
+  pub memory_locking: bool,
+}
+
+/// Capabilities of the service and the hardware it is running on
+///
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Zeroize)]
+#[cfg_attr(feature = "with_specta", derive(specta::Type))]
+#[zeroize(drop)]
+pub struct Capabilities {
+  pub version: String,
+  pub aes_hardware_acceleration: bool,
+  pub preferred_cipher: String,
 }
 
 /// An Identity that might be able to unlock a
@@ -49,6 +80,9 @@ pub struct Identity {
   pub name: String,
   pub email: String,
   pub hidden: bool,
+  /// Unlocking requires a hardware authenticator in addition to the passphrase
+  #[serde(default)]
+  pub hardware_factor: bool,
 }
 
 impl std::fmt::Display for Identity {
@@ -131,23 +165,87 @@ impl fmt::Display for SecretType {
   }
 }
 
+impl std::str::FromStr for SecretType {
+  type Err = String;
+
+  /// Parse the serialized (lowercase) name of a secret type, case is ignored
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "login" => Ok(SecretType::Login),
+      "note" => Ok(SecretType::Note),
+      "licence" => Ok(SecretType::Licence),
+      "wlan" => Ok(SecretType::Wlan),
+      "password" => Ok(SecretType::Password),
+      "other" => Ok(SecretType::Other),
+      _ => Err(format!("Invalid secret type: {}", s)),
+    }
+  }
+}
+
+/// How the `tags` of a SecretListFilter are combined.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "with_specta", derive(specta::Type))]
+#[serde(rename_all = "lowercase")]
+pub enum TagMatch {
+  /// Secret has to be tagged with all tags of the filter
+  #[default]
+  All,
+  /// Secret has to be tagged with at least one tag of the filter
+  Any,
+}
+
+impl Zeroize for TagMatch {
+  fn zeroize(&mut self) {
+    *self = TagMatch::All
+  }
+}
+
 /// A combination of filter criterias to search for a secret.
 ///
 /// All criterias are supposed to be combined by AND (i.e. all criterias have
 /// to match).
-/// Match on `name` is supposed to be "fuzzy" by some fancy scheme.
+/// Match on `name` is fuzzy: The filter has to be a (case insensitive) subsequence of the name,
+/// consecutive matches and matches at the start of words score higher. Matching entries are ranked
+/// by this score (see `SecretEntryMatch::name_score`).
+/// If `expiring_before` is set only secrets with an expiry date before (or at) the given time
+/// will match.
+/// If `modified_after` and/or `modified_before` are set only secrets whose current version has
+/// been modified in this range (bounds included) will match.
+/// Match on `tags` is exact, they are combined by AND or OR depending on `tag_match`.
 ///
 #[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq, Eq, Zeroize)]
 #[cfg_attr(feature = "with_specta", derive(specta::Type))]
 #[zeroize(drop)]
 pub struct SecretListFilter {
   pub url: Option<String>,
-  pub tag: Option<String>,
+  /// Tags to match (older clients send a single optional `tag` instead)
+  #[serde(default, alias = "tag", deserialize_with = "deserialize_filter_tags")]
+  pub tags: Vec<String>,
+  #[serde(default)]
+  pub tag_match: TagMatch,
   #[serde(rename = "type")]
   pub secret_type: Option<SecretType>,
   pub name: Option<String>,
   #[serde(default)]
   pub deleted: bool,
+  pub expiring_before: Option<ZeroizeDateTime>,
+  #[serde(default)]
+  pub modified_after: Option<ZeroizeDateTime>,
+  #[serde(default)]
+  pub modified_before: Option<ZeroizeDateTime>,
+  /// Group the matching entries by their hierarchical tags (see `SecretList::tag_tree`)
+  #[serde(default)]
+  pub group_by_tag: bool,
+  /// Case-insensitive search in the property values (except passwords) of the current versions.
+  /// This has to decrypt every secret matching the other filters, i.e. it is considerably slower.
+  #[serde(default)]
+  pub content: Option<String>,
+  /// Number of matching entries to skip (in the order of the list)
+  #[serde(default)]
+  pub offset: usize,
+  /// Maximum number of entries to return, all remaining entries if not set
+  #[serde(default)]
+  pub limit: Option<usize>,
 }
 
 /// SecretEntry contains all the information of a secrets that should be
@@ -170,6 +268,8 @@ pub struct SecretEntry {
   pub urls: Vec<String>,
   pub timestamp: ZeroizeDateTime,
   pub deleted: bool,
+  #[serde(default)]
+  pub expires_at: Option<ZeroizeDateTime>,
 }
 
 impl SecretEntry {
@@ -190,6 +290,10 @@ impl SecretEntry {
         .map(|u| u.and_then(|u| Ok(u.to_string()?)))
         .collect::<capnp::Result<Vec<String>>>()?,
       deleted: reader.get_deleted(),
+      expires_at: match reader.get_expires_at() {
+        0 => None,
+        expires_at => Some(Utc.timestamp_millis_opt(expires_at).unwrap().into()),
+      },
     })
   }
 
@@ -207,6 +311,7 @@ impl SecretEntry {
       urls.set(idx as u32, url)
     }
     builder.set_deleted(self.deleted);
+    builder.set_expires_at(self.expires_at.map(|e| e.timestamp_millis()).unwrap_or_default());
   }
 }
 
@@ -231,6 +336,24 @@ impl PartialEq for SecretEntry {
   }
 }
 
+fn deserialize_filter_tags<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+  D: Deserializer<'de>,
+{
+  #[derive(Deserialize)]
+  #[serde(untagged)]
+  enum FilterTags {
+    Single(String),
+    Multiple(Vec<String>),
+  }
+
+  Ok(match Option::<FilterTags>::deserialize(deserializer)? {
+    Some(FilterTags::Single(tag)) => vec![tag],
+    Some(FilterTags::Multiple(tags)) => tags,
+    None => vec![],
+  })
+}
+
 /// Representation of a filter match to a SecretEntry.
 ///
 /// For the most part this is just the entry itself with some additional information
@@ -249,6 +372,9 @@ pub struct SecretEntryMatch {
   pub url_highlights: Vec<usize>,
   /// Array of matching tags
   pub tags_highlights: Vec<usize>,
+  /// Matches of the content search (if any)
+  #[serde(default)]
+  pub content_highlights: Vec<ContentHighlight>,
 }
 
 impl Ord for SecretEntryMatch {
@@ -275,12 +401,47 @@ impl PartialEq for SecretEntryMatch {
 /// Convenient wrapper of a list of SecretEntryMatch'es.
 ///
 /// Also contains a unique list of tags of all secrets (e.g. to support autocompletion)
+/// and the ids of all blocks that could not be read (e.g. if the store is not fully synchronized yet).
+/// If the filter requests a page (`offset`/`limit`) only the entries of this page are contained,
+/// `all_tags` and `total` still refer to the whole store respectively all matching entries.
 #[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq, Eq, Zeroize)]
 #[cfg_attr(feature = "with_specta", derive(specta::Type))]
 #[zeroize(drop)]
 pub struct SecretList {
   pub all_tags: Vec<String>,
   pub entries: Vec<SecretEntryMatch>,
+  /// Blocks that could not be read while listing (absent if sent by an older service)
+  #[serde(default)]
+  pub unavailable_blocks: Vec<String>,
+  /// Entries (of the page) grouped by their tags (only if requested by the filter)
+  #[serde(default)]
+  pub tag_tree: Option<TagTree>,
+  /// Number of all matching entries (regardless of pagination)
+  #[serde(default)]
+  pub total: usize,
+}
+
+/// Group of secrets sharing the same password (see `SecretsStore::find_reused_passwords`).
+///
+/// Only the ids of the secrets are contained, never the password itself.
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq, Eq, Zeroize)]
+#[cfg_attr(feature = "with_specta", derive(specta::Type))]
+#[zeroize(drop)]
+pub struct ReuseGroup {
+  pub secret_ids: Vec<String>,
+}
+
+/// Current code of the one-time password of a secret (see `SecretsStore::get_otp`).
+///
+/// For TOTP the code is valid until the `valid_until` timestamp (in seconds), i.e. the end of its
+/// `period`. HOTP codes are valid until used, so both are 0.
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq, Eq, Zeroize)]
+#[cfg_attr(feature = "with_specta", derive(specta::Type))]
+#[zeroize(drop)]
+pub struct OtpToken {
+  pub code: String,
+  pub valid_until: u64,
+  pub period: u32,
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
@@ -339,6 +500,140 @@ pub struct SecretAttachment {
   name: String,
   mime_type: String,
   content: Vec<u8>,
+  /// Chunks of the content if it is stored separately (only used inside the secrets store)
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  chunks: Vec<AttachmentChunk>,
+}
+
+/// Reference to a separately stored chunk of the content of an attachment.
+///
+/// Chunks are encrypted with a key derived from their content, i.e. identical chunks of different
+/// attachments (or secrets) end up in the same block. The key itself is only part of the (encrypted)
+/// secret version referencing the chunk.
+///
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Zeroize)]
+#[cfg_attr(feature = "with_specta", derive(specta::Type))]
+#[zeroize(drop)]
+pub struct AttachmentChunk {
+  pub block_id: String,
+  pub key: Vec<u8>,
+  #[serde(default)]
+  pub compressed: bool,
+}
+
+/// Default maximum size (in bytes) of the content of a single attachment (see `StoreConfig::max_attachment_size`).
+pub const DEFAULT_MAX_ATTACHMENT_SIZE: usize = 4 * 1024 * 1024;
+
+impl SecretAttachment {
+  pub fn new<S: Into<String>, M: Into<String>>(name: S, mime_type: M, content: Vec<u8>) -> SecretAttachment {
+    SecretAttachment {
+      name: name.into(),
+      mime_type: mime_type.into(),
+      content,
+      chunks: vec![],
+    }
+  }
+
+  /// Copy of the attachment with its content replaced by references to chunks.
+  pub(crate) fn with_chunks(&self, chunks: Vec<AttachmentChunk>) -> SecretAttachment {
+    SecretAttachment {
+      name: self.name.clone(),
+      mime_type: self.mime_type.clone(),
+      content: vec![],
+      chunks,
+    }
+  }
+
+  /// Copy of the attachment with its chunks replaced by the actual content.
+  pub(crate) fn with_content(&self, content: Vec<u8>) -> SecretAttachment {
+    SecretAttachment::new(self.name.clone(), self.mime_type.clone(), content)
+  }
+
+  pub(crate) fn chunks(&self) -> &[AttachmentChunk] {
+    &self.chunks
+  }
+
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+
+  pub fn mime_type(&self) -> &str {
+    &self.mime_type
+  }
+
+  pub fn content(&self) -> &[u8] {
+    &self.content
+  }
+
+  /// Guess the mime type of an attachment by its content (magic numbers) and its name
+  /// (file extension), falling back to `application/octet-stream`.
+  pub fn guess_mime_type(name: &str, content: &[u8]) -> &'static str {
+    const MAGIC_NUMBERS: &[(&[u8], &str)] = &[
+      (b"\x89PNG\r\n\x1a\n", "image/png"),
+      (b"\xff\xd8\xff", "image/jpeg"),
+      (b"GIF87a", "image/gif"),
+      (b"GIF89a", "image/gif"),
+      (b"%PDF-", "application/pdf"),
+      (b"PK\x03\x04", "application/zip"),
+      (b"\x1f\x8b", "application/gzip"),
+      (b"-----BEGIN PGP", "application/pgp-keys"),
+      (b"-----BEGIN ", "application/x-pem-file"),
+    ];
+
+    if let Some((_, mime_type)) = MAGIC_NUMBERS.iter().find(|(magic, _)| content.starts_with(magic)) {
+      return mime_type;
+    }
+
+    let extension = name
+      .rsplit_once('.')
+      .map(|(_, ext)| ext.to_lowercase())
+      .unwrap_or_default();
+    match extension.as_str() {
+      "txt" => "text/plain",
+      "json" => "application/json",
+      "pem" | "crt" | "key" => "application/x-pem-file",
+      "asc" | "gpg" => "application/pgp-keys",
+      "png" => "image/png",
+      "jpg" | "jpeg" => "image/jpeg",
+      "pdf" => "application/pdf",
+      _ if std::str::from_utf8(content).is_ok() => "text/plain",
+      _ => "application/octet-stream",
+    }
+  }
+
+  /// Common file extension for the mime type of the attachment (if there is one).
+  pub fn default_extension(&self) -> Option<&'static str> {
+    match self.mime_type.as_str() {
+      "text/plain" => Some("txt"),
+      "application/json" => Some("json"),
+      "application/x-pem-file" => Some("pem"),
+      "application/pgp-keys" => Some("asc"),
+      "image/png" => Some("png"),
+      "image/jpeg" => Some("jpg"),
+      "image/gif" => Some("gif"),
+      "application/pdf" => Some("pdf"),
+      "application/zip" => Some("zip"),
+      "application/gzip" => Some("gz"),
+      _ => None,
+    }
+  }
+}
+
+/// A field of a Secret that has been modified by concurrent versions in different ways.
+///
+/// Two versions are considered concurrent if they have been derived from the same parent
+/// version. If possible concurrent versions are merged, otherwise the value of the latest
+/// version wins and the conflict is reported for the user to resolve.
+///
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Zeroize)]
+#[cfg_attr(feature = "with_specta", derive(specta::Type))]
+#[zeroize(drop)]
+pub struct SecretMergeConflict {
+  /// Name of the field, properties are prefixed with `properties.`, attachments with
+  /// `attachments.`
+  pub field: String,
+  /// Block ids of the conflicting versions
+  pub block_ids: Vec<String>,
 }
 
 /// SecretVersion holds all information of a specific version of a secret.
@@ -392,9 +687,34 @@ pub struct SecretVersion {
   /// to change the Secret and create a new version without the recipient.
   #[serde(default)]
   pub recipients: Vec<String>,
+  /// Optional date when the Secret should be rotated (e.g. a password that has to be changed
+  /// regularly or a licence that runs out).
+  #[serde(default)]
+  pub expires_at: Option<ZeroizeDateTime>,
+  /// Block id of the version this version was derived from (if known).
+  /// This is used to detect concurrent modifications of a Secret (e.g. on different devices
+  /// without synchronization in between).
+  #[serde(default)]
+  pub parent_block_id: Option<String>,
+  /// Id of the identity that has added this version (if known).
+  /// This is set by the store when the version is added, old versions might not have it.
+  #[serde(default)]
+  pub modified_by: Option<String>,
 }
 
 impl SecretVersion {
+  /// Inputs for the estimation of password strengths (see `PasswordEstimator`), i.e. everything a
+  /// password of this secret should not be derived from: name, username, urls and tags.
+  pub fn strength_inputs(&self) -> Vec<&str> {
+    let mut inputs = vec![self.name.as_str()];
+
+    inputs.extend(self.properties.get(PROPERTY_USERNAME).map(String::as_str));
+    inputs.extend(self.urls.iter().map(String::as_str));
+    inputs.extend(self.tags.iter().map(String::as_str));
+
+    inputs
+  }
+
   pub fn to_entry_builder(&self, mut builder: secret_entry::Builder) -> capnp::Result<()> {
     builder.set_id(&self.secret_id);
     builder.set_timestamp(self.timestamp.timestamp_millis());
@@ -403,6 +723,7 @@ impl SecretVersion {
     set_text_list(builder.reborrow().init_tags(self.tags.len() as u32), &self.tags)?;
     set_text_list(builder.reborrow().init_urls(self.urls.len() as u32), &self.urls)?;
     builder.set_deleted(self.deleted);
+    builder.set_expires_at(self.expires_at.map(|e| e.timestamp_millis()).unwrap_or_default());
     Ok(())
   }
 }
@@ -431,6 +752,8 @@ pub struct PasswordStrength {
 pub struct SecretVersionRef {
   pub block_id: String,
   pub timestamp: ZeroizeDateTime,
+  #[serde(default)]
+  pub modified_by: Option<String>,
 }
 
 impl SecretVersionRef {
@@ -438,18 +761,30 @@ impl SecretVersionRef {
     Ok(SecretVersionRef {
       block_id: reader.get_block_id()?.to_string()?,
       timestamp: Utc.timestamp_millis_opt(reader.get_timestamp()).unwrap().into(),
+      modified_by: if reader.has_modified_by() {
+        Some(reader.get_modified_by()?.to_string()?)
+      } else {
+        None
+      },
     })
   }
 
   pub fn to_builder(&self, mut builder: secret_version_ref::Builder) {
     builder.set_block_id(&self.block_id);
     builder.set_timestamp(self.timestamp.timestamp_millis());
+    if let Some(modified_by) = &self.modified_by {
+      builder.set_modified_by(modified_by);
+    }
   }
 }
 
 impl std::fmt::Display for SecretVersionRef {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-    write!(f, "{}", self.timestamp.format("%Y-%m-%d %H:%M:%S"))
+    write!(f, "{}", self.timestamp.format("%Y-%m-%d %H:%M:%S"))?;
+    if let Some(modified_by) = &self.modified_by {
+      write!(f, " by {}", modified_by)?;
+    }
+    Ok(())
   }
 }
 
@@ -466,6 +801,9 @@ pub struct Secret {
   pub current_block_id: String,
   pub versions: Vec<SecretVersionRef>,
   pub password_strengths: HashMap<String, PasswordStrength>,
+  /// Conflicts of concurrent versions that were merged into `current`.
+  #[serde(default)]
+  pub merge_conflicts: Vec<SecretMergeConflict>,
 }
 
 impl Zeroize for Secret {
@@ -476,6 +814,7 @@ impl Zeroize for Secret {
     self.current_block_id.zeroize();
     self.versions.zeroize();
     self.password_strengths.values_mut().for_each(Zeroize::zeroize);
+    self.merge_conflicts.zeroize();
   }
 }
 
@@ -498,6 +837,12 @@ pub struct PasswordGeneratorCharsParam {
   pub require_symbol: bool,
   pub exclude_similar: bool,
   pub exclude_ambiguous: bool,
+  /// Symbols to use instead of the default ones (only ascii characters)
+  #[serde(default)]
+  pub symbol_set: Option<String>,
+  /// Characters that are never used (regardless of their category)
+  #[serde(default)]
+  pub exclude_chars: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Zeroize)]
@@ -506,6 +851,18 @@ pub struct PasswordGeneratorCharsParam {
 pub struct PasswordGeneratorWordsParam {
   pub num_words: u8,
   pub delim: char,
+  /// File with a custom wordlist (one word per line) to use instead of the built-in one
+  #[serde(default)]
+  pub wordlist_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Zeroize)]
+#[cfg_attr(feature = "with_specta", derive(specta::Type))]
+#[zeroize(drop)]
+pub struct PasswordGeneratorPronounceableParam {
+  pub num_chars: u8,
+  pub include_number: bool,
+  pub include_symbol: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Zeroize)]
@@ -515,6 +872,11 @@ pub struct PasswordGeneratorWordsParam {
 pub enum PasswordGeneratorParam {
   Chars(PasswordGeneratorCharsParam),
   Words(PasswordGeneratorWordsParam),
+  Pronounceable(PasswordGeneratorPronounceableParam),
+  /// Numeric PIN of `length` digits
+  Pin {
+    length: u8,
+  },
 }
 
 pub fn set_text_list<I, S>(mut text_list: text_list::Builder, texts: I) -> capnp::Result<()>
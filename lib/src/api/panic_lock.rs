@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+/// Outcome of a panic lock (see `TrustlessService::panic_lock`).
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Zeroize)]
+#[cfg_attr(feature = "with_specta", derive(specta::Type))]
+#[zeroize(drop)]
+pub struct PanicLockReport {
+  /// Names of all stores that have been locked
+  pub locked_stores: Vec<String>,
+  /// Names of stores whose local index blocks have been wiped
+  pub wiped_indexes: Vec<String>,
+  pub clipboard_cleared: bool,
+  /// All session keys have been dropped, i.e. no opened store is unlocked anymore
+  pub sessions_cleared: bool,
+}
@@ -0,0 +1,80 @@
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+/// Outcome of a synchronization of a store with its remote.
+///
+/// Failures of individual blocks or rings do not abort the synchronization, they are collected
+/// in `errors` instead.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Zeroize)]
+#[cfg_attr(feature = "with_specta", derive(specta::Type))]
+#[zeroize(drop)]
+pub struct SyncReport {
+  pub blocks_pushed: u64,
+  pub blocks_pulled: u64,
+  /// Blocks that were already stored locally by a previous (partial) synchronization, i.e. that did not
+  /// have to be downloaded again
+  #[serde(default)]
+  pub blocks_resumed: u64,
+  pub rings_pushed: u64,
+  pub rings_pulled: u64,
+  /// Ids of rings that could not be stored due to a conflicting version on the other side
+  pub ring_conflicts: Vec<String>,
+  pub errors: Vec<SyncError>,
+  /// Bandwidth limit of the transfers (in bytes per second, 0 = unlimited)
+  pub max_rate: u64,
+  /// Ids of all blocks pulled from the remote including the resumed ones (only required to check for concurrent versions)
+  #[serde(skip)]
+  pub pulled_block_ids: Vec<String>,
+  /// Ids of secrets that have concurrent versions (derived from the same parent on different nodes)
+  /// after this synchronization. This can only be checked if the store is unlocked.
+  #[serde(default)]
+  pub concurrent_secrets: Vec<String>,
+}
+
+impl SyncReport {
+  /// Check if the synchronization was complete
+  pub fn is_success(&self) -> bool {
+    self.ring_conflicts.is_empty() && self.errors.is_empty()
+  }
+
+  /// Check if anything has been pulled to the local store
+  pub fn has_local_changes(&self) -> bool {
+    self.blocks_pulled > 0 || self.blocks_resumed > 0 || self.rings_pulled > 0
+  }
+}
+
+/// Part of the synchronization that is currently running
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "with_specta", derive(specta::Type))]
+#[serde(rename_all = "snake_case")]
+pub enum SyncStep {
+  Rings,
+  Blocks,
+}
+
+impl Zeroize for SyncStep {
+  fn zeroize(&mut self) {
+    *self = SyncStep::Rings
+  }
+}
+
+/// Progress of a running synchronization (i.e. one per transferred ring or block)
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Zeroize)]
+#[cfg_attr(feature = "with_specta", derive(specta::Type))]
+#[zeroize(drop)]
+pub struct SyncProgress {
+  pub step: SyncStep,
+  /// Number of transfers of this step that are not finished yet
+  pub remaining: u64,
+  pub message: String,
+}
+
+/// Failure to synchronize data of a specific node
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Zeroize)]
+#[cfg_attr(feature = "with_specta", derive(specta::Type))]
+#[zeroize(drop)]
+pub struct SyncError {
+  /// Node that originated the affected data
+  pub node: String,
+  pub error: String,
+}
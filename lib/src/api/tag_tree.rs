@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use zeroize::Zeroize;
+
+use super::SecretEntryMatch;
+
+/// Separator of the segments of hierarchical tags, e.g. `work/aws/prod`.
+pub const TAG_SEPARATOR: char = '/';
+
+/// Split a tag into the segments of its hierarchy.
+///
+/// Segments are trimmed and empty segments are dropped, i.e. `work//aws/` is the same as `work/aws`.
+pub fn split_tag(tag: &str) -> Vec<&str> {
+  tag
+    .split(TAG_SEPARATOR)
+    .map(str::trim)
+    .filter(|segment| !segment.is_empty())
+    .collect()
+}
+
+/// Entries of a secret list grouped by their (hierarchical) tags.
+///
+/// An entry with multiple tags appears in every branch of its tags.
+///
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq, Eq, Zeroize)]
+#[cfg_attr(feature = "with_specta", derive(specta::Type))]
+#[zeroize(drop)]
+pub struct TagTree {
+  /// Top level tags, sorted by name
+  pub roots: Vec<TagTreeNode>,
+  /// Ids of entries without any (non-empty) tag
+  pub untagged: Vec<String>,
+}
+
+/// A segment of a hierarchical tag.
+///
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq, Eq, Zeroize)]
+#[cfg_attr(feature = "with_specta", derive(specta::Type))]
+#[zeroize(drop)]
+pub struct TagTreeNode {
+  /// Name of the segment
+  pub name: String,
+  /// Full (normalized) tag up to this segment
+  pub path: String,
+  /// Number of distinct entries in this node and all its children
+  pub count: usize,
+  /// Ids of entries tagged with exactly this path (in the order of the list)
+  pub entries: Vec<String>,
+  /// Sub-tags, sorted by name
+  pub children: Vec<TagTreeNode>,
+}
+
+#[derive(Default)]
+struct NodeBuilder {
+  entries: Vec<String>,
+  all_entries: BTreeSet<String>,
+  children: BTreeMap<String, NodeBuilder>,
+}
+
+impl NodeBuilder {
+  fn add(&mut self, segments: &[&str], entry_id: &str) {
+    self.all_entries.insert(entry_id.to_string());
+    match segments.split_first() {
+      Some((first, rest)) => self.children.entry(first.to_string()).or_default().add(rest, entry_id),
+      None if !self.entries.iter().any(|id| id == entry_id) => self.entries.push(entry_id.to_string()),
+      None => (),
+    }
+  }
+
+  fn build(self, name: String, parent_path: Option<&str>) -> TagTreeNode {
+    let path = match parent_path {
+      Some(parent_path) => format!("{}{}{}", parent_path, TAG_SEPARATOR, name),
+      None => name.clone(),
+    };
+    let children = Self::build_children(self.children, Some(&path));
+
+    TagTreeNode {
+      name,
+      count: self.all_entries.len(),
+      entries: self.entries,
+      children,
+      path,
+    }
+  }
+
+  fn build_children(children: BTreeMap<String, NodeBuilder>, parent_path: Option<&str>) -> Vec<TagTreeNode> {
+    children
+      .into_iter()
+      .map(|(name, child)| child.build(name, parent_path))
+      .collect()
+  }
+}
+
+impl TagTree {
+  /// Group entries by the hierarchy of their tags.
+  pub fn build(entries: &[SecretEntryMatch]) -> TagTree {
+    let mut root = NodeBuilder::default();
+    let mut untagged = vec![];
+
+    for entry_match in entries {
+      let entry = &entry_match.entry;
+      let mut tagged = false;
+
+      for tag in &entry.tags {
+        let segments = split_tag(tag);
+
+        if !segments.is_empty() {
+          root.add(&segments, &entry.id);
+          tagged = true;
+        }
+      }
+      if !tagged {
+        untagged.push(entry.id.clone());
+      }
+    }
+
+    TagTree {
+      roots: NodeBuilder::build_children(root.children, None),
+      untagged,
+    }
+  }
+}
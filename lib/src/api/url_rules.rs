@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+use url::Url;
+use zeroize::Zeroize;
+
+/// Public suffixes consisting of more than one label.
+///
+/// This is by no means the complete public suffix list, just the common cases where taking the
+/// last two labels of a host would end up with a public suffix instead of a registrable domain.
+const MULTI_LABEL_SUFFIXES: &[&str] = &[
+  "ac.uk", "co.uk", "gov.uk", "ltd.uk", "me.uk", "net.uk", "org.uk", "plc.uk", "com.au", "net.au", "org.au", "edu.au",
+  "gov.au", "co.nz", "net.nz", "org.nz", "co.jp", "ne.jp", "or.jp", "ac.jp", "co.kr", "or.kr", "com.br", "net.br",
+  "org.br", "com.cn", "net.cn", "org.cn", "com.mx", "com.tr", "co.in", "net.in", "org.in", "co.za", "org.za", "com.ar",
+  "com.sg", "com.hk", "com.tw", "co.il", "com.pl", "co.at", "or.at",
+];
+
+/// Rule to derive tags from the urls of a secret.
+///
+/// * `*.example.com` matches `example.com` and all its subdomains
+/// * `example.com` matches all hosts with the registrable domain `example.com` if it is a
+///   registrable domain itself, otherwise only the exact host (e.g. `gist.github.com`)
+///
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Zeroize)]
+#[cfg_attr(feature = "with_specta", derive(specta::Type))]
+#[zeroize(drop)]
+pub struct UrlTagRule {
+  pub url_pattern: String,
+  pub tags: Vec<String>,
+}
+
+impl UrlTagRule {
+  pub fn matches_host(&self, host: &str) -> bool {
+    let host = normalize_host(host);
+    let pattern = self.url_pattern.trim();
+
+    match pattern.strip_prefix("*.") {
+      Some(domain) => {
+        let domain = normalize_host(domain);
+        host == domain || host.ends_with(&format!(".{}", domain))
+      }
+      None => {
+        let pattern = url_host(pattern).unwrap_or_else(|| normalize_host(pattern));
+        host == pattern
+          || (registrable_domain(&pattern).as_deref() == Some(pattern.as_str())
+            && registrable_domain(&host).as_deref() == Some(pattern.as_str()))
+      }
+    }
+  }
+
+  pub fn matches_url(&self, url: &str) -> bool {
+    url_host(url).filter(|host| self.matches_host(host)).is_some()
+  }
+}
+
+/// Tags derived from all rules matching any of the `urls` (in order of the rules, without duplicates).
+pub fn derive_tags(rules: &[UrlTagRule], urls: &[String]) -> Vec<String> {
+  let mut tags: Vec<String> = vec![];
+
+  for rule in rules {
+    if urls.iter().any(|url| rule.matches_url(url)) {
+      for tag in &rule.tags {
+        if !tags.contains(tag) {
+          tags.push(tag.clone());
+        }
+      }
+    }
+  }
+
+  tags
+}
+
+/// Tags derived from the rules that are not present in `tags` yet.
+pub fn missing_tags(rules: &[UrlTagRule], urls: &[String], tags: &[String]) -> Vec<String> {
+  derive_tags(rules, urls)
+    .into_iter()
+    .filter(|tag| !tags.contains(tag))
+    .collect()
+}
+
+/// Registrable domain of a host, i.e. the public suffix plus one label
+/// (e.g. `github.com` for `gist.github.com` or `bbc.co.uk` for `www.bbc.co.uk`).
+pub fn registrable_domain(host: &str) -> Option<String> {
+  let host = normalize_host(host);
+  if host.is_empty() || host.parse::<std::net::IpAddr>().is_ok() || !host.contains('.') {
+    return None;
+  }
+  let labels: Vec<&str> = host.split('.').collect();
+  if labels.iter().any(|label| label.is_empty()) {
+    return None;
+  }
+  let suffix_labels =
+    if labels.len() >= 2 && MULTI_LABEL_SUFFIXES.contains(&labels[labels.len() - 2..].join(".").as_str()) {
+      2
+    } else {
+      1
+    };
+  if labels.len() <= suffix_labels {
+    return None;
+  }
+
+  Some(labels[labels.len() - suffix_labels - 1..].join("."))
+}
+
+/// Host part of an url, urls without scheme (e.g. `github.com/login`) are accepted as well.
+pub fn url_host(url: &str) -> Option<String> {
+  let url = url.trim();
+  let parsed = match Url::parse(url) {
+    Ok(parsed) if parsed.has_host() => parsed,
+    _ => Url::parse(&format!("https://{}", url)).ok()?,
+  };
+
+  parsed.host_str().map(normalize_host)
+}
+
+fn normalize_host(host: &str) -> String {
+  host.trim().trim_end_matches('.').to_lowercase()
+}
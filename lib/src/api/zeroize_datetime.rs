@@ -9,6 +9,10 @@ use zeroize::Zeroize;
 pub struct ZeroizeDateTime(DateTime<Utc>);
 
 impl ZeroizeDateTime {
+  pub fn now() -> Self {
+    ZeroizeDateTime(Utc::now())
+  }
+
   pub fn timestamp_millis(&self) -> i64 {
     self.0.timestamp_millis()
   }
@@ -0,0 +1,204 @@
+//! Async variant of the block store.
+//!
+//! Threading model: All `BlockStore` implementations are synchronous and may block the calling
+//! thread for an arbitrary amount of time (in case of remote stores this is network I/O). Local
+//! stores are considered fast enough to be called directly from everywhere, remote stores on the
+//! other hand are only accessed via an `AsyncBlockStore` during synchronization:
+//!
+//! * Inside a tokio runtime (i.e. the daemon) `BlockingAsyncStore` moves each call of a
+//!   synchronous store to the blocking thread pool of the runtime, so that a slow remote only ties
+//!   up a thread of that pool but never a worker thread.
+//! * Without a runtime (e.g. cli without daemon) calls are simply executed in place.
+//! * Synchronous callers (like the `SecretsStore`) can use any `AsyncBlockStore` via `BlockOnStore`.
+//!
+use std::fmt;
+use std::sync::Arc;
+
+use futures::executor::block_on;
+use futures::future::{self, BoxFuture, FutureExt};
+use tokio::runtime::Handle;
+
+use super::{BlockStore, Change, ChangeLog, RingContent, RingId, StoreError, StoreResult};
+use crate::memguard::weak::ZeroingWords;
+
+/// Async counterpart of `BlockStore`, see there for a description of the operations.
+pub trait AsyncBlockStore: fmt::Debug + Send + Sync {
+  fn node_id(&self) -> &str;
+
+  fn list_ring_ids(&self) -> BoxFuture<'_, StoreResult<Vec<RingId>>>;
+
+  fn get_ring<'a>(&'a self, ring_id: &'a str) -> BoxFuture<'a, StoreResult<RingContent>>;
+
+  fn store_ring<'a>(&'a self, ring_id: &'a str, version: u64, raw: &'a [u8]) -> BoxFuture<'a, StoreResult<()>>;
+
+  fn change_logs(&self) -> BoxFuture<'_, StoreResult<Vec<ChangeLog>>>;
+
+  fn get_index<'a>(&'a self, index_id: &'a str) -> BoxFuture<'a, StoreResult<Option<ZeroingWords>>>;
+
+  fn store_index<'a>(&'a self, index_id: &'a str, raw: &'a [u8]) -> BoxFuture<'a, StoreResult<()>>;
+
+  fn add_block<'a>(&'a self, raw: &'a [u8]) -> BoxFuture<'a, StoreResult<String>>;
+
+  fn get_block<'a>(&'a self, block: &'a str) -> BoxFuture<'a, StoreResult<ZeroingWords>>;
+
+  fn commit<'a>(&'a self, commit_id: &'a str, changes: &'a [Change]) -> BoxFuture<'a, StoreResult<()>>;
+
+  fn update_change_log(&self, change_log: ChangeLog) -> BoxFuture<'_, StoreResult<()>>;
+}
+
+/// Adapter for synchronous block stores (i.e. all current backends).
+///
+/// If created inside a tokio runtime all operations are executed on its blocking thread pool,
+/// otherwise they are executed in place.
+pub struct BlockingAsyncStore {
+  store: Arc<dyn BlockStore>,
+  runtime: Option<Handle>,
+}
+
+impl BlockingAsyncStore {
+  pub fn new(store: Arc<dyn BlockStore>) -> BlockingAsyncStore {
+    BlockingAsyncStore {
+      store,
+      runtime: Handle::try_current().ok(),
+    }
+  }
+
+  fn run<T, F>(&self, operation: F) -> BoxFuture<'static, StoreResult<T>>
+  where
+    T: Send + 'static,
+    F: FnOnce(&dyn BlockStore) -> StoreResult<T> + Send + 'static,
+  {
+    let store = self.store.clone();
+
+    match &self.runtime {
+      Some(runtime) => runtime
+        .spawn_blocking(move || operation(store.as_ref()))
+        .map(|result| result.map_err(|err| StoreError::IO(format!("Blocking operation failed: {}", err)))?)
+        .boxed(),
+      None => future::ready(operation(store.as_ref())).boxed(),
+    }
+  }
+}
+
+impl fmt::Debug for BlockingAsyncStore {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "Async {:?}", self.store)
+  }
+}
+
+impl AsyncBlockStore for BlockingAsyncStore {
+  fn node_id(&self) -> &str {
+    self.store.node_id()
+  }
+
+  fn list_ring_ids(&self) -> BoxFuture<'_, StoreResult<Vec<RingId>>> {
+    self.run(|store| store.list_ring_ids())
+  }
+
+  fn get_ring<'a>(&'a self, ring_id: &'a str) -> BoxFuture<'a, StoreResult<RingContent>> {
+    let ring_id = ring_id.to_string();
+    self.run(move |store| store.get_ring(&ring_id))
+  }
+
+  fn store_ring<'a>(&'a self, ring_id: &'a str, version: u64, raw: &'a [u8]) -> BoxFuture<'a, StoreResult<()>> {
+    let ring_id = ring_id.to_string();
+    let raw = ZeroingWords::from(raw);
+    self.run(move |store| store.store_ring(&ring_id, version, &raw))
+  }
+
+  fn change_logs(&self) -> BoxFuture<'_, StoreResult<Vec<ChangeLog>>> {
+    self.run(|store| store.change_logs())
+  }
+
+  fn get_index<'a>(&'a self, index_id: &'a str) -> BoxFuture<'a, StoreResult<Option<ZeroingWords>>> {
+    let index_id = index_id.to_string();
+    self.run(move |store| store.get_index(&index_id))
+  }
+
+  fn store_index<'a>(&'a self, index_id: &'a str, raw: &'a [u8]) -> BoxFuture<'a, StoreResult<()>> {
+    let index_id = index_id.to_string();
+    let raw = ZeroingWords::from(raw);
+    self.run(move |store| store.store_index(&index_id, &raw))
+  }
+
+  fn add_block<'a>(&'a self, raw: &'a [u8]) -> BoxFuture<'a, StoreResult<String>> {
+    let raw = ZeroingWords::from(raw);
+    self.run(move |store| store.add_block(&raw))
+  }
+
+  fn get_block<'a>(&'a self, block: &'a str) -> BoxFuture<'a, StoreResult<ZeroingWords>> {
+    let block = block.to_string();
+    self.run(move |store| store.get_block(&block))
+  }
+
+  fn commit<'a>(&'a self, commit_id: &'a str, changes: &'a [Change]) -> BoxFuture<'a, StoreResult<()>> {
+    let commit_id = commit_id.to_string();
+    let changes = changes.to_vec();
+    self.run(move |store| store.commit(&commit_id, &changes))
+  }
+
+  fn update_change_log(&self, change_log: ChangeLog) -> BoxFuture<'_, StoreResult<()>> {
+    self.run(move |store| store.update_change_log(change_log))
+  }
+}
+
+/// Adapter to use an `AsyncBlockStore` from synchronous code.
+///
+/// Note: This blocks the calling thread until the operation is complete, so it should not be used
+/// on a worker thread of an async runtime.
+#[derive(Debug)]
+pub struct BlockOnStore {
+  store: Arc<dyn AsyncBlockStore>,
+}
+
+impl BlockOnStore {
+  pub fn new(store: Arc<dyn AsyncBlockStore>) -> BlockOnStore {
+    BlockOnStore { store }
+  }
+}
+
+impl BlockStore for BlockOnStore {
+  fn node_id(&self) -> &str {
+    self.store.node_id()
+  }
+
+  fn list_ring_ids(&self) -> StoreResult<Vec<RingId>> {
+    block_on(self.store.list_ring_ids())
+  }
+
+  fn get_ring(&self, ring_id: &str) -> StoreResult<RingContent> {
+    block_on(self.store.get_ring(ring_id))
+  }
+
+  fn store_ring(&self, ring_id: &str, version: u64, raw: &[u8]) -> StoreResult<()> {
+    block_on(self.store.store_ring(ring_id, version, raw))
+  }
+
+  fn change_logs(&self) -> StoreResult<Vec<ChangeLog>> {
+    block_on(self.store.change_logs())
+  }
+
+  fn get_index(&self, index_id: &str) -> StoreResult<Option<ZeroingWords>> {
+    block_on(self.store.get_index(index_id))
+  }
+
+  fn store_index(&self, index_id: &str, raw: &[u8]) -> StoreResult<()> {
+    block_on(self.store.store_index(index_id, raw))
+  }
+
+  fn add_block(&self, raw: &[u8]) -> StoreResult<String> {
+    block_on(self.store.add_block(raw))
+  }
+
+  fn get_block(&self, block: &str) -> StoreResult<ZeroingWords> {
+    block_on(self.store.get_block(block))
+  }
+
+  fn commit(&self, commit_id: &str, changes: &[Change]) -> StoreResult<()> {
+    block_on(self.store.commit(commit_id, changes))
+  }
+
+  fn update_change_log(&self, change_log: ChangeLog) -> StoreResult<()> {
+    block_on(self.store.update_change_log(change_log))
+  }
+}
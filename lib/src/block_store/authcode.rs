@@ -0,0 +1,90 @@
+use std::{
+  sync::Arc,
+  thread::{self, JoinHandle},
+};
+
+use log::error;
+use tiny_http::{Header, Response, Server};
+use url::Url;
+
+use crate::block_store::{StoreError, StoreResult};
+
+/// Redirect url of the oauth flows of all cloud stores (has to be registered at the provider).
+pub const REDIRECT_URL: &str = "http://127.0.0.1:9898";
+
+const AUTHCODE_RESPONSE_BODY: &str = r#"
+<!DOCTYPE html>
+<html>
+<head>
+<title>t-rust-less</title>
+</head>
+<body">
+<p style="text-align: center;">&nbsp;</p>
+<p style="text-align: center;">T-Rust-Less is now authenticated to {provider}!</p>
+<p style="text-align: center;">You may now close this window...</p>
+</body>
+</html>
+"#;
+
+pub struct ServerHandle {
+  server: Arc<Server>,
+  join_handle: Option<JoinHandle<Result<String, String>>>,
+}
+
+impl ServerHandle {
+  pub fn wait_for_auth_code(&mut self) -> StoreResult<String> {
+    let join_handle = match self.join_handle.take() {
+      Some(join_handle) => join_handle,
+      None => return Err(StoreError::IO("Already waiting".to_string())),
+    };
+    match join_handle.join() {
+      Ok(Ok(authcode_url)) => Ok(
+        Url::parse(&authcode_url)?
+          .query_pairs()
+          .find_map(|(key, value)| if key == "code" { Some(value.to_string()) } else { None })
+          .ok_or_else(|| StoreError::IO("auth url does not contain code".to_string()))?,
+      ),
+      Ok(Err(err)) => {
+        error!("Failed receiving authcode {}", err);
+        Err(StoreError::IO(err))
+      }
+      Err(err) => {
+        error!("Failed receiving authcode {:?}", err);
+        Err(StoreError::IO(format!("{:?}", err)))
+      }
+    }
+  }
+}
+
+impl Drop for ServerHandle {
+  fn drop(&mut self) {
+    self.server.unblock();
+  }
+}
+
+/// Start a local http server receiving the redirect with the authcode of an oauth flow.
+///
+/// `provider` is only used to tell the user where t-rust-less got authenticated.
+pub fn start_authcode_server(provider: &str) -> StoreResult<ServerHandle> {
+  let server = Arc::new(Server::http("127.0.0.1:9898").map_err(|e| StoreError::IO(format!("{}", e)))?);
+  let server_cloned = server.clone();
+  let response_body = AUTHCODE_RESPONSE_BODY.replace("{provider}", provider);
+
+  let join_handle = thread::spawn(move || {
+    let request = server_cloned.recv().map_err(|e| format!("{}", e))?;
+    let url = format!("{}{}", REDIRECT_URL, request.url());
+    request
+      .respond(
+        Response::from_data(response_body)
+          .with_header(Header::from_bytes(&b"Content-Type"[..], &b"text/html; charset=UTF-8"[..]).unwrap()),
+      )
+      .ok();
+
+    Ok(url)
+  });
+
+  Ok(ServerHandle {
+    server,
+    join_handle: Some(join_handle),
+  })
+}
@@ -1,5 +1,8 @@
 mod initialize;
 
+#[cfg(test)]
+mod retry_tests;
+
 use std::{
   collections::{HashMap, VecDeque},
   io,
@@ -7,8 +10,12 @@ use std::{
   io::BufReader,
   io::Read,
   io::Write,
+  thread,
+  time::Duration,
 };
 
+use log::warn;
+
 pub use initialize::*;
 
 use dropbox_sdk::{
@@ -23,24 +30,78 @@ use crate::{block_store::generate_block_id, memguard::weak::ZeroingWords};
 use super::{BlockStore, Change, ChangeLog, Operation, RingContent, RingId, StoreError, StoreResult};
 
 pub const APP_KEY: &str = "3q0sff542l6r3ly";
-
-pub struct DropboxBlockStore {
+/// Content larger than this is uploaded in chunks of this size via an upload session.
+const UPLOAD_CHUNK_SIZE: usize = 8 * 1024 * 1024;
+/// Default number of attempts of a request failing with a transient error (5xx or rate-limited).
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+/// Initial delay between attempts, doubled after every failed attempt.
+const DEFAULT_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+pub struct DropboxBlockStore<C = UserAuthDefaultClient> {
   node_id: String,
   name: String,
-  client: UserAuthDefaultClient,
+  client: C,
+  max_attempts: u32,
+  retry_delay: Duration,
+  upload_chunk_size: usize,
 }
 
 impl DropboxBlockStore {
   pub fn new(token: &str, name: &str, node_id: &str) -> StoreResult<DropboxBlockStore> {
     let authorization = Authorization::load(APP_KEY.to_string(), token)
       .ok_or_else(|| StoreError::IO("Invalid dropbox token".to_string()))?;
-    let client = UserAuthDefaultClient::new(authorization);
 
-    Ok(DropboxBlockStore {
+    Ok(DropboxBlockStore::with_client(
+      UserAuthDefaultClient::new(authorization),
+      name,
+      node_id,
+    ))
+  }
+}
+
+impl<C: UserAuthClient> DropboxBlockStore<C> {
+  pub fn with_client(client: C, name: &str, node_id: &str) -> DropboxBlockStore<C> {
+    DropboxBlockStore {
       node_id: node_id.to_string(),
       name: name.to_string(),
       client,
-    })
+      max_attempts: DEFAULT_MAX_ATTEMPTS,
+      retry_delay: DEFAULT_RETRY_DELAY,
+      upload_chunk_size: UPLOAD_CHUNK_SIZE,
+    }
+  }
+
+  /// Maximum number of attempts of a request failing with a transient error, 1 disables retries.
+  pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+    self.max_attempts = max_attempts.max(1);
+    self
+  }
+
+  /// Initial delay between attempts (unless Dropbox tells otherwise via `Retry-After`).
+  pub fn with_retry_delay(mut self, retry_delay: Duration) -> Self {
+    self.retry_delay = retry_delay;
+    self
+  }
+
+  /// Invoke a request, retrying on transient errors with exponential backoff.
+  fn retry<T>(&self, mut request: impl FnMut() -> dropbox_sdk::Result<T>) -> dropbox_sdk::Result<T> {
+    let mut attempt = 1;
+    loop {
+      match request() {
+        Err(err) if attempt < self.max_attempts => match retry_delay(&err, self.retry_delay, attempt) {
+          Some(delay) => {
+            warn!(
+              "Dropbox request failed (attempt {}), retry in {:?}: {}",
+              attempt, delay, err
+            );
+            thread::sleep(delay);
+            attempt += 1;
+          }
+          None => return Err(err),
+        },
+        result => return result,
+      }
+    }
   }
 
   fn block_path(&self, block_id: &str) -> StoreResult<String> {
@@ -54,16 +115,13 @@ impl DropboxBlockStore {
     let (_, maybe_content) = self.download_stream(format!("/{}/logs/{}", self.name, node_id))?;
     match maybe_content {
       Some(content) => Self::parse_change_log(node_id, content),
-      _ => Ok(ChangeLog {
-        node: node_id.to_string(),
-        changes: vec![],
-      }),
+      _ => Ok(ChangeLog::new(node_id)),
     }
   }
 
   #[allow(clippy::type_complexity)]
   fn download_stream(&self, path: String) -> StoreResult<(Option<usize>, Option<Box<dyn Read>>)> {
-    match files::download(&self.client, &files::DownloadArg::new(path), None, None)? {
+    match self.retry(|| files::download(&self.client, &files::DownloadArg::new(path.clone()), None, None))? {
       Ok(result) => {
         let content = result.body.ok_or_else(|| StoreError::IO("No body".to_string()))?;
 
@@ -88,6 +146,34 @@ impl DropboxBlockStore {
     }
   }
 
+  /// Upload a file, large files are uploaded via an upload session.
+  ///
+  /// The file only becomes visible once the session is finished, i.e. a failed upload never leaves
+  /// a partial file behind.
+  fn upload(&self, path: String, content: &[u8]) -> StoreResult<()> {
+    if content.len() <= self.upload_chunk_size {
+      self.retry(|| files::upload(&self.client, &files::UploadArg::new(path.clone()), content))??;
+      return Ok(());
+    }
+    let mut chunks = content.chunks(self.upload_chunk_size);
+    let first = chunks.next().unwrap_or_default();
+    let session =
+      self.retry(|| files::upload_session_start(&self.client, &files::UploadSessionStartArg::default(), first))??;
+    let mut cursor = files::UploadSessionCursor::new(session.session_id, first.len() as u64);
+    let last = chunks.next_back().unwrap_or_default();
+
+    for chunk in chunks {
+      self.retry(|| {
+        files::upload_session_append_v2(&self.client, &files::UploadSessionAppendArg::new(cursor.clone()), chunk)
+      })??;
+      cursor.offset += chunk.len() as u64;
+    }
+    let finish = files::UploadSessionFinishArg::new(cursor, files::CommitInfo::new(path));
+    self.retry(|| files::upload_session_finish(&self.client, &finish, last))??;
+
+    Ok(())
+  }
+
   fn parse_change_log<R: Read>(node_id: &str, content: R) -> StoreResult<ChangeLog> {
     let reader = BufReader::new(content);
     let mut change_log = ChangeLog::new(node_id);
@@ -97,6 +183,7 @@ impl DropboxBlockStore {
       match line.split(' ').collect::<Vec<&str>>().as_slice() {
         ["A", block] => change_log.changes.push(Change::new(Operation::Add, *block)),
         ["D", block] => change_log.changes.push(Change::new(Operation::Delete, *block)),
+        ["C", commit_id] => change_log.commits.push(commit_id.to_string()),
         _ => (),
       }
     }
@@ -131,16 +218,32 @@ impl DropboxBlockStore {
   }
 }
 
-impl std::fmt::Debug for DropboxBlockStore {
+/// Delay before the next attempt of a failed request, `None` if the error is not transient.
+///
+/// A `Retry-After` given by Dropbox takes precedence over the exponential backoff.
+fn retry_delay(error: &dropbox_sdk::Error, base_delay: Duration, attempt: u32) -> Option<Duration> {
+  let backoff = base_delay.saturating_mul(1 << (attempt - 1).min(16));
+  match error {
+    dropbox_sdk::Error::RateLimited {
+      retry_after_seconds, ..
+    } if *retry_after_seconds > 0 => Some(Duration::from_secs(*retry_after_seconds as u64)),
+    dropbox_sdk::Error::RateLimited { .. } | dropbox_sdk::Error::ServerError(_) => Some(backoff),
+    dropbox_sdk::Error::UnexpectedHttpError { code, .. } if *code == 429 || *code >= 500 => Some(backoff),
+    _ => None,
+  }
+}
+
+impl<C> std::fmt::Debug for DropboxBlockStore<C> {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     f.debug_struct("DropboxBlockStore")
       .field("node_id", &self.node_id)
       .field("name", &self.name)
+      .field("max_attempts", &self.max_attempts)
       .finish()
   }
 }
 
-impl BlockStore for DropboxBlockStore {
+impl<C: UserAuthClient + Send + Sync> BlockStore for DropboxBlockStore<C> {
   fn node_id(&self) -> &str {
     &self.node_id
   }
@@ -173,8 +276,7 @@ impl BlockStore for DropboxBlockStore {
         ring_id, version
       )));
     }
-    files::upload(&self.client, &files::UploadArg::new(path), raw)??;
-    Ok(())
+    self.upload(path, raw)
   }
 
   fn change_logs(&self) -> StoreResult<Vec<ChangeLog>> {
@@ -200,7 +302,7 @@ impl BlockStore for DropboxBlockStore {
   fn add_block(&self, raw: &[u8]) -> StoreResult<String> {
     let block_id = generate_block_id(raw);
     let path = self.block_path(&block_id)?;
-    files::upload(&self.client, &files::UploadArg::new(path), raw)??;
+    self.upload(path, raw)?;
 
     Ok(block_id)
   }
@@ -212,48 +314,44 @@ impl BlockStore for DropboxBlockStore {
     }
   }
 
-  fn commit(&self, changes: &[Change]) -> StoreResult<()> {
+  fn commit(&self, commit_id: &str, changes: &[Change]) -> StoreResult<()> {
     let mut change_log = match self.download_change_log(&self.node_id) {
       Ok(change_log) => change_log,
       Err(StoreError::InvalidBlock(_)) => ChangeLog::new(&self.node_id),
       Err(err) => return Err(err),
     };
-    if change_log.changes.iter().any(|change| changes.contains(change)) {
-      return Err(StoreError::Conflict("Change already committed".to_string()));
+    if !change_log.check_commit(commit_id, changes)? {
+      // The commit has been applied before (i.e. only the response got lost)
+      return Ok(());
     }
     change_log.changes.extend_from_slice(changes);
+    change_log.commits.push(commit_id.to_string());
 
     let mut buffer = Vec::with_capacity(8192);
-    for change in change_log.changes {
+    for change in &change_log.changes {
       match change.op {
         Operation::Add => writeln!(&mut buffer, "A {}", change.block)?,
         Operation::Delete => writeln!(&mut buffer, "D {}", change.block)?,
       }
     }
-    files::upload(
-      &self.client,
-      &files::UploadArg::new(format!("/{}/logs/{}", self.name, self.node_id)),
-      &buffer,
-    )??;
-
-    Ok(())
+    for commit_id in &change_log.commits {
+      writeln!(&mut buffer, "C {}", commit_id)?;
+    }
+    self.upload(format!("/{}/logs/{}", self.name, self.node_id), &buffer)
   }
 
   fn update_change_log(&self, change_log: ChangeLog) -> StoreResult<()> {
     let mut buffer = Vec::with_capacity(8192);
-    for change in change_log.changes {
+    for change in &change_log.changes {
       match change.op {
         Operation::Add => writeln!(&mut buffer, "A {}", change.block)?,
         Operation::Delete => writeln!(&mut buffer, "D {}", change.block)?,
       }
     }
-    files::upload(
-      &self.client,
-      &files::UploadArg::new(format!("/{}/logs/{}", self.name, change_log.node)),
-      &buffer,
-    )??;
-
-    Ok(())
+    for commit_id in &change_log.commits {
+      writeln!(&mut buffer, "C {}", commit_id)?;
+    }
+    self.upload(format!("/{}/logs/{}", self.name, change_log.node), &buffer)
   }
 }
 
@@ -0,0 +1,221 @@
+use super::{retry_delay, DropboxBlockStore};
+use crate::block_store::{generate_block_id, BlockStore, StoreError};
+use crate::memguard::weak::ZeroingWords;
+use dropbox_sdk::client_trait::{Endpoint, HttpClient, HttpRequestResultRaw, ParamsType, Style};
+use dropbox_sdk::UserAuthClient;
+use spectral::prelude::*;
+use std::io::Cursor;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+const FILE_METADATA: &str = r#"{
+  "name": "block",
+  "id": "id:a4ayc_80_OEAAAAAAAAAXw",
+  "client_modified": "2024-01-01T00:00:00Z",
+  "server_modified": "2024-01-01T00:00:00Z",
+  "rev": "a1c10ce0dd78",
+  "size": 16
+}"#;
+
+/// Client failing the first `failures` requests with `error`, all following requests succeed.
+struct MockClient {
+  failures: u32,
+  error: fn() -> dropbox_sdk::Error,
+  calls: AtomicU32,
+  content: Vec<u8>,
+}
+
+impl MockClient {
+  fn new(failures: u32, error: fn() -> dropbox_sdk::Error) -> MockClient {
+    MockClient {
+      failures,
+      error,
+      calls: AtomicU32::new(0),
+      content: b"0123456789abcdef".to_vec(),
+    }
+  }
+}
+
+impl HttpClient for MockClient {
+  fn request(
+    &self,
+    _endpoint: Endpoint,
+    _style: Style,
+    _function: &str,
+    _params: String,
+    _params_type: ParamsType,
+    _body: Option<&[u8]>,
+    _range_start: Option<u64>,
+    _range_end: Option<u64>,
+  ) -> dropbox_sdk::Result<HttpRequestResultRaw> {
+    if self.calls.fetch_add(1, Ordering::SeqCst) < self.failures {
+      return Err((self.error)());
+    }
+    Ok(HttpRequestResultRaw {
+      result_json: FILE_METADATA.to_string(),
+      content_length: Some(self.content.len() as u64),
+      body: Some(Box::new(Cursor::new(self.content.clone()))),
+    })
+  }
+}
+
+impl UserAuthClient for MockClient {}
+
+/// Client recording all requests, i.e. the function, its (json) parameters and the body.
+#[derive(Default)]
+struct RecordingClient {
+  requests: Mutex<Vec<(String, serde_json::Value, Vec<u8>)>>,
+}
+
+impl HttpClient for RecordingClient {
+  fn request(
+    &self,
+    _endpoint: Endpoint,
+    _style: Style,
+    function: &str,
+    params: String,
+    _params_type: ParamsType,
+    body: Option<&[u8]>,
+    _range_start: Option<u64>,
+    _range_end: Option<u64>,
+  ) -> dropbox_sdk::Result<HttpRequestResultRaw> {
+    self.requests.lock().unwrap().push((
+      function.to_string(),
+      serde_json::from_str(&params).unwrap(),
+      body.unwrap_or_default().to_vec(),
+    ));
+    let result_json = match function {
+      "files/upload_session/start" => r#"{"session_id": "session1"}"#,
+      "files/upload_session/append_v2" => "null",
+      _ => FILE_METADATA,
+    };
+    Ok(HttpRequestResultRaw {
+      result_json: result_json.to_string(),
+      content_length: None,
+      body: None,
+    })
+  }
+}
+
+impl UserAuthClient for RecordingClient {}
+
+fn server_error() -> dropbox_sdk::Error {
+  dropbox_sdk::Error::ServerError("Service Unavailable".to_string())
+}
+
+fn rate_limited() -> dropbox_sdk::Error {
+  dropbox_sdk::Error::RateLimited {
+    reason: dropbox_sdk::auth::RateLimitReason::TooManyRequests,
+    retry_after_seconds: 0,
+  }
+}
+
+fn bad_request() -> dropbox_sdk::Error {
+  dropbox_sdk::Error::BadRequest("Invalid path".to_string())
+}
+
+fn mock_store(client: MockClient) -> DropboxBlockStore<MockClient> {
+  DropboxBlockStore::with_client(client, "test", "node1").with_retry_delay(Duration::from_millis(1))
+}
+
+#[test]
+fn test_retry_transient_errors() {
+  let store = mock_store(MockClient::new(2, server_error));
+
+  assert_that(&store.get_block("0123456789")).is_ok_containing(ZeroingWords::from(&b"0123456789abcdef"[..]));
+  assert_that(&store.client.calls.load(Ordering::SeqCst)).is_equal_to(3);
+
+  let store = mock_store(MockClient::new(2, rate_limited));
+
+  assert_that(&store.add_block(b"0123456789abcdef")).is_ok_containing(generate_block_id(b"0123456789abcdef"));
+  assert_that(&store.client.calls.load(Ordering::SeqCst)).is_equal_to(3);
+}
+
+#[test]
+fn test_retry_gives_up() {
+  let store = mock_store(MockClient::new(3, server_error));
+
+  assert_that(&store.get_block("0123456789"))
+    .is_err()
+    .matches(|error| matches!(error, StoreError::IO(_)));
+  assert_that(&store.client.calls.load(Ordering::SeqCst)).is_equal_to(3);
+
+  let store = mock_store(MockClient::new(2, server_error)).with_max_attempts(1);
+
+  assert_that(&store.get_block("0123456789"))
+    .is_err()
+    .matches(|error| matches!(error, StoreError::IO(_)));
+  assert_that(&store.client.calls.load(Ordering::SeqCst)).is_equal_to(1);
+
+  // Anything else is not supposed to go away on its own
+  let store = mock_store(MockClient::new(1, bad_request));
+
+  assert_that(&store.add_block(b"0123456789abcdef"))
+    .is_err()
+    .matches(|error| matches!(error, StoreError::IO(_)));
+  assert_that(&store.client.calls.load(Ordering::SeqCst)).is_equal_to(1);
+}
+
+#[test]
+fn test_retry_delay() {
+  let base_delay = Duration::from_millis(500);
+
+  assert_that(&retry_delay(&server_error(), base_delay, 1)).is_equal_to(Some(Duration::from_millis(500)));
+  assert_that(&retry_delay(&server_error(), base_delay, 2)).is_equal_to(Some(Duration::from_millis(1000)));
+  assert_that(&retry_delay(&rate_limited(), base_delay, 3)).is_equal_to(Some(Duration::from_millis(2000)));
+  assert_that(&retry_delay(
+    &dropbox_sdk::Error::RateLimited {
+      reason: dropbox_sdk::auth::RateLimitReason::TooManyWriteOperations,
+      retry_after_seconds: 30,
+    },
+    base_delay,
+    1,
+  ))
+  .is_equal_to(Some(Duration::from_secs(30)));
+  assert_that(&retry_delay(
+    &dropbox_sdk::Error::UnexpectedHttpError {
+      code: 503,
+      status: "Service Unavailable".to_string(),
+      json: String::new(),
+    },
+    base_delay,
+    1,
+  ))
+  .is_equal_to(Some(Duration::from_millis(500)));
+  assert_that(&retry_delay(&bad_request(), base_delay, 1)).is_none();
+}
+
+#[test]
+fn test_upload_session() {
+  let mut store = DropboxBlockStore::with_client(RecordingClient::default(), "test", "node1");
+  store.upload_chunk_size = 4;
+
+  store.upload("/test/small".to_string(), b"0123").unwrap();
+  store.upload("/test/large".to_string(), b"0123456789abcd").unwrap();
+
+  let requests = store.client.requests.lock().unwrap();
+  let functions = requests
+    .iter()
+    .map(|(function, _, _)| function.as_str())
+    .collect::<Vec<_>>();
+
+  assert_that(&functions).is_equal_to(vec![
+    "files/upload",
+    "files/upload_session/start",
+    "files/upload_session/append_v2",
+    "files/upload_session/append_v2",
+    "files/upload_session/finish",
+  ]);
+  assert_that(&requests[0].1["path"]).is_equal_to(serde_json::json!("/test/small"));
+  assert_that(&requests[0].2).is_equal_to(b"0123".to_vec());
+  assert_that(&requests[1].2).is_equal_to(b"0123".to_vec());
+  assert_that(&requests[2].1["cursor"]).is_equal_to(serde_json::json!({"session_id": "session1", "offset": 4}));
+  assert_that(&requests[2].2).is_equal_to(b"4567".to_vec());
+  assert_that(&requests[3].1["cursor"]).is_equal_to(serde_json::json!({"session_id": "session1", "offset": 8}));
+  assert_that(&requests[3].2).is_equal_to(b"89ab".to_vec());
+  // The last chunk is uploaded when finishing the session
+  assert_that(&requests[4].1["cursor"]).is_equal_to(serde_json::json!({"session_id": "session1", "offset": 12}));
+  assert_that(&requests[4].1["commit"]["path"]).is_equal_to(serde_json::json!("/test/large"));
+  assert_that(&requests[4].2).is_equal_to(b"cd".to_vec());
+}
@@ -19,6 +19,8 @@ pub enum StoreError {
   Conflict(String),
   #[error("Store with name {0} not found")]
   StoreNotFound(String),
+  #[error("No t-rust-less store at: {0}")]
+  NoStore(String),
 }
 
 pub type StoreResult<T> = Result<T, StoreError>;
@@ -31,6 +33,10 @@ error_convert_from!(sled::Error, StoreError, IO(display));
 error_convert_from!(rmp_serde::encode::Error, StoreError, IO(display));
 #[cfg(feature = "sled")]
 error_convert_from!(rmp_serde::decode::Error, StoreError, IO(display));
+#[cfg(feature = "sled")]
+error_convert_from!(chacha20_poly1305_aead::DecryptError, StoreError, IO(display));
+#[cfg(feature = "keyring")]
+error_convert_from!(keyring::Error, StoreError, IO(display));
 #[cfg(feature = "dropbox")]
 error_convert_from!(std::sync::mpsc::RecvError, StoreError, IO(display));
 #[cfg(feature = "dropbox")]
@@ -41,6 +47,18 @@ error_convert_from!(dropbox_sdk::files::ListFolderError, StoreError, IO(display)
 error_convert_from!(dropbox_sdk::files::ListFolderContinueError, StoreError, IO(display));
 #[cfg(feature = "dropbox")]
 error_convert_from!(dropbox_sdk::files::UploadError, StoreError, IO(display));
+#[cfg(feature = "dropbox")]
+error_convert_from!(dropbox_sdk::files::UploadSessionStartError, StoreError, IO(display));
+#[cfg(feature = "dropbox")]
+error_convert_from!(dropbox_sdk::files::UploadSessionAppendError, StoreError, IO(display));
+#[cfg(feature = "dropbox")]
+error_convert_from!(dropbox_sdk::files::UploadSessionFinishError, StoreError, IO(display));
+#[cfg(any(feature = "webdav", feature = "onedrive"))]
+error_convert_from!(ureq::Error, StoreError, IO(display));
+#[cfg(feature = "onedrive")]
+error_convert_from!(serde_json::Error, StoreError, IO(display));
+#[cfg(feature = "sftp")]
+error_convert_from!(ssh2::Error, StoreError, IO(display));
 
 impl<T> From<std::sync::PoisonError<T>> for StoreError {
   fn from(error: std::sync::PoisonError<T>) -> Self {
@@ -1,11 +1,12 @@
 use super::{
-  generate_block_id, BlockStore, Change, ChangeLog, Operation, RingContent, RingId, StoreError, StoreResult,
+  copy_with_block_id, generate_block_id, generate_commit_id, sync_dir, BlockStore, Change, ChangeLog, Operation,
+  RingContent, RingId, StorageStats, StoreError, StoreResult, VerifyReport,
 };
 use crate::memguard::weak::ZeroingWords;
 use log::warn;
 use log::{debug, info};
-use std::collections::HashMap;
-use std::fs::{metadata, read_dir, DirBuilder, File, OpenOptions};
+use std::collections::{HashMap, HashSet};
+use std::fs::{metadata, read_dir, remove_file, rename, DirBuilder, File, OpenOptions};
 use std::io::prelude::*;
 use std::io::{self, BufReader, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
@@ -46,7 +47,7 @@ impl LocalDirBlockStore {
     match File::open(path) {
       Ok(mut file) => {
         let file_len = file.metadata()?.len() as usize;
-        if file_len % 8 != 0 {
+        if !file_len.is_multiple_of(8) {
           warn!("File length not aligned to 8 bytes. Probably this is not the file you are looking for.");
         }
         let mut content: ZeroingWords = ZeroingWords::allocate_zeroed_vec(file_len / 8);
@@ -69,6 +70,7 @@ impl LocalDirBlockStore {
       match line.split(' ').collect::<Vec<&str>>().as_slice() {
         ["A", block] => change_log.changes.push(Change::new(Operation::Add, *block)),
         ["D", block] => change_log.changes.push(Change::new(Operation::Delete, *block)),
+        ["C", commit_id] => change_log.commits.push(commit_id.to_string()),
         _ => (),
       }
     }
@@ -83,6 +85,33 @@ impl LocalDirBlockStore {
     Ok(base_dir.join("blocks").join(&block_id[0..2]).join(block_id))
   }
 
+  /// Ids of all blocks in the store (incomplete blocks of `add_block_stream` are skipped).
+  fn list_block_files(&self) -> StoreResult<Vec<String>> {
+    let blocks_dir = match read_dir(self.base_dir.read()?.join("blocks")) {
+      Ok(dir) => dir,
+      Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+      Err(err) => return Err(err.into()),
+    };
+    let mut block_ids = vec![];
+
+    for maybe_entry in blocks_dir {
+      let entry = maybe_entry?;
+
+      if !entry.metadata()?.is_dir() {
+        continue;
+      }
+      for maybe_block_entry in read_dir(entry.path())? {
+        let block_entry = maybe_block_entry?;
+
+        if block_entry.metadata()?.is_file() {
+          block_ids.push(block_entry.file_name().to_string_lossy().to_string());
+        }
+      }
+    }
+
+    Ok(block_ids)
+  }
+
   fn list_ring_files(&self) -> StoreResult<HashMap<String, (u64, PathBuf)>> {
     match read_dir(self.base_dir.read()?.join("rings")) {
       Ok(ring_dir) => {
@@ -165,6 +194,7 @@ impl BlockStore for LocalDirBlockStore {
     ring_file.write_all(raw)?;
     ring_file.flush()?;
     ring_file.sync_all()?;
+    sync_dir(&ring_dir)?;
     Ok(())
   }
 
@@ -230,6 +260,106 @@ impl BlockStore for LocalDirBlockStore {
     Ok(block_id)
   }
 
+  fn add_block_stream(&self, reader: &mut dyn Read) -> StoreResult<String> {
+    let base_dir = self.base_dir.write()?;
+    let blocks_dir = base_dir.join("blocks");
+    // The block id is only known at the end, so the content goes to a temporary file first
+    let incoming_path = blocks_dir.join(format!(".incoming-{}", generate_commit_id()));
+
+    DirBuilder::new().recursive(true).create(&blocks_dir)?;
+    let result = File::create(&incoming_path)
+      .map_err(StoreError::from)
+      .and_then(|mut incoming| {
+        let (block_id, _) = copy_with_block_id(reader, &mut incoming)?;
+        incoming.flush()?;
+        incoming.sync_all()?;
+
+        let block_file_path = Self::block_file(&base_dir, &block_id)?;
+        DirBuilder::new()
+          .recursive(true)
+          .create(block_file_path.parent().unwrap())?;
+        rename(&incoming_path, block_file_path)?;
+
+        Ok(block_id)
+      });
+    if result.is_err() {
+      remove_file(&incoming_path).ok();
+    }
+
+    result
+  }
+
+  fn storage_stats(&self) -> StoreResult<StorageStats> {
+    let change_logs = self.change_logs()?;
+    let base_dir = self.base_dir.read()?;
+    let mut ring_versions: HashMap<String, usize> = HashMap::new();
+
+    match read_dir(base_dir.join("rings")) {
+      Ok(ring_dir) => {
+        for maybe_entry in ring_dir {
+          let entry = maybe_entry?;
+
+          if entry.metadata()?.is_file() {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let name = file_name.split('.').next().unwrap_or_default().to_string();
+            *ring_versions.entry(name).or_default() += 1;
+          }
+        }
+      }
+      Err(ref err) if err.kind() == io::ErrorKind::NotFound => (),
+      Err(err) => return Err(err.into()),
+    }
+
+    Ok(StorageStats::collect(
+      &change_logs,
+      ring_versions.into_iter().collect(),
+      |block| {
+        let block_file_path = Self::block_file(&base_dir, block).ok()?;
+        Some(block_file_path.metadata().ok()?.len())
+      },
+    ))
+  }
+
+  fn verify(&self) -> StoreResult<VerifyReport> {
+    let change_logs = self.change_logs()?;
+    let stored_blocks = self.list_block_files()?;
+
+    let base_dir = self.base_dir.read()?;
+
+    VerifyReport::collect(&change_logs, Some(stored_blocks), |block| {
+      let block_file_path = match Self::block_file(&base_dir, block) {
+        Ok(block_file_path) => block_file_path,
+        Err(_) => return Ok(None),
+      };
+      // The block file is hashed as a stream, i.e. blocks are not required to be aligned to words
+      match File::open(block_file_path) {
+        Ok(mut block_file) => {
+          let (block_id, _) = copy_with_block_id(&mut block_file, &mut io::sink())?;
+          Ok(Some(block_id == block))
+        }
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+      }
+    })
+  }
+
+  fn collect_garbage(&self, referenced: &HashSet<String>) -> StoreResult<usize> {
+    let stored_blocks = self.list_block_files()?;
+    let base_dir = self.base_dir.write()?;
+    let mut removed = 0;
+
+    for block_id in stored_blocks {
+      if referenced.contains(&block_id) {
+        continue;
+      }
+      debug!("Removing unreferenced block {}", block_id);
+      remove_file(Self::block_file(&base_dir, &block_id)?)?;
+      removed += 1;
+    }
+
+    Ok(removed)
+  }
+
   fn get_block(&self, block: &str) -> StoreResult<ZeroingWords> {
     let base_dir = self.base_dir.read()?;
     let block_file_path = Self::block_file(&base_dir, block)?;
@@ -237,7 +367,7 @@ impl BlockStore for LocalDirBlockStore {
     Self::read_optional_file(block_file_path)?.ok_or_else(|| StoreError::InvalidBlock(block.to_string()))
   }
 
-  fn commit(&self, changes: &[Change]) -> StoreResult<()> {
+  fn commit(&self, commit_id: &str, changes: &[Change]) -> StoreResult<()> {
     let base_dir = self.base_dir.write()?;
     DirBuilder::new().recursive(true).create(base_dir.join("logs"))?;
     let mut log_file = OpenOptions::new()
@@ -249,8 +379,8 @@ impl BlockStore for LocalDirBlockStore {
     let existing = Self::parse_change_log(&self.node_id, &log_file)?;
     log_file.seek(SeekFrom::End(0))?;
 
-    if existing.changes.iter().any(|change| changes.contains(change)) {
-      return Err(StoreError::Conflict("Change already committed".to_string()));
+    if !existing.check_commit(commit_id, changes)? {
+      return Ok(());
     }
     for change in changes {
       match change.op {
@@ -258,6 +388,7 @@ impl BlockStore for LocalDirBlockStore {
         Operation::Delete => writeln!(log_file, "D {}", change.block)?,
       }
     }
+    writeln!(log_file, "C {}", commit_id)?;
     log_file.flush()?;
     log_file.sync_all()?;
 
@@ -272,12 +403,15 @@ impl BlockStore for LocalDirBlockStore {
       .create(change_log_file_path.parent().unwrap())?;
     let mut change_log_file = File::create(change_log_file_path)?;
 
-    for change in change_log.changes {
+    for change in &change_log.changes {
       match change.op {
         Operation::Add => writeln!(change_log_file, "A {}", change.block)?,
         Operation::Delete => writeln!(change_log_file, "D {}", change.block)?,
       }
     }
+    for commit_id in &change_log.commits {
+      writeln!(change_log_file, "C {}", commit_id)?;
+    }
     change_log_file.flush()?;
     change_log_file.sync_all()?;
 
@@ -10,7 +10,10 @@ use std::{
 
 use crate::memguard::weak::ZeroingWords;
 
-use super::{BlockStore, Change, ChangeLog, Operation, StoreError, StoreResult};
+use super::{
+  copy_with_block_id, sync_dir, BlockStore, Change, ChangeLog, Operation, StorageStats, StoreError, StoreResult,
+  VerifyReport,
+};
 
 #[derive(Debug)]
 pub struct LocalWalBlockStore {
@@ -42,7 +45,7 @@ impl LocalWalBlockStore {
     match File::open(path) {
       Ok(mut file) => {
         let file_len = file.metadata()?.len() as usize;
-        if file_len % 8 != 0 {
+        if !file_len.is_multiple_of(8) {
           warn!("File length not aligned to 8 bytes. Probably this is not the file you are looking for.");
         }
         let mut content: ZeroingWords = ZeroingWords::allocate_zeroed_vec(file_len / 8);
@@ -56,8 +59,8 @@ impl LocalWalBlockStore {
     }
   }
 
-  fn list_ring_files(&self) -> StoreResult<HashMap<String, (u64, PathBuf)>> {
-    let mut ring_files: HashMap<String, (u64, PathBuf)> = HashMap::new();
+  fn all_ring_files(&self) -> StoreResult<Vec<(String, u64, PathBuf)>> {
+    let mut ring_files = vec![];
     for maybe_entry in read_dir(self.base_dir.read()?.as_path())? {
       let entry = maybe_entry?;
 
@@ -77,17 +80,50 @@ impl LocalWalBlockStore {
           _ => continue,
         };
 
-        if let Some((current, _)) = ring_files.get(name) {
-          if *current > version {
-            continue;
-          }
+        ring_files.push((name.to_string(), version, entry.path().to_owned()));
+      }
+    }
+    Ok(ring_files)
+  }
+
+  fn list_ring_files(&self) -> StoreResult<HashMap<String, (u64, PathBuf)>> {
+    let mut ring_files: HashMap<String, (u64, PathBuf)> = HashMap::new();
+    for (name, version, path) in self.all_ring_files()? {
+      if let Some((current, _)) = ring_files.get(&name) {
+        if *current > version {
+          continue;
         }
-        ring_files.insert(name.to_string(), (version, entry.path().to_owned()));
       }
+      ring_files.insert(name, (version, path));
     }
     Ok(ring_files)
   }
 
+  /// Offsets of all chunks of a blocks file and the length of the file.
+  ///
+  /// Chunks after a chunk with an invalid size are not reachable and therefore skipped.
+  fn chunk_offsets(path: &Path) -> StoreResult<(Vec<u64>, u64)> {
+    let mut block_file = File::open(path)?;
+    let file_len = block_file.metadata()?.len();
+    let mut offsets = vec![];
+    let mut offset = 0u64;
+
+    while offset.saturating_add(8) <= file_len {
+      block_file.seek(SeekFrom::Start(offset))?;
+      let mut chunk_size = [0u8; 8];
+      block_file.read_exact(&mut chunk_size)?;
+      let chunk_size = LittleEndian::read_u64(&chunk_size);
+      if chunk_size > file_len - offset - 8 {
+        warn!("Invalid chunk at {}:{}", path.to_string_lossy(), offset);
+        break;
+      }
+      offsets.push(offset);
+      offset += 8 + chunk_size;
+    }
+
+    Ok((offsets, file_len))
+  }
+
   fn parse_change_log(node_id: &str, file: &File) -> StoreResult<ChangeLog> {
     let reader = BufReader::new(file);
     let mut change_log = ChangeLog::new(node_id);
@@ -97,6 +133,7 @@ impl LocalWalBlockStore {
       match line.split(' ').collect::<Vec<&str>>().as_slice() {
         ["A", block] => change_log.changes.push(Change::new(Operation::Add, *block)),
         ["D", block] => change_log.changes.push(Change::new(Operation::Delete, *block)),
+        ["C", commit_id] => change_log.commits.push(commit_id.to_string()),
         _ => (),
       }
     }
@@ -146,6 +183,7 @@ impl BlockStore for LocalWalBlockStore {
     ring_file.write_all(raw)?;
     ring_file.flush()?;
     ring_file.sync_all()?;
+    sync_dir(&base_dir)?;
     Ok(())
   }
 
@@ -212,6 +250,88 @@ impl BlockStore for LocalWalBlockStore {
     Ok(block_id)
   }
 
+  fn add_block_stream(&self, reader: &mut dyn Read) -> StoreResult<String> {
+    let base_dir = self.base_dir.write()?;
+    let block_file_path = base_dir.join(format!("{}.blocks", self.node_id));
+    let mut block_file = File::options()
+      .create(true)
+      .truncate(false)
+      .write(true)
+      .open(block_file_path)?;
+    let offset = block_file.seek(SeekFrom::End(0))?;
+
+    // The size of the chunk is only known at the end
+    let result = block_file
+      .write_all(&[0u8; 8])
+      .map_err(StoreError::from)
+      .and_then(|_| copy_with_block_id(reader, &mut block_file))
+      .and_then(|(_, length)| {
+        let mut chunk_size = [0u8; 8];
+        LittleEndian::write_u64(&mut chunk_size, length);
+        block_file.seek(SeekFrom::Start(offset))?;
+        block_file.write_all(&chunk_size)?;
+        block_file.flush()?;
+        block_file.sync_all()?;
+        Ok(())
+      });
+    if let Err(err) = result {
+      // Do not leave a partial chunk behind
+      block_file.set_len(offset)?;
+      return Err(err);
+    }
+
+    Ok(format!("{}:{}", self.node_id, offset))
+  }
+
+  fn storage_stats(&self) -> StoreResult<StorageStats> {
+    let change_logs = self.change_logs()?;
+    let mut ring_versions: HashMap<String, usize> = HashMap::new();
+
+    for (name, _, _) in self.all_ring_files()? {
+      *ring_versions.entry(name).or_default() += 1;
+    }
+
+    Ok(StorageStats::collect(
+      &change_logs,
+      ring_versions.into_iter().collect(),
+      |block| self.get_block(block).ok().map(|content| content.len() as u64 * 8),
+    ))
+  }
+
+  fn verify(&self) -> StoreResult<VerifyReport> {
+    // Block ids are not content based, instead a block is considered to be mismatched if its id does not
+    // point to the start of a (valid) chunk of the blocks file.
+    let change_logs = self.change_logs()?;
+    let mut chunks: HashMap<String, (Vec<u64>, u64)> = HashMap::new();
+
+    for maybe_entry in read_dir(self.base_dir.read()?.as_path())? {
+      let entry = maybe_entry?;
+
+      if !entry.metadata()?.is_file() {
+        continue;
+      }
+      if let Some(node_id) = entry.file_name().to_str().and_then(|name| name.strip_suffix(".blocks")) {
+        chunks.insert(node_id.to_string(), Self::chunk_offsets(&entry.path())?);
+      }
+    }
+    let stored_blocks = chunks
+      .iter()
+      .flat_map(|(node_id, (offsets, _))| offsets.iter().map(move |offset| format!("{}:{}", node_id, offset)))
+      .collect();
+
+    VerifyReport::collect(&change_logs, Some(stored_blocks), |block| {
+      let (node_id, offset) = match block.split_once(':') {
+        Some((node_id, offset)) => (node_id, offset),
+        None => return Ok(None),
+      };
+      match (chunks.get(node_id), offset.parse::<u64>()) {
+        (Some((offsets, _)), Ok(offset)) if offsets.binary_search(&offset).is_ok() => Ok(Some(true)),
+        (Some((_, file_len)), Ok(offset)) if offset < *file_len => Ok(Some(false)),
+        _ => Ok(None),
+      }
+    })
+  }
+
   fn get_block(&self, block: &str) -> StoreResult<crate::memguard::weak::ZeroingWords> {
     let base_dir = self.base_dir.read()?;
     let (node_id, offset) = block
@@ -221,18 +341,29 @@ impl BlockStore for LocalWalBlockStore {
       .parse::<u64>()
       .map_err(|_| StoreError::InvalidBlock(block.to_string()))?;
 
-    let mut block_file = File::open(base_dir.join(format!("{}.blocks", node_id)))?;
+    let mut block_file = match File::open(base_dir.join(format!("{}.blocks", node_id))) {
+      Ok(file) => file,
+      Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Err(StoreError::InvalidBlock(block.to_string())),
+      Err(err) => return Err(err.into()),
+    };
+    let file_len = block_file.metadata()?.len();
+    if offset.saturating_add(8) > file_len {
+      return Err(StoreError::InvalidBlock(block.to_string()));
+    }
     block_file.seek(SeekFrom::Start(offset))?;
     let mut chunk_size = [0u8; 8];
     block_file.read_exact(&mut chunk_size)?;
-    let chunk_size = LittleEndian::read_u64(&chunk_size) as usize;
-    let mut content: ZeroingWords = ZeroingWords::allocate_zeroed_vec(chunk_size / 8);
+    let chunk_size = LittleEndian::read_u64(&chunk_size);
+    if chunk_size > file_len - offset - 8 {
+      return Err(StoreError::InvalidBlock(block.to_string()));
+    }
+    let mut content: ZeroingWords = ZeroingWords::allocate_zeroed_vec(chunk_size as usize / 8);
     block_file.read_exact(&mut content)?;
 
     Ok(content)
   }
 
-  fn commit(&self, changes: &[super::Change]) -> StoreResult<()> {
+  fn commit(&self, commit_id: &str, changes: &[super::Change]) -> StoreResult<()> {
     let base_dir = self.base_dir.write()?;
     let mut log_file = File::options()
       .create(true)
@@ -243,8 +374,8 @@ impl BlockStore for LocalWalBlockStore {
     let existing = Self::parse_change_log(&self.node_id, &log_file)?;
     log_file.seek(SeekFrom::End(0))?;
 
-    if existing.changes.iter().any(|change| changes.contains(change)) {
-      return Err(StoreError::Conflict("Change already committed".to_string()));
+    if !existing.check_commit(commit_id, changes)? {
+      return Ok(());
     }
     for change in changes {
       match change.op {
@@ -252,6 +383,7 @@ impl BlockStore for LocalWalBlockStore {
         Operation::Delete => writeln!(log_file, "D {}", change.block)?,
       }
     }
+    writeln!(log_file, "C {}", commit_id)?;
     log_file.flush()?;
     log_file.sync_all()?;
 
@@ -260,14 +392,17 @@ impl BlockStore for LocalWalBlockStore {
 
   fn update_change_log(&self, change_log: super::ChangeLog) -> StoreResult<()> {
     let base_dir = self.base_dir.write()?;
-    let mut change_log_file = File::create(base_dir.join(format!("{}.commits", self.node_id)))?;
+    let mut change_log_file = File::create(base_dir.join(format!("{}.commits", change_log.node)))?;
 
-    for change in change_log.changes {
+    for change in &change_log.changes {
       match change.op {
         Operation::Add => writeln!(change_log_file, "A {}", change.block)?,
         Operation::Delete => writeln!(change_log_file, "D {}", change.block)?,
       }
     }
+    for commit_id in &change_log.commits {
+      writeln!(change_log_file, "C {}", commit_id)?;
+    }
     change_log_file.flush()?;
     change_log_file.sync_all()?;
 
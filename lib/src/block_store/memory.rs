@@ -1,7 +1,11 @@
-use std::collections::{btree_map, BTreeMap, HashMap};
+use std::collections::{btree_map, BTreeMap, HashMap, HashSet};
+use std::io::Read;
 use std::sync::RwLock;
 
-use super::{generate_block_id, BlockStore, Change, ChangeLog, RingContent, RingId, StoreError, StoreResult};
+use super::{
+  copy_with_block_id, generate_block_id, BlockStore, Change, ChangeLog, RingContent, RingId, StorageStats, StoreError,
+  StoreResult,
+};
 use crate::memguard::weak::ZeroingWords;
 
 /// Memory based reference implementation of a block store.
@@ -15,7 +19,7 @@ pub struct MemoryBlockStore {
   rings: RwLock<HashMap<String, BTreeMap<u64, ZeroingWords>>>,
   indexes: RwLock<HashMap<String, ZeroingWords>>,
   blocks: RwLock<HashMap<String, ZeroingWords>>,
-  changes: RwLock<HashMap<String, Vec<Change>>>,
+  change_logs: RwLock<HashMap<String, ChangeLog>>,
 }
 
 impl MemoryBlockStore {
@@ -25,7 +29,7 @@ impl MemoryBlockStore {
       rings: RwLock::new(HashMap::new()),
       indexes: RwLock::new(HashMap::new()),
       blocks: RwLock::new(HashMap::new()),
-      changes: RwLock::new(HashMap::new()),
+      change_logs: RwLock::new(HashMap::new()),
     }
   }
 }
@@ -72,17 +76,9 @@ impl BlockStore for MemoryBlockStore {
   }
 
   fn change_logs(&self) -> StoreResult<Vec<ChangeLog>> {
-    let changes = self.changes.read()?;
+    let change_logs = self.change_logs.read()?;
 
-    Ok(
-      changes
-        .iter()
-        .map(|(node, changes)| ChangeLog {
-          node: node.clone(),
-          changes: changes.clone(),
-        })
-        .collect(),
-    )
+    Ok(change_logs.values().cloned().collect())
   }
 
   fn get_index(&self, node: &str) -> StoreResult<Option<ZeroingWords>> {
@@ -106,6 +102,39 @@ impl BlockStore for MemoryBlockStore {
     Ok(block_id)
   }
 
+  fn add_block_stream(&self, reader: &mut dyn Read) -> StoreResult<String> {
+    let mut raw = Vec::with_capacity(8192);
+    let (block_id, _) = copy_with_block_id(reader, &mut raw)?;
+    let mut blocks = self.blocks.write()?;
+
+    blocks.insert(block_id.clone(), raw.as_slice().into());
+    Ok(block_id)
+  }
+
+  fn storage_stats(&self) -> StoreResult<StorageStats> {
+    let change_logs = self.change_logs()?;
+    let ring_versions = self
+      .rings
+      .read()?
+      .iter()
+      .map(|(id, versions)| (id.clone(), versions.len()))
+      .collect();
+    let blocks = self.blocks.read()?;
+
+    Ok(StorageStats::collect(&change_logs, ring_versions, |block| {
+      blocks.get(block).map(|content| content.len() as u64 * 8)
+    }))
+  }
+
+  fn collect_garbage(&self, referenced: &HashSet<String>) -> StoreResult<usize> {
+    let mut blocks = self.blocks.write()?;
+    let count = blocks.len();
+
+    blocks.retain(|block_id, _| referenced.contains(block_id));
+
+    Ok(count - blocks.len())
+  }
+
   fn get_block(&self, block: &str) -> StoreResult<ZeroingWords> {
     let blocks = self.blocks.read()?;
 
@@ -115,27 +144,23 @@ impl BlockStore for MemoryBlockStore {
       .ok_or_else(|| StoreError::InvalidBlock(block.to_string()))
   }
 
-  fn commit(&self, changes: &[Change]) -> StoreResult<()> {
-    let mut stored_changes = self.changes.write()?;
-
-    match stored_changes.get_mut(&self.node_id) {
-      Some(existing) => {
-        if existing.iter().any(|change| changes.contains(change)) {
-          return Err(StoreError::Conflict("Change already committed".to_string()));
-        }
-        existing.extend_from_slice(changes);
-      }
-      None => {
-        stored_changes.insert(self.node_id.to_string(), changes.to_vec());
-      }
+  fn commit(&self, commit_id: &str, changes: &[Change]) -> StoreResult<()> {
+    let mut change_logs = self.change_logs.write()?;
+    let change_log = change_logs
+      .entry(self.node_id.to_string())
+      .or_insert_with(|| ChangeLog::new(&self.node_id));
+
+    if change_log.check_commit(commit_id, changes)? {
+      change_log.changes.extend_from_slice(changes);
+      change_log.commits.push(commit_id.to_string());
     }
     Ok(())
   }
 
   fn update_change_log(&self, change_log: ChangeLog) -> StoreResult<()> {
-    let mut stored_changes = self.changes.write()?;
+    let mut change_logs = self.change_logs.write()?;
 
-    stored_changes.insert(change_log.node, change_log.changes);
+    change_logs.insert(change_log.node.clone(), change_log);
 
     Ok(())
   }
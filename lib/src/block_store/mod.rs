@@ -1,9 +1,15 @@
 pub use self::model::*;
 use data_encoding::HEXLOWER;
+use rand::{thread_rng, RngCore};
 use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::io::{Read, Write};
 use std::sync::Arc;
 use url::Url;
 
+mod async_block_store;
+#[cfg(any(feature = "dropbox", feature = "onedrive"))]
+mod authcode;
 #[cfg(feature = "dropbox")]
 pub mod dropbox;
 mod error;
@@ -11,13 +17,22 @@ mod local_dir;
 mod local_wal;
 mod memory;
 mod model;
+#[cfg(feature = "onedrive")]
+pub mod onedrive;
+#[cfg(feature = "sftp")]
+mod sftp;
 #[cfg(feature = "sled")]
 mod sled;
+#[cfg(feature = "sled")]
+mod sled_crypt;
 pub mod sync;
+#[cfg(feature = "webdav")]
+mod webdav;
 
 #[cfg(test)]
-mod tests;
+pub(crate) mod tests;
 
+pub use self::async_block_store::{AsyncBlockStore, BlockOnStore, BlockingAsyncStore};
 pub use self::error::{StoreError, StoreResult};
 use crate::memguard::weak::ZeroingWords;
 
@@ -100,6 +115,21 @@ pub trait BlockStore: std::fmt::Debug + Send + Sync {
   /// The result of an add operation is a unique key of the data block.
   ///
   fn add_block(&self, raw: &[u8]) -> StoreResult<String>;
+
+  /// Add a new data block to the store by reading it from a stream.
+  ///
+  /// This is supposed to be the same as `add_block` (i.e. the result is the same block id), but
+  /// implementations capable of writing incrementally should override this so that large blocks
+  /// (e.g. attachments) do not have to be kept in memory. The default implementation just reads
+  /// the entire stream into a buffer.
+  ///
+  fn add_block_stream(&self, reader: &mut dyn Read) -> StoreResult<String> {
+    let mut raw = Vec::with_capacity(8192);
+    reader.read_to_end(&mut raw)?;
+
+    self.add_block(&raw)
+  }
+
   /// Get a block by its id.
   ///
   fn get_block(&self, block: &str) -> StoreResult<ZeroingWords>;
@@ -110,12 +140,65 @@ pub trait BlockStore: std::fmt::Debug + Send + Sync {
   /// commit its changes. This will create an entry in the `change_log` so that
   /// other clients will notice the new data blocks.
   ///
-  fn commit(&self, changes: &[Change]) -> StoreResult<()>;
+  /// The `commit_id` is generated by the client (see `generate_commit_id`) and recorded in the
+  /// change log. A commit with an id that has already been applied is considered as success, so
+  /// that a commit can be safely retried if its response got lost.
+  ///
+  fn commit(&self, commit_id: &str, changes: &[Change]) -> StoreResult<()>;
 
   /// Update changelog of other nodes.
   ///
   /// This is intended for store synchronization only.
   fn update_change_log(&self, change_log: ChangeLog) -> StoreResult<()>;
+
+  /// Get storage statistics (for analysis only).
+  ///
+  /// The default implementation simply reads all blocks referenced by the change logs, which
+  /// might be expensive for remote stores. Implementations should override this if there is a
+  /// more efficient way (e.g. file metadata).
+  ///
+  fn storage_stats(&self) -> StoreResult<StorageStats> {
+    let change_logs = self.change_logs()?;
+    let ring_versions = self.list_ring_ids()?.into_iter().map(|(id, _)| (id, 1)).collect();
+
+    Ok(StorageStats::collect(&change_logs, ring_versions, |block| {
+      // Blocks are word aligned, i.e. this might be slightly larger than the original
+      self.get_block(block).ok().map(|content| content.len() as u64 * 8)
+    }))
+  }
+
+  /// Verify the integrity of the store without modifying anything.
+  ///
+  /// The default implementation reads all blocks referenced by the change logs and checks that
+  /// their content matches the block id (see `generate_block_id`). Since the default implementation
+  /// has no way to list all blocks, orphan blocks are never reported. Implementations should
+  /// override this if they are able to list their blocks or do not use content based block ids.
+  ///
+  fn verify(&self) -> StoreResult<VerifyReport> {
+    let change_logs = self.change_logs()?;
+
+    VerifyReport::collect(&change_logs, None, |block| check_block_id(block, self.get_block(block)))
+  }
+
+  /// Remove all data blocks that are not part of `referenced`.
+  ///
+  /// Rings, indexes and change logs are left untouched. It is up to the caller to ensure that `referenced`
+  /// contains every block that is still required (see `SecretsStore::collect_garbage`).
+  ///
+  /// The result is the number of removed blocks. The default implementation does not remove anything,
+  /// which is the only option for append-only stores.
+  ///
+  fn collect_garbage(&self, _referenced: &HashSet<String>) -> StoreResult<usize> {
+    Ok(0)
+  }
+
+  /// Ensure that all previous writes are persisted.
+  ///
+  /// Implementations buffering writes internally have to override this, the default does nothing.
+  ///
+  fn flush(&self) -> StoreResult<()> {
+    Ok(())
+  }
 }
 
 pub fn open_block_store(url: &str, node_id: &str) -> StoreResult<Arc<dyn BlockStore>> {
@@ -123,29 +206,71 @@ pub fn open_block_store(url: &str, node_id: &str) -> StoreResult<Arc<dyn BlockSt
 
   match store_url.scheme() {
     "file" => Ok(Arc::new(local_dir::LocalDirBlockStore::new(
-      store_url.to_file_path().unwrap(),
+      to_file_path(&store_url)?,
       node_id,
     )?)),
     "wal" => Ok(Arc::new(local_wal::LocalWalBlockStore::new(
-      store_url.to_file_path().unwrap(),
+      to_file_path(&store_url)?,
       node_id,
     )?)),
     "memory" => Ok(Arc::new(memory::MemoryBlockStore::new(node_id))),
     #[cfg(feature = "sled")]
-    "sled" => Ok(Arc::new(sled::SledBlockStore::new(
-      store_url.to_file_path().unwrap(),
-      node_id,
-    )?)),
+    "sled" => Ok(Arc::new(open_sled_block_store(&store_url, node_id)?)),
     #[cfg(feature = "dropbox")]
     "dropbox" => Ok(Arc::new(dropbox::DropboxBlockStore::new(
       store_url.username(),
-      store_url.host_str().unwrap(),
+      store_url
+        .host_str()
+        .ok_or_else(|| StoreError::InvalidStoreUrl(url.to_string()))?,
+      node_id,
+    )?)),
+    #[cfg(feature = "onedrive")]
+    "onedrive" => Ok(Arc::new(onedrive::OneDriveBlockStore::new(&store_url, node_id)?)),
+    #[cfg(feature = "sftp")]
+    "sftp" => Ok(Arc::new(sftp::SftpBlockStore::new(
+      &sftp::SftpConfig::from_url(&store_url)?,
       node_id,
     )?)),
+    #[cfg(feature = "webdav")]
+    "webdav" | "webdavs" => Ok(Arc::new(webdav::WebDavBlockStore::new(&store_url, node_id)?)),
     _ => Err(StoreError::InvalidStoreUrl(url.to_string())),
   }
 }
 
+/// Open a sled block store, local encryption-at-rest is enabled via `?encrypted=true`.
+/// The device key is kept in the keyring of the OS, unless a `key_file=<path>` is given (for systems
+/// without a keyring).
+#[cfg(feature = "sled")]
+fn open_sled_block_store(store_url: &Url, node_id: &str) -> StoreResult<sled::SledBlockStore> {
+  let mut encrypted = false;
+  let mut key_file = None;
+
+  for (name, value) in store_url.query_pairs() {
+    match name.as_ref() {
+      "encrypted" => encrypted = value == "true" || value == "1",
+      "key_file" => key_file = Some(std::path::PathBuf::from(value.as_ref())),
+      _ => return Err(StoreError::InvalidStoreUrl(store_url.to_string())),
+    }
+  }
+
+  if encrypted {
+    let db_file = to_file_path(store_url)?;
+    let device_key = match key_file {
+      Some(key_file) => sled_crypt::read_or_create_device_key(key_file)?,
+      None => sled_crypt::read_or_create_keyring_device_key(&db_file)?,
+    };
+    sled::SledBlockStore::new_encrypted(db_file, node_id, &device_key)
+  } else {
+    sled::SledBlockStore::new(to_file_path(store_url)?, node_id)
+  }
+}
+
+fn to_file_path(store_url: &Url) -> StoreResult<std::path::PathBuf> {
+  store_url
+    .to_file_path()
+    .map_err(|_| StoreError::InvalidStoreUrl(store_url.to_string()))
+}
+
 pub fn generate_block_id(data: &[u8]) -> String {
   let mut hasher = Sha256::new();
 
@@ -153,3 +278,76 @@ pub fn generate_block_id(data: &[u8]) -> String {
 
   HEXLOWER.encode(&hasher.finalize())
 }
+
+/// Sync a directory, so that files created in it are persisted as well (not just their content).
+#[cfg(unix)]
+fn sync_dir(dir: &std::path::Path) -> StoreResult<()> {
+  std::fs::File::open(dir)?.sync_all()?;
+  Ok(())
+}
+
+/// Directories can not be opened (and synced) on windows, which is fine since it does not have the problem.
+#[cfg(not(unix))]
+fn sync_dir(_dir: &std::path::Path) -> StoreResult<()> {
+  Ok(())
+}
+
+/// Check the result of a `get_block` against the block id (see `BlockStore::verify`).
+///
+/// The result is `None` if the block does not exist.
+fn check_block_id(block: &str, content: StoreResult<ZeroingWords>) -> StoreResult<Option<bool>> {
+  match content {
+    Ok(content) => Ok(Some(generate_block_id(&content) == block)),
+    Err(StoreError::InvalidBlock(_)) => Ok(None),
+    Err(err) => Err(err),
+  }
+}
+
+/// Copy a stream to `writer` while computing the block id (see `generate_block_id`) on the fly.
+///
+/// The result is the block id and the number of bytes copied.
+pub fn copy_with_block_id(reader: &mut dyn Read, writer: &mut dyn Write) -> StoreResult<(String, u64)> {
+  let mut hasher = Sha256::new();
+  let mut buffer = [0u8; 8192];
+  let mut length = 0u64;
+
+  loop {
+    let n = match reader.read(&mut buffer) {
+      Ok(0) => break,
+      Ok(n) => n,
+      Err(ref err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+      Err(err) => return Err(err.into()),
+    };
+    hasher.update(&buffer[..n]);
+    writer.write_all(&buffer[..n])?;
+    length += n as u64;
+  }
+
+  Ok((HEXLOWER.encode(&hasher.finalize()), length))
+}
+
+/// Generate a (random) id for a set of changes to commit.
+pub fn generate_commit_id() -> String {
+  let mut id = [0u8; 16];
+
+  thread_rng().fill_bytes(&mut id);
+
+  HEXLOWER.encode(&id)
+}
+
+/// Ids of all blocks that have been added by any of the `change_logs` and not deleted afterwards.
+pub fn committed_block_ids(change_logs: &[ChangeLog]) -> HashSet<String> {
+  let deleted: HashSet<&str> = change_logs
+    .iter()
+    .flat_map(|change_log| change_log.changes.iter())
+    .filter(|change| change.op == Operation::Delete)
+    .map(|change| change.block.as_str())
+    .collect();
+
+  change_logs
+    .iter()
+    .flat_map(|change_log| change_log.changes.iter())
+    .filter(|change| change.op == Operation::Add && !deleted.contains(change.block.as_str()))
+    .map(|change| change.block.clone())
+    .collect()
+}
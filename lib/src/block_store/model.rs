@@ -1,6 +1,8 @@
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
+use super::{StoreError, StoreResult};
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum Operation {
@@ -27,6 +29,9 @@ impl Change {
 pub struct ChangeLog {
   pub node: String,
   pub changes: Vec<Change>,
+  /// Ids of all commits that have been applied to this change log
+  #[serde(default)]
+  pub commits: Vec<String>,
 }
 
 impl ChangeLog {
@@ -34,9 +39,25 @@ impl ChangeLog {
     ChangeLog {
       node: node.into(),
       changes: vec![],
+      commits: vec![],
     }
   }
 
+  /// Check if a commit can be appended to the change log.
+  ///
+  /// The result is `false` if a commit with the same id has already been applied, i.e. the commit is
+  /// just a retry and should be treated as success.
+  ///
+  pub fn check_commit(&self, commit_id: &str, changes: &[Change]) -> StoreResult<bool> {
+    if self.commits.iter().any(|id| id == commit_id) {
+      return Ok(false);
+    }
+    if self.changes.iter().any(|change| changes.contains(change)) {
+      return Err(StoreError::Conflict("Change already committed".to_string()));
+    }
+    Ok(true)
+  }
+
   pub fn changes_since(&self, maybe_change: Option<&Change>) -> impl Iterator<Item = &Change> {
     let skip = maybe_change
       .and_then(|change| self.changes.iter().position(|c| c == change).map(|pos| pos + 1))
@@ -45,3 +66,163 @@ impl ChangeLog {
     self.changes.iter().dropping(skip)
   }
 }
+
+/// Number of largest blocks reported in `StorageStats`
+pub const STORAGE_STATS_LARGEST_BLOCKS: usize = 10;
+
+/// Storage statistics of a block store.
+///
+/// These are gathered without decrypting any data, i.e. only the sizes of the raw blocks are
+/// taken into account.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StorageStats {
+  /// Number of distinct data blocks referenced by any change log
+  pub block_count: usize,
+  /// Total size of all (available) data blocks
+  pub total_bytes: u64,
+  /// Largest blocks (id and size), largest first
+  pub largest_blocks: Vec<(String, u64)>,
+  /// Number of blocks referenced by change logs, but not available in the store
+  pub missing_blocks: usize,
+  /// Number of retained versions of each ring
+  pub ring_versions: Vec<(String, usize)>,
+  /// Number of changes in the change log of each node
+  pub change_logs: Vec<(String, usize)>,
+  /// Number of blocks marked as deleted that are still stored (and could be garbage collected)
+  pub reclaimable_blocks: usize,
+  /// Total size of the blocks marked as deleted
+  pub reclaimable_bytes: u64,
+}
+
+impl StorageStats {
+  /// Collect storage stats from the change logs.
+  ///
+  /// * `block_size` gets the size of a block by its id, `None` if the block is not available
+  ///
+  pub fn collect<F>(change_logs: &[ChangeLog], ring_versions: Vec<(String, usize)>, block_size: F) -> Self
+  where
+    F: Fn(&str) -> Option<u64>,
+  {
+    let mut blocks: Vec<&str> = change_logs
+      .iter()
+      .flat_map(|change_log| change_log.changes.iter())
+      .filter(|change| change.op == Operation::Add)
+      .map(|change| change.block.as_str())
+      .collect();
+    blocks.sort_unstable();
+    blocks.dedup();
+    let deleted: Vec<&str> = change_logs
+      .iter()
+      .flat_map(|change_log| change_log.changes.iter())
+      .filter(|change| change.op == Operation::Delete)
+      .map(|change| change.block.as_str())
+      .collect();
+
+    let mut stats = StorageStats {
+      block_count: blocks.len(),
+      ring_versions,
+      change_logs: change_logs
+        .iter()
+        .map(|change_log| (change_log.node.clone(), change_log.changes.len()))
+        .collect(),
+      ..Default::default()
+    };
+    let mut block_sizes = Vec::with_capacity(blocks.len());
+
+    for block in blocks {
+      match block_size(block) {
+        Some(size) => {
+          stats.total_bytes += size;
+          if deleted.contains(&block) {
+            stats.reclaimable_blocks += 1;
+            stats.reclaimable_bytes += size;
+          }
+          block_sizes.push((block.to_string(), size));
+        }
+        None => stats.missing_blocks += 1,
+      }
+    }
+    block_sizes.sort_by(|(id1, size1), (id2, size2)| size2.cmp(size1).then_with(|| id1.cmp(id2)));
+    block_sizes.truncate(STORAGE_STATS_LARGEST_BLOCKS);
+    stats.largest_blocks = block_sizes;
+    stats.ring_versions.sort();
+    stats.change_logs.sort();
+
+    stats
+  }
+}
+
+/// Result of a (read-only) integrity check of a block store (see `BlockStore::verify`).
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct VerifyReport {
+  /// Number of distinct data blocks referenced by any change log that have been checked
+  pub checked_blocks: usize,
+  /// Blocks whose content does not match their block id
+  pub mismatched_blocks: Vec<String>,
+  /// Blocks added by a change log, but not available in the store
+  pub missing_blocks: Vec<String>,
+  /// Blocks available in the store, but not referenced by any change log.
+  ///
+  /// This is always empty for stores that are unable to list their blocks.
+  pub orphan_blocks: Vec<String>,
+}
+
+impl VerifyReport {
+  /// Check all blocks referenced by the change logs.
+  ///
+  /// * `stored_blocks` are the ids of all blocks available in the store, `None` if the store is unable to list them
+  /// * `check_block` checks if the content of a block matches its id, `None` if the block is not available
+  ///
+  /// Blocks that have been deleted are not reported as missing, since they might have been garbage collected.
+  ///
+  pub fn collect<F>(change_logs: &[ChangeLog], stored_blocks: Option<Vec<String>>, check_block: F) -> StoreResult<Self>
+  where
+    F: Fn(&str) -> StoreResult<Option<bool>>,
+  {
+    let mut blocks: Vec<&str> = change_logs
+      .iter()
+      .flat_map(|change_log| change_log.changes.iter())
+      .filter(|change| change.op == Operation::Add)
+      .map(|change| change.block.as_str())
+      .collect();
+    blocks.sort_unstable();
+    blocks.dedup();
+    let deleted: Vec<&str> = change_logs
+      .iter()
+      .flat_map(|change_log| change_log.changes.iter())
+      .filter(|change| change.op == Operation::Delete)
+      .map(|change| change.block.as_str())
+      .collect();
+
+    let mut report = VerifyReport {
+      checked_blocks: blocks.len(),
+      ..Default::default()
+    };
+
+    for block in &blocks {
+      match check_block(block)? {
+        Some(true) => (),
+        Some(false) => report.mismatched_blocks.push(block.to_string()),
+        None if deleted.contains(block) => (),
+        None => report.missing_blocks.push(block.to_string()),
+      }
+    }
+    if let Some(stored_blocks) = stored_blocks {
+      report.orphan_blocks = stored_blocks
+        .into_iter()
+        .filter(|block| blocks.binary_search(&block.as_str()).is_err() && !deleted.contains(&block.as_str()))
+        .collect();
+      report.orphan_blocks.sort();
+    }
+
+    Ok(report)
+  }
+
+  /// `true` if no problems have been found.
+  ///
+  /// Orphan blocks are not considered to be a problem, as they might just be the leftovers of an
+  /// interrupted commit.
+  pub fn is_ok(&self) -> bool {
+    self.mismatched_blocks.is_empty() && self.missing_blocks.is_empty()
+  }
+}
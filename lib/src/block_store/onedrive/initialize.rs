@@ -0,0 +1,78 @@
+use data_encoding::BASE64URL_NOPAD;
+use rand::{thread_rng, RngCore};
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use crate::block_store::authcode::{start_authcode_server, ServerHandle, REDIRECT_URL};
+use crate::block_store::onedrive::{request_token, AUTHORIZE_URL, SCOPE, TOKEN_URL};
+use crate::block_store::{StoreError, StoreResult};
+
+pub struct OneDriveInitializer {
+  name: String,
+  client_id: String,
+  code_verifier: String,
+  pub auth_url: Url,
+  server_handle: ServerHandle,
+}
+
+impl OneDriveInitializer {
+  pub fn wait_for_authentication(mut self) -> StoreResult<String> {
+    let auth_code = self.server_handle.wait_for_auth_code()?;
+
+    let token_response = request_token(
+      &ureq::agent(),
+      TOKEN_URL,
+      &[
+        ("client_id", &self.client_id),
+        ("grant_type", "authorization_code"),
+        ("code", &auth_code),
+        ("redirect_uri", REDIRECT_URL),
+        ("code_verifier", &self.code_verifier),
+        ("scope", SCOPE),
+      ],
+    )?;
+    let refresh_token = token_response
+      .refresh_token
+      .ok_or_else(|| StoreError::IO("Failed to obtain onedrive refresh token".to_string()))?;
+
+    let mut store_url = Url::parse(&format!("onedrive://{}", self.name))?;
+    store_url
+      .set_username(&refresh_token)
+      .map_err(|_| StoreError::InvalidStoreUrl(self.name.clone()))?;
+    store_url.query_pairs_mut().append_pair("client_id", &self.client_id);
+
+    Ok(store_url.to_string())
+  }
+}
+
+/// Start the oauth flow for a new OneDrive store.
+///
+/// In contrast to dropbox there is no t-rust-less app registered at Microsoft, i.e. the `client_id`
+/// of an app registration (public client with `REDIRECT_URL` as redirect) has to be provided.
+pub fn initialize_store(name: &str, client_id: &str) -> StoreResult<OneDriveInitializer> {
+  let mut verifier = [0u8; 32];
+  thread_rng().fill_bytes(&mut verifier);
+  let code_verifier = BASE64URL_NOPAD.encode(&verifier);
+  let code_challenge = BASE64URL_NOPAD.encode(&Sha256::digest(code_verifier.as_bytes()));
+  let auth_url = Url::parse_with_params(
+    AUTHORIZE_URL,
+    &[
+      ("client_id", client_id),
+      ("response_type", "code"),
+      ("redirect_uri", REDIRECT_URL),
+      ("response_mode", "query"),
+      ("scope", SCOPE),
+      ("code_challenge", &code_challenge),
+      ("code_challenge_method", "S256"),
+    ],
+  )?;
+  let server_handle = start_authcode_server("OneDrive")?;
+
+  Ok(OneDriveInitializer {
+    name: name.to_string(),
+    client_id: client_id.to_string(),
+    code_verifier,
+    auth_url,
+    server_handle,
+  })
+}
@@ -0,0 +1,501 @@
+mod initialize;
+
+#[cfg(test)]
+mod tests;
+
+use std::{
+  collections::HashMap,
+  io::{BufRead, BufReader, Read, Write},
+  sync::Mutex,
+  thread,
+  time::Duration,
+};
+
+use log::{info, warn};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::Deserialize;
+use url::Url;
+
+pub use initialize::*;
+
+use crate::{block_store::generate_block_id, memguard::weak::ZeroingWords};
+
+use super::{BlockStore, Change, ChangeLog, Operation, RingContent, RingId, StoreError, StoreResult};
+
+pub const GRAPH_URL: &str = "https://graph.microsoft.com/v1.0";
+pub const AUTHORIZE_URL: &str = "https://login.microsoftonline.com/common/oauth2/v2.0/authorize";
+pub const TOKEN_URL: &str = "https://login.microsoftonline.com/common/oauth2/v2.0/token";
+const SCOPE: &str = "Files.ReadWrite offline_access";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+/// Blocks are uploaded in chunks of this size via an upload session (has to be a multiple of 320 KiB).
+const UPLOAD_CHUNK_SIZE: usize = 10 * 320 * 1024;
+/// Default number of attempts of a request failing with a transient error (5xx or throttled).
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+/// Initial delay between attempts, doubled after every failed attempt.
+const DEFAULT_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Block store in the OneDrive of a user (via Microsoft Graph).
+///
+/// The url has the form `onedrive://<refresh_token>@<name>?client_id=<client_id>` (see `initialize_store`),
+/// the layout of the files is the same as in the dropbox store.
+///
+pub struct OneDriveBlockStore {
+  node_id: String,
+  name: String,
+  client_id: String,
+  graph_url: String,
+  token_url: String,
+  tokens: Mutex<Tokens>,
+  agent: ureq::Agent,
+  max_attempts: u32,
+  retry_delay: Duration,
+}
+
+struct Tokens {
+  access_token: Option<String>,
+  refresh_token: String,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+  access_token: String,
+  refresh_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct UploadSession {
+  #[serde(rename = "uploadUrl")]
+  upload_url: String,
+}
+
+#[derive(Deserialize)]
+struct DriveItems {
+  value: Vec<DriveItem>,
+  #[serde(rename = "@odata.nextLink")]
+  next_link: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct DriveItem {
+  name: String,
+  file: Option<serde_json::Value>,
+}
+
+impl OneDriveBlockStore {
+  pub fn new(store_url: &Url, node_id: &str) -> StoreResult<OneDriveBlockStore> {
+    let name = store_url
+      .host_str()
+      .ok_or_else(|| StoreError::InvalidStoreUrl(store_url.to_string()))?;
+    let refresh_token = percent_decode_str(store_url.username()).decode_utf8_lossy();
+    let client_id = store_url
+      .query_pairs()
+      .find_map(|(key, value)| if key == "client_id" { Some(value) } else { None })
+      .ok_or_else(|| StoreError::InvalidStoreUrl(store_url.to_string()))?;
+    if refresh_token.is_empty() {
+      return Err(StoreError::InvalidStoreUrl(store_url.to_string()));
+    }
+
+    info!("Opening onedrive store: {}", name);
+    Ok(OneDriveBlockStore {
+      node_id: node_id.to_string(),
+      name: name.to_string(),
+      client_id: client_id.to_string(),
+      graph_url: GRAPH_URL.to_string(),
+      token_url: TOKEN_URL.to_string(),
+      tokens: Mutex::new(Tokens {
+        access_token: None,
+        refresh_token: refresh_token.to_string(),
+      }),
+      agent: ureq::AgentBuilder::new().timeout(REQUEST_TIMEOUT).build(),
+      max_attempts: DEFAULT_MAX_ATTEMPTS,
+      retry_delay: DEFAULT_RETRY_DELAY,
+    })
+  }
+
+  /// Use different endpoints for Graph and the token exchange (i.e. anything but the public Microsoft cloud).
+  pub fn with_endpoints(mut self, graph_url: &str, token_url: &str) -> Self {
+    self.graph_url = graph_url.trim_end_matches('/').to_string();
+    self.token_url = token_url.to_string();
+    self
+  }
+
+  /// Maximum number of attempts of a request failing with a transient error, 1 disables retries.
+  pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+    self.max_attempts = max_attempts.max(1);
+    self
+  }
+
+  /// Initial delay between attempts (unless Graph tells otherwise via `Retry-After`).
+  pub fn with_retry_delay(mut self, retry_delay: Duration) -> Self {
+    self.retry_delay = retry_delay;
+    self
+  }
+
+  /// Url of an item (relative to the store folder) or one of its actions (e.g. `content` or `children`).
+  fn item_url(&self, segments: &[&str], action: &str) -> String {
+    let path = [self.name.as_str()]
+      .iter()
+      .chain(segments)
+      .map(|segment| utf8_percent_encode(segment, NON_ALPHANUMERIC).to_string())
+      .collect::<Vec<String>>()
+      .join("/");
+    format!("{}/me/drive/root:/{}:/{}", self.graph_url, path, action)
+  }
+
+  fn block_segments(block_id: &str) -> StoreResult<[&str; 3]> {
+    if block_id.len() < 3 {
+      return Err(StoreError::InvalidBlock(block_id.to_string()));
+    }
+    Ok(["blocks", &block_id[0..2], block_id])
+  }
+
+  /// Get the current access token, a new one is requested if there is none yet (or it has been rejected).
+  fn authorization(&self, renew: bool) -> StoreResult<String> {
+    let mut tokens = self.tokens.lock()?;
+
+    if renew || tokens.access_token.is_none() {
+      let token_response = request_token(
+        &self.agent,
+        &self.token_url,
+        &[
+          ("client_id", &self.client_id),
+          ("grant_type", "refresh_token"),
+          ("refresh_token", &tokens.refresh_token),
+          ("scope", SCOPE),
+        ],
+      )?;
+      if let Some(refresh_token) = token_response.refresh_token {
+        tokens.refresh_token = refresh_token;
+      }
+      tokens.access_token = Some(token_response.access_token);
+    }
+
+    Ok(format!("Bearer {}", tokens.access_token.as_deref().unwrap_or_default()))
+  }
+
+  /// Send a request (with an optional body), the request is created with the `Authorization` header as parameter.
+  ///
+  /// An expired access token is renewed once, transient errors are retried with exponential backoff.
+  /// The result is the response of the last attempt.
+  fn send(
+    &self,
+    request: impl Fn(&str) -> ureq::Request,
+    body: Option<&[u8]>,
+  ) -> StoreResult<Result<ureq::Response, ureq::Error>> {
+    let mut authorization = self.authorization(false)?;
+    let mut renewed = false;
+    let mut attempt = 1;
+
+    loop {
+      let result = match body {
+        Some(body) => request(&authorization).send_bytes(body),
+        None => request(&authorization).call(),
+      };
+      match result {
+        Err(ureq::Error::Status(401, _)) if !renewed => {
+          authorization = self.authorization(true)?;
+          renewed = true;
+        }
+        Err(ureq::Error::Status(status, response))
+          if attempt < self.max_attempts && (status == 429 || status >= 500) =>
+        {
+          let delay = response
+            .header("Retry-After")
+            .and_then(|retry_after| retry_after.parse::<u64>().ok())
+            .map(Duration::from_secs)
+            .unwrap_or_else(|| self.retry_delay.saturating_mul(1 << (attempt - 1).min(16)));
+          warn!(
+            "OneDrive request failed with status {} (attempt {}), retry in {:?}",
+            status, attempt, delay
+          );
+          thread::sleep(delay);
+          attempt += 1;
+        }
+        result => return Ok(result),
+      }
+    }
+  }
+
+  fn download(&self, segments: &[&str]) -> StoreResult<Option<ZeroingWords>> {
+    match self.download_stream(segments)? {
+      Some(mut stream) => {
+        let mut content = Vec::with_capacity(1024);
+        stream.read_to_end(&mut content)?;
+
+        Ok(Some(ZeroingWords::from(content.as_ref())))
+      }
+      None => Ok(None),
+    }
+  }
+
+  fn download_stream(&self, segments: &[&str]) -> StoreResult<Option<impl Read>> {
+    let url = self.item_url(segments, "content");
+    match self.send(
+      |authorization| self.agent.get(&url).set("Authorization", authorization),
+      None,
+    )? {
+      Ok(response) => Ok(Some(response.into_reader())),
+      Err(ureq::Error::Status(404, _)) => Ok(None),
+      Err(err) => Err(err.into()),
+    }
+  }
+
+  /// Simple upload of a (small) file, missing folders are created by OneDrive.
+  ///
+  /// If `exclusive` is set an existing file is not overwritten, the result is `false` in this case.
+  fn upload(&self, segments: &[&str], content: &[u8], exclusive: bool) -> StoreResult<bool> {
+    let conflict_behavior = if exclusive { "fail" } else { "replace" };
+    let url = format!(
+      "{}?@microsoft.graph.conflictBehavior={}",
+      self.item_url(segments, "content"),
+      conflict_behavior
+    );
+    match self.send(
+      |authorization| {
+        self
+          .agent
+          .put(&url)
+          .set("Authorization", authorization)
+          .set("Content-Type", "application/octet-stream")
+      },
+      Some(content),
+    )? {
+      Ok(_) => Ok(true),
+      Err(ureq::Error::Status(409, _)) if exclusive => Ok(false),
+      Err(err) => Err(err.into()),
+    }
+  }
+
+  /// Upload a file via an upload session.
+  ///
+  /// The file only becomes visible once all chunks are uploaded, i.e. a failed upload never leaves
+  /// a partial file behind.
+  fn upload_session(&self, segments: &[&str], content: &[u8]) -> StoreResult<()> {
+    if content.is_empty() {
+      // An upload session requires at least one chunk
+      self.upload(segments, content, false)?;
+      return Ok(());
+    }
+    let url = self.item_url(segments, "createUploadSession");
+    let response = self.send(
+      |authorization| {
+        self
+          .agent
+          .post(&url)
+          .set("Authorization", authorization)
+          .set("Content-Type", "application/json")
+      },
+      Some(br#"{"item":{"@microsoft.graph.conflictBehavior":"replace"}}"#),
+    )??;
+    let session: UploadSession = serde_json::from_reader(response.into_reader())?;
+
+    for (idx, chunk) in content.chunks(UPLOAD_CHUNK_SIZE).enumerate() {
+      let start = idx * UPLOAD_CHUNK_SIZE;
+      let content_range = format!("bytes {}-{}/{}", start, start + chunk.len() - 1, content.len());
+      // The upload url is pre-authenticated, sending the access token is actually discouraged
+      self.send(
+        |_| self.agent.put(&session.upload_url).set("Content-Range", &content_range),
+        Some(chunk),
+      )??;
+    }
+
+    Ok(())
+  }
+
+  /// Names of all files (not folders) of a folder.
+  fn list_files(&self, folder: &str) -> StoreResult<Vec<String>> {
+    let mut files = vec![];
+    let mut next_url = Some(self.item_url(&[folder], "children"));
+
+    while let Some(url) = next_url.take() {
+      let response = match self.send(
+        |authorization| self.agent.get(&url).set("Authorization", authorization),
+        None,
+      )? {
+        Ok(response) => response,
+        Err(ureq::Error::Status(404, _)) => break,
+        Err(err) => return Err(err.into()),
+      };
+      let items: DriveItems = serde_json::from_reader(response.into_reader())?;
+
+      files.extend(
+        items
+          .value
+          .into_iter()
+          .filter(|item| item.file.is_some())
+          .map(|item| item.name),
+      );
+      next_url = items.next_link;
+    }
+
+    Ok(files)
+  }
+
+  fn parse_change_log<R: Read>(node_id: &str, content: R) -> StoreResult<ChangeLog> {
+    let reader = BufReader::new(content);
+    let mut change_log = ChangeLog::new(node_id);
+
+    for maybe_line in reader.lines() {
+      let line = maybe_line?;
+      match line.split(' ').collect::<Vec<&str>>().as_slice() {
+        ["A", block] => change_log.changes.push(Change::new(Operation::Add, *block)),
+        ["D", block] => change_log.changes.push(Change::new(Operation::Delete, *block)),
+        ["C", commit_id] => change_log.commits.push(commit_id.to_string()),
+        _ => (),
+      }
+    }
+
+    Ok(change_log)
+  }
+
+  fn download_change_log(&self, node_id: &str) -> StoreResult<ChangeLog> {
+    match self.download_stream(&["logs", node_id])? {
+      Some(content) => Self::parse_change_log(node_id, content),
+      None => Ok(ChangeLog::new(node_id)),
+    }
+  }
+
+  fn upload_change_log(&self, change_log: &ChangeLog) -> StoreResult<()> {
+    let mut buffer = Vec::with_capacity(8192);
+    for change in &change_log.changes {
+      match change.op {
+        Operation::Add => writeln!(&mut buffer, "A {}", change.block)?,
+        Operation::Delete => writeln!(&mut buffer, "D {}", change.block)?,
+      }
+    }
+    for commit_id in &change_log.commits {
+      writeln!(&mut buffer, "C {}", commit_id)?;
+    }
+    self.upload(&["logs", &change_log.node], &buffer, false)?;
+
+    Ok(())
+  }
+
+  fn list_ring_files(&self) -> StoreResult<HashMap<String, (u64, String)>> {
+    let mut ring_files: HashMap<String, (u64, String)> = HashMap::new();
+
+    for file_name in self.list_files("rings")? {
+      let mut parts = file_name.split('.');
+      let name = parts.next().map(str::to_string).unwrap_or_else(|| file_name.clone());
+      let version = parts
+        .next()
+        .and_then(|version_str| version_str.parse::<u64>().ok())
+        .unwrap_or_default();
+
+      if let Some((current, _)) = ring_files.get(&name) {
+        if *current > version {
+          continue;
+        }
+      }
+      ring_files.insert(name, (version, file_name));
+    }
+    Ok(ring_files)
+  }
+}
+
+/// Request a token from the Microsoft identity platform (either by authcode or refresh token).
+fn request_token(agent: &ureq::Agent, token_url: &str, form: &[(&str, &str)]) -> StoreResult<TokenResponse> {
+  match agent.post(token_url).send_form(form) {
+    Ok(response) => Ok(serde_json::from_reader(response.into_reader())?),
+    Err(ureq::Error::Status(status, response)) => Err(StoreError::IO(format!(
+      "Failed to obtain onedrive token (status {}): {}",
+      status,
+      response.into_string().unwrap_or_default()
+    ))),
+    Err(err) => Err(err.into()),
+  }
+}
+
+impl std::fmt::Debug for OneDriveBlockStore {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("OneDriveBlockStore")
+      .field("node_id", &self.node_id)
+      .field("name", &self.name)
+      .field("max_attempts", &self.max_attempts)
+      .finish()
+  }
+}
+
+impl BlockStore for OneDriveBlockStore {
+  fn node_id(&self) -> &str {
+    &self.node_id
+  }
+
+  fn list_ring_ids(&self) -> StoreResult<Vec<RingId>> {
+    Ok(
+      self
+        .list_ring_files()?
+        .into_iter()
+        .map(|(id, (version, _))| (id, version))
+        .collect(),
+    )
+  }
+
+  fn get_ring(&self, ring_id: &str) -> StoreResult<RingContent> {
+    match self.list_ring_files()?.get(ring_id) {
+      Some((version, file_name)) => match self.download(&["rings", file_name])? {
+        Some(content) => Ok((*version, content)),
+        _ => Err(StoreError::InvalidBlock(ring_id.to_string())),
+      },
+      None => Err(StoreError::InvalidBlock(ring_id.to_string())),
+    }
+  }
+
+  fn store_ring(&self, ring_id: &str, version: u64, raw: &[u8]) -> StoreResult<()> {
+    if !self.upload(&["rings", &format!("{}.{}", ring_id, version)], raw, true)? {
+      return Err(StoreError::Conflict(format!(
+        "Ring {} with version {} already exists",
+        ring_id, version
+      )));
+    }
+    Ok(())
+  }
+
+  fn change_logs(&self) -> StoreResult<Vec<ChangeLog>> {
+    self
+      .list_files("logs")?
+      .iter()
+      .map(|node_id| self.download_change_log(node_id))
+      .collect()
+  }
+
+  fn get_index(&self, _index_id: &str) -> StoreResult<Option<ZeroingWords>> {
+    // Intentionally left blank. This store is not supposed to be used directly
+    Ok(None)
+  }
+
+  fn store_index(&self, _index_id: &str, _raw: &[u8]) -> StoreResult<()> {
+    // Intentionally left blank. This store is not supposed to be used directly
+    Ok(())
+  }
+
+  fn add_block(&self, raw: &[u8]) -> StoreResult<String> {
+    let block_id = generate_block_id(raw);
+    self.upload_session(&Self::block_segments(&block_id)?, raw)?;
+
+    Ok(block_id)
+  }
+
+  fn get_block(&self, block: &str) -> StoreResult<ZeroingWords> {
+    match self.download(&Self::block_segments(block)?)? {
+      Some(content) => Ok(content),
+      _ => Err(StoreError::InvalidBlock(block.to_string())),
+    }
+  }
+
+  fn commit(&self, commit_id: &str, changes: &[Change]) -> StoreResult<()> {
+    let mut change_log = self.download_change_log(&self.node_id)?;
+    if !change_log.check_commit(commit_id, changes)? {
+      // The commit has been applied before (i.e. only the response got lost)
+      return Ok(());
+    }
+    change_log.changes.extend_from_slice(changes);
+    change_log.commits.push(commit_id.to_string());
+
+    self.upload_change_log(&change_log)
+  }
+
+  fn update_change_log(&self, change_log: ChangeLog) -> StoreResult<()> {
+    self.upload_change_log(&change_log)
+  }
+}
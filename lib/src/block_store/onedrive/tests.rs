@@ -0,0 +1,379 @@
+//! Tests of the `OneDriveBlockStore` against a minimal in-process Graph server.
+//!
+//! The server supports the token endpoint (refresh token only), simple uploads/downloads, upload sessions
+//! and paged children listings. Like OneDrive it creates missing folders on upload.
+//!
+use super::OneDriveBlockStore;
+use crate::block_store::{generate_commit_id, BlockStore, Change, ChangeLog, Operation, StoreError};
+use crate::memguard::weak::ZeroingWords;
+use percent_encoding::percent_decode_str;
+use rand::{distributions, thread_rng, Rng};
+use spectral::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tiny_http::{Request, Response, Server};
+use url::Url;
+
+const REFRESH_TOKEN: &str = "refresh/token+1";
+const CLIENT_ID: &str = "11111111-2222-3333-4444-555555555555";
+const PAGE_SIZE: usize = 2;
+
+#[derive(Default)]
+struct Drive {
+  files: BTreeMap<String, Vec<u8>>,
+  sessions: HashMap<String, (String, Vec<u8>)>,
+  access_token: Option<String>,
+}
+
+struct FakeGraphServer {
+  server: Arc<Server>,
+  port: u16,
+  drive: Arc<Mutex<Drive>>,
+  token_requests: Arc<AtomicU32>,
+}
+
+impl FakeGraphServer {
+  fn start() -> FakeGraphServer {
+    let server = Arc::new(Server::http("127.0.0.1:0").unwrap());
+    let port = server.server_addr().to_ip().unwrap().port();
+    let drive = Arc::new(Mutex::new(Drive::default()));
+    let token_requests = Arc::new(AtomicU32::new(0));
+    let handler = server.clone();
+    let handler_drive = drive.clone();
+    let handler_token_requests = token_requests.clone();
+
+    thread::spawn(move || {
+      for request in handler.incoming_requests() {
+        handle_request(
+          port,
+          &mut handler_drive.lock().unwrap(),
+          &handler_token_requests,
+          request,
+        );
+      }
+    });
+
+    FakeGraphServer {
+      server,
+      port,
+      drive,
+      token_requests,
+    }
+  }
+
+  fn open_store(&self, name: &str, node_id: &str) -> OneDriveBlockStore {
+    let mut store_url = Url::parse(&format!("onedrive://{}", name)).unwrap();
+    store_url.set_username(REFRESH_TOKEN).unwrap();
+    store_url.query_pairs_mut().append_pair("client_id", CLIENT_ID);
+
+    OneDriveBlockStore::new(&store_url, node_id)
+      .unwrap()
+      .with_endpoints(
+        &format!("http://127.0.0.1:{}/v1.0", self.port),
+        &format!("http://127.0.0.1:{}/token", self.port),
+      )
+      .with_retry_delay(Duration::from_millis(1))
+  }
+
+  fn expire_access_token(&self) {
+    self.drive.lock().unwrap().access_token = None;
+  }
+}
+
+impl Drop for FakeGraphServer {
+  fn drop(&mut self) {
+    self.server.unblock();
+  }
+}
+
+fn handle_request(port: u16, drive: &mut Drive, token_requests: &AtomicU32, mut request: Request) {
+  let mut content = vec![];
+  request.as_reader().read_to_end(&mut content).unwrap();
+  let (path, query) = match request.url().split_once('?') {
+    Some((path, query)) => (path.to_string(), query.to_string()),
+    None => (request.url().to_string(), String::new()),
+  };
+
+  if path == "/token" {
+    let form: HashMap<String, String> = url::form_urlencoded::parse(&content).into_owned().collect();
+    let response = if form.get("grant_type").map(String::as_str) == Some("refresh_token")
+      && form.get("refresh_token").map(String::as_str) == Some(REFRESH_TOKEN)
+      && form.get("client_id").map(String::as_str) == Some(CLIENT_ID)
+    {
+      let access_token = format!("access{}", token_requests.fetch_add(1, Ordering::SeqCst));
+      drive.access_token = Some(access_token.clone());
+      Response::from_string(format!(
+        r#"{{"token_type":"Bearer","access_token":"{}","expires_in":3600}}"#,
+        access_token
+      ))
+    } else {
+      Response::from_string(r#"{"error":"invalid_grant"}"#).with_status_code(400)
+    };
+    request.respond(response).ok();
+    return;
+  }
+  if let Some(session_id) = path.strip_prefix("/upload/") {
+    let response = upload_chunk(drive, session_id, &request, content);
+    request.respond(response).ok();
+    return;
+  }
+
+  let authorization = request
+    .headers()
+    .iter()
+    .find(|header| header.field.equiv("Authorization"))
+    .map(|header| header.value.as_str().to_string());
+  let response = match (path.strip_prefix("/v1.0/me/drive/root:/"), &drive.access_token) {
+    (_, Some(access_token)) if authorization != Some(format!("Bearer {}", access_token)) => {
+      Response::from_string("").with_status_code(401)
+    }
+    (_, None) => Response::from_string("").with_status_code(401),
+    (Some(item), _) => {
+      let (item_path, action) = item.rsplit_once(":/").unwrap();
+      let item_path = percent_decode_str(item_path).decode_utf8_lossy().to_string();
+
+      match (request.method().as_str(), action) {
+        ("GET", "content") => match drive.files.get(&item_path) {
+          Some(content) => Response::from_data(content.clone()),
+          None => Response::from_string("").with_status_code(404),
+        },
+        ("PUT", "content") => {
+          if drive.files.contains_key(&item_path) && query.contains("conflictBehavior=fail") {
+            Response::from_string("").with_status_code(409)
+          } else {
+            drive.files.insert(item_path, content);
+            Response::from_string("{}").with_status_code(201)
+          }
+        }
+        ("POST", "createUploadSession") => {
+          let session_id = format!("session{}", drive.sessions.len());
+          drive.sessions.insert(session_id.clone(), (item_path, vec![]));
+          Response::from_string(format!(
+            r#"{{"uploadUrl":"http://127.0.0.1:{}/upload/{}"}}"#,
+            port, session_id
+          ))
+        }
+        ("GET", "children") => list_children(port, drive, item, &item_path, &query),
+        _ => Response::from_string("").with_status_code(405),
+      }
+    }
+    _ => Response::from_string("").with_status_code(404),
+  };
+
+  request.respond(response).ok();
+}
+
+fn upload_chunk(
+  drive: &mut Drive,
+  session_id: &str,
+  request: &Request,
+  chunk: Vec<u8>,
+) -> Response<std::io::Cursor<Vec<u8>>> {
+  let content_range = request
+    .headers()
+    .iter()
+    .find(|header| header.field.equiv("Content-Range"))
+    .map(|header| header.value.as_str().to_string())
+    .unwrap_or_default();
+  let (range, total) = content_range.trim_start_matches("bytes ").split_once('/').unwrap();
+  let (start, end) = range.split_once('-').unwrap();
+  let (start, end, total) = (
+    start.parse::<usize>().unwrap(),
+    end.parse::<usize>().unwrap(),
+    total.parse::<usize>().unwrap(),
+  );
+  let (item_path, content) = match drive.sessions.get_mut(session_id) {
+    Some(session) => session,
+    None => return Response::from_string("").with_status_code(404),
+  };
+  if start != content.len() || end + 1 - start != chunk.len() {
+    return Response::from_string("").with_status_code(416);
+  }
+  content.extend(chunk);
+  if content.len() < total {
+    return Response::from_string("{}").with_status_code(202);
+  }
+  let item_path = item_path.clone();
+  let content = std::mem::take(content);
+  drive.sessions.remove(session_id);
+  drive.files.insert(item_path, content);
+
+  Response::from_string("{}").with_status_code(201)
+}
+
+fn list_children(
+  port: u16,
+  drive: &Drive,
+  item: &str,
+  item_path: &str,
+  query: &str,
+) -> Response<std::io::Cursor<Vec<u8>>> {
+  let prefix = format!("{}/", item_path);
+  let mut children: Vec<String> = vec![];
+
+  for file_path in drive.files.keys().filter(|file_path| file_path.starts_with(&prefix)) {
+    let child = match file_path[prefix.len()..].split_once('/') {
+      Some((folder, _)) => format!(r#"{{"name":"{}","folder":{{"childCount":1}}}}"#, folder),
+      None => format!(
+        r#"{{"name":"{}","file":{{"mimeType":"application/octet-stream"}}}}"#,
+        &file_path[prefix.len()..]
+      ),
+    };
+    if !children.contains(&child) {
+      children.push(child);
+    }
+  }
+  if children.is_empty() {
+    return Response::from_string("").with_status_code(404);
+  }
+  let page = query
+    .strip_prefix("page=")
+    .and_then(|page| page.parse::<usize>().ok())
+    .unwrap_or_default();
+  let next_link = if (page + 1) * PAGE_SIZE < children.len() {
+    format!(
+      r#","@odata.nextLink":"http://127.0.0.1:{}/v1.0/me/drive/root:/{}?page={}""#,
+      port,
+      item,
+      page + 1
+    )
+  } else {
+    String::new()
+  };
+  let value = children
+    .iter()
+    .skip(page * PAGE_SIZE)
+    .take(PAGE_SIZE)
+    .cloned()
+    .collect::<Vec<String>>()
+    .join(",");
+
+  Response::from_string(format!(r#"{{"value":[{}]{}}}"#, value, next_link))
+}
+
+fn random_block(len: usize) -> Vec<u8> {
+  thread_rng()
+    .sample_iter(distributions::Standard)
+    .take(len)
+    .collect::<Vec<u8>>()
+}
+
+#[test]
+fn test_onedrive_rings() {
+  let server = FakeGraphServer::start();
+  let store = server.open_store("store", "node1");
+  let ring1 = random_block(200 * 8);
+  let ring2 = random_block(300 * 8);
+  let ring1_update = random_block(100 * 8);
+
+  assert_that(&store.list_ring_ids()).is_ok().is_empty();
+  assert_that(&store.store_ring("ring1", 0, &ring1)).is_ok();
+  assert_that(&store.store_ring("ring2", 0, &ring2)).is_ok();
+  assert_that(&store.store_ring("ring1", 1, &ring1_update)).is_ok();
+  assert_that(&store.store_ring("ring1", 1, &ring1)).is_err_containing(StoreError::Conflict(
+    "Ring ring1 with version 1 already exists".to_string(),
+  ));
+
+  let mut ring_ids = store.list_ring_ids().unwrap();
+  ring_ids.sort();
+  assert_that(&ring_ids).is_equal_to(vec![("ring1".to_string(), 1), ("ring2".to_string(), 0)]);
+  assert_that(&store.get_ring("ring1")).is_ok_containing((1, ZeroingWords::from(ring1_update.as_ref())));
+  assert_that(&store.get_ring("ring2")).is_ok_containing((0, ZeroingWords::from(ring2.as_ref())));
+  assert_that(&store.get_ring("ring3")).is_err_containing(StoreError::InvalidBlock("ring3".to_string()));
+  assert_that(&server.drive.lock().unwrap().files.contains_key("store/rings/ring1.1")).is_true();
+}
+
+#[test]
+fn test_onedrive_blocks_and_change_logs() {
+  let server = FakeGraphServer::start();
+  let store1 = server.open_store("store", "node1");
+  let store2 = server.open_store("store", "node2");
+  let small_block = random_block(100 * 8);
+  // Has to be uploaded in multiple chunks
+  let large_block = random_block(2 * super::UPLOAD_CHUNK_SIZE + 123 * 8);
+
+  let small_id = store1.add_block(&small_block).unwrap();
+  let large_id = store2.add_block(&large_block).unwrap();
+
+  assert_that(&store2.get_block(&small_id)).is_ok_containing(ZeroingWords::from(small_block.as_ref()));
+  assert_that(&store1.get_block(&large_id)).is_ok_containing(ZeroingWords::from(large_block.as_ref()));
+  assert_that(&store1.get_block("1234567890")).is_err_containing(StoreError::InvalidBlock("1234567890".to_string()));
+  assert_that(&server.drive.lock().unwrap().sessions).is_empty();
+  assert_that(&server.drive.lock().unwrap().files.contains_key(&format!(
+    "store/blocks/{}/{}",
+    &small_id[0..2],
+    small_id
+  )))
+  .is_true();
+
+  assert_that(&store1.change_logs()).is_ok().is_empty();
+  let commit_id = generate_commit_id();
+  let changes = vec![Change::new(Operation::Add, &small_id)];
+  assert_that(&store1.commit(&commit_id, &changes)).is_ok();
+  // Retry of the same commit
+  assert_that(&store1.commit(&commit_id, &changes)).is_ok();
+  assert_that(&store2.commit(&generate_commit_id(), &[Change::new(Operation::Add, &large_id)])).is_ok();
+  assert_that(&store2.commit(&generate_commit_id(), &[Change::new(Operation::Delete, &large_id)])).is_ok();
+
+  let mut other_log = ChangeLog::new("node3");
+  other_log.changes.push(Change::new(Operation::Add, &small_id));
+  other_log.commits.push(generate_commit_id());
+  assert_that(&store1.update_change_log(other_log.clone())).is_ok();
+
+  let mut change_logs = store2.change_logs().unwrap();
+  change_logs.sort_by(|log1, log2| log1.node.cmp(&log2.node));
+  assert_that(&change_logs).has_length(3);
+  assert_that(&change_logs[0].node.as_str()).is_equal_to("node1");
+  assert_that(&change_logs[0].changes).is_equal_to(changes);
+  assert_that(&change_logs[0].commits).is_equal_to(vec![commit_id]);
+  assert_that(&change_logs[1].changes).is_equal_to(vec![
+    Change::new(Operation::Add, &large_id),
+    Change::new(Operation::Delete, &large_id),
+  ]);
+  assert_that(&change_logs[2]).is_equal_to(&other_log);
+
+  // Indexes are not supported
+  assert_that(&store1.store_index("index", &small_block)).is_ok();
+  assert_that(&store1.get_index("index")).is_ok_containing(None);
+}
+
+#[test]
+fn test_onedrive_renew_access_token() {
+  let server = FakeGraphServer::start();
+  let store = server.open_store("store", "node1");
+  let block = random_block(10 * 8);
+
+  let block_id = store.add_block(&block).unwrap();
+  assert_that(&server.token_requests.load(Ordering::SeqCst)).is_equal_to(1);
+
+  server.expire_access_token();
+  assert_that(&store.get_block(&block_id)).is_ok_containing(ZeroingWords::from(block.as_ref()));
+  assert_that(&server.token_requests.load(Ordering::SeqCst)).is_equal_to(2);
+}
+
+#[test]
+fn test_onedrive_invalid_refresh_token() {
+  let server = FakeGraphServer::start();
+  let store_url = format!("onedrive://invalid@store?client_id={}", CLIENT_ID);
+  let store = OneDriveBlockStore::new(&Url::parse(&store_url).unwrap(), "node1")
+    .unwrap()
+    .with_endpoints(
+      &format!("http://127.0.0.1:{}/v1.0", server.port),
+      &format!("http://127.0.0.1:{}/token", server.port),
+    );
+
+  assert_that(&store.list_ring_ids())
+    .is_err()
+    .matches(|error| matches!(error, StoreError::IO(_)));
+}
+
+#[test]
+fn test_onedrive_invalid_url() {
+  for url in ["onedrive://store?client_id=abc", "onedrive://token@store"] {
+    assert_that(&OneDriveBlockStore::new(&Url::parse(url).unwrap(), "node1").map(|_| ()))
+      .is_err_containing(StoreError::InvalidStoreUrl(url.to_string()));
+  }
+}
@@ -0,0 +1,413 @@
+use super::{
+  generate_block_id, BlockStore, Change, ChangeLog, Operation, RingContent, RingId, StoreError, StoreResult,
+};
+use crate::memguard::weak::ZeroingWords;
+use log::{debug, info};
+use percent_encoding::percent_decode_str;
+use ssh2::{CheckResult, ErrorCode, KnownHostFileKind, OpenFlags, OpenType, Session, Sftp};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use url::Url;
+
+const DEFAULT_PORT: u16 = 22;
+const SESSION_TIMEOUT_MS: u32 = 60_000;
+/// SFTP status code of a missing file (`SSH_FX_NO_SUCH_FILE`)
+const FX_NO_SUCH_FILE: i32 = 2;
+
+/// Connection parameters of a sftp store.
+///
+/// The url has the form `sftp://user@host:port/path/to/store`, supported query parameters are
+/// `key=<path>` for an explicit private key (the ssh agent is used otherwise) and `known_hosts=<path>`
+/// to override the default `~/.ssh/known_hosts`.
+///
+#[derive(Debug, PartialEq, Eq)]
+pub struct SftpConfig {
+  pub host: String,
+  pub port: u16,
+  pub username: String,
+  pub base_dir: PathBuf,
+  pub key_file: Option<PathBuf>,
+  pub known_hosts: Option<PathBuf>,
+}
+
+impl SftpConfig {
+  pub fn from_url(store_url: &Url) -> StoreResult<SftpConfig> {
+    let host = store_url
+      .host_str()
+      .filter(|host| !host.is_empty())
+      .ok_or_else(|| StoreError::InvalidStoreUrl(store_url.to_string()))?;
+    let username = match store_url.username() {
+      "" => std::env::var("USER").map_err(|_| StoreError::InvalidStoreUrl(store_url.to_string()))?,
+      username => percent_decode_str(username).decode_utf8_lossy().to_string(),
+    };
+    let mut key_file = None;
+    let mut known_hosts = None;
+
+    for (name, value) in store_url.query_pairs() {
+      match name.as_ref() {
+        "key" => key_file = Some(PathBuf::from(value.as_ref())),
+        "known_hosts" => known_hosts = Some(PathBuf::from(value.as_ref())),
+        _ => return Err(StoreError::InvalidStoreUrl(store_url.to_string())),
+      }
+    }
+
+    Ok(SftpConfig {
+      host: host.to_string(),
+      port: store_url.port().unwrap_or(DEFAULT_PORT),
+      username,
+      base_dir: PathBuf::from(percent_decode_str(store_url.path()).decode_utf8_lossy().as_ref()),
+      key_file,
+      known_hosts,
+    })
+  }
+}
+
+/// Block store on a remote host via SFTP.
+///
+/// The file layout is the same as of the `LocalDirBlockStore`, i.e. a store may be cloned
+/// to/from a local directory with rsync.
+///
+pub struct SftpBlockStore {
+  node_id: String,
+  base_dir: PathBuf,
+  sftp: Mutex<Sftp>,
+  // The session has to outlive the sftp channel
+  _session: Session,
+}
+
+impl SftpBlockStore {
+  pub fn new(config: &SftpConfig, node_id: &str) -> StoreResult<SftpBlockStore> {
+    let tcp = TcpStream::connect((config.host.as_str(), config.port))?;
+    let mut session = Session::new()?;
+
+    session.set_timeout(SESSION_TIMEOUT_MS);
+    session.set_tcp_stream(tcp);
+    session.handshake()?;
+    Self::check_host_key(&session, config)?;
+
+    match &config.key_file {
+      Some(key_file) => session.userauth_pubkey_file(&config.username, None, key_file, None)?,
+      None => session.userauth_agent(&config.username)?,
+    }
+    if !session.authenticated() {
+      return Err(StoreError::IO(format!(
+        "Authentication as {} on {} failed",
+        config.username, config.host
+      )));
+    }
+    let sftp = session.sftp()?;
+
+    info!(
+      "Opening sftp store on: {}:{}{}",
+      config.host,
+      config.port,
+      config.base_dir.to_string_lossy()
+    );
+    Ok(SftpBlockStore {
+      node_id: node_id.to_string(),
+      base_dir: config.base_dir.clone(),
+      sftp: Mutex::new(sftp),
+      _session: session,
+    })
+  }
+
+  /// Only connect to hosts with a known (and matching) host key.
+  fn check_host_key(session: &Session, config: &SftpConfig) -> StoreResult<()> {
+    let known_hosts_file = match &config.known_hosts {
+      Some(known_hosts) => known_hosts.clone(),
+      None => dirs::home_dir()
+        .ok_or_else(|| StoreError::IO("Unable to determine home directory".to_string()))?
+        .join(".ssh")
+        .join("known_hosts"),
+    };
+    let mut known_hosts = session.known_hosts()?;
+    known_hosts.read_file(&known_hosts_file, KnownHostFileKind::OpenSSH)?;
+    let (key, _) = session
+      .host_key()
+      .ok_or_else(|| StoreError::IO(format!("No host key for {}", config.host)))?;
+
+    match known_hosts.check_port(&config.host, config.port, key) {
+      CheckResult::Match => Ok(()),
+      CheckResult::Mismatch => Err(StoreError::IO(format!("Host key of {} has changed", config.host))),
+      CheckResult::NotFound | CheckResult::Failure => Err(StoreError::IO(format!(
+        "Unknown host {}, add it to {}",
+        config.host,
+        known_hosts_file.to_string_lossy()
+      ))),
+    }
+  }
+
+  fn is_not_found(error: &ssh2::Error) -> bool {
+    matches!(error.code(), ErrorCode::SFTP(FX_NO_SUCH_FILE))
+  }
+
+  fn read_optional_file(sftp: &Sftp, path: &Path) -> StoreResult<Option<Vec<u8>>> {
+    debug!("Try reading file: {}", path.to_string_lossy());
+    match sftp.open(path) {
+      Ok(mut file) => {
+        let mut content = Vec::with_capacity(1024);
+        file.read_to_end(&mut content)?;
+
+        Ok(Some(content))
+      }
+      Err(ref err) if Self::is_not_found(err) => Ok(None),
+      Err(err) => Err(err.into()),
+    }
+  }
+
+  /// Create a directory including all its missing parents.
+  fn create_dir_all(sftp: &Sftp, dir: &Path) -> StoreResult<()> {
+    match sftp.stat(dir) {
+      Ok(stat) if stat.is_dir() => Ok(()),
+      Ok(_) => Err(StoreError::IO(format!("{} is not a directory", dir.to_string_lossy()))),
+      Err(ref err) if Self::is_not_found(err) => {
+        if let Some(parent) = dir.parent() {
+          Self::create_dir_all(sftp, parent)?;
+        }
+        sftp.mkdir(dir, 0o700)?;
+        Ok(())
+      }
+      Err(err) => Err(err.into()),
+    }
+  }
+
+  fn write_file(sftp: &Sftp, path: &Path, flags: OpenFlags, content: &[u8]) -> StoreResult<()> {
+    if let Some(parent) = path.parent() {
+      Self::create_dir_all(sftp, parent)?;
+    }
+    let mut file = sftp.open_mode(path, flags, 0o600, OpenType::File)?;
+
+    file.write_all(content)?;
+    file.flush()?;
+
+    Ok(())
+  }
+
+  fn parse_change_log<R: Read>(node_id: &str, content: R) -> StoreResult<ChangeLog> {
+    let reader = BufReader::new(content);
+    let mut change_log = ChangeLog::new(node_id);
+
+    for maybe_line in reader.lines() {
+      let line = maybe_line?;
+      match line.split(' ').collect::<Vec<&str>>().as_slice() {
+        ["A", block] => change_log.changes.push(Change::new(Operation::Add, *block)),
+        ["D", block] => change_log.changes.push(Change::new(Operation::Delete, *block)),
+        ["C", commit_id] => change_log.commits.push(commit_id.to_string()),
+        _ => (),
+      }
+    }
+
+    Ok(change_log)
+  }
+
+  fn read_change_log(sftp: &Sftp, path: &Path, node_id: &str) -> StoreResult<ChangeLog> {
+    match Self::read_optional_file(sftp, path)? {
+      Some(content) => Self::parse_change_log(node_id, content.as_slice()),
+      None => Ok(ChangeLog::new(node_id)),
+    }
+  }
+
+  fn block_file(&self, block_id: &str) -> StoreResult<PathBuf> {
+    if block_id.len() < 3 {
+      return Err(StoreError::InvalidBlock(block_id.to_string()));
+    }
+    Ok(self.base_dir.join("blocks").join(&block_id[0..2]).join(block_id))
+  }
+
+  fn list_files(sftp: &Sftp, dir: &Path) -> StoreResult<Vec<(String, PathBuf)>> {
+    match sftp.readdir(dir) {
+      Ok(entries) => Ok(
+        entries
+          .into_iter()
+          .filter(|(_, stat)| stat.is_file())
+          .filter_map(|(path, _)| Some((path.file_name()?.to_string_lossy().to_string(), path)))
+          .collect(),
+      ),
+      Err(ref err) if Self::is_not_found(err) => Ok(vec![]),
+      Err(err) => Err(err.into()),
+    }
+  }
+
+  fn list_ring_files(&self, sftp: &Sftp) -> StoreResult<HashMap<String, (u64, PathBuf)>> {
+    let mut ring_files: HashMap<String, (u64, PathBuf)> = HashMap::new();
+
+    for (file_name, path) in Self::list_files(sftp, &self.base_dir.join("rings"))? {
+      let mut parts = file_name.split('.');
+      let name = parts.next().map(str::to_string).unwrap_or_else(|| file_name.clone());
+      let version = parts
+        .next()
+        .and_then(|version_str| version_str.parse::<u64>().ok())
+        .unwrap_or_default();
+
+      if let Some((current, _)) = ring_files.get(&name) {
+        if *current > version {
+          continue;
+        }
+      }
+      ring_files.insert(name, (version, path));
+    }
+    Ok(ring_files)
+  }
+}
+
+impl std::fmt::Debug for SftpBlockStore {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("SftpBlockStore")
+      .field("node_id", &self.node_id)
+      .field("base_dir", &self.base_dir)
+      .finish()
+  }
+}
+
+impl BlockStore for SftpBlockStore {
+  fn node_id(&self) -> &str {
+    &self.node_id
+  }
+
+  fn list_ring_ids(&self) -> StoreResult<Vec<RingId>> {
+    let sftp = self.sftp.lock()?;
+    Ok(
+      self
+        .list_ring_files(&sftp)?
+        .into_iter()
+        .map(|(id, (version, _))| (id, version))
+        .collect(),
+    )
+  }
+
+  fn get_ring(&self, ring_id: &str) -> StoreResult<RingContent> {
+    let sftp = self.sftp.lock()?;
+    match self.list_ring_files(&sftp)?.get(ring_id) {
+      Some((version, ring_file)) => match Self::read_optional_file(&sftp, ring_file)? {
+        Some(content) => Ok((*version, ZeroingWords::from(content.as_ref()))),
+        None => Err(StoreError::InvalidBlock(ring_id.to_string())),
+      },
+      None => Err(StoreError::InvalidBlock(ring_id.to_string())),
+    }
+  }
+
+  fn store_ring(&self, ring_id: &str, version: u64, raw: &[u8]) -> StoreResult<()> {
+    let sftp = self.sftp.lock()?;
+    let ring_file = self.base_dir.join("rings").join(format!("{}.{}", ring_id, version));
+
+    if sftp.stat(&ring_file).is_ok() {
+      return Err(StoreError::Conflict(format!(
+        "Ring {} with version {} already exists",
+        ring_id, version
+      )));
+    }
+    Self::write_file(
+      &sftp,
+      &ring_file,
+      OpenFlags::WRITE | OpenFlags::CREATE | OpenFlags::EXCLUSIVE,
+      raw,
+    )
+  }
+
+  fn change_logs(&self) -> StoreResult<Vec<ChangeLog>> {
+    debug!("Try retrieve change logs");
+    let sftp = self.sftp.lock()?;
+
+    Self::list_files(&sftp, &self.base_dir.join("logs"))?
+      .into_iter()
+      .map(|(node_id, path)| Self::read_change_log(&sftp, &path, &node_id))
+      .collect()
+  }
+
+  fn get_index(&self, index_id: &str) -> StoreResult<Option<ZeroingWords>> {
+    debug!("Try getting index  {}", index_id);
+    let sftp = self.sftp.lock()?;
+    let index_file = self.base_dir.join("indexes").join(&self.node_id).join(index_id);
+
+    Ok(Self::read_optional_file(&sftp, &index_file)?.map(|content| ZeroingWords::from(content.as_ref())))
+  }
+
+  fn store_index(&self, index_id: &str, raw: &[u8]) -> StoreResult<()> {
+    debug!("Try storing index  {}", index_id);
+    let sftp = self.sftp.lock()?;
+    let index_file = self.base_dir.join("indexes").join(&self.node_id).join(index_id);
+
+    Self::write_file(
+      &sftp,
+      &index_file,
+      OpenFlags::WRITE | OpenFlags::CREATE | OpenFlags::TRUNCATE,
+      raw,
+    )
+  }
+
+  fn add_block(&self, raw: &[u8]) -> StoreResult<String> {
+    let sftp = self.sftp.lock()?;
+    let block_id = generate_block_id(raw);
+    let block_file = self.block_file(&block_id)?;
+
+    Self::write_file(
+      &sftp,
+      &block_file,
+      OpenFlags::WRITE | OpenFlags::CREATE | OpenFlags::TRUNCATE,
+      raw,
+    )?;
+
+    Ok(block_id)
+  }
+
+  fn get_block(&self, block: &str) -> StoreResult<ZeroingWords> {
+    let sftp = self.sftp.lock()?;
+    let block_file = self.block_file(block)?;
+
+    match Self::read_optional_file(&sftp, &block_file)? {
+      Some(content) => Ok(ZeroingWords::from(content.as_ref())),
+      None => Err(StoreError::InvalidBlock(block.to_string())),
+    }
+  }
+
+  fn commit(&self, commit_id: &str, changes: &[Change]) -> StoreResult<()> {
+    let sftp = self.sftp.lock()?;
+    let log_file = self.base_dir.join("logs").join(&self.node_id);
+    let existing = Self::read_change_log(&sftp, &log_file, &self.node_id)?;
+
+    if !existing.check_commit(commit_id, changes)? {
+      return Ok(());
+    }
+    let mut buffer = Vec::with_capacity(1024);
+    for change in changes {
+      match change.op {
+        Operation::Add => writeln!(&mut buffer, "A {}", change.block)?,
+        Operation::Delete => writeln!(&mut buffer, "D {}", change.block)?,
+      }
+    }
+    writeln!(&mut buffer, "C {}", commit_id)?;
+
+    Self::write_file(
+      &sftp,
+      &log_file,
+      OpenFlags::WRITE | OpenFlags::CREATE | OpenFlags::APPEND,
+      &buffer,
+    )
+  }
+
+  fn update_change_log(&self, change_log: ChangeLog) -> StoreResult<()> {
+    let sftp = self.sftp.lock()?;
+    let log_file = self.base_dir.join("logs").join(&change_log.node);
+    let mut buffer = Vec::with_capacity(8192);
+
+    for change in &change_log.changes {
+      match change.op {
+        Operation::Add => writeln!(&mut buffer, "A {}", change.block)?,
+        Operation::Delete => writeln!(&mut buffer, "D {}", change.block)?,
+      }
+    }
+    for commit_id in &change_log.commits {
+      writeln!(&mut buffer, "C {}", commit_id)?;
+    }
+
+    Self::write_file(
+      &sftp,
+      &log_file,
+      OpenFlags::WRITE | OpenFlags::CREATE | OpenFlags::TRUNCATE,
+      &buffer,
+    )
+  }
+}
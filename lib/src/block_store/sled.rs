@@ -1,12 +1,22 @@
-use std::{collections::HashMap, path::Path};
+use std::{
+  collections::{HashMap, HashSet},
+  path::Path,
+};
 
-use sled::transaction::ConflictableTransactionError;
+use sled::transaction::{ConflictableTransactionError, ConflictableTransactionResult};
+use sled::Transactional;
 
-use crate::memguard::weak::ZeroingWords;
+use crate::memguard::{weak::ZeroingWords, SecretBytes};
 
-use super::{generate_block_id, BlockStore, Change, ChangeLog, RingContent, RingId, StoreError, StoreResult};
+use super::sled_crypt::SledCrypt;
+use super::{
+  generate_block_id, BlockStore, Change, ChangeLog, RingContent, RingId, StorageStats, StoreError, StoreResult,
+  VerifyReport,
+};
+
+const ENCRYPTION_MARKER_KEY: &str = "encryption";
+const ENCRYPTION_MARKER: &[u8] = b"t-rust-less device key";
 
-#[derive(Debug)]
 pub struct SledBlockStore {
   node_id: String,
   db: sled::Db,
@@ -14,31 +24,130 @@ pub struct SledBlockStore {
   indices: sled::Tree,
   blocks: sled::Tree,
   change_logs: sled::Tree,
+  commits: sled::Tree,
+  crypt: Option<SledCrypt>,
+}
+
+impl std::fmt::Debug for SledBlockStore {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("SledBlockStore")
+      .field("node_id", &self.node_id)
+      .field("encrypted", &self.crypt.is_some())
+      .finish()
+  }
 }
 
 impl SledBlockStore {
   pub fn new<P: AsRef<Path>>(db_file: P, node_id: &str) -> StoreResult<SledBlockStore> {
+    Self::open(db_file, node_id, None)
+  }
+
+  /// Open a sled database with local encryption-at-rest using a device key.
+  pub fn new_encrypted<P: AsRef<Path>>(
+    db_file: P,
+    node_id: &str,
+    device_key: &SecretBytes,
+  ) -> StoreResult<SledBlockStore> {
+    Self::open(db_file, node_id, Some(SledCrypt::new(device_key)?))
+  }
+
+  fn open<P: AsRef<Path>>(db_file: P, node_id: &str, crypt: Option<SledCrypt>) -> StoreResult<SledBlockStore> {
     let db = sled::open(db_file)?;
     let rings = db.open_tree("rings")?;
     let indices = db.open_tree("indices")?;
     let blocks = db.open_tree("blocks")?;
     let change_logs = db.open_tree("change_logs")?;
+    let commits = db.open_tree("commits")?;
 
-    Ok(SledBlockStore {
+    let store = SledBlockStore {
       node_id: node_id.to_string(),
       db,
       rings,
       indices,
       blocks,
       change_logs,
-    })
+      commits,
+      crypt,
+    };
+    store.check_encryption_marker()?;
+
+    Ok(store)
   }
 
-  fn list_ring_versions(&self) -> StoreResult<HashMap<String, (u64, String)>> {
-    let mut ring_versions: HashMap<String, (u64, String)> = HashMap::new();
+  /// Ensure that an existing database is opened with the same encryption settings (and device key)
+  /// it was created with.
+  fn check_encryption_marker(&self) -> StoreResult<()> {
+    let marker = self.db.get(ENCRYPTION_MARKER_KEY)?;
+    let is_empty =
+      self.rings.is_empty() && self.indices.is_empty() && self.blocks.is_empty() && self.change_logs.is_empty();
 
-    for key in self.rings.iter().keys() {
-      let key = String::from_utf8_lossy(key?.as_ref()).to_string();
+    match (&self.crypt, marker) {
+      (Some(crypt), Some(marker)) => {
+        let db_key = crypt.db_key("", ENCRYPTION_MARKER_KEY.as_bytes());
+        match crypt.open(&db_key, &marker) {
+          Ok((_, content)) if content.borrow().as_bytes() == ENCRYPTION_MARKER => Ok(()),
+          _ => Err(StoreError::IO(
+            "Database is encrypted with a different device key".to_string(),
+          )),
+        }
+      }
+      (Some(crypt), None) if is_empty => {
+        let db_key = crypt.db_key("", ENCRYPTION_MARKER_KEY.as_bytes());
+        let marker = crypt.seal(&db_key, ENCRYPTION_MARKER_KEY.as_bytes(), ENCRYPTION_MARKER)?;
+        self.db.insert(ENCRYPTION_MARKER_KEY, marker)?;
+        self.db.flush()?;
+        Ok(())
+      }
+      (Some(_), None) => Err(StoreError::IO("Database is not encrypted".to_string())),
+      (None, Some(_)) => Err(StoreError::IO("Database is encrypted".to_string())),
+      (None, None) => Ok(()),
+    }
+  }
+
+  fn db_key(&self, tree: &str, key: &str) -> Vec<u8> {
+    match &self.crypt {
+      Some(crypt) => crypt.db_key(tree, key.as_bytes()),
+      None => key.as_bytes().to_vec(),
+    }
+  }
+
+  fn seal(&self, db_key: &[u8], key: &str, value: &[u8]) -> StoreResult<Vec<u8>> {
+    match &self.crypt {
+      Some(crypt) => crypt.seal(db_key, key.as_bytes(), value),
+      None => Ok(value.to_vec()),
+    }
+  }
+
+  /// Access the original key and value of a raw database entry.
+  fn open_with<T, F>(&self, db_key: &[u8], raw: &[u8], f: F) -> StoreResult<T>
+  where
+    F: FnOnce(&[u8], &[u8]) -> StoreResult<T>,
+  {
+    match &self.crypt {
+      Some(crypt) => {
+        let (key, value) = crypt.open(db_key, raw)?;
+        let value_ref = value.borrow();
+        f(&key, value_ref.as_bytes())
+      }
+      None => f(db_key, raw),
+    }
+  }
+
+  /// Ids of the commits of a change log (change logs of older versions do not have any).
+  fn read_commits(&self, node: &str) -> StoreResult<Vec<String>> {
+    let db_key = self.db_key("commits", node);
+    match self.commits.get(&db_key)? {
+      Some(raw) => self.open_with(&db_key, &raw, |_, raw| Ok(rmp_serde::from_read(raw)?)),
+      None => Ok(vec![]),
+    }
+  }
+
+  fn ring_keys(&self) -> StoreResult<Vec<(String, u64, Vec<u8>)>> {
+    let mut ring_keys = vec![];
+
+    for kv in self.rings.iter() {
+      let (db_key, raw) = kv?;
+      let key = self.open_with(&db_key, &raw, |key, _| Ok(String::from_utf8_lossy(key).to_string()))?;
       let mut parts = key.split('.');
       let name = parts.next().map(str::to_string).unwrap_or_else(|| key.clone());
       let version = parts
@@ -46,12 +155,21 @@ impl SledBlockStore {
         .and_then(|version_str| version_str.parse::<u64>().ok())
         .unwrap_or_default();
 
+      ring_keys.push((name, version, db_key.to_vec()));
+    }
+    Ok(ring_keys)
+  }
+
+  fn list_ring_versions(&self) -> StoreResult<HashMap<String, (u64, Vec<u8>)>> {
+    let mut ring_versions: HashMap<String, (u64, Vec<u8>)> = HashMap::new();
+
+    for (name, version, db_key) in self.ring_keys()? {
       if let Some((current, _)) = ring_versions.get(&name) {
         if *current > version {
           continue;
         }
       }
-      ring_versions.insert(name, (version, key));
+      ring_versions.insert(name, (version, db_key));
     }
     Ok(ring_versions)
   }
@@ -81,19 +199,21 @@ impl BlockStore for SledBlockStore {
 
   fn get_ring(&self, ring_id: &str) -> StoreResult<RingContent> {
     match self.list_ring_versions()?.get(ring_id) {
-      Some((version, key)) => self
-        .rings
-        .get(key)?
-        .map(|ring| (*version, ring.as_ref().into()))
-        .ok_or_else(|| StoreError::InvalidBlock(ring_id.to_string())),
+      Some((version, db_key)) => match self.rings.get(db_key)? {
+        Some(raw) => self.open_with(db_key, &raw, |_, ring| Ok((*version, ring.into()))),
+        None => Err(StoreError::InvalidBlock(ring_id.to_string())),
+      },
       None => Err(StoreError::InvalidBlock(ring_id.to_string())),
     }
   }
 
   fn store_ring(&self, ring_id: &str, version: u64, raw: &[u8]) -> StoreResult<()> {
+    let key = format!("{}.{}", ring_id, version);
+    let db_key = self.db_key("rings", &key);
+    let sealed = self.seal(&db_key, &key, raw)?;
     if self
       .rings
-      .compare_and_swap::<String, &[u8], &[u8]>(format!("{}.{}", ring_id, version), None, Some(raw))?
+      .compare_and_swap::<&[u8], &[u8], Vec<u8>>(&db_key, None, Some(sealed))?
       .is_err()
     {
       return Err(StoreError::Conflict(format!(
@@ -112,69 +232,168 @@ impl BlockStore for SledBlockStore {
       .map(|kv| {
         let (k, v) = kv?;
 
-        let changes: Vec<Change> = rmp_serde::from_read(v.as_ref())?;
-        Ok(ChangeLog {
-          node: String::from_utf8_lossy(&k).to_string(),
-          changes,
-        })
+        let (node, changes) = self.open_with(&k, &v, |node, raw| {
+          let changes: Vec<Change> = rmp_serde::from_read(raw)?;
+          Ok((String::from_utf8_lossy(node).to_string(), changes))
+        })?;
+        let commits = self.read_commits(&node)?;
+
+        Ok(ChangeLog { node, changes, commits })
       })
       .collect()
   }
 
   fn get_index(&self, index_id: &str) -> StoreResult<Option<ZeroingWords>> {
-    Ok(self.indices.get(index_id)?.map(|index| index.as_ref().into()))
+    let db_key = self.db_key("indices", index_id);
+    match self.indices.get(&db_key)? {
+      Some(raw) => self.open_with(&db_key, &raw, |_, index| Ok(Some(index.into()))),
+      None => Ok(None),
+    }
   }
 
   fn store_index(&self, index_id: &str, raw: &[u8]) -> StoreResult<()> {
-    self.indices.insert(index_id, raw)?;
+    let db_key = self.db_key("indices", index_id);
+    self.indices.insert(&db_key, self.seal(&db_key, index_id, raw)?)?;
     self.indices.flush()?;
     Ok(())
   }
 
   fn add_block(&self, raw: &[u8]) -> StoreResult<String> {
     let block_id = generate_block_id(raw);
-    self.blocks.insert(&block_id, raw)?;
+    let db_key = self.db_key("blocks", &block_id);
+    self.blocks.insert(&db_key, self.seal(&db_key, &block_id, raw)?)?;
     self.blocks.flush()?;
     Ok(block_id)
   }
 
+  fn storage_stats(&self) -> StoreResult<StorageStats> {
+    let change_logs = self.change_logs()?;
+    let mut ring_versions: HashMap<String, usize> = HashMap::new();
+
+    for (name, _, _) in self.ring_keys()? {
+      *ring_versions.entry(name).or_default() += 1;
+    }
+
+    Ok(StorageStats::collect(
+      &change_logs,
+      ring_versions.into_iter().collect(),
+      |block| self.get_block(block).ok().map(|content| content.len() as u64 * 8),
+    ))
+  }
+
+  fn verify(&self) -> StoreResult<VerifyReport> {
+    let change_logs = self.change_logs()?;
+    let mut stored_blocks = vec![];
+
+    for kv in self.blocks.iter() {
+      let (db_key, raw) = kv?;
+      // Entries that can not be opened are reported as mismatched (if referenced at all)
+      if let Ok(block_id) = self.open_with(&db_key, &raw, |key, _| Ok(String::from_utf8_lossy(key).to_string())) {
+        stored_blocks.push(block_id);
+      }
+    }
+
+    VerifyReport::collect(&change_logs, Some(stored_blocks), |block| {
+      let db_key = self.db_key("blocks", block);
+      match self.blocks.get(&db_key)? {
+        Some(raw) => Ok(Some(
+          self
+            .open_with(&db_key, &raw, |_, content| Ok(generate_block_id(content) == block))
+            .unwrap_or(false),
+        )),
+        None => Ok(None),
+      }
+    })
+  }
+
+  fn collect_garbage(&self, referenced: &HashSet<String>) -> StoreResult<usize> {
+    let referenced_keys: HashSet<Vec<u8>> = referenced
+      .iter()
+      .map(|block_id| self.db_key("blocks", block_id))
+      .collect();
+    let mut removed = 0;
+
+    for kv in self.blocks.iter() {
+      let (db_key, _) = kv?;
+      if !referenced_keys.contains(db_key.as_ref()) {
+        self.blocks.remove(&db_key)?;
+        removed += 1;
+      }
+    }
+    self.blocks.flush()?;
+
+    Ok(removed)
+  }
+
+  fn flush(&self) -> StoreResult<()> {
+    self.db.flush()?;
+    Ok(())
+  }
+
   fn get_block(&self, block: &str) -> StoreResult<ZeroingWords> {
-    self
-      .blocks
-      .get(block)?
-      .map(|ring| ring.as_ref().into())
-      .ok_or_else(|| StoreError::InvalidBlock(block.to_string()))
-  }
-
-  fn commit(&self, changes: &[Change]) -> StoreResult<()> {
-    self.change_logs.transaction::<_, _, StoreError>(|tx| {
-      let new_changes = match tx.get(&self.node_id)? {
-        Some(existing_raw) => {
-          let mut existing: Vec<Change> = rmp_serde::from_read(existing_raw.as_ref())
-            .map_err(|e| ConflictableTransactionError::Abort(StoreError::from(e)))?;
-          if existing.iter().any(|change| changes.contains(change)) {
-            return Err(ConflictableTransactionError::Abort(StoreError::Conflict(
-              "Change already committed".to_string(),
-            )));
-          }
-          existing.extend_from_slice(changes);
-          existing
+    let db_key = self.db_key("blocks", block);
+    match self.blocks.get(&db_key)? {
+      Some(raw) => self.open_with(&db_key, &raw, |_, content| Ok(content.into())),
+      None => Err(StoreError::InvalidBlock(block.to_string())),
+    }
+  }
+
+  fn commit(&self, commit_id: &str, changes: &[Change]) -> StoreResult<()> {
+    let db_key = self.db_key("change_logs", &self.node_id);
+    let commits_key = self.db_key("commits", &self.node_id);
+    (&self.change_logs, &self.commits).transaction(
+      |(change_logs_tx, commits_tx)| -> ConflictableTransactionResult<(), StoreError> {
+        let mut change_log = ChangeLog::new(&self.node_id);
+        if let Some(existing_raw) = change_logs_tx.get(&db_key)? {
+          change_log.changes = self
+            .open_with(&db_key, &existing_raw, |_, raw| Ok(rmp_serde::from_read(raw)?))
+            .map_err(ConflictableTransactionError::Abort)?;
+        }
+        if let Some(existing_raw) = commits_tx.get(&commits_key)? {
+          change_log.commits = self
+            .open_with(&commits_key, &existing_raw, |_, raw| Ok(rmp_serde::from_read(raw)?))
+            .map_err(ConflictableTransactionError::Abort)?;
+        }
+        if !change_log
+          .check_commit(commit_id, changes)
+          .map_err(ConflictableTransactionError::Abort)?
+        {
+          return Ok(());
         }
-        None => changes.to_vec(),
-      };
-      let raw =
-        rmp_serde::to_vec_named(&new_changes).map_err(|e| ConflictableTransactionError::Abort(StoreError::from(e)))?;
-      tx.insert(self.node_id.as_str(), raw)?;
-      Ok(())
-    })?;
+        change_log.changes.extend_from_slice(changes);
+        change_log.commits.push(commit_id.to_string());
+
+        let raw = rmp_serde::to_vec_named(&change_log.changes)
+          .map_err(StoreError::from)
+          .and_then(|raw| self.seal(&db_key, &self.node_id, &raw))
+          .map_err(ConflictableTransactionError::Abort)?;
+        change_logs_tx.insert(db_key.as_slice(), raw)?;
+        let raw = rmp_serde::to_vec_named(&change_log.commits)
+          .map_err(StoreError::from)
+          .and_then(|raw| self.seal(&commits_key, &self.node_id, &raw))
+          .map_err(ConflictableTransactionError::Abort)?;
+        commits_tx.insert(commits_key.as_slice(), raw)?;
+        Ok(())
+      },
+    )?;
     self.change_logs.flush()?;
+    self.commits.flush()?;
     Ok(())
   }
 
   fn update_change_log(&self, change_log: ChangeLog) -> StoreResult<()> {
     let raw = rmp_serde::to_vec_named(&change_log.changes)?;
-    self.change_logs.insert(change_log.node.as_str(), raw)?;
+    let db_key = self.db_key("change_logs", &change_log.node);
+    self
+      .change_logs
+      .insert(&db_key, self.seal(&db_key, &change_log.node, &raw)?)?;
+    let raw = rmp_serde::to_vec_named(&change_log.commits)?;
+    let commits_key = self.db_key("commits", &change_log.node);
+    self
+      .commits
+      .insert(&commits_key, self.seal(&commits_key, &change_log.node, &raw)?)?;
     self.change_logs.flush()?;
+    self.commits.flush()?;
 
     Ok(())
   }
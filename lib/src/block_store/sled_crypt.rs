@@ -0,0 +1,191 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use byteorder::{BigEndian, ByteOrder};
+use chacha20_poly1305_aead::{decrypt, encrypt};
+use hmac::{Hmac, Mac};
+use rand::{thread_rng, RngCore};
+use sha2::Sha256;
+
+use crate::memguard::SecretBytes;
+
+use super::{StoreError, StoreResult};
+
+const DEVICE_KEY_LENGTH: usize = 32;
+const NONCE_LENGTH: usize = 12;
+const TAG_LENGTH: usize = 16;
+
+/// Local encryption-at-rest of a sled database.
+///
+/// All blocks of a store are already encrypted by the secrets store itself, but a sled database
+/// also contains plain meta data like block ids and the change logs of all nodes. With this enabled
+/// keys are replaced by a keyed hash and values are sealed (together with the original key) via
+/// ChaCha20-Poly1305. Both keys are derived from a random device key that never leaves the machine.
+///
+pub struct SledCrypt {
+  key_hash_key: SecretBytes,
+  value_key: SecretBytes,
+}
+
+impl SledCrypt {
+  pub fn new(device_key: &SecretBytes) -> StoreResult<SledCrypt> {
+    if device_key.len() != DEVICE_KEY_LENGTH {
+      return Err(StoreError::IO(format!(
+        "Device key has to be {} bytes, got {}",
+        DEVICE_KEY_LENGTH,
+        device_key.len()
+      )));
+    }
+    Ok(SledCrypt {
+      key_hash_key: derive_key(device_key, b"t-rust-less sled keys"),
+      value_key: derive_key(device_key, b"t-rust-less sled values"),
+    })
+  }
+
+  /// Opaque (but deterministic) key to store a value under.
+  pub fn db_key(&self, tree: &str, key: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(&self.key_hash_key.borrow()).unwrap();
+    mac.update(tree.as_bytes());
+    mac.update(&[0]);
+    mac.update(key);
+    mac.finalize().into_bytes().to_vec()
+  }
+
+  /// Seal a key-value pair. The result is bound to `db_key`, i.e. values can not be swapped around.
+  pub fn seal(&self, db_key: &[u8], key: &[u8], value: &[u8]) -> StoreResult<Vec<u8>> {
+    let mut nonce = [0u8; NONCE_LENGTH];
+    thread_rng().fill_bytes(&mut nonce);
+
+    let mut plain = SecretBytes::with_capacity(4 + key.len() + value.len());
+    {
+      let mut plain_ref = plain.borrow_mut();
+      let mut length = [0u8; 4];
+      BigEndian::write_u32(&mut length, key.len() as u32);
+      plain_ref.write_all(&length)?;
+      plain_ref.write_all(key)?;
+      plain_ref.write_all(value)?;
+    }
+
+    let mut sealed = Vec::with_capacity(NONCE_LENGTH + plain.len() + TAG_LENGTH);
+    sealed.extend_from_slice(&nonce);
+    let tag = encrypt(&self.value_key.borrow(), &nonce, db_key, &plain.borrow(), &mut sealed)?;
+    sealed.extend_from_slice(&tag);
+
+    Ok(sealed)
+  }
+
+  /// Open a sealed key-value pair, returns the original key and value.
+  pub fn open(&self, db_key: &[u8], sealed: &[u8]) -> StoreResult<(Vec<u8>, SecretBytes)> {
+    if sealed.len() < NONCE_LENGTH + TAG_LENGTH + 4 {
+      return Err(StoreError::IO("Encrypted value too short".to_string()));
+    }
+    let tag_offset = sealed.len() - TAG_LENGTH;
+    let mut plain = SecretBytes::with_capacity(tag_offset - NONCE_LENGTH);
+    decrypt(
+      &self.value_key.borrow(),
+      &sealed[0..NONCE_LENGTH],
+      db_key,
+      &sealed[NONCE_LENGTH..tag_offset],
+      &sealed[tag_offset..],
+      &mut plain.borrow_mut(),
+    )?;
+
+    let plain_ref = plain.borrow();
+    let key_length = BigEndian::read_u32(&plain_ref[0..4]) as usize;
+    if 4 + key_length > plain_ref.len() {
+      return Err(StoreError::IO("Invalid encrypted value".to_string()));
+    }
+    let key = plain_ref[4..4 + key_length].to_vec();
+    let value = SecretBytes::from_secured(&plain_ref[4 + key_length..]);
+
+    Ok((key, value))
+  }
+}
+
+fn derive_key(device_key: &SecretBytes, purpose: &[u8]) -> SecretBytes {
+  let mut mac = Hmac::<Sha256>::new_from_slice(&device_key.borrow()).unwrap();
+  mac.update(purpose);
+  SecretBytes::from(mac.finalize().into_bytes().to_vec())
+}
+
+/// Service name of device keys in the keyring of the OS.
+#[cfg(feature = "keyring")]
+const KEYRING_SERVICE: &str = "t-rust-less";
+
+/// Read the device key of the sled database at `db_file` from the keyring of the OS (i.e. Secret Service,
+/// macOS Keychain or Windows Credential Manager), a new random key is created if it does not exist yet.
+///
+/// This way the device key is protected by the login of the user and is not stored on the same disk as
+/// the database.
+#[cfg(feature = "keyring")]
+pub fn read_or_create_keyring_device_key(db_file: &Path) -> StoreResult<SecretBytes> {
+  use data_encoding::HEXLOWER;
+
+  let entry = keyring::Entry::new(KEYRING_SERVICE, &format!("sled:{}", db_file.to_string_lossy()))?;
+
+  match entry.get_password() {
+    Ok(encoded) => {
+      let encoded = SecretBytes::from(encoded);
+      let encoded_ref = encoded.borrow();
+      let mut device_key = SecretBytes::zeroed(DEVICE_KEY_LENGTH);
+      match HEXLOWER.decode_len(encoded_ref.len()) {
+        Ok(DEVICE_KEY_LENGTH) => {
+          HEXLOWER
+            .decode_mut(&encoded_ref, &mut device_key.borrow_mut())
+            .map_err(|_| StoreError::IO("Invalid device key in keyring".to_string()))?;
+        }
+        _ => return Err(StoreError::IO("Invalid device key in keyring".to_string())),
+      }
+      Ok(device_key)
+    }
+    Err(keyring::Error::NoEntry) => {
+      let device_key = SecretBytes::random(&mut thread_rng(), DEVICE_KEY_LENGTH);
+      let mut encoded = SecretBytes::zeroed(HEXLOWER.encode_len(DEVICE_KEY_LENGTH));
+      HEXLOWER.encode_mut(&device_key.borrow(), &mut encoded.borrow_mut());
+      entry.set_password(encoded.borrow().as_str())?;
+      Ok(device_key)
+    }
+    Err(err) => Err(err.into()),
+  }
+}
+
+#[cfg(not(feature = "keyring"))]
+pub fn read_or_create_keyring_device_key(_db_file: &Path) -> StoreResult<SecretBytes> {
+  Err(StoreError::IO(
+    "No keyring support, the device key has to be provided via key_file".to_string(),
+  ))
+}
+
+/// Read the device key from `key_file`, a new random key is created if it does not exist yet.
+///
+/// This is an alternative for systems without a keyring. The key file is only readable by the current
+/// user, but anyone with access to the file (e.g. a stolen disk containing both) is able to decrypt the
+/// database, so it should reside on a different medium than the database itself.
+pub fn read_or_create_device_key<P: AsRef<Path>>(key_file: P) -> StoreResult<SecretBytes> {
+  match File::open(key_file.as_ref()) {
+    Ok(mut file) => {
+      let mut device_key = SecretBytes::zeroed(DEVICE_KEY_LENGTH);
+      file.read_exact(&mut device_key.borrow_mut())?;
+      Ok(device_key)
+    }
+    Err(ref err) if err.kind() == io::ErrorKind::NotFound => {
+      if let Some(parent) = key_file.as_ref().parent() {
+        fs::create_dir_all(parent)?;
+      }
+      let device_key = SecretBytes::random(&mut thread_rng(), DEVICE_KEY_LENGTH);
+      let mut options = OpenOptions::new();
+      options.write(true).create_new(true);
+      #[cfg(unix)]
+      {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+      }
+      let mut file = options.open(key_file.as_ref())?;
+      file.write_all(&device_key.borrow())?;
+      file.sync_all()?;
+      Ok(device_key)
+    }
+    Err(err) => Err(err.into()),
+  }
+}
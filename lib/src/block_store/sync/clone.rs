@@ -0,0 +1,75 @@
+use std::fs;
+use std::sync::Arc;
+
+use log::info;
+use url::Url;
+
+use crate::api::SyncReport;
+use crate::block_store::{open_block_store, BlockStore, StoreError, StoreResult};
+
+use super::SyncBlockStore;
+
+/// Create a new local block store as copy of an existing remote store.
+///
+/// Nothing is written locally unless the remote actually contains a store (i.e. at least one
+/// ring). An existing local store is never overwritten, for directory based stores the
+/// directory has to be empty (or must not exist at all).
+///
+/// Up to `concurrency` blocks are downloaded at the same time.
+pub fn clone_store(local_url: &str, remote_url: &str, node_id: &str, concurrency: usize) -> StoreResult<SyncReport> {
+  let remote = open_block_store(remote_url, node_id)?;
+
+  clone_from_remote(local_url, remote, node_id, concurrency)
+}
+
+pub fn clone_from_remote(
+  local_url: &str,
+  remote: Arc<dyn BlockStore>,
+  node_id: &str,
+  concurrency: usize,
+) -> StoreResult<SyncReport> {
+  if remote.list_ring_ids()?.is_empty() {
+    return Err(StoreError::NoStore(format!("{:?}", remote)));
+  }
+  prepare_local_dir(local_url)?;
+
+  let local = open_block_store(local_url, node_id)?;
+
+  if !local.list_ring_ids()?.is_empty() || !local.change_logs()?.is_empty() {
+    return Err(StoreError::Conflict(format!("Store already exists at {}", local_url)));
+  }
+  info!("Cloning {:?} to {}", remote, local_url);
+
+  let sync_store = SyncBlockStore::new(local, remote);
+
+  sync_store.set_concurrency(concurrency);
+  let report = sync_store.synchronize_report()?;
+
+  if let Some(error) = report.errors.first() {
+    return Err(StoreError::IO(format!("{} ({})", error.error, error.node)));
+  }
+
+  Ok(report)
+}
+
+/// Ensure that the directory of a file based store exists and is empty
+fn prepare_local_dir(local_url: &str) -> StoreResult<()> {
+  let store_url = Url::parse(local_url)?;
+
+  if !matches!(store_url.scheme(), "file" | "wal") {
+    return Ok(());
+  }
+  let path = store_url
+    .to_file_path()
+    .map_err(|_| StoreError::InvalidStoreUrl(local_url.to_string()))?;
+
+  if path.exists() && (!path.is_dir() || fs::read_dir(&path)?.next().is_some()) {
+    return Err(StoreError::Conflict(format!(
+      "{} already exists and is not empty",
+      path.to_string_lossy()
+    )));
+  }
+  fs::create_dir_all(&path)?;
+
+  Ok(())
+}
@@ -0,0 +1,211 @@
+use spectral::prelude::*;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tempfile::Builder;
+use url::Url;
+
+use crate::api::{EventData, EventHub, Identity, StoreConfig, DEFAULT_SYNC_CONCURRENCY};
+use crate::block_store::{
+  open_block_store, BlockStore, Change, ChangeLog, Operation, RingContent, RingId, StoreError, StoreResult,
+};
+use crate::memguard::weak::ZeroingWords;
+use crate::memguard::SecretBytes;
+use crate::secrets_store::open_secrets_store;
+
+use super::{clone_from_remote, clone_store};
+
+/// Remote store recording all modifying operations
+#[derive(Debug)]
+struct RecordingBlockStore {
+  inner: Arc<dyn BlockStore>,
+  modifications: Mutex<Vec<String>>,
+}
+
+impl RecordingBlockStore {
+  fn new(inner: Arc<dyn BlockStore>) -> RecordingBlockStore {
+    RecordingBlockStore {
+      inner,
+      modifications: Mutex::new(vec![]),
+    }
+  }
+
+  fn record(&self, operation: &str) -> StoreResult<()> {
+    self.modifications.lock()?.push(operation.to_string());
+    Ok(())
+  }
+}
+
+impl BlockStore for RecordingBlockStore {
+  fn node_id(&self) -> &str {
+    self.inner.node_id()
+  }
+
+  fn list_ring_ids(&self) -> StoreResult<Vec<RingId>> {
+    self.inner.list_ring_ids()
+  }
+
+  fn get_ring(&self, ring_id: &str) -> StoreResult<RingContent> {
+    self.inner.get_ring(ring_id)
+  }
+
+  fn store_ring(&self, ring_id: &str, version: u64, raw: &[u8]) -> StoreResult<()> {
+    self.record("store_ring")?;
+    self.inner.store_ring(ring_id, version, raw)
+  }
+
+  fn change_logs(&self) -> StoreResult<Vec<ChangeLog>> {
+    self.inner.change_logs()
+  }
+
+  fn get_index(&self, index_id: &str) -> StoreResult<Option<ZeroingWords>> {
+    self.inner.get_index(index_id)
+  }
+
+  fn store_index(&self, index_id: &str, raw: &[u8]) -> StoreResult<()> {
+    self.record("store_index")?;
+    self.inner.store_index(index_id, raw)
+  }
+
+  fn add_block(&self, raw: &[u8]) -> StoreResult<String> {
+    self.record("add_block")?;
+    self.inner.add_block(raw)
+  }
+
+  fn get_block(&self, block: &str) -> StoreResult<ZeroingWords> {
+    self.inner.get_block(block)
+  }
+
+  fn commit(&self, commit_id: &str, changes: &[Change]) -> StoreResult<()> {
+    self.record("commit")?;
+    self.inner.commit(commit_id, changes)
+  }
+
+  fn update_change_log(&self, change_log: ChangeLog) -> StoreResult<()> {
+    self.record("update_change_log")?;
+    self.inner.update_change_log(change_log)
+  }
+}
+
+fn dir_url(path: &Path) -> String {
+  Url::from_directory_path(path).unwrap().to_string()
+}
+
+fn remote_with_store() -> Arc<dyn BlockStore> {
+  let remote = open_block_store("memory://", "other").unwrap();
+  let block_id = remote.add_block(&[1u8; 64]).unwrap();
+
+  remote.store_ring("identity1", 0, &[2u8; 64]).unwrap();
+  remote
+    .commit(
+      "commit1",
+      &[Change {
+        op: Operation::Add,
+        block: block_id,
+      }],
+    )
+    .unwrap();
+
+  remote
+}
+
+#[test]
+fn test_clone_into_new_dir() {
+  let tempdir = Builder::new().prefix("t-rust-less-clone").tempdir().unwrap();
+  let local_path = tempdir.path().join("store");
+  let remote = Arc::new(RecordingBlockStore::new(remote_with_store()));
+
+  let report = clone_from_remote(&dir_url(&local_path), remote.clone(), "node1", DEFAULT_SYNC_CONCURRENCY).unwrap();
+
+  assert_that!(report.rings_pulled).is_equal_to(1);
+  assert_that!(report.blocks_pulled).is_equal_to(1);
+  assert_that!(report.blocks_pushed).is_equal_to(0);
+  assert_that!(*remote.modifications.lock().unwrap()).is_equal_to(Vec::<String>::new());
+
+  let local = open_block_store(&dir_url(&local_path), "node1").unwrap();
+
+  assert_that!(local.list_ring_ids()).is_ok_containing(vec![("identity1".to_string(), 0u64)]);
+  assert_that!(local.change_logs()).is_ok_containing(remote.change_logs().unwrap());
+}
+
+#[test]
+fn test_clone_requires_remote_store() {
+  let tempdir = Builder::new().prefix("t-rust-less-clone").tempdir().unwrap();
+  let local_path = tempdir.path().join("store");
+  let remote = open_block_store("memory://", "other").unwrap();
+
+  assert_that!(clone_from_remote(
+    &dir_url(&local_path),
+    remote,
+    "node1",
+    DEFAULT_SYNC_CONCURRENCY
+  ))
+  .is_err()
+  .matches(|error| matches!(error, StoreError::NoStore(_)));
+  assert_that!(local_path.exists()).is_false();
+}
+
+#[test]
+fn test_clone_refuses_existing_store() {
+  let tempdir = Builder::new().prefix("t-rust-less-clone").tempdir().unwrap();
+  let existing = tempdir.path().join("existing");
+  fs::write(&existing, b"precious").unwrap();
+
+  assert_that!(clone_from_remote(
+    &dir_url(tempdir.path()),
+    remote_with_store(),
+    "node1",
+    DEFAULT_SYNC_CONCURRENCY
+  ))
+  .is_err()
+  .matches(|error| matches!(error, StoreError::Conflict(_)));
+  assert_that!(fs::read(&existing)).is_ok_containing(b"precious".to_vec());
+}
+
+struct TestEventHub;
+
+impl EventHub for TestEventHub {
+  fn send(&self, _event: EventData) {}
+}
+
+#[test]
+#[cfg_attr(debug_assertions, ignore)]
+fn test_clone_and_unlock() {
+  let tempdir = Builder::new().prefix("t-rust-less-clone").tempdir().unwrap();
+  let remote_path = tempdir.path().join("remote");
+  let local_path = tempdir.path().join("local");
+  fs::create_dir_all(&remote_path).unwrap();
+  let (remote_secrets_store, _) = open_secrets_store(
+    &StoreConfig::new("remote", format!("multilane+{}", dir_url(&remote_path)), "node1"),
+    Arc::new(TestEventHub),
+  )
+  .unwrap();
+  let identity = Identity {
+    id: "identity1".to_string(),
+    name: "Name1".to_string(),
+    email: "Email1".to_string(),
+    hidden: false,
+    hardware_factor: false,
+  };
+  remote_secrets_store
+    .add_identity(identity.clone(), SecretBytes::from("Passphrase1".to_string()))
+    .unwrap();
+
+  clone_store(
+    &dir_url(&local_path),
+    &dir_url(&remote_path),
+    "node2",
+    DEFAULT_SYNC_CONCURRENCY,
+  )
+  .unwrap();
+
+  let (secrets_store, _) = open_secrets_store(
+    &StoreConfig::new("local", format!("multilane+{}", dir_url(&local_path)), "node2"),
+    Arc::new(TestEventHub),
+  )
+  .unwrap();
+
+  assert_that!(secrets_store.status().unwrap().locked).is_true();
+  assert_that!(secrets_store.identities()).is_ok_containing(vec![identity]);
+  assert_that!(secrets_store.unlock("identity1", SecretBytes::from("Passphrase1".to_string()))).is_ok();
+}
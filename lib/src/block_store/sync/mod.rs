@@ -1,37 +1,138 @@
-use std::sync::{Arc, Mutex};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Arc;
 
+use futures::executor::block_on;
+use futures::lock::Mutex;
+
+use crate::api::{SyncProgress, SyncReport, DEFAULT_SYNC_CONCURRENCY};
 use crate::memguard::weak::ZeroingWords;
 
-use super::{BlockStore, ChangeLog, RingContent, RingId, StoreError, StoreResult};
+use super::async_block_store::{AsyncBlockStore, BlockingAsyncStore};
+use super::{
+  committed_block_ids, BlockStore, ChangeLog, RingContent, RingId, StorageStats, StoreError, StoreResult, VerifyReport,
+};
 
+mod clone;
 mod synchronize;
+mod throttle;
 
+#[cfg(test)]
+mod clone_tests;
 #[cfg(test)]
 mod synchronize_tests;
+#[cfg(test)]
+mod throttle_tests;
 
+pub use self::clone::{clone_from_remote, clone_store};
+pub use self::throttle::{RateLimiter, ThrottledAsyncStore};
+
+/// Local block store that is synchronized with a remote store.
+///
+/// All regular operations only use the local store (with the exception of blocks and rings that
+/// have not been synchronized yet), the remote is only accessed asynchronously, see
+/// `async_block_store` for the threading model.
 #[derive(Debug)]
 pub struct SyncBlockStore {
   local: Arc<dyn BlockStore>,
-  remote: Arc<dyn BlockStore>,
+  remote: Arc<dyn AsyncBlockStore>,
+  rate_limiter: Arc<RateLimiter>,
+  concurrency: AtomicUsize,
   sync_lock: Arc<Mutex<()>>,
 }
 
 impl SyncBlockStore {
   pub fn new(local: Arc<dyn BlockStore>, remote: Arc<dyn BlockStore>) -> SyncBlockStore {
+    Self::with_async_remote(local, Arc::new(BlockingAsyncStore::new(remote)))
+  }
+
+  pub fn with_async_remote(local: Arc<dyn BlockStore>, remote: Arc<dyn AsyncBlockStore>) -> SyncBlockStore {
+    let rate_limiter = Arc::new(RateLimiter::new(0));
+
     SyncBlockStore {
       local,
-      remote,
+      remote: Arc::new(ThrottledAsyncStore::new(remote, rate_limiter.clone())),
+      rate_limiter,
+      concurrency: AtomicUsize::new(DEFAULT_SYNC_CONCURRENCY),
       sync_lock: Arc::new(Mutex::new(())),
     }
   }
 
+  /// Limit the bandwidth of transfers to/from the remote (in bytes per second, 0 = unlimited)
+  pub fn set_max_rate(&self, max_rate: u64) {
+    self.rate_limiter.set_max_rate(max_rate);
+  }
+
+  /// Limit the number of blocks downloaded concurrently (at least 1)
+  pub fn set_concurrency(&self, concurrency: usize) {
+    self.concurrency.store(concurrency.max(1), Ordering::Relaxed);
+  }
+
+  /// Synchronize with the remote, any failure is treated as an error.
+  /// Returns `true` if there were changes to the local store.
   pub fn synchronize(&self) -> StoreResult<bool> {
-    let _guard = self.sync_lock.lock()?;
+    let (progress, _) = mpsc::channel();
+    self.synchronize_with_progress(progress)
+  }
+
+  /// Like `synchronize`, every transfer of a ring or block is reported to `progress`.
+  pub fn synchronize_with_progress(&self, progress: Sender<SyncProgress>) -> StoreResult<bool> {
+    block_on(self.synchronize_async_with_progress(&progress))
+  }
+
+  pub async fn synchronize_async(&self) -> StoreResult<bool> {
+    let (progress, _) = mpsc::channel();
+    self.synchronize_async_with_progress(&progress).await
+  }
 
-    let mut local_changes = synchronize::synchronize_rings(self.local.clone(), self.remote.clone())?;
-    local_changes |= synchronize::synchronize_blocks(self.local.clone(), self.remote.clone())?;
+  async fn synchronize_async_with_progress(&self, progress: &Sender<SyncProgress>) -> StoreResult<bool> {
+    let report = self.synchronize_report_async_with_progress(progress).await?;
+
+    if let Some(error) = report.errors.first() {
+      return Err(StoreError::IO(format!("{} ({})", error.error, error.node)));
+    }
+    if let Some(ring_id) = report.ring_conflicts.first() {
+      return Err(StoreError::Conflict(format!("Ring {}", ring_id)));
+    }
+
+    Ok(report.has_local_changes())
+  }
 
-    Ok(local_changes)
+  /// Synchronize with the remote, failures of individual blocks or rings are only reported.
+  /// An error is only returned if the synchronization could not be performed at all.
+  pub fn synchronize_report(&self) -> StoreResult<SyncReport> {
+    let (progress, _) = mpsc::channel();
+    self.synchronize_report_with_progress(progress)
+  }
+
+  /// Like `synchronize_report`, every transfer of a ring or block is reported to `progress`.
+  pub fn synchronize_report_with_progress(&self, progress: Sender<SyncProgress>) -> StoreResult<SyncReport> {
+    block_on(self.synchronize_report_async_with_progress(&progress))
+  }
+
+  pub async fn synchronize_report_async(&self) -> StoreResult<SyncReport> {
+    let (progress, _) = mpsc::channel();
+    self.synchronize_report_async_with_progress(&progress).await
+  }
+
+  async fn synchronize_report_async_with_progress(&self, progress: &Sender<SyncProgress>) -> StoreResult<SyncReport> {
+    let _guard = self.sync_lock.lock().await;
+    let mut report = SyncReport::default();
+
+    report.max_rate = self.rate_limiter.max_rate();
+
+    synchronize::synchronize_rings(self.local.as_ref(), self.remote.as_ref(), progress, &mut report).await?;
+    synchronize::synchronize_blocks(
+      self.local.as_ref(),
+      self.remote.as_ref(),
+      self.concurrency.load(Ordering::Relaxed),
+      progress,
+      &mut report,
+    )
+    .await?;
+
+    Ok(report)
   }
 }
 
@@ -47,7 +148,7 @@ impl BlockStore for SyncBlockStore {
   fn get_ring(&self, ring_id: &str) -> StoreResult<RingContent> {
     match self.local.get_ring(ring_id) {
       Ok(ring) => Ok(ring),
-      Err(StoreError::InvalidBlock(_)) => self.remote.get_ring(ring_id),
+      Err(StoreError::InvalidBlock(_)) => block_on(self.remote.get_ring(ring_id)),
       Err(err) => Err(err),
     }
   }
@@ -72,16 +173,42 @@ impl BlockStore for SyncBlockStore {
     self.local.add_block(raw)
   }
 
+  fn add_block_stream(&self, reader: &mut dyn std::io::Read) -> StoreResult<String> {
+    self.local.add_block_stream(reader)
+  }
+
+  fn storage_stats(&self) -> StoreResult<StorageStats> {
+    self.local.storage_stats()
+  }
+
+  fn verify(&self) -> StoreResult<VerifyReport> {
+    self.local.verify()
+  }
+
+  fn collect_garbage(&self, referenced: &HashSet<String>) -> StoreResult<usize> {
+    // A running synchronization stores blocks before their change log, i.e. they would look unreferenced
+    let _guard = block_on(self.sync_lock.lock());
+    // Blocks of a synchronization that finished after `referenced` has been computed are still required
+    let mut referenced = referenced.clone();
+    referenced.extend(committed_block_ids(&self.local.change_logs()?));
+
+    self.local.collect_garbage(&referenced)
+  }
+
+  fn flush(&self) -> StoreResult<()> {
+    self.local.flush()
+  }
+
   fn get_block(&self, block: &str) -> StoreResult<ZeroingWords> {
     match self.local.get_block(block) {
       Ok(content) => Ok(content),
-      Err(StoreError::InvalidBlock(_)) => self.remote.get_block(block),
+      Err(StoreError::InvalidBlock(_)) => block_on(self.remote.get_block(block)),
       Err(err) => Err(err),
     }
   }
 
-  fn commit(&self, changes: &[super::Change]) -> StoreResult<()> {
-    self.local.commit(changes)
+  fn commit(&self, commit_id: &str, changes: &[super::Change]) -> StoreResult<()> {
+    self.local.commit(commit_id, changes)
   }
 
   fn update_change_log(&self, _change_log: ChangeLog) -> StoreResult<()> {
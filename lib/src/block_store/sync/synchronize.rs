@@ -1,45 +1,282 @@
-use std::{
-  collections::{HashMap, HashSet},
-  sync::Arc,
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::Sender;
+
+use futures::stream::{FuturesUnordered, StreamExt};
+use log::{info, warn};
+
+use crate::api::{SyncError, SyncProgress, SyncReport, SyncStep};
+use crate::block_store::{
+  check_block_id, generate_block_id, AsyncBlockStore, BlockStore, Operation, StoreError, StoreResult,
 };
+use crate::memguard::weak::ZeroingWords;
+
+/// Record a failed transfer of a ring, failing to store a ring is a conflict
+fn record_ring_failure(report: &mut SyncReport, node: &str, ring_id: &str, error: StoreError) {
+  match error {
+    StoreError::Conflict(_) => {
+      warn!("Ring conflict: {}", ring_id);
+      report.ring_conflicts.push(ring_id.to_string());
+    }
+    error => record_failure(report, node, format!("Ring {}: {}", ring_id, error)),
+  }
+}
+
+fn record_failure(report: &mut SyncReport, node: &str, error: String) {
+  warn!("Synchronization failure ({}): {}", node, error);
+  report.errors.push(SyncError {
+    node: node.to_string(),
+    error,
+  });
+}
 
-use log::info;
+/// Report progress, it is fine if nobody is listening.
+fn send_progress(progress: &Sender<SyncProgress>, step: SyncStep, remaining: usize, message: String) {
+  progress
+    .send(SyncProgress {
+      step,
+      remaining: remaining as u64,
+      message,
+    })
+    .ok();
+}
 
-use crate::block_store::{BlockStore, Operation, StoreResult};
+/// Ids of all rings that are newer in `from` than in `to`
+fn newer_rings<'a>(from: &'a HashMap<String, u64>, to: &HashMap<String, u64>) -> Vec<&'a String> {
+  from
+    .iter()
+    .filter(|(ring_id, version)| !matches!(to.get(*ring_id), Some(other_version) if *version <= other_version))
+    .map(|(ring_id, _)| ring_id)
+    .collect()
+}
 
-pub fn synchronize_rings(local: Arc<dyn BlockStore>, remote: Arc<dyn BlockStore>) -> StoreResult<bool> {
-  let mut local_changes = false;
+pub async fn synchronize_rings(
+  local: &dyn BlockStore,
+  remote: &dyn AsyncBlockStore,
+  progress: &Sender<SyncProgress>,
+  report: &mut SyncReport,
+) -> StoreResult<()> {
+  let node = local.node_id();
   let local_ring_ids: HashMap<String, u64> = local.list_ring_ids()?.into_iter().collect();
-  let remote_ring_ids: HashMap<String, u64> = remote.list_ring_ids()?.into_iter().collect();
+  let remote_ring_ids: HashMap<String, u64> = remote.list_ring_ids().await?.into_iter().collect();
+  let downloads = newer_rings(&remote_ring_ids, &local_ring_ids);
+  let uploads = newer_rings(&local_ring_ids, &remote_ring_ids);
+  let mut remaining = downloads.len() + uploads.len();
 
-  for (remote_ring_id, remote_version) in remote_ring_ids.iter() {
-    if let Some(local_version) = local_ring_ids.get(remote_ring_id) {
-      if *remote_version <= *local_version {
-        continue;
+  for remote_ring_id in downloads {
+    info!("Downloading ring: {}", remote_ring_id);
+    send_progress(
+      progress,
+      SyncStep::Rings,
+      remaining,
+      format!("Downloading ring {}", remote_ring_id),
+    );
+    remaining -= 1;
+    let result = match remote.get_ring(remote_ring_id).await {
+      Ok((remote_version, ring)) => local.store_ring(remote_ring_id, remote_version, &ring),
+      Err(err) => Err(err),
+    };
+    match result {
+      Ok(_) => report.rings_pulled += 1,
+      Err(err) => record_ring_failure(report, node, remote_ring_id, err),
+    }
+  }
+
+  for local_ring_id in uploads {
+    info!("Uploading ring: {}", local_ring_id);
+    send_progress(
+      progress,
+      SyncStep::Rings,
+      remaining,
+      format!("Uploading ring {}", local_ring_id),
+    );
+    remaining -= 1;
+    let result = match local.get_ring(local_ring_id) {
+      Ok((local_version, ring)) => remote.store_ring(local_ring_id, local_version, &ring).await,
+      Err(err) => Err(err),
+    };
+    match result {
+      Ok(_) => report.rings_pushed += 1,
+      Err(err) => record_ring_failure(report, node, local_ring_id, err),
+    }
+  }
+
+  Ok(())
+}
+
+async fn download_block<'a>(
+  remote: &'a dyn AsyncBlockStore,
+  block: &'a String,
+) -> (&'a String, StoreResult<ZeroingWords>) {
+  info!("Downloading block: {}", block);
+  (block, remote.get_block(block).await)
+}
+
+/// Download blocks with up to `concurrency` downloads at the same time.
+///
+/// As block ids are derived from the content (see `generate_block_id`), every downloaded block is verified
+/// before it is stored. A block that does not match (e.g. a truncated download) is downloaded once more.
+///
+/// After the first failure no further downloads are started. Returns the nodes whose blocks have not
+/// all been downloaded, i.e. whose change logs must not be taken over.
+async fn download_blocks<'a>(
+  local: &dyn BlockStore,
+  remote: &dyn AsyncBlockStore,
+  blocks: Vec<&'a String>,
+  block_nodes: &HashMap<&'a String, &'a str>,
+  concurrency: usize,
+  progress: &Sender<SyncProgress>,
+  report: &mut SyncReport,
+) -> HashSet<&'a str> {
+  let mut failed_nodes: HashSet<&str> = HashSet::new();
+  let mut remaining = blocks.len();
+  let mut pending = blocks.into_iter();
+  let mut downloads: FuturesUnordered<_> = pending
+    .by_ref()
+    .take(concurrency.max(1))
+    .map(|block| download_block(remote, block))
+    .collect();
+  let mut retried: HashSet<&String> = HashSet::new();
+  let mut aborted = false;
+  while let Some((block_id, result)) = downloads.next().await {
+    let node = block_nodes[block_id];
+    let mismatch = matches!(&result, Ok(block) if generate_block_id(block) != *block_id);
+    let result = match result {
+      Ok(_) if mismatch => {
+        if retried.insert(block_id) {
+          warn!("Downloaded block {} does not match its id, downloading again", block_id);
+          send_progress(
+            progress,
+            SyncStep::Blocks,
+            remaining,
+            format!("Downloaded block {} does not match its id, downloading again", block_id),
+          );
+          downloads.push(download_block(remote, block_id));
+          continue;
+        }
+        Err(StoreError::InvalidBlock(format!(
+          "{} (content does not match)",
+          block_id
+        )))
+      }
+      result => result,
+    };
+    remaining -= 1;
+    send_progress(
+      progress,
+      SyncStep::Blocks,
+      remaining,
+      if mismatch {
+        format!("Downloaded block {} does not match its id", block_id)
+      } else {
+        format!("Downloaded block {}", block_id)
+      },
+    );
+    match result.and_then(|block| local.add_block(&block)) {
+      Ok(_) => {
+        report.blocks_pulled += 1;
+        report.pulled_block_ids.push(block_id.clone());
+      }
+      Err(err) => {
+        if !aborted {
+          record_failure(report, node, format!("Block {}: {}", block_id, err));
+          aborted = true;
+        }
+        failed_nodes.insert(node);
       }
     }
-    info!("Downloading ring: {}", remote_ring_id);
-    let (remote_version, ring) = remote.get_ring(remote_ring_id)?;
-    local.store_ring(remote_ring_id, remote_version, &ring)?;
-    local_changes = true
+    if !aborted {
+      if let Some(next) = pending.next() {
+        downloads.push(download_block(remote, next));
+      }
+    }
+  }
+  for skipped in pending {
+    failed_nodes.insert(block_nodes[skipped]);
   }
 
-  for (local_ring_id, local_version) in local_ring_ids.iter() {
-    if let Some(remote_version) = remote_ring_ids.get(local_ring_id) {
-      if *local_version <= *remote_version {
-        continue;
+  failed_nodes
+}
+
+async fn upload_block<'a>(
+  local: &'a dyn BlockStore,
+  remote: &'a dyn AsyncBlockStore,
+  block: &'a String,
+) -> (&'a String, StoreResult<String>) {
+  info!("Uploading block: {}", block);
+  let result = match local.get_block(block) {
+    Ok(content) => remote.add_block(&content).await,
+    Err(err) => Err(err),
+  };
+  (block, result)
+}
+
+/// Upload blocks with up to `concurrency` uploads at the same time.
+///
+/// After the first failure no further uploads are started. Returns `true` if all blocks have been
+/// uploaded, i.e. the local change log may be taken over by the remote.
+async fn upload_blocks(
+  local: &dyn BlockStore,
+  remote: &dyn AsyncBlockStore,
+  blocks: Vec<&String>,
+  concurrency: usize,
+  progress: &Sender<SyncProgress>,
+  report: &mut SyncReport,
+) -> bool {
+  let mut remaining = blocks.len();
+  let mut pending = blocks.into_iter();
+  let mut uploads: FuturesUnordered<_> = pending
+    .by_ref()
+    .take(concurrency.max(1))
+    .map(|block| upload_block(local, remote, block))
+    .collect();
+  let mut aborted = false;
+  while let Some((block_id, result)) = uploads.next().await {
+    remaining -= 1;
+    send_progress(
+      progress,
+      SyncStep::Blocks,
+      remaining,
+      format!("Uploaded block {}", block_id),
+    );
+    match result {
+      Ok(_) => report.blocks_pushed += 1,
+      Err(err) => {
+        if !aborted {
+          record_failure(report, local.node_id(), format!("Block {}: {}", block_id, err));
+          aborted = true;
+        }
+      }
+    }
+    if !aborted {
+      if let Some(next) = pending.next() {
+        uploads.push(upload_block(local, remote, next));
       }
     }
-    info!("Uploading ring: {}", local_ring_id);
-    let (local_version, ring) = local.get_ring(local_ring_id)?;
-    remote.store_ring(local_ring_id, local_version, &ring)?;
   }
 
-  Ok(local_changes)
+  !aborted
 }
 
-pub fn synchronize_blocks(local: Arc<dyn BlockStore>, remote: Arc<dyn BlockStore>) -> StoreResult<bool> {
-  let mut local_changes = false;
+/// Synchronize blocks and change logs.
+///
+/// Change logs are only exchanged if all blocks they refer to have been transferred, otherwise
+/// the missing blocks would be considered as existing in the next synchronization.
+///
+/// Missing blocks are transferred concurrently, see `download_blocks` and `upload_blocks`. Blocks that have already been
+/// stored locally (i.e. by a synchronization that failed later on) are not downloaded again, they are counted as
+/// `blocks_resumed` instead of `blocks_pulled`.
+///
+/// The first failed transfer aborts the batch, but it is not returned as error: It is recorded in the `report`
+/// instead, so that the transfers of the other direction and the rings can still be completed. `Err` is only
+/// returned if the change logs could not be read at all, callers that require the first failure as error
+/// use `SyncBlockStore::synchronize` which converts the first entry of `errors`.
+pub async fn synchronize_blocks(
+  local: &dyn BlockStore,
+  remote: &dyn AsyncBlockStore,
+  concurrency: usize,
+  progress: &Sender<SyncProgress>,
+  report: &mut SyncReport,
+) -> StoreResult<()> {
   let local_change_logs = local.change_logs()?;
   let local_added: HashSet<&String> = local_change_logs
     .iter()
@@ -58,12 +295,17 @@ pub fn synchronize_blocks(local: Arc<dyn BlockStore>, remote: Arc<dyn BlockStore
     })
     .collect();
   let local_existing: HashSet<&String> = local_added.difference(&local_removed).copied().collect();
-  let remote_change_logs = remote.change_logs()?;
-  let remote_added: HashSet<&String> = remote_change_logs
+  let remote_change_logs = remote.change_logs().await?;
+  let remote_added: HashMap<&String, &str> = remote_change_logs
     .iter()
-    .flat_map(|change_log| change_log.changes.iter())
-    .filter_map(|change| match change.op {
-      Operation::Add => Some(&change.block),
+    .flat_map(|change_log| {
+      change_log
+        .changes
+        .iter()
+        .map(move |change| (change, change_log.node.as_str()))
+    })
+    .filter_map(|(change, node)| match change.op {
+      Operation::Add => Some((&change.block, node)),
       _ => None,
     })
     .collect();
@@ -75,39 +317,64 @@ pub fn synchronize_blocks(local: Arc<dyn BlockStore>, remote: Arc<dyn BlockStore
       _ => None,
     })
     .collect();
-  let remote_existing: HashSet<&String> = remote_added.difference(&remote_removed).copied().collect();
+  let remote_existing: HashSet<&String> = remote_added
+    .keys()
+    .copied()
+    .filter(|block| !remote_removed.contains(block))
+    .collect();
 
-  for local_missing in remote_existing.difference(&local_existing).copied() {
-    if local_removed.contains(local_missing) {
-      continue;
-    }
-    info!("Downloading block: {}", local_missing);
-    let block = remote.get_block(local_missing)?;
-    local.add_block(&block)?;
-    local_changes = true;
+  let local_missing: Vec<&String> = remote_existing
+    .difference(&local_existing)
+    .copied()
+    .filter(|block| !local_removed.contains(block))
+    .collect();
+  // Blocks stored by a previous (partial) synchronization are already there, only their change log is missing
+  let (local_present, local_missing): (Vec<&String>, Vec<&String>) = local_missing
+    .into_iter()
+    .partition(|block| matches!(check_block_id(block, local.get_block(block)), Ok(Some(true))));
+  for block in local_present {
+    info!("Block already present: {}", block);
+    report.blocks_resumed += 1;
+    report.pulled_block_ids.push(block.clone());
   }
+  let failed_nodes = download_blocks(
+    local,
+    remote,
+    local_missing,
+    &remote_added,
+    concurrency,
+    progress,
+    report,
+  )
+  .await;
 
-  for remote_missing in local_existing.difference(&remote_existing).copied() {
-    if remote_removed.contains(remote_missing) {
+  let remote_missing: Vec<&String> = local_existing
+    .difference(&remote_existing)
+    .copied()
+    .filter(|block| !remote_removed.contains(block))
+    .collect();
+  let upload_failed = !upload_blocks(local, remote, remote_missing, concurrency, progress, report).await;
+
+  for remote_change_log in remote_change_logs.iter() {
+    if remote_change_log.node == local.node_id() || failed_nodes.contains(remote_change_log.node.as_str()) {
       continue;
     }
-    info!("Uploading block: {}", remote_missing);
-    let block = local.get_block(remote_missing)?;
-    remote.add_block(&block)?;
-  }
-
-  for remote_change_log in remote_change_logs {
-    if remote_change_log.node != local.node_id() {
-      local.update_change_log(remote_change_log)?;
+    if let Err(err) = local.update_change_log(remote_change_log.clone()) {
+      record_failure(report, &remote_change_log.node, format!("Change log: {}", err));
     }
   }
 
+  if upload_failed {
+    return Ok(());
+  }
   if let Some(local_change_log) = local_change_logs
     .into_iter()
     .find(|change_log| change_log.node == local.node_id())
   {
-    remote.update_change_log(local_change_log)?;
+    if let Err(err) = remote.update_change_log(local_change_log).await {
+      record_failure(report, local.node_id(), format!("Change log: {}", err));
+    }
   }
 
-  Ok(local_changes)
+  Ok(())
 }
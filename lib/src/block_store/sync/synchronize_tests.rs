@@ -1,9 +1,15 @@
 use rand::{distributions, prelude::ThreadRng, thread_rng, Rng};
 use spectral::prelude::*;
-use std::sync::Arc;
+use std::collections::HashSet;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
 
 use crate::{
-  block_store::{open_block_store, BlockStore, Change, ChangeLog, Operation, RingId},
+  api::{SyncError, SyncProgress, SyncStep},
+  block_store::{
+    open_block_store, tests::failing::FailingBlockStore, BlockStore, Change, ChangeLog, Operation, RingContent, RingId,
+    StoreResult,
+  },
   memguard::weak::ZeroingWords,
 };
 
@@ -147,7 +153,7 @@ fn test_block_sync(
     op: Operation::Add,
     block: block1_id.clone(),
   }];
-  assert_that!(local_store.commit(&local_changes)).is_ok();
+  assert_that!(local_store.commit("local1", &local_changes)).is_ok();
 
   let block2_id = remote_store.add_block(&block2).unwrap();
   let block3_id = remote_store.add_block(&block3).unwrap();
@@ -162,7 +168,7 @@ fn test_block_sync(
       block: block3_id.clone(),
     },
   ];
-  assert_that!(remote_store.commit(&remote_changes)).is_ok();
+  assert_that!(remote_store.commit("remote1", &remote_changes)).is_ok();
 
   assert_that!(local_store.get_block(&block1_id)).is_ok();
   assert_that!(local_store.get_block(&block2_id)).is_err();
@@ -186,10 +192,12 @@ fn test_block_sync(
     ChangeLog {
       node: "local".to_string(),
       changes: local_changes,
+      commits: vec!["local1".to_string()],
     },
     ChangeLog {
       node: "remote".to_string(),
       changes: remote_changes,
+      commits: vec!["remote1".to_string()],
     },
   ];
   assert_that!(local_store.change_logs().map(|mut change_logs| {
@@ -214,3 +222,420 @@ fn test_sync_memory() {
   test_ring_sync(&mut rng, local_store.clone(), remote_store.clone(), sync_store.clone());
   test_block_sync(&mut rng, local_store, remote_store, sync_store);
 }
+
+/// Remote store that hangs in `list_ring_ids` until it is released
+#[derive(Debug)]
+struct SlowBlockStore {
+  inner: Arc<dyn BlockStore>,
+  entered: Mutex<mpsc::Sender<()>>,
+  release: Mutex<mpsc::Receiver<()>>,
+}
+
+impl BlockStore for SlowBlockStore {
+  fn node_id(&self) -> &str {
+    self.inner.node_id()
+  }
+
+  fn list_ring_ids(&self) -> StoreResult<Vec<RingId>> {
+    self.entered.lock()?.send(()).ok();
+    self.release.lock()?.recv().ok();
+    self.inner.list_ring_ids()
+  }
+
+  fn get_ring(&self, ring_id: &str) -> StoreResult<RingContent> {
+    self.inner.get_ring(ring_id)
+  }
+
+  fn store_ring(&self, ring_id: &str, version: u64, raw: &[u8]) -> StoreResult<()> {
+    self.inner.store_ring(ring_id, version, raw)
+  }
+
+  fn change_logs(&self) -> StoreResult<Vec<ChangeLog>> {
+    self.inner.change_logs()
+  }
+
+  fn get_index(&self, index_id: &str) -> StoreResult<Option<ZeroingWords>> {
+    self.inner.get_index(index_id)
+  }
+
+  fn store_index(&self, index_id: &str, raw: &[u8]) -> StoreResult<()> {
+    self.inner.store_index(index_id, raw)
+  }
+
+  fn add_block(&self, raw: &[u8]) -> StoreResult<String> {
+    self.inner.add_block(raw)
+  }
+
+  fn get_block(&self, block: &str) -> StoreResult<ZeroingWords> {
+    self.inner.get_block(block)
+  }
+
+  fn commit(&self, commit_id: &str, changes: &[Change]) -> StoreResult<()> {
+    self.inner.commit(commit_id, changes)
+  }
+
+  fn update_change_log(&self, change_log: ChangeLog) -> StoreResult<()> {
+    self.inner.update_change_log(change_log)
+  }
+}
+
+#[test]
+fn test_slow_remote_does_not_block_local() {
+  let rng = thread_rng();
+  // Single threaded on purpose: A blocked worker would block everything
+  let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+
+  runtime.block_on(async {
+    let (entered_tx, entered_rx) = mpsc::channel();
+    let (release_tx, release_rx) = mpsc::channel();
+    let local_store = open_block_store("memory://", "local").unwrap();
+    let remote_store = Arc::new(SlowBlockStore {
+      inner: open_block_store("memory://", "remote").unwrap(),
+      entered: Mutex::new(entered_tx),
+      release: Mutex::new(release_rx),
+    });
+    let sync_store = Arc::new(SyncBlockStore::new(local_store, remote_store.clone()));
+
+    let sync_task = tokio::spawn({
+      let sync_store = sync_store.clone();
+      async move { sync_store.synchronize_async().await }
+    });
+    while entered_rx.try_recv().is_err() {
+      tokio::task::yield_now().await;
+      std::thread::sleep(Duration::from_millis(1));
+    }
+
+    // Synchronization is stuck at the remote, local operations are still possible
+    let block = rng
+      .sample_iter(distributions::Standard)
+      .take(200 * 8)
+      .collect::<Vec<u8>>();
+    let block_id = sync_store.add_block(&block).unwrap();
+    let changes = vec![Change {
+      op: Operation::Add,
+      block: block_id.clone(),
+    }];
+    assert_that!(sync_store.commit("commit1", &changes)).is_ok();
+    assert_that!(sync_store.get_block(&block_id)).is_ok_containing(ZeroingWords::from(block.as_ref()));
+    assert_that!(sync_store.change_logs()).is_ok_containing(vec![ChangeLog {
+      node: "local".to_string(),
+      changes,
+      commits: vec!["commit1".to_string()],
+    }]);
+    assert_that!(sync_task.is_finished()).is_false();
+
+    release_tx.send(()).unwrap();
+
+    assert_that!(sync_task.await.unwrap()).is_ok_containing(false);
+    assert_that!(remote_store.get_block(&block_id)).is_ok_containing(ZeroingWords::from(block.as_ref()));
+  });
+}
+
+fn random_content(rng: &mut ThreadRng) -> Vec<u8> {
+  rng
+    .sample_iter(distributions::Standard)
+    .take(200 * 8)
+    .collect::<Vec<u8>>()
+}
+
+fn node_names(change_logs: StoreResult<Vec<ChangeLog>>) -> Vec<String> {
+  let mut nodes: Vec<String> = change_logs
+    .unwrap()
+    .into_iter()
+    .map(|change_log| change_log.node)
+    .collect();
+  nodes.sort();
+  nodes
+}
+
+#[test]
+fn test_partial_sync_failure() {
+  let mut rng = thread_rng();
+  let local_store = open_block_store("memory://", "local").unwrap();
+  let remote_store = Arc::new(FailingBlockStore::new(open_block_store("memory://", "remote").unwrap()));
+  let sync_store = Arc::new(SyncBlockStore::new(local_store.clone(), remote_store.clone()));
+
+  local_store.store_ring("ring1", 0, &random_content(&mut rng)).unwrap();
+  local_store.store_ring("ring2", 0, &random_content(&mut rng)).unwrap();
+  let block1_id = local_store.add_block(&random_content(&mut rng)).unwrap();
+  local_store
+    .commit(
+      "local1",
+      &[Change {
+        op: Operation::Add,
+        block: block1_id,
+      }],
+    )
+    .unwrap();
+  let block2_id = remote_store.add_block(&random_content(&mut rng)).unwrap();
+  let block3_id = remote_store.add_block(&random_content(&mut rng)).unwrap();
+  remote_store
+    .commit(
+      "remote1",
+      &[
+        Change {
+          op: Operation::Add,
+          block: block2_id,
+        },
+        Change {
+          op: Operation::Add,
+          block: block3_id.clone(),
+        },
+      ],
+    )
+    .unwrap();
+
+  remote_store.failing_blocks.lock().unwrap().insert(block3_id.clone());
+  remote_store
+    .conflicting_rings
+    .lock()
+    .unwrap()
+    .insert("ring2".to_string());
+
+  let report = sync_store.synchronize_report().unwrap();
+
+  assert_that!(report.blocks_pushed).is_equal_to(1);
+  assert_that!(report.blocks_pulled).is_equal_to(1);
+  assert_that!(report.rings_pushed).is_equal_to(1);
+  assert_that!(report.rings_pulled).is_equal_to(0);
+  assert_that!(report.ring_conflicts).is_equal_to(vec!["ring2".to_string()]);
+  assert_that!(report.errors).has_length(1);
+  assert_that!(report.errors[0].node).is_equal_to("remote".to_string());
+  assert_that!(report.is_success()).is_false();
+  // The change log of the failing node must not be taken over, otherwise block3 would be lost
+  assert_that!(node_names(local_store.change_logs())).is_equal_to(vec!["local".to_string()]);
+  assert_that!(node_names(remote_store.change_logs())).is_equal_to(vec!["local".to_string(), "remote".to_string()]);
+  assert_that!(sync_store.synchronize()).is_err();
+
+  remote_store.failing_blocks.lock().unwrap().clear();
+  remote_store.conflicting_rings.lock().unwrap().clear();
+
+  let report = sync_store.synchronize_report().unwrap();
+
+  assert_that!(report.blocks_pushed).is_equal_to(0);
+  // As long as the change log of a node is not taken over, all its blocks are pulled again
+  // (the ones that have been stored already are only resumed, i.e. not downloaded again)
+  assert_that!(report.blocks_pulled).is_equal_to(1);
+  assert_that!(report.blocks_resumed).is_equal_to(1);
+  assert_that!(report.rings_pushed).is_equal_to(1);
+  assert_that!(report.errors).is_equal_to(Vec::<SyncError>::new());
+  assert_that!(report.is_success()).is_true();
+  assert_that!(local_store.get_block(&block3_id)).is_ok();
+  assert_that!(node_names(local_store.change_logs())).is_equal_to(vec!["local".to_string(), "remote".to_string()]);
+}
+
+#[test]
+fn test_sync_resume_partial_download() {
+  let mut rng = thread_rng();
+  let local_store = open_block_store("memory://", "local").unwrap();
+  let remote_store = Arc::new(FailingBlockStore::new(open_block_store("memory://", "remote").unwrap()));
+  let sync_store = Arc::new(SyncBlockStore::new(local_store.clone(), remote_store.clone()));
+  let block1 = random_content(&mut rng);
+  let block1_id = remote_store.add_block(&block1).unwrap();
+  let block2_id = remote_store.add_block(&random_content(&mut rng)).unwrap();
+  remote_store
+    .commit(
+      "remote1",
+      &[
+        Change::new(Operation::Add, &block1_id),
+        Change::new(Operation::Add, &block2_id),
+      ],
+    )
+    .unwrap();
+  // State after a partial synchronization: block1 has been stored, but the change log was not taken over
+  local_store.add_block(&block1).unwrap();
+
+  // block1 is already there, i.e. the synchronization succeeds even if it is not readable any more
+  remote_store.failing_blocks.lock().unwrap().insert(block1_id.clone());
+  let (progress, progress_events) = mpsc::channel();
+  let report = sync_store.synchronize_report_with_progress(progress).unwrap();
+
+  assert_that!(report.errors).is_equal_to(Vec::<SyncError>::new());
+  assert_that!(report.blocks_pulled).is_equal_to(1);
+  assert_that!(report.blocks_resumed).is_equal_to(1);
+  assert_that!(report.pulled_block_ids).contains(block1_id);
+  assert_that!(progress_events
+    .into_iter()
+    .filter(|event| event.step == SyncStep::Blocks)
+    .count())
+  .is_equal_to(1);
+  assert_that!(local_store.get_block(&block2_id)).is_ok();
+  assert_that!(node_names(local_store.change_logs())).is_equal_to(vec!["remote".to_string()]);
+}
+
+#[test]
+fn test_sync_verify_downloaded_blocks() {
+  let mut rng = thread_rng();
+  let local_store = open_block_store("memory://", "local").unwrap();
+  let remote_store = Arc::new(FailingBlockStore::new(open_block_store("memory://", "remote").unwrap()));
+  let sync_store = Arc::new(SyncBlockStore::new(local_store.clone(), remote_store.clone()));
+  let block1_id = remote_store.add_block(&random_content(&mut rng)).unwrap();
+  let block2_id = remote_store.add_block(&random_content(&mut rng)).unwrap();
+  remote_store
+    .commit(
+      "remote1",
+      &[
+        Change::new(Operation::Add, &block1_id),
+        Change::new(Operation::Add, &block2_id),
+      ],
+    )
+    .unwrap();
+
+  // Truncated twice: Rejected after the second download
+  remote_store
+    .truncated_blocks
+    .lock()
+    .unwrap()
+    .insert(block1_id.clone(), 2);
+  let (progress, progress_events) = mpsc::channel();
+  let report = sync_store.synchronize_report_with_progress(progress).unwrap();
+  let messages: Vec<String> = progress_events.into_iter().map(|event| event.message.clone()).collect();
+
+  assert_that!(report.errors).has_length(1);
+  assert_that!(report.errors[0].node).is_equal_to("remote".to_string());
+  assert_that!(local_store.get_block(&block1_id)).is_err();
+  assert_that!(messages).contains(format!(
+    "Downloaded block {} does not match its id, downloading again",
+    block1_id
+  ));
+  assert_that!(messages).contains(format!("Downloaded block {} does not match its id", block1_id));
+  assert_that!(node_names(local_store.change_logs())).is_equal_to(Vec::<String>::new());
+
+  // Truncated once: Fixed by the second download
+  remote_store
+    .truncated_blocks
+    .lock()
+    .unwrap()
+    .insert(block1_id.clone(), 1);
+  let report = sync_store.synchronize_report().unwrap();
+
+  assert_that!(report.errors).is_equal_to(Vec::<SyncError>::new());
+  assert_that!(local_store.get_block(&block1_id)).is_equal_to(remote_store.get_block(&block1_id));
+  assert_that!(node_names(local_store.change_logs())).is_equal_to(vec!["remote".to_string()]);
+}
+
+#[test]
+fn test_concurrent_block_sync() {
+  let mut rng = thread_rng();
+  let local_store = open_block_store("memory://", "local").unwrap();
+  let remote_store = open_block_store("memory://", "remote").unwrap();
+  let sync_store = Arc::new(SyncBlockStore::new(local_store.clone(), remote_store.clone()));
+  let mut changes = vec![];
+
+  for _ in 0..100 {
+    changes.push(Change {
+      op: Operation::Add,
+      block: remote_store.add_block(&random_content(&mut rng)).unwrap(),
+    });
+  }
+  remote_store.commit("remote1", &changes).unwrap();
+
+  sync_store.set_concurrency(8);
+  let report = sync_store.synchronize_report().unwrap();
+
+  assert_that!(report.blocks_pulled).is_equal_to(100);
+  assert_that!(report.pulled_block_ids).has_length(100);
+  assert_that!(report.errors).is_equal_to(Vec::<SyncError>::new());
+  for change in changes.iter() {
+    assert_that!(local_store.get_block(&change.block)).is_equal_to(remote_store.get_block(&change.block));
+  }
+  assert_that!(node_names(local_store.change_logs())).is_equal_to(vec!["remote".to_string()]);
+
+  let report = sync_store.synchronize_report().unwrap();
+
+  assert_that!(report.blocks_pulled).is_equal_to(0);
+}
+
+#[test]
+fn test_concurrent_block_upload() {
+  let mut rng = thread_rng();
+  let local_store = open_block_store("memory://", "local").unwrap();
+  let remote_store = open_block_store("memory://", "remote").unwrap();
+  let sync_store = Arc::new(SyncBlockStore::new(local_store.clone(), remote_store.clone()));
+  let mut changes = vec![];
+
+  for _ in 0..100 {
+    changes.push(Change {
+      op: Operation::Add,
+      block: local_store.add_block(&random_content(&mut rng)).unwrap(),
+    });
+  }
+  local_store.commit("local1", &changes).unwrap();
+
+  sync_store.set_concurrency(8);
+  let (progress, progress_events) = mpsc::channel();
+  let report = sync_store.synchronize_report_with_progress(progress).unwrap();
+  let mut remaining: Vec<u64> = progress_events.into_iter().map(|event| event.remaining).collect();
+  remaining.reverse();
+
+  assert_that!(report.blocks_pushed).is_equal_to(100);
+  assert_that!(report.errors).is_equal_to(Vec::<SyncError>::new());
+  assert_that!(remaining).is_equal_to((0..100).collect::<Vec<u64>>());
+  for change in changes.iter() {
+    assert_that!(remote_store.get_block(&change.block)).is_equal_to(local_store.get_block(&change.block));
+  }
+  assert_that!(node_names(remote_store.change_logs())).is_equal_to(vec!["local".to_string()]);
+}
+
+#[test]
+fn test_sync_progress() {
+  let mut rng = thread_rng();
+  let local_store = open_block_store("memory://", "local").unwrap();
+  let remote_store = open_block_store("memory://", "remote").unwrap();
+  let sync_store = Arc::new(SyncBlockStore::new(local_store.clone(), remote_store.clone()));
+  let mut changes = vec![];
+
+  remote_store.store_ring("ring1", 0, &random_content(&mut rng)).unwrap();
+  local_store.store_ring("ring2", 0, &random_content(&mut rng)).unwrap();
+  for _ in 0..5 {
+    changes.push(Change {
+      op: Operation::Add,
+      block: remote_store.add_block(&random_content(&mut rng)).unwrap(),
+    });
+  }
+  remote_store.commit("remote1", &changes).unwrap();
+
+  let (progress, progress_events) = mpsc::channel();
+
+  assert_that!(sync_store.synchronize_with_progress(progress)).is_ok_containing(true);
+
+  let events: Vec<SyncProgress> = progress_events.into_iter().collect();
+  let ring_events: Vec<u64> = events
+    .iter()
+    .filter(|event| event.step == SyncStep::Rings)
+    .map(|event| event.remaining)
+    .collect();
+  let block_events: Vec<u64> = events
+    .iter()
+    .filter(|event| event.step == SyncStep::Blocks)
+    .map(|event| event.remaining)
+    .collect();
+
+  assert_that!(ring_events).is_equal_to(vec![2, 1]);
+  assert_that!(block_events).is_equal_to(vec![4, 3, 2, 1, 0]);
+  assert_that!(events.iter().position(|event| event.step == SyncStep::Blocks)).is_equal_to(Some(2));
+
+  // Nothing to transfer, nothing to report
+  let (progress, progress_events) = mpsc::channel();
+
+  assert_that!(sync_store.synchronize_with_progress(progress)).is_ok_containing(false);
+  assert_that!(progress_events.into_iter().count()).is_equal_to(0);
+}
+
+#[test]
+fn test_collect_garbage_keeps_committed_blocks() {
+  let local_store = open_block_store("memory://", "local").unwrap();
+  let remote_store = open_block_store("memory://", "remote").unwrap();
+  let sync_store = SyncBlockStore::new(local_store.clone(), remote_store);
+
+  // As if synchronized after the referenced blocks have been computed
+  let committed_block_id = local_store.add_block(b"committed").unwrap();
+  local_store
+    .commit("commit1", &[Change::new(Operation::Add, committed_block_id.clone())])
+    .unwrap();
+  let orphan_block_id = local_store.add_block(b"orphan").unwrap();
+
+  assert_that(&sync_store.collect_garbage(&HashSet::new())).is_ok_containing(1);
+  assert_that(&local_store.get_block(&committed_block_id)).is_ok();
+  assert_that(&local_store.get_block(&orphan_block_id)).is_err();
+}
@@ -0,0 +1,166 @@
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures::future::{BoxFuture, FutureExt};
+use tokio::runtime::Handle;
+
+use crate::block_store::{AsyncBlockStore, Change, ChangeLog, RingContent, RingId, StoreError, StoreResult};
+use crate::memguard::weak::ZeroingWords;
+
+/// Token bucket limiting the bandwidth of transfers (in bytes per second, 0 = unlimited).
+///
+/// The bucket holds at most one second worth of transfer. A transfer exceeding the remaining
+/// budget is not rejected, it is only delayed until the budget has recovered, i.e. there is no
+/// minimum (or maximum) transfer size.
+#[derive(Debug)]
+pub struct RateLimiter {
+  max_rate: AtomicU64,
+  budget: Mutex<(Instant, f64)>,
+}
+
+impl RateLimiter {
+  pub fn new(max_rate: u64) -> RateLimiter {
+    RateLimiter {
+      max_rate: AtomicU64::new(max_rate),
+      budget: Mutex::new((Instant::now(), max_rate as f64)),
+    }
+  }
+
+  pub fn max_rate(&self) -> u64 {
+    self.max_rate.load(Ordering::Relaxed)
+  }
+
+  pub fn set_max_rate(&self, max_rate: u64) {
+    self.max_rate.store(max_rate, Ordering::Relaxed);
+  }
+
+  /// Account for a transfer of `bytes` and get the delay necessary to stay within the limit.
+  pub fn reserve(&self, bytes: usize) -> StoreResult<Duration> {
+    let max_rate = self.max_rate();
+    if max_rate == 0 {
+      return Ok(Duration::ZERO);
+    }
+    let rate = max_rate as f64;
+    let mut budget = self.budget.lock()?;
+    let now = Instant::now();
+    let available = (budget.1 + now.duration_since(budget.0).as_secs_f64() * rate).min(rate) - bytes as f64;
+
+    *budget = (now, available);
+
+    if available >= 0.0 {
+      Ok(Duration::ZERO)
+    } else {
+      Ok(Duration::from_secs_f64(-available / rate))
+    }
+  }
+}
+
+/// Limits the bandwidth of ring and block transfers to/from a (remote) store.
+///
+/// Metadata (ring ids, change logs) are not throttled. Like `BlockingAsyncStore` any necessary
+/// delay happens in the blocking thread pool if there is a tokio runtime, in place otherwise.
+pub struct ThrottledAsyncStore {
+  store: Arc<dyn AsyncBlockStore>,
+  rate_limiter: Arc<RateLimiter>,
+  runtime: Option<Handle>,
+}
+
+impl ThrottledAsyncStore {
+  pub fn new(store: Arc<dyn AsyncBlockStore>, rate_limiter: Arc<RateLimiter>) -> ThrottledAsyncStore {
+    ThrottledAsyncStore {
+      store,
+      rate_limiter,
+      runtime: Handle::try_current().ok(),
+    }
+  }
+
+  async fn throttle(&self, bytes: usize) -> StoreResult<()> {
+    let delay = self.rate_limiter.reserve(bytes)?;
+
+    if delay.is_zero() {
+      return Ok(());
+    }
+    match &self.runtime {
+      Some(runtime) => runtime
+        .spawn_blocking(move || std::thread::sleep(delay))
+        .await
+        .map_err(|err| StoreError::IO(format!("Throttling failed: {}", err))),
+      None => {
+        std::thread::sleep(delay);
+        Ok(())
+      }
+    }
+  }
+}
+
+impl fmt::Debug for ThrottledAsyncStore {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "Throttled {:?}", self.store)
+  }
+}
+
+impl AsyncBlockStore for ThrottledAsyncStore {
+  fn node_id(&self) -> &str {
+    self.store.node_id()
+  }
+
+  fn list_ring_ids(&self) -> BoxFuture<'_, StoreResult<Vec<RingId>>> {
+    self.store.list_ring_ids()
+  }
+
+  fn get_ring<'a>(&'a self, ring_id: &'a str) -> BoxFuture<'a, StoreResult<RingContent>> {
+    async move {
+      let (version, raw) = self.store.get_ring(ring_id).await?;
+      self.throttle(raw.len() * 8).await?;
+      Ok((version, raw))
+    }
+    .boxed()
+  }
+
+  fn store_ring<'a>(&'a self, ring_id: &'a str, version: u64, raw: &'a [u8]) -> BoxFuture<'a, StoreResult<()>> {
+    async move {
+      self.throttle(raw.len()).await?;
+      self.store.store_ring(ring_id, version, raw).await
+    }
+    .boxed()
+  }
+
+  fn change_logs(&self) -> BoxFuture<'_, StoreResult<Vec<ChangeLog>>> {
+    self.store.change_logs()
+  }
+
+  fn get_index<'a>(&'a self, index_id: &'a str) -> BoxFuture<'a, StoreResult<Option<ZeroingWords>>> {
+    self.store.get_index(index_id)
+  }
+
+  fn store_index<'a>(&'a self, index_id: &'a str, raw: &'a [u8]) -> BoxFuture<'a, StoreResult<()>> {
+    self.store.store_index(index_id, raw)
+  }
+
+  fn add_block<'a>(&'a self, raw: &'a [u8]) -> BoxFuture<'a, StoreResult<String>> {
+    async move {
+      self.throttle(raw.len()).await?;
+      self.store.add_block(raw).await
+    }
+    .boxed()
+  }
+
+  fn get_block<'a>(&'a self, block: &'a str) -> BoxFuture<'a, StoreResult<ZeroingWords>> {
+    async move {
+      let raw = self.store.get_block(block).await?;
+      self.throttle(raw.len() * 8).await?;
+      Ok(raw)
+    }
+    .boxed()
+  }
+
+  fn commit<'a>(&'a self, commit_id: &'a str, changes: &'a [Change]) -> BoxFuture<'a, StoreResult<()>> {
+    self.store.commit(commit_id, changes)
+  }
+
+  fn update_change_log(&self, change_log: ChangeLog) -> BoxFuture<'_, StoreResult<()>> {
+    self.store.update_change_log(change_log)
+  }
+}
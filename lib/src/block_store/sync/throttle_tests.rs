@@ -0,0 +1,72 @@
+use rand::{distributions, thread_rng, Rng};
+use spectral::prelude::*;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::block_store::{open_block_store, Change, Operation};
+
+use super::{RateLimiter, SyncBlockStore};
+
+#[test]
+fn test_unlimited_rate() {
+  let rate_limiter = RateLimiter::new(0);
+
+  assert_that!(rate_limiter.reserve(100 * 1024 * 1024)).is_ok_containing(Duration::ZERO);
+  assert_that!(rate_limiter.reserve(100 * 1024 * 1024)).is_ok_containing(Duration::ZERO);
+}
+
+#[test]
+fn test_rate_limit_delays() {
+  let rate_limiter = RateLimiter::new(1000);
+
+  // Small transfers within the budget are not delayed at all
+  assert_that!(rate_limiter.reserve(10)).is_ok_containing(Duration::ZERO);
+
+  // Transfers exceeding the budget are delayed by the excess, but never stalled
+  let delay = rate_limiter.reserve(5990).unwrap();
+
+  assert_that!(delay).is_greater_than(Duration::from_millis(4900));
+  assert_that!(delay).is_less_than_or_equal_to(Duration::from_secs(5));
+
+  rate_limiter.set_max_rate(0);
+
+  assert_that!(rate_limiter.reserve(5000)).is_ok_containing(Duration::ZERO);
+}
+
+#[test]
+fn test_throttled_sync() {
+  let mut rng = thread_rng();
+  let local_store = open_block_store("memory://", "local").unwrap();
+  let remote_store = open_block_store("memory://", "remote").unwrap();
+  let sync_store = Arc::new(SyncBlockStore::new(local_store.clone(), remote_store.clone()));
+  let block_size = 8000;
+  let mut changes = vec![];
+
+  for _ in 0..4 {
+    let block = (&mut rng)
+      .sample_iter(distributions::Standard)
+      .take(block_size)
+      .collect::<Vec<u8>>();
+    changes.push(Change {
+      op: Operation::Add,
+      block: remote_store.add_block(&block).unwrap(),
+    });
+  }
+  remote_store.commit("commit1", &changes).unwrap();
+
+  let max_rate = 20_000u64;
+  sync_store.set_max_rate(max_rate);
+
+  let start = Instant::now();
+  let report = sync_store.synchronize_report().unwrap();
+  let elapsed = start.elapsed();
+
+  assert_that!(report.blocks_pulled).is_equal_to(4);
+  assert_that!(report.max_rate).is_equal_to(max_rate);
+  // One second worth of transfer is allowed as burst
+  let expected = Duration::from_secs_f64((4 * block_size) as f64 / max_rate as f64 - 1.0);
+  assert_that!(elapsed).is_greater_than_or_equal_to(expected);
+  for change in changes {
+    assert_that!(local_store.get_block(&change.block)).is_ok();
+  }
+}
@@ -0,0 +1,578 @@
+//! Conformance test suite for `BlockStore` implementations.
+//!
+//! Every backend is supposed to behave identically with regard to the contract of the `BlockStore` trait.
+//! The suite only gets a factory creating new, empty stores for a given node id, so that it can be run
+//! against any backend.
+//!
+use super::super::{open_block_store, BlockStore, Change, ChangeLog, Operation, StoreError};
+use crate::memguard::weak::ZeroingWords;
+use rand::{distributions, thread_rng, Rng};
+use spectral::prelude::*;
+use std::sync::Arc;
+use tempfile::{Builder, TempDir};
+
+/// Factory creating a new block store for a node id.
+type StoreFactory<'a> = dyn FnMut(&str) -> Arc<dyn BlockStore> + 'a;
+
+/// Run the full conformance suite.
+///
+/// `new_store` has to create a new, empty store on every invocation.
+fn run_conformance_suite(new_store: &mut StoreFactory) {
+  conformance_node_id(new_store);
+  conformance_empty_store(new_store);
+  conformance_blocks(new_store);
+  conformance_block_stream(new_store);
+  conformance_unknown_blocks(new_store);
+  conformance_ring_versions(new_store);
+  conformance_ring_conflicts(new_store);
+  conformance_commit_append(new_store);
+  conformance_commit_dedup(new_store);
+  conformance_commit_retry(new_store);
+  conformance_update_change_log(new_store);
+  conformance_indexes(new_store);
+}
+
+/// Run the tests for backends that can be shared between multiple nodes.
+///
+/// `new_store` has to create stores on the same underlying storage on every invocation.
+fn run_shared_suite(new_store: &mut StoreFactory) {
+  let store1 = new_store("node1");
+  let store2 = new_store("node2");
+
+  let block1 = random_block(100);
+  let block2 = random_block(100);
+  let block1_id = store1.add_block(&block1).unwrap();
+  let block2_id = store2.add_block(&block2).unwrap();
+
+  assert_that(&store2.get_block(&block1_id)).is_ok_containing(ZeroingWords::from(block1.as_ref()));
+  assert_that(&store1.get_block(&block2_id)).is_ok_containing(ZeroingWords::from(block2.as_ref()));
+
+  let ring = random_block(50);
+  assert_that(&store1.store_ring("ring", 1, &ring)).is_ok();
+  assert_that(&store2.get_ring("ring")).is_ok_containing((1u64, ZeroingWords::from(ring.as_ref())));
+  assert_that(&store2.store_ring("ring", 1, &ring))
+    .is_err()
+    .matches(|error| matches!(error, StoreError::Conflict(_)));
+
+  // Change logs are kept per node, i.e. the same change may be committed by every node
+  let change = Change::new(Operation::Add, block1_id.as_str());
+  assert_that(&store1.commit("commit1", std::slice::from_ref(&change))).is_ok();
+  assert_that(&store2.commit("commit1", std::slice::from_ref(&change))).is_ok();
+  assert_that(&store1.commit("commit2", std::slice::from_ref(&change)))
+    .is_err()
+    .matches(|error| matches!(error, StoreError::Conflict(_)));
+
+  let expected = vec![
+    ChangeLog {
+      node: "node1".to_string(),
+      changes: vec![change.clone()],
+      commits: vec!["commit1".to_string()],
+    },
+    ChangeLog {
+      node: "node2".to_string(),
+      changes: vec![change.clone()],
+      commits: vec!["commit1".to_string()],
+    },
+  ];
+  assert_that(&sorted_change_logs(store1.as_ref())).is_equal_to(&expected);
+  assert_that(&sorted_change_logs(store2.as_ref())).is_equal_to(&expected);
+
+  // Commit ids are persisted, i.e. a retry is recognized after reopening the store
+  let reopened = new_store("node1");
+  assert_that(&reopened.commit("commit1", std::slice::from_ref(&change))).is_ok();
+  assert_that(&sorted_change_logs(reopened.as_ref())).is_equal_to(&expected);
+
+  // Indexes are local to each node
+  let index = random_block(20);
+  assert_that(&store1.store_index("index", &index)).is_ok();
+  assert_that(&store1.get_index("index")).is_ok_containing(Some(ZeroingWords::from(index.as_ref())));
+  assert_that(&store2.get_index("index")).is_ok_containing(None);
+}
+
+fn random_block(words: usize) -> Vec<u8> {
+  thread_rng()
+    .sample_iter(distributions::Standard)
+    .take(words * 8)
+    .collect()
+}
+
+fn sorted_change_logs(store: &dyn BlockStore) -> Vec<ChangeLog> {
+  let mut change_logs = store.change_logs().unwrap();
+  change_logs.sort_by(|a, b| a.node.cmp(&b.node));
+  change_logs
+}
+
+fn own_changes(store: &dyn BlockStore) -> Vec<Change> {
+  store
+    .change_logs()
+    .unwrap()
+    .into_iter()
+    .find(|change_log| change_log.node == store.node_id())
+    .map(|change_log| change_log.changes)
+    .unwrap_or_default()
+}
+
+fn conformance_node_id(new_store: &mut StoreFactory) {
+  for node_id in ["node1", "a-much-longer-node-id-0123456789"] {
+    let store = new_store(node_id);
+
+    assert_that(&store.node_id()).is_equal_to(node_id);
+
+    let block_id = store.add_block(&random_block(10)).unwrap();
+    assert_that(&store.commit("commit1", &[Change::new(Operation::Add, block_id.as_str())])).is_ok();
+
+    assert_that(&store.change_logs()).is_ok_containing(vec![ChangeLog {
+      node: node_id.to_string(),
+      changes: vec![Change::new(Operation::Add, block_id)],
+      commits: vec!["commit1".to_string()],
+    }]);
+  }
+}
+
+fn conformance_empty_store(new_store: &mut StoreFactory) {
+  let store = new_store("node1");
+
+  assert_that(&store.list_ring_ids()).is_ok_containing(vec![]);
+  assert_that(&store.get_ring("ring1")).is_err_containing(StoreError::InvalidBlock("ring1".to_string()));
+  assert_that(&store.change_logs()).is_ok_containing(vec![]);
+  assert_that(&store.get_index("index1")).is_ok_containing(None);
+
+  let stats = store.storage_stats().unwrap();
+
+  assert_that(&stats.block_count).is_equal_to(0);
+  assert_that(&stats.total_bytes).is_equal_to(0);
+  assert_that(&stats.ring_versions).is_empty();
+  assert_that(&stats.change_logs).is_empty();
+}
+
+fn conformance_blocks(new_store: &mut StoreFactory) {
+  let store = new_store("node1");
+  let blocks = [
+    random_block(1),
+    random_block(200),
+    random_block(4096),
+    random_block(200),
+  ];
+  let block_ids = blocks
+    .iter()
+    .map(|block| store.add_block(block).unwrap())
+    .collect::<Vec<_>>();
+
+  for (i, block_id) in block_ids.iter().enumerate() {
+    for other_id in &block_ids[i + 1..] {
+      assert_that(block_id).is_not_equal_to(other_id);
+    }
+  }
+  for (block_id, block) in block_ids.iter().zip(blocks.iter()) {
+    assert_that(&store.get_block(block_id)).is_ok_containing(ZeroingWords::from(block.as_ref()));
+  }
+
+  // Adding the same content again always has to yield a valid block (even if the id is different)
+  let again_id = store.add_block(&blocks[1]).unwrap();
+  assert_that(&store.get_block(&again_id)).is_ok_containing(ZeroingWords::from(blocks[1].as_ref()));
+  assert_that(&store.get_block(&block_ids[1])).is_ok_containing(ZeroingWords::from(blocks[1].as_ref()));
+
+  // Adding blocks must not create any changes
+  assert_that(&own_changes(store.as_ref())).is_empty();
+}
+
+fn conformance_block_stream(new_store: &mut StoreFactory) {
+  let store = new_store("node1");
+  // Larger than any reasonable copy buffer, so the content has to be written in several steps
+  let blocks = [random_block(1), random_block(5000), Vec::new()];
+
+  for block in &blocks {
+    let block_id = store.add_block_stream(&mut block.as_slice()).unwrap();
+
+    assert_that(&store.get_block(&block_id)).is_ok_containing(ZeroingWords::from(block.as_ref()));
+  }
+
+  // Streamed and regular blocks may be mixed
+  let block_id = store.add_block(&blocks[0]).unwrap();
+  let stream_id = store.add_block_stream(&mut blocks[1].as_slice()).unwrap();
+
+  assert_that(&stream_id).is_not_equal_to(&block_id);
+  assert_that(&store.get_block(&block_id)).is_ok_containing(ZeroingWords::from(blocks[0].as_ref()));
+  assert_that(&store.get_block(&stream_id)).is_ok_containing(ZeroingWords::from(blocks[1].as_ref()));
+  assert_that(&own_changes(store.as_ref())).is_empty();
+}
+
+fn conformance_unknown_blocks(new_store: &mut StoreFactory) {
+  let store = new_store("node1");
+  let other_store = new_store("node1");
+  let foreign_id = other_store.add_block(&random_block(10)).unwrap();
+
+  assert_that(&store.get_block("00000000000")).is_err_containing(StoreError::InvalidBlock("00000000000".to_string()));
+  assert_that(&store.get_block(&foreign_id)).is_err_containing(StoreError::InvalidBlock(foreign_id));
+}
+
+fn conformance_ring_versions(new_store: &mut StoreFactory) {
+  let store = new_store("node1");
+  let ring_v1 = random_block(100);
+  let ring_v5 = random_block(120);
+  let ring_v3 = random_block(80);
+
+  assert_that(&store.store_ring("ring", 1, &ring_v1)).is_ok();
+  assert_that(&store.get_ring("ring")).is_ok_containing((1u64, ZeroingWords::from(ring_v1.as_ref())));
+
+  // Versions do not have to be consecutive
+  assert_that(&store.store_ring("ring", 5, &ring_v5)).is_ok();
+  assert_that(&store.get_ring("ring")).is_ok_containing((5u64, ZeroingWords::from(ring_v5.as_ref())));
+
+  // An older version may still be stored (e.g. by a lagging node), but the latest one always wins
+  assert_that(&store.store_ring("ring", 3, &ring_v3)).is_ok();
+  assert_that(&store.get_ring("ring")).is_ok_containing((5u64, ZeroingWords::from(ring_v5.as_ref())));
+  assert_that(&store.list_ring_ids()).is_ok_containing(vec![("ring".to_string(), 5)]);
+
+  let stats = store.storage_stats().unwrap();
+  assert_that(&stats.ring_versions).is_equal_to(vec![("ring".to_string(), 3)]);
+}
+
+fn conformance_ring_conflicts(new_store: &mut StoreFactory) {
+  let store = new_store("node1");
+  let ring_a = random_block(100);
+  let ring_b = random_block(100);
+  let other = random_block(30);
+
+  assert_that(&store.store_ring("ring", 0, &ring_a)).is_ok();
+  assert_that(&store.store_ring("ring", 0, &ring_b)).is_err_containing(StoreError::Conflict(
+    "Ring ring with version 0 already exists".to_string(),
+  ));
+  // Storing the same content again is a conflict as well
+  assert_that(&store.store_ring("ring", 0, &ring_a)).is_err_containing(StoreError::Conflict(
+    "Ring ring with version 0 already exists".to_string(),
+  ));
+  // A conflict must not change the stored content
+  assert_that(&store.get_ring("ring")).is_ok_containing((0u64, ZeroingWords::from(ring_a.as_ref())));
+
+  // Versions of different rings are independent
+  assert_that(&store.store_ring("other", 0, &other)).is_ok();
+  assert_that(&store.get_ring("other")).is_ok_containing((0u64, ZeroingWords::from(other.as_ref())));
+  assert_that(&store.get_ring("ring")).is_ok_containing((0u64, ZeroingWords::from(ring_a.as_ref())));
+
+  let mut ring_ids = store.list_ring_ids().unwrap();
+  ring_ids.sort();
+  assert_that(&ring_ids).is_equal_to(vec![("other".to_string(), 0), ("ring".to_string(), 0)]);
+}
+
+fn conformance_commit_append(new_store: &mut StoreFactory) {
+  let store = new_store("node1");
+  let block_ids = (0..4)
+    .map(|_| store.add_block(&random_block(20)).unwrap())
+    .collect::<Vec<_>>();
+
+  let first = vec![
+    Change::new(Operation::Add, block_ids[0].as_str()),
+    Change::new(Operation::Add, block_ids[1].as_str()),
+  ];
+  let second = vec![Change::new(Operation::Add, block_ids[2].as_str())];
+  let third = vec![
+    Change::new(Operation::Delete, block_ids[0].as_str()),
+    Change::new(Operation::Add, block_ids[3].as_str()),
+  ];
+
+  assert_that(&store.commit("commit1", &first)).is_ok();
+  assert_that(&own_changes(store.as_ref())).is_equal_to(&first);
+  assert_that(&store.commit("commit2", &second)).is_ok();
+  assert_that(&store.commit("commit3", &third)).is_ok();
+
+  // Changes are kept in the order of their commits
+  let expected = first.into_iter().chain(second).chain(third).collect::<Vec<_>>();
+  assert_that(&own_changes(store.as_ref())).is_equal_to(&expected);
+  assert_that(&store.change_logs().unwrap()).has_length(1);
+}
+
+fn conformance_commit_dedup(new_store: &mut StoreFactory) {
+  let store = new_store("node1");
+  let block_ids = (0..3)
+    .map(|_| store.add_block(&random_block(20)).unwrap())
+    .collect::<Vec<_>>();
+  let add0 = Change::new(Operation::Add, block_ids[0].as_str());
+  let add1 = Change::new(Operation::Add, block_ids[1].as_str());
+  let add2 = Change::new(Operation::Add, block_ids[2].as_str());
+
+  assert_that(&store.commit("commit1", &[add0.clone(), add1.clone()])).is_ok();
+
+  assert_that(&store.commit("commit2", std::slice::from_ref(&add1)))
+    .is_err_containing(StoreError::Conflict("Change already committed".to_string()));
+  // A partially committed batch has to be rejected as a whole
+  assert_that(&store.commit("commit3", &[add2.clone(), add0.clone()]))
+    .is_err_containing(StoreError::Conflict("Change already committed".to_string()));
+  assert_that(&own_changes(store.as_ref())).is_equal_to(vec![add0.clone(), add1.clone()]);
+
+  // The same block with a different operation is a different change
+  let delete0 = Change::new(Operation::Delete, block_ids[0].as_str());
+  assert_that(&store.commit("commit4", &[add2.clone(), delete0.clone()])).is_ok();
+  assert_that(&store.commit("commit5", std::slice::from_ref(&delete0)))
+    .is_err_containing(StoreError::Conflict("Change already committed".to_string()));
+
+  assert_that(&own_changes(store.as_ref())).is_equal_to(vec![add0, add1, add2, delete0]);
+}
+
+fn conformance_commit_retry(new_store: &mut StoreFactory) {
+  let store = new_store("node1");
+  let block_ids = (0..3)
+    .map(|_| store.add_block(&random_block(20)).unwrap())
+    .collect::<Vec<_>>();
+  let add0 = Change::new(Operation::Add, block_ids[0].as_str());
+  let add1 = Change::new(Operation::Add, block_ids[1].as_str());
+  let add2 = Change::new(Operation::Add, block_ids[2].as_str());
+
+  assert_that(&store.commit("commit1", &[add0.clone(), add1.clone()])).is_ok();
+  // Retry as if the response of the first attempt got lost
+  assert_that(&store.commit("commit1", &[add0.clone(), add1.clone()])).is_ok();
+  assert_that(&own_changes(store.as_ref())).is_equal_to(vec![add0.clone(), add1.clone()]);
+
+  // The same changes with a different commit id are still a conflict
+  assert_that(&store.commit("commit2", &[add0.clone(), add1.clone()]))
+    .is_err_containing(StoreError::Conflict("Change already committed".to_string()));
+
+  // Retries are recognized even if there have been other commits in the meantime
+  assert_that(&store.commit("commit3", std::slice::from_ref(&add2))).is_ok();
+  assert_that(&store.commit("commit1", &[add0.clone(), add1.clone()])).is_ok();
+  assert_that(&store.commit("commit3", std::slice::from_ref(&add2))).is_ok();
+
+  assert_that(&store.change_logs()).is_ok_containing(vec![ChangeLog {
+    node: "node1".to_string(),
+    changes: vec![add0, add1, add2],
+    commits: vec!["commit1".to_string(), "commit3".to_string()],
+  }]);
+}
+
+fn conformance_update_change_log(new_store: &mut StoreFactory) {
+  let store = new_store("node1");
+  let block_ids = (0..3)
+    .map(|_| store.add_block(&random_block(20)).unwrap())
+    .collect::<Vec<_>>();
+  let add0 = Change::new(Operation::Add, block_ids[0].as_str());
+  let add1 = Change::new(Operation::Add, block_ids[1].as_str());
+  let add2 = Change::new(Operation::Add, block_ids[2].as_str());
+
+  assert_that(&store.commit("commit1", std::slice::from_ref(&add0))).is_ok();
+
+  // Change logs of other nodes (e.g. from a sync) are stored as they are
+  assert_that(&store.update_change_log(ChangeLog {
+    node: "node2".to_string(),
+    changes: vec![add0.clone(), add1.clone()],
+    commits: vec!["other1".to_string()],
+  }))
+  .is_ok();
+  assert_that(&sorted_change_logs(store.as_ref())).is_equal_to(vec![
+    ChangeLog {
+      node: "node1".to_string(),
+      changes: vec![add0.clone()],
+      commits: vec!["commit1".to_string()],
+    },
+    ChangeLog {
+      node: "node2".to_string(),
+      changes: vec![add0.clone(), add1.clone()],
+      commits: vec!["other1".to_string()],
+    },
+  ]);
+
+  // ... replace any previous version
+  assert_that(&store.update_change_log(ChangeLog {
+    node: "node2".to_string(),
+    changes: vec![add0.clone(), add1.clone(), add2.clone()],
+    commits: vec!["other1".to_string(), "other2".to_string()],
+  }))
+  .is_ok();
+  // ... and do not affect the own change log
+  assert_that(&store.commit("commit2", std::slice::from_ref(&add1))).is_ok();
+  assert_that(&sorted_change_logs(store.as_ref())).is_equal_to(vec![
+    ChangeLog {
+      node: "node1".to_string(),
+      changes: vec![add0.clone(), add1.clone()],
+      commits: vec!["commit1".to_string(), "commit2".to_string()],
+    },
+    ChangeLog {
+      node: "node2".to_string(),
+      changes: vec![add0.clone(), add1.clone(), add2.clone()],
+      commits: vec!["other1".to_string(), "other2".to_string()],
+    },
+  ]);
+
+  // Updating the own change log replaces it as well, later commits are checked against the update
+  assert_that(&store.update_change_log(ChangeLog {
+    node: "node1".to_string(),
+    changes: vec![add2.clone()],
+    commits: vec!["commit1".to_string()],
+  }))
+  .is_ok();
+  assert_that(&own_changes(store.as_ref())).is_equal_to(vec![add2.clone()]);
+  assert_that(&store.commit("commit1", std::slice::from_ref(&add0))).is_ok();
+  assert_that(&own_changes(store.as_ref())).is_equal_to(vec![add2.clone()]);
+  assert_that(&store.commit("commit3", std::slice::from_ref(&add2)))
+    .is_err_containing(StoreError::Conflict("Change already committed".to_string()));
+  assert_that(&store.commit("commit4", std::slice::from_ref(&add0))).is_ok();
+  assert_that(&own_changes(store.as_ref())).is_equal_to(vec![add2, add0]);
+}
+
+fn conformance_indexes(new_store: &mut StoreFactory) {
+  let store = new_store("node1");
+  let index1 = random_block(200);
+  let index1_update = random_block(50);
+  let index2 = random_block(300);
+
+  assert_that(&store.get_index("index1")).is_ok_containing(None);
+  assert_that(&store.store_index("index1", &index1)).is_ok();
+  assert_that(&store.get_index("index1")).is_ok_containing(Some(ZeroingWords::from(index1.as_ref())));
+  assert_that(&store.get_index("index2")).is_ok_containing(None);
+
+  // Indexes may be overwritten at any time (even with smaller content)
+  assert_that(&store.store_index("index1", &index1_update)).is_ok();
+  assert_that(&store.get_index("index1")).is_ok_containing(Some(ZeroingWords::from(index1_update.as_ref())));
+
+  assert_that(&store.store_index("index2", &index2)).is_ok();
+  assert_that(&store.get_index("index2")).is_ok_containing(Some(ZeroingWords::from(index2.as_ref())));
+  assert_that(&store.get_index("index1")).is_ok_containing(Some(ZeroingWords::from(index1_update.as_ref())));
+
+  // Indexes are neither rings nor blocks
+  assert_that(&store.list_ring_ids()).is_ok_containing(vec![]);
+  assert_that(&store.get_ring("index1")).is_err_containing(StoreError::InvalidBlock("index1".to_string()));
+  assert_that(&store.change_logs()).is_ok_containing(vec![]);
+  assert_that(&store.storage_stats().map(|stats| stats.block_count)).is_ok_containing(0);
+}
+
+fn dir_url(scheme: &str, tempdir: &TempDir) -> String {
+  #[cfg(not(windows))]
+  let url = format!("{}://{}", scheme, tempdir.path().to_string_lossy());
+  #[cfg(windows)]
+  let url = format!("{}:///{}", scheme, tempdir.path().to_string_lossy().replace('\\', "/"));
+
+  url
+}
+
+#[test]
+fn test_memory_store_conformance() {
+  run_conformance_suite(&mut |node_id| open_block_store("memory://", node_id).unwrap());
+}
+
+#[test]
+fn test_local_dir_store_conformance() {
+  let mut tempdirs = vec![];
+
+  run_conformance_suite(&mut |node_id| {
+    let tempdir = Builder::new().prefix("t-rust-less-test").tempdir().unwrap();
+    let store = open_block_store(&dir_url("file", &tempdir), node_id).unwrap();
+    tempdirs.push(tempdir);
+    store
+  });
+
+  let shared = Builder::new().prefix("t-rust-less-test").tempdir().unwrap();
+  run_shared_suite(&mut |node_id| open_block_store(&dir_url("file", &shared), node_id).unwrap());
+}
+
+#[test]
+fn test_local_wal_store_conformance() {
+  let mut tempdirs = vec![];
+
+  run_conformance_suite(&mut |node_id| {
+    let tempdir = Builder::new().prefix("t-rust-less-test-wal").tempdir().unwrap();
+    let store = open_block_store(&dir_url("wal", &tempdir), node_id).unwrap();
+    tempdirs.push(tempdir);
+    store
+  });
+
+  let shared = Builder::new().prefix("t-rust-less-test-wal").tempdir().unwrap();
+  run_shared_suite(&mut |node_id| open_block_store(&dir_url("wal", &shared), node_id).unwrap());
+}
+
+#[cfg(feature = "sled")]
+#[test]
+fn test_sled_store_conformance() {
+  let mut tempdirs = vec![];
+
+  run_conformance_suite(&mut |node_id| {
+    let tempdir = Builder::new().prefix("t-rust-less-test").tempdir().unwrap();
+    let store = open_block_store(&dir_url("sled", &tempdir), node_id).unwrap();
+    tempdirs.push(tempdir);
+    store
+  });
+}
+
+#[cfg(feature = "sled")]
+#[test]
+fn test_sled_store_encrypted_conformance() {
+  let mut tempdirs = vec![];
+
+  run_conformance_suite(&mut |node_id| {
+    let tempdir = Builder::new().prefix("t-rust-less-test").tempdir().unwrap();
+    let url = format!(
+      "{}/db?encrypted=true&key_file={}",
+      dir_url("sled", &tempdir),
+      tempdir.path().join("device.key").to_string_lossy()
+    );
+    let store = open_block_store(&url, node_id).unwrap();
+    tempdirs.push(tempdir);
+    store
+  });
+}
+
+#[cfg(feature = "webdav")]
+#[test]
+fn test_webdav_store_conformance() {
+  let server = super::webdav_server::FakeWebDavServer::start();
+  let mut count = 0;
+
+  run_conformance_suite(&mut |node_id| {
+    count += 1;
+    open_block_store(
+      &server.store_url(&format!("remote.php/dav/files/store{}", count)),
+      node_id,
+    )
+    .unwrap()
+  });
+
+  run_shared_suite(&mut |node_id| open_block_store(&server.store_url("shared/"), node_id).unwrap());
+}
+
+#[cfg(feature = "webdav")]
+#[test]
+fn test_webdav_store_unauthorized() {
+  let server = super::webdav_server::FakeWebDavServer::start();
+  let url = server.store_url("store").replace("secret%3A", "wrong%3A");
+  let store = open_block_store(&url, "node1").unwrap();
+
+  assert_that(&store.list_ring_ids())
+    .is_err()
+    .matches(|error| matches!(error, StoreError::IO(_)));
+  assert_that(&store.add_block(&random_block(10)))
+    .is_err()
+    .matches(|error| matches!(error, StoreError::IO(_)));
+}
+
+/// Run against a real server: `T_RUST_LESS_SFTP_URL` has to point to a writable directory (e.g.
+/// `sftp://user@localhost/tmp/t-rust-less`), all stores are created in random sub-directories of it.
+#[cfg(feature = "sftp")]
+#[test]
+#[ignore = "requires a sftp server (T_RUST_LESS_SFTP_URL)"]
+fn test_sftp_store_conformance() {
+  let base_url = match std::env::var("T_RUST_LESS_SFTP_URL") {
+    Ok(base_url) => base_url,
+    Err(_) => {
+      eprintln!("T_RUST_LESS_SFTP_URL not set, skipping");
+      return;
+    }
+  };
+  let store_url = |name: &str| {
+    let mut url = url::Url::parse(&base_url).unwrap();
+    let path = format!("{}/{}", url.path().trim_end_matches('/'), name);
+    url.set_path(&path);
+    url.to_string()
+  };
+  let run_id = thread_rng()
+    .sample_iter(distributions::Alphanumeric)
+    .take(12)
+    .map(char::from)
+    .collect::<String>();
+  let mut count = 0;
+
+  run_conformance_suite(&mut |node_id| {
+    count += 1;
+    open_block_store(&store_url(&format!("{}-store{}", run_id, count)), node_id).unwrap()
+  });
+
+  let shared_url = store_url(&format!("{}-shared", run_id));
+  run_shared_suite(&mut |node_id| open_block_store(&shared_url, node_id).unwrap());
+}
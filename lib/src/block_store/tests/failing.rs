@@ -0,0 +1,84 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use crate::block_store::{BlockStore, Change, ChangeLog, RingContent, RingId, StoreError, StoreResult};
+use crate::memguard::weak::ZeroingWords;
+
+/// Store where access to specific blocks fails and specific rings are in conflict
+///
+/// `truncated_blocks` are returned with the last byte missing the given number of times.
+#[derive(Debug)]
+pub struct FailingBlockStore {
+  inner: Arc<dyn BlockStore>,
+  pub failing_blocks: Mutex<HashSet<String>>,
+  pub conflicting_rings: Mutex<HashSet<String>>,
+  pub truncated_blocks: Mutex<HashMap<String, u32>>,
+}
+
+impl FailingBlockStore {
+  pub fn new(inner: Arc<dyn BlockStore>) -> FailingBlockStore {
+    FailingBlockStore {
+      inner,
+      failing_blocks: Mutex::new(HashSet::new()),
+      conflicting_rings: Mutex::new(HashSet::new()),
+      truncated_blocks: Mutex::new(HashMap::new()),
+    }
+  }
+}
+
+impl BlockStore for FailingBlockStore {
+  fn node_id(&self) -> &str {
+    self.inner.node_id()
+  }
+
+  fn list_ring_ids(&self) -> StoreResult<Vec<RingId>> {
+    self.inner.list_ring_ids()
+  }
+
+  fn get_ring(&self, ring_id: &str) -> StoreResult<RingContent> {
+    self.inner.get_ring(ring_id)
+  }
+
+  fn store_ring(&self, ring_id: &str, version: u64, raw: &[u8]) -> StoreResult<()> {
+    if self.conflicting_rings.lock()?.contains(ring_id) {
+      return Err(StoreError::Conflict(ring_id.to_string()));
+    }
+    self.inner.store_ring(ring_id, version, raw)
+  }
+
+  fn change_logs(&self) -> StoreResult<Vec<ChangeLog>> {
+    self.inner.change_logs()
+  }
+
+  fn get_index(&self, index_id: &str) -> StoreResult<Option<ZeroingWords>> {
+    self.inner.get_index(index_id)
+  }
+
+  fn store_index(&self, index_id: &str, raw: &[u8]) -> StoreResult<()> {
+    self.inner.store_index(index_id, raw)
+  }
+
+  fn add_block(&self, raw: &[u8]) -> StoreResult<String> {
+    self.inner.add_block(raw)
+  }
+
+  fn get_block(&self, block: &str) -> StoreResult<ZeroingWords> {
+    if self.failing_blocks.lock()?.contains(block) {
+      return Err(StoreError::IO(format!("Unable to read {}", block)));
+    }
+    if let Some(count) = self.truncated_blocks.lock()?.get_mut(block).filter(|count| **count > 0) {
+      *count -= 1;
+      let content = self.inner.get_block(block)?;
+      return Ok(ZeroingWords::from(&content[..content.len() - 1]));
+    }
+    self.inner.get_block(block)
+  }
+
+  fn commit(&self, commit_id: &str, changes: &[Change]) -> StoreResult<()> {
+    self.inner.commit(commit_id, changes)
+  }
+
+  fn update_change_log(&self, change_log: ChangeLog) -> StoreResult<()> {
+    self.inner.update_change_log(change_log)
+  }
+}
@@ -0,0 +1,739 @@
+use super::{
+  copy_with_block_id, generate_block_id, generate_commit_id, open_block_store, BlockStore, RingContent, RingId,
+  StoreError, StoreResult,
+};
+use crate::block_store::model::Operation;
+use crate::block_store::{Change, ChangeLog};
+use crate::memguard::weak::ZeroingWords;
+use rand::rngs::ThreadRng;
+use rand::{distributions, thread_rng, Rng};
+use spectral::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tempfile::Builder;
+
+mod conformance;
+pub mod failing;
+#[cfg(feature = "webdav")]
+mod webdav_server;
+
+fn common_store_tests(store: Arc<dyn BlockStore>) {
+  let mut rng = thread_rng();
+  common_test_ring(store.as_ref(), &mut rng);
+  common_test_index(store.as_ref(), &mut rng);
+  common_test_blocks_commits(store.as_ref(), &mut rng);
+  common_test_storage_stats(store.as_ref());
+}
+
+fn sort_ring_ids(ring_ids: Vec<RingId>) -> Vec<String> {
+  let mut ids: Vec<String> = ring_ids
+    .into_iter()
+    .map(|(id, version)| format!("{}.{}", id, version))
+    .collect();
+  ids.sort();
+  ids
+}
+
+fn common_test_ring(store: &dyn BlockStore, rng: &mut ThreadRng) {
+  let ring1a = rng
+    .sample_iter(distributions::Standard)
+    .take(200 * 8)
+    .collect::<Vec<u8>>();
+  let ring1b = rng.sample_iter(distributions::Standard).take(200).collect::<Vec<u8>>();
+  let ring2a = rng
+    .sample_iter(distributions::Standard)
+    .take(300 * 8)
+    .collect::<Vec<u8>>();
+  let ring2b = rng
+    .sample_iter(distributions::Standard)
+    .take(300 * 8)
+    .collect::<Vec<u8>>();
+
+  assert_that!(store.list_ring_ids()).is_ok_containing(vec![]);
+  assert_that!(store.store_ring("ring1", 0, &ring1a)).is_ok();
+  assert_that!(store.get_ring("ring1")).is_ok_containing((0u64, ZeroingWords::from(ring1a.as_ref())));
+  assert_that!(store.list_ring_ids().map(sort_ring_ids)).is_ok_containing(vec!["ring1.0".to_string()]);
+
+  assert_that!(store.store_ring("ring1", 0, &ring1b)).is_err_containing(StoreError::Conflict(
+    "Ring ring1 with version 0 already exists".to_string(),
+  ));
+  assert_that!(store.store_ring("ring1", 1, &ring1b)).is_ok();
+  assert_that!(store.get_ring("ring1")).is_ok_containing((1u64, ZeroingWords::from(ring1b.as_ref())));
+
+  assert_that!(store.list_ring_ids().map(sort_ring_ids)).is_ok_containing(vec!["ring1.1".to_string()]);
+
+  assert_that!(store.store_ring("ring2", 0, &ring2a)).is_ok();
+  assert_that!(store.get_ring("ring2")).is_ok_containing((0u64, ZeroingWords::from(ring2a.as_ref())));
+  assert_that!(store.list_ring_ids().map(sort_ring_ids))
+    .is_ok_containing(vec!["ring1.1".to_string(), "ring2.0".to_string()]);
+  assert_that!(store.store_ring("ring2", 0, &ring2b)).is_err_containing(StoreError::Conflict(
+    "Ring ring2 with version 0 already exists".to_string(),
+  ));
+  assert_that!(store.store_ring("ring2", 123, &ring2b)).is_ok();
+  assert_that!(store.get_ring("ring2")).is_ok_containing((123u64, ZeroingWords::from(ring2b.as_ref())));
+
+  assert_that!(store.list_ring_ids().map(sort_ring_ids))
+    .is_ok_containing(vec!["ring1.1".to_string(), "ring2.123".to_string()]);
+}
+
+fn common_test_index(store: &dyn BlockStore, rng: &mut ThreadRng) {
+  let node1 = rng
+    .sample_iter(distributions::Alphanumeric)
+    .map(char::from)
+    .take(40)
+    .collect::<String>();
+  let node1_index1 = rng
+    .sample_iter(distributions::Standard)
+    .take(200 * 8)
+    .collect::<Vec<u8>>();
+  let node1_index2 = rng
+    .sample_iter(distributions::Standard)
+    .take(200 * 8)
+    .collect::<Vec<u8>>();
+  let node2 = rng
+    .sample_iter(distributions::Alphanumeric)
+    .map(char::from)
+    .take(40)
+    .collect::<String>();
+  let node2_index1 = rng
+    .sample_iter(distributions::Standard)
+    .take(200 * 8)
+    .collect::<Vec<u8>>();
+  let node2_index2 = rng
+    .sample_iter(distributions::Standard)
+    .take(200 * 8)
+    .collect::<Vec<u8>>();
+
+  assert_that!(store.get_index(&node1)).is_ok_containing(None);
+  assert_that!(store.store_index(&node1, &node1_index1)).is_ok();
+  assert_that!(store.get_index(&node2)).is_ok_containing(None);
+  assert_that!(store.store_index(&node2, &node2_index1)).is_ok();
+  assert_that!(store.get_index(&node1)).is_ok_containing(Some(ZeroingWords::from(node1_index1.as_ref())));
+  assert_that!(store.get_index(&node2)).is_ok_containing(Some(ZeroingWords::from(node2_index1.as_ref())));
+  assert_that!(store.store_index(&node1, &node1_index2)).is_ok();
+  assert_that!(store.store_index(&node2, &node2_index2)).is_ok();
+  assert_that!(store.get_index(&node1)).is_ok_containing(Some(ZeroingWords::from(node1_index2.as_ref())));
+  assert_that!(store.get_index(&node2)).is_ok_containing(Some(ZeroingWords::from(node2_index2.as_ref())));
+}
+
+fn common_test_blocks_commits(store: &dyn BlockStore, rng: &mut ThreadRng) {
+  assert_that(&store.get_block("00000000000")).is_err_containing(StoreError::InvalidBlock("00000000000".to_string()));
+
+  let block1 = rng
+    .sample_iter(distributions::Standard)
+    .take(200 * 8)
+    .collect::<Vec<u8>>();
+  let block2 = rng
+    .sample_iter(distributions::Standard)
+    .take(200 * 8)
+    .collect::<Vec<u8>>();
+  let block3 = rng
+    .sample_iter(distributions::Standard)
+    .take(200 * 8)
+    .collect::<Vec<u8>>();
+
+  let block1_id = store.add_block(&block1).unwrap();
+  let block2_id = store.add_block(&block2).unwrap();
+  let block3_id = store.add_block(&block3).unwrap();
+
+  assert_that!(&block1_id).is_not_equal_to(&block2_id);
+  assert_that!(&block1_id).is_not_equal_to(&block3_id);
+  assert_that!(&block2_id).is_not_equal_to(&block3_id);
+
+  assert_that!(store.get_block(&block1_id)).is_ok_containing(ZeroingWords::from(block1.as_ref()));
+  assert_that!(store.get_block(&block2_id)).is_ok_containing(ZeroingWords::from(block2.as_ref()));
+  assert_that!(store.get_block(&block3_id)).is_ok_containing(ZeroingWords::from(block3.as_ref()));
+
+  assert_that!(store.commit(
+    "commit1",
+    &[
+      Change {
+        op: Operation::Add,
+        block: block1_id.clone(),
+      },
+      Change {
+        op: Operation::Add,
+        block: block2_id.clone(),
+      },
+    ]
+  ))
+  .is_ok();
+
+  assert_that!(store.change_logs()).is_ok_containing(vec![ChangeLog {
+    node: store.node_id().to_string(),
+    changes: vec![
+      Change {
+        op: Operation::Add,
+        block: block1_id.clone(),
+      },
+      Change {
+        op: Operation::Add,
+        block: block2_id.clone(),
+      },
+    ],
+    commits: vec!["commit1".to_string()],
+  }]);
+
+  assert_that(&store.commit(
+    "commit2",
+    &[Change {
+      op: Operation::Add,
+      block: block2_id.clone(),
+    }],
+  ))
+  .is_err()
+  .matches(|error| matches!(error, StoreError::Conflict(_)));
+
+  assert_that(&store.commit(
+    "commit3",
+    &[Change {
+      op: Operation::Add,
+      block: block3_id.clone(),
+    }],
+  ))
+  .is_ok();
+
+  assert_that(&store.change_logs()).is_ok_containing(vec![ChangeLog {
+    node: store.node_id().to_string(),
+    changes: vec![
+      Change {
+        op: Operation::Add,
+        block: block1_id,
+      },
+      Change {
+        op: Operation::Add,
+        block: block2_id,
+      },
+      Change {
+        op: Operation::Add,
+        block: block3_id,
+      },
+    ],
+    commits: vec!["commit1".to_string(), "commit3".to_string()],
+  }]);
+}
+
+fn common_test_storage_stats(store: &dyn BlockStore) {
+  let change_logs = store.change_logs().unwrap();
+  let blocks: Vec<String> = change_logs[0].changes.iter().map(|c| c.block.clone()).collect();
+
+  let stats = store.storage_stats().unwrap();
+
+  assert_that(&stats.block_count).is_equal_to(3);
+  assert_that(&stats.total_bytes).is_equal_to(3 * 200 * 8);
+  assert_that(&stats.largest_blocks).has_length(3);
+  assert_that(&stats.missing_blocks).is_equal_to(0);
+  assert_that(&stats.ring_versions.iter().map(|(id, _)| id.clone()).collect::<Vec<_>>())
+    .is_equal_to(vec!["ring1".to_string(), "ring2".to_string()]);
+  assert_that(&stats.change_logs).is_equal_to(vec![(store.node_id().to_string(), 3)]);
+  assert_that(&stats.reclaimable_blocks).is_equal_to(0);
+
+  assert_that(&store.commit(
+    "commit4",
+    &[Change {
+      op: Operation::Delete,
+      block: blocks[0].clone(),
+    }],
+  ))
+  .is_ok();
+
+  let stats = store.storage_stats().unwrap();
+
+  assert_that(&stats.block_count).is_equal_to(3);
+  assert_that(&stats.change_logs).is_equal_to(vec![(store.node_id().to_string(), 4)]);
+  assert_that(&stats.reclaimable_blocks).is_equal_to(1);
+  assert_that(&stats.reclaimable_bytes).is_equal_to(200 * 8);
+}
+
+#[test]
+fn test_local_dir_store() {
+  let tempdir = Builder::new().prefix("t-rust-less-test").tempdir().unwrap();
+  #[cfg(not(windows))]
+  let url = format!("file://{}", tempdir.path().to_string_lossy());
+  #[cfg(windows)]
+  let url = format!("file:///{}", tempdir.path().to_string_lossy().replace('\\', "/"));
+
+  let store = open_block_store(&url, "node1").unwrap();
+
+  common_store_tests(store);
+}
+
+#[test]
+fn test_memory_store() {
+  let store = open_block_store("memory://", "node1").unwrap();
+
+  common_store_tests(store);
+}
+
+#[test]
+fn test_local_wal_store() {
+  let tempdir = Builder::new().prefix("t-rust-less-test-wal").tempdir().unwrap();
+  #[cfg(not(windows))]
+  let url = format!("wal://{}", tempdir.path().to_string_lossy());
+  #[cfg(windows)]
+  let url = format!("wal:///{}", tempdir.path().to_string_lossy().replace('\\', "/"));
+
+  let store = open_block_store(&url, "node1").unwrap();
+
+  common_store_tests(store);
+}
+
+#[cfg(feature = "sled")]
+#[test]
+fn test_sled_store() {
+  let tempdir = Builder::new().prefix("t-rust-less-test").tempdir().unwrap();
+  #[cfg(not(windows))]
+  let url = format!("sled://{}", tempdir.path().to_string_lossy());
+  #[cfg(windows)]
+  let url = format!("sled:///{}", tempdir.path().to_string_lossy().replace('\\', "/"));
+
+  let store = open_block_store(url.as_str(), "node1").unwrap();
+
+  common_store_tests(store);
+}
+
+#[cfg(feature = "sled")]
+#[test]
+fn test_sled_store_encrypted() {
+  let tempdir = Builder::new().prefix("t-rust-less-test").tempdir().unwrap();
+  let db_path = tempdir.path().join("db");
+  let key_file = tempdir.path().join("device.key");
+  #[cfg(not(windows))]
+  let url = format!(
+    "sled://{}?encrypted=true&key_file={}",
+    db_path.to_string_lossy(),
+    key_file.to_string_lossy()
+  );
+  #[cfg(windows)]
+  let url = format!(
+    "sled:///{}?encrypted=true&key_file={}",
+    db_path.to_string_lossy().replace('\\', "/"),
+    key_file.to_string_lossy()
+  );
+
+  let store = open_block_store(url.as_str(), "node1").unwrap();
+  let mut rng = thread_rng();
+  common_test_ring(store.as_ref(), &mut rng);
+  common_test_index(store.as_ref(), &mut rng);
+  common_test_blocks_commits(store.as_ref(), &mut rng);
+  let block_id = store.change_logs().unwrap()[0].changes[0].block.clone();
+  let block = store.get_block(&block_id).unwrap();
+  drop(store);
+
+  // Reopen with the same device key
+  let store = open_block_store(url.as_str(), "node1").unwrap();
+  assert_that(&store.get_block(&block_id)).is_ok_containing(block);
+  assert_that(&store.change_logs().unwrap()[0].changes).has_length(3);
+  assert_that!(store.list_ring_ids().map(sort_ring_ids))
+    .is_ok_containing(vec!["ring1.1".to_string(), "ring2.123".to_string()]);
+  drop(store);
+
+  // Neither block ids nor node ids are visible in the raw database
+  {
+    let db = ::sled::open(&db_path).unwrap();
+    for tree_name in ["blocks", "change_logs", "rings"] {
+      let tree = db.open_tree(tree_name).unwrap();
+      for kv in tree.iter() {
+        let (key, value) = kv.unwrap();
+        assert_that(&String::from_utf8_lossy(&key).contains(&block_id)).is_false();
+        assert_that(&String::from_utf8_lossy(&key).contains("node1")).is_false();
+        assert_that(&String::from_utf8_lossy(&value).contains("node1")).is_false();
+      }
+    }
+  }
+
+  // Opening without encryption or with a different device key has to fail
+  #[cfg(not(windows))]
+  {
+    let plain_url = format!("sled://{}", db_path.to_string_lossy());
+    assert_that(&open_block_store(plain_url.as_str(), "node1")).is_err();
+
+    let other_url = format!(
+      "sled://{}?encrypted=true&key_file={}",
+      db_path.to_string_lossy(),
+      tempdir.path().join("other.key").to_string_lossy()
+    );
+    assert_that(&open_block_store(other_url.as_str(), "node1")).is_err();
+  }
+}
+
+#[test]
+fn test_invalid_store_urls() {
+  assert_that(&open_block_store("unknown://somewhere", "node1"))
+    .is_err()
+    .matches(|error| matches!(error, StoreError::InvalidStoreUrl(_)));
+  assert_that(&open_block_store("file://somehost/relative", "node1"))
+    .is_err()
+    .matches(|error| matches!(error, StoreError::InvalidStoreUrl(_)));
+}
+
+#[cfg(feature = "sftp")]
+#[test]
+fn test_sftp_config() {
+  use super::sftp::SftpConfig;
+  use std::path::PathBuf;
+  use url::Url;
+
+  let config = SftpConfig::from_url(
+    &Url::parse("sftp://backup%40home@example.com:2222/srv/t%20rust?key=/keys/id_ed25519").unwrap(),
+  )
+  .unwrap();
+
+  assert_that(&config).is_equal_to(SftpConfig {
+    host: "example.com".to_string(),
+    port: 2222,
+    username: "backup@home".to_string(),
+    base_dir: PathBuf::from("/srv/t rust"),
+    key_file: Some(PathBuf::from("/keys/id_ed25519")),
+    known_hosts: None,
+  });
+
+  let config =
+    SftpConfig::from_url(&Url::parse("sftp://user@example.com/store?known_hosts=/etc/ssh/known").unwrap()).unwrap();
+
+  assert_that(&config.port).is_equal_to(22);
+  assert_that(&config.key_file).is_none();
+  assert_that(&config.known_hosts).is_equal_to(Some(PathBuf::from("/etc/ssh/known")));
+
+  // Query parameters are percent-decoded as well
+  let config = SftpConfig::from_url(
+    &Url::parse("sftp://user@example.com/store?key=%2Fkeys%2Fmy%20key&known_hosts=%2Fetc%2Fmy%20hosts").unwrap(),
+  )
+  .unwrap();
+
+  assert_that(&config.key_file).is_equal_to(Some(PathBuf::from("/keys/my key")));
+  assert_that(&config.known_hosts).is_equal_to(Some(PathBuf::from("/etc/my hosts")));
+
+  // Without user in the url it is the current one
+  if let Ok(user) = std::env::var("USER") {
+    let config = SftpConfig::from_url(&Url::parse("sftp://example.com/store").unwrap()).unwrap();
+
+    assert_that(&config.username).is_equal_to(user);
+  }
+
+  assert_that(&open_block_store("sftp:///store", "node1"))
+    .is_err()
+    .matches(|error| matches!(error, StoreError::InvalidStoreUrl(_)));
+  assert_that(&open_block_store(
+    "sftp://user@example.com/store?password=secret",
+    "node1",
+  ))
+  .is_err()
+  .matches(|error| matches!(error, StoreError::InvalidStoreUrl(_)));
+  // Nothing is listening on port 1
+  assert_that(&open_block_store("sftp://user@127.0.0.1:1/store", "node1"))
+    .is_err()
+    .matches(|error| matches!(error, StoreError::IO(_)));
+}
+
+/// Store where the response of the first commit gets lost, i.e. the commit is applied but reported as failure
+#[derive(Debug)]
+struct LostResponseStore {
+  inner: Arc<dyn BlockStore>,
+  lost: AtomicBool,
+}
+
+impl BlockStore for LostResponseStore {
+  fn node_id(&self) -> &str {
+    self.inner.node_id()
+  }
+
+  fn list_ring_ids(&self) -> StoreResult<Vec<RingId>> {
+    self.inner.list_ring_ids()
+  }
+
+  fn get_ring(&self, ring_id: &str) -> StoreResult<RingContent> {
+    self.inner.get_ring(ring_id)
+  }
+
+  fn store_ring(&self, ring_id: &str, version: u64, raw: &[u8]) -> StoreResult<()> {
+    self.inner.store_ring(ring_id, version, raw)
+  }
+
+  fn change_logs(&self) -> StoreResult<Vec<ChangeLog>> {
+    self.inner.change_logs()
+  }
+
+  fn get_index(&self, index_id: &str) -> StoreResult<Option<ZeroingWords>> {
+    self.inner.get_index(index_id)
+  }
+
+  fn store_index(&self, index_id: &str, raw: &[u8]) -> StoreResult<()> {
+    self.inner.store_index(index_id, raw)
+  }
+
+  fn add_block(&self, raw: &[u8]) -> StoreResult<String> {
+    self.inner.add_block(raw)
+  }
+
+  fn get_block(&self, block: &str) -> StoreResult<ZeroingWords> {
+    self.inner.get_block(block)
+  }
+
+  fn commit(&self, commit_id: &str, changes: &[Change]) -> StoreResult<()> {
+    self.inner.commit(commit_id, changes)?;
+    if !self.lost.swap(true, Ordering::SeqCst) {
+      return Err(StoreError::IO("Connection reset".to_string()));
+    }
+    Ok(())
+  }
+
+  fn update_change_log(&self, change_log: ChangeLog) -> StoreResult<()> {
+    self.inner.update_change_log(change_log)
+  }
+}
+
+fn check_commit_retry(inner: Arc<dyn BlockStore>) {
+  let store = LostResponseStore {
+    inner,
+    lost: AtomicBool::new(false),
+  };
+  let block_id = store.add_block(&[1u8; 64]).unwrap();
+  let changes = vec![Change::new(Operation::Add, block_id)];
+  let commit_id = generate_commit_id();
+
+  assert_that(&store.commit(&commit_id, &changes)).is_err_containing(StoreError::IO("Connection reset".to_string()));
+  assert_that(&store.commit(&commit_id, &changes)).is_ok();
+
+  assert_that(&store.change_logs()).is_ok_containing(vec![ChangeLog {
+    node: "node1".to_string(),
+    changes: changes.clone(),
+    commits: vec![commit_id],
+  }]);
+
+  // A new commit of the same changes is still a conflict
+  assert_that(&store.commit(&generate_commit_id(), &changes))
+    .is_err_containing(StoreError::Conflict("Change already committed".to_string()));
+}
+
+#[test]
+fn test_commit_retry_after_lost_response() {
+  let tempdir = Builder::new().prefix("t-rust-less-test").tempdir().unwrap();
+  std::fs::create_dir(tempdir.path().join("dir")).unwrap();
+  std::fs::create_dir(tempdir.path().join("wal")).unwrap();
+
+  check_commit_retry(open_block_store("memory://", "node1").unwrap());
+  check_commit_retry(Arc::new(
+    super::local_dir::LocalDirBlockStore::new(tempdir.path().join("dir"), "node1").unwrap(),
+  ));
+  check_commit_retry(Arc::new(
+    super::local_wal::LocalWalBlockStore::new(tempdir.path().join("wal"), "node1").unwrap(),
+  ));
+  #[cfg(feature = "sled")]
+  check_commit_retry(Arc::new(
+    super::sled::SledBlockStore::new(tempdir.path().join("sled"), "node1").unwrap(),
+  ));
+}
+
+#[test]
+fn test_copy_with_block_id() {
+  let rng = thread_rng();
+  let raw = rng
+    .sample_iter(distributions::Standard)
+    .take(20_000)
+    .collect::<Vec<u8>>();
+  let mut copy = Vec::new();
+
+  assert_that(&copy_with_block_id(&mut raw.as_slice(), &mut copy))
+    .is_ok_containing((generate_block_id(&raw), raw.len() as u64));
+  assert_that(&copy).is_equal_to(&raw);
+}
+
+#[test]
+fn test_generate_commit_id() {
+  let id1 = generate_commit_id();
+  let id2 = generate_commit_id();
+
+  assert_that(&id1.len()).is_equal_to(32);
+  assert_that(&id1).is_not_equal_to(&id2);
+}
+
+fn check_verify<O, C>(open: O, missing_block: &str, corrupt: C)
+where
+  O: Fn() -> Arc<dyn BlockStore>,
+  C: FnOnce(&str),
+{
+  let mut rng = thread_rng();
+  let (block1, block2) = {
+    let store = open();
+    let block1 = store
+      .add_block(
+        &(&mut rng)
+          .sample_iter(distributions::Standard)
+          .take(512)
+          .collect::<Vec<u8>>(),
+      )
+      .unwrap();
+    let block2 = store
+      .add_block(
+        &(&mut rng)
+          .sample_iter(distributions::Standard)
+          .take(256)
+          .collect::<Vec<u8>>(),
+      )
+      .unwrap();
+    let orphan = store
+      .add_block(
+        &(&mut rng)
+          .sample_iter(distributions::Standard)
+          .take(128)
+          .collect::<Vec<u8>>(),
+      )
+      .unwrap();
+    store
+      .commit(
+        &generate_commit_id(),
+        &[
+          Change::new(Operation::Add, &block1),
+          Change::new(Operation::Add, &block2),
+          Change::new(Operation::Add, missing_block),
+        ],
+      )
+      .unwrap();
+
+    let report = store.verify().unwrap();
+
+    assert_that(&report.is_ok()).is_false();
+    assert_that(&report.checked_blocks).is_equal_to(3);
+    assert_that(&report.mismatched_blocks).is_empty();
+    assert_that(&report.missing_blocks).is_equal_to(vec![missing_block.to_string()]);
+    assert_that(&report.orphan_blocks).is_equal_to(vec![orphan]);
+
+    (block1, block2)
+  };
+
+  corrupt(&block2);
+
+  let store = open();
+  let report = store.verify().unwrap();
+
+  assert_that(&report.checked_blocks).is_equal_to(3);
+  assert_that(&report.mismatched_blocks).is_equal_to(vec![block2]);
+  assert_that(&report.missing_blocks).is_equal_to(vec![missing_block.to_string()]);
+  assert_that(&report.mismatched_blocks).does_not_contain(&block1);
+}
+
+#[test]
+fn test_verify_local_dir_store() {
+  let tempdir = Builder::new().prefix("t-rust-less-test").tempdir().unwrap();
+
+  check_verify(
+    || Arc::new(super::local_dir::LocalDirBlockStore::new(tempdir.path(), "node1").unwrap()),
+    &generate_block_id(b"not stored"),
+    |block| {
+      std::fs::write(tempdir.path().join("blocks").join(&block[0..2]).join(block), [0u8; 256]).unwrap();
+    },
+  );
+}
+
+#[test]
+fn test_verify_local_wal_store() {
+  let tempdir = Builder::new().prefix("t-rust-less-test-wal").tempdir().unwrap();
+
+  check_verify(
+    || Arc::new(super::local_wal::LocalWalBlockStore::new(tempdir.path(), "node1").unwrap()),
+    "node1:100000",
+    |block| {
+      use std::io::{Seek, SeekFrom, Write};
+
+      // Break the chunk size of the block
+      let offset = block.split_once(':').unwrap().1.parse::<u64>().unwrap();
+      let mut block_file = std::fs::OpenOptions::new()
+        .write(true)
+        .open(tempdir.path().join("node1.blocks"))
+        .unwrap();
+      block_file.seek(SeekFrom::Start(offset)).unwrap();
+      block_file.write_all(&u64::MAX.to_le_bytes()).unwrap();
+    },
+  );
+}
+
+#[cfg(feature = "sled")]
+#[test]
+fn test_verify_sled_store() {
+  let tempdir = Builder::new().prefix("t-rust-less-test").tempdir().unwrap();
+  let db_path = tempdir.path().join("db");
+
+  check_verify(
+    || Arc::new(super::sled::SledBlockStore::new(&db_path, "node1").unwrap()),
+    &generate_block_id(b"not stored"),
+    |block| {
+      let db = ::sled::open(&db_path).unwrap();
+      let blocks = db.open_tree("blocks").unwrap();
+      blocks.insert(block.as_bytes(), vec![0u8; 256]).unwrap();
+      blocks.flush().unwrap();
+    },
+  );
+}
+
+fn check_collect_garbage(store: Arc<dyn BlockStore>, removes: bool) {
+  let referenced = store.add_block(&[1u8; 64]).unwrap();
+  let unreferenced = store.add_block(&[2u8; 64]).unwrap();
+
+  let removed = store
+    .collect_garbage(&std::collections::HashSet::from([referenced.clone()]))
+    .unwrap();
+
+  assert_that(&store.get_block(&referenced)).is_ok();
+  if removes {
+    assert_that(&removed).is_equal_to(1);
+    assert_that(&store.get_block(&unreferenced)).is_err_containing(StoreError::InvalidBlock(unreferenced));
+  } else {
+    assert_that(&removed).is_equal_to(0);
+    assert_that(&store.get_block(&unreferenced)).is_ok();
+  }
+}
+
+#[test]
+fn test_collect_garbage() {
+  let tempdir = Builder::new().prefix("t-rust-less-test").tempdir().unwrap();
+  std::fs::create_dir(tempdir.path().join("dir")).unwrap();
+  std::fs::create_dir(tempdir.path().join("wal")).unwrap();
+
+  check_collect_garbage(open_block_store("memory://", "node1").unwrap(), true);
+  check_collect_garbage(
+    Arc::new(super::local_dir::LocalDirBlockStore::new(tempdir.path().join("dir"), "node1").unwrap()),
+    true,
+  );
+  // Append-only
+  check_collect_garbage(
+    Arc::new(super::local_wal::LocalWalBlockStore::new(tempdir.path().join("wal"), "node1").unwrap()),
+    false,
+  );
+  #[cfg(feature = "sled")]
+  check_collect_garbage(
+    Arc::new(super::sled::SledBlockStore::new(tempdir.path().join("sled"), "node1").unwrap()),
+    true,
+  );
+}
+
+#[cfg(feature = "sled")]
+#[test]
+fn test_sled_store_flush() {
+  let tempdir = Builder::new().prefix("t-rust-less-test").tempdir().unwrap();
+  let db_path = tempdir.path().join("db");
+  let mut rng = thread_rng();
+  let raw = (&mut rng)
+    .sample_iter(distributions::Standard)
+    .take(256)
+    .collect::<Vec<u8>>();
+
+  let block_id = {
+    let store = super::sled::SledBlockStore::new(&db_path, "node1").unwrap();
+    let block_id = store.add_block(&raw).unwrap();
+
+    store
+      .commit(&generate_commit_id(), &[Change::new(Operation::Add, &block_id)])
+      .unwrap();
+    store.store_ring("ring1", 0, &[1u8; 64]).unwrap();
+    assert_that(&store.flush()).is_ok();
+
+    block_id
+  };
+
+  let store = super::sled::SledBlockStore::new(&db_path, "node1").unwrap();
+
+  assert_that(&store.change_logs().unwrap()[0].changes).is_equal_to(vec![Change::new(Operation::Add, &block_id)]);
+  assert_that(&store.get_block(&block_id).unwrap().as_ref()).is_equal_to(raw.as_slice());
+  assert_that(&store.get_ring("ring1").unwrap().0).is_equal_to(0);
+}
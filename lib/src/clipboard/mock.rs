@@ -0,0 +1,195 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use zeroize::Zeroizing;
+
+use super::selection_provider_holder::SelectionProviderHolder;
+use super::state::ClipboardState;
+use super::{
+  ClipboardCommon, ClipboardError, ClipboardEvent, ClipboardEventSource, ClipboardResult, ClipboardSink,
+  PreviousContent, SelectionProvider,
+};
+use crate::api::{ClipboardProviding, EventData, EventHub};
+
+/// Everything that happened to a `MockSink`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SinkOperation {
+  Provide(String),
+  Restore(String),
+  Close,
+}
+
+/// Sink without any connection to a display server, it just records all operations.
+#[derive(Default)]
+pub struct MockSink {
+  operations: Mutex<Vec<SinkOperation>>,
+}
+
+impl ClipboardSink for MockSink {
+  fn provide(&self, value: &str) -> ClipboardResult<()> {
+    self.operations.lock()?.push(SinkOperation::Provide(value.to_string()));
+    Ok(())
+  }
+
+  fn restore(&self, previous: &str) -> ClipboardResult<()> {
+    self
+      .operations
+      .lock()?
+      .push(SinkOperation::Restore(previous.to_string()));
+    Ok(())
+  }
+
+  fn close(&self) {
+    self.operations.lock().unwrap().push(SinkOperation::Close);
+  }
+}
+
+/// Event source with simulated events, all replies are recorded.
+#[derive(Default)]
+pub struct MockEventSource {
+  pending: VecDeque<ClipboardEvent>,
+  replies: Vec<Option<String>>,
+}
+
+impl ClipboardEventSource for MockEventSource {
+  /// Next simulated event, `None` if there are none left.
+  fn next_event(&mut self) -> ClipboardResult<Option<ClipboardEvent>> {
+    Ok(self.pending.pop_front())
+  }
+
+  fn reply(&mut self, value: Option<&str>) {
+    self.replies.push(value.map(str::to_string));
+  }
+}
+
+/// Clipboard backend without any connection to a display server.
+///
+/// Time is simulated (see `advance`) and the events of other applications are simulated via `paste`
+/// and `take_over`, which are dispatched like the events of the X11 or Wayland event loop.
+/// The content of the clipboard before (i.e. of other applications) can be set via `with_previous`.
+pub struct MockClipboard {
+  now: Mutex<SystemTime>,
+  state: ClipboardState,
+  sink: Arc<MockSink>,
+  events: Mutex<MockEventSource>,
+}
+
+impl MockClipboard {
+  pub fn with_previous<T>(
+    selection_provider: T,
+    previous: &str,
+    restore_previous: bool,
+    timeout: Option<Duration>,
+    event_hub: Arc<dyn EventHub>,
+  ) -> ClipboardResult<Self>
+  where
+    T: SelectionProvider + Clone + 'static,
+  {
+    match selection_provider.current_selection() {
+      Some(providing) => event_hub.send(EventData::ClipboardProviding(providing)),
+      None => return Err(ClipboardError::Other("Empty provider".to_string())),
+    };
+
+    let now = SystemTime::UNIX_EPOCH;
+    let previous = if restore_previous {
+      PreviousContent::from_bytes(previous.as_bytes().to_vec())
+    } else {
+      None
+    };
+    let sink = Arc::new(MockSink::default());
+    let state = ClipboardState::new(
+      SelectionProviderHolder::new_at(selection_provider, previous, timeout, event_hub.clone(), now),
+      sink.clone(),
+      event_hub,
+    );
+    state.provide_current();
+
+    Ok(MockClipboard {
+      now: Mutex::new(now),
+      state,
+      sink,
+      events: Mutex::new(MockEventSource::default()),
+    })
+  }
+
+  /// Advance the simulated time, the clipboard is destroyed if the timeout elapsed in the meantime
+  /// (like the timeout watch of a real backend would).
+  pub fn advance(&self, duration: Duration) {
+    let now = {
+      let mut now = self.now.lock().unwrap();
+      *now += duration;
+      *now
+    };
+    if self.state.is_open() {
+      self.state.check_timeout_at(now);
+    }
+  }
+
+  /// Simulate another application pasting the content of the clipboard.
+  pub fn paste(&self) -> Option<Zeroizing<String>> {
+    let mut events = self.events.lock().unwrap();
+    events.pending.push_back(ClipboardEvent::Paste);
+    self.dispatch(&mut events);
+    events.replies.pop().flatten().map(Zeroizing::new)
+  }
+
+  /// Simulate another application taking over the clipboard.
+  pub fn take_over(&self) {
+    let mut events = self.events.lock().unwrap();
+    events.pending.push_back(ClipboardEvent::TakenOver);
+    self.dispatch(&mut events);
+  }
+
+  /// All operations on the sink so far.
+  pub fn sink_operations(&self) -> Vec<SinkOperation> {
+    self.sink.operations.lock().unwrap().clone()
+  }
+
+  /// Dispatch all pending events, this is what `ClipboardState::run` does for a real event source
+  /// (except that running out of events does not mean the clipboard is closed).
+  fn dispatch(&self, events: &mut MockEventSource) {
+    let now = *self.now.lock().unwrap();
+    while let Ok(Some(event)) = events.next_event() {
+      if !self.state.handle_event_at(events, event, now) {
+        self.state.finish();
+        break;
+      }
+    }
+  }
+}
+
+impl ClipboardCommon for MockClipboard {
+  fn new<T>(
+    selection_provider: T,
+    restore_previous: bool,
+    timeout: Option<Duration>,
+    event_hub: Arc<dyn EventHub>,
+  ) -> ClipboardResult<Self>
+  where
+    T: SelectionProvider + Clone + 'static,
+  {
+    Self::with_previous(selection_provider, "", restore_previous, timeout, event_hub)
+  }
+
+  fn destroy(&self) {
+    self.state.destroy()
+  }
+
+  fn is_open(&self) -> bool {
+    self.state.is_open()
+  }
+
+  fn currently_providing(&self) -> Option<ClipboardProviding> {
+    self.state.currently_providing()
+  }
+
+  fn provide_next(&self) {
+    let now = *self.now.lock().unwrap();
+    self.state.provide_next_at(now);
+  }
+
+  fn wait(&self) -> ClipboardResult<()> {
+    Ok(())
+  }
+}
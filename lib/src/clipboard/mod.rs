@@ -1,7 +1,11 @@
 mod error;
+#[cfg(test)]
+mod mock;
+#[cfg(test)]
+mod tests;
 #[cfg(all(unix, feature = "with_x11", feature = "with_wayland"))]
 mod unix_mixed;
-#[cfg(all(unix, not(any(feature = "with_x11", feature = "with_wayland"))))]
+#[cfg(any(all(unix, not(any(feature = "with_x11", feature = "with_wayland"))), test))]
 mod unix_none;
 #[cfg(all(unix, feature = "with_wayland"))]
 pub mod unix_wayland;
@@ -12,10 +16,13 @@ mod windows;
 
 use zeroize::Zeroizing;
 
-#[cfg(not(windows))]
+#[cfg(any(all(unix, any(feature = "with_x11", feature = "with_wayland")), windows, test))]
 mod selection_provider_holder;
+#[cfg(any(all(unix, any(feature = "with_x11", feature = "with_wayland")), windows, test))]
+mod state;
 
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::api::{ClipboardProviding, EventHub};
 
@@ -31,6 +38,38 @@ pub use self::unix_x11::Clipboard;
 #[cfg(windows)]
 pub use self::windows::Clipboard;
 
+/// Maximum size of the previous clipboard content that will be restored.
+pub const MAX_PREVIOUS_CONTENT_SIZE: usize = 64 * 1024;
+
+/// Mime type (resp. X11 target) marking the content of the clipboard as secret. This is a convention
+/// of KDE that is respected by most clipboard managers (i.e. the content does not end up in their history).
+/// The previous content is never restored if it is marked like this (e.g. provided by another instance).
+pub const PASSWORD_MANAGER_HINT: &str = "x-kde-passwordManagerHint";
+pub const PASSWORD_MANAGER_HINT_SECRET: &[u8] = b"secret";
+/// Mime type marking the content of the clipboard as sensitive. Some (non KDE) clipboard managers only
+/// look for this one, its mere presence is enough to skip the history.
+pub const SENSITIVE_MIME: &str = "application/x-sensitive";
+
+/// Content of the clipboard before it was taken over to provide secrets.
+///
+/// This is whatever the user copied before, so it is deliberately not treated like a secret (i.e.
+/// never reported as `ClipboardProviding`). Only reasonably sized plain text is kept.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PreviousContent(String);
+
+impl PreviousContent {
+  pub fn from_bytes(raw: Vec<u8>) -> Option<PreviousContent> {
+    if raw.is_empty() || raw.len() > MAX_PREVIOUS_CONTENT_SIZE || raw.contains(&0) {
+      return None;
+    }
+    String::from_utf8(raw).ok().map(PreviousContent)
+  }
+
+  pub fn as_str(&self) -> &str {
+    &self.0
+  }
+}
+
 pub trait SelectionProvider: Send + Sync {
   fn current_selection(&self) -> Option<ClipboardProviding>;
 
@@ -39,8 +78,54 @@ pub trait SelectionProvider: Send + Sync {
   fn next_selection(&mut self);
 }
 
+/// Platform side of a clipboard, i.e. where a `ClipboardState` puts its selections.
+pub trait ClipboardSink: Send + Sync {
+  /// Offer `value` to other applications.
+  ///
+  /// Backends that are asked for the content whenever it is pasted (X11, Wayland) do not have to do
+  /// anything here, the others (Windows) have to write it to the clipboard right away.
+  fn provide(&self, value: &str) -> ClipboardResult<()>;
+
+  /// Stop offering secrets and hand back the `previous` content of the clipboard.
+  fn restore(&self, previous: &str) -> ClipboardResult<()>;
+
+  /// Clear the clipboard, the event source of the backend (if there is one) is supposed to end afterwards.
+  fn close(&self);
+}
+
+/// Event of the platform clipboard that is relevant for a `ClipboardState`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClipboardEvent {
+  /// Another application requests the content of the clipboard (i.e. it is pasted).
+  /// This has to be answered via `ClipboardEventSource::reply`.
+  Paste,
+  /// Another application took over the clipboard.
+  TakenOver,
+}
+
+/// Source of the events of the platform clipboard, usually the event loop of a backend.
+///
+/// Backends without such events (Windows) are only driven by `ClipboardCommon::provide_next`.
+pub trait ClipboardEventSource {
+  /// Wait for the next event, `None` once the clipboard has been closed (see `ClipboardSink::close`).
+  fn next_event(&mut self) -> ClipboardResult<Option<ClipboardEvent>>;
+
+  /// Answer the last `ClipboardEvent::Paste`, `None` if there is nothing to paste.
+  fn reply(&mut self, value: Option<&str>);
+}
+
 pub trait ClipboardCommon: Sized {
-  fn new<T>(selection_provider: T, event_hub: Arc<dyn EventHub>) -> ClipboardResult<Self>
+  /// Create a clipboard providing all selections of `selection_provider`.
+  /// If `restore_previous` is set the current content of the clipboard is restored once all
+  /// selections are provided or the clipboard is destroyed.
+  /// If there is a `timeout` the clipboard is destroyed once no further selection has been provided
+  /// for that long, even if nobody pasted the current one (a `ClipboardTimedOut` event is sent).
+  fn new<T>(
+    selection_provider: T,
+    restore_previous: bool,
+    timeout: Option<Duration>,
+    event_hub: Arc<dyn EventHub>,
+  ) -> ClipboardResult<Self>
   where
     T: SelectionProvider + Clone + 'static;
 
@@ -1,34 +1,126 @@
-use crate::{api::ClipboardProviding, clipboard::SelectionProvider};
-use std::time::SystemTime;
+use crate::api::{ClipboardProviding, EventData, EventHub};
+use crate::clipboard::{PreviousContent, SelectionProvider};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 use zeroize::{Zeroize, Zeroizing};
 
+/// Time after creation during which every request is answered with an empty value.
+/// Some clipboard managers grab the content as soon as it is offered, which should not count
+/// as a paste by the user.
+const INITIAL_GRACE_PERIOD: Duration = Duration::from_millis(200);
+/// Time during which repeated requests are answered with the same value. Applications tend
+/// to request the selection several times (e.g. for different targets) for a single paste.
+const REPEAT_PERIOD: Duration = Duration::from_millis(200);
+/// Interval in which the clipboard backends check if the timeout has elapsed.
+pub const TIMEOUT_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Platform independent state of a clipboard providing a sequence of selections.
+///
+/// The clipboard backends do not use this directly but via `ClipboardState`, which calls
+/// `get_value` whenever some other application requests the content of the clipboard, every paste
+/// is reported as a `ClipboardPasted` event. All timing is relative to explicit
+/// points in time (see `new_at` and `get_value_at`) so that the behaviour can be tested
+/// deterministically.
+///
+/// If there is a `previous` content, the holder switches to restoring it once all selections
+/// are provided (or on `restore`). The clipboard is considered done at this point, i.e. a
+/// `ClipboardDone` event is sent, but the backend should continue serving `get_value`.
+///
+/// With a `timeout` the holder expires if no further selection has been provided for that long
+/// (see `check_timeout_at`), the clipboard is then expected to be destroyed.
 pub struct SelectionProviderHolder {
   provider: Box<dyn SelectionProvider>,
   initialized: SystemTime,
+  timeout: Option<Duration>,
+  last_provided: SystemTime,
+  timed_out: bool,
   last_moved: Option<SystemTime>,
   last_content: Option<Zeroizing<String>>,
+  previous: Option<PreviousContent>,
+  restoring: bool,
+  paste_count: u32,
+  event_hub: Arc<dyn EventHub>,
 }
 
 impl SelectionProviderHolder {
-  pub fn new<T>(provider: T) -> Self
+  pub fn new<T>(
+    provider: T,
+    previous: Option<PreviousContent>,
+    timeout: Option<Duration>,
+    event_hub: Arc<dyn EventHub>,
+  ) -> Self
+  where
+    T: SelectionProvider + 'static,
+  {
+    Self::new_at(provider, previous, timeout, event_hub, SystemTime::now())
+  }
+
+  pub fn new_at<T>(
+    provider: T,
+    previous: Option<PreviousContent>,
+    timeout: Option<Duration>,
+    event_hub: Arc<dyn EventHub>,
+    initialized: SystemTime,
+  ) -> Self
   where
     T: SelectionProvider + 'static,
   {
     SelectionProviderHolder {
       provider: Box::new(provider),
-      initialized: SystemTime::now(),
+      initialized,
+      timeout,
+      last_provided: initialized,
+      timed_out: false,
       last_moved: None,
       last_content: None,
+      previous,
+      restoring: false,
+      paste_count: 0,
+      event_hub,
     }
   }
 
-  pub fn get_value(&mut self) -> Option<Zeroizing<String>> {
-    let now = SystemTime::now();
+  pub fn get_value_at(&mut self, now: SystemTime) -> Option<Zeroizing<String>> {
+    self.next_value_at(now, true)
+  }
+
+  /// Skip the current selection without it being pasted.
+  pub fn provide_next_at(&mut self, now: SystemTime) {
+    self.last_provided = now;
+    self.next_value_at(now, false);
+  }
+
+  /// Check if the timeout has elapsed since the last selection was provided.
+  /// A `ClipboardTimedOut` event is sent (once) and `true` returned if the clipboard should be destroyed.
+  pub fn check_timeout_at(&mut self, now: SystemTime) -> bool {
+    if self.timed_out || self.restoring {
+      return false;
+    }
+    if self
+      .timeout
+      .zip(now.duration_since(self.last_provided).ok())
+      .filter(|(timeout, elapsed)| elapsed >= timeout)
+      .is_none()
+    {
+      return false;
+    }
+    self.timed_out = true;
+    if let Some(providing) = self.provider.current_selection() {
+      self.event_hub.send(EventData::ClipboardTimedOut {
+        store_name: providing.store_name.clone(),
+      });
+    }
+    true
+  }
 
+  fn next_value_at(&mut self, now: SystemTime, pasted: bool) -> Option<Zeroizing<String>> {
+    if self.restoring {
+      return self.previous_value();
+    }
     if now
       .duration_since(self.initialized)
       .ok()
-      .filter(|elapsed| elapsed.as_millis() < 200)
+      .filter(|elapsed| *elapsed < INITIAL_GRACE_PERIOD)
       .is_some()
     {
       return Some("".to_string().into());
@@ -37,20 +129,78 @@ impl SelectionProviderHolder {
     if self
       .last_moved
       .and_then(|last| now.duration_since(last).ok())
-      .filter(|elapsed| elapsed.as_millis() < 200)
+      .filter(|elapsed| *elapsed < REPEAT_PERIOD)
       .is_none()
     {
+      let providing = self.provider.current_selection();
       self.last_content = self.provider.get_selection_value();
       self.last_moved.replace(now);
       self.provider.next_selection();
+      if let Some(providing) = providing.filter(|_| pasted && self.last_content.is_some()) {
+        self.paste_count += 1;
+        self.event_hub.send(EventData::ClipboardPasted {
+          store_name: providing.store_name.clone(),
+          property: providing.property.clone(),
+          paste_count: self.paste_count,
+        });
+      }
+    }
+
+    if self.last_content.is_none() && self.restore() {
+      return self.previous_value();
     }
 
     self.last_content.clone()
   }
 
+  /// Value of the current selection (resp. the previous content while restoring) without it being pasted.
+  pub fn current_value(&self) -> Option<Zeroizing<String>> {
+    if self.restoring {
+      return self.previous_value();
+    }
+    self.provider.get_selection_value()
+  }
+
   pub fn current_selection(&self) -> Option<ClipboardProviding> {
+    if self.restoring {
+      return None;
+    }
     self.provider.current_selection()
   }
+
+  /// Stop providing selections and switch to restoring the previous content (if there is any).
+  pub fn restore(&mut self) -> bool {
+    if self.previous.is_none() {
+      return false;
+    }
+    if !self.restoring {
+      self.restoring = true;
+      self.last_content.zeroize();
+      self.last_content = None;
+      while self.provider.current_selection().is_some() {
+        self.provider.next_selection();
+      }
+      self.event_hub.send(EventData::ClipboardDone);
+    }
+    true
+  }
+
+  pub fn is_restoring(&self) -> bool {
+    self.restoring
+  }
+
+  /// Drop the previous content as soon as it is not needed anymore (e.g. another application
+  /// took over the clipboard).
+  pub fn forget_previous(&mut self) {
+    self.previous = None;
+  }
+
+  fn previous_value(&self) -> Option<Zeroizing<String>> {
+    self
+      .previous
+      .as_ref()
+      .map(|previous| Zeroizing::new(previous.as_str().to_string()))
+  }
 }
 
 impl Drop for SelectionProviderHolder {
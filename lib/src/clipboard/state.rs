@@ -0,0 +1,228 @@
+use crate::api::{ClipboardProviding, EventData, EventHub};
+use crate::clipboard::selection_provider_holder::{SelectionProviderHolder, TIMEOUT_CHECK_INTERVAL};
+use crate::clipboard::{ClipboardEvent, ClipboardEventSource, ClipboardSink};
+use log::{debug, error};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::SystemTime;
+use zeroize::Zeroizing;
+
+/// Platform independent part of a clipboard shared by all backends.
+///
+/// A backend consists of a `ClipboardSink` and (optionally) a `ClipboardEventSource` that is
+/// consumed by `run`. The `SelectionProviderHolder` decides what is provided, this connects it to
+/// the sink and takes care of the life cycle of the clipboard (i.e. restoring the previous content,
+/// timeout and the `ClipboardDone` event).
+pub struct ClipboardState {
+  open: AtomicBool,
+  restore_notified: AtomicBool,
+  provider_holder: RwLock<SelectionProviderHolder>,
+  sink: Arc<dyn ClipboardSink>,
+  event_hub: Arc<dyn EventHub>,
+}
+
+impl ClipboardState {
+  pub fn new(
+    provider_holder: SelectionProviderHolder,
+    sink: Arc<dyn ClipboardSink>,
+    event_hub: Arc<dyn EventHub>,
+  ) -> Self {
+    ClipboardState {
+      open: AtomicBool::new(true),
+      restore_notified: AtomicBool::new(false),
+      provider_holder: RwLock::new(provider_holder),
+      sink,
+      event_hub,
+    }
+  }
+
+  /// Open as long as secrets are provided (i.e. `false` while restoring the previous content).
+  pub fn is_open(&self) -> bool {
+    self.open.load(Ordering::Relaxed) && !self.is_restoring()
+  }
+
+  pub fn is_restoring(&self) -> bool {
+    self
+      .provider_holder
+      .read()
+      .map(|provider_holder| provider_holder.is_restoring())
+      .unwrap_or_default()
+  }
+
+  pub fn currently_providing(&self) -> Option<ClipboardProviding> {
+    self.provider_holder.read().ok()?.current_selection()
+  }
+
+  /// Hand the current selection to the sink, the clipboard is destroyed if there is none.
+  pub fn provide_current(&self) {
+    let value = match self.provider_holder.read() {
+      Ok(provider_holder) if !provider_holder.is_restoring() => provider_holder.current_value(),
+      Ok(_) => return,
+      Err(err) => {
+        error!("Unable to lock provider {}", err);
+        None
+      }
+    };
+    match value {
+      Some(value) => {
+        if let Err(err) = self.sink.provide(&value) {
+          error!("Providing selection failed: {}", err);
+        }
+      }
+      None => self.destroy(),
+    }
+  }
+
+  pub fn provide_next(&self) {
+    self.provide_next_at(SystemTime::now())
+  }
+
+  /// Skip the current selection without it being pasted.
+  pub fn provide_next_at(&self, now: SystemTime) {
+    if let Ok(mut provider_holder) = self.provider_holder.write() {
+      provider_holder.provide_next_at(now);
+      self.notify_restoring(&provider_holder);
+    }
+    self.provide_current();
+  }
+
+  /// Value for another application pasting the content of the clipboard.
+  /// The clipboard is destroyed once there is nothing left to paste.
+  pub fn paste_at(&self, now: SystemTime) -> Option<Zeroizing<String>> {
+    if !self.open.load(Ordering::Relaxed) {
+      return None;
+    }
+    let value = match self.provider_holder.write() {
+      Ok(mut provider_holder) => {
+        let value = provider_holder.get_value_at(now);
+        self.notify_restoring(&provider_holder);
+        value
+      }
+      Err(err) => {
+        error!("Unable to lock provider {}", err);
+        None
+      }
+    };
+    if value.is_none() {
+      debug!("No more values");
+      self.destroy();
+    }
+    value
+  }
+
+  pub fn check_timeout(&self) -> bool {
+    self.check_timeout_at(SystemTime::now())
+  }
+
+  /// Destroy the clipboard if the timeout has elapsed, returns `true` if this happened.
+  pub fn check_timeout_at(&self, now: SystemTime) -> bool {
+    let timed_out = match self.provider_holder.write() {
+      Ok(mut provider_holder) => provider_holder.check_timeout_at(now),
+      Err(_) => true,
+    };
+    if timed_out {
+      debug!("Clipboard timed out");
+      self.destroy();
+    }
+    timed_out
+  }
+
+  /// Destroy the clipboard, unless the previous content should be restored.
+  pub fn destroy(&self) {
+    let restoring = match self.provider_holder.write() {
+      Ok(mut provider_holder) => {
+        let restoring = provider_holder.restore();
+        self.notify_restoring(&provider_holder);
+        restoring
+      }
+      Err(_) => false,
+    };
+    if !restoring {
+      self.close();
+    }
+  }
+
+  /// Close the clipboard for good (this includes the previous content that might be restored).
+  pub fn close(&self) {
+    if !self.open.swap(false, Ordering::Relaxed) {
+      return;
+    }
+    self.sink.close();
+    // While restoring the `ClipboardDone` has already been sent
+    if !self.is_restoring() {
+      self.event_hub.send(EventData::ClipboardDone);
+    }
+  }
+
+  /// Handle a single event of an event source, returns `false` if there will be no further events.
+  pub fn handle_event_at<S>(&self, source: &mut S, event: ClipboardEvent, now: SystemTime) -> bool
+  where
+    S: ClipboardEventSource + ?Sized,
+  {
+    match event {
+      ClipboardEvent::Paste => {
+        let value = self.paste_at(now);
+        source.reply(value.as_ref().map(|value| value.as_str()));
+        true
+      }
+      ClipboardEvent::TakenOver => {
+        debug!("Lost ownership");
+        false
+      }
+    }
+  }
+
+  /// Handle all events of `source` until the clipboard is closed or taken over by another application.
+  pub fn run<S>(&self, source: &mut S)
+  where
+    S: ClipboardEventSource + ?Sized,
+  {
+    loop {
+      match source.next_event() {
+        Ok(Some(event)) => {
+          if !self.handle_event_at(source, event, SystemTime::now()) {
+            break;
+          }
+        }
+        Ok(None) => break,
+        Err(err) => {
+          error!("Clipboard event error: {}", err);
+          break;
+        }
+      }
+    }
+    self.finish();
+  }
+
+  /// There will be no further events, i.e. the previous content cannot be restored anymore.
+  pub fn finish(&self) {
+    debug!("Ending clipboard");
+    if let Ok(mut provider_holder) = self.provider_holder.write() {
+      provider_holder.forget_previous();
+    }
+    self.close();
+  }
+
+  /// Tell the sink once the holder has switched to restoring the previous content.
+  fn notify_restoring(&self, provider_holder: &SelectionProviderHolder) {
+    if !provider_holder.is_restoring() || self.restore_notified.swap(true, Ordering::Relaxed) {
+      return;
+    }
+    if let Some(previous) = provider_holder.current_value() {
+      if let Err(err) = self.sink.restore(&previous) {
+        error!("Restoring previous content failed: {}", err);
+      }
+    }
+  }
+}
+
+/// Destroy the clipboard once the timeout has elapsed (unless it is closed or restoring before).
+pub fn watch_timeout(state: Arc<ClipboardState>) {
+  while state.is_open() {
+    thread::sleep(TIMEOUT_CHECK_INTERVAL);
+    if state.check_timeout() {
+      break;
+    }
+  }
+}
@@ -0,0 +1,434 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use spectral::prelude::*;
+use zeroize::Zeroizing;
+
+use super::mock::{MockClipboard, SinkOperation};
+use super::{ClipboardCommon, PreviousContent, SelectionProvider, MAX_PREVIOUS_CONTENT_SIZE};
+use crate::api::{ClipboardProviding, EventData, EventHub};
+
+#[derive(Clone)]
+struct TestProvider {
+  stack: Vec<(&'static str, &'static str)>,
+}
+
+impl TestProvider {
+  fn new(properties: &[(&'static str, &'static str)]) -> Self {
+    TestProvider {
+      stack: properties.iter().rev().cloned().collect(),
+    }
+  }
+}
+
+impl SelectionProvider for TestProvider {
+  fn current_selection(&self) -> Option<ClipboardProviding> {
+    self.stack.last().map(|(property, _)| ClipboardProviding {
+      store_name: "store".to_string(),
+      block_id: "block".to_string(),
+      secret_name: "secret".to_string(),
+      property: property.to_string(),
+    })
+  }
+
+  fn get_selection_value(&self) -> Option<Zeroizing<String>> {
+    self.stack.last().map(|(_, value)| Zeroizing::new(value.to_string()))
+  }
+
+  fn next_selection(&mut self) {
+    self.stack.pop();
+  }
+}
+
+#[derive(Default)]
+struct TestEventHub {
+  events: Mutex<Vec<EventData>>,
+}
+
+impl TestEventHub {
+  fn clipboard_done_count(&self) -> usize {
+    let events = self.events.lock().unwrap();
+    events.iter().filter(|e| matches!(e, EventData::ClipboardDone)).count()
+  }
+
+  fn timed_out_count(&self) -> usize {
+    let events = self.events.lock().unwrap();
+    events
+      .iter()
+      .filter(|e| matches!(e, EventData::ClipboardTimedOut { .. }))
+      .count()
+  }
+
+  fn pasted(&self) -> Vec<(String, String, u32)> {
+    let events = self.events.lock().unwrap();
+    events
+      .iter()
+      .filter_map(|e| match e {
+        EventData::ClipboardPasted {
+          store_name,
+          property,
+          paste_count,
+        } => Some((store_name.clone(), property.clone(), *paste_count)),
+        _ => None,
+      })
+      .collect()
+  }
+}
+
+impl EventHub for TestEventHub {
+  fn send(&self, event: EventData) {
+    self.events.lock().unwrap().push(event);
+  }
+}
+
+fn current_property(clipboard: &MockClipboard) -> Option<String> {
+  clipboard
+    .currently_providing()
+    .map(|providing| providing.property.clone())
+}
+
+fn paste(clipboard: &MockClipboard) -> Option<String> {
+  clipboard.paste().map(|value| value.to_string())
+}
+
+#[test]
+fn test_paste_sequence() {
+  let event_hub = Arc::new(TestEventHub::default());
+  let clipboard = MockClipboard::new(
+    TestProvider::new(&[("username", "user"), ("password", "secret")]),
+    false,
+    None,
+    event_hub.clone(),
+  )
+  .unwrap();
+
+  assert_that(&clipboard.is_open()).is_true();
+  assert_that(&current_property(&clipboard)).contains_value("username".to_string());
+
+  // Requests right after offering the content are ignored
+  assert_that(&paste(&clipboard)).contains_value("".to_string());
+  assert_that(&current_property(&clipboard)).contains_value("username".to_string());
+
+  clipboard.advance(Duration::from_millis(300));
+  assert_that(&paste(&clipboard)).contains_value("user".to_string());
+  assert_that(&current_property(&clipboard)).contains_value("password".to_string());
+
+  // Repeated requests of a single paste get the same value
+  clipboard.advance(Duration::from_millis(50));
+  assert_that(&paste(&clipboard)).contains_value("user".to_string());
+
+  clipboard.advance(Duration::from_millis(300));
+  assert_that(&paste(&clipboard)).contains_value("secret".to_string());
+  assert_that(&current_property(&clipboard)).is_none();
+  assert_that(&clipboard.is_open()).is_true();
+
+  clipboard.advance(Duration::from_millis(300));
+  assert_that(&paste(&clipboard)).is_none();
+  assert_that(&clipboard.is_open()).is_false();
+  assert_that(&event_hub.clipboard_done_count()).is_equal_to(1);
+}
+
+#[test]
+fn test_provide_next_and_destroy() {
+  let event_hub = Arc::new(TestEventHub::default());
+  let clipboard = MockClipboard::new(
+    TestProvider::new(&[("username", "user"), ("password", "secret"), ("totpUrl", "otp")]),
+    false,
+    None,
+    event_hub.clone(),
+  )
+  .unwrap();
+
+  // Skipping is ignored during the initial grace period as well
+  clipboard.provide_next();
+  assert_that(&current_property(&clipboard)).contains_value("username".to_string());
+
+  clipboard.advance(Duration::from_millis(300));
+  clipboard.provide_next();
+  assert_that(&current_property(&clipboard)).contains_value("password".to_string());
+
+  clipboard.advance(Duration::from_millis(300));
+  assert_that(&paste(&clipboard)).contains_value("secret".to_string());
+  assert_that(&current_property(&clipboard)).contains_value("totpUrl".to_string());
+
+  clipboard.destroy();
+  clipboard.destroy();
+  assert_that(&clipboard.is_open()).is_false();
+  assert_that(&paste(&clipboard)).is_none();
+  assert_that(&event_hub.clipboard_done_count()).is_equal_to(1);
+}
+
+#[test]
+fn test_paste_events() {
+  let event_hub = Arc::new(TestEventHub::default());
+  let clipboard = MockClipboard::new(
+    TestProvider::new(&[("username", "user-1"), ("password", "s3cr3t"), ("totpUrl", "123456")]),
+    false,
+    None,
+    event_hub.clone(),
+  )
+  .unwrap();
+
+  // Requests of clipboard managers right after offering the content are no pastes
+  assert_that(&paste(&clipboard)).contains_value("".to_string());
+  assert_that(&event_hub.pasted()).is_empty();
+
+  clipboard.advance(Duration::from_millis(300));
+  assert_that(&paste(&clipboard)).contains_value("user-1".to_string());
+  clipboard.advance(Duration::from_millis(50));
+  assert_that(&paste(&clipboard)).contains_value("user-1".to_string());
+  assert_that(&event_hub.pasted()).is_equal_to(vec![("store".to_string(), "username".to_string(), 1)]);
+
+  // Skipped selections are not pasted
+  clipboard.advance(Duration::from_millis(300));
+  clipboard.provide_next();
+  assert_that(&event_hub.pasted()).has_length(1);
+
+  clipboard.advance(Duration::from_millis(300));
+  assert_that(&paste(&clipboard)).contains_value("123456".to_string());
+  assert_that(&event_hub.pasted()).is_equal_to(vec![
+    ("store".to_string(), "username".to_string(), 1),
+    ("store".to_string(), "totpUrl".to_string(), 2),
+  ]);
+
+  clipboard.advance(Duration::from_millis(300));
+  assert_that(&paste(&clipboard)).is_none();
+  assert_that(&event_hub.pasted()).has_length(2);
+
+  let events = format!("{:?}", event_hub.events.lock().unwrap());
+  for value in ["user-1", "s3cr3t", "123456"] {
+    assert_that(&events.contains(value)).is_false();
+  }
+}
+
+#[test]
+fn test_empty_provider() {
+  let event_hub = Arc::new(TestEventHub::default());
+
+  assert_that(&MockClipboard::new(TestProvider::new(&[]), false, None, event_hub).is_err()).is_true();
+}
+
+#[test]
+fn test_restore_previous_after_last_paste() {
+  let event_hub = Arc::new(TestEventHub::default());
+  let clipboard = MockClipboard::with_previous(
+    TestProvider::new(&[("username", "user"), ("password", "secret")]),
+    "something copied before",
+    true,
+    None,
+    event_hub.clone(),
+  )
+  .unwrap();
+
+  clipboard.advance(Duration::from_millis(300));
+  assert_that(&paste(&clipboard)).contains_value("user".to_string());
+  clipboard.advance(Duration::from_millis(300));
+  assert_that(&paste(&clipboard)).contains_value("secret".to_string());
+  assert_that(&clipboard.is_open()).is_true();
+  assert_that(&event_hub.clipboard_done_count()).is_equal_to(0);
+
+  clipboard.advance(Duration::from_millis(300));
+  assert_that(&paste(&clipboard)).contains_value("something copied before".to_string());
+  assert_that(&clipboard.is_open()).is_false();
+  assert_that(&current_property(&clipboard)).is_none();
+  assert_that(&event_hub.clipboard_done_count()).is_equal_to(1);
+
+  // Previous content is provided until some other application takes over
+  clipboard.advance(Duration::from_millis(300));
+  assert_that(&paste(&clipboard)).contains_value("something copied before".to_string());
+  clipboard.destroy();
+  assert_that(&paste(&clipboard)).contains_value("something copied before".to_string());
+  assert_that(&event_hub.clipboard_done_count()).is_equal_to(1);
+
+  clipboard.take_over();
+  assert_that(&paste(&clipboard)).is_none();
+  assert_that(&event_hub.clipboard_done_count()).is_equal_to(1);
+}
+
+#[test]
+fn test_restore_previous_on_destroy() {
+  let event_hub = Arc::new(TestEventHub::default());
+  let clipboard = MockClipboard::with_previous(
+    TestProvider::new(&[("username", "user"), ("password", "secret")]),
+    "something copied before",
+    true,
+    None,
+    event_hub.clone(),
+  )
+  .unwrap();
+
+  clipboard.advance(Duration::from_millis(300));
+  assert_that(&paste(&clipboard)).contains_value("user".to_string());
+
+  clipboard.destroy();
+  assert_that(&clipboard.is_open()).is_false();
+  assert_that(&current_property(&clipboard)).is_none();
+  assert_that(&event_hub.clipboard_done_count()).is_equal_to(1);
+
+  // The secret is never provided again, not even during the repeat period
+  assert_that(&paste(&clipboard)).contains_value("something copied before".to_string());
+}
+
+#[test]
+fn test_restore_disabled_or_unsuitable() {
+  let event_hub = Arc::new(TestEventHub::default());
+  let clipboard = MockClipboard::with_previous(
+    TestProvider::new(&[("password", "secret")]),
+    "something copied before",
+    false,
+    None,
+    event_hub.clone(),
+  )
+  .unwrap();
+
+  clipboard.destroy();
+  assert_that(&clipboard.is_open()).is_false();
+  assert_that(&paste(&clipboard)).is_none();
+
+  assert_that(&PreviousContent::from_bytes(b"text".to_vec())).is_some();
+  assert_that(&PreviousContent::from_bytes(vec![])).is_none();
+  assert_that(&PreviousContent::from_bytes(vec![0x89, b'P', b'N', b'G', 0, 0])).is_none();
+  assert_that(&PreviousContent::from_bytes(vec![0xff, 0xfe, 0xfd])).is_none();
+  assert_that(&PreviousContent::from_bytes(vec![b'a'; MAX_PREVIOUS_CONTENT_SIZE + 1])).is_none();
+}
+
+#[test]
+fn test_timeout() {
+  let event_hub = Arc::new(TestEventHub::default());
+  let clipboard = MockClipboard::new(
+    TestProvider::new(&[("username", "user"), ("password", "secret"), ("totpUrl", "otp")]),
+    false,
+    Some(Duration::from_secs(45)),
+    event_hub.clone(),
+  )
+  .unwrap();
+
+  // Pastes do not reset the timeout, skipping to the next selection does
+  clipboard.advance(Duration::from_secs(30));
+  assert_that(&paste(&clipboard)).contains_value("user".to_string());
+  clipboard.advance(Duration::from_secs(10));
+  clipboard.provide_next();
+  assert_that(&current_property(&clipboard)).contains_value("totpUrl".to_string());
+  clipboard.advance(Duration::from_secs(44));
+  assert_that(&clipboard.is_open()).is_true();
+  assert_that(&event_hub.timed_out_count()).is_equal_to(0);
+
+  clipboard.advance(Duration::from_secs(1));
+  assert_that(&clipboard.is_open()).is_false();
+  assert_that(&paste(&clipboard)).is_none();
+  assert_that(&event_hub.timed_out_count()).is_equal_to(1);
+  assert_that(&event_hub.clipboard_done_count()).is_equal_to(1);
+
+  clipboard.advance(Duration::from_secs(60));
+  assert_that(&event_hub.timed_out_count()).is_equal_to(1);
+}
+
+#[test]
+fn test_timeout_restores_previous() {
+  let event_hub = Arc::new(TestEventHub::default());
+  let clipboard = MockClipboard::with_previous(
+    TestProvider::new(&[("password", "secret")]),
+    "something copied before",
+    true,
+    Some(Duration::from_secs(45)),
+    event_hub.clone(),
+  )
+  .unwrap();
+
+  clipboard.advance(Duration::from_secs(45));
+  assert_that(&clipboard.is_open()).is_false();
+  assert_that(&paste(&clipboard)).contains_value("something copied before".to_string());
+  assert_that(&event_hub.timed_out_count()).is_equal_to(1);
+  assert_that(&event_hub.clipboard_done_count()).is_equal_to(1);
+}
+
+#[test]
+fn test_sink_operations() {
+  let event_hub = Arc::new(TestEventHub::default());
+  let clipboard = MockClipboard::with_previous(
+    TestProvider::new(&[("username", "user"), ("password", "secret")]),
+    "something copied before",
+    true,
+    None,
+    event_hub.clone(),
+  )
+  .unwrap();
+
+  // Push based backends (like Windows) get every selection as it is provided
+  assert_that(&clipboard.sink_operations()).is_equal_to(vec![SinkOperation::Provide("user".to_string())]);
+  clipboard.advance(Duration::from_millis(300));
+  clipboard.provide_next();
+  assert_that(&clipboard.sink_operations()).is_equal_to(vec![
+    SinkOperation::Provide("user".to_string()),
+    SinkOperation::Provide("secret".to_string()),
+  ]);
+
+  clipboard.advance(Duration::from_millis(300));
+  clipboard.provide_next();
+  assert_that(&clipboard.sink_operations()).is_equal_to(vec![
+    SinkOperation::Provide("user".to_string()),
+    SinkOperation::Provide("secret".to_string()),
+    SinkOperation::Restore("something copied before".to_string()),
+  ]);
+  assert_that(&clipboard.is_open()).is_false();
+  assert_that(&event_hub.clipboard_done_count()).is_equal_to(1);
+
+  clipboard.take_over();
+  assert_that(&clipboard.sink_operations().last()).is_equal_to(Some(&SinkOperation::Close));
+  assert_that(&event_hub.clipboard_done_count()).is_equal_to(1);
+}
+
+#[test]
+fn test_sink_closed_after_last_paste() {
+  let event_hub = Arc::new(TestEventHub::default());
+  let clipboard = MockClipboard::new(
+    TestProvider::new(&[("password", "secret")]),
+    false,
+    None,
+    event_hub.clone(),
+  )
+  .unwrap();
+
+  clipboard.advance(Duration::from_millis(300));
+  assert_that(&paste(&clipboard)).contains_value("secret".to_string());
+  clipboard.advance(Duration::from_millis(300));
+  assert_that(&paste(&clipboard)).is_none();
+  assert_that(&clipboard.sink_operations())
+    .is_equal_to(vec![SinkOperation::Provide("secret".to_string()), SinkOperation::Close]);
+
+  // Closing is idempotent
+  clipboard.destroy();
+  clipboard.take_over();
+  assert_that(&clipboard.sink_operations()).has_length(2);
+  assert_that(&event_hub.clipboard_done_count()).is_equal_to(1);
+}
+
+#[cfg(all(unix, feature = "with_wayland"))]
+#[test]
+fn test_wayland_offers_plain_text_first() {
+  let offered: Vec<&str> = super::unix_wayland::offered_mime_types().collect();
+
+  assert_that(&offered[0]).is_equal_to("text/plain;charset=utf-8");
+  assert_that(&offered).contains("text/plain");
+  assert_that(&offered).contains(super::PASSWORD_MANAGER_HINT);
+  assert_that(&offered).contains(super::SENSITIVE_MIME);
+}
+
+#[test]
+fn test_unix_none_is_safe() {
+  let event_hub = Arc::new(TestEventHub::default());
+  let result = super::unix_none::Clipboard::new(TestProvider::new(&[("password", "secret")]), true, None, event_hub);
+
+  assert_that(&matches!(result, Err(super::ClipboardError::Unavailable))).is_true();
+  assert_that(&crate::service::ServiceError::from(super::ClipboardError::Unavailable))
+    .is_equal_to(crate::service::ServiceError::ClipboardUnavailable);
+
+  let clipboard = super::unix_none::Clipboard {};
+
+  assert_that(&clipboard.is_open()).is_false();
+  assert_that(&clipboard.currently_providing()).is_none();
+  clipboard.provide_next();
+  assert_that(&clipboard.wait().is_ok()).is_true();
+  clipboard.destroy();
+  assert_that(&clipboard.is_open()).is_false();
+}
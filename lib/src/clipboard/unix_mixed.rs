@@ -1,4 +1,5 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use log::info;
 
@@ -12,15 +13,20 @@ pub enum Clipboard {
 }
 
 impl ClipboardCommon for Clipboard {
-  fn new<T>(selection_provider: T, event_hub: Arc<dyn EventHub>) -> ClipboardResult<Self>
+  fn new<T>(
+    selection_provider: T,
+    restore_previous: bool,
+    timeout: Option<Duration>,
+    event_hub: Arc<dyn EventHub>,
+  ) -> ClipboardResult<Self>
   where
     T: SelectionProvider + Clone + 'static,
   {
-    match unix_wayland::Clipboard::new(selection_provider.clone(), event_hub.clone()) {
+    match unix_wayland::Clipboard::new(selection_provider.clone(), restore_previous, timeout, event_hub.clone()) {
       Ok(wayland) => Ok(Clipboard::Wayland(wayland)),
       Err(ClipboardError::Unavailable) => {
         info!("Wayland unavailable, fallback to x11");
-        unix_x11::Clipboard::new(selection_provider, event_hub).map(Clipboard::X11)
+        unix_x11::Clipboard::new(selection_provider, restore_previous, timeout, event_hub).map(Clipboard::X11)
       }
       Err(err) => Err(err),
     }
@@ -1,14 +1,25 @@
 use super::{ClipboardCommon, ClipboardError, ClipboardResult, SelectionProvider};
 use crate::api::{ClipboardProviding, EventHub};
+use log::warn;
 use std::sync::Arc;
+use std::time::Duration;
 
+/// Fallback if neither X11 nor Wayland support is compiled in (e.g. a headless server).
+///
+/// A clipboard can never be created, all operations on an existing one are (harmless) no-ops.
 pub struct Clipboard {}
 
 impl ClipboardCommon for Clipboard {
-  fn new<T>(_selection_provider: T, _event_hub: Arc<dyn EventHub>) -> ClipboardResult<Self>
+  fn new<T>(
+    _selection_provider: T,
+    _restore_previous: bool,
+    _timeout: Option<Duration>,
+    _event_hub: Arc<dyn EventHub>,
+  ) -> ClipboardResult<Self>
   where
     T: SelectionProvider + 'static,
   {
+    warn!("No clipboard support (compiled without X11 and Wayland)");
     Err(ClipboardError::Unavailable)
   }
 
@@ -20,11 +31,14 @@ impl ClipboardCommon for Clipboard {
     None
   }
 
-  fn provide_next(&self) {}
+  fn provide_next(&self) {
+    warn!("No clipboard support: Nothing to provide");
+  }
 
   fn destroy(&self) {}
 
   fn wait(&self) -> ClipboardResult<()> {
+    warn!("No clipboard support: Nothing to wait for");
     Ok(())
   }
 }
@@ -1,38 +1,45 @@
 use std::{
-  collections::HashMap,
-  error::Error,
+  collections::{HashMap, VecDeque},
   fs::File,
-  io::Write,
-  os::{fd::AsRawFd, unix::io::FromRawFd},
+  io::{Read, Write},
+  os::{
+    fd::{AsFd, AsRawFd, OwnedFd},
+    unix::io::FromRawFd,
+  },
   sync::{
     atomic::{AtomicBool, Ordering},
-    Arc, Mutex, RwLock,
+    Arc, Mutex,
   },
   thread,
+  time::{Duration, Instant},
 };
 
+use crate::api::{ClipboardProviding, EventData, EventHub};
+use crate::clipboard::selection_provider_holder::SelectionProviderHolder;
+use crate::clipboard::state::{watch_timeout, ClipboardState};
 use log::{debug, error};
 use wayland_client::{
   event_created_child,
   globals::{registry_queue_init, BindError, GlobalListContents},
   protocol::{
+    wl_callback::{self, WlCallback},
     wl_registry::WlRegistry,
     wl_seat::{self, WlSeat},
   },
-  Connection, Dispatch, EventQueue, Proxy,
+  Connection, Dispatch, EventQueue, Proxy, QueueHandle,
 };
 use wayland_protocols_wlr::data_control::v1::client::{
   zwlr_data_control_device_v1::{self, ZwlrDataControlDeviceV1},
   zwlr_data_control_manager_v1::ZwlrDataControlManagerV1,
-  zwlr_data_control_offer_v1::ZwlrDataControlOfferV1,
+  zwlr_data_control_offer_v1::{self, ZwlrDataControlOfferV1},
   zwlr_data_control_source_v1::{self, ZwlrDataControlSourceV1},
 };
-use zeroize::Zeroize;
-
-use crate::api::{ClipboardProviding, EventData, EventHub};
-use crate::clipboard::selection_provider_holder::SelectionProviderHolder;
 
-use super::{ClipboardCommon, ClipboardError, ClipboardResult, SelectionProvider};
+use super::{
+  ClipboardCommon, ClipboardError, ClipboardEvent, ClipboardEventSource, ClipboardResult, ClipboardSink,
+  PreviousContent, SelectionProvider, MAX_PREVIOUS_CONTENT_SIZE, PASSWORD_MANAGER_HINT, PASSWORD_MANAGER_HINT_SECRET,
+  SENSITIVE_MIME,
+};
 
 const TEXT_MIMES: &[&str] = &[
   "text/plain;charset=utf-8",
@@ -42,47 +49,83 @@ const TEXT_MIMES: &[&str] = &[
   "TEXT",
 ];
 
+/// Mime types hinting clipboard managers that the content is a secret.
+const SECRET_HINT_MIMES: &[&str] = &[PASSWORD_MANAGER_HINT, SENSITIVE_MIME];
+
+/// Maximum time to wait for the current owner of the clipboard to hand over its content.
+const READ_PREVIOUS_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Connection to the compositor, this is the sink of the clipboard state.
 struct Context {
-  open: AtomicBool,
   cancel: AtomicBool,
-  provider_holder: RwLock<SelectionProviderHolder>,
+  conn: Connection,
+  qh: QueueHandle<State>,
 }
 
 impl Context {
-  fn new<T>(provider: T) -> Self
-  where
-    T: SelectionProvider + 'static,
-  {
-    Context {
-      open: AtomicBool::new(false),
-      cancel: AtomicBool::new(false),
-      provider_holder: RwLock::new(SelectionProviderHolder::new(provider)),
+  /// Request a roundtrip from the compositor so that a blocking dispatch of the event loop returns.
+  fn wake_up(&self) {
+    self.conn.display().sync(&self.qh, ());
+    if let Err(err) = self.conn.flush() {
+      debug!("Wake up failed: {}", err);
     }
   }
+}
 
-  fn is_open(&self) -> bool {
-    self.open.load(Ordering::Relaxed)
-  }
-
-  fn currently_providing(&self) -> Option<ClipboardProviding> {
-    self.provider_holder.read().ok()?.current_selection()
+impl ClipboardSink for Context {
+  /// The content is requested on every paste (see `Events`).
+  fn provide(&self, _value: &str) -> ClipboardResult<()> {
+    Ok(())
   }
 
-  fn provide_next(&self) {
-    if let Ok(mut provider_holder) = self.provider_holder.write() {
-      provider_holder.get_value();
-    }
+  fn restore(&self, _previous: &str) -> ClipboardResult<()> {
+    Ok(())
   }
 
-  fn destroy(&self) {
-    self.cancel.store(true, Ordering::Relaxed)
+  fn close(&self) {
+    self.cancel.store(true, Ordering::Relaxed);
+    self.wake_up();
   }
 }
 
 struct State {
-  context: Arc<Context>,
+  clipboard: Option<Arc<ClipboardState>>,
+  /// Paste requests (i.e. where to write the content to) that have not been answered yet
+  pending_pastes: VecDeque<OwnedFd>,
+  lost_ownership: bool,
   clipboard_manager: ZwlrDataControlManagerV1,
   seats: HashMap<WlSeat, SeatData>,
+  /// Offers (with their mime types) are only tracked until the previous content has been read
+  track_offers: bool,
+  offers: HashMap<ZwlrDataControlOfferV1, Vec<String>>,
+  selection: Option<ZwlrDataControlOfferV1>,
+}
+
+impl State {
+  fn is_restoring(&self) -> bool {
+    self
+      .clipboard
+      .as_ref()
+      .map(|clipboard| clipboard.is_restoring())
+      .unwrap_or_default()
+  }
+
+  fn set_selection_offer(&mut self, offer: Option<ZwlrDataControlOfferV1>) {
+    for (old_offer, _) in self.offers.drain() {
+      if Some(&old_offer) != offer.as_ref() {
+        old_offer.destroy();
+      }
+    }
+    if let Some(offer) = &offer {
+      self.offers.insert(offer.clone(), vec![]);
+    }
+    self.selection = offer;
+  }
+
+  fn stop_tracking_offers(&mut self) {
+    self.track_offers = false;
+    self.set_selection_offer(None);
+  }
 }
 
 impl Dispatch<WlRegistry, GlobalListContents> for State {
@@ -97,6 +140,18 @@ impl Dispatch<WlRegistry, GlobalListContents> for State {
   }
 }
 
+impl Dispatch<WlCallback, ()> for State {
+  fn event(
+    _state: &mut Self,
+    _proxy: &WlCallback,
+    _event: wl_callback::Event,
+    _data: &(),
+    _conn: &Connection,
+    _qhandle: &wayland_client::QueueHandle<Self>,
+  ) {
+  }
+}
+
 impl Dispatch<WlSeat, ()> for State {
   fn event(
     _state: &mut Self,
@@ -134,28 +189,19 @@ impl Dispatch<ZwlrDataControlSourceV1, ()> for State {
     _qhandle: &wayland_client::QueueHandle<Self>,
   ) {
     match _event {
+      // The previous content (while restoring) is not a secret
+      zwlr_data_control_source_v1::Event::Send { mime_type, fd }
+        if SECRET_HINT_MIMES.contains(&mime_type.as_str()) && !_state.is_restoring() =>
+      {
+        File::from(fd).write_all(PASSWORD_MANAGER_HINT_SECRET).ok();
+      }
       zwlr_data_control_source_v1::Event::Send { mime_type, fd } if TEXT_MIMES.contains(&mime_type.as_str()) => {
         debug!("Event send: {} {:?}", mime_type, fd);
-        match _state.context.provider_holder.write() {
-          Ok(mut selection_provider) => {
-            if let Some(mut content) = selection_provider.get_value() {
-              let mut f = unsafe { File::from_raw_fd(fd.as_raw_fd()) };
-              f.write_all(content.as_bytes()).ok();
-              content.zeroize();
-            } else {
-              debug!("No more values");
-              _state.context.cancel.store(true, Ordering::Relaxed);
-            }
-          }
-          Err(err) => {
-            error!("Lock error: {}", err);
-            _state.context.cancel.store(true, Ordering::Relaxed);
-          }
-        }
+        _state.pending_pastes.push_back(fd);
       }
       zwlr_data_control_source_v1::Event::Cancelled => {
         debug!("Event cancel: Lost ownership");
-        _state.context.cancel.store(true, Ordering::Relaxed);
+        _state.lost_ownership = true;
       }
       _ => (),
     }
@@ -172,7 +218,22 @@ impl Dispatch<ZwlrDataControlDeviceV1, WlSeat> for State {
     _qhandle: &wayland_client::QueueHandle<Self>,
   ) {
     match event {
+      zwlr_data_control_device_v1::Event::DataOffer { id } if state.track_offers => {
+        state.offers.insert(id, vec![]);
+      }
       zwlr_data_control_device_v1::Event::DataOffer { id } => id.destroy(),
+      zwlr_data_control_device_v1::Event::Selection { id } if state.track_offers => {
+        let mime_types = id.as_ref().and_then(|id| state.offers.get(id)).cloned();
+        state.set_selection_offer(id.clone());
+        if let (Some(id), Some(mime_types)) = (id, mime_types) {
+          state.offers.insert(id, mime_types);
+        }
+      }
+      zwlr_data_control_device_v1::Event::PrimarySelection { id: Some(id) }
+        if state.selection.as_ref() != Some(&id) && state.offers.remove(&id).is_some() =>
+      {
+        id.destroy();
+      }
       zwlr_data_control_device_v1::Event::Finished => {
         state.seats.get_mut(seat).unwrap().set_device(None);
       }
@@ -187,23 +248,69 @@ impl Dispatch<ZwlrDataControlDeviceV1, WlSeat> for State {
 
 impl Dispatch<ZwlrDataControlOfferV1, ()> for State {
   fn event(
-    _state: &mut Self,
-    _offer: &ZwlrDataControlOfferV1,
-    _event: <ZwlrDataControlOfferV1 as wayland_client::Proxy>::Event,
+    state: &mut Self,
+    offer: &ZwlrDataControlOfferV1,
+    event: <ZwlrDataControlOfferV1 as wayland_client::Proxy>::Event,
     _data: &(),
     _conn: &wayland_client::Connection,
     _qhandle: &wayland_client::QueueHandle<Self>,
   ) {
+    if let zwlr_data_control_offer_v1::Event::Offer { mime_type } = event {
+      if let Some(mime_types) = state.offers.get_mut(offer) {
+        mime_types.push(mime_type);
+      }
+    }
   }
 }
 
-pub struct Clipboard {
+/// Requests of the compositor, paste requests are answered once the `ClipboardState` has handled them.
+struct Events {
+  queue: EventQueue<State>,
+  state: State,
   context: Arc<Context>,
+  current: Option<OwnedFd>,
+}
+
+impl ClipboardEventSource for Events {
+  fn next_event(&mut self) -> ClipboardResult<Option<ClipboardEvent>> {
+    loop {
+      if let Some(fd) = self.state.pending_pastes.pop_front() {
+        self.current = Some(fd);
+        return Ok(Some(ClipboardEvent::Paste));
+      }
+      if self.state.lost_ownership {
+        return Ok(Some(ClipboardEvent::TakenOver));
+      }
+      if self.context.cancel.load(Ordering::Relaxed) {
+        return Ok(None);
+      }
+      self
+        .queue
+        .blocking_dispatch(&mut self.state)
+        .map_err(|err| ClipboardError::Other(format!("{}", err)))?;
+    }
+  }
+
+  fn reply(&mut self, value: Option<&str>) {
+    // Without a value the request is just closed
+    if let (Some(fd), Some(value)) = (self.current.take(), value) {
+      File::from(fd).write_all(value.as_bytes()).ok();
+    }
+  }
+}
+
+pub struct Clipboard {
+  state: Arc<ClipboardState>,
   handle: Mutex<Option<thread::JoinHandle<()>>>,
 }
 
 impl ClipboardCommon for Clipboard {
-  fn new<T>(selection_provider: T, event_hub: Arc<dyn EventHub>) -> ClipboardResult<Self>
+  fn new<T>(
+    selection_provider: T,
+    restore_previous: bool,
+    timeout: Option<Duration>,
+    event_hub: Arc<dyn EventHub>,
+  ) -> ClipboardResult<Self>
   where
     T: SelectionProvider + Clone + 'static,
   {
@@ -231,45 +338,79 @@ impl ClipboardCommon for Clipboard {
       None => return Err(ClipboardError::Other("Empty provider".to_string())),
     };
 
-    let context = Arc::new(Context::new(selection_provider));
     let mut state = State {
-      context: context.clone(),
+      clipboard: None,
+      pending_pastes: VecDeque::new(),
+      lost_ownership: false,
       clipboard_manager,
       seats,
+      track_offers: restore_previous,
+      offers: HashMap::new(),
+      selection: None,
     };
 
+    for (seat, data) in &mut state.seats {
+      let device = state.clipboard_manager.get_data_device(seat, qh, seat.clone());
+      data.set_device(Some(device));
+    }
+
     queue.roundtrip(&mut state)?;
 
+    let previous = if restore_previous {
+      read_previous(&conn, &mut queue, &mut state)
+    } else {
+      None
+    };
+    state.stop_tracking_offers();
+
+    let context = Arc::new(Context {
+      cancel: AtomicBool::new(false),
+      conn: conn.clone(),
+      qh: qh.clone(),
+    });
+    let clipboard = Arc::new(ClipboardState::new(
+      SelectionProviderHolder::new(selection_provider, previous, timeout, event_hub.clone()),
+      context.clone(),
+      event_hub,
+    ));
+    state.clipboard = Some(clipboard.clone());
+
     let handle = thread::spawn({
-      let cloned = context.clone();
+      let cloned = clipboard.clone();
       move || {
-        if let Err(err) = try_run(queue, state) {
-          cloned.open.store(false, Ordering::Relaxed);
-          error!("Wayland clipboard error: {}", err);
-        }
+        run(
+          &cloned,
+          Events {
+            queue,
+            state,
+            context,
+            current: None,
+          },
+          timeout,
+        )
       }
     });
 
     Ok(Clipboard {
-      context,
+      state: clipboard,
       handle: Mutex::new(Some(handle)),
     })
   }
 
   fn is_open(&self) -> bool {
-    self.context.is_open()
+    self.state.is_open()
   }
 
   fn currently_providing(&self) -> Option<ClipboardProviding> {
-    self.context.currently_providing()
+    self.state.currently_providing()
   }
 
   fn provide_next(&self) {
-    self.context.provide_next()
+    self.state.provide_next()
   }
 
   fn destroy(&self) {
-    self.context.destroy()
+    self.state.destroy()
   }
 
   fn wait(&self) -> ClipboardResult<()> {
@@ -283,37 +424,113 @@ impl ClipboardCommon for Clipboard {
   }
 }
 
-fn try_run(mut queue: EventQueue<State>, mut state: State) -> Result<(), Box<dyn Error>> {
-  let data_source = state.clipboard_manager.create_data_source(&queue.handle(), ());
+fn run(clipboard: &Arc<ClipboardState>, mut events: Events, timeout: Option<Duration>) {
+  let data_source = events
+    .state
+    .clipboard_manager
+    .create_data_source(&events.queue.handle(), ());
 
-  debug!("Seats: {:?}", &state.seats);
+  debug!("Seats: {:?}", &events.state.seats);
 
-  for &mime_type in TEXT_MIMES {
+  for mime_type in offered_mime_types() {
     data_source.offer(mime_type.to_string());
   }
 
-  for (seat, data) in &mut state.seats {
-    let device = state
-      .clipboard_manager
-      .get_data_device(seat, &queue.handle(), seat.clone());
-    device.set_selection(Some(&data_source));
-    data.set_device(Some(device));
+  for data in events.state.seats.values() {
+    if let Some(device) = &data.device {
+      device.set_selection(Some(&data_source));
+    }
   }
 
   debug!("Start event loop");
-  state.context.open.store(true, Ordering::Relaxed);
-  while !state.context.cancel.load(Ordering::Relaxed) {
-    queue.blocking_dispatch(&mut state)?;
+  if timeout.is_some() {
+    thread::spawn({
+      let cloned = clipboard.clone();
+      move || watch_timeout(cloned)
+    });
   }
-  state.context.open.store(false, Ordering::Relaxed);
+  clipboard.run(&mut events);
   debug!("End event loop");
 
-  for data in state.seats.values_mut() {
+  for data in events.state.seats.values_mut() {
     data.set_device(None);
   }
   data_source.destroy();
+  if let Err(err) = events.queue.flush() {
+    error!("Wayland clipboard error: {}", err);
+  }
+}
+
+/// All mime types offered by the data source: The plain text ones first (so that a regular paste, e.g.
+/// `wl-paste`, still gets the text) followed by the hints for clipboard managers.
+pub(crate) fn offered_mime_types() -> impl Iterator<Item = &'static str> {
+  TEXT_MIMES.iter().chain(SECRET_HINT_MIMES.iter()).copied()
+}
 
-  Ok(())
+/// Read the current (text) content of the clipboard before taking ownership.
+fn read_previous(conn: &Connection, queue: &mut EventQueue<State>, state: &mut State) -> Option<PreviousContent> {
+  let offer = state.selection.clone()?;
+  let mime_types = state.offers.get(&offer).cloned().unwrap_or_default();
+  if mime_types.iter().any(|m| m == SENSITIVE_MIME)
+    || (mime_types.iter().any(|m| m == PASSWORD_MANAGER_HINT)
+      && receive_offer(conn, queue, state, &offer, PASSWORD_MANAGER_HINT).as_deref()
+        == Some(PASSWORD_MANAGER_HINT_SECRET))
+  {
+    debug!("Previous clipboard content is a secret");
+    return None;
+  }
+  let mime_type = TEXT_MIMES
+    .iter()
+    .find(|mime_type| mime_types.iter().any(|m| m == *mime_type))?;
+
+  PreviousContent::from_bytes(receive_offer(conn, queue, state, &offer, mime_type)?)
+}
+
+/// Receive the content of an offer in the format of `mime_type` (with a timeout).
+fn receive_offer(
+  conn: &Connection,
+  queue: &mut EventQueue<State>,
+  state: &mut State,
+  offer: &ZwlrDataControlOfferV1,
+  mime_type: &str,
+) -> Option<Vec<u8>> {
+  let (read_fd, write_fd) = create_pipe().ok()?;
+  offer.receive(mime_type.to_string(), write_fd.as_fd());
+  drop(write_fd);
+  conn.flush().ok()?;
+  queue.dispatch_pending(state).ok()?;
+
+  let mut file = File::from(read_fd);
+  let mut content = Vec::new();
+  let mut buffer = [0u8; 4096];
+  let started = Instant::now();
+  loop {
+    let mut poll_fd = libc::pollfd {
+      fd: file.as_raw_fd(),
+      events: libc::POLLIN,
+      revents: 0,
+    };
+    let remaining = READ_PREVIOUS_TIMEOUT.checked_sub(started.elapsed())?;
+    if unsafe { libc::poll(&mut poll_fd, 1, remaining.as_millis() as i32) } <= 0 {
+      debug!("Timeout reading previous clipboard content");
+      return None;
+    }
+    match file.read(&mut buffer) {
+      Ok(0) => break,
+      Ok(n) if content.len() + n <= MAX_PREVIOUS_CONTENT_SIZE => content.extend_from_slice(&buffer[..n]),
+      _ => return None,
+    }
+  }
+
+  Some(content)
+}
+
+fn create_pipe() -> std::io::Result<(OwnedFd, OwnedFd)> {
+  let mut fds = [0; 2];
+  if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } != 0 {
+    return Err(std::io::Error::last_os_error());
+  }
+  unsafe { Ok((OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1]))) }
 }
 
 #[derive(Default, Debug)]
@@ -1,14 +1,17 @@
 use crate::api::{ClipboardProviding, EventData, EventHub};
 use crate::clipboard::selection_provider_holder::SelectionProviderHolder;
-use crate::clipboard::{ClipboardError, ClipboardResult, SelectionProvider};
-use log::{debug, error};
+use crate::clipboard::state::{watch_timeout, ClipboardState};
+use crate::clipboard::{
+  ClipboardError, ClipboardEvent, ClipboardEventSource, ClipboardResult, ClipboardSink, PreviousContent,
+  SelectionProvider, MAX_PREVIOUS_CONTENT_SIZE, PASSWORD_MANAGER_HINT, PASSWORD_MANAGER_HINT_SECRET,
+};
+use log::debug;
 use std::ffi::CString;
 use std::mem::MaybeUninit;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, Mutex, RwLock};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use std::{env, thread};
 use x11::xlib;
-use zeroize::Zeroize;
 
 use super::ClipboardCommon;
 
@@ -19,22 +22,23 @@ struct Atoms {
   pub targets: xlib::Atom,
   pub string: xlib::Atom,
   pub utf8_string: xlib::Atom,
+  pub password_manager_hint: xlib::Atom,
+  pub previous: xlib::Atom,
 }
 
+/// Maximum time to wait for the current owner of the clipboard to hand over its content.
+const READ_PREVIOUS_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Connection to the X server, this is the sink of the clipboard state.
 struct Context {
   display: *mut xlib::Display,
   window: xlib::Window,
   atoms: Atoms,
-  open: AtomicBool,
-  provider_holder: RwLock<SelectionProviderHolder>,
-  event_hub: Arc<dyn EventHub>,
 }
 
 impl Context {
-  fn new<T>(event_hub: Arc<dyn EventHub>, provider: T) -> ClipboardResult<Self>
-  where
-    T: SelectionProvider + 'static,
-  {
+  /// Open the connection, the previous content of the clipboard is read if `restore_previous` is set.
+  fn new(restore_previous: bool) -> ClipboardResult<(Self, Option<PreviousContent>)> {
     unsafe {
       let display_name = env::var("DISPLAY")?;
       let c_display_name = CString::new(display_name)?;
@@ -62,6 +66,8 @@ impl Context {
         debug!("XA_STRING is not named STRING");
       }
       let utf8_string = Self::get_atom(display, "UTF8_STRING");
+      let password_manager_hint = Self::get_atom(display, PASSWORD_MANAGER_HINT);
+      let previous = Self::get_atom(display, "T_RUST_LESS_PREVIOUS");
 
       let atoms = Atoms {
         primary,
@@ -69,18 +75,19 @@ impl Context {
         targets,
         string,
         utf8_string,
+        password_manager_hint,
+        previous,
       };
 
       debug!("{:?}", atoms);
 
-      Ok(Context {
-        display,
-        window,
-        atoms,
-        open: AtomicBool::new(true),
-        provider_holder: RwLock::new(SelectionProviderHolder::new(provider)),
-        event_hub,
-      })
+      let previous = if restore_previous {
+        Self::read_previous(display, window, &atoms)
+      } else {
+        None
+      };
+
+      Ok((Context { display, window, atoms }, previous))
     }
   }
 
@@ -91,23 +98,132 @@ impl Context {
     }
   }
 
-  fn destroy(&self) {
-    if self.open.swap(false, Ordering::Relaxed) {
-      unsafe {
-        xlib::XDestroyWindow(self.display, self.window);
+  /// Read the current (text) content of the clipboard before taking ownership.
+  /// Content marked as secret (e.g. provided by another instance) is never restored.
+  fn read_previous(display: *mut xlib::Display, window: xlib::Window, atoms: &Atoms) -> Option<PreviousContent> {
+    unsafe {
+      if xlib::XGetSelectionOwner(display, atoms.clipboard) == 0 {
+        return None;
+      }
+      if let Some((actual_type, 32, data)) = Self::convert_clipboard(display, window, atoms, atoms.targets) {
+        let is_secret = data
+          .chunks_exact(std::mem::size_of::<xlib::Atom>())
+          .map(|chunk| xlib::Atom::from_ne_bytes(chunk.try_into().unwrap()))
+          .any(|target| target == atoms.password_manager_hint);
+        if actual_type == xlib::XA_ATOM && is_secret {
+          debug!("Previous clipboard content is a secret");
+          return None;
+        }
+      }
+      match Self::convert_clipboard(display, window, atoms, atoms.utf8_string)? {
+        (actual_type, 8, data) if actual_type == atoms.utf8_string || actual_type == atoms.string => {
+          PreviousContent::from_bytes(data)
+        }
+        _ => None,
+      }
+    }
+  }
+
+  /// Request the content of the clipboard in the format of `target` (type, format and raw data).
+  fn convert_clipboard(
+    display: *mut xlib::Display,
+    window: xlib::Window,
+    atoms: &Atoms,
+    target: xlib::Atom,
+  ) -> Option<(xlib::Atom, i32, Vec<u8>)> {
+    unsafe {
+      xlib::XConvertSelection(
+        display,
+        atoms.clipboard,
+        target,
+        atoms.previous,
+        window,
+        xlib::CurrentTime,
+      );
+      xlib::XFlush(display);
+
+      let mut event: xlib::XEvent = MaybeUninit::zeroed().assume_init();
+      let started = Instant::now();
+      loop {
+        if xlib::XCheckTypedWindowEvent(display, window, xlib::SelectionNotify, &mut event) != 0 {
+          break;
+        }
+        if started.elapsed() > READ_PREVIOUS_TIMEOUT {
+          debug!("Timeout reading previous clipboard content");
+          return None;
+        }
+        thread::sleep(Duration::from_millis(5));
+      }
+      if event.selection.property == 0 {
+        return None;
+      }
+
+      let mut actual_type: xlib::Atom = 0;
+      let mut actual_format: i32 = 0;
+      let mut nitems: u64 = 0;
+      let mut bytes_after: u64 = 0;
+      let mut data: *mut u8 = std::ptr::null_mut();
+      xlib::XGetWindowProperty(
+        display,
+        window,
+        atoms.previous,
+        0,
+        (MAX_PREVIOUS_CONTENT_SIZE / 4 + 1) as i64,
+        xlib::True,
+        xlib::AnyPropertyType as u64,
+        &mut actual_type,
+        &mut actual_format,
+        &mut nitems,
+        &mut bytes_after,
+        &mut data,
+      );
+      if data.is_null() {
+        return None;
+      }
+      // Items of format 32 are stored as longs (see XGetWindowProperty)
+      let item_size = match actual_format {
+        8 => 1,
+        16 => std::mem::size_of::<i16>(),
+        _ => std::mem::size_of::<std::os::raw::c_long>(),
+      };
+      let content = if bytes_after == 0 {
+        Some((
+          actual_type,
+          actual_format,
+          std::slice::from_raw_parts(data, nitems as usize * item_size).to_vec(),
+        ))
+      } else {
+        None
+      };
+      xlib::XFree(data as *mut _);
+
+      content
+    }
+  }
+
+  fn release_primary(&self) {
+    unsafe {
+      if xlib::XGetSelectionOwner(self.display, self.atoms.primary) == self.window {
+        xlib::XSetSelectionOwner(self.display, self.atoms.primary, 0, xlib::CurrentTime);
         xlib::XFlush(self.display);
       }
     }
   }
 
+  /// Take ownership of CLIPBOARD and PRIMARY (i.e. the secret can be pasted via middle-click as well).
+  /// Only the ownership of CLIPBOARD is mandatory.
   fn own_selection(&self) -> bool {
     unsafe {
-      xlib::XSetSelectionOwner(self.display, self.atoms.clipboard, self.window, xlib::CurrentTime);
-
-      let owner = xlib::XGetSelectionOwner(self.display, self.atoms.clipboard);
-      if owner != self.window {
-        debug!("Failed taking ownership of {}", self.atoms.clipboard);
-        return false;
+      for selection in [self.atoms.primary, self.atoms.clipboard] {
+        xlib::XSetSelectionOwner(self.display, selection, self.window, xlib::CurrentTime);
+
+        let owner = xlib::XGetSelectionOwner(self.display, selection);
+        if owner != self.window {
+          debug!("Failed taking ownership of {}", selection);
+          if selection == self.atoms.clipboard {
+            return false;
+          }
+        }
       }
     }
 
@@ -122,41 +238,191 @@ impl Context {
       xlib::XFlush(self.display);
     }
   }
+}
 
-  fn is_open(&self) -> bool {
-    self.open.load(Ordering::Relaxed)
+impl Drop for Context {
+  fn drop(&mut self) {
+    unsafe {
+      xlib::XCloseDisplay(self.display);
+    }
   }
+}
 
-  fn currently_providing(&self) -> Option<ClipboardProviding> {
-    self.provider_holder.read().ok()?.current_selection()
+unsafe impl Send for Context {}
+
+unsafe impl Sync for Context {}
+
+impl ClipboardSink for Context {
+  /// The content is requested on every paste (see `Events`).
+  fn provide(&self, _value: &str) -> ClipboardResult<()> {
+    Ok(())
   }
 
-  fn provide_next(&self) {
-    if let Ok(mut provider_holder) = self.provider_holder.write() {
-      provider_holder.get_value();
+  /// The previous content is only restored to CLIPBOARD, i.e. PRIMARY is released.
+  fn restore(&self, _previous: &str) -> ClipboardResult<()> {
+    self.release_primary();
+    Ok(())
+  }
+
+  fn close(&self) {
+    unsafe {
+      xlib::XDestroyWindow(self.display, self.window);
+      xlib::XFlush(self.display);
     }
   }
 }
 
-impl Drop for Context {
-  fn drop(&mut self) {
+/// Selection requests of other applications.
+///
+/// Everything but the actual content (i.e. TARGETS and the password manager hint) is answered right away.
+struct Events {
+  context: Arc<Context>,
+  state: Arc<ClipboardState>,
+  pending: Option<xlib::XSelectionEvent>,
+}
+
+impl Events {
+  fn send_selection(&self, selection: xlib::XSelectionEvent) {
     unsafe {
-      xlib::XCloseDisplay(self.display);
+      xlib::XSendEvent(
+        self.context.display,
+        selection.requestor,
+        xlib::False,
+        xlib::NoEventMask,
+        &mut xlib::XEvent { selection } as *mut xlib::XEvent,
+      );
+
+      xlib::XSync(self.context.display, xlib::False);
     }
   }
 }
 
-unsafe impl Send for Context {}
+impl ClipboardEventSource for Events {
+  fn next_event(&mut self) -> ClipboardResult<Option<ClipboardEvent>> {
+    let context = self.context.clone();
+    unsafe {
+      let mut event: xlib::XEvent = MaybeUninit::zeroed().assume_init();
+
+      loop {
+        xlib::XFlush(context.display);
+        debug!("Wating for event");
+        xlib::XNextEvent(context.display, &mut event);
+
+        debug!("Got event: {}", event.get_type());
+
+        match event.get_type() {
+          xlib::SelectionRequest => {
+            let mut selection: xlib::XSelectionEvent = MaybeUninit::zeroed().assume_init();
+            selection.type_ = xlib::SelectionNotify;
+            selection.display = event.selection_request.display;
+            selection.requestor = event.selection_request.requestor;
+            selection.selection = event.selection_request.selection;
+            selection.time = event.selection_request.time;
+            selection.target = event.selection_request.target;
+            selection.property = event.selection_request.property;
+
+            debug!("Selection requestor: {}", selection.requestor);
+            debug!("Selection target: {}", selection.target);
+
+            let restoring = self.state.is_restoring();
+            if selection.selection == context.atoms.primary && restoring {
+              debug!("PRIMARY while restoring: Reply with NONE");
+              selection.property = 0;
+            } else if selection.target == context.atoms.targets {
+              let atoms = [
+                context.atoms.targets,
+                context.atoms.string,
+                context.atoms.utf8_string,
+                context.atoms.password_manager_hint,
+              ];
+              // The previous content (while restoring) is not a secret
+              let atoms = if restoring { &atoms[..3] } else { &atoms[..] };
+              xlib::XChangeProperty(
+                context.display,
+                selection.requestor,
+                selection.property,
+                xlib::XA_ATOM,
+                32,
+                xlib::PropModeReplace,
+                atoms.as_ptr() as *const u8,
+                atoms.len() as i32,
+              );
+            } else if selection.target == context.atoms.password_manager_hint && !restoring {
+              xlib::XChangeProperty(
+                context.display,
+                selection.requestor,
+                selection.property,
+                context.atoms.string,
+                8,
+                xlib::PropModeReplace,
+                PASSWORD_MANAGER_HINT_SECRET.as_ptr(),
+                PASSWORD_MANAGER_HINT_SECRET.len() as i32,
+              );
+            } else if selection.target == context.atoms.string || selection.target == context.atoms.utf8_string {
+              self.pending = Some(selection);
+              return Ok(Some(ClipboardEvent::Paste));
+            } else {
+              debug!("Reply with NONE");
+              selection.property = 0;
+            }
+
+            self.send_selection(selection);
+          }
+          // Selecting some text elsewhere takes over PRIMARY, which should not end the clipboard
+          xlib::SelectionClear if event.selection_clear.selection == context.atoms.primary => {
+            debug!("Lost ownership of PRIMARY");
+          }
+          xlib::SelectionClear => return Ok(Some(ClipboardEvent::TakenOver)),
+          xlib::DestroyNotify => {
+            debug!("Window destroyed");
+            return Ok(None);
+          }
+          ignored => debug!("Ignoring event: {}", ignored),
+        }
+      }
+    }
+  }
 
-unsafe impl Sync for Context {}
+  fn reply(&mut self, value: Option<&str>) {
+    let mut selection = match self.pending.take() {
+      Some(selection) => selection,
+      None => return,
+    };
+    match value {
+      Some(value) => unsafe {
+        xlib::XChangeProperty(
+          self.context.display,
+          selection.requestor,
+          selection.property,
+          selection.target,
+          8,
+          xlib::PropModeReplace,
+          value.as_ptr(),
+          value.len() as i32,
+        );
+      },
+      None => {
+        self.context.clear_selection();
+        debug!("Last part: Reply with NONE");
+        selection.property = 0;
+      }
+    }
+    self.send_selection(selection);
+  }
+}
 
 pub struct Clipboard {
-  context: Arc<Context>,
+  state: Arc<ClipboardState>,
   handle: Mutex<Option<thread::JoinHandle<()>>>,
 }
 
 impl ClipboardCommon for Clipboard {
-  fn new<T>(selection_provider: T, event_hub: Arc<dyn EventHub>) -> ClipboardResult<Self>
+  fn new<T>(
+    selection_provider: T,
+    restore_previous: bool,
+    timeout: Option<Duration>,
+    event_hub: Arc<dyn EventHub>,
+  ) -> ClipboardResult<Self>
   where
     T: SelectionProvider + Clone + 'static,
   {
@@ -165,33 +431,45 @@ impl ClipboardCommon for Clipboard {
       None => return Err(ClipboardError::Other("Empty provider".to_string())),
     };
 
-    let context = Arc::new(Context::new(event_hub, selection_provider)?);
+    let (context, previous) = Context::new(restore_previous)?;
+    let context = Arc::new(context);
+    let state = Arc::new(ClipboardState::new(
+      SelectionProviderHolder::new(selection_provider, previous, timeout, event_hub.clone()),
+      context.clone(),
+      event_hub,
+    ));
 
     let handle = thread::spawn({
-      let cloned = context.clone();
-      move || run(cloned)
+      let cloned = state.clone();
+      move || run(context, cloned)
     });
+    if timeout.is_some() {
+      thread::spawn({
+        let cloned = state.clone();
+        move || watch_timeout(cloned)
+      });
+    }
 
     Ok(Clipboard {
-      context,
+      state,
       handle: Mutex::new(Some(handle)),
     })
   }
 
   fn destroy(&self) {
-    self.context.destroy()
+    self.state.destroy()
   }
 
   fn is_open(&self) -> bool {
-    self.context.is_open()
+    self.state.is_open()
   }
 
   fn currently_providing(&self) -> Option<ClipboardProviding> {
-    self.context.currently_providing()
+    self.state.currently_providing()
   }
 
   fn provide_next(&self) {
-    self.context.provide_next()
+    self.state.provide_next()
   }
 
   fn wait(&self) -> ClipboardResult<()> {
@@ -207,113 +485,18 @@ impl ClipboardCommon for Clipboard {
 
 impl Drop for Clipboard {
   fn drop(&mut self) {
-    self.destroy()
+    self.state.close()
   }
 }
 
-fn run(context: Arc<Context>) {
-  unsafe {
-    if !context.own_selection() {
-      return;
-    }
-
-    let mut event: xlib::XEvent = MaybeUninit::zeroed().assume_init();
-
-    loop {
-      xlib::XFlush(context.display);
-      debug!("Wating for event");
-      xlib::XNextEvent(context.display, &mut event);
-
-      debug!("Got event: {}", event.get_type());
-
-      match event.get_type() {
-        xlib::SelectionRequest => {
-          let mut selection: xlib::XSelectionEvent = MaybeUninit::zeroed().assume_init();
-          selection.type_ = xlib::SelectionNotify;
-          selection.display = event.selection_request.display;
-          selection.requestor = event.selection_request.requestor;
-          selection.selection = event.selection_request.selection;
-          selection.time = event.selection_request.time;
-          selection.target = event.selection_request.target;
-          selection.property = event.selection_request.property;
-
-          debug!("Selection requestor: {}", selection.requestor);
-          debug!("Selection target: {}", selection.target);
-
-          if selection.target == context.atoms.targets {
-            let atoms = [context.atoms.targets, context.atoms.string, context.atoms.utf8_string];
-            xlib::XChangeProperty(
-              context.display,
-              selection.requestor,
-              selection.property,
-              xlib::XA_ATOM,
-              32,
-              xlib::PropModeReplace,
-              &atoms as *const xlib::Atom as *const u8,
-              atoms.len() as i32,
-            );
-          } else if selection.target == context.atoms.string || selection.target == context.atoms.utf8_string {
-            match context.provider_holder.write() {
-              Ok(mut provider_holder) => {
-                match provider_holder.get_value() {
-                  Some(mut value) => {
-                    let content: &[u8] = value.as_ref();
-
-                    xlib::XChangeProperty(
-                      context.display,
-                      selection.requestor,
-                      selection.property,
-                      selection.target,
-                      8,
-                      xlib::PropModeReplace,
-                      content.as_ptr(),
-                      content.len() as i32,
-                    );
-                    value.zeroize();
-                  }
-                  None => {
-                    context.clear_selection();
-                    debug!("Last part: Reply with NONE");
-                    selection.property = 0;
-                  }
-                };
-              }
-              Err(err) => {
-                error!("Unable to lock provider {}", err);
-                context.clear_selection();
-                selection.property = 0;
-              }
-            };
-          } else {
-            debug!("Reply with NONE");
-            selection.property = 0;
-          }
-
-          xlib::XSendEvent(
-            context.display,
-            selection.requestor,
-            xlib::False,
-            xlib::NoEventMask,
-            &mut xlib::XEvent { selection } as *mut xlib::XEvent,
-          );
-
-          xlib::XSync(context.display, xlib::False);
-        }
-        xlib::SelectionClear => {
-          debug!("Lost ownership");
-          break;
-        }
-        xlib::DestroyNotify => {
-          debug!("Window destroyed");
-
-          break;
-        }
-        ignored => debug!("Ignoring event: {}", ignored),
-      }
-    }
-
-    debug!("Ending event loop");
-    context.event_hub.send(EventData::ClipboardDone);
-    context.destroy();
+fn run(context: Arc<Context>, state: Arc<ClipboardState>) {
+  if !context.own_selection() {
+    return;
   }
+
+  state.run(&mut Events {
+    context,
+    state: state.clone(),
+    pending: None,
+  });
 }
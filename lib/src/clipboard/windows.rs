@@ -1,70 +1,90 @@
 use clipboard_win::formats::RawData;
-use log::error;
 
-use super::{ClipboardCommon, ClipboardResult, SelectionProvider};
+use super::selection_provider_holder::SelectionProviderHolder;
+use super::state::{watch_timeout, ClipboardState};
+use super::{ClipboardCommon, ClipboardError, ClipboardResult, ClipboardSink, PreviousContent, SelectionProvider};
 use crate::api::{ClipboardProviding, EventData, EventHub};
-use std::sync::{Arc, RwLock};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
 
-pub struct Clipboard {
-  provider: Arc<RwLock<dyn SelectionProvider>>,
-  event_hub: Arc<dyn EventHub>,
-}
+/// The Windows clipboard has no notion of paste requests, i.e. there is no event source and the
+/// selections are written to the clipboard as they are provided.
+struct Sink;
 
-impl Clipboard {
-  fn fill(&self) {
-    match self.provider.read() {
-      Ok(provider) => {
-        if let (Some(providing), Some(value)) = (provider.current_selection(), provider.get_selection_value()) {
-          match clipboard_win::set_clipboard_string(&value) {
-            Ok(_) => self.event_hub.send(EventData::ClipboardProviding(providing)),
-            Err(err) => error!("Write to win_clipboard failed {}", err),
-          }
-        } else {
-          self.destroy();
-        }
-      }
-      Err(err) => {
-        error!("Unable to lock provider {}", err);
-        self.destroy();
-      }
-    }
+impl ClipboardSink for Sink {
+  fn provide(&self, value: &str) -> ClipboardResult<()> {
+    clipboard_win::set_clipboard_string(value)
+      .map_err(|err| ClipboardError::Other(format!("Write to win_clipboard failed {}", err)))
+  }
+
+  fn restore(&self, previous: &str) -> ClipboardResult<()> {
+    clipboard_win::set_clipboard_string(previous)
+      .map_err(|err| ClipboardError::Other(format!("Write to win_clipboard failed {}", err)))
+  }
+
+  fn close(&self) {
+    clipboard_win::set_clipboard(RawData(0), b" ").ok();
   }
 }
 
+pub struct Clipboard {
+  state: Arc<ClipboardState>,
+}
+
 impl ClipboardCommon for Clipboard {
-  fn new<T>(selection_provider: T, event_hub: Arc<dyn EventHub>) -> ClipboardResult<Clipboard>
+  fn new<T>(
+    selection_provider: T,
+    restore_previous: bool,
+    timeout: Option<Duration>,
+    event_hub: Arc<dyn EventHub>,
+  ) -> ClipboardResult<Clipboard>
   where
-    T: SelectionProvider + 'static,
+    T: SelectionProvider + Clone + 'static,
   {
-    let clipboard = Clipboard {
-      provider: Arc::new(RwLock::new(selection_provider)),
-      event_hub,
+    match selection_provider.current_selection() {
+      Some(providing) => event_hub.send(EventData::ClipboardProviding(providing)),
+      None => return Err(ClipboardError::Other("Empty provider".to_string())),
     };
-    clipboard.fill();
 
-    Ok(clipboard)
+    let previous = if restore_previous {
+      clipboard_win::get_clipboard_string()
+        .ok()
+        .and_then(|previous| PreviousContent::from_bytes(previous.into_bytes()))
+    } else {
+      None
+    };
+    let state = Arc::new(ClipboardState::new(
+      SelectionProviderHolder::new(selection_provider, previous, timeout, event_hub.clone()),
+      Arc::new(Sink),
+      event_hub,
+    ));
+    state.provide_current();
+
+    if timeout.is_some() {
+      thread::spawn({
+        let cloned = state.clone();
+        move || watch_timeout(cloned)
+      });
+    }
+
+    Ok(Clipboard { state })
   }
 
   fn is_open(&self) -> bool {
-    self.currently_providing().is_none()
+    self.state.is_open()
   }
 
   fn currently_providing(&self) -> Option<ClipboardProviding> {
-    self.provider.read().ok()?.current_selection()
+    self.state.currently_providing()
   }
 
   fn provide_next(&self) {
-    match self.provider.write() {
-      Ok(mut provider) => provider.next_selection(),
-      Err(err) => {
-        error!("Unable to lock provider {}", err);
-      }
-    }
-    self.fill();
+    self.state.provide_next()
   }
 
   fn destroy(&self) {
-    clipboard_win::set_clipboard(RawData(0), b" ").ok();
+    self.state.destroy()
   }
 
   fn wait(&self) -> ClipboardResult<()> {
@@ -1,8 +1,12 @@
-use std::alloc::{alloc, dealloc, Layout};
+#[cfg(not(windows))]
+use std::alloc::{alloc, dealloc};
+use std::alloc::{handle_alloc_error, Layout};
 use std::mem;
 use std::ptr::{self, NonNull};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Once;
 
+use log::warn;
 use rand::rngs::OsRng;
 use rand::RngCore;
 
@@ -10,6 +14,7 @@ use super::memory;
 
 const CANARY_SIZE: usize = 16;
 static ALLOC_INIT: Once = Once::new();
+static MLOCK_FAILED: AtomicBool = AtomicBool::new(false);
 static mut PAGE_SIZE: usize = 0;
 static mut PAGE_MASK: usize = 0;
 static mut CANARY: [u8; CANARY_SIZE] = [0; CANARY_SIZE];
@@ -29,7 +34,7 @@ unsafe fn alloc_init() {
     PAGE_SIZE = si.dwPageSize as usize;
   }
 
-  if PAGE_SIZE < CANARY_SIZE || PAGE_SIZE < mem::size_of::<usize>() {
+  if PAGE_SIZE < CANARY_SIZE || PAGE_SIZE < 2 * mem::size_of::<usize>() {
     panic!("OS page to small to operate with")
   }
 
@@ -38,18 +43,54 @@ unsafe fn alloc_init() {
   OsRng.fill_bytes(&mut CANARY);
 }
 
+#[cfg(not(windows))]
 #[inline]
 pub unsafe fn alloc_aligned(size: usize) -> NonNull<u8> {
   let layout = Layout::from_size_align_unchecked(size, PAGE_SIZE);
-  NonNull::new_unchecked(alloc(layout))
+  match NonNull::new(alloc(layout)) {
+    Some(memptr) => memptr,
+    None => handle_alloc_error(layout),
+  }
+}
+
+/// Windows `VirtualAlloc`.
+///
+/// `VirtualProtect` and `VirtualLock` are only well-defined on pages that are owned exclusively,
+/// so the pages are taken directly from the OS instead of the process heap.
+#[cfg(windows)]
+#[inline]
+pub unsafe fn alloc_aligned(size: usize) -> NonNull<u8> {
+  let memptr = winapi::um::memoryapi::VirtualAlloc(
+    ptr::null_mut(),
+    size as winapi::shared::basetsd::SIZE_T,
+    winapi::um::winnt::MEM_COMMIT | winapi::um::winnt::MEM_RESERVE,
+    winapi::um::winnt::PAGE_READWRITE,
+  );
+  // Out of memory is reported like any other failed allocation
+  match NonNull::new(memptr as *mut u8) {
+    Some(memptr) => memptr,
+    None => handle_alloc_error(Layout::from_size_align_unchecked(size, PAGE_SIZE)),
+  }
 }
 
+#[cfg(not(windows))]
 #[inline]
 pub unsafe fn free_aligned(memptr: *mut u8, size: usize) {
   let layout = Layout::from_size_align_unchecked(size, PAGE_SIZE);
   dealloc(memptr, layout);
 }
 
+/// Windows `VirtualFree`.
+#[cfg(windows)]
+#[inline]
+pub unsafe fn free_aligned(memptr: *mut u8, _size: usize) {
+  winapi::um::memoryapi::VirtualFree(
+    memptr as winapi::shared::minwindef::LPVOID,
+    0,
+    winapi::um::winnt::MEM_RELEASE,
+  );
+}
+
 /// Prot enum.
 #[cfg(unix)]
 #[allow(non_snake_case, non_upper_case_globals)]
@@ -77,7 +118,11 @@ pub mod Prot {
   pub const NoAccess: Ty = winapi::um::winnt::PAGE_NOACCESS;
   pub const ReadOnly: Ty = winapi::um::winnt::PAGE_READONLY;
   pub const ReadWrite: Ty = winapi::um::winnt::PAGE_READWRITE;
-  pub const WriteOnly: Ty = winapi::um::winnt::PAGE_WRITECOPY;
+  // There are no write-only pages on windows and PAGE_WRITECOPY is not supported for
+  // private memory (i.e. VirtualAlloc), so this is the closest match.
+  // Consequently reads of a WriteOnly region are not trapped on windows like they are
+  // on unix: Only writes are guaranteed to succeed, everything else is best effort.
+  pub const WriteOnly: Ty = winapi::um::winnt::PAGE_READWRITE;
   pub const WriteCopy: Ty = winapi::um::winnt::PAGE_WRITECOPY;
   pub const Execute: Ty = winapi::um::winnt::PAGE_EXECUTE;
   pub const ReadExec: Ty = winapi::um::winnt::PAGE_EXECUTE_READ;
@@ -138,6 +183,30 @@ unsafe fn unprotected_ptr_from_user_ptr(memptr: *const u8) -> *mut u8 {
   unprotected_ptr_u as *mut u8
 }
 
+/// Locking of any allocation into RAM has failed (e.g. because of `RLIMIT_MEMLOCK`).
+pub fn mlock_failed() -> bool {
+  MLOCK_FAILED.load(Ordering::Relaxed)
+}
+
+/// Remember that locking has failed, only the first failure is logged (there will be many).
+fn report_mlock_failure() {
+  let error = std::io::Error::last_os_error();
+  if !MLOCK_FAILED.swap(true, Ordering::Relaxed) {
+    warn!(
+      "Unable to lock secure memory into RAM ({}), secrets might be swapped to disk. Consider raising RLIMIT_MEMLOCK (ulimit -l)",
+      error
+    );
+  }
+}
+
+/// Check if an allocation has been successfully locked into RAM.
+#[allow(clippy::cast_ptr_alignment)]
+pub unsafe fn is_locked<T>(memptr: NonNull<T>) -> bool {
+  let unprotected_ptr = unprotected_ptr_from_user_ptr(memptr.as_ptr() as *const u8);
+  let base_ptr = unprotected_ptr.offset(-(PAGE_SIZE as isize * 2));
+  ptr::read_unaligned(base_ptr.add(mem::size_of::<usize>()) as *const usize) != 0
+}
+
 #[allow(clippy::cast_ptr_alignment)]
 pub unsafe fn malloc(size: usize) -> NonNull<u8> {
   ALLOC_INIT.call_once(|| alloc_init());
@@ -156,12 +225,16 @@ pub unsafe fn malloc(size: usize) -> NonNull<u8> {
   // mprotect ptr
   _mprotect(base_ptr.add(PAGE_SIZE), PAGE_SIZE, Prot::NoAccess);
   _mprotect(unprotected_ptr.add(unprotected_size), PAGE_SIZE, Prot::NoAccess);
-  memory::mlock(unprotected_ptr, unprotected_size);
+  let locked = memory::mlock(unprotected_ptr, unprotected_size);
+  if !locked {
+    report_mlock_failure();
+  }
 
   let canary_ptr = unprotected_ptr.offset(unprotected_size as isize - size_with_canary as isize);
   let user_ptr = canary_ptr.add(CANARY_SIZE);
-  ptr::copy_nonoverlapping(CANARY.as_ptr(), canary_ptr, CANARY_SIZE);
+  ptr::copy_nonoverlapping(ptr::addr_of!(CANARY) as *const u8, canary_ptr, CANARY_SIZE);
   ptr::write_unaligned(base_ptr as *mut usize, unprotected_size);
+  ptr::write_unaligned(base_ptr.add(mem::size_of::<usize>()) as *mut usize, locked as usize);
   _mprotect(base_ptr, PAGE_SIZE, Prot::ReadOnly);
 
   assert_eq!(unprotected_ptr_from_user_ptr(user_ptr), unprotected_ptr);
@@ -183,7 +256,11 @@ pub unsafe fn free<T>(memptr: NonNull<T>) {
   _mprotect(base_ptr, total_size, Prot::ReadWrite);
 
   // check
-  assert!(memory::memeq(canary_ptr as *const u8, CANARY.as_ptr(), CANARY_SIZE));
+  assert!(memory::memeq(
+    canary_ptr as *const u8,
+    ptr::addr_of!(CANARY) as *const u8,
+    CANARY_SIZE
+  ));
 
   // free
   memory::memzero(unprotected_ptr, unprotected_size);
@@ -238,9 +315,30 @@ mod tests {
       free(ptr);
 
       // This is actually quite illegal, just testing that memory has been zeroed on free
+      // (on windows the pages are released to the OS right away, so there is nothing to check)
+      #[cfg(not(windows))]
       for idx in 0..137 {
         assert_that(&ptr::read(ptr.as_ptr().offset(idx as isize))).is_equal_to(0);
       }
     }
   }
+
+  #[test]
+  fn test_mprotect_transitions() {
+    unsafe {
+      let ptr = malloc(137);
+
+      assert_that(&mprotect(ptr, Prot::NoAccess)).is_true();
+      assert_that(&mprotect(ptr, Prot::ReadOnly)).is_true();
+      assert_that(&mprotect(ptr, Prot::ReadWrite)).is_true();
+
+      ptr::write(ptr.as_ptr(), 42u8);
+
+      assert_that(&mprotect(ptr, Prot::WriteOnly)).is_true();
+      assert_that(&mprotect(ptr, Prot::ReadOnly)).is_true();
+      assert_that(&ptr::read(ptr.as_ptr())).is_equal_to(42u8);
+
+      free(ptr);
+    }
+  }
 }
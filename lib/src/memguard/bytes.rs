@@ -11,6 +11,7 @@ use std::sync::atomic::{AtomicIsize, Ordering};
 use zeroize::Zeroize;
 
 use crate::memguard::ZeroizeBytesBuffer;
+use crate::secrets_store::{SecretStoreError, SecretStoreResult};
 use byteorder::WriteBytesExt;
 use rand::{CryptoRng, RngCore};
 
@@ -126,16 +127,67 @@ impl SecretBytes {
     self.capacity
   }
 
-  pub fn borrow(&self) -> Ref {
+  pub fn borrow(&self) -> Ref<'_> {
     self.lock_read();
     Ref { bytes: self }
   }
 
-  pub fn borrow_mut(&mut self) -> RefMut {
+  pub fn borrow_mut(&mut self) -> RefMut<'_> {
     self.lock_write();
     RefMut { bytes: self }
   }
 
+  /// Check if the underlying memory is locked into RAM (i.e. will never be swapped to disk).
+  pub fn is_locked(&self) -> bool {
+    unsafe { alloc::is_locked(self.ptr) }
+  }
+
+  /// Append to the existing allocation (i.e. without exceeding `capacity`).
+  ///
+  /// Like `From<&mut [u8]>` the source is zeroed out afterwards.
+  pub fn push_slice(&mut self, data: &mut [u8]) -> SecretStoreResult<()> {
+    if self.size + data.len() > self.capacity {
+      return Err(SecretStoreError::CapacityExceeded(self.capacity));
+    }
+    self.lock_write();
+    unsafe {
+      copy_nonoverlapping(data.as_ptr(), self.ptr.as_ptr().add(self.size), data.len());
+      memory::memzero(data.as_mut_ptr(), data.len());
+    }
+    self.size += data.len();
+    self.unlock_write();
+
+    Ok(())
+  }
+
+  /// Change the length within `capacity`. Bytes added or removed this way are zeroed.
+  pub fn set_len(&mut self, len: usize) -> SecretStoreResult<()> {
+    if len > self.capacity {
+      return Err(SecretStoreError::CapacityExceeded(self.capacity));
+    }
+    let (from, to) = (self.size.min(len), self.size.max(len));
+    self.lock_write();
+    unsafe {
+      memory::memzero(self.ptr.as_ptr().add(from), to - from);
+    }
+    self.size = len;
+    self.unlock_write();
+
+    Ok(())
+  }
+
+  /// Constant-time comparison (e.g. for derived keys).
+  ///
+  /// Differing lengths are never equal, though the common prefix is compared anyway.
+  pub fn ct_eq(&self, other: &SecretBytes) -> bool {
+    let lhs = self.borrow();
+    let rhs = other.borrow();
+    let common_len = lhs.len().min(rhs.len());
+    let prefix_eq = unsafe { memory::memeq(lhs.as_ptr(), rhs.as_ptr(), common_len) };
+
+    prefix_eq & (lhs.len() == rhs.len())
+  }
+
   pub fn locks(&self) -> isize {
     self.locks.load(Ordering::Relaxed)
   }
@@ -165,9 +217,11 @@ impl SecretBytes {
   }
 
   fn lock_write(&mut self) {
-    let locks = self.locks.fetch_sub(1, Ordering::Relaxed);
+    // Only transition from "unlocked" to "write locked", i.e. a failed assertion leaves the counter untouched
+    let locks = self.locks.compare_exchange(0, -1, Ordering::Relaxed, Ordering::Relaxed);
 
-    assert!(locks == 0);
+    assert!(locks.is_ok(), "SecretBytes already borrowed");
+    assert!(self.size <= self.capacity);
 
     unsafe {
       alloc::mprotect(self.ptr, alloc::Prot::ReadWrite);
@@ -175,9 +229,9 @@ impl SecretBytes {
   }
 
   fn unlock_write(&mut self) {
-    let locks = self.locks.fetch_add(1, Ordering::Relaxed);
+    let locks = self.locks.compare_exchange(-1, 0, Ordering::Relaxed, Ordering::Relaxed);
 
-    assert!(locks == -1);
+    assert!(locks.is_ok(), "SecretBytes not borrowed mutably");
 
     unsafe {
       alloc::mprotect(self.ptr, alloc::Prot::NoAccess);
@@ -225,7 +279,7 @@ impl Clone for SecretBytes {
 
 impl PartialEq for SecretBytes {
   fn eq(&self, other: &Self) -> bool {
-    self.borrow().as_bytes() == other.borrow().as_bytes()
+    self.ct_eq(other)
   }
 }
 
@@ -493,6 +547,83 @@ mod tests {
     assert!(actual == expected)
   }
 
+  #[test]
+  fn test_lock_write_while_borrowed() {
+    let mut guarded = SecretBytes::from_secured(b"secret");
+
+    guarded.lock_read();
+
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| guarded.lock_write()));
+
+    assert_that(&result.is_err()).is_true();
+    assert_that(&guarded.locks()).is_equal_to(1);
+
+    guarded.unlock_read();
+
+    assert_that(&guarded.locks()).is_equal_to(0);
+    assert_slices_equal(&guarded.borrow_mut(), b"secret");
+    assert_that(&guarded.locks()).is_equal_to(0);
+  }
+
+  #[test]
+  fn test_push_slice() {
+    let mut guarded = SecretBytes::with_capacity(10);
+    let mut part1 = *b"secret";
+    let mut part2 = *b"1234";
+    let mut part3 = *b"!";
+
+    guarded.push_slice(&mut part1).unwrap();
+    guarded.push_slice(&mut part2).unwrap();
+
+    assert_that(&part1).is_equal_to([0u8; 6]);
+    assert_that(&part2).is_equal_to([0u8; 4]);
+    assert_that(&guarded.push_slice(&mut part3)).is_equal_to(Err(SecretStoreError::CapacityExceeded(10)));
+    assert_that(&part3).is_equal_to(b"!");
+    assert_that(&guarded.locks()).is_equal_to(0);
+    assert_slices_equal(&guarded.borrow(), b"secret1234");
+
+    guarded.set_len(6).unwrap();
+    assert_slices_equal(&guarded.borrow(), b"secret");
+    guarded.set_len(8).unwrap();
+    assert_slices_equal(&guarded.borrow(), b"secret\0\0");
+    assert_that(&guarded.set_len(11)).is_equal_to(Err(SecretStoreError::CapacityExceeded(10)));
+    assert_that(&guarded.len()).is_equal_to(8);
+    assert_that(&guarded.locks()).is_equal_to(0);
+  }
+
+  #[test]
+  fn test_ct_eq() {
+    let secret1 = SecretBytes::from_secured(b"derived key");
+    let secret2 = SecretBytes::from_secured(b"derived key");
+    let secret3 = SecretBytes::from_secured(b"derived kex");
+    let secret4 = SecretBytes::from_secured(b"derived");
+
+    assert_that(&secret1.ct_eq(&secret2)).is_true();
+    assert_that(&secret1.ct_eq(&secret1)).is_true();
+    assert_that(&secret1.ct_eq(&secret3)).is_false();
+    assert_that(&secret1.ct_eq(&secret4)).is_false();
+    assert_that(&secret4.ct_eq(&secret1)).is_false();
+    assert_that(&SecretBytes::with_capacity(0).ct_eq(&SecretBytes::with_capacity(0))).is_true();
+
+    for secret in [&secret1, &secret2, &secret3, &secret4] {
+      assert_that(&secret.locks()).is_equal_to(0);
+    }
+  }
+
+  #[test]
+  fn test_is_locked() {
+    let guarded = SecretBytes::from_secured(b"secret");
+
+    // If there never has been a failure every allocation is locked
+    if !alloc::mlock_failed() {
+      assert_that(&guarded.is_locked()).is_true();
+    }
+    if !guarded.is_locked() {
+      assert_that(&alloc::mlock_failed()).is_true();
+    }
+    assert_slices_equal(&guarded.borrow(), b"secret");
+  }
+
   #[test]
   fn test_borrow_read_only() {
     let rng = thread_rng();
@@ -580,7 +711,17 @@ mod tests {
     assert_that(&guarded.locks()).is_equal_to(0);
     assert_slices_equal(&guarded.borrow(), &expected);
 
-    guarded.borrow_mut().as_mut().copy_from_slice(&source2);
+    {
+      let mut ref1 = guarded.borrow_mut();
+
+      ref1.as_mut().copy_from_slice(&source2);
+      assert_that(&ref1.len()).is_equal_to(200);
+    }
+    assert_that(&guarded.locks()).is_equal_to(0);
+
+    guarded.lock_write();
+    assert_that(&guarded.locks()).is_equal_to(-1);
+    guarded.unlock_write();
 
     assert_that(&guarded.locks()).is_equal_to(0);
     assert_slices_equal(&guarded.borrow(), &expected2);
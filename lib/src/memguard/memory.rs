@@ -132,15 +132,46 @@ pub unsafe fn mlock(addr: *mut u8, len: usize) -> bool {
 
 /// Windows `VirtualLock`.
 ///
+/// Unlike `mlock` on unix `VirtualLock` is limited by the minimum working set size of
+/// the process (which is rather small by default). If the quota is exhausted the working
+/// set is grown by the requested size and the lock is retried once, so that locking
+/// behaves like on unix as long as the system permits it.
+///
 /// # Safety
 ///
 /// `addr` has to point to a memory section of at least `len` bytes
 #[cfg(windows)]
 pub unsafe fn mlock(addr: *mut u8, len: usize) -> bool {
-  winapi::um::memoryapi::VirtualLock(
-    addr as ::winapi::shared::minwindef::LPVOID,
-    len as ::winapi::shared::basetsd::SIZE_T,
-  ) != 0
+  use winapi::shared::basetsd::SIZE_T;
+  use winapi::shared::minwindef::{DWORD, LPVOID};
+  use winapi::um::memoryapi::{GetProcessWorkingSetSizeEx, SetProcessWorkingSetSizeEx, VirtualLock};
+
+  if VirtualLock(addr as LPVOID, len as SIZE_T) != 0 {
+    return true;
+  }
+  if winapi::um::errhandlingapi::GetLastError() != winapi::shared::winerror::ERROR_WORKING_SET_QUOTA {
+    return false;
+  }
+
+  static GROW_WORKING_SET: std::sync::Mutex<()> = std::sync::Mutex::new(());
+  let _guard = GROW_WORKING_SET.lock().unwrap_or_else(|e| e.into_inner());
+  let process = winapi::um::processthreadsapi::GetCurrentProcess();
+  let mut min_size: SIZE_T = 0;
+  let mut max_size: SIZE_T = 0;
+  let mut flags: DWORD = 0;
+
+  if GetProcessWorkingSetSizeEx(process, &mut min_size, &mut max_size, &mut flags) == 0
+    || SetProcessWorkingSetSizeEx(
+      process,
+      min_size.saturating_add(len as SIZE_T),
+      max_size.saturating_add(len as SIZE_T),
+      flags,
+    ) == 0
+  {
+    return false;
+  }
+
+  VirtualLock(addr as LPVOID, len as SIZE_T) != 0
 }
 
 /// Unix `munlock`.
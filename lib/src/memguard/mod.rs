@@ -8,3 +8,12 @@ mod zeroize_buffer;
 pub use self::bytes::SecretBytes;
 pub use self::words::{SecretWords, SecureHHeapAllocator};
 pub use self::zeroize_buffer::ZeroizeBytesBuffer;
+
+/// Check if secure memory is locked into RAM, i.e. secrets are never swapped to disk.
+///
+/// This is `false` as soon as locking of any secure allocation has failed.
+pub fn memory_locking_active() -> bool {
+  // Ensure that there has been at least one secure allocation
+  drop(SecretBytes::with_capacity(1));
+  !alloc::mlock_failed()
+}
@@ -14,6 +14,8 @@ pub enum OTPError {
   InvalidAlgorithm,
   #[error("Invalid secret")]
   InvalidSecret,
+  #[error("Invalid number of digits: {0}. Only 6 to 10 are supported")]
+  InvalidDigits(u32),
   #[error("Missing required parameter: {0}")]
   MissingParameter(String),
 }
@@ -19,7 +19,8 @@ pub struct HOTPGenerator<'a> {
 }
 
 impl<'a> HOTPGenerator<'a> {
-  fn calculate<D>(&mut self) -> String
+  /// Dynamic truncation of the hmac (i.e. 31 bit of it) as described in RFC 4226.
+  fn calculate<D>(&mut self) -> u32
   where
     D: CoreProxy,
     D::Core: HashMarker + UpdateCore + FixedOutputCore + BufferKindUser<BufferKind = Eager> + Default + Clone,
@@ -36,21 +37,25 @@ impl<'a> HOTPGenerator<'a> {
 
     let offset: usize = (digest[digest.len() - 1] & 0xf) as usize;
 
-    let base = BigEndian::read_u32(&digest[offset..offset + 4]) & 0x7fff_ffff;
-
-    format!(
-      "{:01$}",
-      base % (10_u32).pow(u32::from(self.digits)),
-      self.digits as usize
-    )
+    BigEndian::read_u32(&digest[offset..offset + 4]) & 0x7fff_ffff
   }
 
-  pub fn generate(&mut self) -> (String, u64) {
-    let otp = match self.algorithm {
+  pub fn generate_truncated(&mut self) -> u32 {
+    match self.algorithm {
       OTPAlgorithm::SHA1 => self.calculate::<Sha1>(),
       OTPAlgorithm::SHA256 => self.calculate::<Sha256>(),
       OTPAlgorithm::SHA512 => self.calculate::<Sha512>(),
-    };
+    }
+  }
+
+  pub fn generate(&mut self) -> (String, u64) {
+    let base = self.generate_truncated();
+    // 10^10 does not fit into an u32
+    let otp = format!(
+      "{:01$}",
+      u64::from(base) % (10_u64).pow(u32::from(self.digits)),
+      self.digits as usize
+    );
     (otp, self.counter)
   }
 }
@@ -1,30 +1,44 @@
+use percent_encoding::percent_decode_str;
 use std::fmt;
 use url::{form_urlencoded, Url};
 
 mod error;
 mod hotp;
+mod steam;
 mod totp;
 
 #[cfg(test)]
 mod tests;
 
 pub use self::error::*;
+use crate::api::OtpToken;
 use crate::otp::hotp::HOTPGenerator;
+use crate::otp::steam::{SteamGenerator, STEAM_DIGITS};
 use crate::otp::totp::TOTPGenerator;
 use std::str::FromStr;
 use zeroize::Zeroize;
 
 const OTP_URL_SCHEME: &str = "otpauth";
+/// Supported number of digits. More than 10 digits make no sense, since only 31 bits of the hmac are used.
+const OTP_DIGITS: std::ops::RangeInclusive<u32> = 6..=10;
 
 pub enum OTPType {
-  Totp { period: u32 },
-  Hotp { counter: u64 },
+  Totp {
+    period: u32,
+  },
+  Hotp {
+    counter: u64,
+  },
+  /// Steam guard codes, i.e. TOTP with `encoder=steam`.
+  Steam {
+    period: u32,
+  },
 }
 
 impl fmt::Display for OTPType {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     match self {
-      OTPType::Totp { .. } => write!(f, "totp")?,
+      OTPType::Totp { .. } | OTPType::Steam { .. } => write!(f, "totp")?,
       OTPType::Hotp { .. } => write!(f, "hotp")?,
     }
     Ok(())
@@ -59,11 +73,44 @@ impl fmt::Display for OTPSecret {
   }
 }
 
+impl OTPSecret {
+  /// Parse a hex encoded secret (whitespace, dashes and case are ignored).
+  pub fn from_hex(s: &str) -> OTPResult<Self> {
+    let normalized = Self::normalize(s);
+    match data_encoding::HEXUPPER.decode(normalized.as_bytes()) {
+      Ok(bytes) => Ok(OTPSecret(bytes)),
+      Err(_) => Err(OTPError::InvalidSecret),
+    }
+  }
+
+  fn normalize(s: &str) -> String {
+    s.chars()
+      .filter(|c| !c.is_whitespace() && *c != '-')
+      .map(|c| c.to_ascii_uppercase())
+      .collect()
+  }
+}
+
 impl FromStr for OTPSecret {
   type Err = OTPError;
 
+  /// Parse a base32 encoded secret (with or without padding).
+  ///
+  /// Whitespace, dashes and case are ignored. A string that only consists of hex digits, but contains
+  /// some that are not part of the base32 alphabet (0, 1, 8, 9), is considered to be hex encoded.
   fn from_str(s: &str) -> OTPResult<Self> {
-    match data_encoding::BASE32_NOPAD.decode(s.to_uppercase().as_bytes()) {
+    let normalized = Self::normalize(s);
+
+    if normalized.chars().all(|c| c.is_ascii_hexdigit())
+      && normalized.chars().any(|c| matches!(c, '0' | '1' | '8' | '9'))
+    {
+      return Self::from_hex(&normalized);
+    }
+
+    match data_encoding::BASE32
+      .decode(normalized.as_bytes())
+      .or_else(|_| data_encoding::BASE32_NOPAD.decode(normalized.trim_end_matches('=').as_bytes()))
+    {
       Ok(bytes) => Ok(OTPSecret(bytes)),
       Err(_) => Err(OTPError::InvalidSecret),
     }
@@ -76,6 +123,8 @@ pub struct OTPAuthUrl {
   pub digits: u8,
   pub account_name: String,
   pub issuer: Option<String>,
+  /// Issuer prefix of the label, if it disagrees with the `issuer` parameter (which takes precedence).
+  pub label_issuer: Option<String>,
   pub secret: OTPSecret,
 }
 
@@ -88,7 +137,10 @@ impl OTPAuthUrl {
     let otp_type = match url.host_str() {
       Some("totp") => {
         let period = Self::find_parameter(&url, "period")?.unwrap_or(30);
-        OTPType::Totp { period }
+        match Self::find_parameter::<String>(&url, "encoder")?.as_deref() {
+          Some("steam") | Some("STEAM") => OTPType::Steam { period },
+          _ => OTPType::Totp { period },
+        }
       }
       Some("hotp") => {
         let counter = Self::find_required_parameter(&url, "counter")?;
@@ -96,28 +148,32 @@ impl OTPAuthUrl {
       }
       _ => return Err(OTPError::InvalidType),
     };
-    let mut issuer = Self::find_parameter::<String>(&url, "issuer")?;
-    let mut account_name = String::new();
     if url.path().is_empty() {
       return Err(OTPError::MissingParameter("accountname".to_string()));
-    } else {
-      let mut parts = url.path()[1..].split(':');
-      if let Some(issuer_or_account) = parts.next() {
-        account_name = issuer_or_account.to_string();
-      }
-      if let Some(account) = parts.next() {
-        issuer = Some(account_name);
-        account_name = account.to_string();
-      }
     }
+    let issuer_param = Self::find_parameter::<String>(&url, "issuer")?;
+    let (prefix, account_name) = Self::parse_label(&url.path()[1..], issuer_param.as_deref());
+    let (issuer, label_issuer) = match (issuer_param, prefix) {
+      (Some(issuer), Some(prefix)) if issuer != prefix => (Some(issuer), Some(prefix)),
+      (Some(issuer), _) => (Some(issuer), None),
+      (None, prefix) => (prefix, None),
+    };
     let algorithm = match Self::find_parameter::<String>(&url, "algorithm")?.as_deref() {
       Some("SHA1") | None => OTPAlgorithm::SHA1,
       Some("SHA256") => OTPAlgorithm::SHA256,
       Some("SHA512") => OTPAlgorithm::SHA512,
       Some(_) => return Err(OTPError::InvalidAlgorithm),
     };
-    let digits = Self::find_parameter(&url, "digits")?.unwrap_or(6);
-    let secret = Self::find_required_parameter(&url, "secret")?;
+    let digits = match (&otp_type, Self::find_parameter::<u32>(&url, "digits")?.unwrap_or(6)) {
+      (OTPType::Steam { .. }, _) => STEAM_DIGITS,
+      (_, digits) if OTP_DIGITS.contains(&digits) => digits as u8,
+      (_, digits) => return Err(OTPError::InvalidDigits(digits)),
+    };
+    let secret_str = Self::find_required_parameter::<String>(&url, "secret")?;
+    let secret = match Self::find_parameter::<String>(&url, "encoding")?.as_deref() {
+      Some("hex") | Some("HEX") => OTPSecret::from_hex(&secret_str)?,
+      _ => secret_str.parse()?,
+    };
 
     Ok(OTPAuthUrl {
       otp_type,
@@ -125,6 +181,7 @@ impl OTPAuthUrl {
       digits,
       account_name,
       issuer,
+      label_issuer,
       secret,
     })
   }
@@ -133,25 +190,27 @@ impl OTPAuthUrl {
     let mut result = format!("{}://{}/", OTP_URL_SCHEME, self.otp_type);
 
     if let Some(issuer) = &self.issuer {
-      result.extend(form_urlencoded::byte_serialize(issuer.as_bytes()));
+      result += &Self::encode_label_part(issuer);
       result += ":"
     }
-    result.extend(form_urlencoded::byte_serialize(self.account_name.as_bytes()));
+    result += &Self::encode_label_part(&self.account_name);
     result += "?secret=";
     result += &self.secret.to_string();
 
     match self.otp_type {
-      OTPType::Totp { period } if period != 30 => {
+      OTPType::Totp { period } | OTPType::Steam { period } if period != 30 => {
         result += "&period=";
         result += &period.to_string();
       }
-      OTPType::Totp { .. } => (),
+      OTPType::Totp { .. } | OTPType::Steam { .. } => (),
       OTPType::Hotp { counter } => {
         result += "&counter=";
         result += &counter.to_string();
       }
     }
-    if self.digits != 6 {
+    if let OTPType::Steam { .. } = self.otp_type {
+      result += "&encoder=steam";
+    } else if self.digits != 6 {
       result += "&digits=";
       result += &self.digits.to_string();
     }
@@ -183,9 +242,93 @@ impl OTPAuthUrl {
         secret: &self.secret.0,
       }
       .generate(),
+      OTPType::Steam { period } => SteamGenerator {
+        algorithm: self.algorithm,
+        period,
+        secret: &self.secret.0,
+      }
+      .generate(timestamp_or_counter),
+    }
+  }
+
+  /// Like `generate`, additionally with the seconds remaining in the current period (always 0 for HOTP).
+  pub fn generate_with_validity(&self, timestamp: u64) -> (String, u64, u64) {
+    let (otp, valid_until) = self.generate(timestamp);
+    match self.otp_type {
+      OTPType::Totp { .. } | OTPType::Steam { .. } => (otp, valid_until, valid_until.saturating_sub(timestamp)),
+      OTPType::Hotp { .. } => (otp, valid_until, 0),
+    }
+  }
+
+  /// Generate the token valid at `timestamp` (HOTP tokens are generated for the stored counter instead).
+  pub fn generate_token(&self, timestamp: u64) -> OtpToken {
+    match self.otp_type {
+      OTPType::Totp { period } | OTPType::Steam { period } => {
+        let (code, valid_until) = self.generate(timestamp);
+        OtpToken {
+          code,
+          valid_until,
+          period,
+        }
+      }
+      OTPType::Hotp { counter } => OtpToken {
+        code: self.generate(counter).0,
+        valid_until: 0,
+        period: 0,
+      },
+    }
+  }
+
+  /// Verify a HOTP code with a look-ahead window to resync with a drifted counter.
+  ///
+  /// Counters from the stored value up to `look_ahead` ahead are tried, the result is the matching counter
+  /// (i.e. the counter to persist is the result + 1). All counters of the window are checked and compared
+  /// in constant time, so that the timing does not leak which one matched. Always `None` for TOTP.
+  pub fn verify_hotp(&self, code: &str, look_ahead: u32) -> Option<u64> {
+    let start = match self.otp_type {
+      OTPType::Hotp { counter } => counter,
+      OTPType::Totp { .. } | OTPType::Steam { .. } => return None,
+    };
+    let mut matched = None;
+
+    for counter in (0..=u64::from(look_ahead)).map_while(|offset| start.checked_add(offset)) {
+      let (expected, _) = self.generate(counter);
+      if constant_time_eq(code.as_bytes(), expected.as_bytes()) && matched.is_none() {
+        matched = Some(counter);
+      }
+    }
+
+    matched
+  }
+
+  /// Split a (still percent-encoded) label into the issuer prefix and the account name.
+  ///
+  /// The separator is the first literal colon. An encoded colon (`%3A`) is only considered as separator
+  /// if the label is prefixed with the issuer parameter, otherwise it is part of the account name.
+  fn parse_label(label: &str, issuer_param: Option<&str>) -> (Option<String>, String) {
+    let decode = |part: &str| percent_decode_str(part).decode_utf8_lossy().to_string();
+
+    match label.split_once(':') {
+      Some((prefix, account_name)) => (Some(decode(prefix)), decode(account_name).trim_start().to_string()),
+      None => {
+        let decoded = decode(label);
+        match (decoded.split_once(':'), issuer_param) {
+          (Some((prefix, account_name)), Some(issuer)) if prefix == issuer => {
+            (Some(prefix.to_string()), account_name.trim_start().to_string())
+          }
+          _ => (None, decoded),
+        }
+      }
     }
   }
 
+  /// Percent-encode a part of the label, this includes colons and spaces (which are `+` in form encoding).
+  fn encode_label_part(part: &str) -> String {
+    form_urlencoded::byte_serialize(part.as_bytes())
+      .collect::<String>()
+      .replace('+', "%20")
+  }
+
   fn find_parameter<T: FromStr>(url: &Url, name: &str) -> OTPResult<Option<T>> {
     match url.query_pairs().find(|(key, _)| key == name) {
       Some((_, value)) => {
@@ -202,3 +345,11 @@ impl OTPAuthUrl {
     Self::find_parameter(url, name)?.ok_or_else(|| OTPError::MissingParameter(name.to_string()))
   }
 }
+
+/// Compare two byte strings without an early exit on the first difference.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+  if a.len() != b.len() {
+    return false;
+  }
+  a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
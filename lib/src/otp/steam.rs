@@ -0,0 +1,35 @@
+use super::hotp::HOTPGenerator;
+use super::OTPAlgorithm;
+
+/// Alphabet of Steam guard codes (digits and consonants that can not be mixed up).
+const STEAM_ALPHABET: &[u8] = b"23456789BCDFGHJKMNPQRTVWXY";
+/// Steam guard codes always have 5 characters.
+pub const STEAM_DIGITS: u8 = 5;
+
+/// Steam guard variant of TOTP: The truncated hmac is encoded with a custom alphabet instead of decimal digits.
+#[derive(Debug)]
+pub struct SteamGenerator<'a> {
+  pub algorithm: OTPAlgorithm,
+  pub period: u32,
+  pub secret: &'a [u8],
+}
+
+impl<'a> SteamGenerator<'a> {
+  pub fn generate(&self, timestamp: u64) -> (String, u64) {
+    let mut hotp_gen = HOTPGenerator {
+      algorithm: self.algorithm,
+      counter: timestamp / u64::from(self.period),
+      digits: STEAM_DIGITS,
+      secret: self.secret,
+    };
+    let mut base = hotp_gen.generate_truncated() as usize;
+    let mut otp = String::with_capacity(STEAM_DIGITS as usize);
+
+    for _ in 0..STEAM_DIGITS {
+      otp.push(STEAM_ALPHABET[base % STEAM_ALPHABET.len()] as char);
+      base /= STEAM_ALPHABET.len();
+    }
+
+    (otp, (timestamp / u64::from(self.period) + 1) * u64::from(self.period))
+  }
+}
@@ -1,4 +1,5 @@
-use super::{OTPAlgorithm, OTPAuthUrl};
+use super::{OTPAlgorithm, OTPAuthUrl, OTPError, OTPSecret, OTPType};
+use crate::api::OtpToken;
 use spectral::prelude::*;
 
 #[test]
@@ -60,12 +61,315 @@ fn test_totp_lowercase() {
 
   assert_that(&otpauth.algorithm).is_equal_to(OTPAlgorithm::SHA1);
   assert_that(&otpauth.digits).is_equal_to(6);
-  assert_that(&otpauth.issuer).is_equal_to(Some("Test".to_string()));
+  // The issuer parameter takes precedence over the label prefix
+  assert_that(&otpauth.issuer).is_equal_to(Some("Github".to_string()));
+  assert_that(&otpauth.label_issuer).is_equal_to(Some("Test".to_string()));
   assert_that(&otpauth.account_name).is_equal_to("someone".to_string());
 
   assert_that(&otpauth.generate(1_556_733_830)).is_equal_to(("349728".to_string(), 1_556_733_840));
   assert_that(&otpauth.generate(1_556_733_904)).is_equal_to(("141680".to_string(), 1_556_733_930));
 
   assert_that(&otpauth.to_url())
-    .is_equal_to("otpauth://totp/Test:someone?secret=PD7GRYUK4OW2LJ7LZQ7SA5BNDHVNUCI4&issuer=Test".to_string());
+    .is_equal_to("otpauth://totp/Github:someone?secret=PD7GRYUK4OW2LJ7LZQ7SA5BNDHVNUCI4&issuer=Github".to_string());
+}
+
+#[test]
+fn test_secret_formats() {
+  let expected = "JBSWY3DPEHPK3PXP".parse::<OTPSecret>().unwrap().to_string();
+
+  for secret in &[
+    "jbswy3dpehpk3pxp",
+    "JBSW Y3DP EHPK 3PXP",
+    " jbsw y3dp\tehpk 3pxp ",
+    "48656c6c6f21deadbeef",
+    "4865 6C6C 6F21 DEAD BEEF",
+  ] {
+    assert_that(&secret.parse::<OTPSecret>().unwrap().to_string()).is_equal_to(&expected);
+  }
+  assert_that(&OTPSecret::from_hex("48656c6c6f21deadbeef").unwrap().to_string()).is_equal_to(&expected);
+
+  let padded = "JBSWY3DPEE======".parse::<OTPSecret>().unwrap();
+  assert_that(&padded.0).is_equal_to(b"Hello!".to_vec());
+  assert_that(&padded.to_string()).is_equal_to("JBSWY3DPEE".to_string());
+  assert_that(&"jbswy3dpee==".parse::<OTPSecret>().unwrap().0).is_equal_to(b"Hello!".to_vec());
+  for secret in &[
+    "jbswy3dpee======",
+    "JBSW-Y3DP-EE==-====",
+    " jbsw y3dp ee== ==== ",
+    "JBSWY3DPEE=",
+  ] {
+    assert_that(&secret.parse::<OTPSecret>().unwrap().0).is_equal_to(b"Hello!".to_vec());
+  }
+  assert_that(&"JBSW-Y3DP-EHPK-3PXP".parse::<OTPSecret>().unwrap().to_string()).is_equal_to(&expected);
+  assert_that(&"jbsw-y3dp ehpk-3pxp".parse::<OTPSecret>().unwrap().to_string()).is_equal_to(&expected);
+
+  for invalid in &["JBSWY3DP!", "JBSW=Y3DP", "0123456789abcdeg", "4865 6C6", "JBSW_Y3DP"] {
+    assert!(
+      matches!(invalid.parse::<OTPSecret>(), Err(OTPError::InvalidSecret)),
+      "{}",
+      invalid
+    );
+  }
+  assert!(matches!(OTPSecret::from_hex("JBSWY3DP"), Err(OTPError::InvalidSecret)));
+}
+
+#[test]
+fn test_totp_hex_encoding() {
+  let totp_url = "otpauth://totp/Example:someone@somewhere.com?secret=48656c6c6f21deadbeef&issuer=Example";
+  let otpauth = OTPAuthUrl::parse(totp_url).unwrap();
+
+  assert_that(&otpauth.generate(1_556_733_311)).is_equal_to(("184557".to_string(), 1_556_733_330));
+  assert_that(&otpauth.to_url())
+    .is_equal_to("otpauth://totp/Example:someone%40somewhere.com?secret=JBSWY3DPEHPK3PXP&issuer=Example".to_string());
+
+  // Hex secret without any digits outside of the base32 alphabet needs an explicit hint
+  let hinted_url = "otpauth://totp/someone?secret=ABCDEF234567&encoding=hex";
+  let otpauth = OTPAuthUrl::parse(hinted_url).unwrap();
+
+  assert_that(&otpauth.secret.0).is_equal_to(vec![0xab, 0xcd, 0xef, 0x23, 0x45, 0x67]);
+
+  let invalid_url = "otpauth://totp/someone?secret=JBSWY3DP!";
+  assert!(matches!(OTPAuthUrl::parse(invalid_url), Err(OTPError::InvalidSecret)));
+}
+
+#[test]
+fn test_totp_sha256_8_digits() {
+  // Test vectors of RFC 6238
+  let totp_url =
+    "otpauth://totp/rfc6238?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZA&digits=8&algorithm=SHA256";
+  let otpauth = OTPAuthUrl::parse(totp_url).unwrap();
+
+  assert_that(&otpauth.algorithm).is_equal_to(OTPAlgorithm::SHA256);
+  assert_that(&otpauth.digits).is_equal_to(8);
+
+  assert_that(&otpauth.generate(59)).is_equal_to(("46119246".to_string(), 60));
+  assert_that(&otpauth.generate(1_111_111_109)).is_equal_to(("68084774".to_string(), 1_111_111_110));
+  assert_that(&otpauth.generate(1_111_111_111)).is_equal_to(("67062674".to_string(), 1_111_111_140));
+  assert_that(&otpauth.generate(1_234_567_890)).is_equal_to(("91819424".to_string(), 1_234_567_920));
+  assert_that(&otpauth.generate(2_000_000_000)).is_equal_to(("90698825".to_string(), 2_000_000_010));
+  assert_that(&otpauth.generate(20_000_000_000)).is_equal_to(("77737706".to_string(), 20_000_000_010));
+
+  let round_trip = OTPAuthUrl::parse(otpauth.to_url()).unwrap();
+
+  assert_that(&round_trip.to_url()).is_equal_to(otpauth.to_url());
+  assert_that(&round_trip.digits).is_equal_to(8);
+  assert_that(&round_trip.algorithm).is_equal_to(OTPAlgorithm::SHA256);
+  assert_that(&round_trip.generate(1_234_567_890)).is_equal_to(("91819424".to_string(), 1_234_567_920));
+}
+
+#[test]
+fn test_totp_other_digits() {
+  let secret = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZA";
+  let seven = OTPAuthUrl::parse(format!(
+    "otpauth://totp/someone?secret={}&digits=7&algorithm=SHA256",
+    secret
+  ))
+  .unwrap();
+  let ten = OTPAuthUrl::parse(format!(
+    "otpauth://totp/someone?secret={}&digits=10&algorithm=SHA256",
+    secret
+  ))
+  .unwrap();
+
+  assert_that(&seven.generate(2_000_000_000)).is_equal_to(("0698825".to_string(), 2_000_000_010));
+  assert_that(&ten.generate(2_000_000_000)).is_equal_to(("1790698825".to_string(), 2_000_000_010));
+  assert_that(&ten.generate(1_234_567_890)).is_equal_to(("0091819424".to_string(), 1_234_567_920));
+  assert_that(&seven.to_url()).is_equal_to(format!(
+    "otpauth://totp/someone?secret={}&digits=7&algorithm=SHA256",
+    secret
+  ));
+
+  for digits in ["0", "5", "11", "255", "1000"] {
+    let url = format!("otpauth://totp/someone?secret={}&digits={}", secret, digits);
+
+    assert!(matches!(OTPAuthUrl::parse(url), Err(OTPError::InvalidDigits(_))));
+  }
+}
+
+#[test]
+fn test_otpauth_labels() {
+  let secret = "JBSWY3DPEHPK3PXP";
+  // (url, issuer, label_issuer, account_name, to_url)
+  let cases = [
+    (
+      "otpauth://totp/Example:alice@example.com?secret=JBSWY3DPEHPK3PXP",
+      Some("Example"),
+      None,
+      "alice@example.com",
+      "otpauth://totp/Example:alice%40example.com?secret=JBSWY3DPEHPK3PXP&issuer=Example",
+    ),
+    (
+      "otpauth://totp/Example:%20alice%40example.com?secret=JBSWY3DPEHPK3PXP&issuer=Example",
+      Some("Example"),
+      None,
+      "alice@example.com",
+      "otpauth://totp/Example:alice%40example.com?secret=JBSWY3DPEHPK3PXP&issuer=Example",
+    ),
+    (
+      "otpauth://totp/ACME%20Co%3A%20Inc:john.doe?secret=JBSWY3DPEHPK3PXP&issuer=ACME+Co%3A+Inc",
+      Some("ACME Co: Inc"),
+      None,
+      "john.doe",
+      "otpauth://totp/ACME%20Co%3A%20Inc:john.doe?secret=JBSWY3DPEHPK3PXP&issuer=ACME+Co%3A+Inc",
+    ),
+    // Encoded separator (as written by some generators)
+    (
+      "otpauth://totp/Example%3Aalice?secret=JBSWY3DPEHPK3PXP&issuer=Example",
+      Some("Example"),
+      None,
+      "alice",
+      "otpauth://totp/Example:alice?secret=JBSWY3DPEHPK3PXP&issuer=Example",
+    ),
+    // Without issuer an encoded colon is part of the account name
+    (
+      "otpauth://totp/alice%3Awork?secret=JBSWY3DPEHPK3PXP",
+      None,
+      None,
+      "alice:work",
+      "otpauth://totp/alice%3Awork?secret=JBSWY3DPEHPK3PXP",
+    ),
+    (
+      "otpauth://totp/Example:alice%3Awork%2Botp?secret=JBSWY3DPEHPK3PXP&issuer=Example",
+      Some("Example"),
+      None,
+      "alice:work+otp",
+      "otpauth://totp/Example:alice%3Awork%2Botp?secret=JBSWY3DPEHPK3PXP&issuer=Example",
+    ),
+    // Issuer parameter and label prefix disagree
+    (
+      "otpauth://totp/Old%20Name:alice?secret=JBSWY3DPEHPK3PXP&issuer=New%20Name",
+      Some("New Name"),
+      Some("Old Name"),
+      "alice",
+      "otpauth://totp/New%20Name:alice?secret=JBSWY3DPEHPK3PXP&issuer=New+Name",
+    ),
+  ];
+
+  for (url, issuer, label_issuer, account_name, expected_url) in cases {
+    let otpauth = OTPAuthUrl::parse(url).unwrap();
+
+    assert_that(&otpauth.issuer.as_deref()).named(url).is_equal_to(issuer);
+    assert_that(&otpauth.label_issuer.as_deref())
+      .named(url)
+      .is_equal_to(label_issuer);
+    assert_that(&otpauth.account_name.as_str())
+      .named(url)
+      .is_equal_to(account_name);
+    assert_that(&otpauth.secret.to_string()).is_equal_to(secret.to_string());
+    assert_that(&otpauth.to_url())
+      .named(url)
+      .is_equal_to(expected_url.to_string());
+
+    // Round-trips are stable (and do not disagree anymore)
+    let round_trip = OTPAuthUrl::parse(otpauth.to_url()).unwrap();
+
+    assert_that(&round_trip.issuer).named(url).is_equal_to(&otpauth.issuer);
+    assert_that(&round_trip.label_issuer).named(url).is_none();
+    assert_that(&round_trip.account_name)
+      .named(url)
+      .is_equal_to(&otpauth.account_name);
+    assert_that(&round_trip.to_url())
+      .named(url)
+      .is_equal_to(otpauth.to_url());
+  }
+}
+
+#[test]
+fn test_verify_hotp() {
+  // Test vectors of RFC 4226 (secret "12345678901234567890")
+  let hotp_url = "otpauth://hotp/Test:someone?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&counter=2";
+  let otpauth = OTPAuthUrl::parse(hotp_url).unwrap();
+
+  assert_that(&otpauth.generate(2)).is_equal_to(("359152".to_string(), 3));
+
+  // Match at offset 0
+  assert_that(&otpauth.verify_hotp("359152", 0)).is_equal_to(Some(2));
+  assert_that(&otpauth.verify_hotp("359152", 5)).is_equal_to(Some(2));
+  // Match at the look-ahead boundary
+  assert_that(&otpauth.verify_hotp("254676", 3)).is_equal_to(Some(5));
+  assert_that(&otpauth.verify_hotp("254676", 2)).is_none();
+  // Misses: codes before the stored counter, wrong codes and wrong lengths
+  assert_that(&otpauth.verify_hotp("287082", 10)).is_none();
+  assert_that(&otpauth.verify_hotp("000000", 10)).is_none();
+  assert_that(&otpauth.verify_hotp("35915", 10)).is_none();
+  assert_that(&otpauth.verify_hotp("", 10)).is_none();
+
+  let totp_url = "otpauth://totp/Example:someone@somewhere.com?secret=JBSWY3DPEHPK3PXP&issuer=Example";
+
+  assert_that(&OTPAuthUrl::parse(totp_url).unwrap().verify_hotp("184557", 10)).is_none();
+}
+
+#[test]
+fn test_steam() {
+  // Steam shared secret cnOgv/KdpLoP6Nbh0GMkXkPXALQ= (base64)
+  let steam_url = "otpauth://totp/Steam:someone?secret=OJZ2BP7STWSLUD7I23Q5AYZELZB5OAFU&issuer=Steam&encoder=steam";
+  let otpauth = OTPAuthUrl::parse(steam_url).unwrap();
+
+  assert_that(&matches!(otpauth.otp_type, OTPType::Steam { period: 30 })).is_true();
+  assert_that(&otpauth.digits).is_equal_to(5);
+  assert_that(&otpauth.generate(0)).is_equal_to(("W3J46".to_string(), 30));
+  assert_that(&otpauth.generate(1_556_733_311)).is_equal_to(("QHTCB".to_string(), 1_556_733_330));
+  assert_that(&otpauth.generate(1_700_000_000)).is_equal_to(("X45RP".to_string(), 1_700_000_010));
+
+  assert_that(&otpauth.to_url()).is_equal_to(
+    "otpauth://totp/Steam:someone?secret=OJZ2BP7STWSLUD7I23Q5AYZELZB5OAFU&encoder=steam&issuer=Steam".to_string(),
+  );
+
+  let round_trip = OTPAuthUrl::parse(otpauth.to_url()).unwrap();
+
+  assert_that(&matches!(round_trip.otp_type, OTPType::Steam { period: 30 })).is_true();
+  assert_that(&round_trip.generate(1_556_733_311)).is_equal_to(("QHTCB".to_string(), 1_556_733_330));
+
+  // A digits parameter is meaningless for steam
+  let otpauth = OTPAuthUrl::parse(
+    "otpauth://totp/Steam:someone?secret=OJZ2BP7STWSLUD7I23Q5AYZELZB5OAFU&digits=8&period=60&encoder=steam",
+  )
+  .unwrap();
+
+  assert_that(&otpauth.digits).is_equal_to(5);
+  assert_that(&otpauth.generate(59)).is_equal_to(("W3J46".to_string(), 60));
+  assert_that(&otpauth.to_url()).is_equal_to(
+    "otpauth://totp/Steam:someone?secret=OJZ2BP7STWSLUD7I23Q5AYZELZB5OAFU&period=60&encoder=steam&issuer=Steam"
+      .to_string(),
+  );
+}
+
+#[test]
+fn test_generate_with_validity() {
+  let totp_url = "otpauth://totp/Example:someone@somewhere.com?secret=JBSWY3DPEHPK3PXP&issuer=Example";
+  let otpauth = OTPAuthUrl::parse(totp_url).unwrap();
+
+  assert_that(&otpauth.generate_with_validity(1_556_733_311)).is_equal_to(("184557".to_string(), 1_556_733_330, 19));
+  assert_that(&otpauth.generate_with_validity(1_556_733_300)).is_equal_to(("184557".to_string(), 1_556_733_330, 30));
+  assert_that(&otpauth.generate_with_validity(1_556_733_329)).is_equal_to(("184557".to_string(), 1_556_733_330, 1));
+
+  let totp_url = "otpauth://totp/Example:someone@somewhere.com?secret=JBSWY3DPEHPK3PXP&period=60";
+  let otpauth = OTPAuthUrl::parse(totp_url).unwrap();
+
+  assert_that(&otpauth.generate_with_validity(1_556_733_311).2).is_equal_to(49);
+
+  let hotp_url = "otpauth://hotp/Test:someone?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&counter=2";
+  let otpauth = OTPAuthUrl::parse(hotp_url).unwrap();
+
+  assert_that(&otpauth.generate_with_validity(2)).is_equal_to(("359152".to_string(), 3, 0));
+}
+
+#[test]
+fn test_generate_token() {
+  let totp_url = "otpauth://totp/Example:someone@somewhere.com?secret=JBSWY3DPEHPK3PXP&issuer=Example";
+
+  assert_that(&OTPAuthUrl::parse(totp_url).unwrap().generate_token(1_556_733_311)).is_equal_to(OtpToken {
+    code: "184557".to_string(),
+    valid_until: 1_556_733_330,
+    period: 30,
+  });
+
+  // HOTP tokens are generated for the stored counter, regardless of the time
+  let hotp_url = "otpauth://hotp/Test:someone?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&counter=2";
+
+  assert_that(&OTPAuthUrl::parse(hotp_url).unwrap().generate_token(1_556_733_311)).is_equal_to(OtpToken {
+    code: "359152".to_string(),
+    valid_until: 0,
+    period: 0,
+  });
 }
@@ -0,0 +1,156 @@
+use capnp::{message, serialize};
+use chacha20_poly1305_aead::{decrypt, encrypt};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::api::AttachmentChunk;
+use crate::memguard::weak::ZeroingHeapAllocator;
+use crate::memguard::SecretBytes;
+use crate::secrets_store_capnp::block;
+
+use super::multi_lane::decompress_bounded;
+use super::{SecretStoreError, SecretStoreResult};
+
+/// Chunks are never smaller than this (unless it is the last chunk of an attachment).
+pub const MIN_CHUNK_SIZE: usize = 2 * 1024;
+/// Chunks are never larger than this.
+pub const MAX_CHUNK_SIZE: usize = 32 * 1024;
+/// Chunk boundaries are at positions where these (upper) bits of the rolling hash are zero,
+/// i.e. chunks have an average size of about 8 KiB.
+const BOUNDARY_MASK: u64 = ((1 << 13) - 1) << 51;
+
+const CHUNK_KEY_PURPOSE: &[u8] = b"t-rust-less attachment chunk";
+const TAG_LENGTH: usize = 16;
+/// Every chunk key is only ever used for a single plaintext, so a fixed nonce is sufficient.
+const CHUNK_NONCE: [u8; 12] = [0u8; 12];
+/// zstd compression level of chunks (fixed, so that identical chunks always produce identical blocks).
+const CHUNK_COMPRESSION_LEVEL: i32 = 9;
+
+/// Random (but fixed) values for the gear hash.
+const GEAR: [u64; 256] = gear_table();
+
+const fn gear_table() -> [u64; 256] {
+  let mut table = [0u64; 256];
+  let mut state: u64 = 0x7472_7573_746c_6573;
+  let mut i = 0;
+
+  while i < 256 {
+    // splitmix64
+    state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    table[i] = z ^ (z >> 31);
+    i += 1;
+  }
+  table
+}
+
+/// Split content into content-defined chunks.
+///
+/// Boundaries only depend on the content in front of them (via a gear rolling hash), so identical
+/// regions of different attachments will mostly produce identical chunks, even if they are at different
+/// offsets.
+pub fn split_chunks(content: &[u8]) -> Vec<&[u8]> {
+  let mut chunks = Vec::with_capacity(content.len() / (8 * 1024) + 1);
+  let mut remaining = content;
+
+  while !remaining.is_empty() {
+    let length = next_boundary(remaining);
+    let (chunk, rest) = remaining.split_at(length);
+
+    chunks.push(chunk);
+    remaining = rest;
+  }
+
+  chunks
+}
+
+fn next_boundary(content: &[u8]) -> usize {
+  if content.len() <= MIN_CHUNK_SIZE {
+    return content.len();
+  }
+  let end = content.len().min(MAX_CHUNK_SIZE);
+  let mut hash = 0u64;
+
+  for (idx, byte) in content.iter().enumerate().take(end).skip(MIN_CHUNK_SIZE) {
+    hash = (hash << 1).wrapping_add(GEAR[*byte as usize]);
+    if hash & BOUNDARY_MASK == 0 {
+      return idx + 1;
+    }
+  }
+
+  end
+}
+
+/// Key of a chunk, derived from its (uncompressed) content.
+fn chunk_key(content: &[u8]) -> SecretBytes {
+  let mut mac = Hmac::<Sha256>::new_from_slice(CHUNK_KEY_PURPOSE).unwrap();
+  mac.update(content);
+  SecretBytes::from(mac.finalize().into_bytes().to_vec())
+}
+
+/// Encrypt a chunk of an attachment.
+///
+/// The result is the content for the block store and the reference to the chunk (without the block id,
+/// which is only known once the block has been added). Identical chunks always produce identical blocks.
+pub fn encrypt_chunk(content: &[u8], compress: bool) -> SecretStoreResult<(Vec<u8>, AttachmentChunk)> {
+  let key = chunk_key(content);
+  let mut compressed = None;
+
+  if compress {
+    let packed = SecretBytes::from(zstd::bulk::compress(content, CHUNK_COMPRESSION_LEVEL)?);
+    // Incompressible data is stored as is
+    if packed.len() < content.len() {
+      compressed = Some(packed);
+    }
+  }
+
+  let mut crypted = Vec::with_capacity(content.len() + TAG_LENGTH);
+  let tag = match &compressed {
+    Some(packed) => encrypt(&key.borrow(), &CHUNK_NONCE, &[], &packed.borrow(), &mut crypted)?,
+    None => encrypt(&key.borrow(), &CHUNK_NONCE, &[], content, &mut crypted)?,
+  };
+  crypted.extend_from_slice(&tag);
+
+  let mut block_message = message::Builder::new(ZeroingHeapAllocator::default());
+  let mut block = block_message.init_root::<block::Builder>();
+  // Chunks do not have any recipients, they can only be decrypted via the key in the referencing version
+  block.reborrow().init_headers(0);
+  block.set_content(&crypted);
+
+  let chunk = AttachmentChunk {
+    block_id: String::new(),
+    key: key.borrow().to_vec(),
+    compressed: compressed.is_some(),
+  };
+
+  Ok((serialize::write_message_to_words(&block_message), chunk))
+}
+
+/// Decrypt a chunk of an attachment from the raw block.
+pub fn decrypt_chunk(chunk: &AttachmentChunk, mut raw: &[u8]) -> SecretStoreResult<SecretBytes> {
+  let reader = serialize::read_message_from_flat_slice(&mut raw, Default::default())?;
+  let block = reader.get_root::<block::Reader>()?;
+  let crypted = block.get_content()?;
+
+  if crypted.len() < TAG_LENGTH {
+    return Err(SecretStoreError::Cipher("Attachment chunk too short".to_string()));
+  }
+  let tag_offset = crypted.len() - TAG_LENGTH;
+  let mut content = SecretBytes::with_capacity(tag_offset);
+  decrypt(
+    &chunk.key,
+    &CHUNK_NONCE,
+    &[],
+    &crypted[..tag_offset],
+    &crypted[tag_offset..],
+    &mut content.borrow_mut(),
+  )?;
+
+  if chunk.compressed {
+    return decompress_bounded(&content.borrow(), MAX_CHUNK_SIZE);
+  }
+
+  Ok(content)
+}
@@ -0,0 +1,93 @@
+use rand::{thread_rng, RngCore};
+use spectral::prelude::*;
+
+use super::attachment_chunks::{decrypt_chunk, encrypt_chunk, split_chunks, MAX_CHUNK_SIZE, MIN_CHUNK_SIZE};
+
+fn random_content(length: usize) -> Vec<u8> {
+  let mut content = vec![0u8; length];
+  thread_rng().fill_bytes(&mut content);
+  content
+}
+
+#[test]
+fn test_split_chunks() {
+  assert_that(&split_chunks(&[])).is_empty();
+  assert_that(&split_chunks(b"small")).is_equal_to(vec![b"small".as_ref()]);
+
+  let content = random_content(512 * 1024);
+  let chunks = split_chunks(&content);
+
+  assert_that(&chunks.len()).is_greater_than(16);
+  assert_that(&chunks.concat()).is_equal_to(&content);
+  for chunk in &chunks[..chunks.len() - 1] {
+    assert_that(&chunk.len()).is_greater_than_or_equal_to(MIN_CHUNK_SIZE);
+    assert_that(&chunk.len()).is_less_than_or_equal_to(MAX_CHUNK_SIZE);
+  }
+
+  // Chunks are deterministic
+  assert_that(&split_chunks(&content)).is_equal_to(&chunks);
+
+  // Content without any boundaries is split at the maximum chunk size
+  let zeros = vec![0u8; 3 * MAX_CHUNK_SIZE + 10];
+  let zero_chunks = split_chunks(&zeros);
+
+  assert_that(&zero_chunks.iter().map(|chunk| chunk.len()).collect::<Vec<_>>()).is_equal_to(vec![
+    MAX_CHUNK_SIZE,
+    MAX_CHUNK_SIZE,
+    MAX_CHUNK_SIZE,
+    10,
+  ]);
+}
+
+#[test]
+fn test_split_chunks_shifted() {
+  let content = random_content(256 * 1024);
+  let mut shifted = random_content(1000);
+  shifted.extend_from_slice(&content);
+
+  let chunks = split_chunks(&content);
+  let shifted_chunks = split_chunks(&shifted);
+  let shared = shifted_chunks.iter().filter(|chunk| chunks.contains(chunk)).count();
+
+  // Only the first few chunks are affected by the inserted prefix
+  assert_that(&shared).is_greater_than_or_equal_to(chunks.len() - 2);
+}
+
+#[test]
+fn test_encrypt_chunk() {
+  let content = random_content(10 * 1024);
+
+  let (raw1, chunk1) = encrypt_chunk(&content, false).unwrap();
+  let (raw2, chunk2) = encrypt_chunk(&content, false).unwrap();
+
+  // Identical content is encrypted identically
+  assert_that(&raw1).is_equal_to(&raw2);
+  assert_that(&chunk1).is_equal_to(&chunk2);
+  assert_that(&chunk1.compressed).is_false();
+  assert_that(&raw1.windows(64).any(|window| window == &content[0..64])).is_false();
+
+  let decrypted = decrypt_chunk(&chunk1, &raw1).unwrap();
+  assert_that(&decrypted.borrow().as_bytes()).is_equal_to(content.as_slice());
+
+  let (other_raw, other_chunk) = encrypt_chunk(&random_content(10 * 1024), false).unwrap();
+  assert_that(&other_raw).is_not_equal_to(&raw1);
+  assert_that(&decrypt_chunk(&other_chunk, &raw1)).is_err();
+}
+
+#[test]
+fn test_encrypt_chunk_compressed() {
+  let content = b"Some rather repetitive content. ".repeat(200);
+
+  let (raw, chunk) = encrypt_chunk(&content, true).unwrap();
+
+  assert_that(&chunk.compressed).is_true();
+  assert_that(&raw.len()).is_less_than(content.len() / 4);
+  assert_that(&decrypt_chunk(&chunk, &raw).unwrap().borrow().as_bytes()).is_equal_to(content.as_slice());
+
+  // Incompressible content is stored as is
+  let random = random_content(4096);
+  let (raw, chunk) = encrypt_chunk(&random, true).unwrap();
+
+  assert_that(&chunk.compressed).is_false();
+  assert_that(&decrypt_chunk(&chunk, &raw).unwrap().borrow().as_bytes()).is_equal_to(random.as_slice());
+}
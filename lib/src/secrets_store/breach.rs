@@ -0,0 +1,52 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use data_encoding::HEXUPPER;
+use sha1::{Digest, Sha1};
+
+use crate::memguard::SecretBytes;
+use crate::secrets_store::SecretStoreResult;
+
+/// Length of the hash prefix a range file is named after.
+pub const RANGE_PREFIX_LENGTH: usize = 5;
+
+/// Look up a password in a local copy of the "Have I Been Pwned" password ranges.
+///
+/// `breach_dir` is expected to contain one file per prefix of the (upper case hex) SHA-1 hash of the
+/// passwords (either `<PREFIX>` or `<PREFIX>.txt`), each line of the form `<SUFFIX>:<COUNT>`. This is the
+/// format of the k-anonymity range API, i.e. a download of all ranges can be used as is.
+///
+/// Returns how often the password has been seen in breaches, None if it is not part of the ranges (or there
+/// is no range file for its prefix). Nothing is ever sent over the network.
+pub fn lookup_breach_count<P: AsRef<Path>>(breach_dir: P, password: &SecretBytes) -> SecretStoreResult<Option<u64>> {
+  let hash = SecretBytes::from(HEXUPPER.encode(&Sha1::digest(password.borrow().as_bytes())));
+  let hash_ref = hash.borrow();
+  let (prefix, suffix) = hash_ref.as_str().split_at(RANGE_PREFIX_LENGTH);
+  let range_file = match open_range_file(breach_dir.as_ref(), prefix)? {
+    Some(range_file) => range_file,
+    None => return Ok(None),
+  };
+
+  for line in BufReader::new(range_file).lines() {
+    let line = line?;
+    if let Some((line_suffix, count)) = line.trim().split_once(':') {
+      if line_suffix.eq_ignore_ascii_case(suffix) {
+        return Ok(Some(count.trim().parse().unwrap_or(1)));
+      }
+    }
+  }
+
+  Ok(None)
+}
+
+fn open_range_file(breach_dir: &Path, prefix: &str) -> SecretStoreResult<Option<File>> {
+  for file_name in [prefix.to_string(), format!("{}.txt", prefix)] {
+    match File::open(breach_dir.join(file_name)) {
+      Ok(file) => return Ok(Some(file)),
+      Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+      Err(err) => return Err(err.into()),
+    }
+  }
+  Ok(None)
+}
@@ -0,0 +1,48 @@
+use super::breach::lookup_breach_count;
+use crate::memguard::SecretBytes;
+use spectral::prelude::*;
+use std::fs;
+use tempfile::Builder;
+
+// SHA-1("password") = 5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8
+// SHA-1("123456") = 7C4A8D09CA3762AF61E59520943DC26494F8941B
+// SHA-1("passwort") = 2E2B6533A81BC15430CF65DE46DC097EEB5BA70C
+
+fn secret_from_str(s: &str) -> SecretBytes {
+  SecretBytes::from(s.as_bytes().to_vec())
+}
+
+#[test]
+fn test_lookup_breach_count() {
+  let breach_dir = Builder::new().prefix("t-rust-less-breach").tempdir().unwrap();
+
+  fs::write(
+    breach_dir.path().join("5BAA6"),
+    "003D68EB55068C33ACE09247EE4C639306B:3\r\n1E4C9B93F3F0682250B6CF8331B7EE68FD8:9659365\r\n",
+  )
+  .unwrap();
+  fs::write(
+    breach_dir.path().join("2E2B6"),
+    "533A81BC15430CF65DE46DC097EEB5BA70B:2\n533A81BC15430CF65DE46DC097EEB5BA70D:5\n",
+  )
+  .unwrap();
+  fs::write(
+    breach_dir.path().join("7C4A8.txt"),
+    "d09ca3762af61e59520943dc26494f8941b:37359195\n",
+  )
+  .unwrap();
+
+  assert_that(&lookup_breach_count(breach_dir.path(), &secret_from_str("password")).unwrap())
+    .is_equal_to(Some(9659365));
+  // range files named like the range api responses, suffixes in any case
+  assert_that(&lookup_breach_count(breach_dir.path(), &secret_from_str("123456")).unwrap()).is_equal_to(Some(37359195));
+  // prefix present, suffix not
+  assert_that(&lookup_breach_count(breach_dir.path(), &secret_from_str("passwort")).unwrap()).is_none();
+}
+
+#[test]
+fn test_lookup_breach_count_missing_range() {
+  let breach_dir = Builder::new().prefix("t-rust-less-breach").tempdir().unwrap();
+
+  assert_that(&lookup_breach_count(breach_dir.path(), &secret_from_str("password")).unwrap()).is_none();
+}
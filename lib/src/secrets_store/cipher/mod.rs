@@ -1,5 +1,7 @@
+use crate::api::CipherSuite;
 use crate::memguard::SecretBytes;
 use crate::secrets_store_capnp::{block, KeyDerivationType, KeyType};
+use std::time::{Duration, Instant};
 
 use super::SecretStoreResult;
 
@@ -12,7 +14,7 @@ mod rust_x25519_chacha20_poly1305;
 
 #[cfg(feature = "openssl")]
 pub use self::openssl_rsa_aes_gcm::OPEN_SSL_RSA_AES_GCM;
-pub use self::rust_argon2id::RUST_ARGON2_ID;
+pub use self::rust_argon2id::{ARGON2_PRESET_DESKTOP, ARGON2_PRESET_MOBILE, ARGON2_PRESET_PARANOID, RUST_ARGON2_ID};
 #[cfg(feature = "rust_crypto")]
 pub use self::rust_rsa_aes_gcm::RUST_RSA_AES_GCM;
 pub use self::rust_x25519_chacha20_poly1305::RUST_X25519CHA_CHA20POLY1305;
@@ -131,6 +133,9 @@ pub trait KeyDerivation: Send + Sync {
   /// Get the default preset to use (for new keys).
   fn default_preset(&self) -> u8;
 
+  /// Get all available presets ordered from the lightest to the heaviest.
+  fn presets(&self) -> Vec<u8>;
+
   /// Get the minmal length of a nonce for key-derivation.
   fn min_nonce_len(&self) -> usize;
 
@@ -144,4 +149,71 @@ pub trait KeyDerivation: Send + Sync {
   ///
   fn derive(&self, passphrase: &SecretBytes, preset: u8, nonce: &[u8], key_length: usize)
     -> SecretStoreResult<SealKey>;
+
+  /// Find the heaviest preset that derives a key within `target` on this machine.
+  ///
+  /// All presets are timed from the lightest to the heaviest, if even the lightest preset exceeds
+  /// the target it is returned nevertheless.
+  fn calibrate(&self, target: Duration) -> u8 {
+    let passphrase = SecretBytes::from(b"calibrate".to_vec());
+    let nonce = vec![0u8; self.min_nonce_len()];
+    let presets = self.presets();
+    let mut chosen = presets.first().copied().unwrap_or_else(|| self.default_preset());
+
+    for preset in presets {
+      let start = Instant::now();
+
+      if self.derive(&passphrase, preset, &nonce, 32).is_err() || start.elapsed() > target {
+        break;
+      }
+      chosen = preset;
+    }
+
+    chosen
+  }
+}
+
+/// Check if the CPU offers hardware acceleration for AES.
+///
+/// Without it AES-GCM has to fall back to a (constant-time) software implementation that
+/// is considerably slower than ChaCha20-Poly1305.
+///
+pub fn has_aes_hardware_support() -> bool {
+  #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+  {
+    std::arch::is_x86_feature_detected!("aes") && std::arch::is_x86_feature_detected!("pclmulqdq")
+  }
+  #[cfg(target_arch = "aarch64")]
+  {
+    std::arch::is_aarch64_feature_detected!("aes")
+  }
+  #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
+  {
+    false
+  }
+}
+
+/// Get the cipher suite expected to perform best on the current hardware.
+///
+/// By default a multi-lane store encrypts with all available suites (`CipherSuite::All`). A store
+/// may be restricted to a single suite (see `MultiLaneSecretsStore::with_cipher_suite`), in which case
+/// this is the one recommended on init, but it is never selected without being asked for.
+///
+pub fn preferred_cipher() -> &'static dyn Cipher {
+  if has_aes_hardware_support() {
+    #[cfg(feature = "rust_crypto")]
+    return &RUST_RSA_AES_GCM;
+    #[cfg(all(feature = "openssl", not(feature = "rust_crypto")))]
+    return &OPEN_SSL_RSA_AES_GCM;
+  }
+  &RUST_X25519CHA_CHA20POLY1305
+}
+
+/// Get the cipher suite of `preferred_cipher`, i.e. the single suite recommended for new stores.
+///
+pub fn preferred_cipher_suite() -> CipherSuite {
+  match preferred_cipher().key_type() {
+    KeyType::RsaAesGcm => CipherSuite::RsaAesGcm,
+    KeyType::Ed25519Chacha20Poly1305 => CipherSuite::X25519Chacha20Poly1305,
+  }
 }
@@ -14,13 +14,40 @@ struct Preset {
   pub version: Version,
 }
 
-const PRESETS: &[Preset] = &[Preset {
-  lanes: 4,
-  mem_cost: 64 * 1024,
-  time_cost: 4,
-  version: Version::Version13,
-  variant: Variant::Argon2id,
-}];
+/// Preset for regular desktop machines (64 MiB memory, 4 iterations, 4 lanes).
+pub const ARGON2_PRESET_DESKTOP: u8 = 0;
+/// Preset for phones and other constrained devices (32 MiB memory, 3 iterations, 2 lanes).
+pub const ARGON2_PRESET_MOBILE: u8 = 1;
+/// Preset for strong machines (256 MiB memory, 6 iterations, 4 lanes).
+pub const ARGON2_PRESET_PARANOID: u8 = 2;
+
+/// All presets indexed by their number, i.e. existing presets must never be changed or reordered.
+const PRESETS: &[Preset] = &[
+  // ARGON2_PRESET_DESKTOP
+  Preset {
+    lanes: 4,
+    mem_cost: 64 * 1024,
+    time_cost: 4,
+    version: Version::Version13,
+    variant: Variant::Argon2id,
+  },
+  // ARGON2_PRESET_MOBILE
+  Preset {
+    lanes: 2,
+    mem_cost: 32 * 1024,
+    time_cost: 3,
+    version: Version::Version13,
+    variant: Variant::Argon2id,
+  },
+  // ARGON2_PRESET_PARANOID
+  Preset {
+    lanes: 4,
+    mem_cost: 256 * 1024,
+    time_cost: 6,
+    version: Version::Version13,
+    variant: Variant::Argon2id,
+  },
+];
 
 pub struct RustArgon2id();
 
@@ -30,7 +57,11 @@ impl KeyDerivation for RustArgon2id {
   }
 
   fn default_preset(&self) -> u8 {
-    0
+    ARGON2_PRESET_DESKTOP
+  }
+
+  fn presets(&self) -> Vec<u8> {
+    vec![ARGON2_PRESET_MOBILE, ARGON2_PRESET_DESKTOP, ARGON2_PRESET_PARANOID]
   }
 
   fn min_nonce_len(&self) -> usize {
@@ -70,6 +101,7 @@ mod tests {
   use crate::memguard::SecretBytes;
   use data_encoding::HEXLOWER;
   use spectral::prelude::*;
+  use std::time::Duration;
 
   #[test]
   #[cfg_attr(debug_assertions, ignore)]
@@ -100,4 +132,23 @@ mod tests {
       .as_str())
     .is_equal_to("51b1dff59e6bece75db4a2f668622fb110098841820dfded0f724d42cb7dbdd2");
   }
+
+  #[test]
+  #[cfg_attr(debug_assertions, ignore)]
+  fn test_presets() {
+    let passphrase = SecretBytes::from(Vec::from(&b"The password"[..]));
+    let keys = RUST_ARGON2_ID
+      .presets()
+      .into_iter()
+      .map(|preset| RUST_ARGON2_ID.derive(&passphrase, preset, b"12345678", 32).unwrap())
+      .collect::<Vec<_>>();
+
+    assert_that(&keys).has_length(3);
+    assert_that(&keys[0]).is_not_equal_to(&keys[1]);
+    assert_that(&keys[1]).is_not_equal_to(&keys[2]);
+    assert_that(&RUST_ARGON2_ID.derive(&passphrase, 3, b"12345678", 32)).is_err();
+
+    assert_that(&RUST_ARGON2_ID.calibrate(Duration::ZERO)).is_equal_to(ARGON2_PRESET_MOBILE);
+    assert_that(&RUST_ARGON2_ID.calibrate(Duration::from_secs(3600))).is_equal_to(ARGON2_PRESET_PARANOID);
+  }
 }
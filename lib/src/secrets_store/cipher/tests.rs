@@ -2,6 +2,7 @@ use rand::{distributions, thread_rng, Rng};
 use spectral::prelude::*;
 use std::iter;
 
+use crate::api::CipherSuite;
 use crate::memguard::SecretBytes;
 use crate::secrets_store::cipher::RUST_X25519CHA_CHA20POLY1305;
 use crate::secrets_store_capnp::block;
@@ -110,3 +111,14 @@ fn test_rust_x25519_chacha20_poly1305() {
 fn test_rust_rsa_aes_gcm() {
   common_chiper_tests(&crate::secrets_store::cipher::RUST_RSA_AES_GCM);
 }
+
+#[test]
+fn test_preferred_cipher() {
+  let aes_hardware = super::has_aes_hardware_support();
+  let preferred = super::preferred_cipher();
+
+  if !aes_hardware {
+    assert_that(&preferred.name()).is_equal_to(RUST_X25519CHA_CHA20POLY1305.name());
+    assert_that(&super::preferred_cipher_suite()).is_equal_to(CipherSuite::X25519Chacha20Poly1305);
+  }
+}
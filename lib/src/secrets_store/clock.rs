@@ -0,0 +1,17 @@
+use std::time::SystemTime;
+
+/// Source of the current time of a secrets store (autolock and unlock throttle).
+///
+/// This only exists so that time dependent behaviour can be tested without actually waiting.
+pub trait Clock: Send + Sync {
+  fn now(&self) -> SystemTime;
+}
+
+/// The regular wall clock.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+  fn now(&self) -> SystemTime {
+    SystemTime::now()
+  }
+}
@@ -1,4 +1,5 @@
 use crate::block_store::StoreError;
+use crate::otp::OTPError;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use zeroize::Zeroize;
@@ -41,6 +42,20 @@ pub enum SecretStoreError {
   MissingPrivateKey(String),
   #[error("Secret not found")]
   NotFound,
+  #[error("Too many failed unlock attempts, try again in {0}s")]
+  UnlockThrottled(u64),
+  #[error("Attachment {0} is too large (max {1} bytes)")]
+  AttachmentTooLarge(String, usize),
+  #[error("Hardware factor: {0}")]
+  HardwareFactor(String),
+  #[error("Pepper: {0}")]
+  Pepper(String),
+  #[error("Secret exceeds capacity of {0} bytes")]
+  CapacityExceeded(usize),
+  #[error("Garbage collection refused: {0}")]
+  GarbageCollectionRefused(String),
+  #[error("Invalid OTP url: {0}")]
+  InvalidOtpUrl(String),
 }
 
 pub type SecretStoreResult<T> = Result<T, SecretStoreError>;
@@ -54,6 +69,7 @@ error_convert_from!(chacha20_poly1305_aead::DecryptError, SecretStoreError, Ciph
 error_convert_from!(capnp::NotInSchema, SecretStoreError, IO(display));
 error_convert_from!(serde_json::Error, SecretStoreError, Json(display));
 error_convert_from!(StoreError, SecretStoreError, BlockStore(direct));
+error_convert_from!(OTPError, SecretStoreError, InvalidOtpUrl(display));
 #[cfg(feature = "rust_crypto")]
 error_convert_from!(rsa::errors::Error, SecretStoreError, Cipher(display));
 #[cfg(feature = "rust_crypto")]
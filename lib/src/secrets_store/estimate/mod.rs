@@ -1,9 +1,29 @@
-use crate::api::PasswordStrength;
+use std::sync::Arc;
 
+use crate::api::{PasswordStrength, StrengthEstimatorConfig};
+use crate::secrets_store::SecretStoreResult;
+
+mod policy;
 mod zxcvbn;
 
+#[cfg(test)]
+mod policy_tests;
+
+pub use self::policy::*;
 pub use self::zxcvbn::*;
 
-pub trait PasswordEstimator {
-  fn estimate_strength(password: &str, user_inputs: &[&str]) -> PasswordStrength;
+pub trait PasswordEstimator: Send + Sync {
+  fn estimate_strength(&self, password: &str, user_inputs: &[&str]) -> PasswordStrength;
+}
+
+/// Create the password estimator of a store
+pub fn create_estimator(config: &StrengthEstimatorConfig) -> SecretStoreResult<Arc<dyn PasswordEstimator>> {
+  match config {
+    StrengthEstimatorConfig::Zxcvbn => Ok(Arc::new(ZxcvbnEstimator {})),
+    StrengthEstimatorConfig::Policy(policy) => Ok(Arc::new(PolicyEstimator::from_policy(policy)?)),
+    StrengthEstimatorConfig::Combined(policy) => Ok(Arc::new(CombinedEstimator::new(
+      ZxcvbnEstimator {},
+      PolicyEstimator::from_policy(policy)?,
+    ))),
+  }
 }
@@ -0,0 +1,166 @@
+use std::collections::HashSet;
+use std::fs;
+
+use super::PasswordEstimator;
+use crate::api::{PasswordPolicy, PasswordStrength};
+use crate::secrets_store::SecretStoreResult;
+
+/// Guesses per second assumed for the crack time (same as zxcvbn's offline fast hashing)
+const GUESSES_PER_SECOND: f64 = 1e10;
+/// Upper bounds (log2 of guesses) for the scores 0 to 3, taken from zxcvbn
+const SCORE_THRESHOLDS: [f64; 4] = [9.97, 19.93, 26.58, 33.22];
+
+/// Estimate the strength of passwords by the rules of a password policy.
+///
+/// Passwords violating any rule always have a score of 0, otherwise the score is derived from
+/// the (brute force) entropy of the character classes in use.
+pub struct PolicyEstimator {
+  min_length: usize,
+  min_char_classes: usize,
+  banned: HashSet<String>,
+}
+
+impl PolicyEstimator {
+  pub fn new<I, S>(min_length: usize, min_char_classes: usize, banned: I) -> PolicyEstimator
+  where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+  {
+    PolicyEstimator {
+      min_length,
+      min_char_classes,
+      banned: banned
+        .into_iter()
+        .map(|password| password.as_ref().trim().to_lowercase())
+        .filter(|password| !password.is_empty() && !password.starts_with('#'))
+        .collect(),
+    }
+  }
+
+  /// Create from the configuration of a store, the banned list is read from file
+  pub fn from_policy(policy: &PasswordPolicy) -> SecretStoreResult<PolicyEstimator> {
+    let banned = match &policy.banned_list_file {
+      Some(banned_list_file) => fs::read_to_string(banned_list_file)?,
+      None => String::new(),
+    };
+
+    Ok(Self::new(
+      policy.min_length as usize,
+      policy.min_char_classes as usize,
+      banned.lines(),
+    ))
+  }
+
+  /// Check if a password satisfies all rules
+  pub fn is_acceptable(&self, password: &str) -> bool {
+    password.chars().count() >= self.min_length
+      && char_classes(password).len() >= self.min_char_classes
+      && !self.banned.contains(&password.to_lowercase())
+  }
+}
+
+impl PasswordEstimator for PolicyEstimator {
+  fn estimate_strength(&self, password: &str, _user_inputs: &[&str]) -> PasswordStrength {
+    let pool_size: usize = char_classes(password).iter().map(CharClass::pool_size).sum();
+    let entropy = if pool_size > 0 {
+      password.chars().count() as f64 * (pool_size as f64).log2()
+    } else {
+      0.0
+    };
+    let crack_time = entropy.exp2() / GUESSES_PER_SECOND;
+    let score = if self.is_acceptable(password) {
+      SCORE_THRESHOLDS
+        .iter()
+        .position(|threshold| entropy < *threshold)
+        .unwrap_or(SCORE_THRESHOLDS.len()) as u8
+    } else {
+      0
+    };
+
+    PasswordStrength {
+      entropy,
+      crack_time,
+      crack_time_display: display_duration(crack_time),
+      score,
+    }
+  }
+}
+
+/// Use a primary estimator (usually zxcvbn), but passwords violating the policy have a score of 0
+pub struct CombinedEstimator<E> {
+  estimator: E,
+  policy: PolicyEstimator,
+}
+
+impl<E: PasswordEstimator> CombinedEstimator<E> {
+  pub fn new(estimator: E, policy: PolicyEstimator) -> CombinedEstimator<E> {
+    CombinedEstimator { estimator, policy }
+  }
+}
+
+impl<E: PasswordEstimator> PasswordEstimator for CombinedEstimator<E> {
+  fn estimate_strength(&self, password: &str, user_inputs: &[&str]) -> PasswordStrength {
+    let mut strength = self.estimator.estimate_strength(password, user_inputs);
+
+    if !self.policy.is_acceptable(password) {
+      strength.score = 0;
+    }
+
+    strength
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum CharClass {
+  Lower,
+  Upper,
+  Digit,
+  Other,
+}
+
+impl CharClass {
+  fn of(ch: char) -> CharClass {
+    if ch.is_lowercase() {
+      CharClass::Lower
+    } else if ch.is_uppercase() {
+      CharClass::Upper
+    } else if ch.is_numeric() {
+      CharClass::Digit
+    } else {
+      CharClass::Other
+    }
+  }
+
+  fn pool_size(&self) -> usize {
+    match self {
+      CharClass::Lower | CharClass::Upper => 26,
+      CharClass::Digit => 10,
+      CharClass::Other => 33,
+    }
+  }
+}
+
+fn char_classes(password: &str) -> HashSet<CharClass> {
+  password.chars().map(CharClass::of).collect()
+}
+
+fn display_duration(seconds: f64) -> String {
+  const UNITS: [(f64, &str); 5] = [
+    (60.0, "minutes"),
+    (3600.0, "hours"),
+    (86400.0, "days"),
+    (2_629_746.0, "months"),
+    (31_556_952.0, "years"),
+  ];
+
+  if seconds < 1.0 {
+    return "less than a second".to_string();
+  }
+  if seconds >= 100.0 * 31_556_952.0 {
+    return "centuries".to_string();
+  }
+  match UNITS.iter().rev().find(|(unit, _)| seconds >= *unit) {
+    Some((unit, name)) => format!("{} {}", (seconds / unit).round(), name),
+    None => format!("{} seconds", seconds.round()),
+  }
+}
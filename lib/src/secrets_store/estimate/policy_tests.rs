@@ -0,0 +1,92 @@
+use std::fs;
+
+use spectral::prelude::*;
+use tempfile::Builder;
+
+use super::{create_estimator, CombinedEstimator, PasswordEstimator, PolicyEstimator, ZxcvbnEstimator};
+use crate::api::{PasswordPolicy, StrengthEstimatorConfig};
+
+#[test]
+fn test_policy_min_length() {
+  let estimator = PolicyEstimator::new(12, 0, Vec::<String>::new());
+
+  assert_that(&estimator.is_acceptable("Sh0rt!")).is_false();
+  assert_that(&estimator.estimate_strength("Sh0rt!", &[]).score).is_equal_to(0);
+  assert_that(&estimator.is_acceptable("Long enough, 12 chars")).is_true();
+  assert_that(&estimator.estimate_strength("Long enough, 12 chars", &[]).score).is_equal_to(4);
+}
+
+#[test]
+fn test_policy_char_classes() {
+  let estimator = PolicyEstimator::new(0, 3, Vec::<String>::new());
+
+  assert_that(&estimator.is_acceptable("onlylowercaseletters")).is_false();
+  assert_that(&estimator.estimate_strength("onlylowercaseletters", &[]).score).is_equal_to(0);
+  assert_that(&estimator.is_acceptable("LowerAndUpper")).is_false();
+  assert_that(&estimator.is_acceptable("Lower and Upper")).is_true();
+  assert_that(&estimator.is_acceptable("digits4and0lower")).is_false();
+  assert_that(&estimator.is_acceptable("Digits4andUpper")).is_true();
+}
+
+#[test]
+fn test_policy_entropy() {
+  let estimator = PolicyEstimator::new(0, 0, Vec::<String>::new());
+
+  assert_that(&estimator.estimate_strength("", &[]).entropy).is_equal_to(0.0);
+  assert_that(&(estimator.estimate_strength("abcd", &[]).entropy - 4.0 * 26f64.log2()).abs()).is_less_than(1e-9);
+  assert_that(&estimator.estimate_strength("abcd", &[]).score).is_equal_to(1);
+  assert_that(&(estimator.estimate_strength("aB3$", &[]).entropy - 4.0 * 95f64.log2()).abs()).is_less_than(1e-9);
+}
+
+#[test]
+fn test_policy_banned_list() {
+  let dir = Builder::new().prefix("t-rust-less-policy").tempdir().unwrap();
+  let banned_list_file = dir.path().join("banned.txt");
+  fs::write(
+    &banned_list_file,
+    "# common passwords\n\nCorrectHorseBatteryStaple\n  Tr0ub4dor&3  \n",
+  )
+  .unwrap();
+
+  let config = StrengthEstimatorConfig::Policy(PasswordPolicy {
+    min_length: 8,
+    min_char_classes: 2,
+    banned_list_file: Some(banned_list_file.to_string_lossy().to_string()),
+  });
+  let estimator = create_estimator(&config).unwrap();
+
+  assert_that(&estimator.estimate_strength("correcthorsebatterystaple", &[]).score).is_equal_to(0);
+  assert_that(&estimator.estimate_strength("CORRECTHORSEBATTERYSTAPLE", &[]).score).is_equal_to(0);
+  assert_that(&estimator.estimate_strength("tr0ub4dor&3", &[]).score).is_equal_to(0);
+  assert_that(&estimator.estimate_strength("# common passwords", &[]).score).is_greater_than(0);
+  assert_that(&estimator.estimate_strength("CorrectHorseBatteryStable", &[]).score).is_greater_than(0);
+}
+
+#[test]
+fn test_policy_missing_banned_list() {
+  let config = StrengthEstimatorConfig::Policy(PasswordPolicy {
+    min_length: 8,
+    min_char_classes: 2,
+    banned_list_file: Some("/does/not/exist/banned.txt".to_string()),
+  });
+
+  assert_that(&create_estimator(&config).is_err()).is_true();
+}
+
+#[test]
+fn test_combined() {
+  let zxcvbn = ZxcvbnEstimator {};
+  let estimator = CombinedEstimator::new(ZxcvbnEstimator {}, PolicyEstimator::new(30, 0, Vec::<String>::new()));
+  let password = "5w@ll0w.Fl1ght-Gr33n";
+  let expected = zxcvbn.estimate_strength(password, &[]);
+  let actual = estimator.estimate_strength(password, &[]);
+
+  assert_that(&expected.score).is_greater_than(0);
+  assert_that(&actual.score).is_equal_to(0);
+  assert_that(&actual.entropy).is_equal_to(expected.entropy);
+  assert_that(&actual.crack_time_display).is_equal_to(&expected.crack_time_display);
+
+  let estimator = CombinedEstimator::new(ZxcvbnEstimator {}, PolicyEstimator::new(8, 3, Vec::<String>::new()));
+
+  assert_that(&estimator.estimate_strength(password, &[]).score).is_equal_to(expected.score);
+}
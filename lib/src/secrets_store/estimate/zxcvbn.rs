@@ -6,7 +6,7 @@ use zxcvbn::ZxcvbnError;
 pub struct ZxcvbnEstimator {}
 
 impl PasswordEstimator for ZxcvbnEstimator {
-  fn estimate_strength(password: &str, user_inputs: &[&str]) -> PasswordStrength {
+  fn estimate_strength(&self, password: &str, user_inputs: &[&str]) -> PasswordStrength {
     match zxcvbn::zxcvbn(password, user_inputs) {
       Ok(entropy) => PasswordStrength {
         entropy: (entropy.guesses() as f64).log2(),
@@ -0,0 +1,155 @@
+/// Score of every matched char.
+const SCORE_MATCH: isize = 16;
+/// Penalty for the first skipped char between two matches.
+const SCORE_GAP_START: isize = -3;
+/// Penalty for every further skipped char between two matches.
+const SCORE_GAP_EXTENSION: isize = -1;
+/// Bonus for a match at the start of a word (i.e. after whitespace, punctuation or at the start of the text).
+const BONUS_BOUNDARY: isize = 8;
+/// Bonus for a match at a camel case (or letter to digit) transition.
+const BONUS_CAMEL: isize = 7;
+/// Minimum bonus for a match directly following the previous match.
+const BONUS_CONSECUTIVE: isize = 4;
+/// The bonus of the first char of the pattern is multiplied by this (i.e. "where does the match start" matters most).
+const BONUS_FIRST_CHAR_MULTIPLIER: isize = 2;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+  NonWord,
+  Lower,
+  Upper,
+  Digit,
+}
+
+impl CharClass {
+  fn of(ch: char) -> CharClass {
+    if ch.is_lowercase() {
+      CharClass::Lower
+    } else if ch.is_uppercase() {
+      CharClass::Upper
+    } else if ch.is_numeric() {
+      CharClass::Digit
+    } else if ch.is_alphanumeric() {
+      // Letters without case (e.g. CJK) are treated like lower case letters
+      CharClass::Lower
+    } else {
+      CharClass::NonWord
+    }
+  }
+
+  fn bonus(prev: CharClass, current: CharClass) -> isize {
+    match (prev, current) {
+      (_, CharClass::NonWord) => 0,
+      (CharClass::NonWord, _) => BONUS_BOUNDARY,
+      (CharClass::Lower, CharClass::Upper) => BONUS_CAMEL,
+      (CharClass::Lower, CharClass::Digit) | (CharClass::Upper, CharClass::Digit) => BONUS_CAMEL,
+      _ => 0,
+    }
+  }
+}
+
+fn chars_match(pattern_ch: char, text_ch: char) -> bool {
+  pattern_ch == text_ch || pattern_ch.to_lowercase().eq(text_ch.to_lowercase())
+}
+
+/// Match `pattern` as (case insensitive) subsequence of `text`.
+///
+/// Similar to fzf the score rewards consecutive matches and matches at the start of words (or camel
+/// case humps) and penalizes gaps between matches. Of all possible alignments the one with the
+/// best score is chosen, if there are several the one matching first wins.
+///
+/// The result is the score and the indices (in chars) of all matched chars in `text`, or `None` if
+/// `pattern` is not a subsequence of `text` at all.
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<(isize, Vec<usize>)> {
+  let pattern: Vec<char> = pattern.chars().collect();
+  let text: Vec<char> = text.chars().collect();
+
+  if pattern.is_empty() {
+    return Some((0, vec![]));
+  }
+  if pattern.len() > text.len() {
+    return None;
+  }
+
+  let mut prev_class = CharClass::NonWord;
+  let bonus: Vec<isize> = text
+    .iter()
+    .map(|ch| {
+      let class = CharClass::of(*ch);
+      let bonus = CharClass::bonus(prev_class, class);
+      prev_class = class;
+      bonus
+    })
+    .collect();
+
+  // scores[i][j]: best score with pattern[..=i] matched and pattern[i] matched at text[j]
+  // consecutive[i][j]: the bonus of the consecutive chunk pattern[i] belongs to (if any)
+  // predecessor[i][j]: where pattern[i - 1] has been matched in the best alignment
+  let mut scores = vec![vec![None::<isize>; text.len()]; pattern.len()];
+  let mut chunk_bonus = vec![vec![0isize; text.len()]; pattern.len()];
+  let mut predecessor = vec![vec![0usize; text.len()]; pattern.len()];
+
+  for (j, text_ch) in text.iter().enumerate() {
+    if chars_match(pattern[0], *text_ch) {
+      scores[0][j] = Some(SCORE_MATCH + bonus[j] * BONUS_FIRST_CHAR_MULTIPLIER);
+      chunk_bonus[0][j] = bonus[j];
+    }
+  }
+
+  for i in 1..pattern.len() {
+    // Best alignment of pattern[i - 1] with at least one skipped char up to the current column.
+    // All candidates lose the same amount per column, so the best one remains the best.
+    let mut best_gapped: Option<(isize, usize)> = None;
+
+    for j in i..text.len() {
+      best_gapped = best_gapped.map(|(score, k)| (score + SCORE_GAP_EXTENSION, k));
+      if j >= 2 {
+        if let Some(score) = scores[i - 1][j - 2] {
+          let candidate = score + SCORE_GAP_START;
+          if best_gapped.map(|(best, _)| candidate > best).unwrap_or(true) {
+            best_gapped = Some((candidate, j - 2));
+          }
+        }
+      }
+
+      if !chars_match(pattern[i], text[j]) {
+        continue;
+      }
+
+      let gapped = best_gapped.map(|(score, k)| (score + SCORE_MATCH + bonus[j], k, bonus[j]));
+      let consecutive = scores[i - 1][j - 1].map(|score| {
+        let chunk = chunk_bonus[i - 1][j - 1].max(bonus[j]).max(BONUS_CONSECUTIVE);
+        (score + SCORE_MATCH + chunk, j - 1, chunk)
+      });
+      let best = match (gapped, consecutive) {
+        (Some(gapped), Some(consecutive)) if gapped.0 > consecutive.0 => Some(gapped),
+        (_, Some(consecutive)) => Some(consecutive),
+        (gapped, None) => gapped,
+      };
+
+      if let Some((score, k, chunk)) = best {
+        scores[i][j] = Some(score);
+        chunk_bonus[i][j] = chunk;
+        predecessor[i][j] = k;
+      }
+    }
+  }
+
+  let last = pattern.len() - 1;
+  let (mut position, score) = scores[last]
+    .iter()
+    .enumerate()
+    .filter_map(|(j, score)| score.map(|score| (j, score)))
+    .fold(None, |best: Option<(usize, isize)>, (j, score)| match best {
+      Some((_, best_score)) if best_score >= score => best,
+      _ => Some((j, score)),
+    })?;
+
+  let mut highlights = vec![0; pattern.len()];
+  for i in (0..pattern.len()).rev() {
+    highlights[i] = position;
+    position = predecessor[i][position];
+  }
+
+  Some((score, highlights))
+}
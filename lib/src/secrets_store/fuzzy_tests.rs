@@ -0,0 +1,37 @@
+use spectral::prelude::*;
+
+use super::fuzzy::fuzzy_match;
+
+fn score(pattern: &str, text: &str) -> isize {
+  fuzzy_match(pattern, text).map(|(score, _)| score).unwrap()
+}
+
+#[test]
+fn test_subsequence() {
+  assert_that(&fuzzy_match("", "GitHub")).contains_value((0, vec![]));
+  assert_that(&fuzzy_match("gh", "GitHub").map(|(_, highlights)| highlights)).contains_value(vec![0, 3]);
+  assert_that(&fuzzy_match("GH", "github").map(|(_, highlights)| highlights)).contains_value(vec![0, 3]);
+  assert_that(&fuzzy_match("hg", "GitHub")).is_none();
+  assert_that(&fuzzy_match("githubs", "GitHub")).is_none();
+  assert_that(&fuzzy_match("x", "")).is_none();
+}
+
+#[test]
+fn test_highlights_prefer_boundaries() {
+  // "b" at the start of "bank" beats the first "b" of "Hobby"
+  assert_that(&fuzzy_match("hb", "Hobby bank").map(|(_, highlights)| highlights)).contains_value(vec![0, 6]);
+  // Consecutive matches beat scattered ones
+  assert_that(&fuzzy_match("mail", "my amazing mailbox").map(|(_, highlights)| highlights))
+    .contains_value(vec![11, 12, 13, 14]);
+  // Highlights are char indices, not byte indices
+  assert_that(&fuzzy_match("bk", "Über Bank").map(|(_, highlights)| highlights)).contains_value(vec![5, 8]);
+}
+
+#[test]
+fn test_ranking() {
+  assert_that(&score("gh", "GitHub")).is_greater_than(score("gh", "Lighthouse"));
+  assert_that(&score("gh", "github")).is_greater_than(score("gh", "Lighthouse"));
+  assert_that(&score("mail", "Mail")).is_greater_than(score("mail", "Gmail"));
+  assert_that(&score("bank", "My Bank")).is_greater_than(score("bank", "Bbaannkk"));
+  assert_that(&score("ab", "a_b")).is_greater_than(score("ab", "axxb"));
+}
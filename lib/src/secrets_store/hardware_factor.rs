@@ -0,0 +1,237 @@
+use std::io::Write;
+
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+use crate::memguard::SecretBytes;
+use crate::secrets_store::{SecretStoreError, SecretStoreResult};
+
+/// Length of the salt used to challenge a hardware authenticator
+pub const HARDWARE_SALT_LENGTH: usize = 32;
+
+const HKDF_INFO: &[u8] = b"t-rust-less hardware factor seal key";
+
+/// A hardware token that is able to derive a secret response for a (credential, salt) pair,
+/// e.g. the `hmac-secret` extension of a FIDO2 authenticator or a YubiKey challenge-response slot.
+///
+/// The response has to be deterministic, i.e. the same credential and salt always have to produce
+/// the same response, since it is part of the seal key of the private keys.
+pub trait HardwareAuthenticator: Send + Sync {
+  /// Create a new credential for an identity, returns the credential id
+  fn register(&self, identity_id: &str) -> SecretStoreResult<Vec<u8>>;
+
+  /// Get the response for a `salt` from the credential
+  fn challenge(&self, credential_id: &[u8], salt: &[u8]) -> SecretStoreResult<SecretBytes>;
+}
+
+/// Combine the seal key derived from the passphrase with the response of the hardware
+/// authenticator (HKDF-SHA256 with the nonce of the private key as salt).
+pub fn combine_seal_key(
+  passphrase_key: &SecretBytes,
+  hardware_response: &SecretBytes,
+  nonce: &[u8],
+  length: usize,
+) -> SecretStoreResult<SecretBytes> {
+  let mut input_key = SecretBytes::with_capacity(passphrase_key.len() + hardware_response.len());
+  {
+    let mut input_key_mut = input_key.borrow_mut();
+    input_key_mut.write_all(&passphrase_key.borrow())?;
+    input_key_mut.write_all(&hardware_response.borrow())?;
+  }
+  let hkdf = Hkdf::<Sha256>::new(Some(nonce), &input_key.borrow());
+  let mut seal_key = SecretBytes::zeroed(length);
+
+  hkdf
+    .expand(HKDF_INFO, &mut seal_key.borrow_mut())
+    .map_err(|_| SecretStoreError::KeyDerivation("Seal key too long".to_string()))?;
+
+  Ok(seal_key)
+}
+
+#[cfg(feature = "with_fido2")]
+pub use self::fido2::Fido2Authenticator;
+#[cfg(all(test, feature = "with_fido2"))]
+pub(super) use self::fido2::{parse_credential, parse_hmac_secret};
+
+#[cfg(feature = "with_fido2")]
+mod fido2 {
+  use std::io::Write;
+  use std::process::{Command, Stdio};
+
+  use data_encoding::BASE64;
+  use rand::{thread_rng, RngCore};
+
+  use super::HardwareAuthenticator;
+  use crate::memguard::SecretBytes;
+  use crate::secrets_store::{SecretStoreError, SecretStoreResult};
+
+  const RELYING_PARTY: &str = "t-rust-less";
+
+  /// Length of the output of the `hmac-secret` extension for a single salt
+  const HMAC_SECRET_LENGTH: usize = 32;
+
+  /// FIDO2 authenticator using the `hmac-secret` extension.
+  ///
+  /// This relies on the `fido2-token`, `fido2-cred` and `fido2-assert` tools of libfido2, which
+  /// take care of device access (and user interaction like touching the device).
+  #[derive(Default)]
+  pub struct Fido2Authenticator {
+    device: Option<String>,
+  }
+
+  impl Fido2Authenticator {
+    pub fn with_device(device: &str) -> Self {
+      Fido2Authenticator {
+        device: Some(device.to_string()),
+      }
+    }
+
+    fn device(&self) -> SecretStoreResult<String> {
+      if let Some(device) = &self.device {
+        return Ok(device.clone());
+      }
+      let output = Command::new("fido2-token").arg("-L").output()?;
+      String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split(": ").next())
+        .map(str::to_string)
+        .find(|device| !device.is_empty())
+        .ok_or_else(|| SecretStoreError::HardwareFactor("No FIDO2 device found".to_string()))
+    }
+
+    /// Run one of the fido2 tools, the output is kept as `SecretBytes` since it might contain the
+    /// `hmac-secret`.
+    fn run_tool(&self, tool: &str, args: &[&str], input: &str) -> SecretStoreResult<SecretBytes> {
+      let device = self.device()?;
+      let mut child = Command::new(tool)
+        .args(args)
+        .arg(&device)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+      child
+        .stdin
+        .take()
+        .ok_or_else(|| SecretStoreError::HardwareFactor(format!("{} without stdin", tool)))?
+        .write_all(input.as_bytes())?;
+      let output = child.wait_with_output()?;
+      let status = output.status;
+      let stdout = SecretBytes::from(output.stdout);
+      if !status.success() {
+        return Err(SecretStoreError::HardwareFactor(format!("{} failed: {}", tool, status)));
+      }
+
+      Ok(stdout)
+    }
+  }
+
+  fn client_data_hash() -> String {
+    let mut hash = [0u8; 32];
+    thread_rng().fill_bytes(&mut hash);
+    BASE64.encode(&hash)
+  }
+
+  fn output_lines(output: &[u8]) -> Vec<&[u8]> {
+    output
+      .split(|b| *b == b'\n')
+      .map(<[u8]>::trim_ascii)
+      .filter(|line| !line.is_empty())
+      .collect()
+  }
+
+  /// The relying party is the only plain text line of the output, if it is not where it is expected the
+  /// output format of the tool is not the one we know.
+  fn check_relying_party(lines: &[&[u8]], tool: &str) -> SecretStoreResult<()> {
+    match lines.get(1) {
+      Some(relying_party) if *relying_party == RELYING_PARTY.as_bytes() => Ok(()),
+      _ => Err(SecretStoreError::HardwareFactor(format!(
+        "Unexpected output of {} (relying party not found)",
+        tool
+      ))),
+    }
+  }
+
+  fn decode_secret(line: &[u8], what: &str) -> SecretStoreResult<SecretBytes> {
+    let invalid = || SecretStoreError::HardwareFactor(format!("Invalid {} in response", what));
+    let mut decoded = SecretBytes::zeroed(BASE64.decode_len(line.len()).map_err(|_| invalid())?);
+    let length = BASE64
+      .decode_mut(line, &mut decoded.borrow_mut())
+      .map_err(|_| invalid())?;
+    decoded.set_len(length)?;
+
+    Ok(decoded)
+  }
+
+  /// Credential id from the output of `fido2-cred -M`: client data hash, relying party, format,
+  /// authenticator data, credential id, signature and (optionally) certificate.
+  pub(in crate::secrets_store) fn parse_credential(output: &SecretBytes) -> SecretStoreResult<Vec<u8>> {
+    let borrowed = output.borrow();
+    let lines = output_lines(&borrowed);
+    check_relying_party(&lines, "fido2-cred")?;
+    if lines.len() < 6 {
+      return Err(SecretStoreError::HardwareFactor(
+        "Unexpected output of fido2-cred (credential id not found)".to_string(),
+      ));
+    }
+    let credential_id = BASE64
+      .decode(lines[4])
+      .map_err(|_| SecretStoreError::HardwareFactor("Invalid credential id in response".to_string()))?;
+    if credential_id.is_empty() {
+      return Err(SecretStoreError::HardwareFactor(
+        "Empty credential id in response".to_string(),
+      ));
+    }
+
+    Ok(credential_id)
+  }
+
+  /// `hmac-secret` from the output of `fido2-assert -G -h`: client data hash, relying party,
+  /// authenticator data, signature, (optionally) user id and the `hmac-secret` as last line.
+  pub(in crate::secrets_store) fn parse_hmac_secret(output: &SecretBytes) -> SecretStoreResult<SecretBytes> {
+    let borrowed = output.borrow();
+    let lines = output_lines(&borrowed);
+    check_relying_party(&lines, "fido2-assert")?;
+    if lines.len() < 5 {
+      return Err(SecretStoreError::HardwareFactor(
+        "Unexpected output of fido2-assert (hmac-secret not found)".to_string(),
+      ));
+    }
+    let hmac_secret = decode_secret(lines[lines.len() - 1], "hmac-secret")?;
+    if hmac_secret.len() != HMAC_SECRET_LENGTH {
+      return Err(SecretStoreError::HardwareFactor(format!(
+        "Unexpected length of hmac-secret in response: {}",
+        hmac_secret.len()
+      )));
+    }
+
+    Ok(hmac_secret)
+  }
+
+  impl HardwareAuthenticator for Fido2Authenticator {
+    fn register(&self, identity_id: &str) -> SecretStoreResult<Vec<u8>> {
+      let input = format!(
+        "{}\n{}\n{}\n{}\n",
+        client_data_hash(),
+        RELYING_PARTY,
+        identity_id,
+        BASE64.encode(identity_id.as_bytes())
+      );
+      let output = self.run_tool("fido2-cred", &["-M", "-h"], &input)?;
+
+      parse_credential(&output)
+    }
+
+    fn challenge(&self, credential_id: &[u8], salt: &[u8]) -> SecretStoreResult<SecretBytes> {
+      let input = format!(
+        "{}\n{}\n{}\n{}\n",
+        client_data_hash(),
+        RELYING_PARTY,
+        BASE64.encode(credential_id),
+        BASE64.encode(salt)
+      );
+      let output = self.run_tool("fido2-assert", &["-G", "-h"], &input)?;
+
+      parse_hmac_secret(&output)
+    }
+  }
+}
@@ -0,0 +1,196 @@
+use super::hardware_factor::{combine_seal_key, HardwareAuthenticator};
+use super::multi_lane::MultiLaneSecretsStore;
+use super::{SecretStoreError, SecretStoreResult, SecretsStore};
+use crate::api::{EventData, EventHub, Identity};
+use crate::block_store::{open_block_store, BlockStore};
+use crate::memguard::SecretBytes;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use spectral::prelude::*;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Mock of a hardware token: The response is a HMAC of the salt with a device secret
+struct MockAuthenticator {
+  device_secret: Vec<u8>,
+  credentials: Mutex<Vec<Vec<u8>>>,
+}
+
+impl MockAuthenticator {
+  fn new(device_secret: &[u8]) -> MockAuthenticator {
+    MockAuthenticator {
+      device_secret: device_secret.to_vec(),
+      credentials: Mutex::new(vec![]),
+    }
+  }
+}
+
+impl HardwareAuthenticator for MockAuthenticator {
+  fn register(&self, identity_id: &str) -> SecretStoreResult<Vec<u8>> {
+    let credential_id = format!("credential-{}", identity_id).into_bytes();
+
+    self.credentials.lock()?.push(credential_id.clone());
+
+    Ok(credential_id)
+  }
+
+  fn challenge(&self, credential_id: &[u8], salt: &[u8]) -> SecretStoreResult<SecretBytes> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(&self.device_secret).unwrap();
+    mac.update(credential_id);
+    mac.update(salt);
+
+    Ok(SecretBytes::from(mac.finalize().into_bytes().to_vec()))
+  }
+}
+
+struct TestEventHub;
+
+impl EventHub for TestEventHub {
+  fn send(&self, _event: EventData) {}
+}
+
+fn secrets_store(
+  block_store: Arc<dyn BlockStore>,
+  maybe_authenticator: Option<Arc<dyn HardwareAuthenticator>>,
+) -> MultiLaneSecretsStore {
+  let secrets_store =
+    MultiLaneSecretsStore::new("test", block_store, Duration::from_secs(300), 0, Arc::new(TestEventHub));
+
+  match maybe_authenticator {
+    Some(authenticator) => secrets_store.with_hardware_authenticator(authenticator),
+    None => secrets_store,
+  }
+}
+
+fn secret_from_str(s: &str) -> SecretBytes {
+  SecretBytes::from(s.as_bytes().to_vec())
+}
+
+#[test]
+fn test_combine_seal_key() {
+  let passphrase_key = secret_from_str("passphrase key");
+  let response1 = secret_from_str("response1");
+  let response2 = secret_from_str("response2");
+
+  let key1 = combine_seal_key(&passphrase_key, &response1, b"nonce", 80).unwrap();
+  let key2 = combine_seal_key(&passphrase_key, &response2, b"nonce", 80).unwrap();
+  let key3 = combine_seal_key(&passphrase_key, &response1, b"other nonce", 80).unwrap();
+
+  assert_that(&key1.len()).is_equal_to(80);
+  // Seal keys of existing identities must not change
+  assert_that(&key1.borrow().as_bytes()).is_equal_to(
+    &hex!(
+      "7ca8f8771eca35b8403ba332f7e9fff593fa0400cd52cd2f6c7ded885c15e56554c5219d51ba27ede022cbf2a176a415"
+      "600f2021bd1820506c498e98d55e2dfd5e153e9a99d9334c23cee1e0c13fce0f"
+    )[..],
+  );
+  assert_that(&combine_seal_key(&passphrase_key, &response1, b"nonce", 80).unwrap()).is_equal_to(&key1);
+  assert_that(
+    &combine_seal_key(&passphrase_key, &response1, b"nonce", 32)
+      .unwrap()
+      .borrow()
+      .as_bytes(),
+  )
+  .is_equal_to(&key1.borrow().as_bytes()[0..32]);
+  assert_that(&key2).is_not_equal_to(&key1);
+  assert_that(&key3).is_not_equal_to(&key1);
+}
+
+#[test]
+#[cfg_attr(debug_assertions, ignore)]
+fn test_hardware_factor_unlock() {
+  let block_store = open_block_store("memory://", "node1").unwrap();
+  let authenticator = Arc::new(MockAuthenticator::new(b"device1"));
+  let store = secrets_store(block_store.clone(), Some(authenticator.clone()));
+  let identity = Identity {
+    id: "identity1".to_string(),
+    name: "Name1".to_string(),
+    email: "Email1".to_string(),
+    hidden: false,
+    hardware_factor: true,
+  };
+
+  store
+    .add_identity(identity.clone(), secret_from_str("Passphrase1"))
+    .unwrap();
+
+  assert_that(&*authenticator.credentials.lock().unwrap()).is_equal_to(vec![b"credential-identity1".to_vec()]);
+  assert_that(&store.identities().unwrap()).is_equal_to(vec![identity]);
+
+  assert_that(&store.unlock("identity1", secret_from_str("Passphrase2")))
+    .is_err_containing(SecretStoreError::InvalidPassphrase);
+  store.unlock("identity1", secret_from_str("Passphrase1")).unwrap();
+
+  store.change_passphrase(secret_from_str("Passphrase1abc")).unwrap();
+  store.lock().unwrap();
+
+  assert_that(&store.unlock("identity1", secret_from_str("Passphrase1")))
+    .is_err_containing(SecretStoreError::InvalidPassphrase);
+  store.unlock("identity1", secret_from_str("Passphrase1abc")).unwrap();
+  store.lock().unwrap();
+
+  // Correct passphrase, but a different device
+  let other_store = secrets_store(block_store.clone(), Some(Arc::new(MockAuthenticator::new(b"device2"))));
+
+  assert_that(&other_store.unlock("identity1", secret_from_str("Passphrase1abc")))
+    .is_err_containing(SecretStoreError::InvalidPassphrase);
+
+  // Correct passphrase, but no device at all
+  let no_device_store = secrets_store(block_store, None);
+
+  assert_that(&no_device_store.unlock("identity1", secret_from_str("Passphrase1abc"))).is_err();
+}
+
+#[cfg(feature = "with_fido2")]
+#[test]
+fn test_parse_fido2_output() {
+  use super::hardware_factor::{parse_credential, parse_hmac_secret};
+  use data_encoding::BASE64;
+
+  let credential = SecretBytes::from(format!(
+    "{}\nt-rust-less\npacked\n{}\n{}\n{}\n",
+    BASE64.encode(&[1u8; 32]),
+    BASE64.encode(&[2u8; 37]),
+    BASE64.encode(b"credential"),
+    BASE64.encode(&[3u8; 70]),
+  ));
+
+  assert_that(&parse_credential(&credential)).is_ok_containing(b"credential".to_vec());
+
+  let assertion = SecretBytes::from(format!(
+    "{}\nt-rust-less\n{}\n{}\n{}\n",
+    BASE64.encode(&[1u8; 32]),
+    BASE64.encode(&[2u8; 37]),
+    BASE64.encode(&[3u8; 70]),
+    BASE64.encode(&[4u8; 32]),
+  ));
+
+  assert_that(&parse_hmac_secret(&assertion).unwrap().borrow().as_bytes()).is_equal_to(&[4u8; 32][..]);
+
+  // Other relying party, missing or invalid hmac-secret
+  let other_relying_party = SecretBytes::from(format!(
+    "{}\nother\n{}\n{}\n{}\n",
+    BASE64.encode(&[1u8; 32]),
+    BASE64.encode(&[2u8; 37]),
+    BASE64.encode(&[3u8; 70]),
+    BASE64.encode(&[4u8; 32]),
+  ));
+  let missing = SecretBytes::from(format!(
+    "{}\nt-rust-less\n{}\n{}\n",
+    BASE64.encode(&[1u8; 32]),
+    BASE64.encode(&[2u8; 37]),
+    BASE64.encode(&[3u8; 70]),
+  ));
+  let too_short = SecretBytes::from(format!(
+    "{}\nt-rust-less\n{}\n{}\n{}\n",
+    BASE64.encode(&[1u8; 32]),
+    BASE64.encode(&[2u8; 37]),
+    BASE64.encode(&[3u8; 70]),
+    BASE64.encode(&[4u8; 16]),
+  ));
+
+  assert_that(&parse_hmac_secret(&other_relying_party)).is_err();
+  assert_that(&parse_hmac_secret(&missing)).is_err();
+  assert_that(&parse_hmac_secret(&too_short)).is_err();
+  assert_that(&parse_credential(&missing)).is_err();
+}
@@ -1,17 +1,28 @@
-use crate::api::{SecretEntry, SecretEntryMatch, SecretList, SecretListFilter, SecretVersion, SecretVersionRef};
+use crate::api::{
+  SecretEntry, SecretEntryMatch, SecretList, SecretListFilter, SecretVersion, SecretVersionRef, TagMatch, TagTree,
+};
 use crate::block_store::{Change, ChangeLog, Operation};
 use crate::memguard::weak::ZeroingHeapAllocator;
 use crate::memguard::SecretWords;
+use crate::secrets_store::fuzzy::fuzzy_match;
 use crate::secrets_store::{SecretStoreError, SecretStoreResult};
 use crate::secrets_store_capnp::{index, secret_entry};
 use capnp::{message, serialize};
 use itertools::Itertools;
+use log::{debug, info};
 use std::collections::{BTreeSet, HashMap, HashSet};
 
+/// Version of the index format.
+///
+/// Stored indexes with a different version are discarded and rebuilt from scratch, i.e. this has to
+/// be increased whenever the content of an index entry changes.
+pub const INDEX_VERSION: u16 = 1;
+
 struct EffectiveChanges {
   new_heads: HashMap<String, Change>,
   added_versions: HashMap<String, HashMap<String, SecretVersion>>,
   deleted_blocks: HashSet<String>,
+  unavailable_blocks: BTreeSet<String>,
 }
 
 impl EffectiveChanges {
@@ -23,15 +34,41 @@ impl EffectiveChanges {
 #[derive(Clone)]
 pub struct Index {
   heads: HashMap<String, Change>,
+  unavailable_blocks: BTreeSet<String>,
   pub(super) data: SecretWords,
 }
 
 impl Index {
   pub fn from_secured_raw(raw: &[u8]) -> SecretStoreResult<Index> {
     let data = SecretWords::from_secured(raw);
+    let version = {
+      let mut data_borrow: &[u8] = &data.borrow();
+      let reader = serialize::read_message_from_flat_slice(&mut data_borrow, message::ReaderOptions::new())?;
+      reader.get_root::<index::Reader>()?.get_version()
+    };
+    if version != INDEX_VERSION {
+      info!(
+        "Index version {} does not match {}, full rebuild",
+        version, INDEX_VERSION
+      );
+      return Ok(Default::default());
+    }
     let heads = Self::read_heads(&data)?;
+    Self::check_entries(&data)?;
 
-    Ok(Index { heads, data })
+    Ok(Index {
+      heads,
+      unavailable_blocks: BTreeSet::new(),
+      data,
+    })
+  }
+
+  /// Check if there were blocks that could not be read during the last update.
+  ///
+  /// This is usually the case if the store is only partially synchronized (i.e. the change log
+  /// is already there, but the blocks are not yet). Blocks in question are retried on every update.
+  pub fn has_unavailable_blocks(&self) -> bool {
+    !self.unavailable_blocks.is_empty()
   }
 
   pub fn find_versions(&self, secret_id: &str) -> SecretStoreResult<Vec<SecretVersionRef>> {
@@ -53,6 +90,38 @@ impl Index {
     Err(SecretStoreError::NotFound)
   }
 
+  /// Block ids of the current versions of all secrets in the index.
+  pub fn current_block_ids(&self) -> SecretStoreResult<Vec<String>> {
+    let mut data_borrow: &[u8] = &self.data.borrow();
+    let reader = serialize::read_message_from_flat_slice(&mut data_borrow, message::ReaderOptions::new())?;
+    let index = reader.get_root::<index::Reader>()?;
+    let mut block_ids = Vec::new();
+
+    for index_entry in index.get_entries()? {
+      if let Some(version_ref) = index_entry.get_version_refs()?.iter().next() {
+        block_ids.push(version_ref.get_block_id()?.to_string()?);
+      }
+    }
+
+    Ok(block_ids)
+  }
+
+  /// Block ids of all versions of all secrets in the index.
+  pub fn block_ids(&self) -> SecretStoreResult<Vec<String>> {
+    let mut data_borrow: &[u8] = &self.data.borrow();
+    let reader = serialize::read_message_from_flat_slice(&mut data_borrow, message::ReaderOptions::new())?;
+    let index = reader.get_root::<index::Reader>()?;
+    let mut block_ids = Vec::new();
+
+    for index_entry in index.get_entries()? {
+      for version_ref in index_entry.get_version_refs()? {
+        block_ids.push(version_ref.get_block_id()?.to_string()?);
+      }
+    }
+
+    Ok(block_ids)
+  }
+
   pub fn filter_entries(&self, filter: &SecretListFilter) -> SecretStoreResult<SecretList> {
     let mut data_borrow: &[u8] = &self.data.borrow();
     let reader = serialize::read_message_from_flat_slice(&mut data_borrow, message::ReaderOptions::new())?;
@@ -73,10 +142,19 @@ impl Index {
       }
     }
     entries.sort();
+    let total = entries.len();
+    let tag_tree = if filter.group_by_tag {
+      Some(TagTree::build(&entries))
+    } else {
+      None
+    };
 
     Ok(SecretList {
       all_tags: all_tags.into_iter().collect(),
       entries,
+      unavailable_blocks: self.unavailable_blocks.iter().cloned().collect(),
+      tag_tree,
+      total,
     })
   }
 
@@ -84,7 +162,9 @@ impl Index {
   where
     F: Fn(&str) -> SecretStoreResult<Option<SecretVersion>>,
   {
-    let effective_changes = self.collect_changes(change_logs, &version_accessor)?;
+    let mut effective_changes = self.collect_changes(change_logs, &version_accessor)?;
+
+    self.unavailable_blocks = std::mem::take(&mut effective_changes.unavailable_blocks);
 
     if effective_changes.is_empty() {
       return Ok(false); // No change that affects us
@@ -103,6 +183,7 @@ impl Index {
       let old_index = reader.get_root::<index::Reader>()?;
       let mut new_index = index_message.init_root::<index::Builder>();
 
+      new_index.set_version(INDEX_VERSION);
       Self::update_heads(new_index.reborrow(), &effective_changes.new_heads);
       let mut entry_pos = 0;
       let mut new_entries = new_index.init_entries((to_keep.len() + additions) as u32);
@@ -168,6 +249,22 @@ impl Index {
     Ok(heads)
   }
 
+  /// Read all entries once, so that a corrupted index is detected on load rather than on first use.
+  fn check_entries(index_data: &SecretWords) -> SecretStoreResult<()> {
+    let mut index_borrow: &[u8] = &index_data.borrow();
+    let reader = serialize::read_message_from_flat_slice(&mut index_borrow, message::ReaderOptions::new())?;
+    let index = reader.get_root::<index::Reader>()?;
+
+    for index_entry in index.get_entries()? {
+      SecretEntry::from_reader(index_entry.get_entry()?)?;
+      for version_ref in index_entry.get_version_refs()? {
+        SecretVersionRef::from_reader(version_ref)?;
+      }
+    }
+
+    Ok(())
+  }
+
   fn update_heads(index: index::Builder, heads: &HashMap<String, Change>) {
     let mut new_heads = index.init_heads(heads.len() as u32);
 
@@ -218,6 +315,17 @@ impl Index {
     let mut new_heads = HashMap::with_capacity(change_logs.len());
     let mut added_versions = HashMap::<String, HashMap<String, SecretVersion>>::new();
     let mut deleted_blocks = HashSet::new();
+    let mut unavailable_blocks = BTreeSet::new();
+
+    // Blocks that could not be read previously are not part of the index yet, so they are just retried
+    for block_id in &self.unavailable_blocks {
+      Self::collect_added_version(
+        block_id,
+        &version_accessor,
+        &mut added_versions,
+        &mut unavailable_blocks,
+      );
+    }
 
     for change_log in change_logs {
       let changes = change_log.changes_since(self.heads.get(&change_log.node));
@@ -225,12 +333,12 @@ impl Index {
       for change in changes {
         match change.op {
           Operation::Add => {
-            if let Some(secret_version) = version_accessor(&change.block)? {
-              let secret_id = secret_version.secret_id.clone();
-              let mut by_blocks = added_versions.remove(&secret_id).unwrap_or_default();
-              by_blocks.insert(change.block.clone(), secret_version);
-              added_versions.insert(secret_id, by_blocks);
-            }
+            Self::collect_added_version(
+              &change.block,
+              &version_accessor,
+              &mut added_versions,
+              &mut unavailable_blocks,
+            );
           }
           Operation::Delete => {
             deleted_blocks.insert(change.block.clone());
@@ -249,14 +357,44 @@ impl Index {
         by_block.remove(deleted_block);
       }
     }
+    added_versions.retain(|_, by_block| !by_block.is_empty());
+    unavailable_blocks.retain(|block_id| !deleted_blocks.contains(block_id));
+
+    if !unavailable_blocks.is_empty() {
+      debug!("Skipped unavailable blocks: {:?}", unavailable_blocks);
+    }
 
     Ok(EffectiveChanges {
       new_heads,
       added_versions,
       deleted_blocks,
+      unavailable_blocks,
     })
   }
 
+  fn collect_added_version<F>(
+    block_id: &str,
+    version_accessor: F,
+    added_versions: &mut HashMap<String, HashMap<String, SecretVersion>>,
+    unavailable_blocks: &mut BTreeSet<String>,
+  ) where
+    F: Fn(&str) -> SecretStoreResult<Option<SecretVersion>>,
+  {
+    match version_accessor(block_id) {
+      Ok(Some(secret_version)) => {
+        let secret_id = secret_version.secret_id.clone();
+        let mut by_blocks = added_versions.remove(&secret_id).unwrap_or_default();
+        by_blocks.insert(block_id.to_string(), secret_version);
+        added_versions.insert(secret_id, by_blocks);
+      }
+      Ok(None) => (),
+      Err(error) => {
+        debug!("Unable to read block {}: {}", block_id, error);
+        unavailable_blocks.insert(block_id.to_string());
+      }
+    }
+  }
+
   fn update_entry<F>(
     old_version_refs: Vec<SecretVersionRef>,
     mut new_entry: index::entry::Builder,
@@ -278,15 +416,17 @@ impl Index {
     }
     if let Some(added_versions) = maybe_added_versions {
       for (block_id, added_version) in added_versions {
-        if !deleted_blocks.contains(block_id) {
+        // Blocks might be processed more than once if the head of a change log is not found
+        if !deleted_blocks.contains(block_id) && !version_refs.iter().any(|v| &v.block_id == block_id) {
           version_refs.push(SecretVersionRef {
             block_id: block_id.clone(),
             timestamp: added_version.timestamp,
+            modified_by: added_version.modified_by.clone(),
           })
         }
       }
     }
-    version_refs.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    version_refs.sort_by_key(|v| std::cmp::Reverse(v.timestamp));
 
     assert!(!version_refs.is_empty());
 
@@ -318,42 +458,47 @@ impl Index {
     }
 
     let (name_score, name_highlights) = match &filter.name {
-      Some(name_filter) => match sublime_fuzzy::best_match(name_filter, &entry.name) {
-        Some(fuzzy_match) => (
-          fuzzy_match.score(),
-          fuzzy_match.matched_indices().cloned().collect::<Vec<usize>>(),
-        ),
+      Some(name_filter) => match fuzzy_match(name_filter, &entry.name) {
+        Some(name_match) => name_match,
         _ => return Ok(None),
       },
       None => (0, vec![]),
     };
 
     let url_highlights = vec![];
-    let tags_highlights = match &filter.tag {
-      Some(tag_filter) => {
-        let highlights: Vec<usize> = entry
-          .tags
-          .iter()
-          .positions(|tag| tag.as_str() == tag_filter.as_str())
-          .collect();
-        if highlights.is_empty() {
-          return Ok(None);
-        }
-        highlights
-      }
-      None => vec![],
+    let tags_highlights: Vec<usize> = entry.tags.iter().positions(|tag| filter.tags.contains(tag)).collect();
+    let tags_match = match filter.tag_match {
+      TagMatch::All => filter.tags.iter().all(|tag_filter| entry.tags.contains(tag_filter)),
+      TagMatch::Any => filter.tags.is_empty() || !tags_highlights.is_empty(),
     };
+    if !tags_match {
+      return Ok(None);
+    }
 
     if !filter.secret_type.iter().all(|filter| filter == &entry.secret_type) {
       return Ok(None);
     }
 
+    if let Some(expiring_before) = &filter.expiring_before {
+      match &entry.expires_at {
+        Some(expires_at) if expires_at <= expiring_before => (),
+        _ => return Ok(None),
+      }
+    }
+
+    if filter.modified_after.iter().any(|after| &entry.timestamp < after)
+      || filter.modified_before.iter().any(|before| &entry.timestamp > before)
+    {
+      return Ok(None);
+    }
+
     Ok(Some(SecretEntryMatch {
       entry,
       name_score,
       name_highlights,
       url_highlights,
       tags_highlights,
+      content_highlights: vec![],
     }))
   }
 }
@@ -361,12 +506,13 @@ impl Index {
 impl Default for Index {
   fn default() -> Self {
     let mut index_message = message::Builder::new(ZeroingHeapAllocator::default());
-    index_message.init_root::<index::Builder>();
+    index_message.init_root::<index::Builder>().set_version(INDEX_VERSION);
     let index_data = serialize::write_message_to_words(&index_message);
 
     Index {
       data: index_data.into(),
       heads: HashMap::new(),
+      unavailable_blocks: BTreeSet::new(),
     }
   }
 }
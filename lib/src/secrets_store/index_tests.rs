@@ -1,19 +1,32 @@
-use crate::api::{SecretType, SecretVersion};
+use crate::api::{SecretListFilter, SecretType, SecretVersion, TagMatch};
+use crate::block_store::StoreError;
 use crate::block_store::{Change, ChangeLog, Operation};
 use crate::secrets_store::index::Index;
+use crate::secrets_store::SecretStoreResult;
+use crate::secrets_store_capnp::index;
+use capnp::{message, serialize};
 use chrono::prelude::*;
+use chrono::Duration;
 use data_encoding::HEXLOWER;
 use sha2::{Digest, Sha256};
 use spectral::prelude::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Default)]
 struct TestStore {
   versions: HashMap<String, SecretVersion>,
   changes: Vec<Change>,
+  unreadable: HashSet<String>,
 }
 
 impl TestStore {
+  fn get_version(&self, block_id: &str) -> SecretStoreResult<Option<SecretVersion>> {
+    if self.unreadable.contains(block_id) {
+      return Err(StoreError::InvalidBlock(block_id.to_string()).into());
+    }
+    Ok(self.versions.get(block_id).cloned())
+  }
+
   fn add_secret_version(&mut self, secret_id: &str, version_id: i64) {
     let block_id = Self::generate_block_id(secret_id, version_id);
     let version = Self::generate_secret_version(secret_id, version_id);
@@ -25,10 +38,42 @@ impl TestStore {
     });
   }
 
+  fn set_expires_at(&mut self, secret_id: &str, version_id: i64, expires_at: DateTime<Utc>) {
+    let block_id = Self::generate_block_id(secret_id, version_id);
+
+    if let Some(version) = self.versions.get_mut(&block_id) {
+      version.expires_at = Some(expires_at.into());
+    }
+  }
+
+  fn set_tags(&mut self, secret_id: &str, version_id: i64, tags: &[&str]) {
+    let block_id = Self::generate_block_id(secret_id, version_id);
+
+    if let Some(version) = self.versions.get_mut(&block_id) {
+      version.tags = tags.iter().map(|tag| tag.to_string()).collect();
+    }
+  }
+
+  fn set_name(&mut self, secret_id: &str, version_id: i64, name: &str) {
+    let block_id = Self::generate_block_id(secret_id, version_id);
+
+    if let Some(version) = self.versions.get_mut(&block_id) {
+      version.name = name.to_string();
+    }
+  }
+
+  fn delete_secret_version(&mut self, secret_id: &str, version_id: i64) {
+    self.changes.push(Change {
+      op: Operation::Delete,
+      block: Self::generate_block_id(secret_id, version_id),
+    });
+  }
+
   fn make_changelog(&self, node: &str) -> ChangeLog {
     ChangeLog {
       node: node.to_string(),
       changes: self.changes.clone(),
+      commits: vec![],
     }
   }
 
@@ -44,6 +89,9 @@ impl TestStore {
       deleted: false,
       recipients: vec![],
       attachments: vec![],
+      expires_at: None,
+      parent_block_id: None,
+      modified_by: None,
     }
   }
 
@@ -79,6 +127,27 @@ fn test_process_change_logs() {
   let mut all_matches = index.filter_entries(&filter).unwrap();
 
   assert_that(&all_matches.entries).has_length(10);
+  assert_that(&all_matches.tag_tree).is_none();
+
+  let grouped = index
+    .filter_entries(&SecretListFilter {
+      url: None,
+      tags: vec![],
+      tag_match: TagMatch::All,
+      secret_type: None,
+      name: None,
+      deleted: false,
+      expiring_before: None,
+      modified_after: None,
+      modified_before: None,
+      group_by_tag: true,
+      content: None,
+      offset: 0,
+      limit: None,
+    })
+    .unwrap();
+
+  assert_that(&grouped.tag_tree.as_ref().map(|tree| tree.untagged.len())).contains_value(10);
 
   test_store.changes.clear();
 
@@ -99,3 +168,467 @@ fn test_process_change_logs() {
 
   assert_that(&all_matches.entries).has_length(15);
 }
+
+#[test]
+fn test_process_change_logs_with_unavailable_blocks() {
+  let mut test_store: TestStore = Default::default();
+  let mut index: Index = Default::default();
+
+  for i in 0..10 {
+    test_store.add_secret_version(&format!("Secret_{}", i), 0)
+  }
+  for i in 7..10 {
+    let block_id = TestStore::generate_block_id(&format!("Secret_{}", i), 0);
+    test_store.unreadable.insert(block_id);
+  }
+
+  assert_that(
+    &index.process_change_logs(&[test_store.make_changelog("test_node")], |block_id| {
+      test_store.get_version(block_id)
+    }),
+  )
+  .is_ok_containing(true);
+  assert_that(&index.has_unavailable_blocks()).is_true();
+
+  let filter = Default::default();
+  let mut all_matches = index.filter_entries(&filter).unwrap();
+
+  assert_that(&all_matches.entries).has_length(7);
+  assert_that(&all_matches.unavailable_blocks).has_length(3);
+
+  test_store.unreadable.clear();
+
+  assert_that(
+    &index.process_change_logs(&[test_store.make_changelog("test_node")], |block_id| {
+      test_store.get_version(block_id)
+    }),
+  )
+  .is_ok_containing(true);
+  assert_that(&index.has_unavailable_blocks()).is_false();
+
+  all_matches = index.filter_entries(&filter).unwrap();
+
+  assert_that(&all_matches.entries).has_length(10);
+  assert_that(&all_matches.unavailable_blocks).is_empty();
+}
+
+fn expiring_filter(expiring_before: DateTime<Utc>) -> SecretListFilter {
+  SecretListFilter {
+    url: None,
+    tags: vec![],
+    tag_match: TagMatch::All,
+    secret_type: None,
+    name: None,
+    deleted: false,
+    expiring_before: Some(expiring_before.into()),
+    modified_after: None,
+    modified_before: None,
+    group_by_tag: false,
+    content: None,
+    offset: 0,
+    limit: None,
+  }
+}
+
+#[test]
+fn test_filter_expiring_before() {
+  let mut test_store: TestStore = Default::default();
+  let mut index: Index = Default::default();
+  let now = Utc::now();
+
+  for i in 0..5 {
+    test_store.add_secret_version(&format!("Secret_{}", i), 0);
+  }
+  // Secret_0 does not expire, Secret_i expires in 10 * i days
+  for i in 1..5 {
+    test_store.set_expires_at(&format!("Secret_{}", i), 0, now + Duration::days(10 * i));
+  }
+
+  assert_that(
+    &index.process_change_logs(&[test_store.make_changelog("test_node")], |block_id| {
+      test_store.get_version(block_id)
+    }),
+  )
+  .is_ok_containing(true);
+
+  let all_matches = index.filter_entries(&Default::default()).unwrap();
+
+  assert_that(&all_matches.entries).has_length(5);
+
+  let expiring = index
+    .filter_entries(&expiring_filter(now + Duration::days(25)))
+    .unwrap();
+  let mut expiring_ids: Vec<String> = expiring.entries.iter().map(|m| m.entry.id.clone()).collect();
+  expiring_ids.sort();
+
+  assert_that(&expiring_ids).is_equal_to(vec!["Secret_1".to_string(), "Secret_2".to_string()]);
+  assert_that(&expiring.entries[0].entry.expires_at).is_some();
+
+  let expired = index.filter_entries(&expiring_filter(now)).unwrap();
+
+  assert_that(&expired.entries).is_empty();
+}
+
+#[test]
+fn test_filter_tags() {
+  let mut test_store: TestStore = Default::default();
+  let mut index: Index = Default::default();
+
+  for i in 0..4 {
+    test_store.add_secret_version(&format!("Secret_{}", i), 0);
+  }
+  test_store.set_tags("Secret_0", 0, &["work", "aws"]);
+  test_store.set_tags("Secret_1", 0, &["work"]);
+  test_store.set_tags("Secret_2", 0, &["private", "aws"]);
+
+  assert_that(
+    &index.process_change_logs(&[test_store.make_changelog("test_node")], |block_id| {
+      test_store.get_version(block_id)
+    }),
+  )
+  .is_ok_containing(true);
+
+  let matching_ids = |tags: &[&str], tag_match: TagMatch| -> Vec<String> {
+    let filter = SecretListFilter {
+      url: None,
+      tags: tags.iter().map(|tag| tag.to_string()).collect(),
+      tag_match,
+      secret_type: None,
+      name: None,
+      deleted: false,
+      expiring_before: None,
+      modified_after: None,
+      modified_before: None,
+      group_by_tag: false,
+      content: None,
+      offset: 0,
+      limit: None,
+    };
+    let mut ids: Vec<String> = index
+      .filter_entries(&filter)
+      .unwrap()
+      .entries
+      .iter()
+      .map(|m| m.entry.id.clone())
+      .collect();
+    ids.sort();
+    ids
+  };
+
+  assert_that(&matching_ids(&[], TagMatch::All)).has_length(4);
+  assert_that(&matching_ids(&[], TagMatch::Any)).has_length(4);
+  assert_that(&matching_ids(&["work"], TagMatch::All))
+    .is_equal_to(vec!["Secret_0".to_string(), "Secret_1".to_string()]);
+  assert_that(&matching_ids(&["work", "aws"], TagMatch::All)).is_equal_to(vec!["Secret_0".to_string()]);
+  assert_that(&matching_ids(&["work", "aws"], TagMatch::Any)).is_equal_to(vec![
+    "Secret_0".to_string(),
+    "Secret_1".to_string(),
+    "Secret_2".to_string(),
+  ]);
+  assert_that(&matching_ids(&["work", "unknown"], TagMatch::All)).is_empty();
+
+  let filter = SecretListFilter {
+    url: None,
+    tags: vec!["aws".to_string(), "private".to_string()],
+    tag_match: TagMatch::Any,
+    secret_type: None,
+    name: None,
+    deleted: false,
+    expiring_before: None,
+    modified_after: None,
+    modified_before: None,
+    group_by_tag: false,
+    content: None,
+    offset: 0,
+    limit: None,
+  };
+  let matches = index.filter_entries(&filter).unwrap();
+  let secret_2 = matches.entries.iter().find(|m| m.entry.id == "Secret_2").unwrap();
+
+  assert_that(&secret_2.tags_highlights).is_equal_to(vec![0, 1]);
+}
+
+#[test]
+fn test_filter_modified_range() {
+  let mut test_store: TestStore = Default::default();
+  let mut index: Index = Default::default();
+
+  // Secret_i is modified at 1000 + 1000 * i
+  for i in 0..5 {
+    test_store.add_secret_version(&format!("Secret_{}", i), i);
+  }
+
+  assert_that(
+    &index.process_change_logs(&[test_store.make_changelog("test_node")], |block_id| {
+      test_store.get_version(block_id)
+    }),
+  )
+  .is_ok_containing(true);
+
+  let matching_ids = |modified_after: Option<i64>, modified_before: Option<i64>| -> Vec<String> {
+    let filter = SecretListFilter {
+      url: None,
+      tags: vec![],
+      tag_match: TagMatch::All,
+      secret_type: None,
+      name: None,
+      deleted: false,
+      expiring_before: None,
+      modified_after: modified_after.map(|secs| Utc.timestamp_opt(secs, 0).unwrap().into()),
+      modified_before: modified_before.map(|secs| Utc.timestamp_opt(secs, 0).unwrap().into()),
+      group_by_tag: false,
+      content: None,
+      offset: 0,
+      limit: None,
+    };
+    let mut ids: Vec<String> = index
+      .filter_entries(&filter)
+      .unwrap()
+      .entries
+      .iter()
+      .map(|m| m.entry.id.clone())
+      .collect();
+    ids.sort();
+    ids
+  };
+
+  assert_that(&matching_ids(None, None)).has_length(5);
+  assert_that(&matching_ids(Some(3000), None)).is_equal_to(vec![
+    "Secret_2".to_string(),
+    "Secret_3".to_string(),
+    "Secret_4".to_string(),
+  ]);
+  assert_that(&matching_ids(None, Some(2500))).is_equal_to(vec!["Secret_0".to_string(), "Secret_1".to_string()]);
+  assert_that(&matching_ids(Some(2000), Some(4000))).is_equal_to(vec![
+    "Secret_1".to_string(),
+    "Secret_2".to_string(),
+    "Secret_3".to_string(),
+  ]);
+  assert_that(&matching_ids(Some(4000), Some(2000))).is_empty();
+}
+
+fn full_rebuild(change_logs: &[ChangeLog], stores: &[&TestStore]) -> Index {
+  let mut index: Index = Default::default();
+
+  index
+    .process_change_logs(change_logs, |block_id| get_version(stores, block_id))
+    .unwrap();
+
+  index
+}
+
+fn get_version(stores: &[&TestStore], block_id: &str) -> SecretStoreResult<Option<SecretVersion>> {
+  for store in stores {
+    if let Some(version) = store.get_version(block_id)? {
+      return Ok(Some(version));
+    }
+  }
+  Ok(None)
+}
+
+fn assert_same_index(actual: &Index, expected: &Index) {
+  for deleted in [false, true] {
+    let filter = SecretListFilter {
+      url: None,
+      tags: vec![],
+      tag_match: TagMatch::All,
+      secret_type: None,
+      name: None,
+      deleted,
+      expiring_before: None,
+      modified_after: None,
+      modified_before: None,
+      group_by_tag: false,
+      content: None,
+      offset: 0,
+      limit: None,
+    };
+    let actual_list = actual.filter_entries(&filter).unwrap();
+    let expected_list = expected.filter_entries(&filter).unwrap();
+
+    assert_that(&actual_list).is_equal_to(&expected_list);
+    for entry_match in &expected_list.entries {
+      assert_that(&actual.find_versions(&entry_match.entry.id).unwrap())
+        .is_equal_to(expected.find_versions(&entry_match.entry.id).unwrap());
+    }
+  }
+}
+
+type Step = dyn Fn(&mut TestStore, &mut TestStore);
+
+#[test]
+fn test_incremental_matches_full_rebuild() {
+  let mut node1: TestStore = Default::default();
+  let mut node2: TestStore = Default::default();
+  let mut index: Index = Default::default();
+
+  for i in 0..5 {
+    node1.add_secret_version(&format!("Secret_{}", i), 0);
+  }
+  for i in 5..8 {
+    node2.add_secret_version(&format!("Secret_{}", i), 0);
+  }
+  node1.set_tags("Secret_0", 0, &["tag1"]);
+
+  let steps: Vec<Box<Step>> = vec![
+    Box::new(|_, _| ()),
+    Box::new(|node1, node2| {
+      node1.add_secret_version("Secret_0", 1);
+      node1.set_tags("Secret_0", 1, &["tag2", "tag3"]);
+      node1.add_secret_version("Secret_1", 1);
+      node2.delete_secret_version("Secret_5", 0);
+      node2.add_secret_version("Secret_2", 2);
+    }),
+    Box::new(|node1, node2| {
+      node2.add_secret_version("Secret_8", 0);
+      node2.set_tags("Secret_8", 0, &["tag4"]);
+      node1.delete_secret_version("Secret_2", 0);
+      node1.delete_secret_version("Secret_2", 2);
+      node1.add_secret_version("Secret_9", 3);
+    }),
+    Box::new(|node1, _| {
+      node1.add_secret_version("Secret_3", 1);
+      if let Some(version) = node1.versions.get_mut(&TestStore::generate_block_id("Secret_3", 1)) {
+        version.deleted = true;
+      }
+    }),
+  ];
+
+  for step in steps {
+    step(&mut node1, &mut node2);
+
+    let change_logs = [node1.make_changelog("node1"), node2.make_changelog("node2")];
+
+    assert_that(&index.process_change_logs(&change_logs, |block_id| get_version(&[&node1, &node2], block_id))).is_ok();
+    assert_same_index(&index, &full_rebuild(&change_logs, &[&node1, &node2]));
+  }
+
+  let all_matches = index.filter_entries(&Default::default()).unwrap();
+
+  assert_that(&all_matches.entries).has_length(7);
+  assert_that(&all_matches.all_tags).is_equal_to(vec!["tag2".to_string(), "tag3".to_string(), "tag4".to_string()]);
+}
+
+#[test]
+fn test_reprocess_without_head() {
+  let mut test_store: TestStore = Default::default();
+  let mut index: Index = Default::default();
+
+  for i in 0..5 {
+    test_store.add_secret_version(&format!("Secret_{}", i), 0);
+  }
+  index
+    .process_change_logs(&[test_store.make_changelog("test_node")], |block_id| {
+      test_store.get_version(block_id)
+    })
+    .unwrap();
+
+  // Head of the index is no longer part of the change log, so everything is processed again
+  let head = test_store.changes.remove(4);
+  test_store.add_secret_version("Secret_5", 0);
+  test_store.changes.push(head);
+  test_store.add_secret_version("Secret_0", 1);
+  let change_logs = [test_store.make_changelog("other_node")];
+
+  assert_that(&index.process_change_logs(&change_logs, |block_id| test_store.get_version(block_id)))
+    .is_ok_containing(true);
+  assert_same_index(
+    &index,
+    &full_rebuild(
+      &[
+        test_store.make_changelog("test_node"),
+        test_store.make_changelog("other_node"),
+      ],
+      &[&test_store],
+    ),
+  );
+  assert_that(&index.find_versions("Secret_0").unwrap()).has_length(2);
+}
+
+#[test]
+fn test_rebuild_on_version_mismatch() {
+  let mut test_store: TestStore = Default::default();
+  let mut index: Index = Default::default();
+
+  for i in 0..5 {
+    test_store.add_secret_version(&format!("Secret_{}", i), 0);
+  }
+  let change_logs = [test_store.make_changelog("test_node")];
+  index
+    .process_change_logs(&change_logs, |block_id| test_store.get_version(block_id))
+    .unwrap();
+
+  let stored = Index::from_secured_raw(&index.data.borrow()).unwrap();
+
+  assert_same_index(&stored, &index);
+  assert_that(&stored.filter_entries(&Default::default()).unwrap().entries).has_length(5);
+
+  let outdated_raw = {
+    let mut data: &[u8] = &index.data.borrow();
+    let reader = serialize::read_message_from_flat_slice(&mut data, message::ReaderOptions::new()).unwrap();
+    let mut outdated = message::Builder::new_default();
+    outdated.set_root(reader.get_root::<index::Reader>().unwrap()).unwrap();
+    outdated.get_root::<index::Builder>().unwrap().set_version(0);
+    serialize::write_message_to_words(&outdated)
+  };
+  let mut outdated = Index::from_secured_raw(&outdated_raw).unwrap();
+
+  assert_that(&outdated.filter_entries(&Default::default()).unwrap().entries).is_empty();
+
+  assert_that(&outdated.process_change_logs(&change_logs, |block_id| test_store.get_version(block_id)))
+    .is_ok_containing(true);
+  assert_same_index(&outdated, &index);
+}
+
+#[test]
+fn test_filter_name_ranking() {
+  let mut test_store: TestStore = Default::default();
+  let mut index: Index = Default::default();
+
+  for (i, name) in ["Lighthouse", "Gitea", "GitHub", "github", "Stack Overflow", "GitHub"]
+    .iter()
+    .enumerate()
+  {
+    let secret_id = format!("Secret_{}", i);
+    test_store.add_secret_version(&secret_id, 0);
+    test_store.set_name(&secret_id, 0, name);
+  }
+
+  assert_that(
+    &index.process_change_logs(&[test_store.make_changelog("test_node")], |block_id| {
+      test_store.get_version(block_id)
+    }),
+  )
+  .is_ok();
+
+  let filter = SecretListFilter {
+    url: None,
+    tags: vec![],
+    tag_match: TagMatch::All,
+    secret_type: None,
+    name: Some("gh".to_string()),
+    deleted: false,
+    expiring_before: None,
+    modified_after: None,
+    modified_before: None,
+    group_by_tag: false,
+    content: None,
+    offset: 0,
+    limit: None,
+  };
+  let matches = index.filter_entries(&filter).unwrap();
+  let ids: Vec<&str> = matches.entries.iter().map(|m| m.entry.id.as_str()).collect();
+
+  // Equal scores are ordered like the entries themselves (i.e. by name, then id)
+  assert_that(&ids).is_equal_to(vec!["Secret_2", "Secret_5", "Secret_3", "Secret_0"]);
+  assert_that(&matches.entries[0].name_score).is_equal_to(matches.entries[1].name_score);
+  assert_that(&matches.entries[0].name_highlights).is_equal_to(vec![0, 3]);
+  assert_that(&matches.entries[3].name_highlights).is_equal_to(vec![2, 3]);
+
+  // Ranking is deterministic
+  for _ in 0..5 {
+    let again = index.filter_entries(&filter).unwrap();
+    let again_ids: Vec<&str> = again.entries.iter().map(|m| m.entry.id.as_str()).collect();
+    assert_that(&again_ids).is_equal_to(&ids);
+  }
+}
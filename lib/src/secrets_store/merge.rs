@@ -0,0 +1,154 @@
+use std::collections::BTreeMap;
+
+use crate::api::{SecretAttachment, SecretMergeConflict, SecretProperties, SecretVersion};
+
+/// Three-way merge of two concurrent versions of a Secret.
+///
+/// * `base` the common parent version of `ours` and `theirs`
+/// * `ours` block id and content of the latest version
+/// * `theirs` block id and content of the concurrent version
+///
+/// Changes that were only made in one of the versions are taken over, if both versions changed
+/// a field in different ways the value of `ours` is kept and the field is reported as conflict.
+///
+pub fn merge_concurrent_versions(
+  base: &SecretVersion,
+  ours: (&str, &SecretVersion),
+  theirs: (&str, &SecretVersion),
+) -> (SecretVersion, Vec<SecretMergeConflict>) {
+  let (ours_block_id, ours) = ours;
+  let (theirs_block_id, theirs) = theirs;
+  let mut merger = Merger {
+    block_ids: vec![ours_block_id.to_string(), theirs_block_id.to_string()],
+    conflicts: vec![],
+  };
+
+  let merged = SecretVersion {
+    secret_id: ours.secret_id.clone(),
+    secret_type: merger.merge_value("type", &base.secret_type, &ours.secret_type, &theirs.secret_type),
+    timestamp: ours.timestamp,
+    name: merger.merge_value("name", &base.name, &ours.name, &theirs.name),
+    tags: merge_list(&base.tags, &ours.tags, &theirs.tags),
+    urls: merge_list(&base.urls, &ours.urls, &theirs.urls),
+    properties: merger.merge_properties(&base.properties, &ours.properties, &theirs.properties),
+    attachments: merger.merge_attachments(&base.attachments, &ours.attachments, &theirs.attachments),
+    deleted: merger.merge_value("deleted", &base.deleted, &ours.deleted, &theirs.deleted),
+    recipients: merge_list(&base.recipients, &ours.recipients, &theirs.recipients),
+    expires_at: merger.merge_value("expiresAt", &base.expires_at, &ours.expires_at, &theirs.expires_at),
+    parent_block_id: Some(ours_block_id.to_string()),
+    modified_by: None,
+  };
+
+  (merged, merger.conflicts)
+}
+
+struct Merger {
+  block_ids: Vec<String>,
+  conflicts: Vec<SecretMergeConflict>,
+}
+
+impl Merger {
+  fn merge_value<T: PartialEq + Clone>(&mut self, field: &str, base: &T, ours: &T, theirs: &T) -> T {
+    self
+      .merge_optional(field, Some(base), Some(ours), Some(theirs))
+      .unwrap_or_else(|| ours.clone())
+  }
+
+  fn merge_optional<T: PartialEq + Clone>(
+    &mut self,
+    field: &str,
+    base: Option<&T>,
+    ours: Option<&T>,
+    theirs: Option<&T>,
+  ) -> Option<T> {
+    if ours == theirs || theirs == base {
+      ours.cloned()
+    } else if ours == base {
+      theirs.cloned()
+    } else {
+      self.conflicts.push(SecretMergeConflict {
+        field: field.to_string(),
+        block_ids: self.block_ids.clone(),
+      });
+      ours.cloned()
+    }
+  }
+
+  fn merge_properties(
+    &mut self,
+    base: &SecretProperties,
+    ours: &SecretProperties,
+    theirs: &SecretProperties,
+  ) -> SecretProperties {
+    let mut names: Vec<&str> = base
+      .iter()
+      .chain(ours.iter())
+      .chain(theirs.iter())
+      .map(|(k, _)| k)
+      .collect();
+    names.sort_unstable();
+    names.dedup();
+
+    let mut merged = BTreeMap::new();
+    for name in names {
+      if let Some(value) = self.merge_optional(
+        &format!("properties.{}", name),
+        base.get(name),
+        ours.get(name),
+        theirs.get(name),
+      ) {
+        merged.insert(name.to_string(), value);
+      }
+    }
+
+    SecretProperties::new(merged)
+  }
+
+  fn merge_attachments(
+    &mut self,
+    base: &[SecretAttachment],
+    ours: &[SecretAttachment],
+    theirs: &[SecretAttachment],
+  ) -> Vec<SecretAttachment> {
+    let mut names: Vec<&str> = Vec::with_capacity(ours.len());
+    for attachment in ours.iter().chain(theirs.iter()).chain(base.iter()) {
+      if !names.contains(&attachment.name()) {
+        names.push(attachment.name());
+      }
+    }
+
+    names
+      .iter()
+      .filter_map(|name| {
+        self.merge_optional(
+          &format!("attachments.{}", name),
+          find_attachment(base, name),
+          find_attachment(ours, name),
+          find_attachment(theirs, name),
+        )
+      })
+      .collect()
+  }
+}
+
+fn find_attachment<'a>(attachments: &'a [SecretAttachment], name: &str) -> Option<&'a SecretAttachment> {
+  attachments.iter().find(|attachment| attachment.name() == name)
+}
+
+/// Merge lists of strings (e.g. tags), taking additions and removals of both sides.
+/// The order of `ours` is preserved, additions of `theirs` are appended.
+fn merge_list(base: &[String], ours: &[String], theirs: &[String]) -> Vec<String> {
+  let mut merged: Vec<String> = ours
+    .iter()
+    .filter(|item| !base.contains(item) || theirs.contains(item))
+    .cloned()
+    .collect();
+
+  for item in theirs {
+    if !base.contains(item) && !merged.contains(item) {
+      merged.push(item.clone());
+    }
+  }
+
+  merged
+}
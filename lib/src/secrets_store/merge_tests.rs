@@ -0,0 +1,93 @@
+use std::collections::BTreeMap;
+
+use chrono::{TimeZone, Utc};
+use spectral::prelude::*;
+
+use super::merge::merge_concurrent_versions;
+use crate::api::{SecretAttachment, SecretMergeConflict, SecretProperties, SecretType, SecretVersion};
+
+fn version(timestamp: i64, properties: &[(&str, &str)], tags: &[&str]) -> SecretVersion {
+  SecretVersion {
+    secret_id: "secret1".to_string(),
+    secret_type: SecretType::Login,
+    timestamp: Utc.timestamp_opt(timestamp, 0).unwrap().into(),
+    name: "Secret".to_string(),
+    tags: tags.iter().map(ToString::to_string).collect(),
+    urls: vec![],
+    properties: SecretProperties::new(
+      properties
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect::<BTreeMap<String, String>>(),
+    ),
+    attachments: vec![],
+    deleted: false,
+    recipients: vec!["identity1".to_string()],
+    expires_at: None,
+    parent_block_id: None,
+    modified_by: None,
+  }
+}
+
+fn conflicting_fields(conflicts: &[SecretMergeConflict]) -> Vec<String> {
+  conflicts.iter().map(|c| c.field.clone()).collect()
+}
+
+#[test]
+fn test_merge_non_conflicting() {
+  let base = version(1000, &[("username", "user"), ("password", "pw1")], &["a", "b"]);
+  let mut ours = version(2000, &[("username", "user"), ("password", "pw2")], &["a", "b", "c"]);
+  let mut theirs = version(1500, &[("username", "other"), ("password", "pw1")], &["b"]);
+  ours.parent_block_id = Some("base".to_string());
+  theirs.parent_block_id = Some("base".to_string());
+  theirs.name = "Renamed".to_string();
+  theirs
+    .attachments
+    .push(SecretAttachment::new("note.txt", "text/plain", b"note".to_vec()));
+
+  let (merged, conflicts) = merge_concurrent_versions(&base, ("ours", &ours), ("theirs", &theirs));
+
+  assert_that(&conflicts).is_empty();
+  assert_that(&merged.name).is_equal_to("Renamed".to_string());
+  assert_that(&merged.properties.get("username")).contains_value(&"other".to_string());
+  assert_that(&merged.properties.get("password")).contains_value(&"pw2".to_string());
+  assert_that(&merged.tags).is_equal_to(vec!["b".to_string(), "c".to_string()]);
+  assert_that(&merged.attachments).has_length(1);
+  assert_that(&merged.timestamp).is_equal_to(ours.timestamp);
+  assert_that(&merged.parent_block_id).contains_value("ours".to_string());
+}
+
+#[test]
+fn test_merge_conflicting() {
+  let base = version(1000, &[("username", "user"), ("password", "pw1")], &[]);
+  let ours = version(
+    2000,
+    &[("username", "user"), ("password", "pw2"), ("notes", "same")],
+    &[],
+  );
+  let mut theirs = version(1500, &[("password", "pw3"), ("notes", "same")], &[]);
+  theirs.deleted = true;
+
+  let (merged, conflicts) = merge_concurrent_versions(&base, ("ours", &ours), ("theirs", &theirs));
+
+  // Both changed the password differently: latest wins, conflict is reported
+  assert_that(&conflicting_fields(&conflicts)).is_equal_to(vec!["properties.password".to_string()]);
+  assert_that(&conflicts[0].block_ids).is_equal_to(vec!["ours".to_string(), "theirs".to_string()]);
+  assert_that(&merged.properties.get("password")).contains_value(&"pw2".to_string());
+  // Identical additions are no conflict, removal of one side is taken over
+  assert_that(&merged.properties.get("notes")).contains_value(&"same".to_string());
+  assert_that(&merged.properties.get("username")).is_none();
+  assert_that(&merged.deleted).is_true();
+}
+
+#[test]
+fn test_merge_removed_vs_modified() {
+  let base = version(1000, &[("password", "pw1")], &[]);
+  let ours = version(2000, &[], &[]);
+  let theirs = version(1500, &[("password", "pw2")], &[]);
+
+  let (merged, conflicts) = merge_concurrent_versions(&base, ("ours", &ours), ("theirs", &theirs));
+
+  assert_that(&conflicting_fields(&conflicts)).is_equal_to(vec!["properties.password".to_string()]);
+  assert_that(&merged.properties.get("password")).is_none();
+}
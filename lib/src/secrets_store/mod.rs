@@ -1,21 +1,57 @@
-use crate::api::{EventHub, Identity, Secret, SecretList, SecretListFilter, SecretVersion, Status};
+use crate::api::{
+  AuditPolicy, AuditReport, CipherSuite, EventHub, Identity, OtpToken, ReuseGroup, Secret, SecretAttachment,
+  SecretList, SecretListFilter, SecretType, SecretVersion, Status, StoreConfig, StoreDiagnostics, PROPERTY_TOTP_URL,
+};
 use crate::block_store::sync::SyncBlockStore;
+use crate::otp::OTPAuthUrl;
+use crate::secrets_store_capnp::KeyType;
+use chrono::Utc;
+use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+mod attachment_chunks;
+pub mod breach;
 pub mod cipher;
+pub mod clock;
 mod error;
 pub mod estimate;
+mod fuzzy;
+pub mod hardware_factor;
 mod index;
+mod merge;
 mod multi_lane;
 mod padding;
+pub mod passphrase;
+pub mod pepper;
+pub mod site;
+mod throttle;
 
+#[cfg(test)]
+mod attachment_chunks_tests;
+#[cfg(test)]
+mod breach_tests;
+#[cfg(test)]
+mod fuzzy_tests;
+#[cfg(test)]
+mod hardware_factor_tests;
 #[cfg(test)]
 mod index_tests;
 #[cfg(test)]
+mod merge_tests;
+#[cfg(test)]
+mod passphrase_tests;
+#[cfg(test)]
+mod pepper_tests;
+#[cfg(test)]
+mod site_tests;
+#[cfg(test)]
 mod tests;
+#[cfg(test)]
+mod throttle_tests;
 
 pub use self::error::{SecretStoreError, SecretStoreResult};
+pub use self::multi_lane::MultiLaneSecretsStore;
 use crate::block_store::open_block_store;
 use crate::memguard::SecretBytes;
 
@@ -23,7 +59,16 @@ pub trait SecretsStore: std::fmt::Debug + Send + Sync {
   fn status(&self) -> SecretStoreResult<Status>;
 
   fn lock(&self) -> SecretStoreResult<()>;
+  /// Lock the store because it has been idle for its autolock timeout (same as `lock` unless the
+  /// store reports this differently).
+  fn autolock(&self) -> SecretStoreResult<()> {
+    self.lock()
+  }
   fn unlock(&self, identity_id: &str, passphrase: SecretBytes) -> SecretStoreResult<()>;
+  /// Check the passphrase of an identity without changing the lock state of the store.
+  ///
+  /// Failed checks count towards the same throttle as failed unlock attempts.
+  fn verify_passphrase(&self, identity_id: &str, passphrase: SecretBytes) -> SecretStoreResult<bool>;
 
   fn identities(&self) -> SecretStoreResult<Vec<Identity>>;
   fn add_identity(&self, identity: Identity, passphrase: SecretBytes) -> SecretStoreResult<()>;
@@ -31,33 +76,156 @@ pub trait SecretsStore: std::fmt::Debug + Send + Sync {
 
   fn list(&self, filter: &SecretListFilter) -> SecretStoreResult<SecretList>;
   fn update_index(&self) -> SecretStoreResult<()>;
+  /// Discard the current index and rebuild it from all data blocks.
+  fn rebuild_index(&self) -> SecretStoreResult<()>;
+  /// Overwrite the stored index blocks of all identities, i.e. the index is rebuilt on the next unlock.
+  /// (Data blocks and rings are left untouched)
+  fn wipe_index(&self) -> SecretStoreResult<()>;
+  /// Re-encrypt all versions readable by the unlocked identity with the `target` cipher only.
+  ///
+  /// Every migrated version is added as a new block that replaces the old one in the index, the old
+  /// blocks themselves are left untouched. Returns the number of migrated versions.
+  fn rotate_cipher(&self, target: KeyType) -> SecretStoreResult<usize>;
+  /// Remove all blocks from the underlying block store that are no longer referenced.
+  ///
+  /// If `prune_history` is set, previous versions of all secrets readable by the unlocked identity are
+  /// deleted (in the change log) beforehand, so that only the current versions (and the common parents
+  /// of unmerged concurrent versions) remain. Since the change log is synchronized, this removes the
+  /// history on all other nodes as well and cannot be undone. Blocks that are not readable by the
+  /// unlocked identity are always kept.
+  ///
+  /// Returns the number of removed blocks.
+  fn collect_garbage(&self, prune_history: bool) -> SecretStoreResult<usize>;
+  /// Ensure that everything committed to the underlying block store is persisted.
+  ///
+  /// This is only relevant for the process owning the block store, i.e. remote stores do not have to do anything.
+  fn flush(&self) -> SecretStoreResult<()> {
+    Ok(())
+  }
+  /// Remove a recipient from the current versions of all secrets shared with it.
+  ///
+  /// For every affected secret a new version without the recipient is added. Previous versions remain
+  /// readable by the revoked recipient (once published they can not be made unseen), instead it gets a
+  /// deleted version without any content, so that the secret vanishes from its current view.
+  ///
+  /// Returns the ids of all affected secrets.
+  fn revoke_recipient(&self, recipient_id: &str) -> SecretStoreResult<Vec<String>>;
+  /// Gather diagnostic information about the structure of the store (without decrypting anything).
+  fn diagnose(&self) -> SecretStoreResult<StoreDiagnostics>;
 
   fn add(&self, secret_version: SecretVersion) -> SecretStoreResult<String>;
   fn get(&self, secret_id: &str) -> SecretStoreResult<Secret>;
   fn get_version(&self, block_id: &str) -> SecretStoreResult<SecretVersion>;
+  /// Read the attachment `name` of the current version of a secret one chunk at a time, i.e. the result only
+  /// has the content of chunk `index` (`None` after the last one). This way large attachments can be
+  /// extracted without ever having their entire content in memory. Attachments that are not stored in
+  /// chunks consist of a single chunk.
+  fn get_attachment_chunk(
+    &self,
+    secret_id: &str,
+    name: &str,
+    index: usize,
+  ) -> SecretStoreResult<Option<SecretAttachment>>;
+  /// Ids of all secrets that got a concurrent version by one of `block_ids` (i.e. a version derived from
+  /// the same parent as another version). Both versions are kept, `get` merges them.
+  fn find_concurrent_versions(&self, block_ids: &[String]) -> SecretStoreResult<Vec<String>>;
+  /// Find groups of secrets sharing the same password (only the current versions are considered).
+  fn find_reused_passwords(&self) -> SecretStoreResult<Vec<ReuseGroup>>;
+  /// Deterministically derive a key for a site from the private keys of the unlocked identity
+  /// (see `site::derive_site_key`).
+  ///
+  /// The key must never leave the process owning the store, i.e. this is not available for remote stores.
+  fn derive_site_key(&self, site: &str, key_length: usize) -> SecretStoreResult<SecretBytes>;
+  /// Check the passwords of all secrets according to a policy (e.g. for weak or old passwords).
+  fn audit(&self, policy: &AuditPolicy) -> SecretStoreResult<AuditReport>;
+  /// Check the passwords of the current version of a secret against the local breach directory
+  /// (see `breach::lookup_breach_count`).
+  ///
+  /// Returns how often the (most often) breached password has been seen, None if none of the passwords
+  /// is known to be breached or no breach directory is configured.
+  fn check_breached(&self, secret_id: &str) -> SecretStoreResult<Option<u64>>;
+
+  /// Change the type of a secret by adding a new version with all other content unchanged.
+  ///
+  /// Note: Index and password strengths follow the type of the current version, i.e. they
+  /// are updated as well.
+  fn change_type(&self, secret_id: &str, secret_type: SecretType) -> SecretStoreResult<String> {
+    let secret = self.get(secret_id)?;
+    let mut secret_version = secret.current.clone();
+
+    secret_version.timestamp = Utc::now().into();
+    secret_version.parent_block_id = Some(secret.current_block_id.clone());
+    secret_version.secret_type = secret_type;
+
+    self.add(secret_version)
+  }
+
+  /// Share a secret with additional recipients by adding a new version encrypted for them as well.
+  ///
+  /// Only the new version is readable by the additional recipients, previous versions remain
+  /// readable by their original recipients only.
+  fn share_with(&self, secret_id: &str, add_recipients: &[String]) -> SecretStoreResult<String> {
+    let identities = self.identities()?;
+    if let Some(unknown) = add_recipients
+      .iter()
+      .find(|recipient| !identities.iter().any(|identity| &identity.id == *recipient))
+    {
+      return Err(SecretStoreError::InvalidRecipient(unknown.to_string()));
+    }
+
+    let secret = self.get(secret_id)?;
+    let mut secret_version = secret.current.clone();
+
+    for recipient in add_recipients {
+      if !secret_version.recipients.contains(recipient) {
+        secret_version.recipients.push(recipient.clone());
+      }
+    }
+    secret_version.timestamp = Utc::now().into();
+    secret_version.parent_block_id = Some(secret.current_block_id.clone());
+
+    self.add(secret_version)
+  }
+
+  /// Generate the current one-time password of a secret from the totp url of its current version.
+  ///
+  /// Returns None if the current version has no totp url.
+  fn get_otp(&self, secret_id: &str) -> SecretStoreResult<Option<OtpToken>> {
+    let secret = self.get(secret_id)?;
+    let otp_url = match secret.current.properties.get(PROPERTY_TOTP_URL) {
+      Some(otp_url) => otp_url,
+      None => return Ok(None),
+    };
+    let otpauth = OTPAuthUrl::parse(otp_url)?;
+    let now = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .unwrap_or_default()
+      .as_secs();
+
+    Ok(Some(otpauth.generate_token(now)))
+  }
 }
 
 #[allow(clippy::type_complexity)]
 pub fn open_secrets_store(
-  name: &str,
-  url: &str,
-  maybe_remote_url: Option<&str>,
-  node_id: &str,
-  autolock_timeout: Duration,
+  store_config: &StoreConfig,
   event_hub: Arc<dyn EventHub>,
 ) -> SecretStoreResult<(Arc<dyn SecretsStore>, Option<Arc<SyncBlockStore>>)> {
+  let url = store_config.store_url.as_str();
   let (scheme, block_store_url) = match url.find('+') {
     Some(idx) => (&url[..idx], &url[idx + 1..]),
     _ => return Err(SecretStoreError::InvalidStoreUrl(url.to_string())),
   };
 
-  let mut block_store = open_block_store(block_store_url, node_id)?;
+  let mut block_store = open_block_store(block_store_url, &store_config.client_id)?;
 
-  let sync_block_store = match maybe_remote_url {
+  let sync_block_store = match &store_config.remote_url {
     Some(remote_url) => {
-      let remote = open_block_store(remote_url, node_id)?;
+      let remote = open_block_store(remote_url, &store_config.client_id)?;
 
       let sync_block_store = Arc::new(SyncBlockStore::new(block_store, remote));
+      sync_block_store.set_max_rate(store_config.sync_max_rate);
+      sync_block_store.set_concurrency(store_config.sync_concurrency);
 
       block_store = sync_block_store.clone();
 
@@ -67,12 +235,52 @@ pub fn open_secrets_store(
   };
 
   let secrets_store = match scheme {
-    "multilane" => Arc::new(multi_lane::MultiLaneSecretsStore::new(
-      name,
-      block_store,
-      autolock_timeout,
-      event_hub,
-    )),
+    "multilane" => {
+      let secrets_store = multi_lane::MultiLaneSecretsStore::new(
+        &store_config.name,
+        block_store,
+        Duration::from_secs(store_config.autolock_timeout_secs),
+        store_config.unlock_throttle_attempts,
+        event_hub,
+      )
+      .with_default_recipients(store_config.default_recipients.clone())
+      .with_estimator(estimate::create_estimator(&store_config.strength_estimator)?)
+      .with_attachment_storage(store_config.attachment_storage)
+      .with_max_attachment_size(store_config.max_attachment_size)
+      .with_compress_blocks(store_config.compress_blocks);
+      let secrets_store = match store_config.key_derivation_preset {
+        Some(key_derivation_preset) => secrets_store.with_key_derivation_preset(key_derivation_preset),
+        None => secrets_store,
+      };
+      let secrets_store = match store_config.cipher_suite {
+        CipherSuite::All => secrets_store,
+        CipherSuite::RsaAesGcm => secrets_store.with_cipher_suite(KeyType::RsaAesGcm),
+        CipherSuite::X25519Chacha20Poly1305 => secrets_store.with_cipher_suite(KeyType::Ed25519Chacha20Poly1305),
+      };
+      let secrets_store = match &store_config.pepper_file {
+        Some(pepper_file) => secrets_store.with_pepper_file(pepper_file),
+        None => secrets_store,
+      };
+      let secrets_store = match &store_config.breach_dir {
+        Some(breach_dir) => secrets_store.with_breach_dir(breach_dir),
+        None => secrets_store,
+      };
+      // Without throttling there is nothing worth persisting
+      let unlock_throttle_file = match &store_config.unlock_throttle_file {
+        Some(unlock_throttle_file) => Some(PathBuf::from(unlock_throttle_file)),
+        None => throttle::default_unlock_throttle_file(&store_config.name),
+      }
+      .filter(|_| store_config.unlock_throttle_attempts > 0);
+      let secrets_store = match unlock_throttle_file {
+        Some(unlock_throttle_file) => secrets_store.with_unlock_throttle_file(unlock_throttle_file),
+        None => secrets_store,
+      };
+      #[cfg(feature = "with_fido2")]
+      let secrets_store =
+        secrets_store.with_hardware_authenticator(Arc::new(hardware_factor::Fido2Authenticator::default()));
+
+      Arc::new(secrets_store)
+    }
     _ => return Err(SecretStoreError::InvalidStoreUrl(url.to_string())),
   };
 
@@ -1,29 +1,79 @@
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::{Duration, SystemTime};
 
 use capnp::{message, serialize};
+use chrono::Utc;
 
 use crate::memguard::weak::ZeroingHeapAllocator;
-use crate::memguard::SecretBytes;
+use crate::memguard::{memory_locking_active, SecretBytes};
+use crate::secrets_store::attachment_chunks::{decrypt_chunk, encrypt_chunk, split_chunks, MAX_CHUNK_SIZE};
+use crate::secrets_store::breach::lookup_breach_count;
 use crate::secrets_store::cipher::{
   Cipher, KeyDerivation, PrivateKey, PublicKey, RUST_ARGON2_ID, RUST_X25519CHA_CHA20POLY1305,
 };
+use crate::secrets_store::clock::{Clock, SystemClock};
 use crate::secrets_store::estimate::{PasswordEstimator, ZxcvbnEstimator};
+use crate::secrets_store::hardware_factor::{combine_seal_key, HardwareAuthenticator, HARDWARE_SALT_LENGTH};
 use crate::secrets_store::index::Index;
+use crate::secrets_store::merge::merge_concurrent_versions;
 use crate::secrets_store::padding::{NonZeroPadding, Padding, RandomFrontBack};
+use crate::secrets_store::passphrase::normalize_passphrase;
+use crate::secrets_store::pepper::{pepper_passphrase, read_pepper};
+use crate::secrets_store::site::derive_site_key;
+use crate::secrets_store::throttle::UnlockThrottle;
 use crate::secrets_store::{SecretStoreError, SecretStoreResult, SecretsStore};
 use crate::secrets_store_capnp::{block, ring, KeyType};
 use crate::{
   api::ZeroizeDateTime,
-  block_store::{BlockStore, Change, Operation, StoreError},
+  block_store::{committed_block_ids, generate_commit_id, BlockStore, Change, Operation, StoreError},
 };
 use crate::{
-  api::{EventData, EventHub, Identity, Secret, SecretList, SecretListFilter, SecretVersion, Status},
+  api::{
+    find_content_highlights, AttachmentStorage, AuditEntry, AuditPolicy, AuditReason, AuditReport,
+    ChangeLogDiagnostics, DefaultRecipients, EventData, EventHub, Identity, IndexDiagnostics, ReuseGroup,
+    RingDiagnostics, Secret, SecretAttachment, SecretList, SecretListFilter, SecretMergeConflict, SecretVersion,
+    SecretVersionRef, Status, StoreDiagnostics, TagMatch, TagTree, DEFAULT_MAX_ATTACHMENT_SIZE,
+  },
   memguard::ZeroizeBytesBuffer,
 };
-use log::{info, warn};
+use hmac::{Hmac, Mac};
+use log::{debug, info, warn};
 use rand::{thread_rng, RngCore};
-use std::collections::HashMap;
+use sha2::Sha256;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+/// Secrets expiring within this period will be notified via `EventData::SecretExpiring` on unlock.
+const EXPIRY_WARNING_PERIOD_DAYS: i64 = 14;
+
+/// Upper bound of the inflated content of a compressed block (to fend off decompression bombs).
+const MAX_INFLATED_BLOCK_SIZE: usize = 64 * 1024 * 1024;
+
+/// zstd compression level of secret blocks.
+const BLOCK_COMPRESSION_LEVEL: i32 = 9;
+
+/// Compression of the content of a block (as recorded in the `compression` field of the block header).
+///
+/// The values are part of the storage format: Existing values must never change, a new algorithm
+/// gets a new value, so that blocks written with an older algorithm remain readable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum BlockCompression {
+  None = 0,
+  Zstd = 1,
+}
+
+impl BlockCompression {
+  fn from_header(value: u8) -> SecretStoreResult<BlockCompression> {
+    match value {
+      0 => Ok(BlockCompression::None),
+      1 => Ok(BlockCompression::Zstd),
+      _ => Err(SecretStoreError::IO(format!("Unknown block compression: {}", value))),
+    }
+  }
+}
+
+/// Private keys of a ring and the credential id of its hardware factor (if any).
+type OpenedRing = (Vec<(KeyType, PrivateKey)>, Option<Vec<u8>>);
 
 struct User {
   identity: Identity,
@@ -31,6 +81,7 @@ struct User {
   private_keys: Vec<(KeyType, PrivateKey)>,
   autolock_at: SystemTime,
   index: Index,
+  hardware_credential_id: Option<Vec<u8>>,
 }
 
 struct RecipientsForCipher<'a> {
@@ -41,11 +92,25 @@ struct RecipientsForCipher<'a> {
 pub struct MultiLaneSecretsStore {
   name: String,
   ciphers: Vec<&'static dyn Cipher>,
+  /// Ciphers new blocks are encrypted with (identities always have keys for all `ciphers`)
+  block_ciphers: Vec<&'static dyn Cipher>,
   key_derivation: &'static dyn KeyDerivation,
+  key_derivation_preset: u8,
   unlocked_user: RwLock<Option<User>>,
   block_store: Arc<dyn BlockStore>,
   autolock_timeout: Duration,
+  unlock_throttle: Mutex<UnlockThrottle>,
+  unlock_throttle_file: Option<PathBuf>,
+  clock: Arc<dyn Clock>,
   event_hub: Arc<dyn EventHub>,
+  hardware_authenticator: Option<Arc<dyn HardwareAuthenticator>>,
+  default_recipients: DefaultRecipients,
+  estimator: Arc<dyn PasswordEstimator>,
+  attachment_storage: AttachmentStorage,
+  max_attachment_size: usize,
+  compress_blocks: bool,
+  pepper_file: Option<PathBuf>,
+  breach_dir: Option<PathBuf>,
 }
 
 impl MultiLaneSecretsStore {
@@ -53,6 +118,7 @@ impl MultiLaneSecretsStore {
     name: &str,
     block_store: Arc<dyn BlockStore>,
     autolock_timeout: Duration,
+    unlock_throttle_attempts: u32,
     event_hub: Arc<dyn EventHub>,
   ) -> MultiLaneSecretsStore {
     #[cfg(all(feature = "openssl", not(feature = "rust_crypto")))]
@@ -62,13 +128,113 @@ impl MultiLaneSecretsStore {
 
     MultiLaneSecretsStore {
       name: name.to_string(),
+      block_ciphers: ciphers.clone(),
       ciphers,
       key_derivation: &RUST_ARGON2_ID,
+      key_derivation_preset: RUST_ARGON2_ID.default_preset(),
       unlocked_user: RwLock::new(None),
       block_store,
       autolock_timeout,
+      unlock_throttle: Mutex::new(UnlockThrottle::new(unlock_throttle_attempts)),
+      unlock_throttle_file: None,
+      clock: Arc::new(SystemClock),
       event_hub,
+      hardware_authenticator: None,
+      default_recipients: DefaultRecipients::Own,
+      estimator: Arc::new(ZxcvbnEstimator {}),
+      attachment_storage: AttachmentStorage::Inline,
+      max_attachment_size: DEFAULT_MAX_ATTACHMENT_SIZE,
+      compress_blocks: false,
+      pepper_file: None,
+      breach_dir: None,
+    }
+  }
+
+  pub fn with_default_recipients(mut self, default_recipients: DefaultRecipients) -> Self {
+    self.default_recipients = default_recipients;
+    self
+  }
+
+  pub fn with_estimator(mut self, estimator: Arc<dyn PasswordEstimator>) -> Self {
+    self.estimator = estimator;
+    self
+  }
+
+  pub fn with_attachment_storage(mut self, attachment_storage: AttachmentStorage) -> Self {
+    self.attachment_storage = attachment_storage;
+    self
+  }
+
+  /// Maximum size (in bytes) of a single attachment, larger ones are rejected when added
+  pub fn with_max_attachment_size(mut self, max_attachment_size: usize) -> Self {
+    self.max_attachment_size = max_attachment_size;
+    self
+  }
+
+  /// Key derivation preset for sealing the private keys of identities (see `KeyDerivation::calibrate`)
+  pub fn with_key_derivation_preset(mut self, key_derivation_preset: u8) -> Self {
+    self.key_derivation_preset = key_derivation_preset;
+    self
+  }
+
+  /// Compress the content of secret blocks before they are encrypted
+  pub fn with_compress_blocks(mut self, compress_blocks: bool) -> Self {
+    self.compress_blocks = compress_blocks;
+    self
+  }
+
+  /// Encrypt new blocks only with the cipher of `key_type` instead of all ciphers (unknown ones are ignored)
+  pub fn with_cipher_suite(mut self, key_type: KeyType) -> Self {
+    if let Some(cipher) = self.find_cipher(key_type) {
+      self.block_ciphers = vec![cipher];
     }
+    self
+  }
+
+  /// Require the pepper file in addition to the passphrase (see `pepper::read_pepper`)
+  pub fn with_pepper_file<P: Into<PathBuf>>(mut self, pepper_file: P) -> Self {
+    self.pepper_file = Some(pepper_file.into());
+    self
+  }
+
+  /// Keep the state of the unlock throttle in `unlock_throttle_file`, so that it survives a restart
+  /// (otherwise it is only kept in memory). An existing state is restored right away.
+  pub fn with_unlock_throttle_file<P: Into<PathBuf>>(mut self, unlock_throttle_file: P) -> Self {
+    let unlock_throttle_file = unlock_throttle_file.into();
+    if let Ok(unlock_throttle) = self.unlock_throttle.get_mut() {
+      unlock_throttle.read_from(&unlock_throttle_file);
+    }
+    self.unlock_throttle_file = Some(unlock_throttle_file);
+    self
+  }
+
+  pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+    self.clock = clock;
+    self
+  }
+
+  /// Check passwords against a local copy of breached password ranges (see `breach::lookup_breach_count`)
+  pub fn with_breach_dir<P: Into<PathBuf>>(mut self, breach_dir: P) -> Self {
+    self.breach_dir = Some(breach_dir.into());
+    self
+  }
+
+  #[cfg_attr(not(feature = "with_fido2"), allow(dead_code))]
+  pub fn with_hardware_authenticator(mut self, hardware_authenticator: Arc<dyn HardwareAuthenticator>) -> Self {
+    self.hardware_authenticator = Some(hardware_authenticator);
+    self
+  }
+
+  /// Compute the blocks that are still required by the store (see `SecretsStore::collect_garbage`).
+  ///
+  /// These are the versions in the index of the unlocked identity (only the heads and the parents of
+  /// concurrent heads if `prune_history` is set) plus all blocks of the change logs that are not part of
+  /// the index, i.e. that are not readable by the unlocked identity or contain attachment chunks.
+  pub fn compute_live_blocks(&self, prune_history: bool) -> SecretStoreResult<HashSet<String>> {
+    let maybe_unlocked_user = self.unlocked_user.read()?;
+    let unlocked_user = maybe_unlocked_user.as_ref().ok_or(SecretStoreError::Locked)?;
+
+    self.live_blocks(unlocked_user, prune_history)
   }
 }
 
@@ -82,72 +248,36 @@ impl SecretsStore for MultiLaneSecretsStore {
       autolock_at: unlocked_user.as_ref().map(|u| ZeroizeDateTime::from(u.autolock_at)),
       version: env!("CARGO_PKG_VERSION").to_string(),
       autolock_timeout: self.autolock_timeout.as_secs(),
+      unlock_blocked_until: self
+        .unlock_throttle
+        .lock()?
+        .blocked_until(self.clock.now())
+        .map(ZeroizeDateTime::from),
+      memory_locking: memory_locking_active(),
     })
   }
 
   fn lock(&self) -> SecretStoreResult<()> {
-    info!("Locking store");
-    let mut unlocked_user = self.unlocked_user.write()?;
-    unlocked_user.take();
-    self.event_hub.send(EventData::StoreLocked {
-      store_name: self.name.clone(),
-    });
+    self.lock_store(false)
+  }
 
-    Ok(())
+  fn autolock(&self) -> SecretStoreResult<()> {
+    self.lock_store(true)
   }
 
   fn unlock(&self, identity_id: &str, passphrase: SecretBytes) -> SecretStoreResult<()> {
-    let identity = {
-      info!("Unlocking store for {}", identity_id);
-      let mut unlocked_user = self.unlocked_user.write()?;
-
-      if unlocked_user.is_some() {
-        return Err(SecretStoreError::AlreadyUnlocked);
-      }
+    self.unlock_throttle.lock()?.check(self.clock.now())?;
 
-      let mut raw: &[u8] = &self.block_store.get_ring(identity_id)?.1;
-      let reader = serialize::read_message_from_flat_slice(&mut raw, Default::default())?;
-      let ring = reader.get_root::<ring::Reader>()?;
-      let mut private_keys = Vec::with_capacity(self.ciphers.len());
-      let mut public_keys = Vec::with_capacity(self.ciphers.len());
-
-      for user_private_key in ring.get_private_keys()? {
-        if let Some(cipher) = self.find_cipher(user_private_key.get_type()?) {
-          let nonce = user_private_key.get_nonce()?;
-          if user_private_key.get_derivation_type()? != self.key_derivation.key_derivation_type() {
-            return Err(SecretStoreError::KeyDerivation(
-              "Key derivation method is not compatible".to_string(),
-            ));
-          }
-          let seal_key = self.key_derivation.derive(
-            &passphrase,
-            user_private_key.get_preset(),
-            nonce,
-            cipher.seal_key_length(),
-          )?;
-          let private_key = cipher
-            .open_private_key(&seal_key, nonce, user_private_key.get_crypted_key()?)
-            .map_err(|_| SecretStoreError::InvalidPassphrase)?;
-
-          private_keys.push((cipher.key_type(), private_key));
-        }
+    let identity = match self.unlock_identity(identity_id, passphrase) {
+      Ok(identity) => {
+        self.update_unlock_throttle(|throttle| throttle.succeeded())?;
+        identity
       }
-      for user_public_key in ring.get_public_keys()? {
-        if let Some(cipher) = self.find_cipher(user_public_key.get_type()?) {
-          public_keys.push((cipher.key_type(), user_public_key.get_key()?.to_vec()));
-        }
+      Err(SecretStoreError::InvalidPassphrase) => {
+        self.update_unlock_throttle(|throttle| throttle.failed(self.clock.now()))?;
+        return Err(SecretStoreError::InvalidPassphrase);
       }
-      let index = self.read_index(identity_id, &private_keys)?;
-      let identity = Self::identity_from_ring(ring)?;
-      unlocked_user.replace(User {
-        identity: identity.clone(),
-        private_keys,
-        public_keys,
-        autolock_at: SystemTime::now() + self.autolock_timeout,
-        index,
-      });
-
-      identity
+      Err(err) => return Err(err),
     };
 
     self.update_index()?;
@@ -157,9 +287,35 @@ impl SecretsStore for MultiLaneSecretsStore {
       identity,
     });
 
+    // The store is unlocked at this point, so this is just a best-effort reminder
+    if let Err(err) = self.notify_expiring() {
+      warn!("Failed to check for expiring secrets: {}", err);
+    }
+
     Ok(())
   }
 
+  fn verify_passphrase(&self, identity_id: &str, passphrase: SecretBytes) -> SecretStoreResult<bool> {
+    // Subject to the same throttle as unlock, otherwise this would be a free guessing oracle
+    self.unlock_throttle.lock()?.check(self.clock.now())?;
+
+    let mut raw: &[u8] = &self.block_store.get_ring(identity_id)?.1;
+    let reader = serialize::read_message_from_flat_slice(&mut raw, Default::default())?;
+    let ring = reader.get_root::<ring::Reader>()?;
+
+    match self.open_ring(ring, &passphrase) {
+      Ok(_) => {
+        self.update_unlock_throttle(|throttle| throttle.succeeded())?;
+        Ok(true)
+      }
+      Err(SecretStoreError::InvalidPassphrase) => {
+        self.update_unlock_throttle(|throttle| throttle.failed(self.clock.now()))?;
+        Ok(false)
+      }
+      Err(err) => Err(err),
+    }
+  }
+
   fn identities(&self) -> SecretStoreResult<Vec<Identity>> {
     let ring_ids = self.block_store.list_ring_ids()?;
     let mut identities = Vec::with_capacity(ring_ids.len());
@@ -184,6 +340,8 @@ impl SecretsStore for MultiLaneSecretsStore {
     {
       return Err(SecretStoreError::Conflict);
     }
+    let pepper = self.pepper()?;
+    let passphrase = pepper_passphrase(&normalize_passphrase(&passphrase)?, pepper.as_ref())?;
     let mut ring_message = message::Builder::new(ZeroingHeapAllocator::default());
     let mut new_ring = ring_message.init_root::<ring::Builder>();
 
@@ -191,15 +349,30 @@ impl SecretsStore for MultiLaneSecretsStore {
     new_ring.set_name(&identity.name);
     new_ring.set_email(&identity.email);
 
+    let hardware_response = match identity.hardware_factor {
+      true => {
+        let credential_id = self.hardware_authenticator()?.register(&identity.id)?;
+        let salt = Self::generate_nonce(HARDWARE_SALT_LENGTH);
+        let response = self.hardware_authenticator()?.challenge(&credential_id, &salt)?;
+
+        new_ring.set_hardware_factor(true);
+        new_ring.set_hardware_credential_id(&credential_id);
+        new_ring.set_hardware_salt(&salt);
+        Some(response)
+      }
+      false => None,
+    };
+
     new_ring.reborrow().init_public_keys(self.ciphers.len() as u32);
     new_ring.reborrow().init_private_keys(self.ciphers.len() as u32);
 
     for (idx, cipher) in self.ciphers.iter().enumerate() {
       let (public_key, private_key) = cipher.generate_key_pair()?;
       let nonce = Self::generate_nonce(cipher.seal_min_nonce_length().max(self.key_derivation.min_nonce_len()));
-      let seal_key = self.key_derivation.derive(
+      let seal_key = self.seal_key(
         &passphrase,
-        self.key_derivation.default_preset(),
+        hardware_response.as_ref(),
+        self.key_derivation_preset,
         &nonce,
         cipher.seal_key_length(),
       )?;
@@ -216,7 +389,7 @@ impl SecretsStore for MultiLaneSecretsStore {
 
         user_private_key.set_type(cipher.key_type());
         user_private_key.set_derivation_type(self.key_derivation.key_derivation_type());
-        user_private_key.set_preset(self.key_derivation.default_preset());
+        user_private_key.set_preset(self.key_derivation_preset);
         user_private_key.set_nonce(&nonce);
         user_private_key.set_crypted_key(&crypted_key);
       }
@@ -235,6 +408,8 @@ impl SecretsStore for MultiLaneSecretsStore {
   fn change_passphrase(&self, passphrase: SecretBytes) -> SecretStoreResult<()> {
     let maybe_unlocked_user = self.unlocked_user.read()?;
     let unlocked_user = maybe_unlocked_user.as_ref().ok_or(SecretStoreError::Locked)?;
+    let pepper = self.pepper()?;
+    let passphrase = pepper_passphrase(&normalize_passphrase(&passphrase)?, pepper.as_ref())?;
 
     let mut ring_message = message::Builder::new(ZeroingHeapAllocator::default());
     let mut new_ring = ring_message.init_root::<ring::Builder>();
@@ -243,6 +418,20 @@ impl SecretsStore for MultiLaneSecretsStore {
     new_ring.set_name(&unlocked_user.identity.name);
     new_ring.set_email(&unlocked_user.identity.email);
 
+    let hardware_response = match &unlocked_user.hardware_credential_id {
+      Some(credential_id) => {
+        // A fresh salt, so that the old hardware response becomes useless as well
+        let salt = Self::generate_nonce(HARDWARE_SALT_LENGTH);
+        let response = self.hardware_authenticator()?.challenge(credential_id, &salt)?;
+
+        new_ring.set_hardware_factor(true);
+        new_ring.set_hardware_credential_id(credential_id);
+        new_ring.set_hardware_salt(&salt);
+        Some(response)
+      }
+      None => None,
+    };
+
     {
       let mut user_public_keys = new_ring.reborrow().init_public_keys(self.ciphers.len() as u32);
       for (idx, (key_type, public_key)) in unlocked_user.public_keys.iter().enumerate() {
@@ -260,9 +449,10 @@ impl SecretsStore for MultiLaneSecretsStore {
         .find_cipher(*key_type)
         .unwrap_or_else(|| panic!("Unlocked user with unknown cipher"));
       let nonce = Self::generate_nonce(cipher.seal_min_nonce_length().max(self.key_derivation.min_nonce_len()));
-      let seal_key = self.key_derivation.derive(
+      let seal_key = self.seal_key(
         &passphrase,
-        self.key_derivation.default_preset(),
+        hardware_response.as_ref(),
+        self.key_derivation_preset,
         &nonce,
         cipher.seal_key_length(),
       )?;
@@ -270,7 +460,7 @@ impl SecretsStore for MultiLaneSecretsStore {
       let mut user_private_key = user_private_keys.reborrow().get(idx as u32);
 
       user_private_key.set_type(cipher.key_type());
-      user_private_key.set_preset(self.key_derivation.default_preset());
+      user_private_key.set_preset(self.key_derivation_preset);
       user_private_key.set_nonce(&nonce);
       user_private_key.set_crypted_key(&crypted_key);
     }
@@ -289,31 +479,287 @@ impl SecretsStore for MultiLaneSecretsStore {
     let maybe_unlocked_user = self.unlocked_user.read()?;
     let unlocked_user = maybe_unlocked_user.as_ref().ok_or(SecretStoreError::Locked)?;
 
-    unlocked_user.index.filter_entries(filter)
+    let mut list = unlocked_user.index.filter_entries(filter)?;
+    let mut regroup = false;
+
+    if let Some(content) = filter.content.as_deref().filter(|content| !content.is_empty()) {
+      self.filter_content(unlocked_user, &mut list, content);
+      list.total = list.entries.len();
+      regroup = true;
+    }
+    // Entries are already sorted, so pages of the same filter never overlap
+    if filter.offset > 0 || filter.limit.is_some() {
+      let end = match filter.limit {
+        Some(limit) => filter.offset.saturating_add(limit),
+        None => usize::MAX,
+      };
+      list.entries.truncate(end);
+      list.entries.drain(..filter.offset.min(list.entries.len()));
+      regroup = true;
+    }
+    if regroup && filter.group_by_tag {
+      list.tag_tree = Some(TagTree::build(&list.entries));
+    }
+
+    Ok(list)
   }
 
   fn update_index(&self) -> SecretStoreResult<()> {
     let mut maybe_unlocked_user = self.unlocked_user.write()?;
     let unlocked_user = maybe_unlocked_user.as_mut().ok_or(SecretStoreError::Locked)?;
-    let change_logs = self.block_store.change_logs()?;
-    let identity_id = &unlocked_user.identity.id;
-    let private_keys = &unlocked_user.private_keys;
-    let index_updated = unlocked_user.index.process_change_logs(&change_logs, |block_id| {
-      self.get_secret_version(identity_id, private_keys, block_id)
-    })?;
 
-    if index_updated {
-      info!("Index has been updated");
-      self.store_index(&unlocked_user.identity.id, &unlocked_user.index)?;
+    self.update_user_index(unlocked_user)
+  }
+
+  fn rebuild_index(&self) -> SecretStoreResult<()> {
+    let mut maybe_unlocked_user = self.unlocked_user.write()?;
+    let unlocked_user = maybe_unlocked_user.as_mut().ok_or(SecretStoreError::Locked)?;
+
+    info!("Rebuilding index from scratch");
+    unlocked_user.index = Default::default();
+    self.update_user_index(unlocked_user)
+  }
+
+  fn wipe_index(&self) -> SecretStoreResult<()> {
+    for identity in self.identities()? {
+      if let Some(index) = self.block_store.get_index(&identity.id)? {
+        info!("Wiping index of {}", identity.id);
+        self.block_store.store_index(&identity.id, &vec![0u8; index.len()])?;
+      }
     }
 
     Ok(())
   }
 
+  fn revoke_recipient(&self, recipient_id: &str) -> SecretStoreResult<Vec<String>> {
+    let mut maybe_unlocked_user = self.unlocked_user.write()?;
+    let unlocked_user = maybe_unlocked_user.as_mut().ok_or(SecretStoreError::Locked)?;
+
+    if unlocked_user.identity.id == recipient_id {
+      return Err(SecretStoreError::InvalidRecipient(recipient_id.to_string()));
+    }
+
+    let timestamp: ZeroizeDateTime = Utc::now().into();
+    let mut changes = vec![];
+    let mut revoked = vec![];
+
+    for block_id in unlocked_user.index.current_block_ids()? {
+      let current =
+        match self.read_secret_version(&unlocked_user.identity.id, &unlocked_user.private_keys, &block_id)? {
+          Some(current) if current.recipients.iter().any(|recipient| recipient == recipient_id) => current,
+          _ => continue,
+        };
+      let mut secret_version = current.clone();
+
+      secret_version.recipients.retain(|recipient| recipient != recipient_id);
+      secret_version.timestamp = timestamp;
+      secret_version.parent_block_id = Some(block_id.clone());
+      secret_version.modified_by = Some(unlocked_user.identity.id.clone());
+
+      let mut tombstone = secret_version.clone();
+
+      tombstone.deleted = true;
+      tombstone.tags.clear();
+      tombstone.urls.clear();
+      tombstone.properties = Default::default();
+      tombstone.attachments.clear();
+      tombstone.expires_at = None;
+      tombstone.recipients = vec![recipient_id.to_string()];
+
+      for version in [&secret_version, &tombstone] {
+        let block_content = self.encrypt_secret_version(version)?;
+
+        changes.push(Change {
+          op: Operation::Add,
+          block: self.block_store.add_block(&block_content)?,
+        });
+      }
+      revoked.push(current.secret_id.clone());
+    }
+    if !changes.is_empty() {
+      info!("Revoked {} from {} secrets", recipient_id, revoked.len());
+      self.block_store.commit(&generate_commit_id(), &changes)?;
+      self.update_user_index(unlocked_user)?;
+    }
+
+    Ok(revoked)
+  }
+
+  fn rotate_cipher(&self, target: KeyType) -> SecretStoreResult<usize> {
+    let mut maybe_unlocked_user = self.unlocked_user.write()?;
+    let unlocked_user = maybe_unlocked_user.as_mut().ok_or(SecretStoreError::Locked)?;
+    let cipher = self
+      .find_cipher(target)
+      .ok_or_else(|| SecretStoreError::Cipher("Unknown cipher".to_string()))?;
+    let mut changes = vec![];
+
+    info!("Rotating all readable blocks to {}", cipher.name());
+    for block_id in unlocked_user.index.block_ids()? {
+      let block_words = self.block_store.get_block(&block_id)?;
+
+      if Self::block_key_types(&block_words)? == [target] {
+        continue;
+      }
+      let (padded_content, compression) =
+        match self.decrypt_block(&unlocked_user.identity.id, &unlocked_user.private_keys, &block_words)? {
+          Some(decrypted) => decrypted,
+          None => continue,
+        };
+      let secret_version = Self::decode_secret_version(&padded_content, compression)?;
+      let block_content =
+        self.encrypt_block_with(&[cipher], &secret_version.recipients, padded_content, compression)?;
+
+      // The old block is only removed from the index, the block itself remains in the store
+      changes.push(Change {
+        op: Operation::Add,
+        block: self.block_store.add_block(&block_content)?,
+      });
+      changes.push(Change {
+        op: Operation::Delete,
+        block: block_id,
+      });
+    }
+    if changes.is_empty() {
+      return Ok(0);
+    }
+    self.block_store.commit(&generate_commit_id(), &changes)?;
+    self.update_user_index(unlocked_user)?;
+
+    Ok(changes.len() / 2)
+  }
+
+  fn collect_garbage(&self, prune_history: bool) -> SecretStoreResult<usize> {
+    let mut maybe_unlocked_user = self.unlocked_user.write()?;
+    let unlocked_user = maybe_unlocked_user.as_mut().ok_or(SecretStoreError::Locked)?;
+
+    self.update_user_index(unlocked_user)?;
+    self.check_garbage_collection(unlocked_user)?;
+
+    let live_blocks = self.live_blocks(unlocked_user, prune_history)?;
+    if prune_history {
+      // Pruned versions are deleted in the change log as well, so that they are neither re-synchronized
+      // nor missed by other nodes
+      let pruned: Vec<Change> = unlocked_user
+        .index
+        .block_ids()?
+        .into_iter()
+        .filter(|block_id| !live_blocks.contains(block_id))
+        .map(|block_id| Change::new(Operation::Delete, block_id))
+        .collect();
+      if !pruned.is_empty() {
+        info!("Pruning {} previous versions", pruned.len());
+        self.block_store.commit(&generate_commit_id(), &pruned)?;
+        self.update_user_index(unlocked_user)?;
+      }
+    }
+
+    let removed = self.block_store.collect_garbage(&live_blocks)?;
+    info!("Removed {} unreferenced blocks", removed);
+
+    Ok(removed)
+  }
+
+  fn flush(&self) -> SecretStoreResult<()> {
+    Ok(self.block_store.flush()?)
+  }
+
+  fn diagnose(&self) -> SecretStoreResult<StoreDiagnostics> {
+    let stats = self.block_store.storage_stats()?;
+    let mut rings = vec![];
+    let mut indexes = vec![];
+
+    for (ring_id, version) in self.block_store.list_ring_ids()? {
+      let mut raw: &[u8] = &self.block_store.get_ring(&ring_id)?.1;
+      let reader = serialize::read_message_from_flat_slice(&mut raw, Default::default())?;
+      let ring = reader.get_root::<ring::Reader>()?;
+      let mut key_types = vec![];
+      let mut key_derivations = vec![];
+
+      for public_key in ring.get_public_keys()? {
+        key_types.push(format!("{:?}", public_key.get_type()?));
+      }
+      // Only the (public) parameters of the key derivation, never the sealed keys themselves
+      for private_key in ring.get_private_keys()? {
+        key_derivations.push(format!(
+          "{:?}/{:?} (preset {})",
+          private_key.get_type()?,
+          private_key.get_derivation_type()?,
+          private_key.get_preset()
+        ));
+      }
+      rings.push(RingDiagnostics {
+        versions: stats
+          .ring_versions
+          .iter()
+          .find(|(id, _)| id == &ring_id)
+          .map(|(_, versions)| *versions)
+          .unwrap_or(1),
+        version,
+        hidden: ring.get_hidden(),
+        hardware_factor: ring.get_hardware_factor(),
+        key_types,
+        key_derivations,
+        id: ring_id.clone(),
+      });
+      indexes.push(IndexDiagnostics {
+        size: self
+          .block_store
+          .get_index(&ring_id)?
+          .map(|index| index.len() as u64 * 8),
+        identity_id: ring_id,
+      });
+    }
+
+    let change_logs = self
+      .block_store
+      .change_logs()?
+      .iter()
+      .map(|change_log| ChangeLogDiagnostics {
+        node: change_log.node.clone(),
+        adds: change_log
+          .changes
+          .iter()
+          .filter(|change| change.op == Operation::Add)
+          .count(),
+        deletes: change_log
+          .changes
+          .iter()
+          .filter(|change| change.op == Operation::Delete)
+          .count(),
+        commits: change_log.commits.len(),
+      })
+      .collect();
+
+    Ok(StoreDiagnostics {
+      locked: self.unlocked_user.read()?.is_none(),
+      ciphers: self.ciphers.iter().map(|cipher| cipher.name()).collect(),
+      block_count: stats.block_count,
+      total_bytes: stats.total_bytes,
+      missing_blocks: stats.missing_blocks,
+      rings,
+      change_logs,
+      indexes,
+    })
+  }
+
   fn add(&self, mut secret_version: SecretVersion) -> SecretStoreResult<String> {
-    let maybe_unlocked_user = self.unlocked_user.read()?;
-    let unlocked_user = maybe_unlocked_user.as_ref().ok_or(SecretStoreError::Locked)?;
+    let mut maybe_unlocked_user = self.unlocked_user.write()?;
+    let unlocked_user = maybe_unlocked_user.as_mut().ok_or(SecretStoreError::Locked)?;
 
+    if let Some(attachment) = secret_version
+      .attachments
+      .iter()
+      .find(|attachment| attachment.content().len() > self.max_attachment_size)
+    {
+      return Err(SecretStoreError::AttachmentTooLarge(
+        attachment.name().to_string(),
+        self.max_attachment_size,
+      ));
+    }
+
+    if secret_version.recipients.is_empty() {
+      secret_version.recipients = self.expand_default_recipients()?;
+    }
     if !secret_version
       .recipients
       .iter()
@@ -322,22 +768,23 @@ impl SecretsStore for MultiLaneSecretsStore {
       // User adding a secret version to the store is always a recipient
       secret_version.recipients.push(unlocked_user.identity.id.clone());
     }
+    secret_version.modified_by = Some(unlocked_user.identity.id.clone());
 
-    let block_content = {
-      let mut buffer = ZeroizeBytesBuffer::with_capacity(1024);
-      serde_json::to_writer(&mut buffer, &secret_version)?;
-
-      self.ecnrypt_block(
-        &secret_version.recipients,
-        NonZeroPadding::pad_secret_data(&buffer, 512)?,
-      )?
+    let mut changes = match self.attachment_storage {
+      AttachmentStorage::Inline => vec![],
+      AttachmentStorage::Chunked => self.store_attachment_chunks(&mut secret_version, false)?,
+      AttachmentStorage::ChunkedCompressed => self.store_attachment_chunks(&mut secret_version, true)?,
     };
+    let block_content = self.encrypt_secret_version(&secret_version)?;
 
     let block_id = self.block_store.add_block(&block_content)?;
-    self.block_store.commit(&[Change {
+    changes.push(Change {
       op: Operation::Add,
       block: block_id.clone(),
-    }])?;
+    });
+    self.block_store.commit(&generate_commit_id(), &changes)?;
+    // Only the new block (and whatever has been synchronized in the meantime) has to be folded in
+    self.update_user_index(unlocked_user)?;
     self.event_hub.send(EventData::SecretVersionAdded {
       store_name: self.name.clone(),
       secret_id: secret_version.secret_id.clone(),
@@ -355,18 +802,26 @@ impl SecretsStore for MultiLaneSecretsStore {
     assert!(!versions.is_empty());
 
     let current_block_id = versions.first().unwrap().block_id.clone();
-    let current = self
+    let mut current = self
       .get_secret_version(
         &unlocked_user.identity.id,
         &unlocked_user.private_keys,
         &current_block_id,
       )?
       .ok_or(SecretStoreError::NotFound)?;
+    let mut merge_conflicts = vec![];
+
+    if let Some((merged, conflicts)) = self.merge_concurrent(unlocked_user, &current_block_id, &current, &versions) {
+      current = merged;
+      merge_conflicts = conflicts;
+    }
     let mut password_strengths = HashMap::with_capacity(current.secret_type.password_properties().len());
+    let mut user_inputs = current.strength_inputs();
 
+    user_inputs.push(&unlocked_user.identity.name);
     for property in current.secret_type.password_properties() {
       if let Some(value) = current.properties.get(property) {
-        let strength = ZxcvbnEstimator::estimate_strength(value, &[&current.name, &unlocked_user.identity.name]);
+        let strength = self.estimator.estimate_strength(value, &user_inputs);
 
         password_strengths.insert((*property).to_string(), strength);
       }
@@ -384,9 +839,124 @@ impl SecretsStore for MultiLaneSecretsStore {
       current_block_id,
       versions,
       password_strengths,
+      merge_conflicts,
     })
   }
 
+  fn find_reused_passwords(&self) -> SecretStoreResult<Vec<ReuseGroup>> {
+    let maybe_unlocked_user = self.unlocked_user.read()?;
+    let unlocked_user = maybe_unlocked_user.as_ref().ok_or(SecretStoreError::Locked)?;
+    let versions = self.read_current_versions(unlocked_user)?;
+
+    Ok(Self::reuse_groups(&versions))
+  }
+
+  fn check_breached(&self, secret_id: &str) -> SecretStoreResult<Option<u64>> {
+    if self.breach_dir.is_none() {
+      return Ok(None);
+    }
+    // Not using `get` here: Checking for breaches should not count as opening the secret
+    let maybe_unlocked_user = self.unlocked_user.read()?;
+    let unlocked_user = maybe_unlocked_user.as_ref().ok_or(SecretStoreError::Locked)?;
+    let versions = unlocked_user.index.find_versions(secret_id)?;
+    let current_block_id = &versions.first().ok_or(SecretStoreError::NotFound)?.block_id;
+    let mut current = self
+      .get_secret_version(
+        &unlocked_user.identity.id,
+        &unlocked_user.private_keys,
+        current_block_id,
+      )?
+      .ok_or(SecretStoreError::NotFound)?;
+    if let Some((merged, _)) = self.merge_concurrent(unlocked_user, current_block_id, &current, &versions) {
+      current = merged;
+    }
+
+    self.breach_count(&current)
+  }
+
+  fn derive_site_key(&self, site: &str, key_length: usize) -> SecretStoreResult<SecretBytes> {
+    let maybe_unlocked_user = self.unlocked_user.read()?;
+    let unlocked_user = maybe_unlocked_user.as_ref().ok_or(SecretStoreError::Locked)?;
+
+    derive_site_key(&unlocked_user.private_keys, site, key_length)
+  }
+
+  fn audit(&self, policy: &AuditPolicy) -> SecretStoreResult<AuditReport> {
+    let maybe_unlocked_user = self.unlocked_user.read()?;
+    let unlocked_user = maybe_unlocked_user.as_ref().ok_or(SecretStoreError::Locked)?;
+    let versions = self.read_current_versions(unlocked_user)?;
+    let reuse_groups = if policy.include_reuse {
+      Self::reuse_groups(&versions)
+    } else {
+      vec![]
+    };
+    let now = ZeroizeDateTime::now();
+    let mut report = AuditReport::default();
+
+    for version in &versions {
+      let password_properties = version
+        .secret_type
+        .password_properties()
+        .iter()
+        .filter(|property| version.properties.has_non_empty(property))
+        .collect::<Vec<_>>();
+      // Only passwords are audited, i.e. secrets without any are always fine
+      if password_properties.is_empty() {
+        continue;
+      }
+      report.checked += 1;
+
+      let mut reasons = vec![];
+      let mut user_inputs = version.strength_inputs();
+
+      user_inputs.push(&unlocked_user.identity.name);
+      for property in password_properties {
+        if let Some(value) = version.properties.get(property) {
+          let strength = self.estimator.estimate_strength(value, &user_inputs);
+
+          if strength.score < policy.min_score {
+            reasons.push(AuditReason::Weak {
+              property: property.to_string(),
+              score: strength.score,
+            });
+          }
+        }
+      }
+      let age_days = (now - version.timestamp).num_days();
+      if policy.max_age_days > 0 && age_days > policy.max_age_days as i64 {
+        reasons.push(AuditReason::Old { days: age_days });
+      }
+      if let Some(group) = reuse_groups
+        .iter()
+        .find(|group| group.secret_ids.contains(&version.secret_id))
+      {
+        reasons.push(AuditReason::Reused {
+          secret_ids: group
+            .secret_ids
+            .iter()
+            .filter(|secret_id| **secret_id != version.secret_id)
+            .cloned()
+            .collect(),
+        });
+      }
+      if policy.include_breached {
+        if let Some(count) = self.breach_count(version)? {
+          reasons.push(AuditReason::Breached { count });
+        }
+      }
+
+      if !reasons.is_empty() {
+        report.entries.push(AuditEntry {
+          secret_id: version.secret_id.clone(),
+          name: version.name.clone(),
+          reasons,
+        });
+      }
+    }
+
+    Ok(report)
+  }
+
   fn get_version(&self, block_id: &str) -> SecretStoreResult<SecretVersion> {
     let maybe_unlocked_user = self.unlocked_user.read()?;
     let unlocked_user = maybe_unlocked_user.as_ref().ok_or(SecretStoreError::Locked)?;
@@ -395,9 +965,414 @@ impl SecretsStore for MultiLaneSecretsStore {
       .get_secret_version(&unlocked_user.identity.id, &unlocked_user.private_keys, block_id)?
       .ok_or(SecretStoreError::NotFound)
   }
+
+  fn get_attachment_chunk(
+    &self,
+    secret_id: &str,
+    name: &str,
+    index: usize,
+  ) -> SecretStoreResult<Option<SecretAttachment>> {
+    let maybe_unlocked_user = self.unlocked_user.read()?;
+    let unlocked_user = maybe_unlocked_user.as_ref().ok_or(SecretStoreError::Locked)?;
+    let versions = unlocked_user.index.find_versions(secret_id)?;
+    let current_block_id = &versions.first().ok_or(SecretStoreError::NotFound)?.block_id;
+    // Only the chunk requested is read, not the entire attachment (like `get` would)
+    let current = self
+      .read_secret_version(
+        &unlocked_user.identity.id,
+        &unlocked_user.private_keys,
+        current_block_id,
+      )?
+      .ok_or(SecretStoreError::NotFound)?;
+    let attachment = current
+      .attachments
+      .iter()
+      .find(|attachment| attachment.name() == name)
+      .ok_or(SecretStoreError::NotFound)?;
+
+    if attachment.chunks().is_empty() {
+      return Ok(Some(attachment.clone()).filter(|_| index == 0));
+    }
+    match attachment.chunks().get(index) {
+      Some(chunk) => {
+        let raw = self.block_store.get_block(&chunk.block_id)?;
+
+        Ok(Some(
+          attachment.with_content(decrypt_chunk(chunk, &raw)?.borrow().to_vec()),
+        ))
+      }
+      None => Ok(None),
+    }
+  }
+
+  fn find_concurrent_versions(&self, block_ids: &[String]) -> SecretStoreResult<Vec<String>> {
+    let maybe_unlocked_user = self.unlocked_user.read()?;
+    let unlocked_user = maybe_unlocked_user.as_ref().ok_or(SecretStoreError::Locked)?;
+    let mut secret_ids = HashSet::new();
+
+    for block_id in block_ids {
+      // Blocks that are not readable by the unlocked identity (or are not secrets at all) are irrelevant
+      let version = match self.get_secret_version(&unlocked_user.identity.id, &unlocked_user.private_keys, block_id) {
+        Ok(Some(version)) => version,
+        _ => continue,
+      };
+      let parent_block_id = match &version.parent_block_id {
+        Some(parent_block_id) => parent_block_id,
+        None => continue,
+      };
+      let versions = match unlocked_user.index.find_versions(&version.secret_id) {
+        Ok(versions) => versions,
+        Err(SecretStoreError::NotFound) => continue,
+        Err(err) => return Err(err),
+      };
+      // Versions are ordered by timestamp, anything older than the parent can not be concurrent
+      for other in versions.iter() {
+        if &other.block_id == parent_block_id {
+          break;
+        }
+        if &other.block_id == block_id {
+          continue;
+        }
+        let other_version =
+          self.get_secret_version(&unlocked_user.identity.id, &unlocked_user.private_keys, &other.block_id)?;
+        if matches!(other_version, Some(other_version) if other_version.parent_block_id.as_ref() == Some(parent_block_id))
+        {
+          info!(
+            "Concurrent versions of {}: {} and {}",
+            version.secret_id, block_id, other.block_id
+          );
+          secret_ids.insert(version.secret_id.clone());
+          break;
+        }
+      }
+    }
+
+    let mut secret_ids: Vec<String> = secret_ids.into_iter().collect();
+    secret_ids.sort();
+
+    Ok(secret_ids)
+  }
 }
 
 impl MultiLaneSecretsStore {
+  fn lock_store(&self, autolock: bool) -> SecretStoreResult<()> {
+    info!("Locking store");
+    let mut unlocked_user = self.unlocked_user.write()?;
+    unlocked_user.take();
+    self.event_hub.send(EventData::StoreLocked {
+      store_name: self.name.clone(),
+      autolock,
+    });
+
+    Ok(())
+  }
+
+  /// Incrementally update the index of the unlocked user, i.e. only blocks added since the last update
+  /// are decrypted. The index is rebuilt from scratch if it does not fit the change logs.
+  fn update_user_index(&self, unlocked_user: &mut User) -> SecretStoreResult<()> {
+    let change_logs = self.block_store.change_logs()?;
+    let identity_id = &unlocked_user.identity.id;
+    let private_keys = &unlocked_user.private_keys;
+    let index_updated = unlocked_user.index.process_change_logs(&change_logs, |block_id| {
+      self.read_secret_version(identity_id, private_keys, block_id)
+    })?;
+
+    if index_updated {
+      info!("Index has been updated");
+      // As long as there are unavailable blocks the index is kept in-memory only, so that
+      // a later unlock will pick them up again
+      if unlocked_user.index.has_unavailable_blocks() {
+        warn!("Index contains unavailable blocks, not storing it");
+      } else {
+        self.store_index(&unlocked_user.identity.id, &unlocked_user.index)?;
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Blocks of the change logs that are not part of the index are always live. A synchronization that
+  /// finishes after this has been computed might add more of those, which is why `SyncBlockStore`
+  /// extends the set with `committed_block_ids` of its local change logs before collecting.
+  fn live_blocks(&self, unlocked_user: &User, prune_history: bool) -> SecretStoreResult<HashSet<String>> {
+    let indexed: HashSet<String> = unlocked_user.index.block_ids()?.into_iter().collect();
+    let mut live_blocks: HashSet<String> = if prune_history {
+      self.head_block_ids(unlocked_user, &indexed)?
+    } else {
+      indexed.clone()
+    };
+
+    live_blocks.extend(
+      committed_block_ids(&self.block_store.change_logs()?)
+        .into_iter()
+        .filter(|block_id| !indexed.contains(block_id)),
+    );
+
+    Ok(live_blocks)
+  }
+
+  /// Versions that are not the parent of any other version, i.e. the current version of each secret plus
+  /// all concurrent versions that have not been merged yet. The common parent of concurrent versions is
+  /// kept as well, since it is the base of their merge.
+  fn head_block_ids(&self, unlocked_user: &User, indexed: &HashSet<String>) -> SecretStoreResult<HashSet<String>> {
+    let mut parent_block_ids: HashMap<&str, String> = HashMap::new();
+
+    for block_id in indexed {
+      if let Some(parent_block_id) = self
+        .read_secret_version(&unlocked_user.identity.id, &unlocked_user.private_keys, block_id)?
+        .and_then(|version| version.parent_block_id.clone())
+      {
+        parent_block_ids.insert(block_id, parent_block_id);
+      }
+    }
+
+    let parents: HashSet<&str> = parent_block_ids.values().map(String::as_str).collect();
+    let mut head_block_ids: HashSet<String> = indexed
+      .iter()
+      .filter(|block_id| !parents.contains(block_id.as_str()))
+      .cloned()
+      .collect();
+    let mut heads_by_parent: HashMap<&str, usize> = HashMap::new();
+    for block_id in &head_block_ids {
+      if let Some(parent_block_id) = parent_block_ids.get(block_id.as_str()) {
+        *heads_by_parent.entry(parent_block_id).or_default() += 1;
+      }
+    }
+    head_block_ids.extend(
+      heads_by_parent
+        .into_iter()
+        .filter(|(_, count)| *count > 1)
+        .map(|(parent_block_id, _)| parent_block_id.to_string()),
+    );
+
+    Ok(head_block_ids)
+  }
+
+  /// Garbage collection is only safe if the store is complete, i.e. all rings are available and
+  /// the index is up to date.
+  fn check_garbage_collection(&self, unlocked_user: &User) -> SecretStoreResult<()> {
+    for (ring_id, _) in self.block_store.list_ring_ids()? {
+      if let Err(err) = self.block_store.get_ring(&ring_id) {
+        return Err(SecretStoreError::GarbageCollectionRefused(format!(
+          "Ring {} not available: {}",
+          ring_id, err
+        )));
+      }
+    }
+    if self.block_store.get_index(&unlocked_user.identity.id)?.is_none() {
+      return Err(SecretStoreError::GarbageCollectionRefused(format!(
+        "Index of {} not available",
+        unlocked_user.identity.id
+      )));
+    }
+    if unlocked_user.index.has_unavailable_blocks() {
+      return Err(SecretStoreError::GarbageCollectionRefused(
+        "Index refers to unavailable blocks".to_string(),
+      ));
+    }
+
+    Ok(())
+  }
+
+  fn unlock_identity(&self, identity_id: &str, passphrase: SecretBytes) -> SecretStoreResult<Identity> {
+    info!("Unlocking store for {}", identity_id);
+    let mut unlocked_user = self.unlocked_user.write()?;
+
+    if unlocked_user.is_some() {
+      return Err(SecretStoreError::AlreadyUnlocked);
+    }
+
+    let mut raw: &[u8] = &self.block_store.get_ring(identity_id)?.1;
+    let reader = serialize::read_message_from_flat_slice(&mut raw, Default::default())?;
+    let ring = reader.get_root::<ring::Reader>()?;
+    let mut public_keys = Vec::with_capacity(self.ciphers.len());
+    let (private_keys, hardware_credential_id) = self.open_ring(ring, &passphrase)?;
+
+    for user_public_key in ring.get_public_keys()? {
+      if let Some(cipher) = self.find_cipher(user_public_key.get_type()?) {
+        public_keys.push((cipher.key_type(), user_public_key.get_key()?.to_vec()));
+      }
+    }
+    let index = self.read_index(identity_id, &private_keys)?;
+    let identity = Self::identity_from_ring(ring)?;
+    unlocked_user.replace(User {
+      identity: identity.clone(),
+      private_keys,
+      public_keys,
+      autolock_at: self.clock.now() + self.autolock_timeout,
+      index,
+      hardware_credential_id,
+    });
+
+    Ok(identity)
+  }
+
+  /// Open the private keys of a ring with all factors (passphrase, hardware factor and pepper).
+  fn open_ring(&self, ring: ring::Reader, passphrase: &SecretBytes) -> SecretStoreResult<OpenedRing> {
+    let (hardware_credential_id, hardware_response) = match ring.get_hardware_factor() {
+      true => {
+        let credential_id = ring.get_hardware_credential_id()?.to_vec();
+        let response = self
+          .hardware_authenticator()?
+          .challenge(&credential_id, ring.get_hardware_salt()?)?;
+        (Some(credential_id), Some(response))
+      }
+      false => (None, None),
+    };
+    let pepper = self.pepper()?;
+    let normalized_passphrase = normalize_passphrase(passphrase)?;
+    let private_keys = match self.open_private_keys(
+      ring,
+      &pepper_passphrase(&normalized_passphrase, pepper.as_ref())?,
+      hardware_response.as_ref(),
+    ) {
+      // Rings created before passphrases have been normalized
+      Err(SecretStoreError::InvalidPassphrase) if &normalized_passphrase != passphrase => self.open_private_keys(
+        ring,
+        &pepper_passphrase(passphrase, pepper.as_ref())?,
+        hardware_response.as_ref(),
+      )?,
+      result => result?,
+    };
+
+    Ok((private_keys, hardware_credential_id))
+  }
+
+  fn open_private_keys(
+    &self,
+    ring: ring::Reader,
+    passphrase: &SecretBytes,
+    hardware_response: Option<&SecretBytes>,
+  ) -> SecretStoreResult<Vec<(KeyType, PrivateKey)>> {
+    let mut private_keys = Vec::with_capacity(self.ciphers.len());
+
+    for user_private_key in ring.get_private_keys()? {
+      if let Some(cipher) = self.find_cipher(user_private_key.get_type()?) {
+        let nonce = user_private_key.get_nonce()?;
+        if user_private_key.get_derivation_type()? != self.key_derivation.key_derivation_type() {
+          return Err(SecretStoreError::KeyDerivation(
+            "Key derivation method is not compatible".to_string(),
+          ));
+        }
+        let seal_key = self.seal_key(
+          passphrase,
+          hardware_response,
+          user_private_key.get_preset(),
+          nonce,
+          cipher.seal_key_length(),
+        )?;
+        let private_key = cipher
+          .open_private_key(&seal_key, nonce, user_private_key.get_crypted_key()?)
+          .map_err(|_| SecretStoreError::InvalidPassphrase)?;
+
+        private_keys.push((cipher.key_type(), private_key));
+      }
+    }
+
+    Ok(private_keys)
+  }
+
+  /// Merge the current version with a concurrent version (i.e. derived from the same parent).
+  /// Both versions are kept in the history, the merged version only exists until it is
+  /// explicitly added.
+  fn merge_concurrent(
+    &self,
+    unlocked_user: &User,
+    current_block_id: &str,
+    current: &SecretVersion,
+    versions: &[SecretVersionRef],
+  ) -> Option<(SecretVersion, Vec<SecretMergeConflict>)> {
+    let parent_block_id = current.parent_block_id.as_ref()?;
+    let concurrent_block_id = &versions.get(1)?.block_id;
+
+    if concurrent_block_id == parent_block_id {
+      return None;
+    }
+    let concurrent = self
+      .get_secret_version(
+        &unlocked_user.identity.id,
+        &unlocked_user.private_keys,
+        concurrent_block_id,
+      )
+      .ok()??;
+    if concurrent.parent_block_id.as_ref() != Some(parent_block_id) {
+      return None;
+    }
+    let parent = self
+      .get_secret_version(&unlocked_user.identity.id, &unlocked_user.private_keys, parent_block_id)
+      .ok()??;
+
+    debug!(
+      "Merging concurrent versions {} and {}",
+      current_block_id, concurrent_block_id
+    );
+
+    Some(merge_concurrent_versions(
+      &parent,
+      (current_block_id, current),
+      (concurrent_block_id, &concurrent),
+    ))
+  }
+
+  fn notify_expiring(&self) -> SecretStoreResult<()> {
+    let expiring = self.list(&SecretListFilter {
+      url: None,
+      tags: vec![],
+      tag_match: TagMatch::All,
+      secret_type: None,
+      name: None,
+      deleted: false,
+      expiring_before: Some((Utc::now() + chrono::Duration::days(EXPIRY_WARNING_PERIOD_DAYS)).into()),
+      modified_after: None,
+      modified_before: None,
+      group_by_tag: false,
+      content: None,
+      offset: 0,
+      limit: None,
+    })?;
+
+    for entry_match in &expiring.entries {
+      if let Some(expires_at) = entry_match.entry.expires_at {
+        self.event_hub.send(EventData::SecretExpiring {
+          store_name: self.name.clone(),
+          secret_id: entry_match.entry.id.clone(),
+          expires_at,
+        });
+      }
+    }
+
+    Ok(())
+  }
+
+  fn hardware_authenticator(&self) -> SecretStoreResult<&dyn HardwareAuthenticator> {
+    self
+      .hardware_authenticator
+      .as_deref()
+      .ok_or_else(|| SecretStoreError::HardwareFactor("No hardware authenticator available".to_string()))
+  }
+
+  /// Read the pepper (if the store requires one).
+  fn pepper(&self) -> SecretStoreResult<Option<SecretBytes>> {
+    self.pepper_file.as_ref().map(read_pepper).transpose()
+  }
+
+  /// Derive the key to seal a private key, with a hardware factor the passphrase alone is not sufficient.
+  fn seal_key(
+    &self,
+    passphrase: &SecretBytes,
+    hardware_response: Option<&SecretBytes>,
+    preset: u8,
+    nonce: &[u8],
+    length: usize,
+  ) -> SecretStoreResult<SecretBytes> {
+    let passphrase_key = self.key_derivation.derive(passphrase, preset, nonce, length)?;
+
+    match hardware_response {
+      Some(hardware_response) => combine_seal_key(&passphrase_key, hardware_response, nonce, length),
+      None => Ok(passphrase_key),
+    }
+  }
+
   fn generate_nonce(len: usize) -> Vec<u8> {
     let mut rng = thread_rng();
     let mut nonce = vec![0u8; len];
@@ -407,6 +1382,27 @@ impl MultiLaneSecretsStore {
     nonce
   }
 
+  /// Update the throttle of unlock attempts, changes are persisted (if there is a `unlock_throttle_file`)
+  /// so that they survive a restart.
+  fn update_unlock_throttle<F>(&self, update: F) -> SecretStoreResult<()>
+  where
+    F: FnOnce(&mut UnlockThrottle),
+  {
+    let mut unlock_throttle = self.unlock_throttle.lock()?;
+    let before = unlock_throttle.to_bytes();
+
+    update(&mut unlock_throttle);
+
+    if let Some(unlock_throttle_file) = &self.unlock_throttle_file {
+      if unlock_throttle.to_bytes() != before {
+        if let Err(err) = unlock_throttle.write_to(unlock_throttle_file) {
+          warn!("Unable to persist state of unlock throttle: {}", err);
+        }
+      }
+    }
+    Ok(())
+  }
+
   fn find_cipher(&self, key_type: KeyType) -> Option<&'static dyn Cipher> {
     for cipher in self.ciphers.iter() {
       if cipher.key_type() == key_type {
@@ -422,12 +1418,31 @@ impl MultiLaneSecretsStore {
       name: ring.get_name()?.to_string()?,
       email: ring.get_email()?.to_string()?,
       hidden: ring.get_hidden(),
+      hardware_factor: ring.get_hardware_factor(),
     })
   }
 
-  fn find_recipients<'a, T: AsRef<str>>(&self, recipients: &'a [T]) -> SecretStoreResult<Vec<RecipientsForCipher<'a>>> {
-    let mut recipients_for_cipher: Vec<RecipientsForCipher<'a>> = self
-      .ciphers
+  fn expand_default_recipients(&self) -> SecretStoreResult<Vec<String>> {
+    match &self.default_recipients {
+      DefaultRecipients::Own => Ok(vec![]),
+      DefaultRecipients::All => Ok(
+        self
+          .block_store
+          .list_ring_ids()?
+          .into_iter()
+          .map(|(ring_id, _)| ring_id)
+          .collect(),
+      ),
+      DefaultRecipients::Identities(identity_ids) => Ok(identity_ids.clone()),
+    }
+  }
+
+  fn find_recipients<'a, T: AsRef<str>>(
+    &self,
+    ciphers: &[&'static dyn Cipher],
+    recipients: &'a [T],
+  ) -> SecretStoreResult<Vec<RecipientsForCipher<'a>>> {
+    let mut recipients_for_cipher: Vec<RecipientsForCipher<'a>> = ciphers
       .iter()
       .map(|cipher| RecipientsForCipher {
         cipher: *cipher,
@@ -467,24 +1482,41 @@ impl MultiLaneSecretsStore {
 
   fn read_index(&self, identity_id: &str, private_keys: &[(KeyType, PrivateKey)]) -> SecretStoreResult<Index> {
     match self.block_store.get_index(identity_id)? {
-      Some(crypted_index) => match self.decrypt_block(identity_id, private_keys, &crypted_index)? {
-        Some(padded_index_data) => {
-          let borrowed = padded_index_data.borrow();
-          let index_data = RandomFrontBack::unpad_data(&borrowed)?;
-          Ok(Index::from_secured_raw(index_data)?)
-        }
-        None => {
+      Some(crypted_index) => match self.decrypt_index(identity_id, private_keys, &crypted_index) {
+        Ok(Some(index)) => Ok(index),
+        Ok(None) => {
           warn!("User is not allowed recipient for index-data. Will trigger re-index.");
           Ok(Default::default())
         }
+        Err(err) => {
+          // The index can always be rebuilt from the data blocks, so there is no reason to fail here
+          warn!("Index is corrupted ({}). Will trigger re-index.", err);
+          Ok(Default::default())
+        }
       },
       None => Ok(Default::default()),
     }
   }
 
+  fn decrypt_index(
+    &self,
+    identity_id: &str,
+    private_keys: &[(KeyType, PrivateKey)],
+    crypted_index: &[u8],
+  ) -> SecretStoreResult<Option<Index>> {
+    match self.decrypt_block(identity_id, private_keys, crypted_index)? {
+      Some((padded_index_data, _)) => {
+        let borrowed = padded_index_data.borrow();
+        let index_data = RandomFrontBack::unpad_data(&borrowed)?;
+        Ok(Some(Index::from_secured_raw(index_data)?))
+      }
+      None => Ok(None),
+    }
+  }
+
   fn store_index(&self, identity_id: &str, index: &Index) -> SecretStoreResult<()> {
     let secret_content = RandomFrontBack::pad_secret_data(index.data.borrow().as_bytes(), 512)?;
-    let block_content = self.ecnrypt_block(&[identity_id], secret_content)?;
+    let block_content = self.ecnrypt_block(&[identity_id], secret_content, BlockCompression::None)?;
 
     Ok(self.block_store.store_index(identity_id, &block_content)?)
   }
@@ -495,12 +1527,9 @@ impl MultiLaneSecretsStore {
     private_keys: &[(KeyType, PrivateKey)],
     block_id: &str,
   ) -> SecretStoreResult<Option<SecretVersion>> {
-    let block_words = self.block_store.get_block(block_id)?;
-
-    match self.decrypt_block(identity_id, private_keys, &block_words)? {
-      Some(padded_content) => {
-        let borrowed = padded_content.borrow();
-        let version = serde_json::from_slice(NonZeroPadding::unpad_data(&borrowed)?)?;
+    match self.read_secret_version(identity_id, private_keys, block_id)? {
+      Some(mut version) => {
+        self.assemble_attachments(&mut version)?;
 
         Ok(Some(version))
       }
@@ -508,12 +1537,203 @@ impl MultiLaneSecretsStore {
     }
   }
 
+  /// Only keep the entries whose current version contains `content` (see `SecretListFilter::content`).
+  fn filter_content(&self, unlocked_user: &User, list: &mut SecretList, content: &str) {
+    list.entries.retain_mut(|entry_match| {
+      let current_version = unlocked_user
+        .index
+        .find_versions(&entry_match.entry.id)
+        .and_then(|version_refs| match version_refs.first() {
+          Some(version_ref) => self.read_secret_version(
+            &unlocked_user.identity.id,
+            &unlocked_user.private_keys,
+            &version_ref.block_id,
+          ),
+          None => Ok(None),
+        });
+
+      match current_version {
+        Ok(Some(version)) => {
+          entry_match.content_highlights = find_content_highlights(&version, content);
+          !entry_match.content_highlights.is_empty()
+        }
+        Ok(None) => false,
+        Err(error) => {
+          debug!("Unable to read current version of {}: {}", entry_match.entry.id, error);
+          false
+        }
+      }
+    });
+  }
+
+  /// Read the current versions of all (not deleted) secrets, unreadable ones are skipped.
+  ///
+  /// Concurrent versions are not merged, which is sufficient for checks of the passwords.
+  fn read_current_versions(&self, unlocked_user: &User) -> SecretStoreResult<Vec<SecretVersion>> {
+    let list = unlocked_user.index.filter_entries(&SecretListFilter::default())?;
+    let mut versions = Vec::with_capacity(list.entries.len());
+
+    for entry_match in &list.entries {
+      let secret_id = &entry_match.entry.id;
+      let current_version = match unlocked_user.index.find_versions(secret_id)?.first() {
+        Some(version_ref) => self.read_secret_version(
+          &unlocked_user.identity.id,
+          &unlocked_user.private_keys,
+          &version_ref.block_id,
+        ),
+        None => Ok(None),
+      };
+      match current_version {
+        Ok(Some(version)) => versions.push(version),
+        Ok(None) => (),
+        Err(error) => debug!("Unable to read current version of {}: {}", secret_id, error),
+      }
+    }
+
+    Ok(versions)
+  }
+
+  /// Group secrets by their passwords.
+  ///
+  /// Passwords are hashed with a fresh salt for every run, so that the hashes are not comparable to anything
+  /// outside this run.
+  fn reuse_groups(versions: &[SecretVersion]) -> Vec<ReuseGroup> {
+    let salt = SecretBytes::random(&mut thread_rng(), 32);
+    let mut groups: Vec<(SecretBytes, Vec<String>)> = vec![];
+
+    for version in versions {
+      for property in version.secret_type.password_properties() {
+        let value = match version.properties.get(property) {
+          Some(value) if !value.is_empty() => value,
+          _ => continue,
+        };
+        let password = SecretBytes::from_secured(value.as_bytes());
+        let mut mac = Hmac::<Sha256>::new_from_slice(&salt.borrow()).unwrap();
+        mac.update(&password.borrow());
+        let hash = SecretBytes::from(mac.finalize().into_bytes().to_vec());
+
+        match groups.iter_mut().find(|(group_hash, _)| group_hash == &hash) {
+          Some((_, secret_ids)) if secret_ids.contains(&version.secret_id) => (),
+          Some((_, secret_ids)) => secret_ids.push(version.secret_id.clone()),
+          None => groups.push((hash, vec![version.secret_id.clone()])),
+        }
+      }
+    }
+
+    groups
+      .into_iter()
+      .filter(|(_, secret_ids)| secret_ids.len() > 1)
+      .map(|(_, secret_ids)| ReuseGroup { secret_ids })
+      .collect()
+  }
+
+  /// Highest breach count of the passwords of a version (see `SecretsStore::check_breached`).
+  fn breach_count(&self, version: &SecretVersion) -> SecretStoreResult<Option<u64>> {
+    let breach_dir = match &self.breach_dir {
+      Some(breach_dir) => breach_dir,
+      None => return Ok(None),
+    };
+    let mut max_count: Option<u64> = None;
+
+    for property in version.secret_type.password_properties() {
+      if let Some(value) = version.properties.get(property) {
+        if value.is_empty() {
+          continue;
+        }
+        let password = SecretBytes::from_secured(value.as_bytes());
+
+        if let Some(count) = lookup_breach_count(breach_dir, &password)? {
+          max_count = Some(max_count.map_or(count, |max_count| max_count.max(count)));
+        }
+      }
+    }
+
+    Ok(max_count)
+  }
+
+  /// Read a secret version without the content of chunked attachments (which is sufficient for the index).
+  fn read_secret_version(
+    &self,
+    identity_id: &str,
+    private_keys: &[(KeyType, PrivateKey)],
+    block_id: &str,
+  ) -> SecretStoreResult<Option<SecretVersion>> {
+    let block_words = self.block_store.get_block(block_id)?;
+
+    match self.decrypt_block(identity_id, private_keys, &block_words)? {
+      Some((padded_content, compression)) => Ok(Some(Self::decode_secret_version(&padded_content, compression)?)),
+      _ => Ok(None),
+    }
+  }
+
+  fn decode_secret_version(
+    padded_content: &SecretBytes,
+    compression: BlockCompression,
+  ) -> SecretStoreResult<SecretVersion> {
+    let borrowed = padded_content.borrow();
+    let inflated = match compression {
+      BlockCompression::None => return Ok(serde_json::from_slice(NonZeroPadding::unpad_data(&borrowed)?)?),
+      BlockCompression::Zstd => decompress_bounded(RandomFrontBack::unpad_data(&borrowed)?, MAX_INFLATED_BLOCK_SIZE)?,
+    };
+    let inflated_borrow = inflated.borrow();
+
+    Ok(serde_json::from_slice(&inflated_borrow)?)
+  }
+
+  /// Encrypt a secret version for all its recipients.
+  fn encrypt_secret_version(&self, secret_version: &SecretVersion) -> SecretStoreResult<Vec<u8>> {
+    let mut buffer = ZeroizeBytesBuffer::with_capacity(1024);
+    serde_json::to_writer(&mut buffer, secret_version)?;
+
+    match self.compress_block_content(&buffer) {
+      Some(compressed) => self.ecnrypt_block(
+        &secret_version.recipients,
+        RandomFrontBack::pad_secret_data(&compressed.borrow(), 512)?,
+        BlockCompression::Zstd,
+      ),
+      None => self.ecnrypt_block(
+        &secret_version.recipients,
+        NonZeroPadding::pad_secret_data(&buffer, 512)?,
+        BlockCompression::None,
+      ),
+    }
+  }
+
+  /// Compress the content of a secret block with zstd if compression is enabled and actually reduces its size.
+  ///
+  /// Compression happens before the padding, so that the padding still hides the length of the content.
+  /// As compressed data may contain `\0` it is padded with `RandomFrontBack` instead of `NonZeroPadding`.
+  fn compress_block_content(&self, content: &[u8]) -> Option<SecretBytes> {
+    if !self.compress_blocks {
+      return None;
+    }
+    let compressed = SecretBytes::from(zstd::bulk::compress(content, BLOCK_COMPRESSION_LEVEL).ok()?);
+    // Incompressible data is stored as is
+    if compressed.len() < content.len() {
+      Some(compressed)
+    } else {
+      None
+    }
+  }
+
   fn ecnrypt_block<T: AsRef<str>>(
     &self,
     recipients: &[T],
+    secret_content: SecretBytes,
+    compression: BlockCompression,
+  ) -> SecretStoreResult<Vec<u8>> {
+    self.encrypt_block_with(&self.block_ciphers, recipients, secret_content, compression)
+  }
+
+  /// Encrypt a block with a specific set of ciphers (each cipher adds a layer of encryption).
+  fn encrypt_block_with<T: AsRef<str>>(
+    &self,
+    ciphers: &[&'static dyn Cipher],
+    recipients: &[T],
     mut secret_content: SecretBytes,
+    compression: BlockCompression,
   ) -> SecretStoreResult<Vec<u8>> {
-    let recipients_for_cipher = self.find_recipients(recipients)?;
+    let recipients_for_cipher = self.find_recipients(ciphers, recipients)?;
     let mut block_message = message::Builder::new(ZeroingHeapAllocator::default());
     let mut block = block_message.init_root::<block::Builder>();
     let mut headers = block.reborrow().init_headers(recipients_for_cipher.len() as u32);
@@ -524,21 +1744,34 @@ impl MultiLaneSecretsStore {
       secret_content = SecretBytes::from(content);
     }
     block.set_content(&secret_content.borrow());
+    block.set_compression(compression as u8);
 
     Ok(serialize::write_message_to_words(&block_message))
   }
 
+  fn block_key_types(mut block_words: &[u8]) -> SecretStoreResult<Vec<KeyType>> {
+    let reader = serialize::read_message_from_flat_slice(&mut block_words, Default::default())?;
+    let block = reader.get_root::<block::Reader>()?;
+
+    block
+      .get_headers()?
+      .iter()
+      .map(|header| Ok(header.get_type()?))
+      .collect()
+  }
+
   fn decrypt_block(
     &self,
     identity_id: &str,
     private_keys: &[(KeyType, PrivateKey)],
     mut block_words: &[u8],
-  ) -> SecretStoreResult<Option<SecretBytes>> {
+  ) -> SecretStoreResult<Option<(SecretBytes, BlockCompression)>> {
     let reader = serialize::read_message_from_flat_slice(&mut block_words, Default::default())?;
     let index_block = reader.get_root::<block::Reader>()?;
     let headers = index_block.reborrow().get_headers()?;
 
-    if !Self::check_recipient(identity_id, &headers)? {
+    // Blocks without any headers are attachment chunks that are not encrypted for a recipient
+    if headers.is_empty() || !Self::check_recipient(identity_id, &headers)? {
       return Ok(None);
     }
 
@@ -557,7 +1790,92 @@ impl MultiLaneSecretsStore {
       content = next_content;
     }
 
-    Ok(Some(content))
+    Ok(Some((
+      content,
+      BlockCompression::from_header(index_block.get_compression())?,
+    )))
+  }
+
+  /// Move the content of all attachments to chunk blocks.
+  ///
+  /// Returns the changes for all chunks that are not part of the change log of this node yet.
+  fn store_attachment_chunks(
+    &self,
+    secret_version: &mut SecretVersion,
+    compress: bool,
+  ) -> SecretStoreResult<Vec<Change>> {
+    if secret_version
+      .attachments
+      .iter()
+      .all(|attachment| attachment.content().is_empty())
+    {
+      return Ok(vec![]);
+    }
+    let mut committed: HashSet<String> = self
+      .block_store
+      .change_logs()?
+      .into_iter()
+      .filter(|change_log| change_log.node == self.block_store.node_id())
+      .flat_map(|change_log| change_log.changes.into_iter())
+      .filter(|change| change.op == Operation::Add)
+      .map(|change| change.block)
+      .collect();
+    let mut changes = vec![];
+    let mut attachments = Vec::with_capacity(secret_version.attachments.len());
+
+    for attachment in &secret_version.attachments {
+      if attachment.content().is_empty() {
+        attachments.push(attachment.clone());
+        continue;
+      }
+      let mut chunks = vec![];
+
+      for content in split_chunks(attachment.content()) {
+        let (raw, mut chunk) = encrypt_chunk(content, compress)?;
+
+        chunk.block_id = self.block_store.add_block(&raw)?;
+        if committed.insert(chunk.block_id.clone()) {
+          changes.push(Change::new(Operation::Add, chunk.block_id.as_str()));
+        }
+        chunks.push(chunk);
+      }
+      debug!("Stored attachment {} as {} chunks", attachment.name(), chunks.len());
+      attachments.push(attachment.with_chunks(chunks));
+    }
+    secret_version.attachments = attachments;
+
+    Ok(changes)
+  }
+
+  /// Replace the chunks of all attachments by their actual content.
+  fn assemble_attachments(&self, secret_version: &mut SecretVersion) -> SecretStoreResult<()> {
+    if secret_version
+      .attachments
+      .iter()
+      .all(|attachment| attachment.chunks().is_empty())
+    {
+      return Ok(());
+    }
+    let mut attachments = Vec::with_capacity(secret_version.attachments.len());
+
+    for attachment in &secret_version.attachments {
+      if attachment.chunks().is_empty() {
+        attachments.push(attachment.clone());
+        continue;
+      }
+      // Allocate upfront, so that there are no copies of the content left behind by reallocation
+      let mut content = Vec::with_capacity(self.max_attachment_size.min(attachment.chunks().len() * MAX_CHUNK_SIZE));
+
+      for chunk in attachment.chunks() {
+        let raw = self.block_store.get_block(&chunk.block_id)?;
+
+        content.extend_from_slice(&decrypt_chunk(chunk, &raw)?.borrow());
+      }
+      attachments.push(attachment.with_content(content));
+    }
+    secret_version.attachments = attachments;
+
+    Ok(())
   }
 
   fn check_recipient(
@@ -581,3 +1899,30 @@ impl std::fmt::Debug for MultiLaneSecretsStore {
     write!(f, "Multilane secrets store")
   }
 }
+
+/// Decompress a zstd frame that must not inflate to more than `max_size` bytes.
+///
+/// The buffer is sized from the content size in the frame header (which is always present in frames
+/// written by `zstd::bulk::compress`), frames without it or exceeding `max_size` are rejected before
+/// anything is allocated.
+pub(super) fn decompress_bounded(compressed: &[u8], max_size: usize) -> SecretStoreResult<SecretBytes> {
+  let size = match zstd::zstd_safe::get_frame_content_size(compressed) {
+    Ok(Some(size)) if size <= max_size as u64 => size as usize,
+    Ok(Some(size)) => {
+      return Err(SecretStoreError::IO(format!(
+        "Compressed content too large: {} bytes (max {} bytes)",
+        size, max_size
+      )))
+    }
+    _ => return Err(SecretStoreError::IO("Invalid compressed content".to_string())),
+  };
+  let mut inflated = SecretBytes::zeroed(size);
+  let written = zstd::bulk::decompress_to_buffer(compressed, &mut inflated.borrow_mut())
+    .map_err(|err| SecretStoreError::IO(format!("Invalid compressed content: {}", err)))?;
+
+  if written != size {
+    return Err(SecretStoreError::IO("Invalid compressed content".to_string()));
+  }
+
+  Ok(inflated)
+}
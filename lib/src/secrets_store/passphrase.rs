@@ -0,0 +1,35 @@
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+
+use crate::memguard::SecretBytes;
+use crate::secrets_store::{SecretStoreError, SecretStoreResult};
+
+/// Upper bound of the growth of an UTF-8 string when normalized to NFC (see UAX #15).
+const NFC_MAX_EXPANSION: usize = 3;
+
+/// Normalize a passphrase to NFC before it is used for key derivation.
+///
+/// Depending on platform and input method the same passphrase might be entered in different
+/// normal forms (e.g. macOS tends to NFD), which would result in different seal keys.
+///
+/// Note: The normalized passphrase is the input of the key derivation, i.e. changing the
+/// normalization scheme is a breaking change for all existing rings.
+///
+pub fn normalize_passphrase(passphrase: &SecretBytes) -> SecretStoreResult<SecretBytes> {
+  let passphrase_ref = passphrase.borrow();
+  let passphrase_str = std::str::from_utf8(&passphrase_ref)
+    .map_err(|_| SecretStoreError::KeyDerivation("Passphrase is not valid UTF-8".to_string()))?;
+
+  if is_nfc_quick(passphrase_str.chars()) == IsNormalized::Yes {
+    return Ok(passphrase.clone());
+  }
+
+  let mut normalized = SecretBytes::with_capacity(passphrase_str.len() * NFC_MAX_EXPANSION);
+  {
+    let mut normalized_ref = normalized.borrow_mut();
+    for ch in passphrase_str.nfc() {
+      normalized_ref.append_char(ch);
+    }
+  }
+
+  Ok(normalized)
+}
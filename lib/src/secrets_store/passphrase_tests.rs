@@ -0,0 +1,88 @@
+use super::cipher::{KeyDerivation, RUST_ARGON2_ID};
+use super::passphrase::normalize_passphrase;
+use super::{open_secrets_store, SecretStoreError};
+use crate::api::{EventData, EventHub, Identity, StoreConfig};
+use crate::memguard::SecretBytes;
+use spectral::prelude::*;
+use std::sync::Arc;
+
+/// Passphrases in (NFC, NFD) form
+const COMBINING_PASSPHRASES: &[(&str, &str)] = &[
+  ("caf\u{e9} cr\u{e8}me", "cafe\u{301} cre\u{300}me"),
+  ("\u{c5}ngstr\u{f6}m", "A\u{30a}ngstro\u{308}m"),
+  (
+    "\u{d55c}\u{ad6d}\u{c5b4}",
+    "\u{1112}\u{1161}\u{11ab}\u{1100}\u{116e}\u{11a8}\u{110b}\u{1165}",
+  ),
+  ("\u{1e69}\u{1e0d}", "s\u{323}\u{307}d\u{323}"),
+];
+
+fn secret_from_str(s: &str) -> SecretBytes {
+  SecretBytes::from(s.as_bytes().to_vec())
+}
+
+#[test]
+fn test_normalize_passphrase() {
+  for (nfc, nfd) in COMBINING_PASSPHRASES {
+    assert_that(&nfc.as_bytes()).is_not_equal_to(nfd.as_bytes());
+    assert_that(&normalize_passphrase(&secret_from_str(nfc)).unwrap()).is_equal_to(secret_from_str(nfc));
+    assert_that(&normalize_passphrase(&secret_from_str(nfd)).unwrap()).is_equal_to(secret_from_str(nfc));
+  }
+  // Singleton decomposition: The angstrom sign is not stable under NFC
+  assert_that(&normalize_passphrase(&secret_from_str("\u{212b}")).unwrap()).is_equal_to(secret_from_str("\u{c5}"));
+  assert_that(&normalize_passphrase(&secret_from_str("plain ascii")).unwrap())
+    .is_equal_to(secret_from_str("plain ascii"));
+  assert_that(&normalize_passphrase(&SecretBytes::from(vec![0x66u8, 0xff, 0x66]))).is_err();
+}
+
+#[test]
+#[cfg_attr(debug_assertions, ignore)]
+fn test_normalized_derived_keys() {
+  let nonce = b"0123456789abcdef";
+
+  for (nfc, nfd) in COMBINING_PASSPHRASES {
+    let nfc_key = RUST_ARGON2_ID
+      .derive(&normalize_passphrase(&secret_from_str(nfc)).unwrap(), 0, nonce, 32)
+      .unwrap();
+    let nfd_key = RUST_ARGON2_ID
+      .derive(&normalize_passphrase(&secret_from_str(nfd)).unwrap(), 0, nonce, 32)
+      .unwrap();
+
+    assert_that(&nfd_key).is_equal_to(&nfc_key);
+  }
+}
+
+struct TestEventHub;
+
+impl EventHub for TestEventHub {
+  fn send(&self, _event: EventData) {}
+}
+
+#[test]
+#[cfg_attr(debug_assertions, ignore)]
+fn test_unlock_with_other_normal_form() {
+  let (nfc, nfd) = COMBINING_PASSPHRASES[0];
+  let (secrets_store, _) = open_secrets_store(
+    &StoreConfig::new("test", "multilane+memory://", "node1"),
+    Arc::new(TestEventHub),
+  )
+  .unwrap();
+  let identity = Identity {
+    id: "identity1".to_string(),
+    name: "Name1".to_string(),
+    email: "Email1".to_string(),
+    hidden: false,
+    hardware_factor: false,
+  };
+
+  secrets_store.add_identity(identity, secret_from_str(nfd)).unwrap();
+
+  secrets_store.unlock("identity1", secret_from_str(nfc)).unwrap();
+  secrets_store.change_passphrase(secret_from_str(nfc)).unwrap();
+  secrets_store.lock().unwrap();
+  secrets_store.unlock("identity1", secret_from_str(nfd)).unwrap();
+  secrets_store.lock().unwrap();
+
+  assert_that(&secrets_store.unlock("identity1", secret_from_str("cafe creme")))
+    .is_err_containing(SecretStoreError::InvalidPassphrase);
+}
@@ -0,0 +1,86 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use hmac::{Hmac, Mac};
+use rand::thread_rng;
+use sha2::Sha256;
+
+use crate::memguard::SecretBytes;
+use crate::secrets_store::{SecretStoreError, SecretStoreResult};
+
+/// Pepper files have to contain at least this many bytes.
+pub const MIN_PEPPER_LENGTH: usize = 16;
+/// Length of newly created peppers.
+pub const PEPPER_LENGTH: usize = 32;
+
+const PEPPER_PURPOSE: &[u8] = b"t-rust-less pepper";
+
+/// Read a pepper file, i.e. a device-local secret that is required in addition to the passphrase.
+///
+/// Any file with at least `MIN_PEPPER_LENGTH` bytes can be used, the result is a fixed length digest
+/// of its content.
+pub fn read_pepper<P: AsRef<Path>>(pepper_file: P) -> SecretStoreResult<SecretBytes> {
+  let mut file = File::open(pepper_file.as_ref()).map_err(|err| {
+    SecretStoreError::Pepper(format!(
+      "Unable to read {}: {}",
+      pepper_file.as_ref().to_string_lossy(),
+      err
+    ))
+  })?;
+  let mut content = Vec::with_capacity(file.metadata().map(|metadata| metadata.len() as usize).unwrap_or(0) + 1);
+  file.read_to_end(&mut content)?;
+  let content = SecretBytes::from(content);
+
+  if content.len() < MIN_PEPPER_LENGTH {
+    return Err(SecretStoreError::Pepper(format!(
+      "{} is too short (at least {} bytes required)",
+      pepper_file.as_ref().to_string_lossy(),
+      MIN_PEPPER_LENGTH
+    )));
+  }
+  let mut mac = Hmac::<Sha256>::new_from_slice(PEPPER_PURPOSE).unwrap();
+  mac.update(&content.borrow());
+
+  Ok(SecretBytes::from(mac.finalize().into_bytes().to_vec()))
+}
+
+/// Create a new random pepper file (only readable by the current user).
+///
+/// An existing file is never overwritten, since this would render every store using it inaccessible.
+pub fn create_pepper_file<P: AsRef<Path>>(pepper_file: P) -> SecretStoreResult<()> {
+  if let Some(parent) = pepper_file.as_ref().parent() {
+    fs::create_dir_all(parent)?;
+  }
+  let pepper = SecretBytes::random(&mut thread_rng(), PEPPER_LENGTH);
+  let mut options = OpenOptions::new();
+  options.write(true).create_new(true);
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::OpenOptionsExt;
+    options.mode(0o600);
+  }
+  let mut file = options.open(pepper_file.as_ref()).map_err(|err| match err.kind() {
+    io::ErrorKind::AlreadyExists => {
+      SecretStoreError::Pepper(format!("{} already exists", pepper_file.as_ref().to_string_lossy()))
+    }
+    _ => err.into(),
+  })?;
+  file.write_all(&pepper.borrow())?;
+  file.sync_all()?;
+
+  Ok(())
+}
+
+/// Mix the pepper (if any) into the passphrase, i.e. the input of the key derivation.
+pub fn pepper_passphrase(passphrase: &SecretBytes, pepper: Option<&SecretBytes>) -> SecretStoreResult<SecretBytes> {
+  match pepper {
+    Some(pepper) => {
+      let mut peppered = SecretBytes::with_capacity(passphrase.len() + pepper.len());
+      peppered.borrow_mut().write_all(&passphrase.borrow())?;
+      peppered.borrow_mut().write_all(&pepper.borrow())?;
+      Ok(peppered)
+    }
+    None => Ok(passphrase.clone()),
+  }
+}
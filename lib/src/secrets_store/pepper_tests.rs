@@ -0,0 +1,144 @@
+use super::pepper::{create_pepper_file, pepper_passphrase, read_pepper, PEPPER_LENGTH};
+use super::{open_secrets_store, SecretStoreError, SecretsStore};
+use crate::api::{EventData, EventHub, Identity, StoreConfig};
+use crate::memguard::SecretBytes;
+use spectral::prelude::*;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use tempfile::Builder;
+use url::Url;
+
+struct TestEventHub;
+
+impl EventHub for TestEventHub {
+  fn send(&self, _event: EventData) {}
+}
+
+fn secret_from_str(s: &str) -> SecretBytes {
+  SecretBytes::from(s.as_bytes().to_vec())
+}
+
+fn open_store(store_path: &Path, pepper_file: Option<&Path>) -> Arc<dyn SecretsStore> {
+  let store_url = format!("multilane+{}", Url::from_directory_path(store_path).unwrap());
+  let pepper_file = pepper_file.map(|pepper_file| pepper_file.to_string_lossy().to_string());
+  let mut store_config = StoreConfig::new("test", &store_url, "node1");
+  store_config.pepper_file = pepper_file;
+  let (secrets_store, _) = open_secrets_store(&store_config, Arc::new(TestEventHub)).unwrap();
+  secrets_store
+}
+
+#[test]
+fn test_read_pepper() {
+  let tempdir = Builder::new().prefix("t-rust-less-test").tempdir().unwrap();
+  let pepper_file = tempdir.path().join("pepper");
+  let other_pepper_file = tempdir.path().join("other");
+  let short_pepper_file = tempdir.path().join("short");
+
+  fs::write(&pepper_file, b"0123456789abcdef").unwrap();
+  fs::write(&other_pepper_file, b"0123456789abcdeg").unwrap();
+  fs::write(&short_pepper_file, b"0123456789abcde").unwrap();
+
+  let pepper = read_pepper(&pepper_file).unwrap();
+
+  assert_that(&pepper.len()).is_equal_to(32);
+  assert_that(&read_pepper(&pepper_file).unwrap()).is_equal_to(&pepper);
+  assert_that(&read_pepper(&other_pepper_file).unwrap()).is_not_equal_to(&pepper);
+  assert_that(&matches!(
+    read_pepper(&short_pepper_file),
+    Err(SecretStoreError::Pepper(_))
+  ))
+  .is_true();
+  assert_that(&matches!(
+    read_pepper(tempdir.path().join("missing")),
+    Err(SecretStoreError::Pepper(_))
+  ))
+  .is_true();
+
+  let passphrase = secret_from_str("Passphrase1");
+  let peppered = pepper_passphrase(&passphrase, Some(&pepper)).unwrap();
+
+  assert_that(&pepper_passphrase(&passphrase, None).unwrap()).is_equal_to(&passphrase);
+  assert_that(&peppered.len()).is_equal_to(passphrase.len() + pepper.len());
+  assert_that(&peppered).is_not_equal_to(&passphrase);
+}
+
+#[test]
+fn test_create_pepper_file() {
+  let tempdir = Builder::new().prefix("t-rust-less-test").tempdir().unwrap();
+  let pepper_file = tempdir.path().join("sub").join("pepper");
+
+  create_pepper_file(&pepper_file).unwrap();
+
+  let content = fs::read(&pepper_file).unwrap();
+
+  assert_that(&content.len()).is_equal_to(PEPPER_LENGTH);
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::PermissionsExt;
+    assert_that(&(fs::metadata(&pepper_file).unwrap().permissions().mode() & 0o777)).is_equal_to(0o600);
+  }
+  // Never overwrite an existing pepper
+  assert_that(&matches!(
+    create_pepper_file(&pepper_file),
+    Err(SecretStoreError::Pepper(_))
+  ))
+  .is_true();
+  assert_that(&fs::read(&pepper_file).unwrap()).is_equal_to(&content);
+}
+
+#[test]
+#[cfg_attr(debug_assertions, ignore)]
+fn test_unlock_with_pepper() {
+  let tempdir = Builder::new().prefix("t-rust-less-test").tempdir().unwrap();
+  let store_path = tempdir.path().join("store");
+  let pepper_file = tempdir.path().join("pepper");
+  let other_pepper_file = tempdir.path().join("other");
+  fs::create_dir_all(&store_path).unwrap();
+  create_pepper_file(&pepper_file).unwrap();
+  create_pepper_file(&other_pepper_file).unwrap();
+
+  let identity = Identity {
+    id: "identity1".to_string(),
+    name: "Name1".to_string(),
+    email: "Email1".to_string(),
+    hidden: false,
+    hardware_factor: false,
+  };
+  let secrets_store = open_store(&store_path, Some(&pepper_file));
+
+  secrets_store
+    .add_identity(identity, secret_from_str("Passphrase1"))
+    .unwrap();
+  secrets_store
+    .unlock("identity1", secret_from_str("Passphrase1"))
+    .unwrap();
+  secrets_store.change_passphrase(secret_from_str("Passphrase2")).unwrap();
+  secrets_store.lock().unwrap();
+  secrets_store
+    .unlock("identity1", secret_from_str("Passphrase2"))
+    .unwrap();
+  secrets_store.lock().unwrap();
+
+  let without_pepper = open_store(&store_path, None);
+
+  assert_that(&without_pepper.unlock("identity1", secret_from_str("Passphrase2")))
+    .is_err_containing(SecretStoreError::InvalidPassphrase);
+
+  let other_pepper = open_store(&store_path, Some(&other_pepper_file));
+
+  assert_that(&other_pepper.unlock("identity1", secret_from_str("Passphrase2")))
+    .is_err_containing(SecretStoreError::InvalidPassphrase);
+
+  let missing_pepper = open_store(&store_path, Some(&tempdir.path().join("missing")));
+
+  assert_that(&matches!(
+    missing_pepper.unlock("identity1", secret_from_str("Passphrase2")),
+    Err(SecretStoreError::Pepper(_))
+  ))
+  .is_true();
+
+  let with_pepper = open_store(&store_path, Some(&pepper_file));
+
+  with_pepper.unlock("identity1", secret_from_str("Passphrase2")).unwrap();
+}
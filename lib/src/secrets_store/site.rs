@@ -0,0 +1,68 @@
+use hkdf::Hkdf;
+use sha2::Sha256;
+use url::Url;
+
+use crate::memguard::SecretBytes;
+use crate::secrets_store::cipher::{KeyDerivation, PrivateKey, ARGON2_PRESET_DESKTOP, RUST_ARGON2_ID};
+use crate::secrets_store::{SecretStoreError, SecretStoreResult};
+use crate::secrets_store_capnp::KeyType;
+
+/// Key derivation preset of site keys.
+///
+/// This must never change, otherwise all passwords derived so far would change as well.
+pub const SITE_KEY_PRESET: u8 = ARGON2_PRESET_DESKTOP;
+
+/// Private key types in the order they are used as base of site keys (only the first one present is used).
+///
+/// New key types must only be appended, otherwise the site keys of existing identities would change.
+pub const SITE_KEY_TYPES: &[KeyType] = &[KeyType::Ed25519Chacha20Poly1305, KeyType::RsaAesGcm];
+
+/// HKDF info of site keys, the version has to be raised for every change of the derivation.
+const SITE_KEY_INFO: &[u8] = b"t-rust-less site key v1";
+
+const SITE_STRETCHED_KEY_LENGTH: usize = 32;
+
+const SITE_SALT_PREFIX: &str = "t-rust-less site:";
+
+/// Normalize the name of a site, so that e.g. `https://www.Example.com/login` and `example.com` are the same site.
+pub fn normalize_site(site: &str) -> String {
+  let site = site.trim().to_lowercase();
+  let host = match Url::parse(&site) {
+    Ok(url) if url.has_host() => url.host_str().unwrap_or_default().to_string(),
+    _ => site.split(['/', '?', '#']).next().unwrap_or_default().to_string(),
+  };
+
+  host.strip_prefix("www.").unwrap_or(&host).to_string()
+}
+
+/// Deterministically derive a key for a site from a single private key of an identity.
+///
+/// The first private key in the order of `SITE_KEY_TYPES` is stretched with Argon2id (salted by the normalized
+/// site) and expanded with HKDF-SHA256 (with the versioned `SITE_KEY_INFO`), i.e. other keys of the identity do
+/// not affect the site keys.
+pub fn derive_site_key(
+  private_keys: &[(KeyType, PrivateKey)],
+  site: &str,
+  key_length: usize,
+) -> SecretStoreResult<SecretBytes> {
+  let private_key = SITE_KEY_TYPES
+    .iter()
+    .find_map(|site_key_type| {
+      private_keys
+        .iter()
+        .find(|(key_type, _)| key_type == site_key_type)
+        .map(|(_, private_key)| private_key)
+    })
+    .ok_or_else(|| SecretStoreError::KeyDerivation("No private key to derive site keys from".to_string()))?;
+  let salt = format!("{}{}", SITE_SALT_PREFIX, normalize_site(site));
+  let stretched_key =
+    RUST_ARGON2_ID.derive(private_key, SITE_KEY_PRESET, salt.as_bytes(), SITE_STRETCHED_KEY_LENGTH)?;
+  let hkdf = Hkdf::<Sha256>::new(Some(salt.as_bytes()), &stretched_key.borrow());
+  let mut site_key = SecretBytes::zeroed(key_length);
+
+  hkdf
+    .expand(SITE_KEY_INFO, &mut site_key.borrow_mut())
+    .map_err(|_| SecretStoreError::KeyDerivation("Site key too long".to_string()))?;
+
+  Ok(site_key)
+}
@@ -0,0 +1,50 @@
+use super::site::{derive_site_key, normalize_site};
+use crate::memguard::SecretBytes;
+use crate::secrets_store_capnp::KeyType;
+use spectral::prelude::*;
+
+#[test]
+fn test_normalize_site() {
+  assert_that(&normalize_site("example.com")).is_equal_to("example.com".to_string());
+  assert_that(&normalize_site("  Example.COM ")).is_equal_to("example.com".to_string());
+  assert_that(&normalize_site("www.example.com")).is_equal_to("example.com".to_string());
+  assert_that(&normalize_site("https://www.Example.com/login?next=/")).is_equal_to("example.com".to_string());
+  assert_that(&normalize_site("http://login.example.com:8080")).is_equal_to("login.example.com".to_string());
+  assert_that(&normalize_site("example.com/login#top")).is_equal_to("example.com".to_string());
+  assert_that(&normalize_site("login.example.com")).is_not_equal_to("example.com".to_string());
+}
+
+#[test]
+#[cfg_attr(debug_assertions, ignore)]
+fn test_derive_site_key() {
+  let private_keys = vec![
+    (
+      KeyType::Ed25519Chacha20Poly1305,
+      SecretBytes::from(b"ed25519 key".to_vec()),
+    ),
+    (KeyType::RsaAesGcm, SecretBytes::from(b"rsa key".to_vec())),
+  ];
+  let reordered_keys = vec![
+    (KeyType::RsaAesGcm, SecretBytes::from(b"rsa key".to_vec())),
+    (
+      KeyType::Ed25519Chacha20Poly1305,
+      SecretBytes::from(b"ed25519 key".to_vec()),
+    ),
+  ];
+  let single_key = vec![(
+    KeyType::Ed25519Chacha20Poly1305,
+    SecretBytes::from(b"ed25519 key".to_vec()),
+  )];
+  let other_keys = vec![(KeyType::RsaAesGcm, SecretBytes::from(b"other rsa key".to_vec()))];
+
+  let key = derive_site_key(&private_keys, "example.com", 32).unwrap();
+
+  assert_that(&key.len()).is_equal_to(32);
+  assert_that(&derive_site_key(&private_keys, "example.com", 32).unwrap()).is_equal_to(&key);
+  assert_that(&derive_site_key(&reordered_keys, "https://www.example.com/", 32).unwrap()).is_equal_to(&key);
+  // Only a single key is used, i.e. other keys of the identity do not matter
+  assert_that(&derive_site_key(&single_key, "example.com", 32).unwrap()).is_equal_to(&key);
+  assert_that(&derive_site_key(&private_keys, "example.org", 32).unwrap()).is_not_equal_to(&key);
+  assert_that(&derive_site_key(&other_keys, "example.com", 32).unwrap()).is_not_equal_to(&key);
+  assert_that(&derive_site_key(&[], "example.com", 32)).is_err();
+}
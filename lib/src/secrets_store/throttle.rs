@@ -0,0 +1,139 @@
+use std::convert::TryInto;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use log::warn;
+
+use super::{SecretStoreError, SecretStoreResult};
+
+/// Delay after the first failed unlock attempt exceeding the allowed number of attempts.
+const INITIAL_DELAY: Duration = Duration::from_secs(5);
+/// Upper bound of the delay between unlock attempts.
+const MAX_DELAY: Duration = Duration::from_secs(15 * 60);
+/// Size of the persisted state: failed attempts and end of the block in millis since epoch (0 = none), both as u64
+const PERSISTED_SIZE: usize = 16;
+
+/// Default file the state of the unlock throttle of a store is kept in, i.e.
+/// `<local data dir>/t-rust-less/<store name>.unlock-throttle`.
+pub fn default_unlock_throttle_file(store_name: &str) -> Option<PathBuf> {
+  dirs::data_local_dir().map(|data_dir| {
+    data_dir
+      .join("t-rust-less")
+      .join(format!("{}.unlock-throttle", store_name))
+  })
+}
+
+/// Throttle for unlock attempts.
+///
+/// After `max_attempts` consecutive failed attempts (i.e. invalid passphrase) every further
+/// attempt is rejected for an exponentially increasing period of time. The state can be persisted
+/// in a local file (see `read_from` and `write_to`), so restarting the process holding the store
+/// does not reset it. This file is deliberately not part of the block store: It is neither affected
+/// by wiping indexes nor synchronized, and works the same for every kind of store.
+///
+/// All operations take the current time as parameter so that they can be tested without
+/// actually waiting.
+pub struct UnlockThrottle {
+  max_attempts: u32,
+  failed_attempts: u32,
+  blocked_until: Option<SystemTime>,
+}
+
+impl UnlockThrottle {
+  /// Create a new throttle. `max_attempts` of 0 disables throttling altogether.
+  pub fn new(max_attempts: u32) -> Self {
+    UnlockThrottle {
+      max_attempts,
+      failed_attempts: 0,
+      blocked_until: None,
+    }
+  }
+
+  /// Restore a throttle from its persisted state, a missing or invalid state is the same as no failed attempts.
+  pub fn restore(max_attempts: u32, raw: Option<&[u8]>) -> Self {
+    let mut throttle = Self::new(max_attempts);
+
+    if let Some(raw) = raw.filter(|raw| raw.len() == PERSISTED_SIZE) {
+      throttle.failed_attempts = u64::from_le_bytes(raw[0..8].try_into().unwrap()).min(u32::MAX as u64) as u32;
+      throttle.blocked_until = match u64::from_le_bytes(raw[8..16].try_into().unwrap()) {
+        0 => None,
+        millis => SystemTime::UNIX_EPOCH.checked_add(Duration::from_millis(millis)),
+      };
+    }
+    throttle
+  }
+
+  /// Replace the state of the throttle with the one persisted in `file` (if there is any).
+  ///
+  /// Being unable to read the file is not fatal, the throttle then just starts over.
+  pub fn read_from(&mut self, file: &Path) {
+    match fs::read(file) {
+      Ok(raw) => *self = Self::restore(self.max_attempts, Some(&raw)),
+      Err(err) if err.kind() == io::ErrorKind::NotFound => (),
+      Err(err) => warn!(
+        "Unable to read state of unlock throttle {}: {}",
+        file.to_string_lossy(),
+        err
+      ),
+    }
+  }
+
+  /// Persist the state of the throttle to `file`, which is replaced atomically.
+  pub fn write_to(&self, file: &Path) -> io::Result<()> {
+    if let Some(parent) = file.parent() {
+      fs::create_dir_all(parent)?;
+    }
+    let tmp_file = file.with_extension("tmp");
+    fs::write(&tmp_file, self.to_bytes())?;
+    fs::rename(&tmp_file, file)
+  }
+
+  /// State of the throttle to be persisted.
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let blocked_until_millis = self
+      .blocked_until
+      .and_then(|blocked_until| blocked_until.duration_since(SystemTime::UNIX_EPOCH).ok())
+      .map(|since_epoch| since_epoch.as_millis() as u64)
+      .unwrap_or_default();
+    let mut raw = Vec::with_capacity(PERSISTED_SIZE);
+
+    raw.extend_from_slice(&u64::from(self.failed_attempts).to_le_bytes());
+    raw.extend_from_slice(&blocked_until_millis.to_le_bytes());
+    raw
+  }
+
+  pub fn blocked_until(&self, now: SystemTime) -> Option<SystemTime> {
+    self.blocked_until.filter(|blocked_until| *blocked_until > now)
+  }
+
+  pub fn check(&self, now: SystemTime) -> SecretStoreResult<()> {
+    match self.blocked_until(now) {
+      Some(blocked_until) => {
+        let remaining = blocked_until.duration_since(now).unwrap_or_default();
+        // Round up, "try again in 0s" is not helpful
+        let remaining_secs = remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0);
+        Err(SecretStoreError::UnlockThrottled(remaining_secs))
+      }
+      None => Ok(()),
+    }
+  }
+
+  pub fn failed(&mut self, now: SystemTime) {
+    if self.max_attempts == 0 {
+      return;
+    }
+    self.failed_attempts += 1;
+    if self.failed_attempts >= self.max_attempts {
+      let exponent = (self.failed_attempts - self.max_attempts).min(16);
+      let delay = INITIAL_DELAY.saturating_mul(1 << exponent).min(MAX_DELAY);
+      self.blocked_until = Some(now + delay);
+    }
+  }
+
+  pub fn succeeded(&mut self) {
+    self.failed_attempts = 0;
+    self.blocked_until = None;
+  }
+}
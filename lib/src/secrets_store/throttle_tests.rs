@@ -0,0 +1,112 @@
+use std::time::{Duration, SystemTime};
+
+use spectral::prelude::*;
+
+use super::throttle::UnlockThrottle;
+use super::SecretStoreError;
+
+#[test]
+fn test_throttle_disabled() {
+  let mut throttle = UnlockThrottle::new(0);
+  let now = SystemTime::UNIX_EPOCH;
+
+  for _ in 0..100 {
+    throttle.failed(now);
+  }
+
+  assert_that(&throttle.check(now)).is_ok();
+  assert_that(&throttle.blocked_until(now)).is_none();
+}
+
+#[test]
+fn test_throttle_increasing_delay() {
+  let mut throttle = UnlockThrottle::new(3);
+  let mut now = SystemTime::UNIX_EPOCH;
+
+  throttle.failed(now);
+  throttle.failed(now);
+  assert_that(&throttle.check(now)).is_ok();
+
+  throttle.failed(now);
+  assert_that(&throttle.check(now)).is_err_containing(SecretStoreError::UnlockThrottled(5));
+  assert_that(&throttle.blocked_until(now)).contains_value(now + Duration::from_secs(5));
+
+  now += Duration::from_millis(2500);
+  assert_that(&throttle.check(now)).is_err_containing(SecretStoreError::UnlockThrottled(3));
+
+  now += Duration::from_millis(2500);
+  assert_that(&throttle.check(now)).is_ok();
+
+  throttle.failed(now);
+  assert_that(&throttle.check(now)).is_err_containing(SecretStoreError::UnlockThrottled(10));
+
+  now += Duration::from_secs(10);
+  throttle.failed(now);
+  assert_that(&throttle.check(now)).is_err_containing(SecretStoreError::UnlockThrottled(20));
+
+  for _ in 0..20 {
+    throttle.failed(now);
+  }
+  assert_that(&throttle.check(now)).is_err_containing(SecretStoreError::UnlockThrottled(15 * 60));
+}
+
+#[test]
+fn test_throttle_reset_on_success() {
+  let mut throttle = UnlockThrottle::new(2);
+  let now = SystemTime::UNIX_EPOCH;
+
+  throttle.failed(now);
+  throttle.failed(now);
+  assert_that(&throttle.check(now)).is_err();
+
+  throttle.succeeded();
+  assert_that(&throttle.check(now)).is_ok();
+
+  throttle.failed(now);
+  assert_that(&throttle.check(now)).is_ok();
+}
+
+#[test]
+fn test_throttle_restore() {
+  let mut throttle = UnlockThrottle::new(2);
+  let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+  throttle.failed(now);
+  throttle.failed(now);
+
+  let restored = UnlockThrottle::restore(2, Some(&throttle.to_bytes()));
+
+  assert_that(&restored.blocked_until(now)).is_equal_to(throttle.blocked_until(now));
+  assert_that(&restored.check(now)).is_err_containing(SecretStoreError::UnlockThrottled(5));
+
+  // Failed attempts are restored as well, i.e. the next one doubles the delay
+  let mut restored = UnlockThrottle::restore(2, Some(&throttle.to_bytes()));
+  restored.failed(now);
+  assert_that(&restored.check(now)).is_err_containing(SecretStoreError::UnlockThrottled(10));
+
+  restored.succeeded();
+  assert_that(&UnlockThrottle::restore(2, Some(&restored.to_bytes())).check(now)).is_ok();
+  assert_that(&UnlockThrottle::restore(2, None).check(now)).is_ok();
+  assert_that(&UnlockThrottle::restore(2, Some(b"garbage")).check(now)).is_ok();
+}
+
+#[test]
+fn test_throttle_file() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("state").join("test.unlock-throttle");
+  let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+  let mut throttle = UnlockThrottle::new(2);
+
+  // Nothing persisted yet
+  throttle.read_from(&file);
+  assert_that(&throttle.check(now)).is_ok();
+
+  throttle.failed(now);
+  throttle.failed(now);
+  throttle.write_to(&file).unwrap();
+
+  let mut restored = UnlockThrottle::new(2);
+  restored.read_from(&file);
+
+  assert_that(&restored.check(now)).is_err_containing(SecretStoreError::UnlockThrottled(5));
+}
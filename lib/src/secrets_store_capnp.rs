@@ -325,6 +325,32 @@ pub mod ring {
     pub fn get_hidden(self) -> bool {
       self.reader.get_bool_field(0)
     }
+    #[inline]
+    pub fn get_hardware_factor(self) -> bool {
+      self.reader.get_bool_field(1)
+    }
+    #[inline]
+    pub fn get_hardware_credential_id(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+      ::capnp::traits::FromPointerReader::get_from_pointer(
+        &self.reader.get_pointer_field(5),
+        ::core::option::Option::None,
+      )
+    }
+    #[inline]
+    pub fn has_hardware_credential_id(&self) -> bool {
+      !self.reader.get_pointer_field(5).is_null()
+    }
+    #[inline]
+    pub fn get_hardware_salt(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+      ::capnp::traits::FromPointerReader::get_from_pointer(
+        &self.reader.get_pointer_field(6),
+        ::core::option::Option::None,
+      )
+    }
+    #[inline]
+    pub fn has_hardware_salt(&self) -> bool {
+      !self.reader.get_pointer_field(6).is_null()
+    }
   }
 
   pub struct Builder<'a> {
@@ -332,7 +358,7 @@ pub mod ring {
   }
   impl<'a> ::capnp::traits::HasStructSize for Builder<'a> {
     const STRUCT_SIZE: ::capnp::private::layout::StructSize =
-      ::capnp::private::layout::StructSize { data: 1, pointers: 5 };
+      ::capnp::private::layout::StructSize { data: 1, pointers: 7 };
   }
   impl<'a> ::capnp::traits::HasTypeId for Builder<'a> {
     const TYPE_ID: u64 = _private::TYPE_ID;
@@ -530,6 +556,52 @@ pub mod ring {
     pub fn set_hidden(&mut self, value: bool) {
       self.builder.set_bool_field(0, value);
     }
+    #[inline]
+    pub fn get_hardware_factor(self) -> bool {
+      self.builder.get_bool_field(1)
+    }
+    #[inline]
+    pub fn set_hardware_factor(&mut self, value: bool) {
+      self.builder.set_bool_field(1, value);
+    }
+    #[inline]
+    pub fn get_hardware_credential_id(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+      ::capnp::traits::FromPointerBuilder::get_from_pointer(
+        self.builder.get_pointer_field(5),
+        ::core::option::Option::None,
+      )
+    }
+    #[inline]
+    pub fn set_hardware_credential_id(&mut self, value: ::capnp::data::Reader<'_>) {
+      self.builder.reborrow().get_pointer_field(5).set_data(value);
+    }
+    #[inline]
+    pub fn init_hardware_credential_id(self, size: u32) -> ::capnp::data::Builder<'a> {
+      self.builder.get_pointer_field(5).init_data(size)
+    }
+    #[inline]
+    pub fn has_hardware_credential_id(&self) -> bool {
+      !self.builder.is_pointer_field_null(5)
+    }
+    #[inline]
+    pub fn get_hardware_salt(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+      ::capnp::traits::FromPointerBuilder::get_from_pointer(
+        self.builder.get_pointer_field(6),
+        ::core::option::Option::None,
+      )
+    }
+    #[inline]
+    pub fn set_hardware_salt(&mut self, value: ::capnp::data::Reader<'_>) {
+      self.builder.reborrow().get_pointer_field(6).set_data(value);
+    }
+    #[inline]
+    pub fn init_hardware_salt(self, size: u32) -> ::capnp::data::Builder<'a> {
+      self.builder.get_pointer_field(6).init_data(size)
+    }
+    #[inline]
+    pub fn has_hardware_salt(&self) -> bool {
+      !self.builder.is_pointer_field_null(6)
+    }
   }
 
   pub struct Pipeline {
@@ -542,17 +614,17 @@ pub mod ring {
   }
   impl Pipeline {}
   mod _private {
-    pub static ENCODED_NODE: [::capnp::Word; 126] = [
+    pub static ENCODED_NODE: [::capnp::Word; 175] = [
       ::capnp::word(0, 0, 0, 0, 5, 0, 6, 0),
       ::capnp::word(133, 30, 124, 165, 221, 11, 43, 165),
       ::capnp::word(24, 0, 0, 0, 1, 0, 1, 0),
       ::capnp::word(103, 128, 46, 172, 72, 114, 174, 137),
-      ::capnp::word(5, 0, 7, 0, 0, 0, 0, 0),
+      ::capnp::word(7, 0, 7, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(21, 0, 0, 0, 234, 0, 0, 0),
       ::capnp::word(33, 0, 0, 0, 39, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(61, 0, 0, 0, 87, 1, 0, 0),
+      ::capnp::word(61, 0, 0, 0, 255, 1, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(115, 114, 99, 47, 115, 101, 99, 114),
@@ -568,49 +640,70 @@ pub mod ring {
       ::capnp::word(121, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(80, 114, 105, 118, 97, 116, 101, 75),
       ::capnp::word(101, 121, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(24, 0, 0, 0, 3, 0, 4, 0),
+      ::capnp::word(36, 0, 0, 0, 3, 0, 4, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 1, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(153, 0, 0, 0, 26, 0, 0, 0),
+      ::capnp::word(237, 0, 0, 0, 26, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(148, 0, 0, 0, 3, 0, 1, 0),
-      ::capnp::word(160, 0, 0, 0, 2, 0, 1, 0),
+      ::capnp::word(232, 0, 0, 0, 3, 0, 1, 0),
+      ::capnp::word(244, 0, 0, 0, 2, 0, 1, 0),
       ::capnp::word(1, 0, 0, 0, 1, 0, 0, 0),
       ::capnp::word(0, 0, 1, 0, 1, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(157, 0, 0, 0, 42, 0, 0, 0),
+      ::capnp::word(241, 0, 0, 0, 42, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(152, 0, 0, 0, 3, 0, 1, 0),
-      ::capnp::word(164, 0, 0, 0, 2, 0, 1, 0),
+      ::capnp::word(236, 0, 0, 0, 3, 0, 1, 0),
+      ::capnp::word(248, 0, 0, 0, 2, 0, 1, 0),
       ::capnp::word(2, 0, 0, 0, 2, 0, 0, 0),
       ::capnp::word(0, 0, 1, 0, 2, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(161, 0, 0, 0, 50, 0, 0, 0),
+      ::capnp::word(245, 0, 0, 0, 50, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(156, 0, 0, 0, 3, 0, 1, 0),
-      ::capnp::word(168, 0, 0, 0, 2, 0, 1, 0),
+      ::capnp::word(240, 0, 0, 0, 3, 0, 1, 0),
+      ::capnp::word(252, 0, 0, 0, 2, 0, 1, 0),
       ::capnp::word(3, 0, 0, 0, 3, 0, 0, 0),
       ::capnp::word(0, 0, 1, 0, 3, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(165, 0, 0, 0, 90, 0, 0, 0),
+      ::capnp::word(249, 0, 0, 0, 90, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(164, 0, 0, 0, 3, 0, 1, 0),
-      ::capnp::word(192, 0, 0, 0, 2, 0, 1, 0),
+      ::capnp::word(248, 0, 0, 0, 3, 0, 1, 0),
+      ::capnp::word(20, 1, 0, 0, 2, 0, 1, 0),
       ::capnp::word(4, 0, 0, 0, 4, 0, 0, 0),
       ::capnp::word(0, 0, 1, 0, 4, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(189, 0, 0, 0, 98, 0, 0, 0),
+      ::capnp::word(17, 1, 0, 0, 98, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(188, 0, 0, 0, 3, 0, 1, 0),
-      ::capnp::word(216, 0, 0, 0, 2, 0, 1, 0),
+      ::capnp::word(16, 1, 0, 0, 3, 0, 1, 0),
+      ::capnp::word(44, 1, 0, 0, 2, 0, 1, 0),
       ::capnp::word(5, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 1, 0, 5, 0, 0, 0),
       ::capnp::word(1, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(213, 0, 0, 0, 58, 0, 0, 0),
+      ::capnp::word(41, 1, 0, 0, 58, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(36, 1, 0, 0, 3, 0, 1, 0),
+      ::capnp::word(48, 1, 0, 0, 2, 0, 1, 0),
+      ::capnp::word(6, 0, 0, 0, 1, 0, 0, 0),
+      ::capnp::word(0, 0, 1, 0, 6, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(45, 1, 0, 0, 122, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(208, 0, 0, 0, 3, 0, 1, 0),
-      ::capnp::word(220, 0, 0, 0, 2, 0, 1, 0),
+      ::capnp::word(44, 1, 0, 0, 3, 0, 1, 0),
+      ::capnp::word(56, 1, 0, 0, 2, 0, 1, 0),
+      ::capnp::word(7, 0, 0, 0, 5, 0, 0, 0),
+      ::capnp::word(0, 0, 1, 0, 7, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(53, 1, 0, 0, 170, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(56, 1, 0, 0, 3, 0, 1, 0),
+      ::capnp::word(68, 1, 0, 0, 2, 0, 1, 0),
+      ::capnp::word(8, 0, 0, 0, 6, 0, 0, 0),
+      ::capnp::word(0, 0, 1, 0, 8, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(65, 1, 0, 0, 106, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(64, 1, 0, 0, 3, 0, 1, 0),
+      ::capnp::word(76, 1, 0, 0, 2, 0, 1, 0),
       ::capnp::word(105, 100, 0, 0, 0, 0, 0, 0),
       ::capnp::word(12, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
@@ -669,6 +762,34 @@ pub mod ring {
       ::capnp::word(1, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(104, 97, 114, 100, 119, 97, 114, 101),
+      ::capnp::word(70, 97, 99, 116, 111, 114, 0, 0),
+      ::capnp::word(1, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(1, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(104, 97, 114, 100, 119, 97, 114, 101),
+      ::capnp::word(67, 114, 101, 100, 101, 110, 116, 105),
+      ::capnp::word(97, 108, 73, 100, 0, 0, 0, 0),
+      ::capnp::word(13, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(13, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(1, 0, 0, 0, 2, 0, 0, 0),
+      ::capnp::word(104, 97, 114, 100, 119, 97, 114, 101),
+      ::capnp::word(83, 97, 108, 116, 0, 0, 0, 0),
+      ::capnp::word(13, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(13, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(1, 0, 0, 0, 2, 0, 0, 0),
     ];
     pub fn get_field_types(index: u16) -> ::capnp::introspect::Type {
       match index {
@@ -678,6 +799,9 @@ pub mod ring {
         3 => <::capnp::struct_list::Owned<crate::secrets_store_capnp::ring::public_key::Owned> as ::capnp::introspect::Introspect>::introspect(),
         4 => <::capnp::struct_list::Owned<crate::secrets_store_capnp::ring::private_key::Owned> as ::capnp::introspect::Introspect>::introspect(),
         5 => <bool as ::capnp::introspect::Introspect>::introspect(),
+        6 => <bool as ::capnp::introspect::Introspect>::introspect(),
+        7 => <::capnp::data::Owned as ::capnp::introspect::Introspect>::introspect(),
+        8 => <::capnp::data::Owned as ::capnp::introspect::Introspect>::introspect(),
         _ => panic!("invalid field index {}", index),
       }
     }
@@ -690,9 +814,9 @@ pub mod ring {
       members_by_discriminant: MEMBERS_BY_DISCRIMINANT,
       members_by_name: MEMBERS_BY_NAME,
     };
-    pub static NONUNION_MEMBERS: &[u16] = &[0, 1, 2, 3, 4, 5];
+    pub static NONUNION_MEMBERS: &[u16] = &[0, 1, 2, 3, 4, 5, 6, 7, 8];
     pub static MEMBERS_BY_DISCRIMINANT: &[u16] = &[];
-    pub static MEMBERS_BY_NAME: &[u16] = &[2, 5, 0, 1, 4, 3];
+    pub static MEMBERS_BY_NAME: &[u16] = &[2, 7, 6, 8, 5, 0, 1, 4, 3];
     pub const TYPE_ID: u64 = 0xa52b_0bdd_a57c_1e85;
   }
 
@@ -1542,6 +1666,10 @@ pub mod block {
     pub fn has_content(&self) -> bool {
       !self.reader.get_pointer_field(1).is_null()
     }
+    #[inline]
+    pub fn get_compression(self) -> u8 {
+      self.reader.get_data_field::<u8>(0)
+    }
   }
 
   pub struct Builder<'a> {
@@ -1549,7 +1677,7 @@ pub mod block {
   }
   impl<'a> ::capnp::traits::HasStructSize for Builder<'a> {
     const STRUCT_SIZE: ::capnp::private::layout::StructSize =
-      ::capnp::private::layout::StructSize { data: 0, pointers: 2 };
+      ::capnp::private::layout::StructSize { data: 1, pointers: 2 };
   }
   impl<'a> ::capnp::traits::HasTypeId for Builder<'a> {
     const TYPE_ID: u64 = _private::TYPE_ID;
@@ -1671,6 +1799,14 @@ pub mod block {
     pub fn has_content(&self) -> bool {
       !self.builder.is_pointer_field_null(1)
     }
+    #[inline]
+    pub fn get_compression(self) -> u8 {
+      self.builder.get_data_field::<u8>(0)
+    }
+    #[inline]
+    pub fn set_compression(&mut self, value: u8) {
+      self.builder.set_data_field::<u8>(0, value);
+    }
   }
 
   pub struct Pipeline {
@@ -1683,17 +1819,17 @@ pub mod block {
   }
   impl Pipeline {}
   mod _private {
-    pub static ENCODED_NODE: [::capnp::Word; 59] = [
+    pub static ENCODED_NODE: [::capnp::Word; 75] = [
       ::capnp::word(0, 0, 0, 0, 5, 0, 6, 0),
       ::capnp::word(145, 242, 158, 22, 178, 24, 61, 141),
-      ::capnp::word(24, 0, 0, 0, 1, 0, 0, 0),
+      ::capnp::word(24, 0, 0, 0, 1, 0, 1, 0),
       ::capnp::word(103, 128, 46, 172, 72, 114, 174, 137),
       ::capnp::word(2, 0, 7, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(21, 0, 0, 0, 242, 0, 0, 0),
       ::capnp::word(33, 0, 0, 0, 39, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(57, 0, 0, 0, 119, 0, 0, 0),
+      ::capnp::word(57, 0, 0, 0, 175, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(115, 114, 99, 47, 115, 101, 99, 114),
@@ -1708,21 +1844,28 @@ pub mod block {
       ::capnp::word(72, 101, 97, 100, 101, 114, 0, 0),
       ::capnp::word(82, 101, 99, 105, 112, 105, 101, 110),
       ::capnp::word(116, 75, 101, 121, 0, 0, 0, 0),
-      ::capnp::word(8, 0, 0, 0, 3, 0, 4, 0),
+      ::capnp::word(12, 0, 0, 0, 3, 0, 4, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 1, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(41, 0, 0, 0, 66, 0, 0, 0),
+      ::capnp::word(69, 0, 0, 0, 66, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(36, 0, 0, 0, 3, 0, 1, 0),
-      ::capnp::word(64, 0, 0, 0, 2, 0, 1, 0),
+      ::capnp::word(64, 0, 0, 0, 3, 0, 1, 0),
+      ::capnp::word(92, 0, 0, 0, 2, 0, 1, 0),
       ::capnp::word(1, 0, 0, 0, 1, 0, 0, 0),
       ::capnp::word(0, 0, 1, 0, 1, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(61, 0, 0, 0, 66, 0, 0, 0),
+      ::capnp::word(89, 0, 0, 0, 66, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(56, 0, 0, 0, 3, 0, 1, 0),
-      ::capnp::word(68, 0, 0, 0, 2, 0, 1, 0),
+      ::capnp::word(84, 0, 0, 0, 3, 0, 1, 0),
+      ::capnp::word(96, 0, 0, 0, 2, 0, 1, 0),
+      ::capnp::word(2, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 1, 0, 2, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(93, 0, 0, 0, 98, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(92, 0, 0, 0, 3, 0, 1, 0),
+      ::capnp::word(104, 0, 0, 0, 2, 0, 1, 0),
       ::capnp::word(104, 101, 97, 100, 101, 114, 115, 0),
       ::capnp::word(14, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
@@ -1743,11 +1886,21 @@ pub mod block {
       ::capnp::word(13, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(99, 111, 109, 112, 114, 101, 115, 115),
+      ::capnp::word(105, 111, 110, 0, 0, 0, 0, 0),
+      ::capnp::word(6, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(6, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
     ];
     pub fn get_field_types(index: u16) -> ::capnp::introspect::Type {
       match index {
         0 => <::capnp::struct_list::Owned<crate::secrets_store_capnp::block::header::Owned> as ::capnp::introspect::Introspect>::introspect(),
         1 => <::capnp::data::Owned as ::capnp::introspect::Introspect>::introspect(),
+        2 => <u8 as ::capnp::introspect::Introspect>::introspect(),
         _ => panic!("invalid field index {}", index),
       }
     }
@@ -1760,9 +1913,9 @@ pub mod block {
       members_by_discriminant: MEMBERS_BY_DISCRIMINANT,
       members_by_name: MEMBERS_BY_NAME,
     };
-    pub static NONUNION_MEMBERS: &[u16] = &[0, 1];
+    pub static NONUNION_MEMBERS: &[u16] = &[0, 1, 2];
     pub static MEMBERS_BY_DISCRIMINANT: &[u16] = &[];
-    pub static MEMBERS_BY_NAME: &[u16] = &[1, 0];
+    pub static MEMBERS_BY_NAME: &[u16] = &[2, 1, 0];
     pub const TYPE_ID: u64 = 0x8d3d_18b2_169e_f291;
   }
 
@@ -2591,6 +2744,10 @@ pub mod index {
     pub fn has_entries(&self) -> bool {
       !self.reader.get_pointer_field(1).is_null()
     }
+    #[inline]
+    pub fn get_version(self) -> u16 {
+      self.reader.get_data_field::<u16>(0)
+    }
   }
 
   pub struct Builder<'a> {
@@ -2598,7 +2755,7 @@ pub mod index {
   }
   impl<'a> ::capnp::traits::HasStructSize for Builder<'a> {
     const STRUCT_SIZE: ::capnp::private::layout::StructSize =
-      ::capnp::private::layout::StructSize { data: 0, pointers: 2 };
+      ::capnp::private::layout::StructSize { data: 1, pointers: 2 };
   }
   impl<'a> ::capnp::traits::HasTypeId for Builder<'a> {
     const TYPE_ID: u64 = _private::TYPE_ID;
@@ -2728,6 +2885,14 @@ pub mod index {
     pub fn has_entries(&self) -> bool {
       !self.builder.is_pointer_field_null(1)
     }
+    #[inline]
+    pub fn get_version(self) -> u16 {
+      self.builder.get_data_field::<u16>(0)
+    }
+    #[inline]
+    pub fn set_version(&mut self, value: u16) {
+      self.builder.set_data_field::<u16>(0, value);
+    }
   }
 
   pub struct Pipeline {
@@ -2740,17 +2905,17 @@ pub mod index {
   }
   impl Pipeline {}
   mod _private {
-    pub static ENCODED_NODE: [::capnp::Word; 66] = [
+    pub static ENCODED_NODE: [::capnp::Word; 81] = [
       ::capnp::word(0, 0, 0, 0, 5, 0, 6, 0),
       ::capnp::word(185, 245, 217, 11, 187, 125, 205, 237),
-      ::capnp::word(24, 0, 0, 0, 1, 0, 0, 0),
+      ::capnp::word(24, 0, 0, 0, 1, 0, 1, 0),
       ::capnp::word(103, 128, 46, 172, 72, 114, 174, 137),
       ::capnp::word(2, 0, 7, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(21, 0, 0, 0, 242, 0, 0, 0),
       ::capnp::word(33, 0, 0, 0, 55, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(69, 0, 0, 0, 119, 0, 0, 0),
+      ::capnp::word(69, 0, 0, 0, 175, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(115, 114, 99, 47, 115, 101, 99, 114),
@@ -2768,21 +2933,28 @@ pub mod index {
       ::capnp::word(97, 116, 105, 111, 110, 0, 0, 0),
       ::capnp::word(72, 101, 97, 100, 0, 0, 0, 0),
       ::capnp::word(69, 110, 116, 114, 121, 0, 0, 0),
-      ::capnp::word(8, 0, 0, 0, 3, 0, 4, 0),
+      ::capnp::word(12, 0, 0, 0, 3, 0, 4, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 1, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(41, 0, 0, 0, 50, 0, 0, 0),
+      ::capnp::word(69, 0, 0, 0, 50, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(36, 0, 0, 0, 3, 0, 1, 0),
-      ::capnp::word(64, 0, 0, 0, 2, 0, 1, 0),
+      ::capnp::word(64, 0, 0, 0, 3, 0, 1, 0),
+      ::capnp::word(92, 0, 0, 0, 2, 0, 1, 0),
       ::capnp::word(1, 0, 0, 0, 1, 0, 0, 0),
       ::capnp::word(0, 0, 1, 0, 1, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(61, 0, 0, 0, 66, 0, 0, 0),
+      ::capnp::word(89, 0, 0, 0, 66, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(56, 0, 0, 0, 3, 0, 1, 0),
-      ::capnp::word(84, 0, 0, 0, 2, 0, 1, 0),
+      ::capnp::word(84, 0, 0, 0, 3, 0, 1, 0),
+      ::capnp::word(112, 0, 0, 0, 2, 0, 1, 0),
+      ::capnp::word(2, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 1, 0, 2, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(109, 0, 0, 0, 66, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(104, 0, 0, 0, 3, 0, 1, 0),
+      ::capnp::word(116, 0, 0, 0, 2, 0, 1, 0),
       ::capnp::word(104, 101, 97, 100, 115, 0, 0, 0),
       ::capnp::word(14, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
@@ -2807,11 +2979,20 @@ pub mod index {
       ::capnp::word(14, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(118, 101, 114, 115, 105, 111, 110, 0),
+      ::capnp::word(7, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(7, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
     ];
     pub fn get_field_types(index: u16) -> ::capnp::introspect::Type {
       match index {
         0 => <::capnp::struct_list::Owned<crate::secrets_store_capnp::index::head::Owned> as ::capnp::introspect::Introspect>::introspect(),
         1 => <::capnp::struct_list::Owned<crate::secrets_store_capnp::index::entry::Owned> as ::capnp::introspect::Introspect>::introspect(),
+        2 => <u16 as ::capnp::introspect::Introspect>::introspect(),
         _ => panic!("invalid field index {}", index),
       }
     }
@@ -2824,9 +3005,9 @@ pub mod index {
       members_by_discriminant: MEMBERS_BY_DISCRIMINANT,
       members_by_name: MEMBERS_BY_NAME,
     };
-    pub static NONUNION_MEMBERS: &[u16] = &[0, 1];
+    pub static NONUNION_MEMBERS: &[u16] = &[0, 1, 2];
     pub static MEMBERS_BY_DISCRIMINANT: &[u16] = &[];
-    pub static MEMBERS_BY_NAME: &[u16] = &[1, 0];
+    pub static MEMBERS_BY_NAME: &[u16] = &[1, 0, 2];
     pub const TYPE_ID: u64 = 0xedcd_7dbb_0bd9_f5b9;
   }
 
@@ -3884,6 +4065,10 @@ pub mod secret_entry {
     pub fn get_deleted(self) -> bool {
       self.reader.get_bool_field(80)
     }
+    #[inline]
+    pub fn get_expires_at(self) -> i64 {
+      self.reader.get_data_field::<i64>(2)
+    }
   }
 
   pub struct Builder<'a> {
@@ -3891,7 +4076,7 @@ pub mod secret_entry {
   }
   impl<'a> ::capnp::traits::HasStructSize for Builder<'a> {
     const STRUCT_SIZE: ::capnp::private::layout::StructSize =
-      ::capnp::private::layout::StructSize { data: 2, pointers: 4 };
+      ::capnp::private::layout::StructSize { data: 3, pointers: 4 };
   }
   impl<'a> ::capnp::traits::HasTypeId for Builder<'a> {
     const TYPE_ID: u64 = _private::TYPE_ID;
@@ -4075,6 +4260,14 @@ pub mod secret_entry {
     pub fn set_deleted(&mut self, value: bool) {
       self.builder.set_bool_field(80, value);
     }
+    #[inline]
+    pub fn get_expires_at(self) -> i64 {
+      self.builder.get_data_field::<i64>(2)
+    }
+    #[inline]
+    pub fn set_expires_at(&mut self, value: i64) {
+      self.builder.set_data_field::<i64>(2, value);
+    }
   }
 
   pub struct Pipeline {
@@ -4087,17 +4280,17 @@ pub mod secret_entry {
   }
   impl Pipeline {}
   mod _private {
-    pub static ENCODED_NODE: [::capnp::Word; 133] = [
+    pub static ENCODED_NODE: [::capnp::Word; 149] = [
       ::capnp::word(0, 0, 0, 0, 5, 0, 6, 0),
       ::capnp::word(223, 139, 11, 251, 164, 3, 247, 252),
-      ::capnp::word(24, 0, 0, 0, 1, 0, 2, 0),
+      ::capnp::word(24, 0, 0, 0, 1, 0, 3, 0),
       ::capnp::word(103, 128, 46, 172, 72, 114, 174, 137),
       ::capnp::word(4, 0, 7, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(21, 0, 0, 0, 34, 1, 0, 0),
       ::capnp::word(37, 0, 0, 0, 7, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(33, 0, 0, 0, 143, 1, 0, 0),
+      ::capnp::word(33, 0, 0, 0, 199, 1, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(115, 114, 99, 47, 115, 101, 99, 114),
@@ -4106,56 +4299,63 @@ pub mod secret_entry {
       ::capnp::word(83, 101, 99, 114, 101, 116, 69, 110),
       ::capnp::word(116, 114, 121, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 1, 0, 1, 0),
-      ::capnp::word(28, 0, 0, 0, 3, 0, 4, 0),
+      ::capnp::word(32, 0, 0, 0, 3, 0, 4, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 1, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(181, 0, 0, 0, 26, 0, 0, 0),
+      ::capnp::word(209, 0, 0, 0, 26, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(176, 0, 0, 0, 3, 0, 1, 0),
-      ::capnp::word(188, 0, 0, 0, 2, 0, 1, 0),
+      ::capnp::word(204, 0, 0, 0, 3, 0, 1, 0),
+      ::capnp::word(216, 0, 0, 0, 2, 0, 1, 0),
       ::capnp::word(1, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 1, 0, 1, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(185, 0, 0, 0, 82, 0, 0, 0),
+      ::capnp::word(213, 0, 0, 0, 82, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(184, 0, 0, 0, 3, 0, 1, 0),
-      ::capnp::word(196, 0, 0, 0, 2, 0, 1, 0),
+      ::capnp::word(212, 0, 0, 0, 3, 0, 1, 0),
+      ::capnp::word(224, 0, 0, 0, 2, 0, 1, 0),
       ::capnp::word(2, 0, 0, 0, 1, 0, 0, 0),
       ::capnp::word(0, 0, 1, 0, 2, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(193, 0, 0, 0, 42, 0, 0, 0),
+      ::capnp::word(221, 0, 0, 0, 42, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(188, 0, 0, 0, 3, 0, 1, 0),
-      ::capnp::word(200, 0, 0, 0, 2, 0, 1, 0),
+      ::capnp::word(216, 0, 0, 0, 3, 0, 1, 0),
+      ::capnp::word(228, 0, 0, 0, 2, 0, 1, 0),
       ::capnp::word(3, 0, 0, 0, 4, 0, 0, 0),
       ::capnp::word(0, 0, 1, 0, 3, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(197, 0, 0, 0, 42, 0, 0, 0),
+      ::capnp::word(225, 0, 0, 0, 42, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(192, 0, 0, 0, 3, 0, 1, 0),
-      ::capnp::word(204, 0, 0, 0, 2, 0, 1, 0),
+      ::capnp::word(220, 0, 0, 0, 3, 0, 1, 0),
+      ::capnp::word(232, 0, 0, 0, 2, 0, 1, 0),
       ::capnp::word(4, 0, 0, 0, 2, 0, 0, 0),
       ::capnp::word(0, 0, 1, 0, 4, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(201, 0, 0, 0, 42, 0, 0, 0),
+      ::capnp::word(229, 0, 0, 0, 42, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(196, 0, 0, 0, 3, 0, 1, 0),
-      ::capnp::word(224, 0, 0, 0, 2, 0, 1, 0),
+      ::capnp::word(224, 0, 0, 0, 3, 0, 1, 0),
+      ::capnp::word(252, 0, 0, 0, 2, 0, 1, 0),
       ::capnp::word(5, 0, 0, 0, 3, 0, 0, 0),
       ::capnp::word(0, 0, 1, 0, 5, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(221, 0, 0, 0, 42, 0, 0, 0),
+      ::capnp::word(249, 0, 0, 0, 42, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(216, 0, 0, 0, 3, 0, 1, 0),
-      ::capnp::word(244, 0, 0, 0, 2, 0, 1, 0),
+      ::capnp::word(244, 0, 0, 0, 3, 0, 1, 0),
+      ::capnp::word(16, 1, 0, 0, 2, 0, 1, 0),
       ::capnp::word(6, 0, 0, 0, 80, 0, 0, 0),
       ::capnp::word(0, 0, 1, 0, 6, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(241, 0, 0, 0, 66, 0, 0, 0),
+      ::capnp::word(13, 1, 0, 0, 66, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(236, 0, 0, 0, 3, 0, 1, 0),
-      ::capnp::word(248, 0, 0, 0, 2, 0, 1, 0),
+      ::capnp::word(8, 1, 0, 0, 3, 0, 1, 0),
+      ::capnp::word(20, 1, 0, 0, 2, 0, 1, 0),
+      ::capnp::word(7, 0, 0, 0, 2, 0, 0, 0),
+      ::capnp::word(0, 0, 1, 0, 7, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(17, 1, 0, 0, 82, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(16, 1, 0, 0, 3, 0, 1, 0),
+      ::capnp::word(28, 1, 0, 0, 2, 0, 1, 0),
       ::capnp::word(105, 100, 0, 0, 0, 0, 0, 0),
       ::capnp::word(12, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
@@ -4221,6 +4421,15 @@ pub mod secret_entry {
       ::capnp::word(1, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(101, 120, 112, 105, 114, 101, 115, 65),
+      ::capnp::word(116, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(5, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(5, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
     ];
     pub fn get_field_types(index: u16) -> ::capnp::introspect::Type {
       match index {
@@ -4231,6 +4440,7 @@ pub mod secret_entry {
         4 => <::capnp::text_list::Owned as ::capnp::introspect::Introspect>::introspect(),
         5 => <::capnp::text_list::Owned as ::capnp::introspect::Introspect>::introspect(),
         6 => <bool as ::capnp::introspect::Introspect>::introspect(),
+        7 => <i64 as ::capnp::introspect::Introspect>::introspect(),
         _ => panic!("invalid field index {}", index),
       }
     }
@@ -4243,9 +4453,9 @@ pub mod secret_entry {
       members_by_discriminant: MEMBERS_BY_DISCRIMINANT,
       members_by_name: MEMBERS_BY_NAME,
     };
-    pub static NONUNION_MEMBERS: &[u16] = &[0, 1, 2, 3, 4, 5, 6];
+    pub static NONUNION_MEMBERS: &[u16] = &[0, 1, 2, 3, 4, 5, 6, 7];
     pub static MEMBERS_BY_DISCRIMINANT: &[u16] = &[];
-    pub static MEMBERS_BY_NAME: &[u16] = &[6, 0, 2, 4, 1, 3, 5];
+    pub static MEMBERS_BY_NAME: &[u16] = &[6, 7, 0, 2, 4, 1, 3, 5];
     pub const TYPE_ID: u64 = 0xfcf7_03a4_fb0b_8bdf;
   }
 }
@@ -4362,6 +4572,17 @@ pub mod secret_version_ref {
     pub fn get_timestamp(self) -> i64 {
       self.reader.get_data_field::<i64>(0)
     }
+    #[inline]
+    pub fn get_modified_by(self) -> ::capnp::Result<::capnp::text::Reader<'a>> {
+      ::capnp::traits::FromPointerReader::get_from_pointer(
+        &self.reader.get_pointer_field(1),
+        ::core::option::Option::None,
+      )
+    }
+    #[inline]
+    pub fn has_modified_by(&self) -> bool {
+      !self.reader.get_pointer_field(1).is_null()
+    }
   }
 
   pub struct Builder<'a> {
@@ -4369,7 +4590,7 @@ pub mod secret_version_ref {
   }
   impl<'a> ::capnp::traits::HasStructSize for Builder<'a> {
     const STRUCT_SIZE: ::capnp::private::layout::StructSize =
-      ::capnp::private::layout::StructSize { data: 1, pointers: 1 };
+      ::capnp::private::layout::StructSize { data: 1, pointers: 2 };
   }
   impl<'a> ::capnp::traits::HasTypeId for Builder<'a> {
     const TYPE_ID: u64 = _private::TYPE_ID;
@@ -4473,6 +4694,26 @@ pub mod secret_version_ref {
     pub fn set_timestamp(&mut self, value: i64) {
       self.builder.set_data_field::<i64>(0, value);
     }
+    #[inline]
+    pub fn get_modified_by(self) -> ::capnp::Result<::capnp::text::Builder<'a>> {
+      ::capnp::traits::FromPointerBuilder::get_from_pointer(
+        self.builder.get_pointer_field(1),
+        ::core::option::Option::None,
+      )
+    }
+    #[inline]
+    pub fn set_modified_by(&mut self, value: impl ::capnp::traits::SetterInput<::capnp::text::Owned>) {
+      ::capnp::traits::SetterInput::set_pointer_builder(self.builder.reborrow().get_pointer_field(1), value, false)
+        .unwrap()
+    }
+    #[inline]
+    pub fn init_modified_by(self, size: u32) -> ::capnp::text::Builder<'a> {
+      self.builder.get_pointer_field(1).init_text(size)
+    }
+    #[inline]
+    pub fn has_modified_by(&self) -> bool {
+      !self.builder.is_pointer_field_null(1)
+    }
   }
 
   pub struct Pipeline {
@@ -4485,17 +4726,17 @@ pub mod secret_version_ref {
   }
   impl Pipeline {}
   mod _private {
-    pub static ENCODED_NODE: [::capnp::Word; 51] = [
+    pub static ENCODED_NODE: [::capnp::Word; 68] = [
       ::capnp::word(0, 0, 0, 0, 5, 0, 6, 0),
       ::capnp::word(67, 125, 164, 21, 101, 25, 93, 222),
       ::capnp::word(24, 0, 0, 0, 1, 0, 1, 0),
       ::capnp::word(103, 128, 46, 172, 72, 114, 174, 137),
-      ::capnp::word(1, 0, 7, 0, 0, 0, 0, 0),
+      ::capnp::word(2, 0, 7, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(21, 0, 0, 0, 74, 1, 0, 0),
       ::capnp::word(41, 0, 0, 0, 7, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(37, 0, 0, 0, 119, 0, 0, 0),
+      ::capnp::word(37, 0, 0, 0, 175, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(115, 114, 99, 47, 115, 101, 99, 114),
@@ -4505,21 +4746,28 @@ pub mod secret_version_ref {
       ::capnp::word(114, 115, 105, 111, 110, 82, 101, 102),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 1, 0, 1, 0),
-      ::capnp::word(8, 0, 0, 0, 3, 0, 4, 0),
+      ::capnp::word(12, 0, 0, 0, 3, 0, 4, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 1, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(41, 0, 0, 0, 66, 0, 0, 0),
+      ::capnp::word(69, 0, 0, 0, 66, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(36, 0, 0, 0, 3, 0, 1, 0),
-      ::capnp::word(48, 0, 0, 0, 2, 0, 1, 0),
+      ::capnp::word(64, 0, 0, 0, 3, 0, 1, 0),
+      ::capnp::word(76, 0, 0, 0, 2, 0, 1, 0),
       ::capnp::word(1, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 1, 0, 1, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(45, 0, 0, 0, 82, 0, 0, 0),
+      ::capnp::word(73, 0, 0, 0, 82, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
-      ::capnp::word(44, 0, 0, 0, 3, 0, 1, 0),
-      ::capnp::word(56, 0, 0, 0, 2, 0, 1, 0),
+      ::capnp::word(72, 0, 0, 0, 3, 0, 1, 0),
+      ::capnp::word(84, 0, 0, 0, 2, 0, 1, 0),
+      ::capnp::word(2, 0, 0, 0, 1, 0, 0, 0),
+      ::capnp::word(0, 0, 1, 0, 2, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(81, 0, 0, 0, 90, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(80, 0, 0, 0, 3, 0, 1, 0),
+      ::capnp::word(92, 0, 0, 0, 2, 0, 1, 0),
       ::capnp::word(98, 108, 111, 99, 107, 73, 100, 0),
       ::capnp::word(12, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
@@ -4537,11 +4785,22 @@ pub mod secret_version_ref {
       ::capnp::word(5, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
       ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(109, 111, 100, 105, 102, 105, 101, 100),
+      ::capnp::word(66, 121, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(12, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(12, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
+      ::capnp::word(1, 0, 0, 0, 10, 0, 0, 0),
+      ::capnp::word(0, 0, 0, 0, 0, 0, 0, 0),
     ];
     pub fn get_field_types(index: u16) -> ::capnp::introspect::Type {
       match index {
         0 => <::capnp::text::Owned as ::capnp::introspect::Introspect>::introspect(),
         1 => <i64 as ::capnp::introspect::Introspect>::introspect(),
+        2 => <::capnp::text::Owned as ::capnp::introspect::Introspect>::introspect(),
         _ => panic!("invalid field index {}", index),
       }
     }
@@ -4554,9 +4813,9 @@ pub mod secret_version_ref {
       members_by_discriminant: MEMBERS_BY_DISCRIMINANT,
       members_by_name: MEMBERS_BY_NAME,
     };
-    pub static NONUNION_MEMBERS: &[u16] = &[0, 1];
+    pub static NONUNION_MEMBERS: &[u16] = &[0, 1, 2];
     pub static MEMBERS_BY_DISCRIMINANT: &[u16] = &[];
-    pub static MEMBERS_BY_NAME: &[u16] = &[0, 1];
+    pub static MEMBERS_BY_NAME: &[u16] = &[0, 2, 1];
     pub const TYPE_ID: u64 = 0xde5d_1965_15a4_7d43;
   }
 }
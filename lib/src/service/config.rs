@@ -6,10 +6,48 @@ use std::fs::{self, File};
 use std::io::{self, Read, Write};
 use std::path::PathBuf;
 
-#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Config {
   pub default_store: Option<String>,
   pub stores: HashMap<String, StoreConfig>,
+  /// Restore the previous content of the clipboard once a secret has been pasted
+  #[serde(default)]
+  pub restore_clipboard: bool,
+  /// Clear the clipboard if a store is autolocked while the clipboard still provides one of its secrets,
+  /// otherwise the autolock is deferred until the clipboard is done
+  #[serde(default = "default_autolock_clears_clipboard")]
+  pub autolock_clears_clipboard: bool,
+  /// Lock all stores (and clear the clipboard) when the system is about to sleep/suspend
+  #[serde(default = "default_lock_on_sleep")]
+  pub lock_on_sleep: bool,
+  /// Lock all stores (and clear the clipboard) when the screen is locked
+  #[serde(default = "default_lock_on_screen_lock")]
+  pub lock_on_screen_lock: bool,
+}
+
+impl Default for Config {
+  fn default() -> Self {
+    Config {
+      default_store: None,
+      stores: HashMap::new(),
+      restore_clipboard: false,
+      autolock_clears_clipboard: default_autolock_clears_clipboard(),
+      lock_on_sleep: default_lock_on_sleep(),
+      lock_on_screen_lock: default_lock_on_screen_lock(),
+    }
+  }
+}
+
+fn default_autolock_clears_clipboard() -> bool {
+  true
+}
+
+fn default_lock_on_sleep() -> bool {
+  true
+}
+
+fn default_lock_on_screen_lock() -> bool {
+  true
 }
 
 pub fn config_file() -> PathBuf {
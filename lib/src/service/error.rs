@@ -20,8 +20,14 @@ pub enum ServiceError {
   StoreNotFound(String),
   #[error("Clipboard closed")]
   ClipboardClosed,
+  #[error("Clipboard not available (no display or compiled without clipboard support)")]
+  ClipboardUnavailable,
   #[error("Functionality not available (on your platform)")]
   NotAvailable,
+  #[error("Invalid wordlist: {0}")]
+  InvalidWordlist(String),
+  #[error("Invalid password generator parameters: {0}")]
+  InvalidGeneratorParams(String),
 }
 
 pub type ServiceResult<T> = Result<T, ServiceError>;
@@ -30,12 +36,20 @@ error_convert_from!(std::io::Error, ServiceError, IO(display));
 error_convert_from!(toml::de::Error, ServiceError, IO(display));
 error_convert_from!(SecretStoreError, ServiceError, SecretsStore(direct));
 error_convert_from!(StoreError, ServiceError, StoreError(direct));
-error_convert_from!(ClipboardError, ServiceError, IO(display));
 error_convert_from!(futures::task::SpawnError, ServiceError, IO(display));
 error_convert_from!(serde_json::Error, ServiceError, IO(display));
 error_convert_from!(rmp_serde::encode::Error, ServiceError, IO(display));
 error_convert_from!(rmp_serde::decode::Error, ServiceError, IO(display));
 
+impl From<ClipboardError> for ServiceError {
+  fn from(error: ClipboardError) -> Self {
+    match error {
+      ClipboardError::Unavailable => ServiceError::ClipboardUnavailable,
+      error => ServiceError::IO(format!("{}", error)),
+    }
+  }
+}
+
 impl<T> From<std::sync::PoisonError<T>> for ServiceError {
   fn from(error: std::sync::PoisonError<T>) -> Self {
     ServiceError::Mutex(format!("{}", error))
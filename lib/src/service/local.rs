@@ -1,8 +1,13 @@
-use super::pw_generator::{generate_chars, generate_words};
+use super::pw_generator::{generate_derived_password, generate_password};
 use super::synchronizer::Synchronizer;
-use crate::api::{ClipboardProviding, Event, EventData, EventHub, PasswordGeneratorParam, StoreConfig};
+use crate::api::{
+  Capabilities, ClipboardProviding, Diagnostics, Event, EventData, EventHub, PanicLockReport, PasswordGeneratorParam,
+  StoreConfig, SyncReport, ZeroizeDateTime,
+};
 use crate::block_store::StoreError;
 use crate::clipboard::{Clipboard, ClipboardCommon};
+use crate::memguard::memory_locking_active;
+use crate::secrets_store::cipher::{has_aes_hardware_support, preferred_cipher};
 use crate::secrets_store::{open_secrets_store, SecretStoreResult, SecretsStore};
 use crate::service::config::{read_config, write_config, Config};
 use crate::service::error::{ServiceError, ServiceResult};
@@ -10,7 +15,7 @@ use crate::service::error::{ServiceError, ServiceResult};
 use crate::service::secrets_provider::SecretsProvider;
 use crate::service::{ClipboardControl, TrustlessService};
 use chrono::{DateTime, Utc};
-use log::{error, info};
+use log::{debug, error, info, warn};
 use rand::{distributions, thread_rng, Rng};
 use std::collections::{HashMap, VecDeque};
 use std::sync::{Arc, Mutex, RwLock};
@@ -51,6 +56,72 @@ impl ClipboardControl for ClipboardHolder {
   }
 }
 
+pub(crate) const SITE_KEY_LENGTH: usize = 32;
+
+/// Maximum time an autolock is deferred while the clipboard still provides a secret of the store.
+pub(crate) const MAX_AUTOLOCK_CLIPBOARD_GRACE: chrono::Duration = chrono::Duration::seconds(60);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum AutolockOutcome {
+  Locked,
+  LockedAndClearedClipboard,
+  Deferred,
+}
+
+/// Deterministically generate the password of a site from the site key of the store.
+///
+/// Nothing is persisted, the site key is wiped right after the password has been generated.
+///
+/// Stability contract: As these passwords are never stored, the same private key, site and `param` have to
+/// yield the same password forever. This pins
+/// * the site key derivation (see `secrets_store::site::derive_site_key`, versioned by its HKDF info),
+/// * and the mapping of the site key to a password (see `pw_generator::generate_derived_password`, versioned
+///   by its HKDF info as well).
+///
+/// Neither depends on the internals of a dependency, changing them requires a new version. The golden vectors
+/// in `local_tests` have to catch any accidental change.
+pub(crate) fn derive_site_password(
+  secrets_store: &dyn SecretsStore,
+  site: &str,
+  param: &PasswordGeneratorParam,
+) -> ServiceResult<String> {
+  let site_key = secrets_store.derive_site_key(site, SITE_KEY_LENGTH)?;
+
+  generate_derived_password(&site_key, param)
+}
+
+/// Autolock a store that is due, taking care of a clipboard that might still provide one of its secrets.
+///
+/// The clipboard only holds already decrypted values, so there is no need to keep the store unlocked for it:
+/// Either the clipboard is cleared together with the store, or the lock is deferred until the clipboard is
+/// done (but at most `MAX_AUTOLOCK_CLIPBOARD_GRACE`).
+pub(crate) fn autolock_store(
+  name: &str,
+  secrets_store: &dyn SecretsStore,
+  autolock_at: ZeroizeDateTime,
+  clipboard: &dyn ClipboardControl,
+  clears_clipboard: bool,
+  now: DateTime<Utc>,
+) -> ServiceResult<AutolockOutcome> {
+  let providing_store = !clipboard.is_done()?
+    && clipboard
+      .currently_providing()?
+      .map(|providing| providing.store_name == name)
+      .unwrap_or(false);
+
+  if !providing_store {
+    secrets_store.autolock()?;
+    return Ok(AutolockOutcome::Locked);
+  }
+  if !clears_clipboard && ZeroizeDateTime::from(now) - autolock_at < MAX_AUTOLOCK_CLIPBOARD_GRACE {
+    return Ok(AutolockOutcome::Deferred);
+  }
+  secrets_store.autolock()?;
+  clipboard.destroy()?;
+
+  Ok(AutolockOutcome::LockedAndClearedClipboard)
+}
+
 struct LocalEventQueue {
   last_id: u64,
   limit: usize,
@@ -71,7 +142,11 @@ impl LocalEventQueue {
       self.queue.pop_front();
     }
     self.last_id += 1;
-    self.queue.push_back(Event { id: self.last_id, data });
+    self.queue.push_back(Event {
+      id: self.last_id,
+      timestamp: ZeroizeDateTime::now(),
+      data,
+    });
   }
 
   fn poll(&self, last_id: u64) -> Vec<Event> {
@@ -114,7 +189,7 @@ impl EventHub for LocalEventHub {
 pub struct LocalTrustlessService {
   config: RwLock<Config>,
   opened_stores: RwLock<HashMap<String, Arc<dyn SecretsStore>>>,
-  synchronizers: Mutex<Vec<Synchronizer>>,
+  synchronizers: Mutex<Vec<Arc<Mutex<Synchronizer>>>>,
   clipboard: RwLock<Arc<ClipboardHolder>>,
   event_hub: Arc<LocalEventHub>,
 }
@@ -131,6 +206,41 @@ impl LocalTrustlessService {
       event_hub: Arc::new(LocalEventHub::new(100)),
     })
   }
+
+  /// Flush all opened stores (e.g. on shutdown), errors are only logged.
+  pub fn flush_stores(&self) {
+    match self.opened_stores.read() {
+      Ok(opened_stores) => {
+        for (name, secrets_store) in opened_stores.iter() {
+          if let Err(error) = secrets_store.flush() {
+            error!("Flush of {} failed: {}", name, error);
+          }
+        }
+      }
+      Err(err) => error!("Failed flushing opened stores: {}", err),
+    }
+  }
+
+  /// Lock all opened stores and clear the clipboard (e.g. on system sleep or screen lock).
+  fn lock_all_automatically(&self) {
+    match self.opened_stores.read() {
+      Ok(opened_stores) => {
+        for (name, secrets_store) in opened_stores.iter() {
+          if let Err(error) = secrets_store.autolock() {
+            error!("Lock of {} failed: {}", name, error);
+          }
+        }
+      }
+      Err(err) => error!("Failed locking opened stores: {}", err),
+    }
+    match self.clipboard.write() {
+      Ok(mut clipboard) => match clipboard.destroy() {
+        Ok(_) => *clipboard = Arc::new(ClipboardHolder::Empty),
+        Err(error) => error!("Clear of clipboard failed: {}", error),
+      },
+      Err(error) => error!("Clear of clipboard failed: {}", error),
+    }
+  }
 }
 
 impl TrustlessService for LocalTrustlessService {
@@ -180,21 +290,16 @@ impl TrustlessService for LocalTrustlessService {
       .stores
       .get(name)
       .ok_or_else(|| StoreError::StoreNotFound(name.to_string()))?;
-    let (store, maybe_sync_block_store) = open_secrets_store(
-      name,
-      &store_config.store_url,
-      store_config.remote_url.as_deref(),
-      &store_config.client_id,
-      Duration::from_secs(store_config.autolock_timeout_secs),
-      self.event_hub.clone(),
-    )?;
+    let (store, maybe_sync_block_store) = open_secrets_store(store_config, self.event_hub.clone())?;
 
     if let Some(sync_block_store) = maybe_sync_block_store {
-      self.synchronizers.lock()?.push(Synchronizer::new(
+      self.synchronizers.lock()?.push(Arc::new(Mutex::new(Synchronizer::new(
+        name,
         store.clone(),
         sync_block_store,
         chrono::Duration::seconds(store_config.sync_interval_sec as i64),
-      ));
+        self.event_hub.clone(),
+      ))));
     }
 
     opened_stores.insert(name.to_string(), store.clone());
@@ -233,6 +338,18 @@ impl TrustlessService for LocalTrustlessService {
       let secret_version = store.get_version(block_id)?;
       let secret_provider =
         SecretsProvider::new(store_name.to_string(), block_id.to_string(), secret_version, properties);
+      let (restore_previous, timeout) = {
+        let config = self.config.read()?;
+        let store_config = config.stores.get(store_name);
+        let restore_previous = store_config
+          .and_then(|store_config| store_config.restore_clipboard)
+          .unwrap_or(config.restore_clipboard);
+        let timeout = store_config
+          .map(|store_config| store_config.clipboard_timeout_secs)
+          .filter(|secs| *secs > 0)
+          .map(Duration::from_secs);
+        (restore_previous, timeout)
+      };
       let mut clipboard = self.clipboard.write()?;
 
       clipboard.destroy()?;
@@ -241,6 +358,8 @@ impl TrustlessService for LocalTrustlessService {
 
       let next_clipboard = Arc::new(ClipboardHolder::Providing(Clipboard::new(
         secret_provider,
+        restore_previous,
+        timeout,
         self.event_hub.clone(),
       )?));
       *clipboard = next_clipboard.clone();
@@ -249,7 +368,7 @@ impl TrustlessService for LocalTrustlessService {
     }
     #[cfg(not(any(unix, windows)))]
     {
-      Err(ServiceError::NotAvailable)
+      Err(ServiceError::ClipboardUnavailable)
     }
   }
 
@@ -270,10 +389,19 @@ impl TrustlessService for LocalTrustlessService {
   }
 
   fn generate_password(&self, param: PasswordGeneratorParam) -> ServiceResult<String> {
-    match &param {
-      PasswordGeneratorParam::Chars(params) => Ok(generate_chars(params)),
-      PasswordGeneratorParam::Words(params) => Ok(generate_words(params)),
-    }
+    generate_password(&mut thread_rng(), &param)
+  }
+
+  fn derive_site_password(&self, store_name: &str, site: &str, param: PasswordGeneratorParam) -> ServiceResult<String> {
+    derive_site_password(self.open_store(store_name)?.as_ref(), site, &param)
+  }
+
+  fn capabilities(&self) -> ServiceResult<Capabilities> {
+    Ok(Capabilities {
+      version: env!("CARGO_PKG_VERSION").to_string(),
+      aes_hardware_acceleration: has_aes_hardware_support(),
+      preferred_cipher: preferred_cipher().name(),
+    })
   }
 
   fn check_autolock(&self) {
@@ -284,6 +412,20 @@ impl TrustlessService for LocalTrustlessService {
         return;
       }
     };
+    let clears_clipboard = match self.config.read() {
+      Ok(config) => config.autolock_clears_clipboard,
+      Err(err) => {
+        error!("Failed reading config: {}", err);
+        true
+      }
+    };
+    let clipboard = match self.clipboard.read() {
+      Ok(clipboard) => clipboard.clone(),
+      Err(err) => {
+        error!("Failed locking clipboard: {}", err);
+        return;
+      }
+    };
 
     for (name, secrets_store) in opened_stores.iter() {
       let status = match secrets_store.status() {
@@ -295,16 +437,46 @@ impl TrustlessService for LocalTrustlessService {
       };
 
       if let Some(autolock_at) = status.autolock_at {
-        if autolock_at < Utc::now().into() {
-          info!("Autolocking {}", name);
-          if let Err(error) = secrets_store.lock() {
-            error!("Autolocker was unable to lock store: {}", error);
+        let now = Utc::now();
+        if autolock_at < now.into() {
+          match autolock_store(
+            name,
+            secrets_store.as_ref(),
+            autolock_at,
+            clipboard.as_ref(),
+            clears_clipboard,
+            now,
+          ) {
+            Ok(AutolockOutcome::Locked) => info!("Autolocking {}", name),
+            Ok(AutolockOutcome::LockedAndClearedClipboard) => info!("Autolocking {} and clearing clipboard", name),
+            Ok(AutolockOutcome::Deferred) => debug!("Autolock of {} deferred for active clipboard", name),
+            Err(error) => error!("Autolocker was unable to lock store: {}", error),
           }
         }
       }
     }
   }
 
+  fn lock_on_sleep(&self) {
+    match self.config.read() {
+      Ok(config) if !config.lock_on_sleep => return,
+      Ok(_) => (),
+      Err(err) => error!("Failed reading config: {}", err),
+    }
+    info!("System is going to sleep, locking all stores");
+    self.lock_all_automatically();
+  }
+
+  fn lock_on_screen_lock(&self) {
+    match self.config.read() {
+      Ok(config) if !config.lock_on_screen_lock => return,
+      Ok(_) => (),
+      Err(err) => error!("Failed reading config: {}", err),
+    }
+    info!("Screen has been locked, locking all stores");
+    self.lock_all_automatically();
+  }
+
   fn needs_synchronization(&self) -> bool {
     if let Ok(config) = self.config.read() {
       config
@@ -317,10 +489,18 @@ impl TrustlessService for LocalTrustlessService {
   }
 
   fn synchronize(&self) -> Option<DateTime<Utc>> {
-    match self.synchronizers.lock() {
-      Ok(mut synchronizers) => {
-        let mut result = None;
-        for synchronizer in synchronizers.iter_mut() {
+    // The list is copied, so that opening a store does not have to wait for a (slow) synchronization
+    let synchronizers = match self.synchronizers.lock() {
+      Ok(synchronizers) => synchronizers.clone(),
+      Err(err) => {
+        error!("Synchronization lock failed: {}", err);
+        return None;
+      }
+    };
+    let mut result = None;
+    for synchronizer in synchronizers {
+      match synchronizer.lock() {
+        Ok(mut synchronizer) => {
           if let Err(err) = synchronizer.synchronize() {
             error!("Synchronization failed: {}", err);
           }
@@ -331,13 +511,88 @@ impl TrustlessService for LocalTrustlessService {
             None => Some(next),
           };
         }
-        result
+        Err(err) => error!("Synchronization lock failed: {}", err),
       }
-      Err(err) => {
-        error!("Synchronization lock failed: {}", err);
-        None
+    }
+    result
+  }
+
+  fn synchronize_now(&self, store_name: &str) -> ServiceResult<SyncReport> {
+    // Opening the store registers its synchronizer (if there is a remote)
+    self.open_store(store_name)?;
+    let synchronizers = self.synchronizers.lock()?.clone();
+    let synchronizer = synchronizers
+      .into_iter()
+      .find(|synchronizer| match synchronizer.lock() {
+        Ok(synchronizer) => synchronizer.store_name() == store_name,
+        Err(_) => false,
+      })
+      .ok_or(ServiceError::NotAvailable)?;
+    let mut synchronizer = synchronizer.lock()?;
+
+    synchronizer.synchronize_now()
+  }
+
+  fn panic_lock(&self, wipe_local: bool) -> ServiceResult<PanicLockReport> {
+    warn!("Panic lock (wipe local: {})", wipe_local);
+    let mut report = PanicLockReport::default();
+    // Every step is attempted, no matter if a previous one failed
+    {
+      let opened_stores = match self.opened_stores.read() {
+        Ok(opened_stores) => opened_stores,
+        Err(poisoned) => poisoned.into_inner(),
+      };
+
+      for (name, secrets_store) in opened_stores.iter() {
+        match secrets_store.lock() {
+          Ok(_) => report.locked_stores.push(name.clone()),
+          Err(error) => error!("Panic lock of {} failed: {}", name, error),
+        }
+        if wipe_local {
+          match secrets_store.wipe_index() {
+            Ok(_) => report.wiped_indexes.push(name.clone()),
+            Err(error) => error!("Wiping index of {} failed: {}", name, error),
+          }
+        }
       }
+      report.sessions_cleared = opened_stores
+        .values()
+        .all(|secrets_store| secrets_store.status().map(|status| status.locked).unwrap_or(false));
+    }
+    match self.clipboard.write() {
+      Ok(mut clipboard) => match clipboard.destroy() {
+        Ok(_) => {
+          *clipboard = Arc::new(ClipboardHolder::Empty);
+          report.clipboard_cleared = true;
+        }
+        Err(error) => error!("Panic clear of clipboard failed: {}", error),
+      },
+      Err(error) => error!("Panic clear of clipboard failed: {}", error),
     }
+
+    self.event_hub.send(EventData::PanicLocked {
+      store_names: report.locked_stores.clone(),
+      wiped_local: wipe_local,
+    });
+
+    Ok(report)
+  }
+
+  fn diagnose(&self, store_name: &str) -> ServiceResult<Diagnostics> {
+    let store_config = self
+      .config
+      .read()?
+      .stores
+      .get(store_name)
+      .cloned()
+      .ok_or_else(|| StoreError::StoreNotFound(store_name.to_string()))?;
+    let store_diagnostics = self.open_store(store_name)?.diagnose()?;
+
+    Ok(Diagnostics::new(
+      &store_config,
+      store_diagnostics,
+      memory_locking_active(),
+    ))
   }
 }
 
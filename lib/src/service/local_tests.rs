@@ -0,0 +1,293 @@
+use super::local::{
+  autolock_store, derive_site_password, AutolockOutcome, MAX_AUTOLOCK_CLIPBOARD_GRACE, SITE_KEY_LENGTH,
+};
+use super::pw_generator::generate_derived_password;
+use super::{ClipboardControl, ServiceResult};
+use crate::api::{
+  ClipboardProviding, EventData, EventHub, Identity, PasswordGeneratorCharsParam, PasswordGeneratorParam,
+  PasswordGeneratorPronounceableParam, PasswordGeneratorWordsParam, StoreConfig, ZeroizeDateTime,
+};
+use crate::memguard::SecretBytes;
+use crate::secrets_store::site::derive_site_key;
+use crate::secrets_store::{open_secrets_store, SecretsStore};
+use crate::secrets_store_capnp::KeyType;
+use chrono::Utc;
+use spectral::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+#[derive(Default)]
+struct TestEventHub {
+  events: Mutex<Vec<EventData>>,
+}
+
+impl TestEventHub {
+  fn autolocked(&self) -> Vec<bool> {
+    self
+      .events
+      .lock()
+      .unwrap()
+      .iter()
+      .filter_map(|event| match event {
+        EventData::StoreLocked { autolock, .. } => Some(*autolock),
+        _ => None,
+      })
+      .collect()
+  }
+}
+
+impl EventHub for TestEventHub {
+  fn send(&self, event: EventData) {
+    self.events.lock().unwrap().push(event);
+  }
+}
+
+struct TestClipboard {
+  store_name: String,
+  open: AtomicBool,
+}
+
+impl TestClipboard {
+  fn providing(store_name: &str) -> TestClipboard {
+    TestClipboard {
+      store_name: store_name.to_string(),
+      open: AtomicBool::new(true),
+    }
+  }
+}
+
+impl ClipboardControl for TestClipboard {
+  fn is_done(&self) -> ServiceResult<bool> {
+    Ok(!self.open.load(Ordering::Relaxed))
+  }
+
+  fn currently_providing(&self) -> ServiceResult<Option<ClipboardProviding>> {
+    if !self.open.load(Ordering::Relaxed) {
+      return Ok(None);
+    }
+    Ok(Some(ClipboardProviding {
+      store_name: self.store_name.clone(),
+      block_id: "block1".to_string(),
+      secret_name: "secret1".to_string(),
+      property: "password".to_string(),
+    }))
+  }
+
+  fn provide_next(&self) -> ServiceResult<()> {
+    Ok(())
+  }
+
+  fn destroy(&self) -> ServiceResult<()> {
+    self.open.store(false, Ordering::Relaxed);
+    Ok(())
+  }
+}
+
+fn unlocked_store() -> Arc<dyn SecretsStore> {
+  unlocked_store_with_events(Arc::new(TestEventHub::default()))
+}
+
+fn unlocked_store_with_events(event_hub: Arc<TestEventHub>) -> Arc<dyn SecretsStore> {
+  let (secrets_store, _) =
+    open_secrets_store(&StoreConfig::new("test", "multilane+memory://", "node1"), event_hub).unwrap();
+  let identity = Identity {
+    id: "identity1".to_string(),
+    name: "Name1".to_string(),
+    email: "Email1".to_string(),
+    hidden: false,
+    hardware_factor: false,
+  };
+  let passphrase = SecretBytes::from(b"Passphrase1".to_vec());
+
+  secrets_store.add_identity(identity, passphrase.clone()).unwrap();
+  secrets_store.unlock("identity1", passphrase).unwrap();
+
+  secrets_store
+}
+
+fn is_locked(secrets_store: &dyn SecretsStore) -> bool {
+  secrets_store.status().unwrap().locked
+}
+
+#[test]
+#[cfg_attr(debug_assertions, ignore)]
+fn test_autolock_during_active_clipboard() {
+  let secrets_store = unlocked_store();
+  let now = Utc::now();
+  let autolock_at = ZeroizeDateTime::from(now);
+
+  // Clipboard providing a secret of another store is not affected
+  let clipboard = TestClipboard::providing("other");
+  assert_that(&autolock_store("test", secrets_store.as_ref(), autolock_at, &clipboard, true, now).unwrap())
+    .is_equal_to(AutolockOutcome::Locked);
+  assert_that(&is_locked(secrets_store.as_ref())).is_true();
+  assert_that(&clipboard.is_done().unwrap()).is_false();
+
+  // Store is locked and the clipboard cleared
+  let secrets_store = unlocked_store();
+  let clipboard = TestClipboard::providing("test");
+  assert_that(&autolock_store("test", secrets_store.as_ref(), autolock_at, &clipboard, true, now).unwrap())
+    .is_equal_to(AutolockOutcome::LockedAndClearedClipboard);
+  assert_that(&is_locked(secrets_store.as_ref())).is_true();
+  assert_that(&clipboard.is_done().unwrap()).is_true();
+}
+
+#[test]
+#[cfg_attr(debug_assertions, ignore)]
+fn test_autolock_deferred_for_active_clipboard() {
+  let secrets_store = unlocked_store();
+  let now = Utc::now();
+  let autolock_at = ZeroizeDateTime::from(now);
+
+  // Lock is deferred until the clipboard is done
+  let clipboard = TestClipboard::providing("test");
+  assert_that(&autolock_store("test", secrets_store.as_ref(), autolock_at, &clipboard, false, now).unwrap())
+    .is_equal_to(AutolockOutcome::Deferred);
+  assert_that(&is_locked(secrets_store.as_ref())).is_false();
+  assert_that(&clipboard.is_done().unwrap()).is_false();
+
+  clipboard.destroy().unwrap();
+  assert_that(&autolock_store("test", secrets_store.as_ref(), autolock_at, &clipboard, false, now).unwrap())
+    .is_equal_to(AutolockOutcome::Locked);
+  assert_that(&is_locked(secrets_store.as_ref())).is_true();
+
+  // ... but not longer than the grace period
+  let secrets_store = unlocked_store();
+  let clipboard = TestClipboard::providing("test");
+  let later = now + MAX_AUTOLOCK_CLIPBOARD_GRACE + chrono::Duration::seconds(1);
+  assert_that(&autolock_store("test", secrets_store.as_ref(), autolock_at, &clipboard, false, later).unwrap())
+    .is_equal_to(AutolockOutcome::LockedAndClearedClipboard);
+  assert_that(&is_locked(secrets_store.as_ref())).is_true();
+  assert_that(&clipboard.is_done().unwrap()).is_true();
+}
+
+#[test]
+#[cfg_attr(debug_assertions, ignore)]
+fn test_autolock_event() {
+  let event_hub = Arc::new(TestEventHub::default());
+  let secrets_store = unlocked_store_with_events(event_hub.clone());
+  let now = Utc::now();
+  let clipboard = TestClipboard::providing("other");
+
+  autolock_store("test", secrets_store.as_ref(), now.into(), &clipboard, true, now).unwrap();
+  secrets_store.lock().unwrap();
+
+  assert_that(&event_hub.autolocked()).is_equal_to(vec![true, false]);
+}
+
+fn site_password_param() -> PasswordGeneratorParam {
+  PasswordGeneratorParam::Chars(PasswordGeneratorCharsParam {
+    num_chars: 20,
+    include_uppers: true,
+    include_numbers: true,
+    include_symbols: true,
+    require_upper: true,
+    require_number: true,
+    require_symbol: true,
+    exclude_similar: false,
+    exclude_ambiguous: false,
+    symbol_set: None,
+    exclude_chars: None,
+  })
+}
+
+#[test]
+#[cfg_attr(debug_assertions, ignore)]
+fn test_derive_site_password() {
+  let secrets_store = unlocked_store();
+  let param = site_password_param();
+
+  let password = derive_site_password(secrets_store.as_ref(), "example.com", &param).unwrap();
+
+  assert_that(&password.chars().count()).is_equal_to(20);
+  assert_that(&password.chars().any(|ch| ch.is_ascii_uppercase())).is_true();
+  assert_that(&password.chars().any(|ch| ch.is_ascii_digit())).is_true();
+  // Same inputs yield the same password (also for other spellings of the site)
+  assert_that(&derive_site_password(secrets_store.as_ref(), "example.com", &param).unwrap()).is_equal_to(&password);
+  assert_that(&derive_site_password(secrets_store.as_ref(), "https://www.example.com/login", &param).unwrap())
+    .is_equal_to(&password);
+  assert_that(&derive_site_password(secrets_store.as_ref(), "example.org", &param).unwrap()).is_not_equal_to(&password);
+
+  let pin = derive_site_password(
+    secrets_store.as_ref(),
+    "example.com",
+    &PasswordGeneratorParam::Pin { length: 6 },
+  )
+  .unwrap();
+  assert_that(&pin.len()).is_equal_to(6);
+  assert_that(
+    &derive_site_password(
+      secrets_store.as_ref(),
+      "example.com",
+      &PasswordGeneratorParam::Pin { length: 6 },
+    )
+    .unwrap(),
+  )
+  .is_equal_to(&pin);
+}
+
+#[test]
+#[cfg_attr(debug_assertions, ignore)]
+fn test_derive_site_password_locked() {
+  let secrets_store = unlocked_store();
+
+  secrets_store.lock().unwrap();
+
+  assert_that(&derive_site_password(
+    secrets_store.as_ref(),
+    "example.com",
+    &site_password_param(),
+  ))
+  .is_err();
+}
+
+/// One golden vector per kind of generator, derived passwords must never change (see `derive_site_password`).
+fn golden_params() -> Vec<(PasswordGeneratorParam, &'static str)> {
+  vec![
+    (site_password_param(), r#"V\o4C89B3YD/s"LB$a0j"#),
+    (
+      PasswordGeneratorParam::Words(PasswordGeneratorWordsParam {
+        num_words: 4,
+        delim: '.',
+        wordlist_path: None,
+      }),
+      "wain.faery.rep.kyle",
+    ),
+    (
+      PasswordGeneratorParam::Pronounceable(PasswordGeneratorPronounceableParam {
+        num_chars: 16,
+        include_number: true,
+        include_symbol: true,
+      }),
+      "aupaig&r1eetieki",
+    ),
+    (PasswordGeneratorParam::Pin { length: 6 }, "147869"),
+  ]
+}
+
+#[test]
+fn test_site_password_golden_vectors() {
+  let site_key = SecretBytes::from((0..SITE_KEY_LENGTH as u8).collect::<Vec<_>>());
+
+  for (param, expected) in golden_params() {
+    assert_that(&generate_derived_password(&site_key, &param).unwrap()).is_equal_to(expected.to_string());
+  }
+}
+
+#[test]
+#[cfg_attr(debug_assertions, ignore)]
+fn test_derive_site_password_golden_vectors() {
+  let private_keys = vec![
+    (KeyType::RsaAesGcm, SecretBytes::from(b"rsa key".to_vec())),
+    (
+      KeyType::Ed25519Chacha20Poly1305,
+      SecretBytes::from(b"ed25519 key".to_vec()),
+    ),
+  ];
+  let site_key = derive_site_key(&private_keys, "https://www.example.com/login", SITE_KEY_LENGTH).unwrap();
+
+  assert_that(&generate_derived_password(&site_key, &site_password_param()).unwrap())
+    .is_equal_to(r#"-&UvKN,zL8cV$T4sH\F-"#.to_string());
+  assert_that(&generate_derived_password(&site_key, &PasswordGeneratorParam::Pin { length: 6 }).unwrap())
+    .is_equal_to("383691".to_string());
+}
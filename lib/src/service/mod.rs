@@ -1,15 +1,22 @@
 use chrono::{DateTime, Utc};
 
-use crate::api::{ClipboardProviding, Event, PasswordGeneratorParam, StoreConfig};
+use crate::api::{
+  Capabilities, ClipboardProviding, Diagnostics, Event, PanicLockReport, PasswordGeneratorParam, StoreConfig,
+  SyncReport,
+};
 use std::sync::Arc;
 
 mod config;
 mod error;
 pub mod local;
+#[cfg(test)]
+mod local_tests;
 pub mod pw_generator;
 mod remote;
 pub mod secrets_provider;
 mod synchronizer;
+#[cfg(test)]
+mod synchronizer_tests;
 
 #[cfg(unix)]
 pub mod unix;
@@ -65,11 +72,39 @@ pub trait TrustlessService: std::fmt::Debug + Send + Sync {
 
   fn generate_password(&self, param: PasswordGeneratorParam) -> ServiceResult<String>;
 
+  /// Deterministically derive the password of a site from the private keys of the identity a store is
+  /// unlocked with, i.e. the same site (and param) always yields the same password without storing anything.
+  fn derive_site_password(&self, store_name: &str, site: &str, param: PasswordGeneratorParam) -> ServiceResult<String>;
+
+  fn capabilities(&self) -> ServiceResult<Capabilities>;
+
   fn check_autolock(&self);
 
+  /// Lock all opened stores and clear the clipboard because the system is about to sleep/suspend
+  /// (unless disabled in the config).
+  fn lock_on_sleep(&self);
+
+  /// Lock all opened stores and clear the clipboard because the screen has been locked
+  /// (unless disabled in the config).
+  fn lock_on_screen_lock(&self);
+
   fn needs_synchronization(&self) -> bool;
 
+  /// Synchronize all stores that are due, returns the next time a synchronization is due.
+  /// (This is intended for the background loop, errors are only logged)
   fn synchronize(&self) -> Option<DateTime<Utc>>;
+
+  /// Synchronize a store with its remote right away
+  fn synchronize_now(&self, store_name: &str) -> ServiceResult<SyncReport>;
+
+  /// Lock all opened stores (dropping all session keys) and clear the clipboard right away.
+  /// If `wipe_local` is set the local index blocks are wiped as well, data blocks and rings are
+  /// never touched, i.e. the stores can always be unlocked again with the passphrase.
+  fn panic_lock(&self, wipe_local: bool) -> ServiceResult<PanicLockReport>;
+
+  /// Gather diagnostic information of a store that is safe to be attached to a bug report,
+  /// i.e. it never contains any secret material.
+  fn diagnose(&self, store_name: &str) -> ServiceResult<Diagnostics>;
 }
 
 pub fn create_service() -> ServiceResult<Arc<dyn TrustlessService>> {
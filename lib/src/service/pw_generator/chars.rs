@@ -1,39 +1,50 @@
 use crate::api::PasswordGeneratorCharsParam;
+use crate::service::{ServiceError, ServiceResult};
 use rand::seq::SliceRandom;
-use rand::{thread_rng, Rng};
+use rand::Rng;
 
 const LOWERS: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
-const UPPERS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
-const NUMBERS: &[u8] = b"0123456789";
+pub(super) const UPPERS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+pub(super) const NUMBERS: &[u8] = b"0123456789";
 const SYMBOLS: &[u8] = b"!-+*#_$%&/()=?{}[]()/\\'\"`-,;:.<>";
 const AMBIGOUS_CHARS: &[u8] = b"{}[]()/\\'\"`-,;:.<>";
 const SIMILAR_CHARS: &[u8] = b"QO01lIB8S5G62ZUV";
 
-pub fn generate_chars(params: &PasswordGeneratorCharsParam) -> String {
-  let mut rng = thread_rng();
+pub fn generate_chars<R: Rng>(rng: &mut R, params: &PasswordGeneratorCharsParam) -> ServiceResult<String> {
+  let symbols = symbol_set(params)?;
   let mut pool = Vec::with_capacity(params.num_chars as usize);
 
   if params.require_upper {
-    pool.push(pick_char_from(&mut rng, UPPERS, params));
+    pool.push(pick_char_from(rng, UPPERS, params, "upper case letters")?);
   }
   if params.require_number {
-    pool.push(pick_char_from(&mut rng, NUMBERS, params));
+    pool.push(pick_char_from(rng, NUMBERS, params, "numbers")?);
   }
   if params.require_symbol {
-    pool.push(pick_char_from(&mut rng, SYMBOLS, params));
+    pool.push(pick_char_from(rng, symbols, params, "symbols")?);
   }
-  let candidates = create_base_set(params);
+  let candidates = create_base_set(params, symbols)?;
   while pool.len() < params.num_chars as usize {
-    pool.push(*candidates.choose(&mut rng).unwrap());
+    pool.push(*candidates.choose(rng).unwrap());
   }
 
-  pool.shuffle(&mut rng);
+  pool.shuffle(rng);
 
-  String::from_utf8(pool).unwrap()
+  Ok(String::from_utf8(pool).unwrap())
 }
 
-fn create_base_set(params: &PasswordGeneratorCharsParam) -> Vec<u8> {
-  let mut candidates = Vec::with_capacity(LOWERS.len() + UPPERS.len() + NUMBERS.len() + SYMBOLS.len());
+pub(super) fn symbol_set(params: &PasswordGeneratorCharsParam) -> ServiceResult<&[u8]> {
+  match &params.symbol_set {
+    Some(symbol_set) if !symbol_set.is_ascii() => Err(ServiceError::InvalidGeneratorParams(
+      "Only ascii characters are supported as symbols".to_string(),
+    )),
+    Some(symbol_set) => Ok(symbol_set.as_bytes()),
+    None => Ok(SYMBOLS),
+  }
+}
+
+pub(super) fn create_base_set(params: &PasswordGeneratorCharsParam, symbols: &[u8]) -> ServiceResult<Vec<u8>> {
+  let mut candidates = Vec::with_capacity(LOWERS.len() + UPPERS.len() + NUMBERS.len() + symbols.len());
 
   filter_set(&mut candidates, LOWERS, params);
   if params.include_uppers {
@@ -43,10 +54,15 @@ fn create_base_set(params: &PasswordGeneratorCharsParam) -> Vec<u8> {
     filter_set(&mut candidates, NUMBERS, params);
   }
   if params.include_symbols {
-    filter_set(&mut candidates, SYMBOLS, params);
+    filter_set(&mut candidates, symbols, params);
+  }
+  if candidates.is_empty() {
+    return Err(ServiceError::InvalidGeneratorParams(
+      "No characters left to generate a password".to_string(),
+    ));
   }
 
-  candidates
+  Ok(candidates)
 }
 
 fn filter_set(candidates: &mut Vec<u8>, set: &[u8], params: &PasswordGeneratorCharsParam) {
@@ -57,52 +73,157 @@ fn filter_set(candidates: &mut Vec<u8>, set: &[u8], params: &PasswordGeneratorCh
     if params.exclude_ambiguous && AMBIGOUS_CHARS.contains(ch) {
       continue;
     }
+    if params
+      .exclude_chars
+      .iter()
+      .any(|exclude_chars| exclude_chars.as_bytes().contains(ch))
+    {
+      continue;
+    }
     candidates.push(*ch);
   }
 }
 
-fn pick_char_from<R: Rng>(rng: &mut R, set: &[u8], params: &PasswordGeneratorCharsParam) -> u8 {
+fn pick_char_from<R: Rng>(
+  rng: &mut R,
+  set: &[u8],
+  params: &PasswordGeneratorCharsParam,
+  category: &str,
+) -> ServiceResult<u8> {
+  Ok(*required_set(set, params, category)?.choose(rng).unwrap())
+}
+
+/// Characters of `set` that may be used to satisfy the requirement of a `category`
+pub(super) fn required_set(set: &[u8], params: &PasswordGeneratorCharsParam, category: &str) -> ServiceResult<Vec<u8>> {
   let mut candidates = Vec::with_capacity(set.len());
   filter_set(&mut candidates, set, params);
 
-  *candidates.choose(rng).unwrap()
+  if candidates.is_empty() {
+    return Err(ServiceError::InvalidGeneratorParams(format!(
+      "No {} left to satisfy the requirement",
+      category
+    )));
+  }
+
+  Ok(candidates)
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
+  use rand::thread_rng;
   use spectral::prelude::*;
 
   #[test]
   fn test_generate_chars() {
-    let pw1 = generate_chars(&PasswordGeneratorCharsParam {
-      num_chars: 14,
-      include_uppers: false,
-      include_numbers: false,
-      include_symbols: false,
-      require_number: false,
-      require_upper: false,
-      require_symbol: false,
-      exclude_similar: false,
-      exclude_ambiguous: false,
-    });
+    let pw1 = generate_chars(
+      &mut thread_rng(),
+      &PasswordGeneratorCharsParam {
+        num_chars: 14,
+        include_uppers: false,
+        include_numbers: false,
+        include_symbols: false,
+        require_number: false,
+        require_upper: false,
+        require_symbol: false,
+        exclude_similar: false,
+        exclude_ambiguous: false,
+        symbol_set: None,
+        exclude_chars: None,
+      },
+    )
+    .unwrap();
 
     assert_that(&pw1.len()).is_equal_to(14);
     assert_that(&pw1.chars().all(|ch| ch.is_lowercase())).is_true();
 
-    let pw2: String = generate_chars(&PasswordGeneratorCharsParam {
-      num_chars: 20,
+    let pw2: String = generate_chars(
+      &mut thread_rng(),
+      &PasswordGeneratorCharsParam {
+        num_chars: 20,
+        include_uppers: true,
+        include_numbers: false,
+        include_symbols: false,
+        require_number: false,
+        require_upper: true,
+        require_symbol: false,
+        exclude_similar: false,
+        exclude_ambiguous: false,
+        symbol_set: None,
+        exclude_chars: None,
+      },
+    )
+    .unwrap();
+
+    assert_that(&pw2.len()).is_equal_to(20);
+    assert_that(&pw2.chars().any(|ch| ch.is_uppercase())).is_true();
+  }
+
+  #[test]
+  fn test_generate_chars_custom_symbols() {
+    let params = PasswordGeneratorCharsParam {
+      num_chars: 30,
       include_uppers: true,
-      include_numbers: false,
-      include_symbols: false,
-      require_number: false,
+      include_numbers: true,
+      include_symbols: true,
+      require_number: true,
       require_upper: true,
-      require_symbol: false,
+      require_symbol: true,
       exclude_similar: false,
       exclude_ambiguous: false,
-    });
+      symbol_set: Some("!#".to_string()),
+      exclude_chars: Some("aeiouAEIOU#".to_string()),
+    };
 
-    assert_that(&pw2.len()).is_equal_to(20);
-    assert_that(&pw2.chars().any(|ch| ch.is_uppercase())).is_true();
+    for _ in 0..10 {
+      let pw = generate_chars(&mut thread_rng(), &params).unwrap();
+
+      assert_that(&pw.len()).is_equal_to(30);
+      assert_that(&pw.contains('!')).is_true();
+      assert_that(&pw.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '!')).is_true();
+      assert_that(&pw.chars().any(|ch| "aeiouAEIOU#".contains(ch))).is_false();
+    }
+  }
+
+  #[test]
+  fn test_generate_chars_empty_alphabet() {
+    let params = PasswordGeneratorCharsParam {
+      num_chars: 10,
+      include_uppers: false,
+      include_numbers: false,
+      include_symbols: true,
+      require_number: false,
+      require_upper: false,
+      require_symbol: true,
+      exclude_similar: false,
+      exclude_ambiguous: false,
+      symbol_set: Some("!#".to_string()),
+      exclude_chars: Some("!#".to_string()),
+    };
+
+    assert_that(&matches!(
+      generate_chars(&mut thread_rng(), &params),
+      Err(ServiceError::InvalidGeneratorParams(_))
+    ))
+    .is_true();
+
+    let mut params = params;
+    params.require_symbol = false;
+    params.exclude_chars = Some("abcdefghijklmnopqrstuvwxyz!#".to_string());
+
+    assert_that(&matches!(
+      generate_chars(&mut thread_rng(), &params),
+      Err(ServiceError::InvalidGeneratorParams(_))
+    ))
+    .is_true();
+
+    params.symbol_set = Some("§".to_string());
+    params.exclude_chars = None;
+
+    assert_that(&matches!(
+      generate_chars(&mut thread_rng(), &params),
+      Err(ServiceError::InvalidGeneratorParams(_))
+    ))
+    .is_true();
   }
 }
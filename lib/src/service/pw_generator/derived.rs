@@ -0,0 +1,224 @@
+use super::wordlist::WORDLIST;
+use super::{chars, pronounceable, words};
+use crate::api::{
+  PasswordGeneratorCharsParam, PasswordGeneratorParam, PasswordGeneratorPronounceableParam, PasswordGeneratorWordsParam,
+};
+use crate::memguard::SecretBytes;
+use crate::service::ServiceResult;
+use hkdf::Hkdf;
+use itertools::Itertools;
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+/// HKDF info of the derived bytes (followed by the big-endian number of the block).
+///
+/// The version has to be raised for every change of the mapping below (including the character sets and the
+/// built-in wordlist it refers to), as it changes all passwords derived so far.
+const DERIVED_PASSWORD_INFO: &[u8] = b"t-rust-less derived password v1";
+
+const BLOCK_SIZE: usize = 32;
+
+/// Deterministically generate a password according to `param` from `key`.
+///
+/// Other than `generate_password` this does not use a general purpose `Rng`: The key is expanded with
+/// HKDF-SHA256 and the bytes are mapped to the password by the fixed scheme of `DerivedStream`, i.e. the
+/// result does not depend on the internals of any crate.
+pub fn generate_derived_password(key: &SecretBytes, param: &PasswordGeneratorParam) -> ServiceResult<String> {
+  let mut stream = DerivedStream::new(key);
+
+  match param {
+    PasswordGeneratorParam::Chars(params) => derive_chars(&mut stream, params),
+    PasswordGeneratorParam::Words(params) => derive_words(&mut stream, params),
+    PasswordGeneratorParam::Pronounceable(params) => Ok(derive_pronounceable(&mut stream, params)),
+    PasswordGeneratorParam::Pin { length } => Ok(
+      (0..*length)
+        .map(|_| char::from(b'0' + stream.index(10) as u8))
+        .collect(),
+    ),
+  }
+}
+
+fn derive_chars(stream: &mut DerivedStream, params: &PasswordGeneratorCharsParam) -> ServiceResult<String> {
+  let symbols = chars::symbol_set(params)?;
+  let mut pool = Vec::with_capacity(params.num_chars as usize);
+
+  if params.require_upper {
+    pool.push(*stream.choose(&chars::required_set(chars::UPPERS, params, "upper case letters")?));
+  }
+  if params.require_number {
+    pool.push(*stream.choose(&chars::required_set(chars::NUMBERS, params, "numbers")?));
+  }
+  if params.require_symbol {
+    pool.push(*stream.choose(&chars::required_set(symbols, params, "symbols")?));
+  }
+  let candidates = chars::create_base_set(params, symbols)?;
+  while pool.len() < params.num_chars as usize {
+    pool.push(*stream.choose(&candidates));
+  }
+
+  stream.shuffle(&mut pool);
+
+  Ok(String::from_utf8(pool).unwrap())
+}
+
+fn derive_words(stream: &mut DerivedStream, params: &PasswordGeneratorWordsParam) -> ServiceResult<String> {
+  let delim = params.delim.to_string();
+
+  match &params.wordlist_path {
+    Some(wordlist_path) => {
+      let wordlist = words::read_wordlist(wordlist_path)?;
+      Ok(
+        stream
+          .choose_multiple(&wordlist, params.num_words as usize)
+          .into_iter()
+          .join(&delim),
+      )
+    }
+    None => Ok(
+      stream
+        .choose_multiple(WORDLIST, params.num_words as usize)
+        .into_iter()
+        .join(&delim),
+    ),
+  }
+}
+
+fn derive_pronounceable(stream: &mut DerivedStream, params: &PasswordGeneratorPronounceableParam) -> String {
+  let mut extras = Vec::with_capacity(2);
+
+  if params.include_number {
+    extras.push(*stream.choose(pronounceable::NUMBERS));
+  }
+  if params.include_symbol {
+    extras.push(*stream.choose(pronounceable::SYMBOLS));
+  }
+  extras.truncate(params.num_chars as usize);
+
+  let num_letters = params.num_chars as usize - extras.len();
+  let mut pool = Vec::with_capacity(params.num_chars as usize);
+  let mut consonant = stream.index(2) == 0;
+  while pool.len() < num_letters {
+    let clusters = if consonant {
+      pronounceable::CONSONANTS
+    } else {
+      pronounceable::VOWELS
+    };
+    pool.extend_from_slice(stream.choose(clusters).as_bytes());
+    consonant = !consonant;
+  }
+  pool.truncate(num_letters);
+
+  for extra in extras {
+    let position = stream.index(pool.len() + 1);
+    pool.insert(position, extra);
+  }
+
+  String::from_utf8(pool).unwrap()
+}
+
+/// Bytes expanded from a key with HKDF-SHA256 (in blocks of `BLOCK_SIZE` with `DERIVED_PASSWORD_INFO`).
+///
+/// All choices are made by `index`, i.e. the mapping of the bytes is fixed by this module alone.
+struct DerivedStream {
+  hkdf: Hkdf<Sha256>,
+  block_number: u32,
+  block: Zeroizing<[u8; BLOCK_SIZE]>,
+  position: usize,
+}
+
+impl DerivedStream {
+  fn new(key: &SecretBytes) -> Self {
+    DerivedStream {
+      hkdf: Hkdf::<Sha256>::new(None, &key.borrow()),
+      block_number: 0,
+      block: Zeroizing::new([0u8; BLOCK_SIZE]),
+      position: BLOCK_SIZE,
+    }
+  }
+
+  fn next_byte(&mut self) -> u8 {
+    if self.position == BLOCK_SIZE {
+      let mut info = Vec::with_capacity(DERIVED_PASSWORD_INFO.len() + 4);
+      info.extend_from_slice(DERIVED_PASSWORD_INFO);
+      info.extend_from_slice(&self.block_number.to_be_bytes());
+      // A single block is way below the output limit of HKDF (255 hashes)
+      self
+        .hkdf
+        .expand(&info, self.block.as_mut())
+        .expect("Block exceeds HKDF output limit");
+      self.block_number += 1;
+      self.position = 0;
+    }
+    let byte = self.block[self.position];
+    self.position += 1;
+    byte
+  }
+
+  /// Uniformly distributed index in `0..n` (`n > 0`): The next four bytes are taken as big-endian u32,
+  /// values beyond the largest multiple of `n` are rejected.
+  fn index(&mut self, n: usize) -> usize {
+    let n = n as u64;
+    let zone = (1u64 << 32) - (1u64 << 32) % n;
+
+    loop {
+      let value = u32::from_be_bytes([self.next_byte(), self.next_byte(), self.next_byte(), self.next_byte()]) as u64;
+      if value < zone {
+        return (value % n) as usize;
+      }
+    }
+  }
+
+  fn choose<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+    &items[self.index(items.len())]
+  }
+
+  /// Fisher-Yates shuffle (from the last to the second element).
+  fn shuffle<T>(&mut self, items: &mut [T]) {
+    for i in (1..items.len()).rev() {
+      let j = self.index(i + 1);
+      items.swap(i, j);
+    }
+  }
+
+  /// Up to `amount` distinct elements of `items` (partial Fisher-Yates shuffle of the indices).
+  fn choose_multiple<'a, T>(&mut self, items: &'a [T], amount: usize) -> Vec<&'a T> {
+    let amount = amount.min(items.len());
+    let mut indices: Vec<usize> = (0..items.len()).collect();
+
+    for i in 0..amount {
+      let j = i + self.index(items.len() - i);
+      indices.swap(i, j);
+    }
+
+    indices[..amount].iter().map(|idx| &items[*idx]).collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use spectral::prelude::*;
+
+  #[test]
+  fn test_derived_stream() {
+    let key = SecretBytes::from(b"derived stream key".to_vec());
+    let mut stream = DerivedStream::new(&key);
+    let mut other_stream = DerivedStream::new(&key);
+
+    let indices: Vec<usize> = (0..100).map(|_| stream.index(7)).collect();
+
+    assert_that(&indices.iter().all(|idx| *idx < 7)).is_true();
+    assert_that(&(0..100).map(|_| other_stream.index(7)).collect::<Vec<_>>()).is_equal_to(&indices);
+    assert_that(&stream.block_number).is_greater_than(1);
+
+    let mut items: Vec<usize> = (0..50).collect();
+    stream.shuffle(&mut items);
+    let mut sorted = items.clone();
+    sorted.sort_unstable();
+    assert_that(&sorted).is_equal_to((0..50).collect::<Vec<_>>());
+
+    let chosen = stream.choose_multiple(&items, 20);
+    assert_that(&chosen.iter().unique().count()).is_equal_to(20);
+    assert_that(&stream.choose_multiple(&items[..3], 5)).has_length(3);
+  }
+}
@@ -1,6 +1,26 @@
 mod chars;
+mod derived;
+mod pin;
+mod pronounceable;
 mod wordlist;
 mod words;
 
 pub use chars::generate_chars;
+pub use derived::generate_derived_password;
+pub use pin::generate_pin;
+pub use pronounceable::generate_pronounceable;
 pub use words::generate_words;
+
+use crate::api::PasswordGeneratorParam;
+use crate::service::ServiceResult;
+use rand::Rng;
+
+/// Generate a password according to `param` with all randomness taken from `rng`.
+pub fn generate_password<R: Rng>(rng: &mut R, param: &PasswordGeneratorParam) -> ServiceResult<String> {
+  match param {
+    PasswordGeneratorParam::Chars(params) => generate_chars(rng, params),
+    PasswordGeneratorParam::Words(params) => generate_words(rng, params),
+    PasswordGeneratorParam::Pronounceable(params) => Ok(generate_pronounceable(rng, params)),
+    PasswordGeneratorParam::Pin { length } => Ok(generate_pin(rng, *length)),
+  }
+}
@@ -0,0 +1,31 @@
+use rand::Rng;
+
+/// Uniformly random decimal digits (i.e. leading zeros are just as likely as any other digit).
+pub fn generate_pin<R: Rng>(rng: &mut R, length: u8) -> String {
+  (0..length).map(|_| char::from(b'0' + rng.gen_range(0..10))).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rand::thread_rng;
+  use spectral::prelude::*;
+
+  #[test]
+  fn test_generate_pin() {
+    let pin1 = generate_pin(&mut thread_rng(), 4);
+
+    assert_that(&pin1.len()).is_equal_to(4);
+    assert_that(&pin1.chars().all(|ch| ch.is_ascii_digit())).is_true();
+
+    assert_that(&generate_pin(&mut thread_rng(), 0)).is_equal_to(String::new());
+
+    // With 200 pins of length 8 every digit should show up at the first position
+    let mut first_digits: Vec<char> = (0..200)
+      .filter_map(|_| generate_pin(&mut thread_rng(), 8).chars().next())
+      .collect();
+    first_digits.sort_unstable();
+    first_digits.dedup();
+    assert_that(&first_digits).has_length(10);
+  }
+}
@@ -0,0 +1,89 @@
+use crate::api::PasswordGeneratorPronounceableParam;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+pub(super) const CONSONANTS: &[&str] = &[
+  "b", "c", "d", "f", "g", "h", "j", "k", "l", "m", "n", "p", "r", "s", "t", "v", "w", "z", "bl", "br", "ch", "cl",
+  "cr", "dr", "fl", "fr", "gr", "kr", "pl", "pr", "sh", "sl", "st", "th", "tr",
+];
+pub(super) const VOWELS: &[&str] = &["a", "e", "i", "o", "u", "ai", "au", "ea", "ee", "ie", "oo", "ou"];
+pub(super) const NUMBERS: &[u8] = b"0123456789";
+// Only symbols that are easy to reach on a phone keyboard
+pub(super) const SYMBOLS: &[u8] = b"!#$%&*+-=?@_";
+
+pub fn generate_pronounceable<R: Rng>(rng: &mut R, params: &PasswordGeneratorPronounceableParam) -> String {
+  let mut extras = Vec::with_capacity(2);
+
+  if params.include_number {
+    extras.push(*NUMBERS.choose(rng).unwrap());
+  }
+  if params.include_symbol {
+    extras.push(*SYMBOLS.choose(rng).unwrap());
+  }
+  extras.truncate(params.num_chars as usize);
+
+  let num_letters = params.num_chars as usize - extras.len();
+  let mut pool = Vec::with_capacity(params.num_chars as usize);
+  let mut consonant = rng.gen_bool(0.5);
+  while pool.len() < num_letters {
+    let clusters = if consonant { CONSONANTS } else { VOWELS };
+    pool.extend_from_slice(clusters.choose(rng).unwrap().as_bytes());
+    consonant = !consonant;
+  }
+  pool.truncate(num_letters);
+
+  // Numbers and symbols are inserted between the syllables, i.e. the letters remain pronounceable
+  for extra in extras {
+    let position = rng.gen_range(0..=pool.len());
+    pool.insert(position, extra);
+  }
+
+  String::from_utf8(pool).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rand::thread_rng;
+  use spectral::prelude::*;
+
+  #[test]
+  fn test_generate_pronounceable() {
+    let pw1 = generate_pronounceable(
+      &mut thread_rng(),
+      &PasswordGeneratorPronounceableParam {
+        num_chars: 12,
+        include_number: false,
+        include_symbol: false,
+      },
+    );
+
+    assert_that(&pw1.len()).is_equal_to(12);
+    assert_that(&pw1.chars().all(|ch| ch.is_ascii_lowercase())).is_true();
+    assert_that(&pw1.contains(|ch| "aeiou".contains(ch))).is_true();
+
+    let pw2 = generate_pronounceable(
+      &mut thread_rng(),
+      &PasswordGeneratorPronounceableParam {
+        num_chars: 16,
+        include_number: true,
+        include_symbol: true,
+      },
+    );
+
+    assert_that(&pw2.len()).is_equal_to(16);
+    assert_that(&pw2.chars().filter(|ch| ch.is_ascii_digit()).count()).is_equal_to(1);
+    assert_that(&pw2.chars().filter(|ch| SYMBOLS.contains(&(*ch as u8))).count()).is_equal_to(1);
+
+    let pw3 = generate_pronounceable(
+      &mut thread_rng(),
+      &PasswordGeneratorPronounceableParam {
+        num_chars: 1,
+        include_number: true,
+        include_symbol: true,
+      },
+    );
+
+    assert_that(&pw3.len()).is_equal_to(1);
+  }
+}
@@ -1,38 +1,137 @@
 use super::wordlist::WORDLIST;
 use crate::api::PasswordGeneratorWordsParam;
+use crate::service::{ServiceError, ServiceResult};
 use itertools::Itertools;
 use rand::seq::SliceRandom;
-use rand::thread_rng;
+use rand::Rng;
+use std::collections::BTreeSet;
+use std::fs;
 
-pub fn generate_words(params: &PasswordGeneratorWordsParam) -> String {
-  let mut rng = thread_rng();
+/// Minimum number of distinct words of a custom wordlist, i.e. every word contributes at least 10 bits of entropy.
+const MIN_WORDLIST_SIZE: usize = 1024;
 
-  WORDLIST
-    .choose_multiple(&mut rng, params.num_words as usize)
-    .join(&params.delim.to_string())
+pub fn generate_words<R: Rng>(rng: &mut R, params: &PasswordGeneratorWordsParam) -> ServiceResult<String> {
+  match &params.wordlist_path {
+    Some(wordlist_path) => {
+      let wordlist = read_wordlist(wordlist_path)?;
+      Ok(
+        wordlist
+          .choose_multiple(rng, params.num_words as usize)
+          .join(&params.delim.to_string()),
+      )
+    }
+    None => Ok(
+      WORDLIST
+        .choose_multiple(rng, params.num_words as usize)
+        .join(&params.delim.to_string()),
+    ),
+  }
+}
+
+/// Read a wordlist with one word per line. Lines may be prefixed by dice numbers (like the EFF lists),
+/// empty lines and lines starting with `#` are ignored.
+pub(super) fn read_wordlist(wordlist_path: &str) -> ServiceResult<Vec<String>> {
+  let content = fs::read_to_string(wordlist_path)
+    .map_err(|err| ServiceError::InvalidWordlist(format!("{}: {}", wordlist_path, err)))?;
+  let words: BTreeSet<&str> = content
+    .lines()
+    .map(str::trim)
+    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+    .filter_map(|line| line.split_whitespace().last())
+    .collect();
+
+  if words.len() < MIN_WORDLIST_SIZE {
+    return Err(ServiceError::InvalidWordlist(format!(
+      "{}: only {} distinct words (at least {} required)",
+      wordlist_path,
+      words.len(),
+      MIN_WORDLIST_SIZE
+    )));
+  }
+
+  Ok(words.into_iter().map(str::to_string).collect())
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
+  use rand::thread_rng;
   use spectral::prelude::*;
+  use std::io::Write;
+  use tempfile::NamedTempFile;
 
   #[test]
   fn test_generate_words() {
-    let pw1 = generate_words(&PasswordGeneratorWordsParam {
-      num_words: 3,
-      delim: '.',
-    });
+    let pw1 = generate_words(
+      &mut thread_rng(),
+      &PasswordGeneratorWordsParam {
+        num_words: 3,
+        delim: '.',
+        wordlist_path: None,
+      },
+    )
+    .unwrap();
 
     assert_that(&pw1.len()).is_greater_than(5);
     assert_that(&pw1.split(".").count()).is_equal_to(3);
 
-    let pw2 = generate_words(&PasswordGeneratorWordsParam {
-      num_words: 5,
-      delim: '-',
-    });
+    let pw2 = generate_words(
+      &mut thread_rng(),
+      &PasswordGeneratorWordsParam {
+        num_words: 5,
+        delim: '-',
+        wordlist_path: None,
+      },
+    )
+    .unwrap();
 
     assert_that(&pw2.len()).is_greater_than(9);
     assert_that(&pw2.split("-").count()).is_equal_to(5);
   }
+
+  #[test]
+  fn test_custom_wordlist() {
+    let mut wordlist = NamedTempFile::new().unwrap();
+    writeln!(wordlist, "# custom list").unwrap();
+    for i in 0..MIN_WORDLIST_SIZE {
+      writeln!(wordlist, "{:05}\tword{}", i, i).unwrap();
+      writeln!(wordlist, "word{}", i).unwrap();
+    }
+    wordlist.flush().unwrap();
+
+    let pw = generate_words(
+      &mut thread_rng(),
+      &PasswordGeneratorWordsParam {
+        num_words: 4,
+        delim: ' ',
+        wordlist_path: Some(wordlist.path().to_string_lossy().to_string()),
+      },
+    )
+    .unwrap();
+
+    assert_that(&pw.split(' ').count()).is_equal_to(4);
+    assert_that(&pw.split(' ').all(|word| word.starts_with("word"))).is_true();
+
+    let mut too_small = NamedTempFile::new().unwrap();
+    for _ in 0..MIN_WORDLIST_SIZE {
+      writeln!(too_small, "same").unwrap();
+    }
+    too_small.flush().unwrap();
+
+    for wordlist_path in [
+      too_small.path().to_string_lossy().to_string(),
+      "/does/not/exist".to_string(),
+    ] {
+      let result = generate_words(
+        &mut thread_rng(),
+        &PasswordGeneratorWordsParam {
+          num_words: 4,
+          delim: ' ',
+          wordlist_path: Some(wordlist_path),
+        },
+      );
+
+      assert_that(&matches!(result, Err(ServiceError::InvalidWordlist(_)))).is_true();
+    }
+  }
 }
@@ -1,10 +1,11 @@
+use crate::api::{AuditPolicy, AuditReport, Capabilities, Event, PasswordGeneratorParam};
 use crate::api::{
-  ClipboardProviding, Command, CommandResult, Identity, Secret, SecretList, SecretListFilter, SecretVersion, Status,
-  StoreConfig,
+  ClipboardProviding, Command, CommandResult, Diagnostics, Identity, PanicLockReport, ReuseGroup, Secret,
+  SecretAttachment, SecretList, SecretListFilter, SecretVersion, Status, StoreConfig, StoreDiagnostics, SyncReport,
 };
-use crate::api::{Event, PasswordGeneratorParam};
 use crate::memguard::{SecretBytes, ZeroizeBytesBuffer};
 use crate::secrets_store::{SecretStoreError, SecretStoreResult, SecretsStore};
+use crate::secrets_store_capnp::KeyType;
 use crate::service::{ClipboardControl, ServiceError, ServiceResult, TrustlessService};
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use chrono::{DateTime, Utc};
@@ -130,10 +131,34 @@ where
     send_recv::<_, ServiceError>(&self.stream, Command::GeneratePassword(param))?.into()
   }
 
+  fn derive_site_password(&self, store_name: &str, site: &str, param: PasswordGeneratorParam) -> ServiceResult<String> {
+    send_recv::<_, ServiceError>(
+      &self.stream,
+      Command::DeriveSitePassword {
+        store_name: store_name.to_string(),
+        site: site.to_string(),
+        param,
+      },
+    )?
+    .into()
+  }
+
+  fn capabilities(&self) -> ServiceResult<Capabilities> {
+    send_recv::<_, ServiceError>(&self.stream, Command::Capabilities)?.into()
+  }
+
   fn check_autolock(&self) {
     // This should be done by the remote sever itself
   }
 
+  fn lock_on_sleep(&self) {
+    // This should be done by the remote sever itself
+  }
+
+  fn lock_on_screen_lock(&self) {
+    // This should be done by the remote sever itself
+  }
+
   fn needs_synchronization(&self) -> bool {
     false
   }
@@ -142,6 +167,18 @@ where
     // This should be done by the remote sever itself
     None
   }
+
+  fn synchronize_now(&self, store_name: &str) -> ServiceResult<SyncReport> {
+    send_recv::<_, ServiceError>(&self.stream, Command::SynchronizeNow(store_name.to_string()))?.into()
+  }
+
+  fn panic_lock(&self, wipe_local: bool) -> ServiceResult<PanicLockReport> {
+    send_recv::<_, ServiceError>(&self.stream, Command::PanicLock { wipe_local })?.into()
+  }
+
+  fn diagnose(&self, store_name: &str) -> ServiceResult<Diagnostics> {
+    send_recv::<_, ServiceError>(&self.stream, Command::Diagnose(store_name.to_string()))?.into()
+  }
 }
 
 #[derive(Debug)]
@@ -186,6 +223,18 @@ where
     .into()
   }
 
+  fn verify_passphrase(&self, identity_id: &str, passphrase: SecretBytes) -> SecretStoreResult<bool> {
+    send_recv::<_, SecretStoreError>(
+      &self.stream,
+      Command::VerifyPassphrase {
+        store_name: self.name.clone(),
+        identity_id: identity_id.to_string(),
+        passphrase,
+      },
+    )?
+    .into()
+  }
+
   fn identities(&self) -> SecretStoreResult<Vec<Identity>> {
     send_recv::<_, SecretStoreError>(&self.stream, Command::Identities(self.name.clone()))?.into()
   }
@@ -228,6 +277,51 @@ where
     send_recv::<_, SecretStoreError>(&self.stream, Command::UpdateIndex(self.name.clone()))?.into()
   }
 
+  fn rebuild_index(&self) -> SecretStoreResult<()> {
+    send_recv::<_, SecretStoreError>(&self.stream, Command::RebuildIndex(self.name.clone()))?.into()
+  }
+
+  fn wipe_index(&self) -> SecretStoreResult<()> {
+    send_recv::<_, SecretStoreError>(&self.stream, Command::WipeIndex(self.name.clone()))?.into()
+  }
+
+  fn rotate_cipher(&self, target: KeyType) -> SecretStoreResult<usize> {
+    send_recv::<_, SecretStoreError>(
+      &self.stream,
+      Command::RotateCipher {
+        store_name: self.name.clone(),
+        target: target.into(),
+      },
+    )?
+    .into()
+  }
+
+  fn collect_garbage(&self, prune_history: bool) -> SecretStoreResult<usize> {
+    send_recv::<_, SecretStoreError>(
+      &self.stream,
+      Command::CollectGarbage {
+        store_name: self.name.clone(),
+        prune_history,
+      },
+    )?
+    .into()
+  }
+
+  fn revoke_recipient(&self, recipient_id: &str) -> SecretStoreResult<Vec<String>> {
+    send_recv::<_, SecretStoreError>(
+      &self.stream,
+      Command::RevokeRecipient {
+        store_name: self.name.clone(),
+        recipient_id: recipient_id.to_string(),
+      },
+    )?
+    .into()
+  }
+
+  fn diagnose(&self) -> SecretStoreResult<StoreDiagnostics> {
+    send_recv::<_, SecretStoreError>(&self.stream, Command::Diagnose(self.name.clone()))?.into()
+  }
+
   fn add(&self, secret_version: SecretVersion) -> SecretStoreResult<String> {
     send_recv::<_, SecretStoreError>(
       &self.stream,
@@ -260,6 +354,67 @@ where
     )?
     .into()
   }
+
+  fn get_attachment_chunk(
+    &self,
+    secret_id: &str,
+    name: &str,
+    index: usize,
+  ) -> SecretStoreResult<Option<SecretAttachment>> {
+    send_recv::<_, SecretStoreError>(
+      &self.stream,
+      Command::GetAttachmentChunk {
+        store_name: self.name.clone(),
+        secret_id: secret_id.to_string(),
+        name: name.to_string(),
+        index,
+      },
+    )?
+    .into()
+  }
+
+  fn find_concurrent_versions(&self, block_ids: &[String]) -> SecretStoreResult<Vec<String>> {
+    send_recv::<_, SecretStoreError>(
+      &self.stream,
+      Command::FindConcurrentVersions {
+        store_name: self.name.clone(),
+        block_ids: block_ids.to_vec(),
+      },
+    )?
+    .into()
+  }
+
+  fn find_reused_passwords(&self) -> SecretStoreResult<Vec<ReuseGroup>> {
+    send_recv::<_, SecretStoreError>(&self.stream, Command::FindReusedPasswords(self.name.clone()))?.into()
+  }
+
+  fn derive_site_key(&self, _site: &str, _key_length: usize) -> SecretStoreResult<SecretBytes> {
+    Err(SecretStoreError::IO(
+      "Site keys are not available for remote stores".to_string(),
+    ))
+  }
+
+  fn audit(&self, policy: &AuditPolicy) -> SecretStoreResult<AuditReport> {
+    send_recv::<_, SecretStoreError>(
+      &self.stream,
+      Command::Audit {
+        store_name: self.name.clone(),
+        policy: policy.clone(),
+      },
+    )?
+    .into()
+  }
+
+  fn check_breached(&self, secret_id: &str) -> SecretStoreResult<Option<u64>> {
+    send_recv::<_, SecretStoreError>(
+      &self.stream,
+      Command::CheckBreached {
+        store_name: self.name.clone(),
+        secret_id: secret_id.to_string(),
+      },
+    )?
+    .into()
+  }
 }
 
 #[derive(Debug)]
@@ -1,55 +1,186 @@
 use chrono::{DateTime, Duration, Utc};
-use log::info;
-use std::sync::Arc;
+use log::{info, warn};
+use std::sync::{mpsc, Arc};
+use std::thread;
 
-use crate::{block_store::sync::SyncBlockStore, secrets_store::SecretsStore};
+use crate::{
+  api::{EventData, EventHub, SyncReport},
+  block_store::sync::SyncBlockStore,
+  secrets_store::{SecretStoreError, SecretsStore},
+};
 
 use super::ServiceResult;
 
-#[derive(Debug)]
+/// Minimum time between two index refreshes after background synchronizations,
+/// so that a series of synchronizations does not constantly update the index.
+pub const CONTENT_REFRESH_DEBOUNCE: Duration = Duration::seconds(10);
+
 pub struct Synchronizer {
+  store_name: String,
   secret_store: Arc<dyn SecretsStore>,
   sync_block_store: Arc<SyncBlockStore>,
   sync_interval: Duration,
+  event_hub: Arc<dyn EventHub>,
   last_run: Option<DateTime<Utc>>,
+  /// Something has been pulled that is not yet part of the index
+  content_changed: bool,
+  last_refresh: Option<DateTime<Utc>>,
+}
+
+impl std::fmt::Debug for Synchronizer {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("Synchronizer")
+      .field("store_name", &self.store_name)
+      .field("sync_interval", &self.sync_interval)
+      .field("last_run", &self.last_run)
+      .field("content_changed", &self.content_changed)
+      .field("last_refresh", &self.last_refresh)
+      .finish()
+  }
 }
 
 impl Synchronizer {
   pub fn new(
+    store_name: &str,
     secret_store: Arc<dyn SecretsStore>,
     sync_block_store: Arc<SyncBlockStore>,
     sync_interval: Duration,
+    event_hub: Arc<dyn EventHub>,
   ) -> Self {
     Synchronizer {
+      store_name: store_name.to_string(),
       secret_store,
       sync_block_store,
       sync_interval,
+      event_hub,
       last_run: None,
+      content_changed: false,
+      last_refresh: None,
     }
   }
 
   pub fn synchronize(&mut self) -> ServiceResult<()> {
-    if let Some(last_run) = self.last_run {
-      if last_run + self.sync_interval > Utc::now() {
-        return Ok(());
+    let now = Utc::now();
+
+    if !matches!(self.last_run, Some(last_run) if last_run + self.sync_interval > now) {
+      let report = self.pull()?;
+
+      if !report.is_success() {
+        warn!(
+          "Synchronization of {} incomplete: {} errors, {} ring conflicts",
+          self.store_name,
+          report.errors.len(),
+          report.ring_conflicts.len()
+        );
       }
     }
-    info!("Start store synchronization");
-    self.last_run = Some(Utc::now());
 
-    let local_changes = self.sync_block_store.synchronize()?;
+    self.refresh_content(now, false)
+  }
 
-    if local_changes && !self.secret_store.status()?.locked {
-      self.secret_store.update_index()?;
+  /// Synchronize regardless of the sync interval (the index is refreshed immediately)
+  pub fn synchronize_now(&mut self) -> ServiceResult<SyncReport> {
+    let report = self.pull()?;
+
+    self.refresh_content(Utc::now(), true)?;
+
+    Ok(report)
+  }
+
+  /// Refresh the index of the store if something has been pulled since the last refresh.
+  ///
+  /// Unless `force` is set this happens at most once per `CONTENT_REFRESH_DEBOUNCE`, pending changes
+  /// are picked up by a later call (see `next_run`).
+  pub fn refresh_content(&mut self, now: DateTime<Utc>, force: bool) -> ServiceResult<()> {
+    if !self.content_changed
+      || !force && matches!(self.last_refresh, Some(last_refresh) if last_refresh + CONTENT_REFRESH_DEBOUNCE > now)
+    {
+      return Ok(());
     }
+    self.content_changed = false;
+    // A locked store reads the complete index on unlock anyway
+    if self.secret_store.status()?.locked {
+      return Ok(());
+    }
+    self.last_refresh = Some(now);
+    self.secret_store.update_index()?;
+    self.event_hub.send(EventData::StoreContentChanged {
+      store_name: self.store_name.clone(),
+    });
 
     Ok(())
   }
 
+  pub fn store_name(&self) -> &str {
+    &self.store_name
+  }
+
   pub fn next_run(&self) -> DateTime<Utc> {
-    match self.last_run {
+    let next_sync = match self.last_run {
       Some(last_run) => last_run + self.sync_interval,
       None => Utc::now(),
+    };
+
+    match self.last_refresh {
+      Some(last_refresh) if self.content_changed => next_sync.min(last_refresh + CONTENT_REFRESH_DEBOUNCE),
+      _ => next_sync,
+    }
+  }
+
+  fn pull(&mut self) -> ServiceResult<SyncReport> {
+    info!("Start store synchronization: {}", self.store_name);
+    self.last_run = Some(Utc::now());
+
+    // Progress is forwarded as events while the synchronization is running
+    let (progress, progress_events) = mpsc::channel();
+    let forwarder = thread::spawn({
+      let store_name = self.store_name.clone();
+      let event_hub = self.event_hub.clone();
+      move || {
+        for progress in progress_events {
+          event_hub.send(EventData::SyncProgress {
+            store_name: store_name.clone(),
+            progress,
+          });
+        }
+      }
+    });
+    let result = self.sync_block_store.synchronize_report_with_progress(progress);
+    forwarder.join().ok();
+
+    let mut report = match result {
+      Ok(report) => report,
+      Err(err) => {
+        self.event_hub.send(EventData::SyncFailed {
+          store_name: self.store_name.clone(),
+          error: err.to_string(),
+        });
+        return Err(err.into());
+      }
+    };
+    if !report.pulled_block_ids.is_empty() {
+      match self.secret_store.find_concurrent_versions(&report.pulled_block_ids) {
+        Ok(secret_ids) => report.concurrent_secrets = secret_ids,
+        // A locked store can not tell, the concurrent versions are merged on access anyway
+        Err(SecretStoreError::Locked) => (),
+        Err(err) => warn!("Unable to check {} for concurrent versions: {}", self.store_name, err),
+      }
+    }
+    if !report.concurrent_secrets.is_empty() {
+      self.event_hub.send(EventData::ConcurrentVersions {
+        store_name: self.store_name.clone(),
+        secret_ids: report.concurrent_secrets.clone(),
+      });
     }
+    self.event_hub.send(EventData::SyncCompleted {
+      store_name: self.store_name.clone(),
+      report: report.clone(),
+    });
+
+    if report.has_local_changes() {
+      self.content_changed = true;
+    }
+
+    Ok(report)
   }
 }
@@ -0,0 +1,290 @@
+use super::synchronizer::{Synchronizer, CONTENT_REFRESH_DEBOUNCE};
+use crate::api::{EventData, EventHub, Identity, SecretListFilter, SecretType, SecretVersion, StoreConfig};
+use crate::block_store::sync::SyncBlockStore;
+use crate::memguard::SecretBytes;
+use crate::secrets_store::{open_secrets_store, SecretsStore};
+use chrono::Utc;
+use spectral::prelude::*;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tempfile::Builder;
+use url::Url;
+
+#[derive(Default)]
+struct TestEventHub {
+  events: Mutex<Vec<EventData>>,
+}
+
+impl TestEventHub {
+  fn content_changed(&self) -> usize {
+    self
+      .events
+      .lock()
+      .unwrap()
+      .iter()
+      .filter(|event| matches!(event, EventData::StoreContentChanged { store_name } if store_name == "local"))
+      .count()
+  }
+}
+
+impl TestEventHub {
+  fn sync_completed(&self) -> usize {
+    self
+      .events
+      .lock()
+      .unwrap()
+      .iter()
+      .filter(|event| matches!(event, EventData::SyncCompleted { store_name, .. } if store_name == "local"))
+      .count()
+  }
+
+  fn sync_progress(&self) -> usize {
+    self
+      .events
+      .lock()
+      .unwrap()
+      .iter()
+      .filter(|event| matches!(event, EventData::SyncProgress { store_name, .. } if store_name == "local"))
+      .count()
+  }
+}
+
+impl TestEventHub {
+  fn concurrent_versions(&self) -> Vec<Vec<String>> {
+    self
+      .events
+      .lock()
+      .unwrap()
+      .iter()
+      .filter_map(|event| match event {
+        EventData::ConcurrentVersions { store_name, secret_ids } if store_name == "local" => Some(secret_ids.clone()),
+        _ => None,
+      })
+      .collect()
+  }
+}
+
+impl EventHub for TestEventHub {
+  fn send(&self, event: EventData) {
+    self.events.lock().unwrap().push(event);
+  }
+}
+
+fn dir_url(path: &Path) -> String {
+  fs::create_dir_all(path).unwrap();
+  Url::from_directory_path(path).unwrap().to_string()
+}
+
+fn open_store(
+  name: &str,
+  path: &Path,
+  remote_path: &Path,
+  node_id: &str,
+  event_hub: Arc<TestEventHub>,
+) -> (Arc<dyn SecretsStore>, Arc<SyncBlockStore>) {
+  let mut store_config = StoreConfig::new(name, format!("multilane+{}", dir_url(path)), node_id);
+  store_config.remote_url = Some(dir_url(remote_path));
+  let (secrets_store, sync_block_store) = open_secrets_store(&store_config, event_hub).unwrap();
+
+  (secrets_store, sync_block_store.unwrap())
+}
+
+fn add_secret(secrets_store: &dyn SecretsStore, secret_id: &str) {
+  secrets_store
+    .add(SecretVersion {
+      secret_id: secret_id.to_string(),
+      secret_type: SecretType::Login,
+      timestamp: Utc::now().into(),
+      name: secret_id.to_string(),
+      tags: vec![],
+      urls: vec![],
+      properties: Default::default(),
+      attachments: vec![],
+      deleted: false,
+      recipients: vec![],
+      expires_at: None,
+      parent_block_id: None,
+      modified_by: None,
+    })
+    .unwrap();
+}
+
+fn listed_ids(secrets_store: &dyn SecretsStore) -> Vec<String> {
+  secrets_store
+    .list(&SecretListFilter::default())
+    .unwrap()
+    .entries
+    .iter()
+    .map(|entry| entry.entry.id.clone())
+    .collect()
+}
+
+#[test]
+#[cfg_attr(debug_assertions, ignore)]
+fn test_refresh_after_sync() {
+  let tempdir = Builder::new().prefix("t-rust-less-test").tempdir().unwrap();
+  let remote_path = tempdir.path().join("remote");
+  let other_hub = Arc::new(TestEventHub::default());
+  let event_hub = Arc::new(TestEventHub::default());
+  let (other_store, other_sync) = open_store("other", &tempdir.path().join("other"), &remote_path, "node1", other_hub);
+  let identity = Identity {
+    id: "identity1".to_string(),
+    name: "Name1".to_string(),
+    email: "Email1".to_string(),
+    hidden: false,
+    hardware_factor: false,
+  };
+  other_store
+    .add_identity(identity, SecretBytes::from("Passphrase1".to_string()))
+    .unwrap();
+  other_store
+    .unlock("identity1", SecretBytes::from("Passphrase1".to_string()))
+    .unwrap();
+  add_secret(other_store.as_ref(), "secret1");
+  other_sync.synchronize().unwrap();
+
+  let (secrets_store, sync_block_store) = open_store(
+    "local",
+    &tempdir.path().join("local"),
+    &remote_path,
+    "node2",
+    event_hub.clone(),
+  );
+  let mut synchronizer = Synchronizer::new(
+    "local",
+    secrets_store.clone(),
+    sync_block_store,
+    chrono::Duration::seconds(0),
+    event_hub.clone(),
+  );
+
+  // Pulls the ring, the store is still locked
+  synchronizer.synchronize().unwrap();
+  secrets_store
+    .unlock("identity1", SecretBytes::from("Passphrase1".to_string()))
+    .unwrap();
+
+  assert_that(&listed_ids(secrets_store.as_ref())).is_equal_to(vec!["secret1".to_string()]);
+  assert_that(&event_hub.content_changed()).is_equal_to(0);
+  assert_that(&event_hub.sync_completed()).is_equal_to(1);
+  assert_that(&event_hub.sync_progress()).is_greater_than(0);
+
+  add_secret(other_store.as_ref(), "secret2");
+  other_sync.synchronize().unwrap();
+  synchronizer.synchronize().unwrap();
+
+  // No manual update_index required
+  assert_that(&listed_ids(secrets_store.as_ref())).is_equal_to(vec!["secret1".to_string(), "secret2".to_string()]);
+  assert_that(&event_hub.content_changed()).is_equal_to(1);
+
+  // Further changes within the debounce period are deferred
+  add_secret(other_store.as_ref(), "secret3");
+  other_sync.synchronize().unwrap();
+  synchronizer.synchronize().unwrap();
+
+  assert_that(&listed_ids(secrets_store.as_ref())).is_equal_to(vec!["secret1".to_string(), "secret2".to_string()]);
+  assert_that(&event_hub.content_changed()).is_equal_to(1);
+  assert_that(&(synchronizer.next_run() <= Utc::now() + CONTENT_REFRESH_DEBOUNCE)).is_true();
+
+  synchronizer
+    .refresh_content(Utc::now() + CONTENT_REFRESH_DEBOUNCE, false)
+    .unwrap();
+
+  assert_that(&listed_ids(secrets_store.as_ref())).is_equal_to(vec![
+    "secret1".to_string(),
+    "secret2".to_string(),
+    "secret3".to_string(),
+  ]);
+  assert_that(&event_hub.content_changed()).is_equal_to(2);
+
+  // Nothing pulled, nothing to refresh
+  synchronizer.synchronize_now().unwrap();
+
+  assert_that(&event_hub.content_changed()).is_equal_to(2);
+  assert_that(&event_hub.sync_completed()).is_equal_to(4);
+}
+
+fn modify_secret(secrets_store: &dyn SecretsStore, secret_id: &str, name: &str) -> String {
+  let secret = secrets_store.get(secret_id).unwrap();
+  let mut version = secret.current.clone();
+  version.timestamp = Utc::now().into();
+  version.name = name.to_string();
+  version.parent_block_id = Some(secret.current_block_id.clone());
+
+  secrets_store.add(version).unwrap()
+}
+
+#[test]
+#[cfg_attr(debug_assertions, ignore)]
+fn test_concurrent_versions() {
+  let tempdir = Builder::new().prefix("t-rust-less-test").tempdir().unwrap();
+  let remote_path = tempdir.path().join("remote");
+  let other_hub = Arc::new(TestEventHub::default());
+  let event_hub = Arc::new(TestEventHub::default());
+  let (other_store, other_sync) = open_store("other", &tempdir.path().join("other"), &remote_path, "node1", other_hub);
+  let identity = Identity {
+    id: "identity1".to_string(),
+    name: "Name1".to_string(),
+    email: "Email1".to_string(),
+    hidden: false,
+    hardware_factor: false,
+  };
+  other_store
+    .add_identity(identity, SecretBytes::from("Passphrase1".to_string()))
+    .unwrap();
+  other_store
+    .unlock("identity1", SecretBytes::from("Passphrase1".to_string()))
+    .unwrap();
+  add_secret(other_store.as_ref(), "secret1");
+  add_secret(other_store.as_ref(), "secret2");
+  other_sync.synchronize().unwrap();
+
+  let (secrets_store, sync_block_store) = open_store(
+    "local",
+    &tempdir.path().join("local"),
+    &remote_path,
+    "node2",
+    event_hub.clone(),
+  );
+  let mut synchronizer = Synchronizer::new(
+    "local",
+    secrets_store.clone(),
+    sync_block_store,
+    chrono::Duration::seconds(0),
+    event_hub.clone(),
+  );
+
+  synchronizer.synchronize_now().unwrap();
+  secrets_store
+    .unlock("identity1", SecretBytes::from("Passphrase1".to_string()))
+    .unwrap();
+
+  // Both nodes modify secret1 based on the same version, secret2 is only modified by the other node
+  modify_secret(secrets_store.as_ref(), "secret1", "Local name");
+  modify_secret(other_store.as_ref(), "secret1", "Other name");
+  modify_secret(other_store.as_ref(), "secret2", "Other name");
+  other_sync.synchronize().unwrap();
+
+  let report = synchronizer.synchronize_now().unwrap();
+
+  assert_that(&report.blocks_pulled).is_equal_to(2);
+  assert_that(&report.blocks_pushed).is_equal_to(1);
+  assert_that(&report.concurrent_secrets).is_equal_to(vec!["secret1".to_string()]);
+  assert_that(&event_hub.concurrent_versions()).is_equal_to(vec![vec!["secret1".to_string()]]);
+  // Both versions are kept
+  assert_that(&secrets_store.get("secret1").unwrap().versions).has_length(3);
+  assert_that(&secrets_store.get("secret2").unwrap().versions).has_length(2);
+
+  // The other node gets both versions as well
+  other_sync.synchronize().unwrap();
+  other_store.update_index().unwrap();
+
+  assert_that(&other_store.get("secret1").unwrap().versions).has_length(3);
+
+  // Nothing new
+  let report = synchronizer.synchronize_now().unwrap();
+
+  assert_that(&report.concurrent_secrets).is_empty();
+  assert_that(&event_hub.concurrent_versions()).has_length(1);
+}
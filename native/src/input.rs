@@ -1,5 +1,4 @@
 use byteorder::{ByteOrder, NativeEndian};
-use serde::Deserialize;
 use std::io::{Read, Result};
 
 pub struct Input<I> {
@@ -18,19 +17,15 @@ where
     }
   }
 
-  pub fn read<'a, M>(&'a mut self) -> Result<Option<M>>
-  where
-    M: Deserialize<'a>,
-  {
+  /// Read the next message without deserializing it.
+  pub fn read_raw(&mut self) -> Result<&[u8]> {
     let mut length_buffer = [0u8; 4];
     self.underlying.read_exact(&mut length_buffer)?;
     let length = NativeEndian::read_u32(&length_buffer) as usize;
     self.buffer.resize(length, 0);
     self.underlying.read_exact(&mut self.buffer)?;
 
-    let message = serde_json::from_slice::<M>(&self.buffer);
-
-    Ok(message.ok())
+    Ok(&self.buffer)
   }
 
   pub fn clear_buffer(&mut self) {
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 use t_rust_less_lib::api::{
-  ClipboardProviding, Event, Identity, Secret, SecretList, SecretListFilter, SecretVersion, Status, StoreConfig,
+  ClipboardProviding, Event, Identity, PanicLockReport, Secret, SecretList, SecretListFilter, SecretVersion, Status,
+  StoreConfig, SyncReport,
 };
 use t_rust_less_lib::secrets_store::SecretStoreResult;
 use t_rust_less_lib::service::{ServiceError, ServiceResult};
@@ -10,6 +11,13 @@ use zeroize::Zeroize;
 #[allow(clippy::large_enum_variant)]
 #[serde(rename_all = "snake_case")]
 pub enum Command {
+  /// Initial handshake: The extension sends its version and the commands it intends to use
+  /// (an empty list meaning all commands supported by the host).
+  Hello {
+    version: String,
+    #[serde(default)]
+    commands: Vec<String>,
+  },
   ListStores,
   UpsertStoreConfig(StoreConfig),
   DeleteStoreConfig(String),
@@ -46,6 +54,12 @@ pub enum Command {
     store_name: String,
     passphrase: String,
   },
+  SynchronizeNow {
+    store_name: String,
+  },
+  PanicLock {
+    wipe_local: bool,
+  },
 
   ListSecrets {
     store_name: String,
@@ -69,11 +83,70 @@ pub enum Command {
   ClipboardDestroy,
 }
 
+/// All commands actually supported by the host (i.e. the processor), in serialized form.
+pub const SUPPORTED_COMMANDS: &[&str] = &[
+  "hello",
+  "list_stores",
+  "upsert_store_config",
+  "delete_store_config",
+  "get_default_store",
+  "set_default_store",
+  "secret_to_clipboard",
+  "status",
+  "lock",
+  "unlock",
+  "list_identities",
+  "add_identity",
+  "change_passphrase",
+  "synchronize_now",
+  "panic_lock",
+  "list_secrets",
+  "add_secret",
+  "get_secret",
+  "get_secret_version",
+  "clipboard_is_done",
+  "clipboard_currently_providing",
+  "clipboard_destroy",
+];
+
+impl Command {
+  /// Name of the command as it appears in the serialized form.
+  pub fn name(&self) -> &'static str {
+    match self {
+      Command::Hello { .. } => "hello",
+      Command::ListStores => "list_stores",
+      Command::UpsertStoreConfig(_) => "upsert_store_config",
+      Command::DeleteStoreConfig(_) => "delete_store_config",
+      Command::GetDefaultStore => "get_default_store",
+      Command::SetDefaultStore(_) => "set_default_store",
+      Command::DirectClipboardAvailable => "direct_clipboard_available",
+      Command::SecretToClipboard { .. } => "secret_to_clipboard",
+      Command::Status { .. } => "status",
+      Command::Lock { .. } => "lock",
+      Command::Unlock { .. } => "unlock",
+      Command::ListIdentities { .. } => "list_identities",
+      Command::AddIdentity { .. } => "add_identity",
+      Command::ChangePassphrase { .. } => "change_passphrase",
+      Command::SynchronizeNow { .. } => "synchronize_now",
+      Command::PanicLock { .. } => "panic_lock",
+      Command::ListSecrets { .. } => "list_secrets",
+      Command::AddSecret { .. } => "add_secret",
+      Command::GetSecret { .. } => "get_secret",
+      Command::GetSecretVersion { .. } => "get_secret_version",
+      Command::ClipboardIsDone => "clipboard_is_done",
+      Command::ClipboardCurrentlyProviding => "clipboard_currently_providing",
+      Command::ClipboardDestroy => "clipboard_destroy",
+    }
+  }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[allow(clippy::large_enum_variant)]
 #[serde(rename_all = "snake_case")]
 pub enum CommandResult {
   Invalid,
+  Hello { version: String, commands: Vec<String> },
+  Unsupported { command: String },
   Success,
   Error { error: ServiceError, display: String },
   Empty,
@@ -85,6 +158,8 @@ pub enum CommandResult {
 
   Status(Status),
   Identities(Vec<Identity>),
+  SyncReport(SyncReport),
+  PanicLockReport(PanicLockReport),
 
   SecretList(SecretList),
   SecretVersion(SecretVersion),
@@ -186,6 +261,18 @@ impl From<Vec<Identity>> for CommandResult {
   }
 }
 
+impl From<SyncReport> for CommandResult {
+  fn from(report: SyncReport) -> Self {
+    CommandResult::SyncReport(report)
+  }
+}
+
+impl From<PanicLockReport> for CommandResult {
+  fn from(report: PanicLockReport) -> Self {
+    CommandResult::PanicLockReport(report)
+  }
+}
+
 impl From<SecretList> for CommandResult {
   fn from(list: SecretList) -> Self {
     CommandResult::SecretList(list)
@@ -216,7 +303,26 @@ pub struct Request {
   pub command: Command,
 }
 
+/// Fallback for requests with an unknown (or malformed) command, to at least be able to
+/// answer with the correct id.
+#[derive(Debug, Deserialize)]
+pub struct UnknownRequest {
+  pub id: u64,
+  pub command: serde_json::Value,
+}
+
+impl UnknownRequest {
+  pub fn command_name(&self) -> String {
+    match &self.command {
+      serde_json::Value::String(name) => name.clone(),
+      serde_json::Value::Object(fields) => fields.keys().next().cloned().unwrap_or_default(),
+      _ => String::new(),
+    }
+  }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
+#[allow(clippy::large_enum_variant)]
 #[serde(rename_all = "snake_case")]
 pub enum Response {
   Command { id: u64, result: CommandResult },
@@ -246,4 +352,15 @@ mod tests {
     assert_that(&serde_json::to_string(&request2).unwrap())
       .is_equal_to(r#"{"id":13,"command":{"status":{"store_name":"bla"}}}"#.to_string());
   }
+
+  #[test]
+  fn test_command_names() {
+    let hello: Request = serde_json::from_str(r#"{"id":1,"command":{"hello":{"version":"1.0.0"}}}"#).unwrap();
+
+    assert_that(&hello.command.name()).is_equal_to("hello");
+    assert_that(&Command::ClipboardIsDone.name()).is_equal_to("clipboard_is_done");
+    assert_that(&serde_json::to_string(&Command::ClipboardCurrentlyProviding).unwrap())
+      .is_equal_to(r#""clipboard_currently_providing""#.to_string());
+    assert_that(&SUPPORTED_COMMANDS.contains(&Command::DirectClipboardAvailable.name())).is_false();
+  }
 }
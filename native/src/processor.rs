@@ -1,7 +1,7 @@
 use crate::input::Input;
-use crate::messages::{Command, CommandResult, Request, Response};
+use crate::messages::{Command, CommandResult, Request, Response, UnknownRequest, SUPPORTED_COMMANDS};
 use crate::output::Output;
-use log::error;
+use log::{error, info, warn};
 use std::io::{Read, Result, Write};
 use std::sync::Arc;
 use t_rust_less_lib::memguard::SecretBytes;
@@ -14,6 +14,7 @@ pub struct Processor<I, O> {
   output: Arc<Output<O>>,
   current_store: Option<(String, Arc<dyn SecretsStore>)>,
   current_clipboard: Option<Arc<dyn ClipboardControl>>,
+  negotiated_commands: Option<Vec<String>>,
 }
 
 impl<I, O> Processor<I, O>
@@ -30,20 +31,32 @@ where
       output,
       current_store: None,
       current_clipboard: None,
+      negotiated_commands: None,
     })
   }
 
   pub fn process(&mut self) -> Result<()> {
     loop {
-      let response = match self.input.read::<Request>()? {
-        Some(request) => self.process_request(request),
-        None => {
-          error!("Invalid request");
-          Response::Command {
-            id: 0,
-            result: CommandResult::Invalid,
+      let raw = self.input.read_raw()?;
+      let response = match serde_json::from_slice::<Request>(raw) {
+        Ok(request) => self.process_request(request),
+        Err(_) => match serde_json::from_slice::<UnknownRequest>(raw) {
+          Ok(unknown) => {
+            let command = unknown.command_name();
+            warn!("Unknown command: {}", command);
+            Response::Command {
+              id: unknown.id,
+              result: CommandResult::Unsupported { command },
+            }
           }
-        }
+          Err(_) => {
+            error!("Invalid request");
+            Response::Command {
+              id: 0,
+              result: CommandResult::Invalid,
+            }
+          }
+        },
       };
       self.input.clear_buffer();
       self.output.send(&response)?;
@@ -51,7 +64,21 @@ where
   }
 
   fn process_request(&mut self, request: Request) -> Response {
+    let command_name = request.command.name();
+    if let Some(negotiated) = &self.negotiated_commands {
+      if command_name != "hello" && !negotiated.iter().any(|name| name == command_name) {
+        warn!("Command not negotiated: {}", command_name);
+        return Response::Command {
+          id: request.id,
+          result: CommandResult::Unsupported {
+            command: command_name.to_string(),
+          },
+        };
+      }
+    }
+
     let result = match request.command {
+      Command::Hello { version, commands } => self.negotiate(&version, &commands),
       Command::ListStores => self.service.list_stores().into(),
       Command::DeleteStoreConfig(store_name) => self.service.delete_store_config(&store_name).into(),
       Command::UpsertStoreConfig(config) => self.service.upsert_store_config(config).into(),
@@ -112,6 +139,8 @@ where
           .and_then(move |store| store.change_passphrase(passphrase_in))
           .into()
       }
+      Command::SynchronizeNow { store_name } => self.service.synchronize_now(&store_name).into(),
+      Command::PanicLock { wipe_local } => self.service.panic_lock(wipe_local).into(),
       Command::ListSecrets { store_name, filter } => self
         .open_store(&store_name)
         .and_then(move |store| store.list(&filter))
@@ -141,12 +170,41 @@ where
         Some(clipboard) => clipboard.destroy().into(),
         None => CommandResult::Success,
       },
-      _ => CommandResult::Invalid,
+      _ => CommandResult::Unsupported {
+        command: command_name.to_string(),
+      },
     };
 
     Response::Command { id: request.id, result }
   }
 
+  /// Handshake with the extension: Only commands supported by both sides will be accepted
+  /// from here on. Until then all supported commands are accepted (for older extensions).
+  fn negotiate(&mut self, extension_version: &str, requested: &[String]) -> CommandResult {
+    let commands: Vec<String> = SUPPORTED_COMMANDS
+      .iter()
+      .filter(|name| **name == "hello" || requested.is_empty() || requested.iter().any(|requested| requested == *name))
+      .map(|name| name.to_string())
+      .collect();
+    for unsupported in requested
+      .iter()
+      .filter(|name| !SUPPORTED_COMMANDS.contains(&name.as_str()))
+    {
+      warn!(
+        "Extension {} requested command not supported by host: {}",
+        extension_version, unsupported
+      );
+    }
+    info!("Handshake with extension {}", extension_version);
+
+    self.negotiated_commands.replace(commands.clone());
+
+    CommandResult::Hello {
+      version: env!("CARGO_PKG_VERSION").to_string(),
+      commands,
+    }
+  }
+
   fn open_store(&mut self, store_name: &str) -> SecretStoreResult<Arc<dyn SecretsStore>> {
     match &self.current_store {
       Some((name, store)) if name == store_name => Ok(store.clone()),
@@ -160,3 +218,205 @@ where
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use byteorder::{ByteOrder, NativeEndian};
+  use chrono::{DateTime, Utc};
+  use serde_json::{json, Value};
+  use spectral::prelude::*;
+  use std::io::{Cursor, ErrorKind};
+  use std::sync::Mutex;
+  use t_rust_less_lib::api::{
+    Capabilities, Diagnostics, Event, PanicLockReport, PasswordGeneratorParam, StoreConfig, SyncReport,
+  };
+  use t_rust_less_lib::service::ServiceResult;
+
+  #[derive(Debug)]
+  struct TestService;
+
+  impl TrustlessService for TestService {
+    fn list_stores(&self) -> ServiceResult<Vec<StoreConfig>> {
+      Ok(vec![])
+    }
+
+    fn upsert_store_config(&self, _store_config: StoreConfig) -> ServiceResult<()> {
+      unimplemented!()
+    }
+
+    fn delete_store_config(&self, _name: &str) -> ServiceResult<()> {
+      unimplemented!()
+    }
+
+    fn open_store(&self, _name: &str) -> SecretStoreResult<Arc<dyn SecretsStore>> {
+      unimplemented!()
+    }
+
+    fn get_default_store(&self) -> ServiceResult<Option<String>> {
+      Ok(Some("default".to_string()))
+    }
+
+    fn set_default_store(&self, _name: &str) -> ServiceResult<()> {
+      unimplemented!()
+    }
+
+    fn secret_to_clipboard(
+      &self,
+      _store_name: &str,
+      _block_id: &str,
+      _properties: &[&str],
+    ) -> ServiceResult<Arc<dyn ClipboardControl>> {
+      unimplemented!()
+    }
+
+    fn poll_events(&self, _last_id: u64) -> ServiceResult<Vec<Event>> {
+      Ok(vec![])
+    }
+
+    fn generate_id(&self) -> ServiceResult<String> {
+      unimplemented!()
+    }
+
+    fn generate_password(&self, _param: PasswordGeneratorParam) -> ServiceResult<String> {
+      unimplemented!()
+    }
+
+    fn derive_site_password(
+      &self,
+      _store_name: &str,
+      _site: &str,
+      _param: PasswordGeneratorParam,
+    ) -> ServiceResult<String> {
+      unimplemented!()
+    }
+
+    fn capabilities(&self) -> ServiceResult<Capabilities> {
+      unimplemented!()
+    }
+
+    fn check_autolock(&self) {}
+
+    fn lock_on_sleep(&self) {}
+
+    fn lock_on_screen_lock(&self) {}
+
+    fn needs_synchronization(&self) -> bool {
+      false
+    }
+
+    fn synchronize(&self) -> Option<DateTime<Utc>> {
+      None
+    }
+
+    fn synchronize_now(&self, _store_name: &str) -> ServiceResult<SyncReport> {
+      unimplemented!()
+    }
+
+    fn panic_lock(&self, _wipe_local: bool) -> ServiceResult<PanicLockReport> {
+      unimplemented!()
+    }
+
+    fn diagnose(&self, _store_name: &str) -> ServiceResult<Diagnostics> {
+      unimplemented!()
+    }
+  }
+
+  #[derive(Clone, Default)]
+  struct SharedOutput(Arc<Mutex<Vec<u8>>>);
+
+  impl Write for SharedOutput {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+      self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+      Ok(())
+    }
+  }
+
+  fn frame(messages: &[Value]) -> Cursor<Vec<u8>> {
+    let mut input = vec![];
+    for message in messages {
+      let raw = serde_json::to_vec(message).unwrap();
+      let mut length_buffer = [0u8; 4];
+      NativeEndian::write_u32(&mut length_buffer, raw.len() as u32);
+      input.extend_from_slice(&length_buffer);
+      input.extend_from_slice(&raw);
+    }
+    Cursor::new(input)
+  }
+
+  fn unframe(output: &[u8]) -> Vec<Value> {
+    let mut messages = vec![];
+    let mut offset = 0;
+    while offset < output.len() {
+      let length = NativeEndian::read_u32(&output[offset..offset + 4]) as usize;
+      messages.push(serde_json::from_slice(&output[offset + 4..offset + 4 + length]).unwrap());
+      offset += 4 + length;
+    }
+    messages
+  }
+
+  fn run(messages: &[Value]) -> Vec<Value> {
+    let output = SharedOutput::default();
+    let mut processor = Processor::new(Arc::new(TestService), frame(messages), output.clone()).unwrap();
+
+    // Processing only ends when stdin is closed
+    let result = processor.process();
+    assert_that(&result.map_err(|e| e.kind())).is_err_containing(ErrorKind::UnexpectedEof);
+
+    let raw = output.0.lock().unwrap();
+    unframe(&raw)
+  }
+
+  #[test]
+  fn test_handshake_all_commands() {
+    let responses = run(&[
+      json!({"id": 1, "command": {"hello": {"version": "1.0.0"}}}),
+      json!({"id": 2, "command": "list_stores"}),
+    ]);
+
+    assert_that(&responses).has_length(2);
+    assert_that(&responses[0]["command"]["id"]).is_equal_to(json!(1));
+    assert_that(&responses[0]["command"]["result"]["hello"]["version"]).is_equal_to(json!(env!("CARGO_PKG_VERSION")));
+    assert_that(&responses[0]["command"]["result"]["hello"]["commands"]).is_equal_to(json!(SUPPORTED_COMMANDS));
+    assert_that(&responses[1]).is_equal_to(json!({"command": {"id": 2, "result": {"store_config_list": []}}}));
+  }
+
+  #[test]
+  fn test_handshake_restricts_commands() {
+    let responses = run(&[
+      json!({"id": 1, "command": {"hello": {"version": "2.0.0", "commands": ["list_stores", "fly_to_the_moon"]}}}),
+      json!({"id": 2, "command": "list_stores"}),
+      json!({"id": 3, "command": "get_default_store"}),
+      json!({"id": 4, "command": {"fly_to_the_moon": {"speed": 42}}}),
+    ]);
+
+    assert_that(&responses).has_length(4);
+    assert_that(&responses[0]["command"]["result"]["hello"]["commands"]).is_equal_to(json!(["hello", "list_stores"]));
+    assert_that(&responses[1]).is_equal_to(json!({"command": {"id": 2, "result": {"store_config_list": []}}}));
+    assert_that(&responses[2])
+      .is_equal_to(json!({"command": {"id": 3, "result": {"unsupported": {"command": "get_default_store"}}}}));
+    assert_that(&responses[3])
+      .is_equal_to(json!({"command": {"id": 4, "result": {"unsupported": {"command": "fly_to_the_moon"}}}}));
+  }
+
+  #[test]
+  fn test_without_handshake() {
+    let responses = run(&[
+      json!({"id": 1, "command": "get_default_store"}),
+      json!({"id": 2, "command": "direct_clipboard_available"}),
+      json!({"id": 3, "command": "unknown_command"}),
+      json!({"garbage": true}),
+    ]);
+
+    assert_that(&responses).has_length(4);
+    assert_that(&responses[0]).is_equal_to(json!({"command": {"id": 1, "result": {"string": "default"}}}));
+    assert_that(&responses[1])
+      .is_equal_to(json!({"command": {"id": 2, "result": {"unsupported": {"command": "direct_clipboard_available"}}}}));
+    assert_that(&responses[2])
+      .is_equal_to(json!({"command": {"id": 3, "result": {"unsupported": {"command": "unknown_command"}}}}));
+    assert_that(&responses[3]).is_equal_to(json!({"command": {"id": 0, "result": "invalid"}}));
+  }
+}